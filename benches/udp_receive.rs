@@ -0,0 +1,84 @@
+//! Receive paths under a 16-track load
+//!
+//! Each iteration sends one frame's worth of datagrams, one per track
+//! socket, over loopback and reads them all back: once by polling the
+//! sockets as the receiver does by default, once through each batch
+//! backend the platform has.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::net::UdpSocket;
+use std::sync::Arc;
+use std::time::Duration;
+
+use lan_audio_streamer::config::ReceiveBackend;
+use lan_audio_streamer::network::timestamp;
+use lan_audio_streamer::network::udp::BatchReceiver;
+
+const TRACKS: usize = 16;
+
+/// A 2.5 ms Opus frame is a few hundred bytes
+const PAYLOAD: [u8; 320] = [0x55; 320];
+
+fn sockets() -> Vec<Arc<UdpSocket>> {
+    (0..TRACKS)
+        .map(|_| {
+            let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+            socket.set_nonblocking(true).unwrap();
+            timestamp::enable(&socket);
+            Arc::new(socket)
+        })
+        .collect()
+}
+
+fn send_frame(sender: &UdpSocket, sockets: &[Arc<UdpSocket>]) {
+    for socket in sockets {
+        sender.send_to(&PAYLOAD, socket.local_addr().unwrap()).unwrap();
+    }
+}
+
+fn bench_receive(c: &mut Criterion) {
+    let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let mut group = c.benchmark_group("udp_receive_16_tracks");
+
+    let polled = sockets();
+    let mut buf = [0u8; 2048];
+    group.bench_function("poll", |b| {
+        b.iter(|| {
+            send_frame(&sender, &polled);
+            let mut received = 0;
+            while received < TRACKS {
+                // The receiver's loop: a pass over every socket, a short sleep when all are idle
+                let mut idle = true;
+                for socket in &polled {
+                    if timestamp::recv_from(socket, &mut buf).is_ok() {
+                        received += 1;
+                        idle = false;
+                    }
+                }
+                if idle {
+                    std::thread::sleep(Duration::from_micros(100));
+                }
+            }
+        })
+    });
+
+    for backend in [ReceiveBackend::IoUring, ReceiveBackend::Overlapped] {
+        let batched = sockets();
+        let Ok(mut receiver) = BatchReceiver::new(backend, &batched) else {
+            continue;
+        };
+        group.bench_function(format!("{:?}", backend), |b| {
+            b.iter(|| {
+                send_frame(&sender, &batched);
+                let mut received = 0;
+                while received < TRACKS {
+                    received += receiver.receive(Duration::from_millis(100), |_| {}).unwrap();
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_receive);
+criterion_main!(benches);
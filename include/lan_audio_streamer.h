@@ -0,0 +1,134 @@
+/*
+ * C bindings for lan-audio-streamer (build with `--features ffi`).
+ *
+ * Functions returning int32_t return 0 (or a track ID) on success and -1 on
+ * failure; las_last_error() then describes the failure on the calling
+ * thread. Callbacks run on library threads, never the caller's.
+ *
+ * Generated from src/ffi.rs with `cbindgen --config cbindgen.toml`.
+ */
+
+#ifndef LAN_AUDIO_STREAMER_H
+#define LAN_AUDIO_STREAMER_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Kind of event passed to a LasEventCallback */
+typedef enum LasEventKind {
+  LAS_EVENT_KIND_CREATED = 0,
+  LAS_EVENT_KIND_REMOVED = 1,
+  LAS_EVENT_KIND_STARTED = 2,
+  LAS_EVENT_KIND_STOPPED = 3,
+  /* Message describes the alert; raised or cleared */
+  LAS_EVENT_KIND_ALERT = 4,
+  LAS_EVENT_KIND_XRUN = 5,
+  /* Message describes the error */
+  LAS_EVENT_KIND_ERROR = 6,
+  /* Message is the new state ("running", "degraded", ...) */
+  LAS_EVENT_KIND_STATE_CHANGED = 7,
+} LasEventKind;
+
+/* Decoding receiver; created with las_receiver_create */
+typedef struct LasReceiver LasReceiver;
+
+/* Encoding sender; created with las_sender_create */
+typedef struct LasSender LasSender;
+
+/* Track statistics over the last few seconds */
+typedef struct LasTrackStats {
+  uint8_t track_id;
+  uint64_t packets;
+  uint64_t bytes;
+  uint64_t frames;
+  uint64_t packets_lost;
+  uint64_t overflows;
+  uint64_t underruns;
+  double packets_per_sec;
+  double bitrate_kbps;
+  double loss_pct;
+  /* End-to-end estimate from the stage breakdown */
+  double latency_ms;
+} LasTrackStats;
+
+/* Track event callback: user data, kind, track ID and a message or NULL.
+ * The message is only valid during the call. */
+typedef void (*LasEventCallback)(void *user_data, LasEventKind kind, uint8_t track_id, const char *message);
+
+/* Audio callback: user data, track ID, interleaved samples, sample count and channels */
+typedef void (*LasAudioCallback)(void *user_data, uint8_t track_id, const float *samples, size_t len, uint16_t channels);
+
+/* Message describing the last failure on this thread, or NULL.
+ * The string stays valid until the next failing call on the same thread. */
+const char *las_last_error(void);
+
+/* Create a sender streaming to `target` ("host:port"); NULL on failure */
+LasSender *las_sender_create(const char *target);
+
+/* Add a track captured from `device_id` (NULL for the default input).
+ * `channels` and `bitrate` may be 0 for the defaults. Returns the track ID. */
+int32_t las_sender_add_track(LasSender *sender,
+                             const char *name,
+                             const char *device_id,
+                             uint16_t channels,
+                             uint32_t bitrate);
+
+/* Add a track fed by las_sender_push_audio instead of a device */
+int32_t las_sender_add_push_track(LasSender *sender, const char *name, uint16_t channels, uint32_t bitrate);
+
+/* Queue interleaved 48 kHz samples on a push track.
+ * Any length is accepted; samples are encoded once a full frame is queued. */
+int32_t las_sender_push_audio(LasSender *sender, uint8_t track_id, const float *samples, size_t len);
+
+/* Start capturing and sending all tracks */
+int32_t las_sender_start(LasSender *sender);
+
+/* Stop all tracks; they can be started again */
+int32_t las_sender_stop(LasSender *sender);
+
+/* Fill `out` with a track's statistics */
+int32_t las_sender_get_stats(const LasSender *sender, uint8_t track_id, LasTrackStats *out);
+
+/* Receive track events through `callback` (NULL to stop) */
+int32_t las_sender_set_event_callback(LasSender *sender, LasEventCallback callback, void *user_data);
+
+/* Stop and free a sender, sending end of stream to the receiver */
+void las_sender_destroy(LasSender *sender);
+
+/* Create a receiver listening on UDP `port` (0 for the default) */
+LasReceiver *las_receiver_create(uint16_t port);
+
+/* Play track `track_id` on `device_id` (NULL for the default output) */
+int32_t las_receiver_add_track(LasReceiver *receiver, uint8_t track_id, const char *name, const char *device_id);
+
+/* Hand track `track_id` to `callback` in real time instead of a device */
+int32_t las_receiver_add_callback_track(LasReceiver *receiver,
+                                        uint8_t track_id,
+                                        const char *name,
+                                        LasAudioCallback callback,
+                                        void *user_data);
+
+/* Start receiving and playing all tracks */
+int32_t las_receiver_start(LasReceiver *receiver);
+
+/* Stop receiving; can be started again */
+int32_t las_receiver_stop(LasReceiver *receiver);
+
+/* Fill `out` with a track's statistics */
+int32_t las_receiver_get_stats(const LasReceiver *receiver, uint8_t track_id, LasTrackStats *out);
+
+/* Receive track events through `callback` (NULL to stop) */
+int32_t las_receiver_set_event_callback(LasReceiver *receiver, LasEventCallback callback, void *user_data);
+
+/* Stop and free a receiver */
+void las_receiver_destroy(LasReceiver *receiver);
+
+#ifdef __cplusplus
+}  /* extern "C" */
+#endif
+
+#endif  /* LAN_AUDIO_STREAMER_H */
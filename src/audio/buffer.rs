@@ -4,14 +4,22 @@
 //! optimized for real-time audio with minimal latency.
 
 use crossbeam::queue::ArrayQueue;
-use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::Arc;
+use parking_lot::Mutex;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+
+use crate::audio::delay::DelayLine;
+use crate::audio::samples::Samples;
+use crate::constants::DEFAULT_LATE_THRESHOLD;
+use crate::wake::Wakeup;
+
 
 /// Audio frame containing interleaved samples
 #[derive(Clone)]
 pub struct AudioFrame {
-    /// Interleaved audio samples (f32)
-    pub samples: Vec<f32>,
+    /// Interleaved audio samples (f32), shared between clones of the frame
+    pub samples: Samples,
     /// Number of channels
     pub channels: u16,
     /// Timestamp in microseconds
@@ -21,9 +29,9 @@ pub struct AudioFrame {
 }
 
 impl AudioFrame {
-    pub fn new(samples: Vec<f32>, channels: u16, timestamp: u64, sequence: u32) -> Self {
+    pub fn new(samples: impl Into<Samples>, channels: u16, timestamp: u64, sequence: u32) -> Self {
         Self {
-            samples,
+            samples: samples.into(),
             channels,
             timestamp,
             sequence,
@@ -46,6 +54,8 @@ pub struct RingBuffer {
     queue: ArrayQueue<AudioFrame>,
     overflow_count: AtomicUsize,
     underrun_count: AtomicUsize,
+    /// Rung on every push
+    wakeup: OnceLock<Wakeup>,
 }
 
 impl RingBuffer {
@@ -55,14 +65,27 @@ impl RingBuffer {
             queue: ArrayQueue::new(capacity),
             overflow_count: AtomicUsize::new(0),
             underrun_count: AtomicUsize::new(0),
+            wakeup: OnceLock::new(),
         }
     }
     
+    /// Ring `wakeup` whenever a frame is pushed, so the consumer need not poll
+    /// 
+    /// Set once; later calls are ignored.
+    pub fn set_wakeup(&self, wakeup: Wakeup) {
+        let _ = self.wakeup.set(wakeup);
+    }
+    
     /// Push a frame into the buffer
     /// Returns false if buffer is full (overflow)
     pub fn push(&self, frame: AudioFrame) -> bool {
         match self.queue.push(frame) {
-            Ok(()) => true,
+            Ok(()) => {
+                if let Some(wakeup) = self.wakeup.get() {
+                    wakeup.wake();
+                }
+                true
+            }
             Err(_) => {
                 self.overflow_count.fetch_add(1, Ordering::Relaxed);
                 false
@@ -137,136 +160,582 @@ pub fn create_shared_buffer(capacity: usize) -> SharedRingBuffer {
     Arc::new(RingBuffer::new(capacity))
 }
 
-/// Jitter buffer for packet reordering
-pub struct JitterBuffer {
-    /// Buffer slots indexed by sequence modulo capacity
-    slots: Vec<Option<AudioFrame>>,
-    /// Capacity (must be power of 2)
+/// What to do when a packet arrives at a full jitter buffer
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowPolicy {
+    /// Discard the oldest buffered frame to make room (favours latency)
+    #[default]
+    DropOldest,
+    /// Discard the arriving frame (favours continuity)
+    DropNewest,
+}
+
+/// Occupancy samples kept for [`JitterBuffer::occupancy_history`]
+const OCCUPANCY_HISTORY: usize = 256;
+
+/// Sequences remembered for spotting duplicates
+const DUPLICATE_WINDOW: usize = 1024;
+
+/// Buffer level over a stretch of release attempts
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct OccupancySummary {
+    /// Release attempts sampled
+    pub samples: usize,
+    pub min: usize,
+    pub max: usize,
+    pub avg: f32,
+    /// Attempts that found the buffer empty
+    pub empty: usize,
+}
+
+/// Running [`OccupancySummary`] since the last take
+#[derive(Default)]
+struct OccupancyPeriod {
+    samples: usize,
+    min: usize,
+    max: usize,
+    sum: usize,
+    empty: usize,
+}
+
+impl OccupancyPeriod {
+    fn record(&mut self, level: usize) {
+        self.min = if self.samples == 0 { level } else { self.min.min(level) };
+        self.max = self.max.max(level);
+        self.sum += level;
+        self.empty += (level == 0) as usize;
+        self.samples += 1;
+    }
+    
+    fn take(&mut self) -> Option<OccupancySummary> {
+        let period = std::mem::take(self);
+        (period.samples > 0).then(|| OccupancySummary {
+            samples: period.samples,
+            min: period.min,
+            max: period.max,
+            avg: period.sum as f32 / period.samples as f32,
+            empty: period.empty,
+        })
+    }
+}
+
+/// Jitter buffer state, guarded by the [`JitterBuffer`] lock
+struct JitterState {
+    /// Buffered frames keyed by extended sequence
+    frames: BTreeMap<u64, AudioFrame>,
+    /// Maximum number of buffered frames
     capacity: usize,
-    /// Mask for fast modulo
-    mask: usize,
-    /// Next expected sequence number
-    next_sequence: u32,
+    /// Next extended sequence to release (None until the first release)
+    next_sequence: Option<u64>,
+    /// Most recent extended sequence seen, used to unwrap new ones
+    reference: Option<u64>,
+    /// Timestamp of the last released frame
+    last_timestamp: u64,
     /// Minimum buffer delay in frames
     min_delay: usize,
-    /// Current buffer level
-    level: AtomicUsize,
+    /// Behaviour when full
+    policy: OverflowPolicy,
     /// Packets received
-    received: AtomicUsize,
-    /// Packets lost
-    lost: AtomicUsize,
-    /// Late packets
-    late: AtomicUsize,
+    received: usize,
+    /// Packets that never arrived within `late_threshold` of their slot
+    lost: usize,
+    /// Packets that arrived after their slot, within `late_threshold`
+    late: usize,
+    /// Packets that arrived in time but after a later sequence
+    reordered: usize,
+    /// Slots played out empty, not counted lost yet
+    missing: BTreeSet<u64>,
+    /// Slots a missing packet may trail playout by before it counts as lost
+    late_threshold: usize,
+    /// Frames discarded by the overflow policy
+    overflowed: usize,
+    /// Packets dropped as copies of one already taken
+    duplicates: usize,
+    /// Extended sequences taken lately, newest [`DUPLICATE_WINDOW`]
+    recent: BTreeSet<u64>,
+    /// Buffer level at each release attempt
+    occupancy: VecDeque<usize>,
+    /// Buffer level since [`JitterBuffer::take_occupancy`] was last called
+    period: OccupancyPeriod,
 }
 
-impl JitterBuffer {
-    /// Create a new jitter buffer
-    /// capacity must be a power of 2
-    pub fn new(capacity: usize, min_delay: usize) -> Self {
-        assert!(capacity.is_power_of_two(), "Capacity must be power of 2");
-        
-        let mut slots = Vec::with_capacity(capacity);
-        slots.resize_with(capacity, || None);
-        
+impl JitterState {
+    fn new(capacity: usize, min_delay: usize, policy: OverflowPolicy) -> Self {
         Self {
-            slots,
-            capacity,
-            mask: capacity - 1,
-            next_sequence: 0,
+            frames: BTreeMap::new(),
+            capacity: capacity.max(1),
+            next_sequence: None,
+            reference: None,
+            last_timestamp: 0,
             min_delay,
-            level: AtomicUsize::new(0),
-            received: AtomicUsize::new(0),
-            lost: AtomicUsize::new(0),
-            late: AtomicUsize::new(0),
+            policy,
+            received: 0,
+            lost: 0,
+            late: 0,
+            reordered: 0,
+            missing: BTreeSet::new(),
+            late_threshold: DEFAULT_LATE_THRESHOLD,
+            overflowed: 0,
+            duplicates: 0,
+            recent: BTreeSet::new(),
+            occupancy: VecDeque::with_capacity(OCCUPANCY_HISTORY),
+            period: OccupancyPeriod::default(),
         }
     }
     
+    /// Extend a 32-bit sequence to 64 bits around the reference
+    fn extend(&self, seq: u32) -> u64 {
+        match self.reference {
+            Some(reference) => {
+                let delta = seq.wrapping_sub(reference as u32) as i32;
+                (reference as i64 + delta as i64) as u64
+            }
+            // Start well away from zero so early wraps backwards stay positive
+            None => (1u64 << 32) | seq as u64,
+        }
+    }
+    
+    /// Count `seq` as a duplicate if it was taken already
+    fn check_duplicate(&mut self, seq: u64) -> bool {
+        let duplicate = self.recent.contains(&seq);
+        if duplicate {
+            self.duplicates += 1;
+        }
+        duplicate
+    }
+    
     /// Insert a frame into the jitter buffer
-    pub fn insert(&mut self, frame: AudioFrame) -> bool {
-        let seq = frame.sequence;
+    fn insert(&mut self, frame: AudioFrame) -> bool {
+        let mut seq = self.extend(frame.sequence);
+        if self.check_duplicate(seq) {
+            return false;
+        }
         
-        // Check if packet is too late
-        if seq < self.next_sequence {
-            let diff = self.next_sequence - seq;
-            if diff > self.capacity as u32 / 2 {
-                // Sequence wrapped around, this is actually a future packet
-            } else {
-                // Packet is late
-                self.late.fetch_add(1, Ordering::Relaxed);
+        if let Some(next) = self.next_sequence {
+            let distance = seq.abs_diff(next);
+            let restarted = distance > self.capacity as u64 * 4
+                && frame.timestamp > self.last_timestamp;
+            
+            if restarted {
+                tracing::debug!("Sequence jump with newer timestamp, resetting jitter buffer");
+                self.reset();
+                seq = self.extend(frame.sequence);
+            } else if seq < next {
+                // Late if its slot is still waiting on it, otherwise already lost
+                if self.missing.remove(&seq) {
+                    self.late += 1;
+                }
                 return false;
             }
         }
+        if self.reference.is_some_and(|reference| seq < reference) {
+            self.reordered += 1;
+        }
+        
+        if self.frames.len() >= self.capacity && !self.frames.contains_key(&seq) {
+            match self.policy {
+                OverflowPolicy::DropNewest => {
+                    self.overflowed += 1;
+                    return false;
+                }
+                OverflowPolicy::DropOldest => {
+                    if let Some((&oldest, _)) = self.frames.iter().next() {
+                        if oldest > seq {
+                            // The arriving frame is itself the oldest
+                            self.overflowed += 1;
+                            return false;
+                        }
+                        self.frames.remove(&oldest);
+                        self.overflowed += 1;
+                        if let Some(next) = self.next_sequence.as_mut() {
+                            *next = (*next).max(oldest + 1);
+                        }
+                    }
+                }
+            }
+        }
         
-        let index = (seq as usize) & self.mask;
-        self.slots[index] = Some(frame);
-        self.received.fetch_add(1, Ordering::Relaxed);
-        self.level.fetch_add(1, Ordering::Relaxed);
+        self.reference = Some(self.reference.map_or(seq, |r| r.max(seq)));
+        self.frames.insert(seq, frame);
+        self.received += 1;
+        self.recent.insert(seq);
+        if self.recent.len() > DUPLICATE_WINDOW {
+            self.recent.pop_first();
+        }
         
         true
     }
     
     /// Get the next frame if available and buffered enough
-    pub fn get_next(&mut self) -> Option<AudioFrame> {
+    fn get_next(&mut self) -> Option<AudioFrame> {
+        self.record_occupancy();
+        
         // Check if we have minimum delay buffered
-        if self.level.load(Ordering::Relaxed) < self.min_delay {
+        if self.frames.len() < self.min_delay {
             return None;
         }
         
-        let index = (self.next_sequence as usize) & self.mask;
-        let frame = self.slots[index].take();
-        
-        if frame.is_some() {
-            self.level.fetch_sub(1, Ordering::Relaxed);
-        } else {
-            // Packet was lost
-            self.lost.fetch_add(1, Ordering::Relaxed);
-        }
-        
-        self.next_sequence = self.next_sequence.wrapping_add(1);
-        frame
+        self.release()
     }
     
     /// Force get the next frame even if buffer level is low
-    pub fn force_get_next(&mut self) -> Option<AudioFrame> {
-        let index = (self.next_sequence as usize) & self.mask;
-        let frame = self.slots[index].take();
+    fn force_get_next(&mut self) -> Option<AudioFrame> {
+        self.record_occupancy();
+        self.release()
+    }
+    
+    fn release(&mut self) -> Option<AudioFrame> {
+        let next = match self.next_sequence {
+            Some(next) => next,
+            None => *self.frames.keys().next()?,
+        };
         
-        if frame.is_some() {
-            let _ = self.level.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |v| {
-                if v > 0 { Some(v - 1) } else { Some(0) }
-            });
-        } else {
-            self.lost.fetch_add(1, Ordering::Relaxed);
+        let frame = self.frames.remove(&next);
+        match frame {
+            Some(ref frame) => self.last_timestamp = frame.timestamp,
+            // Lost unless it turns up within the late threshold
+            None => {
+                self.missing.insert(next);
+            }
         }
         
-        self.next_sequence = self.next_sequence.wrapping_add(1);
+        self.next_sequence = Some(next + 1);
+        self.settle_missing(next + 1);
         frame
     }
     
-    /// Reset the jitter buffer
-    pub fn reset(&mut self) {
-        for slot in &mut self.slots {
-            *slot = None;
+    /// Count missing slots more than `late_threshold` behind `next` as lost
+    fn settle_missing(&mut self, next: u64) {
+        let horizon = next.saturating_sub(self.late_threshold as u64);
+        let pending = self.missing.split_off(&horizon);
+        self.lost += self.missing.len();
+        self.missing = pending;
+    }
+    
+    fn record_occupancy(&mut self) {
+        if self.occupancy.len() == OCCUPANCY_HISTORY {
+            self.occupancy.pop_front();
         }
-        self.next_sequence = 0;
-        self.level.store(0, Ordering::Relaxed);
+        self.occupancy.push_back(self.frames.len());
+        self.period.record(self.frames.len());
+    }
+    
+    /// Reset the jitter buffer
+    fn reset(&mut self) {
+        // Slots still waiting on a packet won't get it now
+        self.lost += self.missing.len();
+        self.missing.clear();
+        self.frames.clear();
+        self.recent.clear();
+        self.next_sequence = None;
+        self.reference = None;
     }
     
     /// Set the next expected sequence (for sync)
-    pub fn set_next_sequence(&mut self, seq: u32) {
+    fn set_next_sequence(&mut self, seq: u32) {
         self.reset();
-        self.next_sequence = seq;
+        let extended = self.extend(seq);
+        self.reference = Some(extended);
+        self.next_sequence = Some(extended);
     }
     
     /// Get statistics
-    pub fn stats(&self) -> JitterBufferStats {
+    fn stats(&self) -> JitterBufferStats {
+        let (min, max, sum) = self.occupancy
+            .iter()
+            .fold((usize::MAX, 0, 0), |(min, max, sum), &level| {
+                (min.min(level), max.max(level), sum + level)
+            });
+        
         JitterBufferStats {
-            level: self.level.load(Ordering::Relaxed),
+            level: self.frames.len(),
             capacity: self.capacity,
-            received: self.received.load(Ordering::Relaxed),
-            lost: self.lost.load(Ordering::Relaxed),
-            late: self.late.load(Ordering::Relaxed),
+            received: self.received,
+            lost: self.lost,
+            late: self.late,
+            reordered: self.reordered,
+            pending: self.missing.len(),
+            overflowed: self.overflowed,
+            duplicates: self.duplicates,
+            occupancy_min: if self.occupancy.is_empty() { 0 } else { min },
+            occupancy_max: max,
+            occupancy_avg: if self.occupancy.is_empty() {
+                0.0
+            } else {
+                sum as f32 / self.occupancy.len() as f32
+            },
+        }
+    }
+}
+
+/// Jitter buffer for packet reordering
+///
+/// Frames are kept ordered by an extended (wrap-free) sequence number.
+/// The last [`DUPLICATE_WINDOW`] sequences taken are remembered, so a
+/// second copy of a packet (some WiFi drivers deliver them) is counted and
+/// dropped rather than replacing the first or counting as late. A frame
+/// whose sequence lies far outside the window but whose timestamp is newer
+/// than anything played is taken as a sender restart and resets the buffer
+/// instead of being dropped as late.
+///
+/// A slot played out empty is not lost yet: if its packet arrives within
+/// the late threshold (in packets) it counts as late, otherwise as lost.
+/// Packets that arrive in time but behind a later one count as reordered.
+///
+/// Internally locked, so the network thread can insert while the playback
+/// callback pulls from the same buffer through an `Arc`.
+pub struct JitterBuffer {
+    state: Mutex<JitterState>,
+}
+
+impl JitterBuffer {
+    /// Create a new jitter buffer
+    pub fn new(capacity: usize, min_delay: usize) -> Self {
+        Self::with_policy(capacity, min_delay, OverflowPolicy::default())
+    }
+    
+    /// Create a new jitter buffer with an explicit overflow policy
+    pub fn with_policy(capacity: usize, min_delay: usize, policy: OverflowPolicy) -> Self {
+        Self {
+            state: Mutex::new(JitterState::new(capacity, min_delay, policy)),
+        }
+    }
+    
+    /// Insert a frame into the jitter buffer
+    pub fn insert(&self, frame: AudioFrame) -> bool {
+        self.state.lock().insert(frame)
+    }
+    
+    /// True (and counted) if a packet with `seq` was already taken
+    /// 
+    /// Lets the receiver drop a duplicate before decoding it.
+    pub fn check_duplicate(&self, seq: u32) -> bool {
+        let mut state = self.state.lock();
+        let seq = state.extend(seq);
+        state.check_duplicate(seq)
+    }
+    
+    /// Get the next frame if available and buffered enough
+    pub fn get_next(&self) -> Option<AudioFrame> {
+        self.state.lock().get_next()
+    }
+    
+    /// Force get the next frame even if buffer level is low
+    pub fn force_get_next(&self) -> Option<AudioFrame> {
+        self.state.lock().force_get_next()
+    }
+    
+    /// Reset the jitter buffer
+    pub fn reset(&self) {
+        self.state.lock().reset();
+    }
+    
+    /// Set the next expected sequence (for sync)
+    pub fn set_next_sequence(&self, seq: u32) {
+        self.state.lock().set_next_sequence(seq);
+    }
+    
+    /// Get the overflow policy
+    pub fn policy(&self) -> OverflowPolicy {
+        self.state.lock().policy
+    }
+    
+    /// Set the overflow policy
+    pub fn set_policy(&self, policy: OverflowPolicy) {
+        self.state.lock().policy = policy;
+    }
+    
+    /// Set how many packets past its slot a missing packet counts as late rather than lost
+    pub fn set_late_threshold(&self, packets: usize) {
+        self.state.lock().late_threshold = packets;
+    }
+    
+    /// Get number of buffered frames
+    pub fn len(&self) -> usize {
+        self.state.lock().frames.len()
+    }
+    
+    /// Check if no frames are buffered
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    
+    /// Get maximum number of buffered frames
+    pub fn capacity(&self) -> usize {
+        self.state.lock().capacity
+    }
+    
+    /// Buffer level at recent release attempts, oldest first
+    pub fn occupancy_history(&self) -> Vec<usize> {
+        self.state.lock().occupancy.iter().copied().collect()
+    }
+    
+    /// Buffer level since the last call (None if nothing was released since)
+    /// 
+    /// Meant for one periodic reporter; see [`crate::tracks::stats::OccupancyTrend`].
+    pub fn take_occupancy(&self) -> Option<OccupancySummary> {
+        self.state.lock().period.take()
+    }
+    
+    /// Get statistics
+    pub fn stats(&self) -> JitterBufferStats {
+        self.state.lock().stats()
+    }
+}
+
+/// Thread-safe handle to a jitter buffer
+pub type SharedJitterBuffer = Arc<JitterBuffer>;
+
+/// Work done on each frame as playback releases it
+pub type FrameStage = Box<dyn FnMut(&mut AudioFrame) + Send>;
+
+/// Frames generated or released outside the jitter buffer
+const RELEASE_QUEUE: usize = 64;
+
+/// A jitter buffer that playback releases from directly
+///
+/// The output callback (or sink thread) takes each frame out of the jitter
+/// buffer, runs the owner's [`FrameStage`] and the playout delay on it, and
+/// plays it; no thread in between copies frames along. While the jitter
+/// buffer has nothing to release, frames the owner generated (comfort
+/// noise) play instead. With [`keep_released`](Self::keep_released), the
+/// released frames are also queued for the owner's meters and mixes,
+/// sharing their samples with the played ones.
+pub struct JitterRelease {
+    jitter: SharedJitterBuffer,
+    generated: SharedRingBuffer,
+    released: SharedRingBuffer,
+    keep_released: AtomicBool,
+    stage: Mutex<ReleaseStage>,
+}
+
+#[derive(Default)]
+struct ReleaseStage {
+    process: Option<FrameStage>,
+    delay: Option<DelayLine>,
+}
+
+impl JitterRelease {
+    pub fn new(jitter: SharedJitterBuffer) -> Self {
+        Self {
+            jitter,
+            generated: create_shared_buffer(RELEASE_QUEUE),
+            released: create_shared_buffer(RELEASE_QUEUE),
+            keep_released: AtomicBool::new(false),
+            stage: Mutex::new(ReleaseStage::default()),
+        }
+    }
+
+    /// The jitter buffer released from
+    pub fn jitter_buffer(&self) -> &SharedJitterBuffer {
+        &self.jitter
+    }
+
+    /// Replace the work done on each released frame (None = none)
+    pub fn set_stage(&self, stage: Option<FrameStage>) {
+        self.stage.lock().process = stage;
+    }
+
+    /// Set the playout delay of a `sample_rate`, `channels` stream
+    pub fn set_delay_ms(&self, delay_ms: u32, sample_rate: u32, channels: u16) {
+        let mut stage = self.stage.lock();
+        if stage.delay.is_none() && delay_ms == 0 {
+            return;
+        }
+        stage.delay.get_or_insert_with(|| DelayLine::new(sample_rate, channels)).set_delay_ms(delay_ms);
+    }
+
+    /// Playout delay in milliseconds
+    pub fn delay_ms(&self) -> u32 {
+        self.stage.lock().delay.as_ref().map_or(0, DelayLine::delay_ms)
+    }
+
+    /// Queue a frame to play while the jitter buffer has none
+    pub fn push_generated(&self, frame: AudioFrame) -> bool {
+        self.generated.push(frame)
+    }
+
+    /// Queue released frames for [`take_released`](Self::take_released)
+    pub fn keep_released(&self, keep: bool) {
+        self.keep_released.store(keep, Ordering::Relaxed);
+    }
+
+    /// Next frame released since the last call, after the stage and delay
+    pub fn take_released(&self) -> Option<AudioFrame> {
+        self.released.try_pop()
+    }
+
+    /// Frames waiting to be played, released or generated
+    pub fn queued_frames(&self) -> usize {
+        self.jitter.len() + self.generated.len()
+    }
+
+    /// Take the next frame to play, if any
+    fn next_frame(&self) -> Option<AudioFrame> {
+        let mut stage = self.stage.lock();
+        let (mut frame, released) = match self.jitter.get_next() {
+            Some(mut frame) => {
+                if let Some(process) = stage.process.as_mut() {
+                    process(&mut frame);
+                }
+                (frame, true)
+            }
+            None => (self.generated.try_pop()?, false),
+        };
+        if let Some(delay) = stage.delay.as_mut().filter(|delay| delay.delay_ms() > 0) {
+            delay.process(&mut frame.samples);
+        }
+        if released && self.keep_released.load(Ordering::Relaxed) {
+            let _ = self.released.push(frame.clone());
         }
+        Some(frame)
+    }
+}
+
+/// Where playback (a device or a custom sink) pulls frames from
+#[derive(Clone)]
+pub enum PlaybackSource {
+    /// Frames already released and processed upstream
+    Ring(SharedRingBuffer),
+    /// Frames released straight out of a shared jitter buffer
+    Jitter(Arc<JitterRelease>),
+}
+
+impl PlaybackSource {
+    /// Take the next frame to play, if any
+    pub(crate) fn next_frame(&self) -> Option<AudioFrame> {
+        match self {
+            Self::Ring(buffer) => buffer.try_pop(),
+            Self::Jitter(release) => release.next_frame(),
+        }
+    }
+
+    /// Buffered frames and capacity
+    #[cfg(feature = "cpal")]
+    pub(crate) fn fill(&self) -> (usize, usize) {
+        match self {
+            Self::Ring(buffer) => (buffer.len(), buffer.capacity()),
+            Self::Jitter(release) => (release.queued_frames(), release.jitter_buffer().capacity()),
+        }
+    }
+}
+
+impl From<SharedRingBuffer> for PlaybackSource {
+    fn from(buffer: SharedRingBuffer) -> Self {
+        Self::Ring(buffer)
+    }
+}
+
+impl From<SharedJitterBuffer> for PlaybackSource {
+    fn from(jitter: SharedJitterBuffer) -> Self {
+        Self::Jitter(Arc::new(JitterRelease::new(jitter)))
+    }
+}
+
+impl From<Arc<JitterRelease>> for PlaybackSource {
+    fn from(release: Arc<JitterRelease>) -> Self {
+        Self::Jitter(release)
     }
 }
 
@@ -276,8 +745,22 @@ pub struct JitterBufferStats {
     pub level: usize,
     pub capacity: usize,
     pub received: usize,
+    /// Packets that never arrived within the late threshold
     pub lost: usize,
+    /// Packets that arrived after their slot was played, within the late threshold
     pub late: usize,
+    /// Packets that arrived in time but out of order
+    pub reordered: usize,
+    /// Slots played empty whose packet may still count as late
+    pub pending: usize,
+    /// Frames discarded because the buffer was full
+    pub overflowed: usize,
+    /// Packets dropped as duplicates
+    pub duplicates: usize,
+    /// Occupancy over the recent history window
+    pub occupancy_min: usize,
+    pub occupancy_max: usize,
+    pub occupancy_avg: f32,
 }
 
 impl JitterBufferStats {
@@ -316,7 +799,7 @@ mod tests {
     
     #[test]
     fn test_jitter_buffer() {
-        let mut jitter = JitterBuffer::new(16, 2);
+        let jitter = JitterBuffer::new(16, 2);
         
         // Insert out of order
         jitter.insert(AudioFrame::new(vec![], 2, 20000, 2));
@@ -333,4 +816,135 @@ mod tests {
         // Not enough buffered for min_delay now
         assert!(jitter.get_next().is_none());
     }
+    
+    #[test]
+    fn test_jitter_buffer_overflow_and_wrap() {
+        // A burst past capacity drops the oldest frames instead of aliasing slots
+        let jitter = JitterBuffer::new(4, 1);
+        for seq in 0..6u32 {
+            jitter.insert(AudioFrame::new(vec![], 2, seq as u64 * 10, seq));
+        }
+        assert_eq!(jitter.stats().overflowed, 2);
+        assert_eq!(jitter.get_next().unwrap().sequence, 2);
+        
+        // Newest-first policy keeps what is already buffered
+        let jitter = JitterBuffer::with_policy(2, 1, OverflowPolicy::DropNewest);
+        assert!(jitter.insert(AudioFrame::new(vec![], 2, 0, 0)));
+        assert!(jitter.insert(AudioFrame::new(vec![], 2, 10, 1)));
+        assert!(!jitter.insert(AudioFrame::new(vec![], 2, 20, 2)));
+        
+        // Sequence numbers wrap without a reset
+        let jitter = JitterBuffer::new(8, 1);
+        jitter.insert(AudioFrame::new(vec![], 2, 0, u32::MAX));
+        jitter.insert(AudioFrame::new(vec![], 2, 10, 0));
+        assert_eq!(jitter.get_next().unwrap().sequence, u32::MAX);
+        assert_eq!(jitter.get_next().unwrap().sequence, 0);
+        assert_eq!(jitter.stats().lost, 0);
+        assert_eq!(jitter.occupancy_history(), vec![2, 1]);
+        let period = jitter.take_occupancy().unwrap();
+        assert_eq!((period.samples, period.min, period.max, period.avg, period.empty), (2, 1, 2, 1.5, 0));
+        assert_eq!(jitter.take_occupancy(), None);
+    }
+    
+    #[test]
+    fn test_jitter_buffer_duplicates() {
+        let jitter = JitterBuffer::new(8, 1);
+        assert!(jitter.insert(AudioFrame::new(vec![1.0], 1, 0, 5)));
+        assert!(jitter.check_duplicate(5));
+        assert!(!jitter.check_duplicate(6));
+        // A copy is dropped, not counted late, and doesn't replace the first
+        assert!(!jitter.insert(AudioFrame::new(vec![2.0], 1, 0, 5)));
+        assert_eq!(jitter.get_next().unwrap().samples, vec![1.0]);
+        // Still a duplicate after its slot was played out
+        assert!(!jitter.insert(AudioFrame::new(vec![2.0], 1, 0, 5)));
+        let stats = jitter.stats();
+        assert_eq!((stats.duplicates, stats.late, stats.received), (3, 0, 1));
+        
+        // A restart forgets the old sequences
+        jitter.reset();
+        assert!(jitter.insert(AudioFrame::new(vec![3.0], 1, 0, 5)));
+    }
+    
+    #[test]
+    fn test_jitter_buffer_loss_classification() {
+        let jitter = JitterBuffer::new(16, 1);
+        jitter.set_late_threshold(2);
+        // 1 arrives after 2: reordered, not lost
+        for seq in [0, 2, 1] {
+            jitter.insert(AudioFrame::new(vec![], 1, seq as u64 * 10, seq));
+        }
+        // 3 and 4 are missing when played out
+        jitter.insert(AudioFrame::new(vec![], 1, 50, 5));
+        for _ in 0..5 {
+            jitter.force_get_next();
+        }
+        let stats = jitter.stats();
+        assert_eq!((stats.reordered, stats.lost, stats.pending), (1, 0, 2));
+        
+        // 3 turns up within the threshold, then 4's slot runs out of time
+        assert!(!jitter.insert(AudioFrame::new(vec![], 1, 30, 3)));
+        jitter.insert(AudioFrame::new(vec![], 1, 60, 6));
+        jitter.force_get_next();
+        jitter.force_get_next();
+        let stats = jitter.stats();
+        assert_eq!((stats.late, stats.lost, stats.pending), (1, 1, 0));
+        
+        // Once lost, a straggler stays lost
+        assert!(!jitter.insert(AudioFrame::new(vec![], 1, 40, 4)));
+        let stats = jitter.stats();
+        assert_eq!((stats.late, stats.lost, stats.received), (1, 1, 5));
+    }
+    
+    #[test]
+    fn test_jitter_buffer_shared() {
+        // Network thread inserts while another thread releases
+        let jitter: SharedJitterBuffer = Arc::new(JitterBuffer::new(64, 1));
+        let writer = {
+            let jitter = jitter.clone();
+            std::thread::spawn(move || {
+                for seq in 0..32u32 {
+                    jitter.insert(AudioFrame::new(vec![], 2, seq as u64 * 10, seq));
+                }
+            })
+        };
+        writer.join().unwrap();
+        
+        let mut released = 0;
+        while let Some(frame) = jitter.force_get_next() {
+            assert_eq!(frame.sequence, released);
+            released += 1;
+        }
+        assert_eq!(released, 32);
+    }
+    
+    #[test]
+    fn test_jitter_release() {
+        let jitter: SharedJitterBuffer = Arc::new(JitterBuffer::new(16, 1));
+        let release = Arc::new(JitterRelease::new(jitter.clone()));
+        let source = PlaybackSource::from(release.clone());
+        release.set_stage(Some(Box::new(|frame: &mut AudioFrame| frame.samples.make_mut().iter_mut().for_each(|s| *s *= 0.5))));
+        release.keep_released(true);
+        
+        // Released frames go through the stage and are kept for the owner
+        jitter.insert(AudioFrame::new(vec![1.0, 1.0], 2, 0, 0));
+        jitter.insert(AudioFrame::new(vec![1.0, 1.0], 2, 10, 1));
+        assert_eq!(release.queued_frames(), 2);
+        assert_eq!(&source.next_frame().unwrap().samples[..], &[0.5, 0.5]);
+        assert_eq!(release.take_released().unwrap().sequence, 0);
+        assert!(release.take_released().is_none());
+        assert_eq!(source.next_frame().unwrap().sequence, 1);
+        
+        // Generated frames fill in while the jitter buffer is empty, untouched
+        assert!(source.next_frame().is_none());
+        release.push_generated(AudioFrame::new(vec![0.25, 0.25], 2, 20, 0));
+        assert_eq!(&source.next_frame().unwrap().samples[..], &[0.25, 0.25]);
+        assert_eq!(release.take_released().unwrap().sequence, 1);
+        assert!(release.take_released().is_none());
+        
+        // A playout delay holds samples back
+        release.set_delay_ms(1, 1000, 1);
+        assert_eq!(release.delay_ms(), 1);
+        release.push_generated(AudioFrame::new(vec![0.25, 0.75], 1, 30, 0));
+        assert_eq!(&source.next_frame().unwrap().samples[..], &[0.0, 0.25]);
+    }
 }
@@ -9,12 +9,18 @@ use crossbeam_channel::{bounded, Receiver};
 use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::thread::{self, JoinHandle};
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use crate::audio::buffer::{AudioFrame, SharedRingBuffer};
 use crate::audio::device::get_device_by_id;
-use crate::constants::DEFAULT_SAMPLE_RATE;
+use crate::audio::fade::Ramp;
+use crate::audio::media_clock::MediaClock;
+use crate::audio::playback::XRUN_QUEUE;
+use crate::audio::samples::SamplePool;
+use crate::constants::{DEFAULT_SAMPLE_RATE, TRANSITION_FADE_MS};
 use crate::error::AudioError;
+use crate::protocol::{XrunEvent, XrunKind};
+use crate::sync::now_us;
 
 /// Audio capture instance for a single device
 pub struct AudioCapture {
@@ -36,12 +42,24 @@ pub struct AudioCapture {
     /// Channel for stream errors
     error_rx: Option<Receiver<AudioError>>,
     
+    /// Overflows reported by the input callback
+    xrun_rx: Option<Receiver<XrunEvent>>,
+    
     /// Current sequence number
     sequence: Arc<AtomicU32>,
     
     /// Total samples captured
     samples_captured: Arc<AtomicU64>,
     
+    /// Audio clock drift against the stream clock in ppm (f64 bits, NaN until known)
+    drift_ppm: Arc<AtomicU64>,
+    
+    /// Stop requested; input is ramping down
+    stopping: Arc<AtomicBool>,
+    
+    /// Stop ramp has reached silence
+    stop_faded: Arc<AtomicBool>,
+    
     /// Stream configuration
     config: StreamConfig,
     
@@ -80,8 +98,12 @@ impl AudioCapture {
             output_buffer,
             thread_handle: None,
             error_rx: None,
+            xrun_rx: None,
             sequence: Arc::new(AtomicU32::new(0)),
             samples_captured: Arc::new(AtomicU64::new(0)),
+            drift_ppm: Arc::new(AtomicU64::new(f64::NAN.to_bits())),
+            stopping: Arc::new(AtomicBool::new(false)),
+            stop_faded: Arc::new(AtomicBool::new(false)),
             config,
             start_time: Instant::now(),
         })
@@ -96,19 +118,30 @@ impl AudioCapture {
         let device = get_device_by_id(&self.device_id)?;
         let (error_tx, error_rx) = bounded::<AudioError>(16);
         self.error_rx = Some(error_rx);
+        let (xrun_tx, xrun_rx) = bounded::<XrunEvent>(XRUN_QUEUE);
+        self.xrun_rx = Some(xrun_rx);
         
+        let track_id = self.track_id;
         let running = self.running.clone();
         let running_for_loop = self.running.clone();
         let output_buffer = self.output_buffer.clone();
         let sequence = self.sequence.clone();
         let samples_captured = self.samples_captured.clone();
+        let drift_ppm = self.drift_ppm.clone();
         let config = self.config.clone();
         let channels = self.config.channels;
-        let _sample_rate = self.config.sample_rate.0;
+        let sample_rate = self.config.sample_rate.0;
+        let stopping = self.stopping.clone();
+        let stop_faded = self.stop_faded.clone();
+        // Enough buffers for a full ring, so the callback never allocates
+        let pool = SamplePool::with_capacity(self.output_buffer.capacity());
+        self.stopping.store(false, Ordering::SeqCst);
+        self.stop_faded.store(false, Ordering::SeqCst);
         
         // Reset counters
         self.sequence.store(0, Ordering::SeqCst);
         self.samples_captured.store(0, Ordering::SeqCst);
+        self.drift_ppm.store(f64::NAN.to_bits(), Ordering::SeqCst);
         self.start_time = Instant::now();
         let start_time = self.start_time;
         
@@ -119,16 +152,46 @@ impl AudioCapture {
             .spawn(move || {
                 let cpal_device = device.into_inner();
                 
+                // Fade in on start, out on stop
+                let mut ramp = Ramp::new(sample_rate, TRANSITION_FADE_MS, 0.0);
+                ramp.set_target(1.0);
+                let mut was_full = false;
+                
+                // Timestamps count captured frames from the first sample's capture time
+                let mut clock = MediaClock::new(sample_rate);
+                let mut first_capture: Option<(cpal::StreamInstant, Duration)> = None;
+                
                 let stream = cpal_device.build_input_stream(
                     &config,
-                    move |data: &[f32], _: &cpal::InputCallbackInfo| {
-                        if !running.load(Ordering::Relaxed) {
+                    move |data: &[f32], info: &cpal::InputCallbackInfo| {
+                        if !running.load(Ordering::Relaxed) || stop_faded.load(Ordering::Relaxed) {
                             return;
                         }
                         
-                        // Calculate timestamp
-                        let elapsed = start_time.elapsed();
-                        let timestamp = elapsed.as_micros() as u64;
+                        let mut samples = pool.copy_from(data);
+                        if stopping.load(Ordering::Relaxed) {
+                            ramp.set_target(0.0);
+                        }
+                        ramp.apply(&mut samples, channels);
+                        if stopping.load(Ordering::Relaxed) && ramp.gain() == 0.0 {
+                            stop_faded.store(true, Ordering::Relaxed);
+                        }
+                        
+                        // When the block's first sample came in, on the stream clock
+                        // where the host reports one, anchored to the first block
+                        let stamps = info.timestamp();
+                        let &mut (first, anchor) = first_capture.get_or_insert_with(|| {
+                            let latency = stamps.callback.duration_since(&stamps.capture).unwrap_or_default();
+                            (stamps.capture, start_time.elapsed().saturating_sub(latency))
+                        });
+                        let captured = match stamps.capture.duration_since(&first) {
+                            Some(since) => anchor + since,
+                            None => start_time.elapsed(),
+                        };
+                        let timestamp = clock.stamp(data.len() / channels.max(1) as usize, captured);
+                        if let Some(drift) = clock.drift_ppm() {
+                            drift_ppm.store(drift.to_bits(), Ordering::Relaxed);
+                        }
                         
                         // Get sequence number
                         let seq = sequence.fetch_add(1, Ordering::Relaxed);
@@ -138,14 +201,25 @@ impl AudioCapture {
                         
                         // Create frame and push to buffer
                         let frame = AudioFrame::new(
-                            data.to_vec(),
+                            samples,
                             channels,
                             timestamp,
                             seq,
                         );
                         
-                        // Push to ring buffer (may fail on overflow)
-                        let _ = output_buffer.push(frame);
+                        // Push to ring buffer; report the start of each overflow
+                        let full = !output_buffer.push(frame);
+                        if full && !was_full {
+                            let _ = xrun_tx.try_send(XrunEvent {
+                                track_id,
+                                kind: XrunKind::Overflow,
+                                timestamp_us: now_us(),
+                                buffer_fill: output_buffer.len(),
+                                buffer_capacity: output_buffer.capacity(),
+                                callback_frames: data.len() / channels.max(1) as usize,
+                            });
+                        }
+                        was_full = full;
                     },
                     move |err| {
                         let _ = error_tx.try_send(AudioError::StreamError(err.to_string()));
@@ -179,7 +253,19 @@ impl AudioCapture {
     }
     
     /// Stop capturing audio
+    /// 
+    /// The last captured samples ramp down to silence so the stream does
+    /// not end on a click.
     pub fn stop(&mut self) {
+        if self.running.load(Ordering::SeqCst) {
+            self.stopping.store(true, Ordering::SeqCst);
+            let deadline = Instant::now()
+                + std::time::Duration::from_millis(TRANSITION_FADE_MS as u64 * 4);
+            while !self.stop_faded.load(Ordering::SeqCst) && Instant::now() < deadline {
+                thread::sleep(std::time::Duration::from_millis(1));
+            }
+        }
+        
         self.running.store(false, Ordering::SeqCst);
         
         if let Some(handle) = self.thread_handle.take() {
@@ -187,11 +273,42 @@ impl AudioCapture {
         }
     }
     
+    /// Stop and reopen the device, e.g. after an error
+    pub fn restart(&mut self) -> Result<(), AudioError> {
+        self.stop();
+        self.start()
+    }
+    
     /// Check if capture is running
     pub fn is_running(&self) -> bool {
         self.running.load(Ordering::SeqCst)
     }
     
+    /// Move capture to another input device
+    /// 
+    /// The old device fades out and the new one fades in.
+    pub fn set_device(&mut self, device_id: &str) -> Result<(), AudioError> {
+        if device_id == self.device_id {
+            return Ok(());
+        }
+        get_device_by_id(device_id)?;
+        
+        let was_running = self.is_running();
+        self.stop();
+        self.device_id = device_id.to_string();
+        tracing::info!("Track {} capture moved to {}", self.track_id, device_id);
+        
+        if was_running {
+            self.start()?;
+        }
+        Ok(())
+    }
+    
+    /// Get device identifier
+    pub fn device_id(&self) -> &str {
+        &self.device_id
+    }
+    
     /// Get current sequence number
     pub fn current_sequence(&self) -> u32 {
         self.sequence.load(Ordering::Relaxed)
@@ -207,6 +324,25 @@ impl AudioCapture {
         &self.config
     }
     
+    /// When capture started; frame timestamps count from here
+    pub fn started_at(&self) -> Instant {
+        self.start_time
+    }
+    
+    /// Drift of the device's sample clock against the stream clock in ppm
+    /// (positive: samples arrive faster than nominal), once measured
+    pub fn clock_drift_ppm(&self) -> Option<f64> {
+        let drift = f64::from_bits(self.drift_ppm.load(Ordering::Relaxed));
+        (!drift.is_nan()).then_some(drift)
+    }
+    
+    /// When a frame was captured, its media timestamp corrected for drift
+    pub fn captured_at(&self, frame: &AudioFrame) -> Instant {
+        let media = Duration::from_micros(frame.timestamp);
+        let scale = 1.0 - self.clock_drift_ppm().unwrap_or(0.0) / 1e6;
+        self.start_time + media.mul_f64(scale.max(0.0))
+    }
+    
     /// Get sample rate
     pub fn sample_rate(&self) -> u32 {
         self.config.sample_rate.0
@@ -221,6 +357,11 @@ impl AudioCapture {
     pub fn check_errors(&self) -> Option<AudioError> {
         self.error_rx.as_ref().and_then(|rx| rx.try_recv().ok())
     }
+    
+    /// Take overflow events reported since the last call
+    pub fn drain_xruns(&self) -> Vec<XrunEvent> {
+        self.xrun_rx.as_ref().map(|rx| rx.try_iter().collect()).unwrap_or_default()
+    }
 }
 
 impl Drop for AudioCapture {
@@ -0,0 +1,168 @@
+//! Click train and onset detection for latency measurement
+//!
+//! `latency-bench` plays a [`ClickTrain`] into the chain and runs an
+//! [`OnsetDetector`] on what comes back; each click's latency is the time
+//! between the frame it starts on and the frame the detector fires on.
+//! [`LatencyReport`] summarises the results.
+
+use serde::{Deserialize, Serialize};
+
+use crate::tracks::stats::Histogram;
+
+/// Length of one click
+pub const CLICK_MS: u32 = 2;
+
+/// Click amplitude
+const CLICK_AMPLITUDE: f32 = 0.9;
+
+/// Silence with a short full-scale square burst every interval
+#[derive(Debug)]
+pub struct ClickTrain {
+    channels: usize,
+    /// Frames from one click to the next
+    interval: usize,
+    /// Frames per click
+    length: usize,
+    /// Frames into the current interval
+    position: usize,
+}
+
+impl ClickTrain {
+    pub fn new(sample_rate: u32, channels: u16, interval_ms: u32) -> Self {
+        let frames = |ms: u32| (sample_rate as u64 * ms as u64 / 1000).max(1) as usize;
+        Self {
+            channels: channels.max(1) as usize,
+            interval: frames(interval_ms).max(frames(CLICK_MS) * 2),
+            length: frames(CLICK_MS),
+            position: 0,
+        }
+    }
+
+    /// Fill interleaved `samples`; returns the frame a click starts on, if any
+    pub fn fill(&mut self, samples: &mut [f32]) -> Option<usize> {
+        let mut onset = None;
+        for (index, frame) in samples.chunks_mut(self.channels).enumerate() {
+            if self.position == 0 && onset.is_none() {
+                onset = Some(index);
+            }
+            let value = if self.position < self.length {
+                // Alternate sign so the burst carries no DC
+                if self.position.is_multiple_of(2) { CLICK_AMPLITUDE } else { -CLICK_AMPLITUDE }
+            } else {
+                0.0
+            };
+            frame.fill(value);
+            self.position = (self.position + 1) % self.interval;
+        }
+        onset
+    }
+}
+
+/// Finds the first sample above a threshold, then ignores the rest of the click
+#[derive(Debug)]
+pub struct OnsetDetector {
+    threshold: f32,
+    /// Frames ignored after an onset
+    holdoff: usize,
+    remaining: usize,
+}
+
+impl OnsetDetector {
+    /// `holdoff_ms` should be under the click interval but cover any echo of the click
+    pub fn new(sample_rate: u32, threshold: f32, holdoff_ms: u32) -> Self {
+        Self {
+            threshold,
+            holdoff: (sample_rate as u64 * holdoff_ms as u64 / 1000) as usize,
+            remaining: 0,
+        }
+    }
+
+    /// Scan interleaved `samples`; returns the frame an onset was found on, if any
+    pub fn process(&mut self, samples: &[f32], channels: u16) -> Option<usize> {
+        let mut onset = None;
+        for (index, frame) in samples.chunks(channels.max(1) as usize).enumerate() {
+            if self.remaining > 0 {
+                self.remaining -= 1;
+                continue;
+            }
+            if onset.is_none() && frame.iter().any(|s| s.abs() >= self.threshold) {
+                onset = Some(index);
+                self.remaining = self.holdoff;
+            }
+        }
+        onset
+    }
+}
+
+/// Latency distribution over one benchmark run, in microseconds
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LatencyReport {
+    /// Clicks played
+    pub sent: u64,
+    /// Clicks detected coming back
+    pub received: u64,
+    pub lost: u64,
+    pub min_us: u64,
+    pub mean_us: u64,
+    pub p50_us: u64,
+    pub p95_us: u64,
+    pub p99_us: u64,
+    pub max_us: u64,
+}
+
+impl LatencyReport {
+    pub fn new(sent: u64, latencies_us: &[u64]) -> Self {
+        let mut histogram = Histogram::new();
+        for &latency in latencies_us {
+            histogram.record(latency);
+        }
+        let received = latencies_us.len() as u64;
+        let percentiles = histogram.percentiles().unwrap_or_default();
+        Self {
+            sent,
+            received,
+            lost: sent.saturating_sub(received),
+            min_us: latencies_us.iter().copied().min().unwrap_or(0),
+            mean_us: latencies_us.iter().sum::<u64>().checked_div(received).unwrap_or(0),
+            p50_us: percentiles.p50,
+            p95_us: percentiles.p95,
+            p99_us: percentiles.p99,
+            max_us: percentiles.max,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_click_round_trip() {
+        // 10 ms clicks at 1 kHz, stereo
+        let mut train = ClickTrain::new(1000, 2, 10);
+        let mut detector = OnsetDetector::new(1000, 0.5, 5);
+        let mut buffer = vec![0.0; 2 * 8];
+        let mut played = Vec::new();
+        let mut heard = Vec::new();
+        // The "chain" delays the audio by 3 frames
+        let mut chain = vec![0.0; 2 * 3];
+        for block in 0..5 {
+            if let Some(frame) = train.fill(&mut buffer) {
+                played.push(block * 8 + frame);
+            }
+            chain.extend_from_slice(&buffer);
+            let delayed: Vec<f32> = chain.drain(..buffer.len()).collect();
+            if let Some(frame) = detector.process(&delayed, 2) {
+                heard.push(block * 8 + frame);
+            }
+        }
+        assert_eq!(played, vec![0, 10, 20, 30]);
+        // The 2-frame burst fires once per click
+        assert_eq!(heard, vec![3, 13, 23, 33]);
+
+        let report = LatencyReport::new(4, &[2000, 4000, 3000]);
+        assert_eq!((report.received, report.lost), (3, 1));
+        assert_eq!((report.min_us, report.mean_us, report.max_us), (2000, 3000, 4000));
+        assert_eq!(LatencyReport::new(2, &[]).lost, 2);
+    }
+}
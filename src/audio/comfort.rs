@@ -0,0 +1,88 @@
+//! Comfort noise for DTX gaps
+//!
+//! While the sender is in DTX nothing arrives, and dropping to digital
+//! silence makes the background noise of a mic cut in and out. The
+//! generator fills the gap with soft noise at the level of the last
+//! frame received before the gap.
+
+/// Loudest comfort noise ever generated (-50 dBFS)
+const MAX_COMFORT_LEVEL: f32 = 0.003_162;
+
+/// One-pole lowpass coefficient, tilts white noise towards room tone
+const SMOOTHING: f32 = 0.5;
+
+/// Comfort noise generator
+pub struct ComfortNoise {
+    /// Target RMS level
+    level: f32,
+    /// Xorshift state
+    seed: u32,
+    /// Lowpass state per channel
+    state: Vec<f32>,
+}
+
+impl ComfortNoise {
+    /// Create a generator for interleaved output
+    pub fn new(channels: u16) -> Self {
+        Self {
+            level: 0.0,
+            seed: 0x9E37_79B9,
+            state: vec![0.0; channels.max(1) as usize],
+        }
+    }
+
+    /// Match the noise level to decoded background audio
+    pub fn set_level_from(&mut self, samples: &[f32]) {
+        if samples.is_empty() {
+            return;
+        }
+        let rms = (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt();
+        self.level = rms.min(MAX_COMFORT_LEVEL);
+    }
+
+    /// Current target RMS level
+    pub fn level(&self) -> f32 {
+        self.level
+    }
+
+    /// Generate `len` interleaved samples of comfort noise
+    pub fn generate(&mut self, len: usize) -> Vec<f32> {
+        let channels = self.state.len();
+        // Uniform noise has RMS 1/√3 and the lowpass keeps (1-a)/(1+a) of
+        // its power; scale back to the target level
+        let scale = self.level * (3.0 * (1.0 + SMOOTHING) / (1.0 - SMOOTHING)).sqrt();
+
+        (0..len)
+            .map(|i| {
+                self.seed ^= self.seed << 13;
+                self.seed ^= self.seed >> 17;
+                self.seed ^= self.seed << 5;
+                let white = self.seed as f32 / u32::MAX as f32 * 2.0 - 1.0;
+
+                let state = &mut self.state[i % channels];
+                *state = SMOOTHING * *state + (1.0 - SMOOTHING) * white;
+                *state * scale
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_comfort_noise_level() {
+        let mut noise = ComfortNoise::new(2);
+        assert!(noise.generate(960).iter().all(|&s| s == 0.0));
+
+        noise.set_level_from(&[0.001, -0.001, 0.001, -0.001]);
+        let samples = noise.generate(48_000);
+        let rms = (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt();
+        assert!((rms - 0.001).abs() < 0.0002, "rms {}", rms);
+
+        // Loud audio before the gap is not reproduced as loud noise
+        noise.set_level_from(&[0.5; 16]);
+        assert_eq!(noise.level(), MAX_COMFORT_LEVEL);
+    }
+}
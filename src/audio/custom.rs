@@ -0,0 +1,403 @@
+//! Custom audio sources and sinks
+//!
+//! [`AudioSource`] and [`AudioSink`] let a track take its audio from
+//! something other than a sound card (a synthetic generator, a DAW bridge)
+//! or play it out somewhere else (a file writer). [`SourceDriver`] and
+//! [`SinkDriver`] run them on their own thread against the same ring and
+//! jitter buffers device capture and playback use, paced in real time
+//! unless the source or sink blocks on its own clock.
+
+use crossbeam_channel::{bounded, Receiver};
+use std::f32::consts::TAU;
+use std::fs::File;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::audio::buffer::{AudioFrame, PlaybackSource, SharedRingBuffer};
+use crate::audio::media_clock::MediaClock;
+use crate::constants::{DEFAULT_FRAME_SIZE_MS, DEFAULT_SAMPLE_RATE};
+use crate::error::AudioError;
+
+/// Audio input in place of a capture device
+pub trait AudioSource: Send {
+    fn sample_rate(&self) -> u32;
+    fn channels(&self) -> u16;
+
+    /// Fill `samples` with interleaved audio; returns how many were written
+    ///
+    /// An error stops the source.
+    fn read(&mut self, samples: &mut [f32]) -> Result<usize, AudioError>;
+
+    /// True if `read` blocks until audio is due (e.g. a DAW bridge);
+    /// otherwise the driver calls it in real time
+    fn is_clocked(&self) -> bool {
+        false
+    }
+}
+
+/// Audio output in place of a playback device
+pub trait AudioSink: Send {
+    /// Take one frame of released audio
+    fn write(&mut self, frame: &AudioFrame) -> Result<(), AudioError>;
+
+    /// True if `write` blocks until the sink is ready for more
+    fn is_clocked(&self) -> bool {
+        false
+    }
+
+    /// Called once when the driver stops (e.g. to finish a file)
+    fn finish(&mut self) -> Result<(), AudioError> {
+        Ok(())
+    }
+}
+
+/// Sleep until `deadline` unless it already passed
+fn sleep_until(deadline: Instant) {
+    let now = Instant::now();
+    if deadline > now {
+        thread::sleep(deadline - now);
+    }
+}
+
+/// Runs an [`AudioSource`] into a ring buffer
+pub struct SourceDriver {
+    track_id: u8,
+    source: Option<Box<dyn AudioSource>>,
+    buffer: SharedRingBuffer,
+    running: Arc<AtomicBool>,
+    start_time: Instant,
+    thread_handle: Option<JoinHandle<Box<dyn AudioSource>>>,
+    error_rx: Option<Receiver<AudioError>>,
+}
+
+impl SourceDriver {
+    pub fn new(track_id: u8, source: Box<dyn AudioSource>, buffer: SharedRingBuffer) -> Self {
+        Self {
+            track_id,
+            source: Some(source),
+            buffer,
+            running: Arc::new(AtomicBool::new(false)),
+            start_time: Instant::now(),
+            thread_handle: None,
+            error_rx: None,
+        }
+    }
+
+    /// Start pulling from the source
+    pub fn start(&mut self) -> Result<(), AudioError> {
+        let Some(mut source) = self.source.take() else {
+            return Ok(());
+        };
+        let (error_tx, error_rx) = bounded::<AudioError>(1);
+        self.error_rx = Some(error_rx);
+        self.start_time = Instant::now();
+        self.running.store(true, Ordering::SeqCst);
+
+        let running = self.running.clone();
+        let buffer = self.buffer.clone();
+        let start_time = self.start_time;
+        let handle = thread::Builder::new()
+            .name(format!("source-track-{}", self.track_id))
+            .spawn(move || {
+                let channels = source.channels().max(1);
+                let period_frames = (source.sample_rate() as f32 * DEFAULT_FRAME_SIZE_MS / 1000.0) as usize;
+                let period = Duration::from_secs_f32(DEFAULT_FRAME_SIZE_MS / 1000.0);
+                let mut samples = vec![0.0f32; period_frames * channels as usize];
+                let mut sequence: u32 = 0;
+                let mut deadline = Instant::now();
+                let mut clock = MediaClock::new(source.sample_rate());
+
+                while running.load(Ordering::Relaxed) {
+                    let written = match source.read(&mut samples) {
+                        Ok(written) => written.min(samples.len()),
+                        Err(e) => {
+                            let _ = error_tx.try_send(e);
+                            break;
+                        }
+                    };
+                    if written > 0 {
+                        let timestamp = clock.stamp(written / channels as usize, start_time.elapsed());
+                        buffer.push(AudioFrame::new(samples[..written].to_vec(), channels, timestamp, sequence));
+                        sequence = sequence.wrapping_add(1);
+                    }
+                    if !source.is_clocked() {
+                        deadline += period;
+                        sleep_until(deadline);
+                    }
+                }
+                running.store(false, Ordering::SeqCst);
+                source
+            })
+            .map_err(|e| AudioError::StreamError(e.to_string()))?;
+
+        self.thread_handle = Some(handle);
+        Ok(())
+    }
+
+    /// Stop the thread; the source can be started again
+    pub fn stop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+        if let Some(handle) = self.thread_handle.take() {
+            if let Ok(source) = handle.join() {
+                self.source = Some(source);
+            }
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::SeqCst)
+    }
+
+    /// When the source started; frame timestamps count from here
+    pub fn started_at(&self) -> Instant {
+        self.start_time
+    }
+
+    /// Error that stopped the source, if any
+    pub fn check_errors(&self) -> Option<AudioError> {
+        self.error_rx.as_ref().and_then(|rx| rx.try_recv().ok())
+    }
+}
+
+impl Drop for SourceDriver {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Runs an [`AudioSink`] from a ring or jitter buffer
+pub struct SinkDriver {
+    track_id: u8,
+    sink: Option<Box<dyn AudioSink>>,
+    input: PlaybackSource,
+    running: Arc<AtomicBool>,
+    thread_handle: Option<JoinHandle<Box<dyn AudioSink>>>,
+    error_rx: Option<Receiver<AudioError>>,
+}
+
+impl SinkDriver {
+    pub fn new(track_id: u8, sink: Box<dyn AudioSink>, input: impl Into<PlaybackSource>) -> Self {
+        Self {
+            track_id,
+            sink: Some(sink),
+            input: input.into(),
+            running: Arc::new(AtomicBool::new(false)),
+            thread_handle: None,
+            error_rx: None,
+        }
+    }
+
+    /// Start feeding the sink
+    ///
+    /// An unclocked sink takes frames at the rate their length implies;
+    /// when none is buffered it waits rather than writing silence.
+    pub fn start(&mut self) -> Result<(), AudioError> {
+        let Some(mut sink) = self.sink.take() else {
+            return Ok(());
+        };
+        let (error_tx, error_rx) = bounded::<AudioError>(1);
+        self.error_rx = Some(error_rx);
+        self.running.store(true, Ordering::SeqCst);
+
+        let running = self.running.clone();
+        let input = self.input.clone();
+        let handle = thread::Builder::new()
+            .name(format!("sink-track-{}", self.track_id))
+            .spawn(move || {
+                let idle = Duration::from_millis(1);
+                let mut deadline = Instant::now();
+
+                while running.load(Ordering::Relaxed) {
+                    let Some(frame) = input.next_frame() else {
+                        thread::sleep(idle);
+                        deadline = deadline.max(Instant::now());
+                        continue;
+                    };
+                    if let Err(e) = sink.write(&frame) {
+                        let _ = error_tx.try_send(e);
+                        break;
+                    }
+                    if !sink.is_clocked() {
+                        let frames = frame.samples.len() / frame.channels.max(1) as usize;
+                        deadline += Duration::from_secs_f64(frames as f64 / DEFAULT_SAMPLE_RATE as f64);
+                        sleep_until(deadline);
+                    }
+                }
+                if let Err(e) = sink.finish() {
+                    let _ = error_tx.try_send(e);
+                }
+                running.store(false, Ordering::SeqCst);
+                sink
+            })
+            .map_err(|e| AudioError::StreamError(e.to_string()))?;
+
+        self.thread_handle = Some(handle);
+        Ok(())
+    }
+
+    /// Stop the thread and finish the sink; it can be started again
+    pub fn stop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+        if let Some(handle) = self.thread_handle.take() {
+            if let Ok(sink) = handle.join() {
+                self.sink = Some(sink);
+            }
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::SeqCst)
+    }
+
+    /// Error that stopped the sink, if any
+    pub fn check_errors(&self) -> Option<AudioError> {
+        self.error_rx.as_ref().and_then(|rx| rx.try_recv().ok())
+    }
+}
+
+impl Drop for SinkDriver {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Sine test tone
+pub struct ToneSource {
+    frequency: f32,
+    amplitude: f32,
+    sample_rate: u32,
+    channels: u16,
+    phase: f32,
+}
+
+impl ToneSource {
+    pub fn new(frequency: f32, amplitude: f32, channels: u16) -> Self {
+        Self { frequency, amplitude, sample_rate: DEFAULT_SAMPLE_RATE, channels, phase: 0.0 }
+    }
+}
+
+impl AudioSource for ToneSource {
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn read(&mut self, samples: &mut [f32]) -> Result<usize, AudioError> {
+        let step = TAU * self.frequency / self.sample_rate as f32;
+        for frame in samples.chunks_mut(self.channels.max(1) as usize) {
+            frame.fill(self.amplitude * self.phase.sin());
+            self.phase = (self.phase + step) % TAU;
+        }
+        Ok(samples.len())
+    }
+}
+
+/// Writes 32-bit float WAV
+pub struct WavSink {
+    writer: BufWriter<File>,
+    channels: Option<u16>,
+    data_bytes: u32,
+}
+
+impl WavSink {
+    /// Create (or truncate) a WAV file
+    pub fn create(path: impl AsRef<Path>) -> Result<Self, AudioError> {
+        let file = File::create(path).map_err(|e| AudioError::StreamError(e.to_string()))?;
+        Ok(Self { writer: BufWriter::new(file), channels: None, data_bytes: 0 })
+    }
+
+    fn write_header(&mut self, channels: u16) -> std::io::Result<()> {
+        let block_align = channels as u32 * 4;
+        let w = &mut self.writer;
+        w.write_all(b"RIFF")?;
+        w.write_all(&(36 + self.data_bytes).to_le_bytes())?;
+        w.write_all(b"WAVEfmt ")?;
+        w.write_all(&16u32.to_le_bytes())?;
+        w.write_all(&3u16.to_le_bytes())?; // IEEE float
+        w.write_all(&channels.to_le_bytes())?;
+        w.write_all(&DEFAULT_SAMPLE_RATE.to_le_bytes())?;
+        w.write_all(&(DEFAULT_SAMPLE_RATE * block_align).to_le_bytes())?;
+        w.write_all(&(block_align as u16).to_le_bytes())?;
+        w.write_all(&32u16.to_le_bytes())?;
+        w.write_all(b"data")?;
+        w.write_all(&self.data_bytes.to_le_bytes())
+    }
+}
+
+impl AudioSink for WavSink {
+    fn write(&mut self, frame: &AudioFrame) -> Result<(), AudioError> {
+        let result = (|| {
+            if self.channels.is_none() {
+                self.channels = Some(frame.channels);
+                self.write_header(frame.channels)?;
+            }
+            for sample in &frame.samples {
+                self.writer.write_all(&sample.to_le_bytes())?;
+            }
+            self.data_bytes += frame.samples.len() as u32 * 4;
+            Ok::<_, std::io::Error>(())
+        })();
+        result.map_err(|e| AudioError::StreamError(e.to_string()))
+    }
+
+    /// Fill in the sizes now that the length is known
+    fn finish(&mut self) -> Result<(), AudioError> {
+        let Some(channels) = self.channels else {
+            return Ok(());
+        };
+        let result = (|| {
+            self.writer.seek(SeekFrom::Start(0))?;
+            self.write_header(channels)?;
+            self.writer.seek(SeekFrom::End(0))?;
+            self.writer.flush()
+        })();
+        result.map_err(|e| AudioError::StreamError(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::buffer::create_shared_buffer;
+    use std::sync::Mutex;
+
+    /// Collects written frames
+    struct Collect(Arc<Mutex<Vec<AudioFrame>>>);
+
+    impl AudioSink for Collect {
+        fn write(&mut self, frame: &AudioFrame) -> Result<(), AudioError> {
+            self.0.lock().unwrap().push(frame.clone());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_tone_through_drivers() {
+        let captured = create_shared_buffer(64);
+        let mut source = SourceDriver::new(1, Box::new(ToneSource::new(1000.0, 0.5, 2)), captured.clone());
+        source.start().unwrap();
+        thread::sleep(Duration::from_millis(50));
+        source.stop();
+        assert!(source.check_errors().is_none());
+
+        let first = captured.try_pop().expect("source produced frames");
+        assert_eq!(first.channels, 2);
+        assert_eq!(first.samples.len(), 480 * 2);
+        assert!(first.samples.iter().all(|s| s.abs() <= 0.5));
+        assert!(first.samples.iter().any(|s| s.abs() > 0.4));
+
+        let frames = Arc::new(Mutex::new(Vec::new()));
+        let mut sink = SinkDriver::new(1, Box::new(Collect(frames.clone())), captured.clone());
+        sink.start().unwrap();
+        thread::sleep(Duration::from_millis(30));
+        sink.stop();
+        assert!(!frames.lock().unwrap().is_empty());
+    }
+}
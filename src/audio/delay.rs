@@ -0,0 +1,99 @@
+//! Static delay line for playout offset
+//!
+//! Delays an interleaved sample stream by a fixed number of sample frames,
+//! e.g. to line desktop audio up with a webcam that lags behind.
+
+use std::collections::VecDeque;
+
+/// Fixed delay applied to interleaved samples
+pub struct DelayLine {
+    /// Samples waiting to be released
+    buffer: VecDeque<f32>,
+    /// Delay in sample frames (per channel)
+    delay_frames: usize,
+    channels: usize,
+    sample_rate: u32,
+}
+
+impl DelayLine {
+    /// Create a delay line with no delay
+    pub fn new(sample_rate: u32, channels: u16) -> Self {
+        Self {
+            buffer: VecDeque::new(),
+            delay_frames: 0,
+            channels: channels.max(1) as usize,
+            sample_rate,
+        }
+    }
+
+    /// Set the delay in milliseconds
+    ///
+    /// Growing the delay inserts silence; shrinking it discards the
+    /// oldest delayed samples.
+    pub fn set_delay_ms(&mut self, delay_ms: u32) {
+        let frames = (self.sample_rate as u64 * delay_ms as u64 / 1000) as usize;
+        if frames == self.delay_frames {
+            return;
+        }
+
+        let target = frames * self.channels;
+        if self.buffer.len() < target {
+            let missing = target - self.buffer.len();
+            for _ in 0..missing {
+                self.buffer.push_front(0.0);
+            }
+        } else {
+            let excess = self.buffer.len() - target;
+            self.buffer.drain(..excess);
+        }
+
+        self.delay_frames = frames;
+    }
+
+    /// Get the delay in milliseconds
+    pub fn delay_ms(&self) -> u32 {
+        (self.delay_frames as u64 * 1000 / self.sample_rate as u64) as u32
+    }
+
+    /// Delay samples in place
+    pub fn process(&mut self, samples: &mut [f32]) {
+        if self.delay_frames == 0 {
+            return;
+        }
+
+        for sample in samples.iter_mut() {
+            self.buffer.push_back(*sample);
+            *sample = self.buffer.pop_front().unwrap_or(0.0);
+        }
+    }
+
+    /// Drop all delayed samples (delay stays configured)
+    pub fn clear(&mut self) {
+        let len = self.buffer.len();
+        self.buffer.clear();
+        self.buffer.resize(len, 0.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delay_line() {
+        // 1 ms at 4 kHz mono = 4 samples
+        let mut delay = DelayLine::new(4000, 1);
+        delay.set_delay_ms(1);
+        assert_eq!(delay.delay_ms(), 1);
+
+        let mut samples = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0];
+        delay.process(&mut samples);
+        assert_eq!(samples, [0.0, 0.0, 0.0, 0.0, 1.0, 2.0]);
+
+        // Removing the delay passes audio straight through
+        delay.set_delay_ms(0);
+        let mut samples = [7.0, 8.0];
+        delay.process(&mut samples);
+        assert_eq!(samples, [7.0, 8.0]);
+    }
+}
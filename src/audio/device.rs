@@ -1,6 +1,9 @@
 //! Audio device enumeration and management
 
 use cpal::traits::{DeviceTrait, HostTrait};
+use std::collections::HashMap;
+
+use crate::audio::device_id::{self, DeviceMatch};
 use crate::error::AudioError;
 use crate::protocol::AudioDeviceInfo;
 
@@ -77,15 +80,19 @@ pub fn list_devices() -> Vec<AudioDeviceInfo> {
     
     // Input devices
     if let Ok(input_devices) = host.input_devices() {
+        let endpoints = endpoint_ids(true);
         for device in input_devices {
             if let Ok(name) = device.name() {
                 let id = format!("input:{}", name);
+                let stable_id = stable_id("input", &name, &endpoints);
                 let is_default = default_input_name.as_ref() == Some(&name);
                 
                 let (sample_rates, channels) = get_device_capabilities(&device, true);
                 
                 devices.push(AudioDeviceInfo {
+                    aliases: device_id::aliases_for(&[&id, &stable_id]),
                     id,
+                    stable_id,
                     name: name.clone(),
                     is_input: true,
                     is_output: false,
@@ -99,9 +106,11 @@ pub fn list_devices() -> Vec<AudioDeviceInfo> {
     
     // Output devices
     if let Ok(output_devices) = host.output_devices() {
+        let endpoints = endpoint_ids(false);
         for device in output_devices {
             if let Ok(name) = device.name() {
                 let id = format!("output:{}", name);
+                let stable_id = stable_id("output", &name, &endpoints);
                 let is_default = default_output_name.as_ref() == Some(&name);
                 
                 let (sample_rates, channels) = get_device_capabilities(&device, false);
@@ -114,7 +123,9 @@ pub fn list_devices() -> Vec<AudioDeviceInfo> {
                     }
                 } else {
                     devices.push(AudioDeviceInfo {
+                        aliases: device_id::aliases_for(&[&id, &stable_id]),
                         id,
+                        stable_id,
                         name,
                         is_input: false,
                         is_output: true,
@@ -130,6 +141,59 @@ pub fn list_devices() -> Vec<AudioDeviceInfo> {
     devices
 }
 
+/// Stable ID of a device: its endpoint ID if known, else its name without instance numbering
+fn stable_id(kind: &str, name: &str, endpoints: &HashMap<String, String>) -> String {
+    match endpoints.get(name) {
+        Some(endpoint) => format!("{}:{}", kind, endpoint),
+        None => device_id::fallback_stable_id(kind, name),
+    }
+}
+
+/// Endpoint ID of each active capture or render device, by friendly name
+#[cfg(target_os = "windows")]
+fn endpoint_ids(is_input: bool) -> HashMap<String, String> {
+    use windows::Win32::Devices::FunctionDiscovery::PKEY_Device_FriendlyName;
+    use windows::Win32::Media::Audio::{eCapture, eRender, IMMDeviceEnumerator, MMDeviceEnumerator, DEVICE_STATE_ACTIVE};
+    use windows::Win32::System::Com::{CoCreateInstance, CoInitializeEx, CoTaskMemFree, CLSCTX_ALL, COINIT_MULTITHREADED, STGM_READ};
+    use windows::Win32::UI::Shell::PropertiesSystem::PropVariantToStringAlloc;
+    
+    let mut ids = HashMap::new();
+    // SAFETY: COM calls on interfaces we own; strings are freed once copied
+    unsafe {
+        // Fails harmlessly when COM is already set up on this thread
+        let _ = CoInitializeEx(None, COINIT_MULTITHREADED);
+        let Ok(enumerator) = CoCreateInstance::<_, IMMDeviceEnumerator>(&MMDeviceEnumerator, None, CLSCTX_ALL) else {
+            return ids;
+        };
+        let flow = if is_input { eCapture } else { eRender };
+        let Ok(collection) = enumerator.EnumAudioEndpoints(flow, DEVICE_STATE_ACTIVE) else {
+            return ids;
+        };
+        for index in 0..collection.GetCount().unwrap_or(0) {
+            let Ok(device) = collection.Item(index) else {
+                continue;
+            };
+            let (Ok(id), Ok(store)) = (device.GetId(), device.OpenPropertyStore(STGM_READ)) else {
+                continue;
+            };
+            if let Ok(name) = store.GetValue(&PKEY_Device_FriendlyName).and_then(|value| PropVariantToStringAlloc(&value)) {
+                if let (Ok(id), Ok(name)) = (id.to_string(), name.to_string()) {
+                    ids.insert(name, id);
+                }
+                CoTaskMemFree(Some(name.0 as _));
+            }
+            CoTaskMemFree(Some(id.0 as _));
+        }
+    }
+    ids
+}
+
+/// Endpoint IDs are a Windows notion; elsewhere names are stable enough
+#[cfg(not(target_os = "windows"))]
+fn endpoint_ids(_is_input: bool) -> HashMap<String, String> {
+    HashMap::new()
+}
+
 /// Get device capabilities
 fn get_device_capabilities(device: &cpal::Device, is_input: bool) -> (Vec<u32>, Vec<u16>) {
     let mut sample_rates = Vec::new();
@@ -142,10 +206,11 @@ fn get_device_capabilities(device: &cpal::Device, is_input: bool) -> (Vec<u32>,
             // Common sample rates
             for rate_val in [44100u32, 48000, 88200, 96000, 176400, 192000] {
                 let rate = cpal::SampleRate(rate_val);
-                if rate >= config.min_sample_rate() && rate <= config.max_sample_rate() {
-                    if !rates.contains(&rate_val) {
-                        rates.push(rate_val);
-                    }
+                if rate >= config.min_sample_rate()
+                    && rate <= config.max_sample_rate()
+                    && !rates.contains(&rate_val)
+                {
+                    rates.push(rate_val);
                 }
             }
             
@@ -178,40 +243,57 @@ fn get_device_capabilities(device: &cpal::Device, is_input: bool) -> (Vec<u32>,
 }
 
 /// Get a device by its ID
+///
+/// The ID may be an alias, an `input:`/`output:` name or a stable ID; a
+/// device renamed by Windows is still found by its old name.
 pub fn get_device_by_id(id: &str) -> Result<AudioDevice, AudioError> {
     let host = cpal::default_host();
+    let resolved = device_id::resolve_alias(id);
     
     // Parse device type from ID
-    let (device_type, name) = if let Some(name) = id.strip_prefix("input:") {
-        ("input", name)
-    } else if let Some(name) = id.strip_prefix("output:") {
-        ("output", name)
+    let device_type = if resolved.starts_with("output:") {
+        "output"
     } else {
         // Assume input for backward compatibility
-        ("input", id)
+        "input"
     };
     
     let devices = match device_type {
         "input" => host.input_devices(),
-        "output" => host.output_devices(),
-        _ => return Err(AudioError::DeviceNotFound(id.to_string())),
+        _ => host.output_devices(),
     };
     
-    let devices = devices.map_err(|e| AudioError::DeviceNotFound(e.to_string()))?;
+    let devices: Vec<(cpal::Device, String)> = devices
+        .map_err(|e| AudioError::DeviceNotFound(e.to_string()))?
+        .filter_map(|device| device.name().ok().map(|name| (device, name)))
+        .collect();
+    let endpoints = endpoint_ids(device_type == "input");
+    let candidates: Vec<(String, String)> = devices
+        .iter()
+        .map(|(_, name)| (name.clone(), stable_id(device_type, name, &endpoints)))
+        .collect();
     
-    for device in devices {
-        if let Ok(device_name) = device.name() {
-            if device_name == name {
-                return Ok(AudioDevice::from_cpal(
-                    device,
-                    device_type == "input",
-                    device_type == "output",
-                ));
-            }
-        }
+    let (index, matched) = device_id::find_device(&resolved, &candidates)
+        .ok_or_else(|| AudioError::DeviceNotFound(id.to_string()))?;
+    if matched == DeviceMatch::Renamed {
+        tracing::warn!("Device {} not found; using {} with the same name", id, candidates[index].0);
+    }
+    let (device, _) = devices.into_iter().nth(index).ok_or_else(|| AudioError::DeviceNotFound(id.to_string()))?;
+    Ok(AudioDevice::from_cpal(
+        device,
+        device_type == "input",
+        device_type == "output",
+    ))
+}
+
+/// ID of the default input or output device
+pub fn default_device_id(is_input: bool) -> Option<String> {
+    let host = cpal::default_host();
+    if is_input {
+        host.default_input_device().and_then(|d| d.name().ok()).map(|name| format!("input:{}", name))
+    } else {
+        host.default_output_device().and_then(|d| d.name().ok()).map(|name| format!("output:{}", name))
     }
-    
-    Err(AudioError::DeviceNotFound(id.to_string()))
 }
 
 /// Get default input device
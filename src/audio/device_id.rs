@@ -0,0 +1,129 @@
+//! Stable device identification
+//!
+//! A `device_id` is normally `input:<name>` or `output:<name>`, but Windows
+//! renames a device when the port changes or a second one with the same name
+//! appears ("Microphone (2- USB Audio)"). Devices therefore also carry a
+//! stable ID: the endpoint ID on Windows (`input:{0.0.1.00000000}.{guid}`),
+//! elsewhere the name without instance numbering. A `device_id` may also be
+//! an alias from `[device_aliases]` in the config.
+//!
+//! A configured ID is resolved through the aliases, then matched against
+//! device names, then stable IDs, and finally names with the instance
+//! numbering removed on both sides.
+
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+
+/// Alias -> device ID, from the config
+static ALIASES: RwLock<BTreeMap<String, String>> = RwLock::new(BTreeMap::new());
+
+/// How a configured ID matched a device
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceMatch {
+    Name,
+    StableId,
+    /// Same name apart from instance numbering: the device was renamed
+    Renamed,
+}
+
+/// Replace the device aliases
+pub fn set_aliases(aliases: &BTreeMap<String, String>) {
+    *ALIASES.write().unwrap_or_else(|e| e.into_inner()) = aliases.clone();
+}
+
+/// The device ID an alias stands for; other IDs are returned unchanged
+pub fn resolve_alias(id: &str) -> String {
+    let aliases = ALIASES.read().unwrap_or_else(|e| e.into_inner());
+    aliases.get(id).cloned().unwrap_or_else(|| id.to_string())
+}
+
+/// Aliases pointing at any of `ids`
+pub fn aliases_for(ids: &[&str]) -> Vec<String> {
+    let aliases = ALIASES.read().unwrap_or_else(|e| e.into_inner());
+    aliases
+        .iter()
+        .filter(|(_, target)| ids.contains(&target.as_str()))
+        .map(|(alias, _)| alias.clone())
+        .collect()
+}
+
+/// A device name without Windows instance numbering
+///
+/// `Microphone (2- USB Audio)` and `Speakers (2)` become
+/// `Microphone (USB Audio)` and `Speakers`.
+pub fn normalize_name(name: &str) -> String {
+    let mut out = String::with_capacity(name.len());
+    let mut rest = name;
+    while let Some(open) = rest.find('(') {
+        out.push_str(&rest[..=open]);
+        rest = &rest[open + 1..];
+        let digits = rest.bytes().take_while(u8::is_ascii_digit).count();
+        if digits > 0 && rest[digits..].starts_with("- ") {
+            rest = &rest[digits + 2..];
+        }
+    }
+    out.push_str(rest);
+
+    let suffix = out
+        .strip_suffix(')')
+        .and_then(|s| s.rfind(" (").map(|open| (open, &s[open + 2..])))
+        .filter(|(_, number)| !number.is_empty() && number.bytes().all(|b| b.is_ascii_digit()));
+    if let Some((open, _)) = suffix {
+        out.truncate(open);
+    }
+    out
+}
+
+/// Stable ID of a device without an endpoint ID
+pub fn fallback_stable_id(kind: &str, name: &str) -> String {
+    format!("{}:{}", kind, normalize_name(name))
+}
+
+/// Pick the device `id` refers to from `(name, stable_id)` pairs
+///
+/// `id` has its alias resolved already.
+pub fn find_device(id: &str, devices: &[(String, String)]) -> Option<(usize, DeviceMatch)> {
+    let name = id.strip_prefix("input:").or_else(|| id.strip_prefix("output:")).unwrap_or(id);
+    if let Some(index) = devices.iter().position(|(n, _)| n == name) {
+        return Some((index, DeviceMatch::Name));
+    }
+    if let Some(index) = devices.iter().position(|(_, stable)| stable == id) {
+        return Some((index, DeviceMatch::StableId));
+    }
+    let normalized = normalize_name(name);
+    devices
+        .iter()
+        .position(|(n, _)| normalize_name(n) == normalized)
+        .map(|index| (index, DeviceMatch::Renamed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_device_matching() {
+        assert_eq!(normalize_name("Microphone (2- USB Audio)"), "Microphone (USB Audio)");
+        assert_eq!(normalize_name("Line (12- Mixer (3- Out))"), "Line (Mixer (Out))");
+        assert_eq!(normalize_name("Speakers (2)"), "Speakers");
+        assert_eq!(normalize_name("hw:CARD=USB,DEV=0"), "hw:CARD=USB,DEV=0");
+        assert_eq!(normalize_name("Mic (Pro)"), "Mic (Pro)");
+
+        let devices = vec![
+            ("Microphone (2- USB Audio)".to_string(), "input:{0.0.1.00000000}.{a1}".to_string()),
+            ("Line In (Realtek)".to_string(), fallback_stable_id("input", "Line In (Realtek)")),
+        ];
+        assert_eq!(find_device("input:Line In (Realtek)", &devices), Some((1, DeviceMatch::Name)));
+        assert_eq!(find_device("input:{0.0.1.00000000}.{a1}", &devices), Some((0, DeviceMatch::StableId)));
+        // Saved before Windows renamed it
+        assert_eq!(find_device("input:Microphone (USB Audio)", &devices), Some((0, DeviceMatch::Renamed)));
+        assert_eq!(find_device("input:Headset", &devices), None);
+
+        let aliases = BTreeMap::from([("Host mic".to_string(), "input:{0.0.1.00000000}.{a1}".to_string())]);
+        set_aliases(&aliases);
+        assert_eq!(resolve_alias("Host mic"), "input:{0.0.1.00000000}.{a1}");
+        assert_eq!(resolve_alias("input:Headset"), "input:Headset");
+        assert_eq!(aliases_for(&["input:Microphone (2- USB Audio)", "input:{0.0.1.00000000}.{a1}"]), vec!["Host mic"]);
+        set_aliases(&BTreeMap::new());
+    }
+}
@@ -0,0 +1,124 @@
+//! Gain ramps for click-free starts and stops
+
+/// Apply a linear gain ramp across interleaved samples
+///
+/// Gain moves from `from` on the first sample frame to `to` on the last.
+pub fn apply_ramp(samples: &mut [f32], channels: u16, from: f32, to: f32) {
+    let channels = channels.max(1) as usize;
+    let frames = samples.len() / channels;
+    if frames == 0 {
+        return;
+    }
+
+    let step = if frames > 1 { (to - from) / (frames - 1) as f32 } else { 0.0 };
+    for (i, frame) in samples.chunks_mut(channels).enumerate() {
+        let gain = from + step * i as f32;
+        for sample in frame {
+            *sample *= gain;
+        }
+    }
+}
+
+/// Gain that glides towards a target, for device callbacks
+///
+/// Starts, stops, mutes and volume changes all move the gain at the same
+/// rate: full scale over the configured duration.
+#[derive(Debug, Clone)]
+pub struct Ramp {
+    gain: f32,
+    target: f32,
+    /// Gain change per sample frame
+    step: f32,
+}
+
+impl Ramp {
+    /// Create a ramp starting at `initial` gain
+    pub fn new(sample_rate: u32, duration_ms: u32, initial: f32) -> Self {
+        let frames = (sample_rate as u64 * duration_ms as u64 / 1000).max(1);
+        Self {
+            gain: initial,
+            target: initial,
+            step: 1.0 / frames as f32,
+        }
+    }
+
+    /// Set the gain to glide towards
+    pub fn set_target(&mut self, target: f32) {
+        self.target = target;
+    }
+
+    /// Jump to a gain immediately
+    pub fn jump(&mut self, gain: f32) {
+        self.gain = gain;
+    }
+
+    /// Current gain
+    pub fn gain(&self) -> f32 {
+        self.gain
+    }
+
+    /// Check if the gain has reached its target
+    pub fn is_settled(&self) -> bool {
+        self.gain == self.target
+    }
+
+    /// Apply the ramp to interleaved samples
+    pub fn apply(&mut self, samples: &mut [f32], channels: u16) {
+        if self.is_settled() && self.gain == 1.0 {
+            return;
+        }
+
+        for frame in samples.chunks_mut(channels.max(1) as usize) {
+            if self.gain < self.target {
+                self.gain = (self.gain + self.step).min(self.target);
+            } else if self.gain > self.target {
+                self.gain = (self.gain - self.step).max(self.target);
+            }
+            for sample in frame {
+                *sample *= self.gain;
+            }
+        }
+    }
+}
+
+/// Fade samples out to silence
+pub fn fade_out(samples: &mut [f32], channels: u16) {
+    apply_ramp(samples, channels, 1.0, 0.0);
+}
+
+/// Fade samples in from silence
+pub fn fade_in(samples: &mut [f32], channels: u16) {
+    apply_ramp(samples, channels, 0.0, 1.0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fade_out() {
+        let mut samples = vec![1.0; 10];
+        fade_out(&mut samples, 2);
+
+        // Both channels of a frame share the gain
+        assert_eq!(samples[0], 1.0);
+        assert_eq!(samples[1], 1.0);
+        assert_eq!(samples[8], 0.0);
+        assert_eq!(samples[9], 0.0);
+        assert!((samples[4] - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_ramp() {
+        // 1 ms at 4 kHz = 4 frames from silence to full scale
+        let mut ramp = Ramp::new(4000, 1, 0.0);
+        ramp.set_target(1.0);
+
+        let mut samples = vec![1.0; 12];
+        ramp.apply(&mut samples, 2);
+        assert_eq!(samples[0], 0.25);
+        assert_eq!(samples[7], 1.0);
+        assert_eq!(samples[11], 1.0);
+        assert!(ramp.is_settled());
+    }
+}
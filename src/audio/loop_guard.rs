@@ -0,0 +1,210 @@
+//! Feedback loop prevention for full-duplex operation
+//!
+//! When one process both plays received audio and captures local audio, a
+//! capture device that hears the playback (a monitor source, "Stereo Mix",
+//! or speakers next to the microphone) sends the remote side's audio straight
+//! back to it. [`is_monitor_device`] refuses monitor devices by name before
+//! capture starts; [`LoopGuard`] catches the rest by comparing the level
+//! envelope of captured audio with what was played shortly before.
+
+use std::collections::VecDeque;
+
+/// Envelope block length in milliseconds
+const BLOCK_MS: usize = 10;
+
+/// Blocks compared per check (half a second)
+const WINDOW_BLOCKS: usize = 50;
+
+/// Correlation drop below the threshold before the loop is considered gone
+const RELEASE_MARGIN: f32 = 0.15;
+
+/// Played audio quieter than this (linear RMS, about -60 dBFS) is never matched
+const MIN_PLAYED_RMS: f32 = 0.001;
+
+/// Whether capturing `capture_name` would record the output `output_name`
+///
+/// Matches PulseAudio/PipeWire monitor sources ("Monitor of X", "X.monitor")
+/// and Windows loopback inputs, which follow the default output.
+pub fn is_monitor_device(capture_name: &str, output_name: &str) -> bool {
+    let capture = capture_name.to_lowercase();
+    let output = output_name.to_lowercase();
+    capture == format!("monitor of {}", output)
+        || capture == format!("{}.monitor", output)
+        || ["stereo mix", "what u hear", "wave out mix"].iter().any(|name| capture.contains(name))
+}
+
+/// Running RMS of one fixed-size block
+#[derive(Default)]
+struct BlockAccumulator {
+    sum_squares: f32,
+    frames: usize,
+}
+
+impl BlockAccumulator {
+    /// Add interleaved samples; calls `complete` with each finished block's RMS
+    fn push(&mut self, samples: &[f32], channels: u16, block_frames: usize, mut complete: impl FnMut(f32)) {
+        for frame in samples.chunks(channels.max(1) as usize) {
+            let mono = frame.iter().sum::<f32>() / frame.len() as f32;
+            self.sum_squares += mono * mono;
+            self.frames += 1;
+            if self.frames == block_frames {
+                complete((self.sum_squares / block_frames as f32).sqrt());
+                *self = Self::default();
+            }
+        }
+    }
+}
+
+/// Detects captured audio that follows the played audio
+pub struct LoopGuard {
+    block_frames: usize,
+    max_lag: usize,
+    threshold: f32,
+    played: VecDeque<f32>,
+    captured: VecDeque<f32>,
+    played_block: BlockAccumulator,
+    captured_block: BlockAccumulator,
+    looped: bool,
+}
+
+impl LoopGuard {
+    /// Guard for playback-to-capture delays up to `max_delay_ms`
+    ///
+    /// `threshold` is the envelope correlation (0..1) at which capture counts
+    /// as a loop.
+    pub fn new(sample_rate: u32, threshold: f32, max_delay_ms: u32) -> Self {
+        let max_lag = max_delay_ms as usize / BLOCK_MS;
+        Self {
+            block_frames: (sample_rate as usize * BLOCK_MS / 1000).max(1),
+            max_lag,
+            threshold,
+            played: VecDeque::with_capacity(WINDOW_BLOCKS + max_lag + 1),
+            captured: VecDeque::with_capacity(WINDOW_BLOCKS + 1),
+            played_block: BlockAccumulator::default(),
+            captured_block: BlockAccumulator::default(),
+            looped: false,
+        }
+    }
+
+    /// Record audio that was handed to an output device
+    pub fn played(&mut self, samples: &[f32], channels: u16) {
+        let capacity = WINDOW_BLOCKS + self.max_lag;
+        let played = &mut self.played;
+        self.played_block.push(samples, channels, self.block_frames, |rms| {
+            if played.len() == capacity {
+                played.pop_front();
+            }
+            played.push_back(rms);
+        });
+    }
+
+    /// Record captured audio; returns whether capture is currently a loop
+    pub fn captured(&mut self, samples: &[f32], channels: u16) -> bool {
+        let mut completed = 0;
+        let captured = &mut self.captured;
+        self.captured_block.push(samples, channels, self.block_frames, |rms| {
+            if captured.len() == WINDOW_BLOCKS {
+                captured.pop_front();
+            }
+            captured.push_back(rms);
+            completed += 1;
+        });
+        if completed > 0 {
+            let correlation = self.best_correlation();
+            if self.looped {
+                self.looped = correlation >= self.threshold - RELEASE_MARGIN;
+            } else if correlation >= self.threshold {
+                self.looped = true;
+            }
+        }
+        self.looped
+    }
+
+    /// Whether the last check found a loop
+    pub fn is_looped(&self) -> bool {
+        self.looped
+    }
+
+    /// Highest envelope correlation over the lags the history allows
+    fn best_correlation(&self) -> f32 {
+        if self.captured.len() < WINDOW_BLOCKS {
+            return 0.0;
+        }
+        let captured: Vec<f32> = self.captured.iter().copied().collect();
+        let played: Vec<f32> = self.played.iter().copied().collect();
+        (0..=self.max_lag)
+            .take_while(|lag| played.len() >= WINDOW_BLOCKS + lag)
+            .map(|lag| {
+                let end = played.len() - lag;
+                let reference = &played[end - WINDOW_BLOCKS..end];
+                let mean = reference.iter().sum::<f32>() / WINDOW_BLOCKS as f32;
+                if mean < MIN_PLAYED_RMS {
+                    return 0.0;
+                }
+                correlation(reference, &captured)
+            })
+            .fold(0.0, f32::max)
+    }
+}
+
+/// Pearson correlation of two equal-length series (0 if either is flat)
+fn correlation(a: &[f32], b: &[f32]) -> f32 {
+    let n = a.len() as f32;
+    let mean_a = a.iter().sum::<f32>() / n;
+    let mean_b = b.iter().sum::<f32>() / n;
+    let (mut cov, mut var_a, mut var_b) = (0.0, 0.0, 0.0);
+    for (&x, &y) in a.iter().zip(b) {
+        cov += (x - mean_a) * (y - mean_b);
+        var_a += (x - mean_a) * (x - mean_a);
+        var_b += (y - mean_b) * (y - mean_b);
+    }
+    if var_a <= f32::EPSILON || var_b <= f32::EPSILON {
+        return 0.0;
+    }
+    cov / (var_a * var_b).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 10 ms blocks of mono audio at a pseudo-random level each
+    fn blocks(seed: u32, count: usize) -> Vec<Vec<f32>> {
+        let mut state = seed;
+        (0..count)
+            .map(|_| {
+                state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                let level = (state >> 8) as f32 / (1u32 << 24) as f32 * 0.5;
+                (0..480).map(|i| if i % 2 == 0 { level } else { -level }).collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_detects_delayed_echo_only() {
+        assert!(is_monitor_device("Monitor of Built-in Audio", "Built-in Audio"));
+        assert!(!is_monitor_device("USB Headset", "USB Headset"));
+
+        // Capture hears the playback 70 ms later at a third of the level
+        let played = blocks(1, 200);
+        let mut guard = LoopGuard::new(48_000, 0.9, 300);
+        let mut looped = false;
+        for i in 0..played.len() {
+            guard.played(&played[i], 1);
+            let echo: Vec<f32> = match i.checked_sub(7) {
+                Some(j) => played[j].iter().map(|s| s * 0.3).collect(),
+                None => vec![0.0; 480],
+            };
+            looped = guard.captured(&echo, 1);
+        }
+        assert!(looped);
+
+        // Unrelated capture is left alone
+        let other = blocks(2, 200);
+        let mut guard = LoopGuard::new(48_000, 0.9, 300);
+        for (played, captured) in played.iter().zip(&other) {
+            guard.played(played, 1);
+            assert!(!guard.captured(captured, 1));
+        }
+    }
+}
@@ -0,0 +1,174 @@
+//! Media clock for captured audio
+//!
+//! Frame timestamps are counted from the sample frames captured rather
+//! than read from the clock when the callback happens to run, so each one
+//! advances by exactly the duration of the audio before it. The clock is
+//! anchored to when the first sample was captured, and from then on only
+//! the sample count moves it, until the count and the reference clock part
+//! by more than [`RESYNC_THRESHOLD`] (a dropout lost samples, or the
+//! reference stalled): then it is anchored again and the drift fit starts
+//! over.
+//!
+//! Every block is also compared against a reference clock (the device's
+//! stream timestamps where the host has them, the system clock otherwise);
+//! the least-squares slope of the difference is the drift of the audio
+//! clock, which scheduling jitter no longer swamps.
+
+use std::time::Duration;
+
+/// Span of reference time before a drift estimate is given
+pub const DRIFT_MIN_SPAN: Duration = Duration::from_secs(10);
+
+/// Gap between sample count and reference clock that re-anchors the clock
+pub const RESYNC_THRESHOLD: Duration = Duration::from_millis(100);
+
+/// Sample-counting clock for one capture stream
+pub struct MediaClock {
+    sample_rate: u32,
+    /// Sample frames captured so far
+    frames: u64,
+    /// Timestamp of the first frame (µs since capture start)
+    origin_us: Option<u64>,
+    /// Running sums for the drift regression: x is reference time, y is
+    /// media time minus reference time, both in seconds since the origin
+    n: f64,
+    sum_x: f64,
+    sum_y: f64,
+    sum_xx: f64,
+    sum_xy: f64,
+    span: f64,
+}
+
+impl MediaClock {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate: sample_rate.max(1),
+            frames: 0,
+            origin_us: None,
+            n: 0.0,
+            sum_x: 0.0,
+            sum_y: 0.0,
+            sum_xx: 0.0,
+            sum_xy: 0.0,
+            span: 0.0,
+        }
+    }
+
+    /// Timestamp (µs since capture start) for a block of `frames` sample
+    /// frames whose first sample was captured `captured` after start on
+    /// the reference clock; advances the clock past the block
+    pub fn stamp(&mut self, frames: usize, captured: Duration) -> u64 {
+        let captured_us = captured.as_micros() as u64;
+        if let Some(origin) = self.origin_us {
+            if (origin + self.position_us()).abs_diff(captured_us) > RESYNC_THRESHOLD.as_micros() as u64 {
+                self.reset();
+            }
+        }
+        let origin = *self.origin_us.get_or_insert(captured_us);
+        let media_us = self.position_us();
+
+        let x = captured_us.saturating_sub(origin) as f64 / 1e6;
+        let y = media_us as f64 / 1e6 - x;
+        self.n += 1.0;
+        self.sum_x += x;
+        self.sum_y += y;
+        self.sum_xx += x * x;
+        self.sum_xy += x * y;
+        self.span = self.span.max(x);
+
+        self.frames += frames as u64;
+        origin + media_us
+    }
+
+    /// Time the frames captured so far cover (µs)
+    pub fn position_us(&self) -> u64 {
+        // From the total count, so rounding never accumulates
+        (self.frames as u128 * 1_000_000 / self.sample_rate as u128) as u64
+    }
+
+    /// Sample frames captured so far
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    /// Audio clock rate against the reference in parts per million
+    /// (positive: the device delivers samples faster than nominal), once
+    /// [`DRIFT_MIN_SPAN`] of reference time has been observed
+    pub fn drift_ppm(&self) -> Option<f64> {
+        if self.span < DRIFT_MIN_SPAN.as_secs_f64() {
+            return None;
+        }
+        let var = self.n * self.sum_xx - self.sum_x * self.sum_x;
+        (var > 0.0).then(|| (self.n * self.sum_xy - self.sum_x * self.sum_y) / var * 1e6)
+    }
+
+    /// Start over, for a restarted stream
+    pub fn reset(&mut self) {
+        *self = Self::new(self.sample_rate);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timestamps_follow_samples() {
+        let mut clock = MediaClock::new(48_000);
+        // Callbacks run late and unevenly; timestamps don't
+        let runs = [5_000u64, 15_400, 24_900, 35_700];
+        let stamps: Vec<u64> = runs.iter().map(|&us| clock.stamp(480, Duration::from_micros(us))).collect();
+        assert_eq!(stamps, vec![5_000, 15_000, 25_000, 35_000]);
+
+        // 441 frames at 44.1 kHz is 10 ms each, with no rounding build-up
+        let mut clock = MediaClock::new(44_100);
+        for i in 0..1000u64 {
+            assert_eq!(clock.stamp(441, Duration::from_millis(i * 10)), i * 10_000);
+        }
+        assert_eq!(clock.position_us(), 10_000_000);
+        assert_eq!(clock.frames(), 441_000);
+    }
+
+    #[test]
+    fn test_drift_estimate() {
+        // The device runs 100 ppm fast: 480.048 frames per 10 ms of reference time
+        let mut clock = MediaClock::new(48_000);
+        let mut owed = 0.0f64;
+        for i in 0..2000u64 {
+            let jitter = [0, 700, 150, 1200, 40][i as usize % 5];
+            let frames = 480.048 + owed;
+            let whole = frames.floor();
+            owed = frames - whole;
+            if i == 500 {
+                assert_eq!(clock.drift_ppm(), None);
+            }
+            clock.stamp(whole as usize, Duration::from_micros(i * 10_000 + jitter));
+        }
+        let drift = clock.drift_ppm().unwrap();
+        assert!((drift - 100.0).abs() < 5.0, "{}", drift);
+
+        clock.reset();
+        assert_eq!((clock.frames(), clock.drift_ppm()), (0, None));
+    }
+
+    #[test]
+    fn test_dropout_reanchors() {
+        let mut clock = MediaClock::new(48_000);
+        for i in 0..1500u64 {
+            clock.stamp(480, Duration::from_micros(i * 10_000));
+        }
+        assert!(clock.drift_ppm().unwrap().abs() < 1.0);
+
+        // The device stalls for half a second: timestamps follow the
+        // reference again instead of running 500 ms behind for good
+        let resumed = 15_500_000;
+        assert_eq!(clock.stamp(480, Duration::from_micros(resumed)), resumed);
+        assert_eq!(clock.stamp(480, Duration::from_micros(resumed + 10_400)), resumed + 10_000);
+
+        // The stall is not drift: the fit starts over
+        assert_eq!((clock.frames(), clock.drift_ppm()), (960, None));
+
+        // Jitter under the threshold does not re-anchor
+        assert_eq!(clock.stamp(480, Duration::from_micros(resumed + 80_000)), resumed + 20_000);
+    }
+}
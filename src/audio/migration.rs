@@ -0,0 +1,99 @@
+//! Device migration
+//!
+//! When a track's device disappears mid-stream the track moves to its
+//! `fallback_device`, or the system default, rather than going silent until
+//! the device is plugged back in. The original is looked for every
+//! [`DEVICE_RETURN_CHECK_MS`] and the track moves back once it is present.
+//! The track's `device_id` is left as configured throughout, so saving the
+//! config keeps the original device.
+
+use std::time::{Duration, Instant};
+
+use crate::constants::DEVICE_RETURN_CHECK_MS;
+
+/// Where a track's audio goes while its device is missing
+#[derive(Debug, Default)]
+pub struct DeviceMigration {
+    fallback: Option<String>,
+    /// (original, fallback in use)
+    moved: Option<(String, String)>,
+    last_check: Option<Instant>,
+}
+
+impl DeviceMigration {
+    /// Move to `fallback` (None = the system default) when the device is lost
+    pub fn new(fallback: Option<String>) -> Self {
+        Self { fallback, ..Default::default() }
+    }
+
+    /// The fallback in use while the track is away from `configured`
+    ///
+    /// A track moved to another device through the API no longer waits for
+    /// its old one.
+    pub fn active(&mut self, configured: &str) -> Option<&str> {
+        if self.moved.as_ref().is_some_and(|(original, _)| original != configured) {
+            self.moved = None;
+        }
+        self.moved.as_ref().map(|(_, fallback)| fallback.as_str())
+    }
+
+    /// Device to move to after `configured` was lost; None when already moved or nowhere to go
+    pub fn lost(&self, configured: &str, default: Option<String>) -> Option<String> {
+        if self.moved.is_some() {
+            return None;
+        }
+        self.fallback.clone().or(default).filter(|target| target != configured)
+    }
+
+    /// The move from `original` to `fallback` succeeded
+    pub fn moved(&mut self, original: &str, fallback: &str) {
+        self.moved = Some((original.to_string(), fallback.to_string()));
+        self.last_check = Some(Instant::now());
+    }
+
+    /// The original device, when it is time to check whether it came back
+    pub fn return_due(&mut self) -> Option<&str> {
+        self.moved.as_ref()?;
+        if self.last_check.is_some_and(|t| t.elapsed() < Duration::from_millis(DEVICE_RETURN_CHECK_MS)) {
+            return None;
+        }
+        self.last_check = Some(Instant::now());
+        self.moved.as_ref().map(|(original, _)| original.as_str())
+    }
+
+    /// The track is back on its original device
+    pub fn returned(&mut self) {
+        self.moved = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migration_round_trip() {
+        let mut migration = DeviceMigration::new(None);
+        assert_eq!(migration.lost("input:USB", None), None);
+        assert_eq!(migration.lost("input:Mic", Some("input:Mic".to_string())), None);
+        let target = migration.lost("input:USB", Some("input:Mic".to_string())).unwrap();
+        assert_eq!(target, "input:Mic");
+
+        migration.moved("input:USB", &target);
+        assert_eq!(migration.active("input:USB"), Some("input:Mic"));
+        assert_eq!(migration.lost("input:USB", Some("input:Mic".to_string())), None);
+        // Checked again only after the interval
+        assert_eq!(migration.return_due(), None);
+        migration.last_check = Some(Instant::now() - Duration::from_millis(DEVICE_RETURN_CHECK_MS));
+        assert_eq!(migration.return_due(), Some("input:USB"));
+        migration.returned();
+        assert_eq!(migration.active("input:USB"), None);
+
+        // A configured fallback wins; moving the track elsewhere ends the migration
+        let mut migration = DeviceMigration::new(Some("input:Backup".to_string()));
+        assert_eq!(migration.lost("input:USB", Some("input:Mic".to_string())).as_deref(), Some("input:Backup"));
+        migration.moved("input:USB", "input:Backup");
+        assert_eq!(migration.active("input:Line"), None);
+        assert_eq!(migration.return_due(), None);
+    }
+}
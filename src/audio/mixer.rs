@@ -0,0 +1,145 @@
+//! Mixdown bus
+//!
+//! Sums several tracks into one output, e.g. so everything can be heard
+//! on headphones while each track still has its own virtual device.
+//! Gain and pan are applied per track before summing.
+
+use std::collections::{HashMap, VecDeque};
+
+use crate::audio::buffer::AudioFrame;
+
+/// Maximum queued audio per input, in output frames
+const MAX_QUEUED_FRAMES: usize = 8;
+
+/// Mixes track frames into a single interleaved stream
+pub struct Mixer {
+    /// Pending gain/pan-applied samples per track, in output channel layout
+    inputs: HashMap<u8, VecDeque<f32>>,
+    /// Tracks to mix (empty = all)
+    tracks: Vec<u8>,
+    /// Output channel count
+    channels: u16,
+    /// Samples per channel in each mixed frame
+    frame_size: usize,
+}
+
+impl Mixer {
+    /// Create a mixer producing `frame_size` samples per channel per frame
+    pub fn new(channels: u16, frame_size: usize, tracks: &[u8]) -> Self {
+        Self {
+            inputs: HashMap::new(),
+            tracks: tracks.to_vec(),
+            channels: channels.clamp(1, 2),
+            frame_size: frame_size.max(1),
+        }
+    }
+
+    /// Check if a track is part of the mix
+    pub fn includes(&self, track_id: u8) -> bool {
+        self.tracks.is_empty() || self.tracks.contains(&track_id)
+    }
+
+    /// Add a track frame with linear gain and pan (-1.0 left .. 1.0 right)
+    pub fn push(&mut self, track_id: u8, frame: &AudioFrame, gain: f32, pan: f32) {
+        if !self.includes(track_id) {
+            return;
+        }
+
+        let pan = pan.clamp(-1.0, 1.0);
+        let left_gain = gain * (1.0 - pan).min(1.0);
+        let right_gain = gain * (1.0 + pan).min(1.0);
+        let in_channels = frame.channels.max(1) as usize;
+        let out_channels = self.channels;
+        let max_len = MAX_QUEUED_FRAMES * self.frame_len();
+
+        let input = self.inputs.entry(track_id).or_default();
+        for chunk in frame.samples.chunks(in_channels) {
+            let (left, right) = match chunk {
+                [mono] => (*mono, *mono),
+                [left, right, ..] => (*left, *right),
+                [] => continue,
+            };
+
+            if out_channels == 2 {
+                input.push_back(left * left_gain);
+                input.push_back(right * right_gain);
+            } else {
+                input.push_back((left + right) * 0.5 * gain);
+            }
+        }
+
+        // A stalled output must not let inputs grow without bound
+        if input.len() > max_len {
+            let excess = input.len() - max_len;
+            input.drain(..excess);
+        }
+    }
+
+    /// Produce the next mixed frame once any input has a full frame queued
+    ///
+    /// Inputs with less audio contribute what they have.
+    pub fn mix(&mut self) -> Option<Vec<f32>> {
+        let frame_len = self.frame_len();
+        if !self.inputs.values().any(|input| input.len() >= frame_len) {
+            return None;
+        }
+
+        let mut output = vec![0.0f32; frame_len];
+        for input in self.inputs.values_mut() {
+            let take = input.len().min(frame_len);
+            for (out, sample) in output.iter_mut().zip(input.drain(..take)) {
+                *out += sample;
+            }
+        }
+
+        for sample in &mut output {
+            *sample = sample.clamp(-1.0, 1.0);
+        }
+
+        Some(output)
+    }
+
+    /// Forget a track's pending audio
+    pub fn remove(&mut self, track_id: u8) {
+        self.inputs.remove(&track_id);
+    }
+
+    /// Get output channel count
+    pub fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn frame_len(&self) -> usize {
+        self.frame_size * self.channels as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mix_gain_and_pan() {
+        let mut mixer = Mixer::new(2, 2, &[]);
+
+        // Mono track panned hard left
+        mixer.push(0, &AudioFrame::new(vec![0.5, 0.5], 1, 0, 0), 1.0, -1.0);
+        // Stereo track at half gain
+        mixer.push(1, &AudioFrame::new(vec![0.2, 0.4, 0.2, 0.4], 2, 0, 0), 0.5, 0.0);
+
+        let mixed = mixer.mix().unwrap();
+        assert_eq!(mixed.len(), 4);
+        assert!((mixed[0] - 0.6).abs() < 1e-6);
+        assert!((mixed[1] - 0.2).abs() < 1e-6);
+        assert!(mixer.mix().is_none());
+    }
+
+    #[test]
+    fn test_track_selection() {
+        let mut mixer = Mixer::new(2, 1, &[1]);
+        mixer.push(0, &AudioFrame::new(vec![1.0, 1.0], 2, 0, 0), 1.0, 0.0);
+        assert!(mixer.mix().is_none());
+        assert!(!mixer.includes(0));
+        assert!(mixer.includes(1));
+    }
+}
@@ -1,11 +1,37 @@
 //! Audio subsystem module
 
+#[cfg(feature = "cpal")]
 pub mod capture;
+#[cfg(feature = "cpal")]
 pub mod playback;
 pub mod buffer;
+pub mod click;
+pub mod comfort;
+pub mod custom;
+pub mod delay;
+#[cfg(feature = "cpal")]
 pub mod device;
+pub mod device_id;
+pub mod fade;
+pub mod loop_guard;
+pub mod media_clock;
+pub mod migration;
+pub mod mixer;
+pub mod monitor;
+pub mod samples;
 
+#[cfg(feature = "cpal")]
 pub use capture::AudioCapture;
+#[cfg(feature = "cpal")]
 pub use playback::AudioPlayback;
 pub use buffer::RingBuffer;
+pub use comfort::ComfortNoise;
+pub use custom::{AudioSink, AudioSource, SinkDriver, SourceDriver};
+pub use delay::DelayLine;
+pub use loop_guard::LoopGuard;
+pub use media_clock::MediaClock;
+pub use mixer::Mixer;
+pub use monitor::SignalMonitor;
+pub use samples::{SamplePool, Samples};
+#[cfg(feature = "cpal")]
 pub use device::{list_devices, get_device_by_id, AudioDevice};
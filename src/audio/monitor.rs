@@ -0,0 +1,140 @@
+//! Clipping and silence detection
+//!
+//! Watches a track's samples and reports when it starts or stops clipping
+//! (samples at or above 0 dBFS) for a sustained period, or when it stays
+//! below a silence threshold for several seconds — a dead mic, a muted
+//! interface or a cable that fell out.
+
+use crate::config::AlertConfig;
+use crate::protocol::AlertKind;
+
+/// Alert state change reported by [`SignalMonitor::process`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AlertChange {
+    pub kind: AlertKind,
+    /// True when the alert was raised, false when it cleared
+    pub active: bool,
+}
+
+/// Per-track clipping and silence detector
+pub struct SignalMonitor {
+    sample_rate: u32,
+    clip_us: u64,
+    silence_threshold: f32,
+    silence_us: u64,
+    /// Leaky clipping time: grows on clipped blocks, drains on clean ones
+    clipped_us: u64,
+    silent_us: u64,
+    clipping: bool,
+    silent: bool,
+}
+
+impl SignalMonitor {
+    /// Create a monitor from the alert configuration
+    pub fn new(sample_rate: u32, config: &AlertConfig) -> Self {
+        Self {
+            sample_rate: sample_rate.max(1),
+            clip_us: config.clip_ms as u64 * 1000,
+            silence_threshold: 10f32.powf(config.silence_threshold_db / 20.0),
+            silence_us: config.silence_secs as u64 * 1_000_000,
+            clipped_us: 0,
+            silent_us: 0,
+            clipping: false,
+            silent: false,
+        }
+    }
+
+    /// Analyze a block of interleaved samples
+    ///
+    /// Returns the alerts raised or cleared by this block.
+    pub fn process(&mut self, samples: &[f32], channels: u16) -> Vec<AlertChange> {
+        let frames = samples.len() / channels.max(1) as usize;
+        if frames == 0 {
+            return Vec::new();
+        }
+
+        let duration_us = frames as u64 * 1_000_000 / self.sample_rate as u64;
+        let peak = samples.iter().fold(0.0f32, |max, s| max.max(s.abs()));
+        let mut changes = Vec::new();
+
+        if peak >= 1.0 {
+            self.clipped_us = (self.clipped_us + duration_us).min(self.clip_us);
+        } else {
+            self.clipped_us = self.clipped_us.saturating_sub(duration_us);
+        }
+
+        if !self.clipping && self.clip_us > 0 && self.clipped_us >= self.clip_us {
+            self.clipping = true;
+            changes.push(AlertChange { kind: AlertKind::Clipping, active: true });
+        } else if self.clipping && self.clipped_us == 0 {
+            self.clipping = false;
+            changes.push(AlertChange { kind: AlertKind::Clipping, active: false });
+        }
+
+        if peak < self.silence_threshold {
+            self.silent_us += duration_us;
+            if !self.silent && self.silence_us > 0 && self.silent_us >= self.silence_us {
+                self.silent = true;
+                changes.push(AlertChange { kind: AlertKind::Silence, active: true });
+            }
+        } else {
+            self.silent_us = 0;
+            if self.silent {
+                self.silent = false;
+                changes.push(AlertChange { kind: AlertKind::Silence, active: false });
+            }
+        }
+
+        changes
+    }
+
+    /// Forget accumulated state (e.g. after a pause or mute)
+    pub fn reset(&mut self) {
+        self.clipped_us = 0;
+        self.silent_us = 0;
+    }
+
+    /// Check if the track is currently clipping
+    pub fn is_clipping(&self) -> bool {
+        self.clipping
+    }
+
+    /// Check if the track is currently silent
+    pub fn is_silent(&self) -> bool {
+        self.silent
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clipping_and_silence() {
+        let config = AlertConfig {
+            clip_ms: 20,
+            silence_secs: 1,
+            ..Default::default()
+        };
+        let mut monitor = SignalMonitor::new(1000, &config);
+
+        // 10 ms blocks at 1 kHz mono
+        let loud = vec![1.0f32; 10];
+        assert!(monitor.process(&loud, 1).is_empty());
+        let changes = monitor.process(&loud, 1);
+        assert_eq!(changes, vec![AlertChange { kind: AlertKind::Clipping, active: true }]);
+
+        let quiet = vec![0.0f32; 10];
+        let changes = monitor.process(&quiet, 1);
+        assert!(changes.is_empty());
+        let changes = monitor.process(&quiet, 1);
+        assert_eq!(changes, vec![AlertChange { kind: AlertKind::Clipping, active: false }]);
+
+        for _ in 0..97 {
+            assert!(monitor.process(&quiet, 1).is_empty());
+        }
+        let changes = monitor.process(&quiet, 1);
+        assert_eq!(changes, vec![AlertChange { kind: AlertKind::Silence, active: true }]);
+        assert!(monitor.is_silent());
+    }
+}
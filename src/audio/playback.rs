@@ -10,10 +10,18 @@ use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::thread::{self, JoinHandle};
 
-use crate::audio::buffer::{AudioFrame, JitterBuffer, SharedRingBuffer};
+use crate::audio::buffer::{AudioFrame, JitterBuffer, JitterRelease, SharedJitterBuffer};
+pub use crate::audio::buffer::PlaybackSource;
 use crate::audio::device::get_device_by_id;
-use crate::constants::DEFAULT_SAMPLE_RATE;
+use crate::audio::fade::Ramp;
+use crate::audio::samples::Samples;
+use crate::constants::{DEFAULT_SAMPLE_RATE, TRANSITION_FADE_MS};
 use crate::error::AudioError;
+use crate::protocol::{XrunEvent, XrunKind};
+use crate::sync::now_us;
+
+/// Xrun events kept until the owner drains them
+pub(crate) const XRUN_QUEUE: usize = 64;
 
 /// Audio playback instance for a single device/track
 pub struct AudioPlayback {
@@ -26,8 +34,8 @@ pub struct AudioPlayback {
     /// Whether playback is running
     running: Arc<AtomicBool>,
     
-    /// Input buffer for frames to play
-    input_buffer: SharedRingBuffer,
+    /// Source of frames to play
+    input_buffer: PlaybackSource,
     
     /// Stream thread handle
     thread_handle: Option<JoinHandle<()>>,
@@ -35,12 +43,27 @@ pub struct AudioPlayback {
     /// Channel for stream errors
     error_rx: Option<Receiver<AudioError>>,
     
+    /// Underruns reported by the output callback
+    xrun_rx: Option<Receiver<XrunEvent>>,
+    
     /// Total samples played
     samples_played: Arc<AtomicU64>,
     
-    /// Buffer underruns
+    /// Output callbacks that ran out of audio
     underruns: Arc<AtomicU32>,
     
+    /// Fade-out length in sample frames (0 = no fade)
+    fade_frames: Arc<AtomicU32>,
+    
+    /// Sample frames played since the fade started
+    fade_pos: Arc<AtomicU32>,
+    
+    /// Stop requested; output is ramping down
+    stopping: Arc<AtomicBool>,
+    
+    /// Stop ramp has reached silence
+    stop_faded: Arc<AtomicBool>,
+    
     /// Stream configuration
     config: StreamConfig,
     
@@ -53,13 +76,16 @@ pub struct AudioPlayback {
 
 impl AudioPlayback {
     /// Create a new audio playback for the specified device
+    /// 
+    /// `input_buffer` is either a ring buffer of ready frames or a shared
+    /// jitter buffer the output callback pulls from directly.
     pub fn new(
         track_id: u8,
         device_id: &str,
         sample_rate: Option<u32>,
         channels: Option<u16>,
         buffer_size: Option<u32>,
-        input_buffer: SharedRingBuffer,
+        input_buffer: impl Into<PlaybackSource>,
     ) -> Result<Self, AudioError> {
         let device = get_device_by_id(device_id)?;
         
@@ -79,11 +105,16 @@ impl AudioPlayback {
             track_id,
             device_id: device_id.to_string(),
             running: Arc::new(AtomicBool::new(false)),
-            input_buffer,
+            input_buffer: input_buffer.into(),
             thread_handle: None,
             error_rx: None,
+            xrun_rx: None,
             samples_played: Arc::new(AtomicU64::new(0)),
             underruns: Arc::new(AtomicU32::new(0)),
+            fade_frames: Arc::new(AtomicU32::new(0)),
+            fade_pos: Arc::new(AtomicU32::new(0)),
+            stopping: Arc::new(AtomicBool::new(false)),
+            stop_faded: Arc::new(AtomicBool::new(false)),
             config,
             muted: Arc::new(AtomicBool::new(false)),
             volume: Arc::new(parking_lot::RwLock::new(1.0)),
@@ -99,28 +130,43 @@ impl AudioPlayback {
         let device = get_device_by_id(&self.device_id)?;
         let (error_tx, error_rx) = bounded::<AudioError>(16);
         self.error_rx = Some(error_rx);
+        let (xrun_tx, xrun_rx) = bounded::<XrunEvent>(XRUN_QUEUE);
+        self.xrun_rx = Some(xrun_rx);
         
+        let track_id = self.track_id;
         let running = self.running.clone();
         let running_for_loop = self.running.clone();
         let input_buffer = self.input_buffer.clone();
         let samples_played = self.samples_played.clone();
         let underruns = self.underruns.clone();
         let config = self.config.clone();
-        let _channels = self.config.channels as usize;
+        let channels = self.config.channels as usize;
         let muted = self.muted.clone();
+        let fade_frames = self.fade_frames.clone();
+        let fade_pos = self.fade_pos.clone();
+        let stopping = self.stopping.clone();
+        let stop_faded = self.stop_faded.clone();
         let volume = self.volume.clone();
+        let sample_rate = self.config.sample_rate.0;
         
+        self.stopping.store(false, Ordering::SeqCst);
+        self.stop_faded.store(false, Ordering::SeqCst);
         running.store(true, Ordering::SeqCst);
         
         let handle = thread::Builder::new()
-            .name(format!("playback-track-{}", self.track_id))
+            // Linux keeps only 15 bytes of a thread name
+            .name(format!("play-track-{}", self.track_id))
             .spawn(move || {
                 let cpal_device = device.into_inner();
                 
                 // Buffered samples for smooth playback
-                let mut sample_buffer: Vec<f32> = Vec::new();
+                let mut sample_buffer = Samples::default();
                 let mut sample_pos = 0;
                 
+                // Starts from silence so the first frames fade in
+                let mut ramp = Ramp::new(sample_rate, TRANSITION_FADE_MS, 0.0);
+                let mut was_starved = false;
+                
                 let stream = cpal_device.build_output_stream(
                     &config,
                     move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
@@ -132,32 +178,66 @@ impl AudioPlayback {
                             return;
                         }
                         
-                        let is_muted = muted.load(Ordering::Relaxed);
-                        let vol = *volume.read();
+                        // Mute, volume and stop all glide instead of stepping
+                        let silent = muted.load(Ordering::Relaxed) || stopping.load(Ordering::Relaxed);
+                        ramp.set_target(if silent { 0.0 } else { *volume.read() });
                         
+                        let mut starved = false;
                         for sample in data.iter_mut() {
                             // Check if we need more samples
                             if sample_pos >= sample_buffer.len() {
                                 // Try to get next frame
-                                if let Some(frame) = input_buffer.try_pop() {
+                                if let Some(frame) = input_buffer.next_frame() {
                                     sample_buffer = frame.samples;
                                     sample_pos = 0;
                                 } else {
-                                    // Underrun - output silence
-                                    underruns.fetch_add(1, Ordering::Relaxed);
+                                    // Underrun - output silence, fade back in afterwards
+                                    starved = true;
+                                    ramp.jump(0.0);
                                     *sample = 0.0;
                                     continue;
                                 }
                             }
                             
-                            // Output sample (with mute and volume)
-                            if is_muted {
-                                *sample = 0.0;
-                            } else {
-                                *sample = sample_buffer[sample_pos] * vol;
-                            }
+                            *sample = sample_buffer[sample_pos];
                             sample_pos += 1;
                         }
+                        if starved {
+                            underruns.fetch_add(1, Ordering::Relaxed);
+                            
+                            // One event per episode, not per callback
+                            if !was_starved {
+                                let (buffer_fill, buffer_capacity) = input_buffer.fill();
+                                let _ = xrun_tx.try_send(XrunEvent {
+                                    track_id,
+                                    kind: XrunKind::Underrun,
+                                    timestamp_us: now_us(),
+                                    buffer_fill,
+                                    buffer_capacity,
+                                    callback_frames: data.len() / channels.max(1),
+                                });
+                            }
+                        }
+                        was_starved = starved;
+                        
+                        ramp.apply(data, channels as u16);
+                        if stopping.load(Ordering::Relaxed) && ramp.gain() == 0.0 {
+                            stop_faded.store(true, Ordering::Relaxed);
+                        }
+                        
+                        // Fade-out ramp, silence once complete
+                        let fade_len = fade_frames.load(Ordering::Relaxed);
+                        if fade_len > 0 {
+                            let mut pos = fade_pos.load(Ordering::Relaxed);
+                            for frame in data.chunks_mut(channels.max(1)) {
+                                let gain = 1.0 - (pos.min(fade_len) as f32 / fade_len as f32);
+                                for sample in frame.iter_mut() {
+                                    *sample *= gain;
+                                }
+                                pos = pos.saturating_add(1);
+                            }
+                            fade_pos.store(pos, Ordering::Relaxed);
+                        }
                         
                         samples_played.fetch_add(data.len() as u64, Ordering::Relaxed);
                     },
@@ -191,7 +271,19 @@ impl AudioPlayback {
     }
     
     /// Stop playback
+    /// 
+    /// Output ramps down to silence first; gives up waiting after a few
+    /// ramp lengths in case the device stopped calling back.
     pub fn stop(&mut self) {
+        if self.running.load(Ordering::SeqCst) {
+            self.stopping.store(true, Ordering::SeqCst);
+            let deadline = std::time::Instant::now()
+                + std::time::Duration::from_millis(TRANSITION_FADE_MS as u64 * 4);
+            while !self.stop_faded.load(Ordering::SeqCst) && std::time::Instant::now() < deadline {
+                thread::sleep(std::time::Duration::from_millis(1));
+            }
+        }
+        
         self.running.store(false, Ordering::SeqCst);
         
         if let Some(handle) = self.thread_handle.take() {
@@ -199,11 +291,42 @@ impl AudioPlayback {
         }
     }
     
+    /// Stop and reopen the device, e.g. after an error
+    pub fn restart(&mut self) -> Result<(), AudioError> {
+        self.stop();
+        self.start()
+    }
+    
     /// Check if playback is running
     pub fn is_running(&self) -> bool {
         self.running.load(Ordering::SeqCst)
     }
     
+    /// Move playback to another output device
+    /// 
+    /// The old device fades out and the new one fades in.
+    pub fn set_device(&mut self, device_id: &str) -> Result<(), AudioError> {
+        if device_id == self.device_id {
+            return Ok(());
+        }
+        get_device_by_id(device_id)?;
+        
+        let was_running = self.is_running();
+        self.stop();
+        self.device_id = device_id.to_string();
+        tracing::info!("Track {} playback moved to {}", self.track_id, device_id);
+        
+        if was_running {
+            self.start()?;
+        }
+        Ok(())
+    }
+    
+    /// Get device identifier
+    pub fn device_id(&self) -> &str {
+        &self.device_id
+    }
+    
     /// Set mute state
     pub fn set_muted(&self, muted: bool) {
         self.muted.store(muted, Ordering::Relaxed);
@@ -224,6 +347,25 @@ impl AudioPlayback {
         *self.volume.read()
     }
     
+    /// Fade output to silence over the given duration
+    pub fn fade_out(&self, duration_ms: u32) {
+        let frames = (self.config.sample_rate.0 as u64 * duration_ms as u64 / 1000).max(1);
+        self.fade_pos.store(0, Ordering::Relaxed);
+        self.fade_frames.store(frames as u32, Ordering::Relaxed);
+    }
+    
+    /// Check if a fade-out has completed
+    pub fn is_faded_out(&self) -> bool {
+        let fade_len = self.fade_frames.load(Ordering::Relaxed);
+        fade_len > 0 && self.fade_pos.load(Ordering::Relaxed) >= fade_len
+    }
+    
+    /// Cancel a fade-out and restore full level
+    pub fn clear_fade(&self) {
+        self.fade_frames.store(0, Ordering::Relaxed);
+        self.fade_pos.store(0, Ordering::Relaxed);
+    }
+    
     /// Get total samples played
     pub fn samples_played(&self) -> u64 {
         self.samples_played.load(Ordering::Relaxed)
@@ -253,6 +395,11 @@ impl AudioPlayback {
     pub fn check_errors(&self) -> Option<AudioError> {
         self.error_rx.as_ref().and_then(|rx| rx.try_recv().ok())
     }
+    
+    /// Take underrun events reported since the last call
+    pub fn drain_xruns(&self) -> Vec<XrunEvent> {
+        self.xrun_rx.as_ref().map(|rx| rx.try_iter().collect()).unwrap_or_default()
+    }
 }
 
 impl Drop for AudioPlayback {
@@ -262,15 +409,17 @@ impl Drop for AudioPlayback {
 }
 
 /// Playback with jitter buffer for network audio
+///
+/// The output callback releases frames straight from the jitter buffer
+/// (see [`JitterRelease`]), running the owner's stage and the playout
+/// delay as it does.
 pub struct NetworkPlayback {
     /// Inner playback
     playback: AudioPlayback,
     
-    /// Jitter buffer for reordering
-    jitter_buffer: parking_lot::Mutex<JitterBuffer>,
-    
-    /// Decoded frame buffer
-    decoded_buffer: SharedRingBuffer,
+    /// Jitter buffer shared with the network thread, and what the
+    /// callback does with its frames
+    release: Arc<JitterRelease>,
 }
 
 impl NetworkPlayback {
@@ -283,44 +432,81 @@ impl NetworkPlayback {
         jitter_buffer_size: usize,
         min_jitter_delay: usize,
     ) -> Result<Self, AudioError> {
-        let decoded_buffer = crate::audio::buffer::create_shared_buffer(64);
-        
+        let jitter_buffer = Arc::new(JitterBuffer::new(
+            jitter_buffer_size.next_power_of_two(),
+            min_jitter_delay,
+        ));
+        Self::with_jitter_buffer(track_id, device_id, sample_rate, channels, jitter_buffer)
+    }
+    
+    /// Create network playback around an existing shared jitter buffer
+    /// 
+    /// The network thread can keep inserting into `jitter_buffer` directly
+    /// instead of going through [`push_frame`](Self::push_frame).
+    pub fn with_jitter_buffer(
+        track_id: u8,
+        device_id: &str,
+        sample_rate: Option<u32>,
+        channels: Option<u16>,
+        jitter_buffer: SharedJitterBuffer,
+    ) -> Result<Self, AudioError> {
+        let release = Arc::new(JitterRelease::new(jitter_buffer));
         let playback = AudioPlayback::new(
             track_id,
             device_id,
             sample_rate,
             channels,
             None,
-            decoded_buffer.clone(),
+            release.clone(),
         )?;
         
-        let jitter_buffer = parking_lot::Mutex::new(JitterBuffer::new(
-            jitter_buffer_size.next_power_of_two(),
-            min_jitter_delay,
-        ));
-        
         Ok(Self {
             playback,
-            jitter_buffer,
-            decoded_buffer,
+            release,
         })
     }
     
     /// Push a decoded frame to the jitter buffer
     pub fn push_frame(&self, frame: AudioFrame) -> bool {
-        let mut jitter = self.jitter_buffer.lock();
-        jitter.insert(frame)
+        self.release.jitter_buffer().insert(frame)
     }
     
-    /// Process jitter buffer and push to playback
-    pub fn process(&self) -> Option<AudioFrame> {
-        let mut jitter = self.jitter_buffer.lock();
-        if let Some(frame) = jitter.get_next() {
-            let _ = self.decoded_buffer.push(frame.clone());
-            Some(frame)
-        } else {
-            None
-        }
+    /// Run `stage` on each frame as the output callback releases it
+    /// 
+    /// It runs on the audio thread, so it must not block.
+    pub fn set_stage(&self, stage: impl FnMut(&mut AudioFrame) + Send + 'static) {
+        self.release.set_stage(Some(Box::new(stage)));
+    }
+    
+    /// Keep released frames for [`take_released`](Self::take_released)
+    pub fn keep_released(&self, keep: bool) {
+        self.release.keep_released(keep);
+    }
+    
+    /// Next frame the output callback released since the last call
+    pub fn take_released(&self) -> Option<AudioFrame> {
+        self.release.take_released()
+    }
+    
+    /// Queue a locally generated frame (e.g. comfort noise) to play while
+    /// the jitter buffer has nothing to release
+    pub fn push_generated(&self, frame: AudioFrame) -> bool {
+        self.release.push_generated(frame)
+    }
+    
+    /// Get number of frames waiting for the output device
+    pub fn queued_frames(&self) -> usize {
+        self.release.queued_frames()
+    }
+    
+    /// Set static playout delay in milliseconds
+    pub fn set_delay_ms(&self, delay_ms: u32) {
+        self.release.set_delay_ms(delay_ms, self.playback.sample_rate(), self.playback.channels());
+    }
+    
+    /// Get static playout delay in milliseconds
+    pub fn delay_ms(&self) -> u32 {
+        self.release.delay_ms()
     }
     
     /// Start playback
@@ -333,9 +519,19 @@ impl NetworkPlayback {
         self.playback.stop();
     }
     
+    /// Set what the jitter buffer drops when full
+    pub fn set_overflow_policy(&self, policy: crate::audio::buffer::OverflowPolicy) {
+        self.release.jitter_buffer().set_policy(policy);
+    }
+    
     /// Get jitter buffer stats
     pub fn jitter_stats(&self) -> crate::audio::buffer::JitterBufferStats {
-        self.jitter_buffer.lock().stats()
+        self.release.jitter_buffer().stats()
+    }
+    
+    /// Get a handle to the jitter buffer for inserting from another thread
+    pub fn jitter_buffer(&self) -> SharedJitterBuffer {
+        self.release.jitter_buffer().clone()
     }
     
     /// Get inner playback
@@ -0,0 +1,216 @@
+//! Shared sample buffers
+//!
+//! [`Samples`] is the storage of an [`AudioFrame`](crate::audio::buffer::AudioFrame):
+//! reference counted, so the jitter buffer, recorder, meters and playback
+//! can all hold the same frame without copying its samples, and copied on
+//! write when one of them changes it. Buffers taken from a [`SamplePool`]
+//! go back to it when the last reference drops, so the capture callback
+//! and decoders reuse allocations instead of making one per frame.
+
+use crossbeam::queue::ArrayQueue;
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
+
+/// Buffers a pool keeps for reuse
+const POOL_BUFFERS: usize = 64;
+
+/// Free buffers waiting to be reused
+pub struct SamplePool {
+    free: ArrayQueue<Vec<f32>>,
+}
+
+impl SamplePool {
+    /// Pool keeping up to [`POOL_BUFFERS`] free buffers
+    pub fn new() -> Arc<Self> {
+        Self::with_capacity(POOL_BUFFERS)
+    }
+
+    /// Pool keeping up to `buffers` free buffers
+    pub fn with_capacity(buffers: usize) -> Arc<Self> {
+        Arc::new(Self { free: ArrayQueue::new(buffers.max(1)) })
+    }
+
+    /// Buffer holding a copy of `data`
+    pub fn copy_from(self: &Arc<Self>, data: &[f32]) -> Samples {
+        let mut vec = self.take();
+        vec.extend_from_slice(data);
+        self.wrap(vec)
+    }
+
+    /// Buffer of `len` zeroes
+    pub fn zeroed(self: &Arc<Self>, len: usize) -> Samples {
+        let mut vec = self.take();
+        vec.resize(len, 0.0);
+        self.wrap(vec)
+    }
+
+    /// Number of free buffers
+    pub fn available(&self) -> usize {
+        self.free.len()
+    }
+
+    fn take(&self) -> Vec<f32> {
+        let mut vec = self.free.pop().unwrap_or_default();
+        vec.clear();
+        vec
+    }
+
+    fn wrap(self: &Arc<Self>, data: Vec<f32>) -> Samples {
+        Samples(Arc::new(Buffer { data, pool: Some(self.clone()) }))
+    }
+}
+
+/// One allocation, returned to its pool on drop
+struct Buffer {
+    data: Vec<f32>,
+    pool: Option<Arc<SamplePool>>,
+}
+
+impl Drop for Buffer {
+    fn drop(&mut self) {
+        if let Some(pool) = self.pool.take() {
+            // A full pool just lets the buffer go
+            let _ = pool.free.push(std::mem::take(&mut self.data));
+        }
+    }
+}
+
+/// Reference-counted interleaved samples
+///
+/// Cloning shares the buffer. Mutable access goes through
+/// [`make_mut`](Samples::make_mut) (or `DerefMut`), which copies the
+/// samples first if the buffer is shared.
+#[derive(Clone)]
+pub struct Samples(Arc<Buffer>);
+
+impl Samples {
+    /// Samples for writing, copied out of the shared buffer if another
+    /// reference holds it
+    pub fn make_mut(&mut self) -> &mut Vec<f32> {
+        if Arc::get_mut(&mut self.0).is_none() {
+            let copy = match &self.0.pool {
+                Some(pool) => pool.copy_from(&self.0.data),
+                None => Samples::from(self.0.data.clone()),
+            };
+            *self = copy;
+        }
+        &mut Arc::get_mut(&mut self.0).expect("buffer was just made unique").data
+    }
+
+    /// Check if another reference shares this buffer
+    pub fn is_shared(&self) -> bool {
+        Arc::strong_count(&self.0) > 1
+    }
+
+    /// Take the samples out, copying them if the buffer is shared
+    pub fn into_vec(mut self) -> Vec<f32> {
+        std::mem::take(self.make_mut())
+    }
+}
+
+impl Default for Samples {
+    fn default() -> Self {
+        Self::from(Vec::new())
+    }
+}
+
+impl From<Vec<f32>> for Samples {
+    /// Wrap an existing allocation; it is freed rather than pooled
+    fn from(data: Vec<f32>) -> Self {
+        Samples(Arc::new(Buffer { data, pool: None }))
+    }
+}
+
+impl Deref for Samples {
+    type Target = [f32];
+
+    fn deref(&self) -> &[f32] {
+        &self.0.data
+    }
+}
+
+impl DerefMut for Samples {
+    fn deref_mut(&mut self) -> &mut [f32] {
+        self.make_mut()
+    }
+}
+
+impl<'a> IntoIterator for &'a Samples {
+    type Item = &'a f32;
+    type IntoIter = std::slice::Iter<'a, f32>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl fmt::Debug for Samples {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl PartialEq for Samples {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl PartialEq<Vec<f32>> for Samples {
+    fn eq(&self, other: &Vec<f32>) -> bool {
+        **self == other[..]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shared_until_written() {
+        let pool = SamplePool::new();
+        let mut first = pool.copy_from(&[1.0, 2.0, 3.0]);
+        let second = first.clone();
+        assert!(first.is_shared());
+        assert_eq!(first.as_ptr(), second.as_ptr());
+
+        // Writing to one copies it away from the other
+        first[0] = 9.0;
+        assert!(!first.is_shared());
+        assert_eq!(first, vec![9.0, 2.0, 3.0]);
+        assert_eq!(second, vec![1.0, 2.0, 3.0]);
+
+        // Unshared buffers are written in place
+        let ptr = first.as_ptr();
+        first[1] = 0.0;
+        assert_eq!(first.as_ptr(), ptr);
+        first.make_mut().push(4.0);
+        assert_eq!(first, vec![9.0, 0.0, 3.0, 4.0]);
+    }
+
+    #[test]
+    fn test_pool_reuse() {
+        let pool = SamplePool::with_capacity(2);
+        let samples = pool.zeroed(480);
+        let ptr = samples.as_ptr();
+        assert_eq!(pool.available(), 0);
+
+        // The buffer only returns once the last reference is gone
+        let shared = samples.clone();
+        drop(samples);
+        assert_eq!(pool.available(), 0);
+        drop(shared);
+        assert_eq!(pool.available(), 1);
+
+        let reused = pool.copy_from(&[0.5; 480]);
+        assert_eq!(reused.as_ptr(), ptr);
+        assert!(reused.iter().all(|&s| s == 0.5));
+
+        // Full pools drop extra buffers; unpooled ones never enter
+        let extra: Vec<_> = (0..4).map(|_| pool.zeroed(16)).collect();
+        drop(extra);
+        drop(Samples::from(vec![0.0; 16]));
+        assert_eq!(pool.available(), 2);
+    }
+}
@@ -0,0 +1,576 @@
+//! Full-Duplex Audio Application
+//!
+//! Runs the sender and receiver pipelines in one process for two-way audio
+//! (talkback) between two PCs. Both directions share one UDP socket, one
+//! track manager and one web UI. Local capture keeps its configured track
+//! IDs; received tracks are managed as `duplex.remote_track_base + N` so the
+//! two sides never collide.
+//!
+//! Loop prevention: capture from a monitor of the playback device is refused,
+//! and with `duplex.loop_guard` capture is muted (with a feedback alert)
+//! while it follows the audio just played.
+
+use anyhow::Result;
+use crossbeam_channel::bounded;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use lan_audio_streamer::{
+    audio::{
+        buffer::{create_shared_buffer, AudioFrame, JitterBuffer},
+        capture::AudioCapture,
+        device::list_devices,
+        device_id,
+        loop_guard::{is_monitor_device, LoopGuard},
+        playback::NetworkPlayback,
+    },
+    codec::{OpusDecoder, OpusEncoder},
+    config::{AppConfig, MuteSide, OpusConfig},
+    constants::*,
+    events::EventLog,
+    network::{metadata, mqtt, nat::NatTraversal, osc, receiver::{AudioReceiver, ReceivedPacket}, resolve::Target, sender::MultiTrackSender, SourceFilter},
+    diagnostics::{self, Diagnostics},
+    hooks,
+    notify,
+    stats_log,
+    protocol::{AlertKind, PeerMessage, ReceiverCapabilities, TrackConfig, TrackType},
+    recovery::Recovery,
+    reload::ConfigReload,
+    service::{Service, ServiceArgs},
+    shutdown::ShutdownCoordinator,
+    tracks::{Counter, Gauge, ResetSource, Scenes, TrackCounters, TrackManager},
+    ui::WebServer,
+    wake::{Wait, Wakeup},
+};
+
+/// Per-track state of a received stream
+struct RemoteTrack {
+    decoder: OpusDecoder,
+    playback: Option<NetworkPlayback>,
+    jitter_buffer: Arc<JitterBuffer>,
+    /// Peer ended the stream; playback is fading out
+    ended: bool,
+}
+
+/// Device name from an `input:`/`output:` device ID
+fn device_name(id: &str) -> &str {
+    id.split_once(':').map_or(id, |(_, name)| name)
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    // Initialize logging
+    let args = ServiceArgs::parse(std::env::args());
+    let event_log = Arc::new(EventLog::new(EVENT_LOG_CAPACITY));
+    let log_control = Arc::new(diagnostics::init_logging(event_log.clone(), !args.service));
+    
+    tracing::info!("Starting LAN Audio Duplex");
+    
+    // Load the saved config, if any
+    let config = AppConfig::load_or_default(args.config_path.as_ref())?;
+    device_id::set_aliases(&config.device_aliases);
+    let remote_base = config.duplex.remote_track_base;
+    
+    // List available devices (services have no console to print to)
+    let devices = list_devices();
+    if !args.service {
+        println!("\n=== Available Audio Devices ===");
+        for device in &devices {
+            let device_type = match (device.is_input, device.is_output) {
+                (true, true) => "Input/Output",
+                (true, false) => "Input",
+                (false, true) => "Output",
+                _ => "Unknown",
+            };
+            let default_marker = if device.is_default { " [DEFAULT]" } else { "" };
+            println!("  {} ({}){}:", device.name, device_type, default_marker);
+            println!("    ID: {}", device.id);
+        }
+        println!();
+    }
+    
+    // Ctrl+C or a service stop ends both directions cleanly
+    let shutdown = ShutdownCoordinator::new();
+    shutdown.listen_for_ctrl_c();
+    let service = Service::start("lan-audio-duplex", args.service, &shutdown);
+    
+    // One track manager and web UI for both directions
+    let track_manager = Arc::new(TrackManager::with_max_tracks(config.audio.max_tracks));
+    track_manager.health().configure(&config.health);
+    track_manager.set_profiles(config.profiles.clone());
+    track_manager.set_bandwidth_cap(config.alerts.bandwidth_cap_kbps);
+    
+    // Safe config changes apply without a restart
+    let reload = Arc::new(ConfigReload::start(
+        AppConfig::path_in_use(args.config_path.as_ref()),
+        config.clone(),
+        track_manager.clone(),
+    ));
+    track_manager.set_bitrate_budget(config.network.bitrate_budget);
+    
+    let web_server = WebServer::new(config.ui.clone(), track_manager.clone(), true)
+        .with_event_log(event_log.clone())
+        .with_scenes(Arc::new(Scenes::new(track_manager.clone(), &config.scenes)))
+        .with_config(reload.clone())
+        .with_diagnostics(Arc::new(Diagnostics::new(
+            log_control,
+            event_log.clone(),
+            reload.clone(),
+            track_manager.clone(),
+            true,
+        )));
+    let ui_state = web_server.state();
+    let _web_handle = web_server.start_background();
+    
+    tokio::spawn(notify::run(track_manager.clone(), reload.subscribe(), true));
+    tokio::spawn(hooks::run(track_manager.clone(), reload.subscribe(), true));
+    tokio::spawn(stats_log::run(track_manager.clone(), config.stats_log.clone()));
+    tokio::spawn(osc::run(track_manager.clone(), config.osc.clone()));
+    tokio::spawn(mqtt::run(track_manager.clone(), config.mqtt.clone(), true));
+    
+    // The peer runs the same binary on the same port; a hostname is waited for until it resolves
+    let mut peer = Target::spawn(args.positional.first().map_or("127.0.0.1:5000", String::as_str))?;
+    let Some(peer_addr) = peer.resolved(&shutdown).await else {
+        service.stopping();
+        service.stopped();
+        return Ok(());
+    };
+    
+    tracing::info!("Peer: {}", peer_addr);
+    
+    // Created before the receiver binds the port it will share
+    let mut network_sender = MultiTrackSender::new(&config.network, peer_addr)?;
+    
+    let (packet_tx, packet_rx) = bounded::<ReceivedPacket>(4096);
+    let mut receiver = AudioReceiver::new();
+    receiver.set_global_channel(packet_tx);
+    receiver.set_source_filter(SourceFilter::new(&config)?);
+    Recovery::new("Network receiver", config.recovery.network.clone(), shutdown.clone())
+        .run(|| receiver.start(config.network.clone()))
+        .await?;
+    
+    // Send from the receiver's socket; its thread also handles control messages
+    let (Some(socket), Some(control)) = (receiver.socket(), receiver.control()) else {
+        anyhow::bail!("Receiver started without a socket");
+    };
+    network_sender.start_shared(config.network.clone(), &socket, control.clone())?;
+    
+    let udp_port = control.local_port().unwrap_or(config.network.udp_port);
+    ui_state.udp_port.store(udp_port, Ordering::Relaxed);
+    tracing::info!("Duplex stream on port {} started", udp_port);
+    
+    // Received tracks play on the default output unless saved otherwise
+    let default_output = devices.iter()
+        .find(|d| d.is_output && d.is_default)
+        .map(|d| d.id.clone())
+        .unwrap_or_default();
+    
+    // Told to the sender so it can flag tracks that would be resampled or dropped
+    let capabilities = ReceiverCapabilities::for_output(
+        devices.iter().find(|d| d.is_output && d.is_default),
+        config.max_auto_tracks(),
+    );
+    
+    // Local capture: the first saved track, else the default input
+    let track_config = match config.tracks.first() {
+        Some(saved) => Some(TrackConfig { track_id: saved.track_id.or(Some(0)), ..saved.clone() }),
+        None => devices.iter().find(|d| d.is_input && d.is_default).map(|input_device| TrackConfig {
+            track_id: Some(0),
+            name: format!("Talkback - {}", input_device.name),
+            device_id: input_device.id.clone(),
+            bitrate: 64_000,
+            frame_size_ms: 10.0,
+            channels: 2,
+            track_type: TrackType::Voice,
+            ..Default::default()
+        }),
+    };
+    let track_config = track_config.filter(|t| {
+        if t.track_id.is_some_and(|id| id >= remote_base) {
+            tracing::warn!("Local track ID must be below {}; capture disabled", remote_base);
+            return false;
+        }
+        if is_monitor_device(device_name(&t.device_id), device_name(&default_output)) {
+            tracing::warn!("{} records the playback device; capture disabled to avoid a feedback loop", t.device_id);
+            return false;
+        }
+        true
+    });
+    
+    let capture_buffer = create_shared_buffer(RING_BUFFER_CAPACITY);
+    let wakeup = Wakeup::new();
+    capture_buffer.set_wakeup(wakeup.clone());
+    let mut local = match track_config {
+        Some(track_config) => {
+            let device_id = track_config.device_id.clone();
+            let track_id = track_manager.create_track(track_config)?;
+            let mut capture = AudioCapture::new(
+                track_id,
+                &device_id,
+                Some(DEFAULT_SAMPLE_RATE),
+                Some(DEFAULT_CHANNELS),
+                None,
+                capture_buffer.clone(),
+            )?;
+            capture.start()?;
+            let _ = track_manager.start_track(track_id);
+            tracing::info!("Capturing track {} from {}", track_id, device_id);
+            Some((track_id, capture, OpusEncoder::new(OpusConfig { channels: DEFAULT_CHANNELS, ..OpusConfig::voice() })?))
+        }
+        None => {
+            tracing::info!("No local capture; receiving only");
+            None
+        }
+    };
+    
+    let mut guard = config.duplex.loop_guard.then(|| {
+        LoopGuard::new(DEFAULT_SAMPLE_RATE, config.duplex.loop_threshold, config.duplex.loop_max_delay_ms)
+    });
+    let mut sample_buffer: Vec<f32> = Vec::new();
+    let counters = track_manager.counters();
+    let mut paused = false;
+    let mut remote: HashMap<u8, RemoteTrack> = HashMap::with_capacity(config.audio.max_tracks);
+    let mut last_stats_report = Instant::now();
+    let mut last_subscribe: Option<Instant> = None;
+    let mut last_track_info: Option<Instant> = None;
+    let mut live = reload.subscribe();
+    let mut nat = NatTraversal::new(&config.network);
+    
+    service.ready();
+    tracing::info!("Running - press Ctrl+C to stop");
+    
+    while !shutdown.is_triggered() {
+        // Jitter buffer settings from a reloaded config
+        if live.has_changed().unwrap_or(false) {
+            let audio = live.borrow_and_update().audio.clone();
+            for track in remote.values() {
+                track.jitter_buffer.set_policy(audio.jitter_overflow);
+                track.jitter_buffer.set_late_threshold(audio.jitter_late_threshold);
+            }
+        }
+        
+        // Receive: decode into each remote track's jitter buffer
+        while let Ok(packet) = packet_rx.try_recv() {
+            let track_id = remote_base.wrapping_add(packet.track_id);
+            if track_id < remote_base {
+                continue;
+            }
+            if !config.network.subscribe.is_empty() && !config.network.subscribe.contains(&packet.track_id) {
+                continue;
+            }
+            
+            let full = remote.len() >= config.max_auto_tracks();
+            if let Entry::Vacant(entry) = remote.entry(track_id) {
+                if full {
+                    continue;
+                }
+                tracing::info!("New remote track {} detected, managed as {}", packet.track_id, track_id);
+                let channels = if packet.is_stereo { 2 } else { 1 };
+                let frame_size = (DEFAULT_SAMPLE_RATE as f32 * DEFAULT_FRAME_SIZE_MS / 1000.0) as usize;
+                let decoder = match OpusDecoder::new(DEFAULT_SAMPLE_RATE, channels, frame_size) {
+                    Ok(d) => d,
+                    Err(e) => {
+                        tracing::error!("Failed to create decoder for track {}: {}", track_id, e);
+                        continue;
+                    }
+                };
+                
+                let saved = config.tracks.iter().find(|t| t.track_id == Some(track_id));
+                let output = saved.map(|t| t.device_id.clone())
+                    .filter(|id| !id.is_empty())
+                    .unwrap_or_else(|| default_output.clone());
+                let jitter_buffer = Arc::new(JitterBuffer::with_policy(32, 2, live.borrow().audio.jitter_overflow));
+                jitter_buffer.set_late_threshold(live.borrow().audio.jitter_late_threshold);
+                let playback = match NetworkPlayback::with_jitter_buffer(
+                    track_id,
+                    &output,
+                    Some(DEFAULT_SAMPLE_RATE),
+                    Some(channels),
+                    jitter_buffer.clone(),
+                ) {
+                    Ok(mut p) => {
+                        // The loop guard compares capture with what the output played
+                        p.keep_released(guard.is_some());
+                        match p.start() {
+                            Ok(()) => Some(p),
+                            Err(e) => {
+                                tracing::warn!("Failed to start playback for track {}: {}", track_id, e);
+                                None
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        tracing::warn!("Failed to create playback for track {}: {}", track_id, e);
+                        None
+                    }
+                };
+                
+                let created = track_manager.create_track(TrackConfig {
+                    track_id: Some(track_id),
+                    name: saved.map(|t| t.name.clone()).unwrap_or_else(|| format!("Remote {}", packet.track_id)),
+                    device_id: output,
+                    bitrate: DEFAULT_BITRATE,
+                    frame_size_ms: DEFAULT_FRAME_SIZE_MS,
+                    channels,
+                    profile: saved.and_then(|t| t.profile.clone()),
+                    ..Default::default()
+                });
+                if let Err(e) = created {
+                    tracing::warn!("Failed to add track {}: {}", track_id, e);
+                }
+                let _ = track_manager.start_track(track_id);
+                entry.insert(RemoteTrack {
+                    decoder,
+                    playback,
+                    jitter_buffer,
+                    ended: false,
+                });
+            }
+            
+            let Some(state) = remote.get_mut(&track_id) else {
+                continue;
+            };
+            counters.add(track_id, Counter::Packets, 1);
+            counters.add(track_id, Counter::Bytes, packet.payload.len() as u64);
+            track_manager.session().add_bytes(track_id, packet.payload.len());
+            if state.ended {
+                state.ended = false;
+                if let Some(ref playback) = state.playback {
+                    playback.playback().clear_fade();
+                }
+                let _ = track_manager.start_track(track_id);
+            }
+            if state.jitter_buffer.check_duplicate(packet.sequence) {
+                continue;
+            }
+            match state.decoder.decode(&packet.payload) {
+                Ok(samples) => {
+                    counters.add(track_id, Counter::Frames, 1);
+                    let frame = AudioFrame::new(samples, state.decoder.channels(), packet.send_us(), packet.sequence);
+                    if let Some(ref playback) = state.playback {
+                        playback.jitter_buffer().insert(frame);
+                    }
+                }
+                Err(e) => tracing::warn!("Decode error on track {}: {}", track_id, e),
+            }
+        }
+        
+        // Control messages from the peer's sending side, and its subscription to ours
+        while let Some(inbound) = control.try_recv() {
+            if network_sender.handle_control(&inbound) || nat.handle(&inbound).is_some() {
+                continue;
+            }
+            match inbound.message {
+                PeerMessage::StreamPaused { track_id, paused } => {
+                    let _ = track_manager.set_paused(remote_base.wrapping_add(track_id), paused);
+                }
+                PeerMessage::TrackInfo(ref info) => {
+                    let track_id = remote_base.wrapping_add(info.track_id);
+                    let keep_name = config.tracks.iter().any(|t| t.track_id == Some(track_id));
+                    if track_id >= remote_base {
+                        let _ = metadata::apply(&track_manager, track_id, info, keep_name);
+                    }
+                }
+                // The peer reset its encoder (our decoder follows) or its decoder (our encoder follows)
+                PeerMessage::ResetTrack { track_id, by_sender: true } => {
+                    let _ = track_manager.request_reset(remote_base.wrapping_add(track_id), ResetSource::Peer);
+                }
+                PeerMessage::ResetTrack { track_id, by_sender: false } if track_id < remote_base => {
+                    let _ = track_manager.request_reset(track_id, ResetSource::Peer);
+                }
+                PeerMessage::Capabilities(ref capabilities) => {
+                    track_manager.apply_receiver_capabilities(capabilities.clone(), DEFAULT_SAMPLE_RATE, |id| id < remote_base);
+                }
+                PeerMessage::EndOfStream => {
+                    tracing::info!("Peer ended its stream");
+                    for (&track_id, state) in remote.iter_mut() {
+                        let _ = track_manager.stop_track(track_id);
+                        state.ended = true;
+                        if let Some(ref playback) = state.playback {
+                            playback.playback().fade_out(SHUTDOWN_FADE_MS);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        
+        // Keep NAT mappings towards the peer open
+        nat.tick(&control);
+        
+        // Repeat our subscription (the peer's track IDs) so a restarted peer picks it up
+        if last_subscribe.is_none_or(|t| t.elapsed() >= Duration::from_millis(SUBSCRIBE_INTERVAL_MS)) {
+            let _ = network_sender.send_control(&PeerMessage::Subscribe { tracks: config.network.subscribe.clone() });
+            let _ = network_sender.send_control(&PeerMessage::Capabilities(capabilities.clone()));
+            last_subscribe = Some(Instant::now());
+        }
+        
+        // Labels of our own tracks for the peer's UI
+        if last_track_info.is_none_or(|t| t.elapsed() >= Duration::from_millis(TRACK_INFO_INTERVAL_MS)) {
+            for message in metadata::announcements(&track_manager) {
+                if matches!(message, PeerMessage::TrackInfo(ref info) if info.track_id < remote_base) {
+                    let _ = network_sender.send_control(&message);
+                }
+            }
+            last_track_info = Some(Instant::now());
+        }
+        
+        // Playback: note what the output released for the loop guard
+        for (&track_id, state) in remote.iter_mut() {
+            // Garbled track: start the decoder over and resync to the next packet
+            if let Some(source) = track_manager.take_reset(track_id) {
+                tracing::info!("Track {}: decoder and jitter buffer reset", track_id);
+                if let Err(e) = state.decoder.reset() {
+                    tracing::warn!("Failed to reset decoder on track {}: {}", track_id, e);
+                }
+                state.jitter_buffer.reset();
+                if source == ResetSource::Local {
+                    let peer_track = track_id.wrapping_sub(remote_base);
+                    let _ = network_sender.send_control(&PeerMessage::ResetTrack { track_id: peer_track, by_sender: false });
+                }
+            }
+            let Some(ref playback) = state.playback else {
+                continue;
+            };
+            if let Some(track) = track_manager.get_track(track_id) {
+                playback.set_delay_ms(track.config.playout_delay_ms);
+            }
+            let audible = track_manager.should_output(track_id);
+            if config.audio.mute_side == MuteSide::Receiver {
+                playback.playback().set_muted(!audible);
+            }
+            while let Some(frame) = playback.take_released() {
+                if let (Some(guard), true) = (guard.as_mut(), audible) {
+                    guard.played(&frame.samples, frame.channels);
+                }
+            }
+        }
+        
+        // Capture: encode and send the local track
+        if let Some((track_id, ref mut capture, ref mut encoder)) = local {
+            if let Some(e) = capture.check_errors() {
+                tracing::warn!("Capture error on track {}: {}", track_id, e);
+                track_manager.set_alert(track_id, AlertKind::DeviceLost, true);
+            }
+            
+            let frame_size = encoder.samples_per_frame();
+            while let Some(frame) = capture_buffer.try_pop() {
+                sample_buffer.extend_from_slice(&frame.samples);
+            }
+            while sample_buffer.len() >= frame_size {
+                let mut samples: Vec<f32> = sample_buffer.drain(..frame_size).collect();
+                
+                let now_paused = track_manager.is_paused(track_id);
+                if now_paused != paused {
+                    paused = now_paused;
+                    let _ = network_sender.send_control(&PeerMessage::StreamPaused { track_id, paused });
+                }
+                if paused || !network_sender.is_subscribed(track_id) {
+                    continue;
+                }
+                
+                // Capture that follows our own playback goes out as silence
+                if let Some(guard) = guard.as_mut() {
+                    let was_looped = guard.is_looped();
+                    let looped = guard.captured(&samples, DEFAULT_CHANNELS);
+                    if looped != was_looped {
+                        if looped {
+                            tracing::warn!("Track {} is picking up received audio; muting capture", track_id);
+                        }
+                        track_manager.notify_alert(track_id, AlertKind::Feedback, looped);
+                    }
+                    if looped {
+                        samples.fill(0.0);
+                    }
+                }
+                if config.audio.mute_side == MuteSide::Sender && !track_manager.should_output(track_id) {
+                    samples.fill(0.0);
+                }
+                
+                if let Some(source) = track_manager.take_reset(track_id) {
+                    tracing::info!("Track {}: encoder reset", track_id);
+                    if let Err(e) = encoder.reset() {
+                        tracing::warn!("Failed to reset encoder on track {}: {}", track_id, e);
+                    }
+                    if source == ResetSource::Local {
+                        let _ = network_sender.send_control(&PeerMessage::ResetTrack { track_id, by_sender: true });
+                    }
+                }
+                
+                match encoder.encode(&samples) {
+                    Ok(encoded) => {
+                        counters.add(track_id, Counter::Frames, 1);
+                        counters.add(track_id, Counter::Bytes, encoded.len() as u64);
+                        track_manager.session().add_bytes(track_id, encoded.len());
+                        match network_sender.send_audio(track_id, encoded, encoder.frame_ticks(), DEFAULT_CHANNELS == 2) {
+                            Ok(_) => counters.add(track_id, Counter::Packets, 1),
+                            Err(e) => tracing::warn!("Failed to send packet: {}", e),
+                        }
+                    }
+                    Err(e) => tracing::warn!("Encoding failed: {}", e),
+                }
+            }
+        }
+        
+        // Per-track counters for the stats API
+        if last_stats_report.elapsed() >= Duration::from_secs(1) {
+            last_stats_report = Instant::now();
+            if let Some((track_id, _, _)) = local {
+                counters.store(track_id, Counter::Overflows, capture_buffer.overflow_count() as u64);
+                track_manager.sample_stats(track_id);
+            }
+            for (&track_id, state) in remote.iter() {
+                // The jitter buffer keeps its own running totals
+                if let Some(jitter_stats) = state.playback.as_ref().map(|p| p.jitter_stats()) {
+                    counters.store(track_id, Counter::PacketsLost, jitter_stats.lost as u64);
+                    counters.store(track_id, Counter::LatePackets, jitter_stats.late as u64);
+                    counters.store(track_id, Counter::DuplicatePackets, jitter_stats.duplicates as u64);
+                    counters.store(track_id, Counter::ReorderedPackets, jitter_stats.reordered as u64);
+                    counters.set(track_id, Gauge::JitterLevel, Some(jitter_stats.level as u64));
+                    counters.set(track_id, Gauge::JitterCapacity, Some(jitter_stats.capacity as u64));
+                }
+                track_manager.report_stats(track_id, TrackCounters {
+                    jitter_occupancy: state.jitter_buffer.take_occupancy(),
+                    ..counters.snapshot(track_id)
+                });
+            }
+        }
+        
+        // Sleep until audio is captured or a packet or message arrives
+        let inbound = control.inbound();
+        tokio::task::block_in_place(|| {
+            Wait::new()
+                .wakeup(&wakeup)
+                .channel(&packet_rx)
+                .channel(&inbound)
+                .until(Duration::from_millis(LOOP_IDLE_MS))
+        });
+    }
+    
+    // Stop capture and end our stream, then fade out what is playing
+    service.stopping();
+    if let Some((track_id, ref mut capture, _)) = local {
+        capture.stop();
+        let _ = track_manager.stop_track(track_id);
+    }
+    network_sender.finish();
+    for state in remote.values() {
+        if let Some(ref playback) = state.playback {
+            playback.playback().fade_out(SHUTDOWN_FADE_MS);
+        }
+    }
+    tokio::time::sleep(Duration::from_millis(SHUTDOWN_FADE_MS as u64 + 20)).await;
+    for state in remote.values_mut() {
+        if let Some(ref mut playback) = state.playback {
+            playback.stop();
+        }
+    }
+    receiver.stop();
+    tracing::info!("Duplex stopped");
+    service.stopped();
+    
+    Ok(())
+}
@@ -0,0 +1,196 @@
+//! Glass-to-glass latency benchmark
+//!
+//! Plays a click every `--interval-ms` out of one device and listens for it
+//! on another. Route the output into the sender's capture device (e.g. a
+//! virtual cable) and the receiver's playback back into the input (a
+//! loopback cable or the playback device's monitor) and each click's
+//! latency covers the whole chain: capture, encode, network, jitter buffer,
+//! decode, playback and both ends' device buffers.
+//!
+//! ```text
+//! latency-bench [--output output:<name>] [--input input:<name>]
+//!               [--count N] [--interval-ms N] [--threshold X] [--json]
+//! ```
+//!
+//! Play and capture times come from the devices' own stream timestamps, so
+//! callback scheduling does not show up in the numbers. The interval must
+//! be longer than the latency being measured.
+
+use anyhow::{bail, Context, Result};
+use cpal::traits::{DeviceTrait, StreamTrait};
+use cpal::{StreamConfig, StreamInstant};
+use crossbeam_channel::unbounded;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use lan_audio_streamer::audio::{
+    click::{ClickTrain, LatencyReport, OnsetDetector},
+    device::{get_default_input_device, get_default_output_device},
+    get_device_by_id, AudioDevice,
+};
+
+/// Benchmark settings from the command line
+struct BenchArgs {
+    output: Option<String>,
+    input: Option<String>,
+    count: u64,
+    interval_ms: u32,
+    threshold: f32,
+    json: bool,
+}
+
+impl BenchArgs {
+    fn parse(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let mut parsed = Self {
+            output: None,
+            input: None,
+            count: 50,
+            interval_ms: 500,
+            threshold: 0.2,
+            json: false,
+        };
+        let mut args = args.into_iter().skip(1);
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().with_context(|| format!("{} needs a value", arg));
+            match arg.as_str() {
+                "--output" => parsed.output = Some(value()?),
+                "--input" => parsed.input = Some(value()?),
+                "--count" => parsed.count = value()?.parse().context("--count")?,
+                "--interval-ms" => parsed.interval_ms = value()?.parse().context("--interval-ms")?,
+                "--threshold" => parsed.threshold = value()?.parse().context("--threshold")?,
+                "--json" => parsed.json = true,
+                other => bail!("unknown argument {}", other),
+            }
+        }
+        if parsed.count == 0 {
+            bail!("--count must be at least 1");
+        }
+        Ok(parsed)
+    }
+}
+
+/// `instant` plus `frames` at `sample_rate`
+fn offset(instant: StreamInstant, frames: usize, sample_rate: u32) -> Option<StreamInstant> {
+    instant.add(Duration::from_micros(frames as u64 * 1_000_000 / sample_rate as u64))
+}
+
+fn open(id: Option<&str>, is_input: bool) -> Result<AudioDevice> {
+    let device = match id {
+        Some(id) => get_device_by_id(id),
+        None if is_input => get_default_input_device(),
+        None => get_default_output_device(),
+    };
+    Ok(device?)
+}
+
+fn main() -> Result<()> {
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::new(
+            std::env::var("RUST_LOG").unwrap_or_else(|_| "warn".into()),
+        ))
+        .init();
+    let args = BenchArgs::parse(std::env::args())?;
+
+    let output = open(args.output.as_deref(), false)?;
+    let input = open(args.input.as_deref(), true)?;
+    let output_config: StreamConfig = output.default_output_config()?.into();
+    let input_config: StreamConfig = input.default_input_config()?.into();
+    let output_rate = output_config.sample_rate.0;
+    let input_rate = input_config.sample_rate.0;
+    let input_channels = input_config.channels;
+
+    // Play times of clicks and capture times of onsets, in stream time
+    let (played_tx, played_rx) = unbounded::<StreamInstant>();
+    let (heard_tx, heard_rx) = unbounded::<StreamInstant>();
+
+    let mut train = ClickTrain::new(output_rate, output_config.channels, args.interval_ms);
+    let mut remaining = args.count;
+    let output_stream = output.into_inner().build_output_stream(
+        &output_config,
+        move |data: &mut [f32], info: &cpal::OutputCallbackInfo| {
+            if remaining == 0 {
+                data.fill(0.0);
+                return;
+            }
+            if let Some(frame) = train.fill(data) {
+                remaining -= 1;
+                if let Some(at) = offset(info.timestamp().playback, frame, output_rate) {
+                    let _ = played_tx.send(at);
+                }
+            }
+        },
+        |err| tracing::error!("Output stream error: {}", err),
+        None,
+    )?;
+
+    let mut detector = OnsetDetector::new(input_rate, args.threshold, args.interval_ms / 2);
+    let input_stream = input.into_inner().build_input_stream(
+        &input_config,
+        move |data: &[f32], info: &cpal::InputCallbackInfo| {
+            if let Some(frame) = detector.process(data, input_channels) {
+                if let Some(at) = offset(info.timestamp().capture, frame, input_rate) {
+                    let _ = heard_tx.send(at);
+                }
+            }
+        },
+        |err| tracing::error!("Input stream error: {}", err),
+        None,
+    )?;
+
+    input_stream.play()?;
+    output_stream.play()?;
+    if !args.json {
+        println!("Playing {} clicks every {} ms...", args.count, args.interval_ms);
+    }
+
+    // Each onset belongs to the latest click played less than an interval before it
+    let interval = Duration::from_millis(args.interval_ms as u64);
+    let deadline = Instant::now() + interval * (args.count as u32 + 2);
+    let mut pending: VecDeque<StreamInstant> = VecDeque::new();
+    let mut sent = 0;
+    let mut latencies_us = Vec::new();
+    while Instant::now() < deadline {
+        let Ok(heard) = heard_rx.recv_timeout(Duration::from_millis(50)) else {
+            continue;
+        };
+        for played in played_rx.try_iter() {
+            pending.push_back(played);
+            sent += 1;
+        }
+        // Clicks too old for this onset were lost
+        while pending.front().is_some_and(|played| heard.duration_since(played).is_some_and(|d| d >= interval)) {
+            pending.pop_front();
+        }
+        // Before any click it is noise
+        if let Some(latency) = pending.front().and_then(|played| heard.duration_since(played)) {
+            pending.pop_front();
+            latencies_us.push(latency.as_micros() as u64);
+            if !args.json {
+                println!("click {:>4}: {:>7.2} ms", latencies_us.len(), latency.as_secs_f64() * 1000.0);
+            }
+        }
+    }
+    sent += played_rx.try_iter().count() as u64;
+    drop(output_stream);
+    drop(input_stream);
+
+    let report = LatencyReport::new(sent, &latencies_us);
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+    let ms = |us: u64| us as f64 / 1000.0;
+    println!("sent {}, received {}, lost {}", report.sent, report.received, report.lost);
+    if report.received > 0 {
+        println!(
+            "min {:.2} ms, mean {:.2} ms, p50 {:.2} ms, p95 {:.2} ms, p99 {:.2} ms, max {:.2} ms",
+            ms(report.min_us),
+            ms(report.mean_us),
+            ms(report.p50_us),
+            ms(report.p95_us),
+            ms(report.p99_us),
+            ms(report.max_us),
+        );
+    }
+    Ok(())
+}
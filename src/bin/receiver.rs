@@ -4,76 +4,224 @@
 
 use anyhow::Result;
 use crossbeam_channel::bounded;
-use std::collections::HashMap;
+use parking_lot::Mutex;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
-use std::time::Duration;
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use std::time::{Duration, Instant};
 
 use lan_audio_streamer::{
     audio::{
-        buffer::{AudioFrame, JitterBuffer},
-        device::list_devices,
-        playback::NetworkPlayback,
+        buffer::{create_shared_buffer, AudioFrame, JitterBuffer, SharedJitterBuffer},
+        comfort::ComfortNoise,
+        custom::SinkDriver,
+        device::{default_device_id, get_device_by_id, list_devices},
+        device_id,
+        migration::DeviceMigration,
+        mixer::Mixer,
+        monitor::SignalMonitor,
+        playback::{AudioPlayback, NetworkPlayback},
     },
     codec::OpusDecoder,
-    config::{AppConfig},
+    config::{AppConfig, ClockSource, MuteSide},
+    dsp::{ProcessorChain, ProcessorConfig},
     constants::*,
-    network::receiver::{AudioReceiver, ReceivedPacket},
-    protocol::TrackConfig,
-    tracks::TrackManager,
+    events::EventLog,
+    integrations::VirtualDeviceCheck,
+    network::{metadata, mqtt, nat::NatTraversal, ogg_stream, osc, receiver::{AudioReceiver, ReceivedPacket}, talkback::TalkbackTarget, LinkedControl, OggStreamer, SourceFilter, Talkback},
+    diagnostics::{self, Diagnostics},
+    hooks,
+    notify,
+    stats_log,
+    protocol::{AlertKind, HealthSignal, PeerMessage, ProtocolFeatures, ReceiverCapabilities, TrackConfig},
+    recovery::Recovery,
+    reload::ConfigReload,
+    selftest,
+    service::{Service, ServiceArgs},
+    shutdown::ShutdownCoordinator,
+    sync::{now_us, ClockSync, SyncGroup},
+    tracks::{stats::Histogram, Counter, Gauge, LatencyRecorder, ResetSource, Stage, Scenes, TrackCounters, TrackManager},
     ui::WebServer,
+    wake::Wait,
 };
+#[cfg(feature = "webrtc")]
+use lan_audio_streamer::network::webrtc::{self, WebRtcPublisher};
+
+/// Larger sequence steps are treated as a stream restart, not loss
+const MAX_SEQUENCE_JUMP: u32 = 1000;
+
+/// Playback ID used by the mixdown bus
+const MIX_BUS_ID: u8 = u8::MAX;
 
 /// Per-track receiver state
 struct TrackState {
     decoder: OpusDecoder,
-    jitter_buffer: JitterBuffer,
+    /// Shared with the playback, which releases from it
+    jitter_buffer: SharedJitterBuffer,
     playback: Option<NetworkPlayback>,
-    packets_received: u64,
+    /// Custom sink from the track manager, playing instead of the device
+    sink: Option<SinkDriver>,
     packets_lost: u64,
+    /// Sender paused the stream; silence is expected
+    paused: bool,
+    /// Sender ended the stream; playback is fading out
+    ended: bool,
+    /// Last measured sender-to-receiver transit time (µs)
+    transit_us: i64,
+    /// Configuration the playback's processing chain was built from
+    processors: Option<Vec<ProcessorConfig>>,
+    /// Sender is in DTX; fill the gap with comfort noise
+    dtx: bool,
+    comfort: ComfortNoise,
+    /// Clipping/silence detection on the released audio
+    monitor: Option<SignalMonitor>,
+    /// When the last packet arrived
+    last_packet: Instant,
+    /// Linked sender session the duplicate check is keyed to
+    session: Option<u64>,
+    /// Jitter buffer (received, lost) at the last stats interval
+    loss_mark: (usize, usize),
+    /// Playback underruns at the last stats interval
+    underrun_mark: u32,
+    /// Last in-order packet: sequence, local arrival and stream position (µs)
+    last_arrival: Option<(u32, u64, u64)>,
+    /// Inter-arrival jitter (µs) and loss gap length (packets)
+    arrival_jitter: Histogram,
+    gap_lengths: Histogram,
+    /// Packet path stages since the last stats interval
+    latency: LatencyRecorder,
+    /// Restarts of the output device after errors
+    recovery: Recovery,
+    /// Fallback output while the device is missing
+    migration: DeviceMigration,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     // Initialize logging
-    tracing_subscriber::registry()
-        .with(tracing_subscriber::EnvFilter::new(
-            std::env::var("RUST_LOG").unwrap_or_else(|_| "info".into()),
-        ))
-        .with(tracing_subscriber::fmt::layer())
-        .init();
+    let args = ServiceArgs::parse(std::env::args());
+    let event_log = Arc::new(EventLog::new(EVENT_LOG_CAPACITY));
+    let log_control = Arc::new(diagnostics::init_logging(event_log.clone(), !args.service));
     
     tracing::info!("Starting LAN Audio Receiver");
     
-    // Load or create config
-    let config = AppConfig::default();
+    // Load the saved config, if any
+    let config = AppConfig::load_or_default(args.config_path.as_ref())?;
+    device_id::set_aliases(&config.device_aliases);
+    
+    // Check devices, codec and ports, then exit
+    if args.self_test {
+        let report = selftest::run(&config, false);
+        println!("{}", report);
+        std::process::exit(if report.passed() { 0 } else { 1 });
+    }
     
-    // List available output devices
-    println!("\n=== Available Output Devices ===");
+    // List available output devices (services have no console to print to)
     let devices = list_devices();
-    for device in &devices {
-        if device.is_output {
-            let default_marker = if device.is_default { " [DEFAULT]" } else { "" };
-            println!("  {}{}:", device.name, default_marker);
-            println!("    ID: {}", device.id);
-            println!("    Sample rates: {:?}", device.sample_rates);
-            println!("    Channels: {:?}", device.channels);
+    if !args.service {
+        println!("\n=== Available Output Devices ===");
+        for device in &devices {
+            if device.is_output {
+                let default_marker = if device.is_default { " [DEFAULT]" } else { "" };
+                println!("  {}{}:", device.name, default_marker);
+                println!("    ID: {}", device.id);
+                println!("    Sample rates: {:?}", device.sample_rates);
+                println!("    Channels: {:?}", device.channels);
+            }
         }
+        println!();
     }
-    println!();
+    
+    // Tracks mapped onto virtual cables by name, and enough cables for them
+    let virtual_devices = VirtualDeviceCheck::run(&config.virtual_devices, &devices);
+    for (track_id, device) in &virtual_devices.tracks {
+        tracing::info!("Track {} plays into {} ({})", track_id, device.name, device.kind.label());
+    }
+    for line in virtual_devices.guidance() {
+        tracing::warn!("{}", line);
+    }
+    
+    // Ctrl+C or a service stop fades playback out before exit
+    let shutdown = ShutdownCoordinator::new();
+    shutdown.listen_for_ctrl_c();
+    let service = Service::start("lan-audio-receiver", args.service, &shutdown);
     
     // Create track manager
-    let track_manager = Arc::new(TrackManager::new());
+    let track_manager = Arc::new(TrackManager::with_max_tracks(config.audio.max_tracks));
+    track_manager.health().configure(&config.health);
+    track_manager.set_profiles(config.profiles.clone());
+    track_manager.set_bandwidth_cap(config.alerts.bandwidth_cap_kbps);
+    
+    // Safe config changes apply without a restart
+    let reload = Arc::new(ConfigReload::start(
+        AppConfig::path_in_use(args.config_path.as_ref()),
+        config.clone(),
+        track_manager.clone(),
+    ));
     
     // Start web UI
     let web_server = WebServer::new(
         config.ui.clone(),
         track_manager.clone(),
         false, // is_receiver
-    );
+    )
+    .with_event_log(event_log.clone())
+    .with_scenes(Arc::new(Scenes::new(track_manager.clone(), &config.scenes)))
+    .with_config(reload.clone())
+    .with_diagnostics(Arc::new(Diagnostics::new(
+        log_control,
+        event_log.clone(),
+        reload.clone(),
+        track_manager.clone(),
+        false,
+    )));
+    
+    // Sender track changes from this UI
+    let linked = Arc::new(LinkedControl::new());
+    let web_server = if config.linked_control.enabled {
+        web_server.with_linked_control(linked.clone())
+    } else {
+        web_server
+    };
+    
+    // Optional browser output
+    #[cfg(feature = "webrtc")]
+    let webrtc_publisher = if config.webrtc.enabled {
+        Some(Arc::new(WebRtcPublisher::new(config.webrtc.clone(), track_manager.clone())?))
+    } else {
+        None
+    };
+    #[cfg(feature = "webrtc")]
+    let web_server = match webrtc_publisher {
+        Some(ref publisher) => web_server.merge(webrtc::routes(publisher.clone())),
+        None => web_server,
+    };
+    
+    // Optional Ogg Opus streams for LAN players and Icecast
+    let mix_frame_size = (DEFAULT_SAMPLE_RATE as f32 * DEFAULT_FRAME_SIZE_MS / 1000.0) as usize;
+    let ogg_streamer = if config.http_stream.enabled {
+        Some(Arc::new(OggStreamer::new(config.http_stream.clone(), mix_frame_size)?))
+    } else {
+        None
+    };
+    let web_server = match ogg_streamer {
+        Some(ref streamer) => web_server.merge(ogg_stream::routes(streamer.clone())),
+        None => web_server,
+    };
+    
+    let ui_state = web_server.state();
     let _web_handle = web_server.start_background();
+    if let (Some(ref streamer), Some(icecast)) = (&ogg_streamer, config.http_stream.icecast.clone()) {
+        tokio::spawn(ogg_stream::run_icecast(streamer.clone(), icecast));
+    }
     
-    tracing::info!("Web UI available at http://{}:{}", config.ui.bind_address, config.ui.http_port);
+    tokio::spawn(notify::run(track_manager.clone(), reload.subscribe(), false));
+    tokio::spawn(hooks::run(track_manager.clone(), reload.subscribe(), false));
+    tokio::spawn(stats_log::run(track_manager.clone(), config.stats_log.clone()));
+    tokio::spawn(osc::run(track_manager.clone(), config.osc.clone()));
+    tokio::spawn(mqtt::run(track_manager.clone(), config.mqtt.clone(), false));
     
     // Create packet receiver channel
     let (packet_tx, packet_rx) = bounded::<ReceivedPacket>(4096);
@@ -81,12 +229,69 @@ async fn main() -> Result<()> {
     // Create and start network receiver
     let mut receiver = AudioReceiver::new();
     receiver.set_global_channel(packet_tx);
-    receiver.start(config.network.clone())?;
+    receiver.set_source_filter(SourceFilter::new(&config)?);
+    if !config.access.allowed_sources.is_empty() {
+        tracing::info!("Accepting senders from {:?}", config.access.allowed_sources);
+    }
+    Recovery::new("Network receiver", config.recovery.network.clone(), shutdown.clone())
+        .run(|| receiver.start(config.network.clone()))
+        .await?;
+    
+    let udp_port = receiver.control().and_then(|c| c.local_port()).unwrap_or(config.network.udp_port);
+    ui_state.udp_port.store(udp_port, Ordering::Relaxed);
+    tracing::info!("Network receiver started on port {}", udp_port);
+    
+    // Push-to-talk microphone back to the sender, from the same socket
+    let talkback_target = TalkbackTarget::default();
+    let mut talkback = match (config.talkback.enabled, receiver.socket()) {
+        (true, Some(socket)) => {
+            match Talkback::start_capture(&config.talkback, socket, talkback_target.clone(), track_manager.clone()) {
+                Ok(talkback) => {
+                    track_manager.set_talkback_available(true);
+                    Some(talkback)
+                }
+                Err(e) => {
+                    tracing::warn!("Talkback unavailable: {}", e);
+                    None
+                }
+            }
+        }
+        _ => None,
+    };
     
-    tracing::info!("Network receiver started on port {}", config.network.udp_port);
+    // Clock synchronization with the sender over the control channel
+    let control = receiver.control();
+    let mut clock_sync = ClockSync::new(config.sync.clock_source, config.sync.window);
+    let mut sender_addr: Option<SocketAddr> = None;
+    let mut last_clock_request = Instant::now();
+    let mut last_subscribe: Option<Instant> = None;
+    if !config.network.subscribe.is_empty() {
+        tracing::info!("Subscribing to tracks {:?}", config.network.subscribe);
+    }
+    
+    // Optional playout alignment across a group of tracks
+    // (shared with the output callbacks, which align frames as they release them)
+    let sync_group = if config.sync.align_tracks.is_empty() {
+        None
+    } else {
+        tracing::info!("Aligning playout of tracks {:?}", config.sync.align_tracks);
+        Some(Arc::new(Mutex::new(SyncGroup::new(
+            &config.sync.align_tracks,
+            DEFAULT_SAMPLE_RATE,
+            config.sync.align_tolerance_us,
+            config.sync.align_max_step,
+        ))))
+    };
     
-    // Track states
-    let mut track_states: HashMap<u8, TrackState> = HashMap::new();
+    // Track states, sized for every track the config allows
+    let max_tracks = track_manager.max_tracks();
+    let mut track_states: HashMap<u8, TrackState> = HashMap::with_capacity(max_tracks);
+    // Tracks turned away by the auto-create cap or the track limit (logged once)
+    let mut refused_tracks: HashSet<u8> = HashSet::new();
+    // Jitter buffers allocated up front, so new tracks don't allocate on the receive path
+    let mut spare_jitter: Vec<Arc<JitterBuffer>> = (0..config.max_auto_tracks())
+        .map(|_| Arc::new(JitterBuffer::with_policy(32, 2, config.audio.jitter_overflow)))
+        .collect();
     
     // Get default output device
     let default_output = devices.iter()
@@ -94,19 +299,125 @@ async fn main() -> Result<()> {
         .map(|d| d.id.clone())
         .unwrap_or_default();
     
+    // Told to the sender so it can flag tracks that would be resampled or dropped
+    let capabilities = ReceiverCapabilities::for_output(
+        devices.iter().find(|d| d.is_output && d.is_default),
+        config.max_auto_tracks(),
+    );
+    
     tracing::info!("Default output device: {}", default_output);
+    
+    // Optional mixdown of all (or selected) tracks to one device
+    let mix_buffer = create_shared_buffer(RING_BUFFER_CAPACITY);
+    let mut mix_sequence: u32 = 0;
+    let mut mix_playback = if config.mix.enabled {
+        let mix_device = config.mix.device_id.clone().unwrap_or_else(|| default_output.clone());
+        match AudioPlayback::new(
+            MIX_BUS_ID,
+            &mix_device,
+            Some(DEFAULT_SAMPLE_RATE),
+            Some(DEFAULT_CHANNELS),
+            None,
+            mix_buffer.clone(),
+        ) {
+            Ok(mut playback) => match playback.start() {
+                Ok(()) => {
+                    tracing::info!("Mixdown bus playing on {}", mix_device);
+                    Some(playback)
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to start mixdown bus: {}", e);
+                    None
+                }
+            },
+            Err(e) => {
+                tracing::warn!("Failed to create mixdown bus: {}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+    // The mix stream listens to the mixdown too
+    let mut mixer = (mix_playback.is_some() || ogg_streamer.is_some())
+        .then(|| Mixer::new(DEFAULT_CHANNELS, mix_frame_size, &config.mix.tracks));
     tracing::info!("Waiting for audio streams...");
+    service.ready();
     
     // Main receiving loop
-    let mut last_stats_time = std::time::Instant::now();
+    let mut last_stats_time = Instant::now();
+    let mut last_stats_report = Instant::now();
+    let counters = track_manager.counters();
+    let mut live = reload.subscribe();
+    let mut nat = NatTraversal::new(&config.network);
     
-    loop {
+    while !shutdown.is_triggered() {
+        // Jitter buffer settings from a reloaded config
+        if live.has_changed().unwrap_or(false) {
+            let audio = live.borrow_and_update().audio.clone();
+            for state in track_states.values() {
+                state.jitter_buffer.set_policy(audio.jitter_overflow);
+                state.jitter_buffer.set_late_threshold(audio.jitter_late_threshold);
+            }
+        }
+        
         // Process received packets
         while let Ok(packet) = packet_rx.try_recv() {
             let track_id = packet.track_id;
             
+            // Control, clock sync and talkback go back to whoever is sending,
+            // on its first link when it sends over two
+            let source = control.as_ref().map_or(packet.source, |control| control.primary_link(packet.source));
+            if sender_addr != Some(source) {
+                *talkback_target.lock() = Some(source);
+                last_subscribe = None;
+                if let Some(ref control) = control {
+                    linked.attach(control.clone(), source);
+                }
+            }
+            sender_addr = Some(source);
+            
+            // Until the sender applies the subscription (or if it predates them)
+            if !config.network.subscribe.is_empty() && !config.network.subscribe.contains(&track_id) {
+                continue;
+            }
+            
+            // Simulcast layers only go to browsers; playback decodes the primary encoding
+            if packet.layer != 0 {
+                #[cfg(feature = "webrtc")]
+                if let Some(ref publisher) = webrtc_publisher {
+                    publisher.publish(track_id, packet.layer, packet.payload.clone()).await;
+                }
+                continue;
+            }
+            
+            if !track_states.contains_key(&track_id) && track_states.len() >= max_tracks {
+                if refused_tracks.insert(track_id) {
+                    tracing::warn!(
+                        "Ignoring track {} from {}: audio.max_tracks ({}) tracks already playing",
+                        track_id,
+                        packet.source,
+                        max_tracks
+                    );
+                }
+                continue;
+            }
+            
+            // Don't let a misbehaving sender open unbounded decoders and devices
+            if !track_states.contains_key(&track_id) && track_states.len() >= config.max_auto_tracks() {
+                if refused_tracks.insert(track_id) {
+                    tracing::warn!(
+                        "Ignoring track {} from {}: auto-create limit of {} reached",
+                        track_id,
+                        packet.source,
+                        config.max_auto_tracks()
+                    );
+                }
+                continue;
+            }
+            
             // Initialize track state if new
-            if !track_states.contains_key(&track_id) {
+            if let Entry::Vacant(entry) = track_states.entry(track_id) {
                 tracing::info!("New track {} detected, initializing...", track_id);
                 
                 // Determine channel count from packet
@@ -122,25 +433,36 @@ async fn main() -> Result<()> {
                     }
                 };
                 
-                // Create jitter buffer (32 slots, 2 frame minimum delay)
-                let jitter_buffer = JitterBuffer::new(32, 2);
+                // Saved tracks keep their name and output device
+                let saved = config.tracks.iter().find(|t| t.track_id == Some(track_id));
+                let output = saved.map(|t| t.device_id.clone())
+                    .filter(|id| !id.is_empty())
+                    .or_else(|| virtual_devices.device_for(track_id).map(str::to_string))
+                    .unwrap_or_else(|| default_output.clone());
+                
+                // Jitter buffer (32 slots, 2 frame minimum delay), from the spares while they last
+                let jitter_buffer = spare_jitter.pop()
+                    .unwrap_or_else(|| Arc::new(JitterBuffer::with_policy(32, 2, live.borrow().audio.jitter_overflow)));
+                jitter_buffer.set_policy(live.borrow().audio.jitter_overflow);
+                jitter_buffer.set_late_threshold(live.borrow().audio.jitter_late_threshold);
                 
                 // Create playback (optional - may not have output device)
-                let playback = if !default_output.is_empty() {
-                    match NetworkPlayback::new(
+                let playback = if !output.is_empty() {
+                    match NetworkPlayback::with_jitter_buffer(
                         track_id,
-                        &default_output,
+                        &output,
                         Some(DEFAULT_SAMPLE_RATE),
                         Some(channels),
-                        32, // jitter buffer size
-                        2,  // min delay
+                        jitter_buffer.clone(),
                     ) {
                         Ok(mut p) => {
+                            // The signal monitor and the mixdown bus see what the output played
+                            p.keep_released(config.alerts.enabled || mixer.is_some());
                             if let Err(e) = p.start() {
                                 tracing::warn!("Failed to start playback for track {}: {}", track_id, e);
                                 None
                             } else {
-                                tracing::info!("Started playback for track {} on {}", track_id, default_output);
+                                tracing::info!("Started playback for track {} on {}", track_id, output);
                                 Some(p)
                             }
                         }
@@ -156,46 +478,141 @@ async fn main() -> Result<()> {
                 // Create track in manager
                 let track_config = TrackConfig {
                     track_id: Some(track_id),
-                    name: format!("Track {}", track_id),
-                    device_id: default_output.clone(),
+                    name: saved.map(|t| t.name.clone()).unwrap_or_else(|| format!("Track {}", track_id)),
+                    device_id: output,
                     bitrate: DEFAULT_BITRATE,
                     frame_size_ms: DEFAULT_FRAME_SIZE_MS,
                     channels,
+                    profile: saved.and_then(|t| t.profile.clone()),
+                    fallback_device: saved.and_then(|t| t.fallback_device.clone()),
                     ..Default::default()
                 };
-                let _ = track_manager.create_track(track_config);
+                let migration = DeviceMigration::new(track_config.fallback_device.clone());
+                if let Err(e) = track_manager.create_track(track_config) {
+                    tracing::warn!("Failed to add track {}: {}", track_id, e);
+                }
+                let _ = track_manager.start_track(track_id);
                 
-                track_states.insert(track_id, TrackState {
+                entry.insert(TrackState {
                     decoder,
                     jitter_buffer,
                     playback,
-                    packets_received: 0,
+                    sink: None,
                     packets_lost: 0,
+                    paused: false,
+                    ended: false,
+                    transit_us: 0,
+                    processors: None,
+                    dtx: false,
+                    comfort: ComfortNoise::new(channels),
+                    monitor: config.alerts.enabled
+                        .then(|| SignalMonitor::new(DEFAULT_SAMPLE_RATE, &config.alerts)),
+                    last_packet: Instant::now(),
+                    session: None,
+                    loss_mark: (0, 0),
+                    underrun_mark: 0,
+                    last_arrival: None,
+                    arrival_jitter: Histogram::new(),
+                    gap_lengths: Histogram::new(),
+                    latency: LatencyRecorder::new(),
+                    recovery: Recovery::new(
+                        format!("Track {} playback", track_id),
+                        config.recovery.playback.clone(),
+                        shutdown.clone(),
+                    ),
+                    migration,
                 });
             }
             
+            // Forward to browsers before decoding
+            #[cfg(feature = "webrtc")]
+            if let Some(ref publisher) = webrtc_publisher {
+                publisher.publish(track_id, 0, packet.payload.clone()).await;
+            }
+            
             // Process packet
             if let Some(state) = track_states.get_mut(&track_id) {
-                state.packets_received += 1;
+                counters.add(track_id, Counter::Packets, 1);
+                counters.add(track_id, Counter::Bytes, packet.payload.len() as u64);
+                state.last_packet = Instant::now();
+                
+                // A new sender session numbers its packets afresh
+                let session = control.as_ref().and_then(|control| control.link_session(packet.source));
+                if let Some(session) = session.filter(|&s| state.session != Some(s)) {
+                    if state.session.replace(session).is_some() {
+                        state.jitter_buffer.reset();
+                    }
+                }
+                
+                // A second copy of a packet is counted but never decoded
+                if state.jitter_buffer.check_duplicate(packet.sequence) {
+                    continue;
+                }
+                
+                // Stream listeners get the packet as received
+                if let Some(ref streamer) = ogg_streamer {
+                    streamer.publish(track_id, packet.payload.clone());
+                }
+                
+                // Arrival spacing against send spacing, and how many packets each gap swallowed
+                let arrival = packet.arrival_us;
+                let step = state.last_arrival.map(|(last_seq, _, _)| packet.sequence.wrapping_sub(last_seq));
+                let reordered = step.is_some_and(|s| s == 0 || s > u32::MAX - MAX_SEQUENCE_JUMP);
+                if !reordered {
+                    if let (Some(step), Some((_, last_arrival, last_ts))) = (step, state.last_arrival) {
+                        if step < MAX_SEQUENCE_JUMP {
+                            if step > 1 {
+                                state.gap_lengths.record(step as u64 - 1);
+                            }
+                            let spacing = arrival as i64 - last_arrival as i64;
+                            let sent = packet.stream_us() as i64 - last_ts as i64;
+                            state.arrival_jitter.record((spacing - sent).unsigned_abs());
+                        }
+                    }
+                    state.last_arrival = Some((packet.sequence, arrival, packet.stream_us()));
+                }
+                track_manager.session().add_bytes(track_id, packet.payload.len());
+                if track_manager.set_alert(track_id, AlertKind::ReceiverTimeout, false) {
+                    track_manager.session().record_reconnect();
+                }
+                
+                // Sender came back after ending the stream
+                if state.ended {
+                    track_manager.session().record_reconnect();
+                    state.ended = false;
+                    state.paused = false;
+                    if let Some(ref playback) = state.playback {
+                        playback.playback().clear_fade();
+                    }
+                    let _ = track_manager.start_track(track_id);
+                }
+                if let Some(sent) = packet.sender_us.filter(|_| clock_sync.is_synchronized()) {
+                    state.transit_us = packet.arrival_us as i64 - clock_sync.remote_to_local(sent) as i64;
+                    state.latency.record(Stage::Network, Duration::from_micros(state.transit_us.max(0) as u64));
+                    counters.set(track_id, Gauge::TransitUs, Some(state.transit_us.max(0) as u64));
+                }
+                state.latency.record(Stage::Receive, packet.receive_time.elapsed());
                 
                 // Decode audio
-                match state.decoder.decode(&packet.payload) {
+                let decoded = state.latency.time(Stage::Decode, track_id, || state.decoder.decode(&packet.payload));
+                match decoded {
                     Ok(samples) => {
+                        counters.add(track_id, Counter::Frames, 1);
+                        state.dtx = packet.is_dtx;
+                        if packet.is_dtx {
+                            state.comfort.set_level_from(&samples);
+                        }
+                        
                         // Create audio frame
                         let frame = AudioFrame::new(
                             samples,
                             state.decoder.channels(),
-                            packet.timestamp,
+                            packet.send_us(),
                             packet.sequence,
                         );
                         
-                        // Insert into jitter buffer
-                        state.jitter_buffer.insert(frame.clone());
-                        
-                        // Push to playback if available
-                        if let Some(ref playback) = state.playback {
-                            playback.push_frame(frame);
-                        }
+                        // Playback releases from the same buffer
+                        state.jitter_buffer.insert(frame);
                     }
                     Err(e) => {
                         tracing::warn!("Decode error on track {}: {}", track_id, e);
@@ -205,41 +622,468 @@ async fn main() -> Result<()> {
             }
         }
         
+        // Clock exchanges with the sender
+        if let Some(ref control) = control {
+            while let Some(inbound) = control.try_recv() {
+                match inbound.message {
+                    PeerMessage::ClockResponse { t0, t1, t2 } => {
+                        clock_sync.add_exchange(t0, t1, t2, inbound.receive_time);
+                    }
+                    PeerMessage::StreamPaused { track_id, paused } => {
+                        tracing::info!(
+                            "Sender {} track {}",
+                            if paused { "paused" } else { "resumed" },
+                            track_id
+                        );
+                        let _ = track_manager.set_paused(track_id, paused);
+                        if let Some(state) = track_states.get_mut(&track_id) {
+                            state.paused = paused;
+                            if let Some(ref mut monitor) = state.monitor {
+                                monitor.reset();
+                            }
+                        }
+                    }
+                    PeerMessage::TrackState(_) => {
+                        linked.handle(&inbound);
+                    }
+                    // The sender reset its encoder
+                    PeerMessage::ResetTrack { track_id, by_sender: true } if sender_addr.map(|a| a.ip()) == Some(inbound.from.ip()) => {
+                        let _ = track_manager.request_reset(track_id, ResetSource::Peer);
+                    }
+                    PeerMessage::PeerAddress { .. } => {
+                        nat.handle(&inbound);
+                    }
+                    // Sender's labels, unless this config names the track
+                    PeerMessage::TrackInfo(ref info) if sender_addr.map(|a| a.ip()) == Some(inbound.from.ip()) => {
+                        let keep_name = config.tracks.iter().any(|t| t.track_id == Some(info.track_id));
+                        if let Ok(true) = metadata::apply(&track_manager, info.track_id, info, keep_name) {
+                            tracing::info!("Track {} labelled \"{}\" by the sender", info.track_id, info.name);
+                        }
+                    }
+                    PeerMessage::EndOfStream => {
+                        tracing::info!("Sender ended the stream");
+                        for (&track_id, state) in track_states.iter_mut() {
+                            let _ = track_manager.stop_track(track_id);
+                            state.ended = true;
+                            state.paused = true;
+                            if let Some(ref playback) = state.playback {
+                                playback.playback().fade_out(SHUTDOWN_FADE_MS);
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            
+            // Keep NAT mappings towards remote senders open
+            nat.tick(control);
+            
+            if config.sync.clock_source == ClockSource::Estimated
+                && last_clock_request.elapsed() >= Duration::from_millis(config.sync.interval_ms)
+            {
+                if let Some(addr) = sender_addr {
+                    let _ = control.send(&PeerMessage::ClockRequest { t0: now_us() }, addr);
+                }
+                last_clock_request = Instant::now();
+            }
+            
+            // Repeat the subscription so a restarted sender picks it up
+            if let Some(addr) = sender_addr {
+                if last_subscribe.is_none_or(|t| t.elapsed() >= Duration::from_millis(SUBSCRIBE_INTERVAL_MS)) {
+                    let message = PeerMessage::Subscribe { tracks: config.network.subscribe.clone() };
+                    let _ = control.send(&message, addr);
+                    let _ = control.send(&PeerMessage::Capabilities(capabilities.clone()), addr);
+                    if control.negotiated(addr).features.contains(ProtocolFeatures::SIMULCAST) {
+                        #[cfg(feature = "webrtc")]
+                        let layers = webrtc_publisher.as_ref().map(|p| p.layers()).unwrap_or_default();
+                        #[cfg(not(feature = "webrtc"))]
+                        let layers = Vec::new();
+                        let _ = control.send(&PeerMessage::SubscribeLayers { layers }, addr);
+                    }
+                    last_subscribe = Some(Instant::now());
+                }
+            }
+        }
+        
         // Process jitter buffers and feed playback
-        for (_, state) in &mut track_states {
+        for (&track_id, state) in track_states.iter_mut() {
+            // Per-track trim (with the group's gain) and soft clipping happen in the decoder
+            let gain_db = track_manager.output_gain_db(track_id);
+            if let Some(track) = track_manager.get_track(track_id) {
+                if gain_db != state.decoder.gain_db() {
+                    if let Err(e) = state.decoder.set_gain_db(gain_db) {
+                        tracing::warn!("Failed to set decoder gain on track {}: {}", track_id, e);
+                    }
+                }
+                state.decoder.set_soft_clip(track.config.soft_clip);
+            }
+            
+            // Garbled track: start the decoder over and resync to the next packet
+            if let Some(source) = track_manager.take_reset(track_id) {
+                tracing::info!("Track {}: decoder and jitter buffer reset", track_id);
+                if let Err(e) = state.decoder.reset() {
+                    tracing::warn!("Failed to reset decoder on track {}: {}", track_id, e);
+                }
+                state.jitter_buffer.reset();
+                state.last_arrival = None;
+                if let (ResetSource::Local, Some(control), Some(addr)) = (source, control.as_ref(), sender_addr) {
+                    let _ = control.send(&PeerMessage::ResetTrack { track_id, by_sender: false }, addr);
+                }
+            }
+            
+            // A custom sink set through the track manager takes over from the device
+            if let Some(sink) = track_manager.take_sink(track_id) {
+                if let Some(mut playback) = state.playback.take() {
+                    playback.stop();
+                }
+                if let Some(mut driver) = state.sink.take() {
+                    driver.stop();
+                }
+                let mut driver = SinkDriver::new(track_id, sink, state.jitter_buffer.clone());
+                match driver.start() {
+                    Ok(()) => tracing::info!("Track {} playing to a custom sink", track_id),
+                    Err(e) => tracing::warn!("Failed to start custom sink for track {}: {}", track_id, e),
+                }
+                state.sink = Some(driver);
+            }
+            if let Some(e) = state.sink.as_ref().and_then(|driver| driver.check_errors()) {
+                tracing::warn!("Custom sink error on track {}: {}", track_id, e);
+                track_manager.report_error(track_id, e.to_string());
+            }
+            
+            // Follow output device changes made through the API, staying on a fallback while migrated
+            if let (Some(playback), Some(track)) = (state.playback.as_mut(), track_manager.get_track(track_id)) {
+                let device_id = state.migration.active(&track.config.device_id).unwrap_or(&track.config.device_id).to_string();
+                if device_id != playback.playback().device_id() {
+                    match playback.playback_mut().set_device(&device_id) {
+                        Ok(()) => {
+                            track_manager.set_alert(track_id, AlertKind::DeviceLost, false);
+                        }
+                        Err(e) => {
+                            tracing::warn!("Failed to move track {} to {}: {}", track_id, device_id, e);
+                        }
+                    }
+                }
+            }
+            
+            // Underruns while paused or ended are expected
+            if let Some(ref playback) = state.playback {
+                for xrun in playback.playback().drain_xruns() {
+                    if !state.paused {
+                        track_manager.notify_xrun(xrun);
+                    }
+                }
+            }
+            
+            // Device errors usually mean it was unplugged: carry on on the
+            // fallback device, else restart this one
+            if let Some(e) = state.playback.as_ref().and_then(|p| p.playback().check_errors()) {
+                tracing::warn!("Playback error on track {}: {}", track_id, e);
+                track_manager.health().record(HealthSignal::DeviceError, track_id, 1);
+                track_manager.set_alert(track_id, AlertKind::DeviceLost, true);
+                let moved = match (state.playback.as_mut(), config.audio.migrate_devices) {
+                    (Some(playback), true) => {
+                        let original = playback.playback().device_id().to_string();
+                        state
+                            .migration
+                            .lost(&original, default_device_id(false))
+                            .and_then(|fallback| match playback.playback_mut().set_device(&fallback) {
+                                Ok(()) => Some((original, fallback)),
+                                Err(e) => {
+                                    tracing::warn!("Track {} could not move to its fallback device: {}", track_id, e);
+                                    None
+                                }
+                            })
+                    }
+                    _ => None,
+                };
+                match moved {
+                    Some((original, fallback)) => {
+                        state.migration.moved(&original, &fallback);
+                        track_manager.notify_device_migrated(track_id, &fallback, true);
+                    }
+                    None => {
+                        state.recovery.fail(&e);
+                    }
+                }
+            }
+            
+            // Move back once the original device is present again
+            if let (Some(playback), Some(original)) = (state.playback.as_mut(), state.migration.return_due()) {
+                let original = original.to_string();
+                if get_device_by_id(&original).is_ok() {
+                    match playback.playback_mut().set_device(&original) {
+                        Ok(()) => {
+                            state.migration.returned();
+                            track_manager.notify_device_migrated(track_id, &original, false);
+                            track_manager.set_alert(track_id, AlertKind::DeviceLost, false);
+                        }
+                        Err(e) => {
+                            tracing::debug!("Track {} could not return to {}: {}", track_id, original, e);
+                        }
+                    }
+                }
+            }
+            if state.recovery.due() {
+                if let Some(ref mut playback) = state.playback {
+                    match playback.playback_mut().restart() {
+                        Ok(()) => {
+                            tracing::info!("Track {} playback restarted", track_id);
+                            track_manager.set_alert(track_id, AlertKind::DeviceLost, false);
+                        }
+                        Err(e) => {
+                            state.recovery.fail(&e);
+                        }
+                    }
+                }
+            }
+            
+            // Packets stopped without the sender pausing or ending the stream
+            let timeout = config.alerts.receive_timeout_secs;
+            if timeout > 0
+                && !state.paused
+                && !state.ended
+                && state.last_packet.elapsed() >= Duration::from_secs(timeout as u64)
+            {
+                track_manager.set_alert(track_id, AlertKind::ReceiverTimeout, true);
+            }
+            
             if let Some(ref playback) = state.playback {
-                // Process jitter buffer
-                playback.process();
+                // Apply the configured playout delay and processing (changes live via API);
+                // the chain runs in the output callback as frames leave the jitter buffer
+                if let Some(track) = track_manager.get_track(track_id) {
+                    playback.set_delay_ms(track.config.playout_delay_ms);
+                    if state.processors.as_ref() != Some(&track.config.processors) {
+                        let configs = state.processors.insert(track.config.processors.clone());
+                        let mut chain = ProcessorChain::with_meters(
+                            configs,
+                            DEFAULT_SAMPLE_RATE,
+                            track.meters(),
+                            track_manager.meter_bus(),
+                        );
+                        let group = sync_group.clone().filter(|g| g.lock().contains(track_id));
+                        let jitter = state.jitter_buffer.clone();
+                        playback.set_stage(move |frame| {
+                            chain.process(&mut frame.samples, frame.channels);
+                            if let Some(ref group) = group {
+                                group.lock().align_frame(track_id, frame, now_us(), jitter.len());
+                            }
+                        });
+                    }
+                }
+                
+                // Mute/solo from the track manager
+                if config.audio.mute_side == MuteSide::Receiver {
+                    playback.playback().set_muted(!track_manager.should_output(track_id));
+                }
+                
+                // Frames the output callback released since the last pass
+                while let Some(frame) = playback.take_released() {
+                    // Watch for clipping and dead inputs while the sender is live
+                    if let Some(monitor) = state.monitor.as_mut() {
+                        if !state.paused {
+                            for change in monitor.process(&frame.samples, frame.channels) {
+                                track_manager.notify_alert(track_id, change.kind, change.active);
+                            }
+                        }
+                    }
+                    
+                    // Feed the mixdown bus
+                    if let Some(ref mut mixer) = mixer {
+                        let audible = config.audio.mute_side != MuteSide::Receiver
+                            || track_manager.should_output(track_id);
+                        if audible {
+                            if let Some(track) = track_manager.get_track(track_id) {
+                                let gain = 10f32.powf(track.config.mix_gain_db / 20.0);
+                                mixer.push(track_id, &frame, gain, track.config.mix_pan);
+                            }
+                        }
+                    }
+                }
+                
+                // Nothing is sent during DTX: keep the output fed with comfort noise
+                if state.dtx && !state.paused && playback.queued_frames() < 2 {
+                    let len = state.decoder.frame_size() * state.decoder.channels() as usize;
+                    let samples = state.comfort.generate(len);
+                    playback.push_generated(AudioFrame::new(samples, state.decoder.channels(), now_us(), 0));
+                }
+            }
+        }
+        
+        if let Some(ref mut mixer) = mixer {
+            while let Some(samples) = mixer.mix() {
+                if let Some(ref streamer) = ogg_streamer {
+                    streamer.publish_mix(&samples);
+                }
+                if mix_playback.is_some() {
+                    let frame = AudioFrame::new(samples, mixer.channels(), now_us(), mix_sequence);
+                    mix_sequence = mix_sequence.wrapping_add(1);
+                    let _ = mix_buffer.push(frame);
+                }
+            }
+        }
+        
+        // Per-track counters for the stats API
+        if last_stats_report.elapsed() >= Duration::from_secs(1) {
+            last_stats_report = Instant::now();
+            for (&track_id, state) in track_states.iter_mut() {
+                // The jitter buffer and playback keep their own running totals
+                let jitter_stats = state.jitter_buffer.stats();
+                counters.store(track_id, Counter::PacketsLost, jitter_stats.lost as u64);
+                counters.store(track_id, Counter::LatePackets, jitter_stats.late as u64);
+                counters.store(track_id, Counter::Overflows, jitter_stats.overflowed as u64);
+                counters.store(track_id, Counter::DuplicatePackets, jitter_stats.duplicates as u64);
+                counters.store(track_id, Counter::ReorderedPackets, jitter_stats.reordered as u64);
+                if let Some(ref playback) = state.playback {
+                    counters.store(track_id, Counter::Underruns, playback.playback().underruns() as u64);
+                }
+                counters.set(track_id, Gauge::JitterLevel, Some(jitter_stats.level as u64));
+                counters.set(track_id, Gauge::JitterCapacity, Some(jitter_stats.capacity as u64));
+                track_manager.report_stats(track_id, TrackCounters {
+                    jitter_occupancy: state.jitter_buffer.take_occupancy(),
+                    jitter_us: state.arrival_jitter.percentiles(),
+                    gap_packets: state.gap_lengths.percentiles(),
+                    ..counters.snapshot(track_id)
+                });
+                
+                // Frames waiting in the jitter buffer and output queue
+                let frame = Duration::from_secs_f64(state.decoder.frame_size() as f64 / DEFAULT_SAMPLE_RATE as f64);
+                state.latency.record(Stage::Jitter, frame * jitter_stats.level as u32);
+                if let Some(ref playback) = state.playback {
+                    let delay_ms = track_manager.get_track(track_id).map_or(0, |t| t.config.playout_delay_ms);
+                    let queued = frame * playback.queued_frames() as u32;
+                    state.latency.record(Stage::Playback, queued + Duration::from_millis(delay_ms as u64));
+                }
+                track_manager.report_latency(track_id, state.latency.take());
             }
         }
         
         // Periodic stats
         if last_stats_time.elapsed() >= Duration::from_secs(5) {
-            last_stats_time = std::time::Instant::now();
+            last_stats_time = Instant::now();
             
             let recv_stats = receiver.stats();
             tracing::info!(
-                "Receiver stats: {} packets, {} bytes, {} invalid",
+                "Receiver stats: {} packets, {} bytes, {} invalid, {} rejected, {} rate limited",
                 recv_stats.packets_received,
                 recv_stats.bytes_received,
-                recv_stats.invalid_packets
+                recv_stats.invalid_packets,
+                recv_stats.rejected_packets,
+                recv_stats.rate_limited_packets
             );
+            if recv_stats.invalid_packets > 0 {
+                let invalid = recv_stats.invalid;
+                tracing::info!(
+                    "Invalid packets: {} truncated, {} bad magic, {} oversized, {} checksum, {} bad control, {} bad signature, {} bad fragment",
+                    invalid.truncated,
+                    invalid.bad_magic,
+                    invalid.too_large,
+                    invalid.checksum,
+                    invalid.bad_control,
+                    invalid.bad_signature,
+                    invalid.bad_fragment
+                );
+            }
+            
+            if let Some(rtt) = clock_sync.round_trip_us() {
+                tracing::info!(
+                    "Clock offset to sender: {} µs (rtt {} µs)",
+                    clock_sync.offset_us(),
+                    rtt
+                );
+            }
             
-            for (track_id, state) in &track_states {
+            for (&track_id, state) in track_states.iter_mut() {
+                // Underruns while paused are expected; only count live ones
+                if let Some(ref playback) = state.playback {
+                    let underruns = playback.playback().underruns();
+                    if !state.paused {
+                        let new_underruns = underruns.saturating_sub(state.underrun_mark);
+                        track_manager.health().record(HealthSignal::Xrun, track_id, new_underruns as u64);
+                    }
+                    state.underrun_mark = underruns;
+                }
+                
+                if state.paused {
+                    tracing::info!("Track {} paused by sender", track_id);
+                    continue;
+                }
+                
                 let jitter_stats = state.jitter_buffer.stats();
+                
+                // Loss over this interval only, so old bursts don't linger
+                let received = jitter_stats.received.saturating_sub(state.loss_mark.0);
+                let lost = jitter_stats.lost.saturating_sub(state.loss_mark.1);
+                state.loss_mark = (jitter_stats.received, jitter_stats.lost);
+                track_manager.health().record_loss(track_id, lost as u64, (received + lost) as u64);
+                if received + lost > 0 {
+                    let loss_pct = lost as f32 * 100.0 / (received + lost) as f32;
+                    track_manager.set_alert(
+                        track_id,
+                        AlertKind::PacketLoss,
+                        loss_pct > config.alerts.loss_threshold_pct,
+                    );
+                }
                 tracing::info!(
-                    "Track {} stats: {} received, {} lost ({:.1}% loss), jitter buffer: {}/{}",
+                    "Track {} stats: {} received, {} lost ({:.1}% loss), jitter buffer: {}/{}, transit {:.1} ms",
                     track_id,
-                    state.packets_received,
+                    counters.get(track_id, Counter::Packets),
                     state.packets_lost,
                     jitter_stats.loss_rate() * 100.0,
                     jitter_stats.level,
-                    jitter_stats.capacity
+                    jitter_stats.capacity,
+                    state.transit_us as f64 / 1000.0
                 );
             }
         }
         
-        // Small sleep to prevent busy-waiting
-        tokio::time::sleep(Duration::from_micros(500)).await;
+        // Sleep until a packet or control message arrives, or for at most a
+        // frame so released audio and comfort noise keep up with the output
+        let idle = track_states
+            .values()
+            .map(|state| Duration::from_micros(state.decoder.frame_size() as u64 * 1_000_000 / DEFAULT_SAMPLE_RATE as u64))
+            .filter(|frame| !frame.is_zero())
+            .fold(Duration::from_millis(LOOP_IDLE_MS), Duration::min);
+        let inbound = control.as_ref().map(|control| control.inbound());
+        tokio::task::block_in_place(|| {
+            let wait = Wait::new().channel(&packet_rx);
+            let wait = match inbound {
+                Some(ref inbound) => wait.channel(inbound),
+                None => wait,
+            };
+            wait.until(idle)
+        });
     }
+    
+    // Fade out whatever is still playing, then stop the devices
+    service.stopping();
+    for state in track_states.values() {
+        if let Some(ref playback) = state.playback {
+            playback.playback().fade_out(SHUTDOWN_FADE_MS);
+        }
+    }
+    if let Some(ref playback) = mix_playback {
+        playback.fade_out(SHUTDOWN_FADE_MS);
+    }
+    tokio::time::sleep(Duration::from_millis(SHUTDOWN_FADE_MS as u64 + 20)).await;
+    
+    for state in track_states.values_mut() {
+        if let Some(ref mut playback) = state.playback {
+            playback.stop();
+        }
+        if let Some(ref mut sink) = state.sink {
+            sink.stop();
+        }
+    }
+    if let Some(ref mut playback) = mix_playback {
+        playback.stop();
+    }
+    if let Some(ref mut talkback) = talkback {
+        talkback.stop();
+    }
+    receiver.stop();
+    tracing::info!("Receiver stopped");
+    service.stopped();
+    
+    Ok(())
 }
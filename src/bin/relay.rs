@@ -0,0 +1,333 @@
+//! Relay/Mixer Node Application
+//!
+//! Receives streams from any number of senders and sends them on to the
+//! receivers in `relay.targets` (plus any given on the command line), either
+//! untouched or mixed and re-encoded. Needs no audio devices, so it can run
+//! on a headless box or a server with a public IP.
+
+use anyhow::Result;
+use crossbeam_channel::bounded;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use lan_audio_streamer::{
+    config::AppConfig,
+    constants::*,
+    events::EventLog,
+    network::{
+        metadata,
+        mqtt,
+        nat::Rendezvous,
+        osc,
+        passthrough::{self, OggOpusReader, FILE_SOURCE},
+        receiver::{AudioReceiver, ReceivedPacket},
+        resolve::Target,
+        Relay, SourceFilter,
+    },
+    diagnostics::{self, Diagnostics},
+    hooks,
+    notify,
+    stats_log,
+    protocol::{AudioPacket, PeerMessage, ProtocolFeatures, TrackConfig, TrackInfo},
+    recovery::Recovery,
+    reload::ConfigReload,
+    service::{Service, ServiceArgs},
+    shutdown::ShutdownCoordinator,
+    tracks::{Counter, Scenes, TrackManager},
+    ui::WebServer,
+};
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    // Initialize logging
+    let args = ServiceArgs::parse(std::env::args());
+    let event_log = Arc::new(EventLog::new(EVENT_LOG_CAPACITY));
+    let log_control = Arc::new(diagnostics::init_logging(event_log.clone(), !args.service));
+
+    tracing::info!("Starting LAN Audio Relay");
+
+    // Load the saved config, if any
+    let config = AppConfig::load_or_default(args.config_path.as_ref())?;
+
+    // Ctrl+C or a service stop ends the streams to the receivers cleanly
+    let shutdown = ShutdownCoordinator::new();
+    shutdown.listen_for_ctrl_c();
+    let service = Service::start("lan-audio-relay", args.service, &shutdown);
+
+    // Relayed tracks show up in the web UI, where they can be muted
+    let track_manager = Arc::new(TrackManager::with_max_tracks(config.audio.max_tracks));
+    track_manager.health().configure(&config.health);
+    track_manager.set_profiles(config.profiles.clone());
+    track_manager.set_bandwidth_cap(config.alerts.bandwidth_cap_kbps);
+
+    // Safe config changes apply without a restart
+    let reload = Arc::new(ConfigReload::start(
+        AppConfig::path_in_use(args.config_path.as_ref()),
+        config.clone(),
+        track_manager.clone(),
+    ));
+
+    let web_server = WebServer::new(config.ui.clone(), track_manager.clone(), false)
+        .with_event_log(event_log.clone())
+        .with_scenes(Arc::new(Scenes::new(track_manager.clone(), &config.scenes)))
+        .with_config(reload.clone())
+        .with_diagnostics(Arc::new(Diagnostics::new(
+            log_control,
+            event_log.clone(),
+            reload.clone(),
+            track_manager.clone(),
+            false,
+        )));
+    let ui_state = web_server.state();
+    let _web_handle = web_server.start_background();
+
+    tokio::spawn(notify::run(track_manager.clone(), reload.subscribe(), false));
+    tokio::spawn(hooks::run(track_manager.clone(), reload.subscribe(), false));
+    tokio::spawn(stats_log::run(track_manager.clone(), config.stats_log.clone()));
+    tokio::spawn(osc::run(track_manager.clone(), config.osc.clone()));
+    tokio::spawn(mqtt::run(track_manager.clone(), config.mqtt.clone(), false));
+
+    // Receivers from the config and the command line; hostnames are waited for until they resolve
+    let mut targets: Vec<SocketAddr> = Vec::new();
+    for name in config.relay.targets.iter().chain(&args.positional) {
+        let mut target = match Target::spawn(name) {
+            Ok(target) => target,
+            Err(e) => anyhow::bail!("Invalid relay target {}: {}", name, e),
+        };
+        match target.resolved(&shutdown).await {
+            Some(addr) => targets.push(addr),
+            None => {
+                service.stopping();
+                service.stopped();
+                return Ok(());
+            }
+        }
+    }
+    if targets.is_empty() {
+        tracing::warn!("No relay targets; pass receivers as host:port or set relay.targets");
+    }
+    tracing::info!("Relaying to {:?}", targets);
+
+    // Senders send here; the same socket sends on to the receivers
+    let (packet_tx, packet_rx) = bounded::<ReceivedPacket>(4096);
+    let mut receiver = AudioReceiver::new();
+    receiver.set_global_channel(packet_tx.clone());
+    receiver.set_source_filter(SourceFilter::new(&config)?);
+    Recovery::new("Network receiver", config.recovery.network.clone(), shutdown.clone())
+        .run(|| receiver.start(config.network.clone()))
+        .await?;
+    let (Some(socket), Some(control)) = (receiver.socket(), receiver.control()) else {
+        anyhow::bail!("Receiver started without a socket");
+    };
+
+    let udp_port = control.local_port().unwrap_or(config.network.udp_port);
+    ui_state.udp_port.store(udp_port, Ordering::Relaxed);
+    tracing::info!("Relay listening on port {}", udp_port);
+
+    let mut relay = Relay::new(config.relay.clone());
+    if !config.relay.mix.is_empty() {
+        tracing::info!("Mixing tracks {:?} into track {}", config.relay.mix, config.relay.mix_track_id);
+        open_track(&track_manager, TrackConfig {
+            track_id: Some(config.relay.mix_track_id),
+            name: "Relay mix".to_string(),
+            bitrate: config.relay.mix_bitrate,
+            ..Default::default()
+        });
+    }
+
+    // Ogg/Opus files join the same packet stream, so they are forwarded or mixed like senders
+    for file in config.relay.files.clone() {
+        let stereo = match OggOpusReader::open(&file.path) {
+            Ok(reader) => reader.is_stereo(),
+            Err(e) => {
+                tracing::warn!("Skipping relay file: {}", e);
+                continue;
+            }
+        };
+        let Some((id, true)) = relay.route(FILE_SOURCE, file.track_id) else {
+            tracing::warn!("Skipping {}: track {} is already used by another file", file.path.display(), file.track_id);
+            continue;
+        };
+        tracing::info!("Playing {} as relay track {}", file.path.display(), id);
+        let name = file.path.file_stem().map_or_else(|| format!("File #{}", id), |stem| stem.to_string_lossy().into_owned());
+        open_track(&track_manager, TrackConfig {
+            track_id: Some(id),
+            name,
+            channels: if stereo { 2 } else { 1 },
+            ..Default::default()
+        });
+        let (packets, shutdown) = (packet_tx.clone(), shutdown.clone());
+        std::thread::spawn(move || match passthrough::stream_file(&file, packets, shutdown) {
+            Ok(()) => tracing::info!("Relay file {} finished", file.path.display()),
+            Err(e) => tracing::warn!("Relay file {} stopped: {}", file.path.display(), e),
+        });
+    }
+
+    let send = |message: &PeerMessage| {
+        for &target in &targets {
+            if let Err(e) = control.send(message, target) {
+                tracing::debug!("Control to {} failed: {}", target, e);
+            }
+        }
+    };
+
+    // Tracks each receiver subscribed to (none or empty = all)
+    let mut subscriptions: HashMap<SocketAddr, Vec<u8>> = HashMap::new();
+    
+    // Packets and bytes sent on, per relay track
+    let counters = track_manager.counters();
+    let mut last_stats_report = Instant::now();
+    let mut last_mtu_probe: Option<Instant> = None;
+    let mut rendezvous = config.relay.rendezvous.then(Rendezvous::new);
+    if rendezvous.is_some() {
+        tracing::info!("Rendezvous for NAT hole punching enabled");
+    }
+
+    service.ready();
+    tracing::info!("Relaying - press Ctrl+C to stop");
+
+    while !shutdown.is_triggered() {
+        while let Ok(packet) = packet_rx.try_recv() {
+            let Some((id, new)) = relay.route(packet.source, packet.track_id) else {
+                tracing::warn!("No free relay track ID for track {} from {}", packet.track_id, packet.source);
+                continue;
+            };
+            if new {
+                tracing::info!("Track {} from {} relayed as track {}", packet.track_id, packet.source, id);
+                open_track(&track_manager, TrackConfig {
+                    track_id: Some(id),
+                    name: format!("{} #{}", packet.source.ip(), packet.track_id),
+                    channels: if packet.is_stereo { 2 } else { 1 },
+                    ..Default::default()
+                });
+            }
+            track_manager.session().add_bytes(id, packet.payload.len());
+
+            // Muted or not soloed: drop it from forwarding and the mix
+            if !track_manager.should_output(id) {
+                continue;
+            }
+            let (gain, pan) = track_manager.get_track(id)
+                .map_or((1.0, 0.0), |t| (10f32.powf(t.config.mix_gain_db / 20.0), t.config.mix_pan));
+            let outgoing = match relay.process(id, &packet, gain, pan) {
+                Ok(outgoing) => outgoing,
+                Err(e) => {
+                    tracing::warn!("Mixing track {} failed: {}", id, e);
+                    continue;
+                }
+            };
+
+            for out in outgoing {
+                counters.add(out.track_id, Counter::Packets, 1);
+                for &target in &targets {
+                    if subscriptions.get(&target).is_some_and(|tracks| !tracks.is_empty() && !tracks.contains(&out.track_id)) {
+                        continue;
+                    }
+                    // Checksummed and fragmented to fit each receiver's path, as far as it speaks them
+                    let features = control.negotiated(target).features;
+                    let crc = config.network.payload_crc && features.contains(ProtocolFeatures::PAYLOAD_CRC);
+                    let out = AudioPacket { flags: out.flags.set_crc(crc), ..out.clone() };
+                    let max_datagram = if features.contains(ProtocolFeatures::FRAGMENTS) { control.path_mtu(target) } else { usize::MAX };
+                    let datagrams = match out.serialize_fragments(max_datagram) {
+                        Ok(datagrams) => datagrams,
+                        Err(e) => {
+                            tracing::warn!("Dropped relay track {} packet: {}", out.track_id, e);
+                            continue;
+                        }
+                    };
+                    let to = SocketAddr::new(target.ip(), config.network.track_port(target.port(), out.track_id));
+                    for data in datagrams {
+                        counters.add(out.track_id, Counter::Bytes, data.len() as u64);
+                        if let Err(e) = socket.send_to(&data, to) {
+                            tracing::debug!("Send to {} failed: {}", target, e);
+                        }
+                    }
+                }
+            }
+        }
+
+        // Pass pauses on under the relay's track IDs; one sender ending only pauses its tracks
+        while let Some(inbound) = control.try_recv() {
+            match inbound.message {
+                PeerMessage::Subscribe { tracks } if targets.contains(&inbound.from) => {
+                    if subscriptions.get(&inbound.from) != Some(&tracks) {
+                        tracing::info!("Receiver {} subscribed to tracks {:?}", inbound.from, tracks);
+                    }
+                    subscriptions.insert(inbound.from, tracks);
+                }
+                PeerMessage::StreamPaused { track_id, paused } => {
+                    if let Some(id) = relay.lookup(inbound.from, track_id) {
+                        let _ = track_manager.set_paused(id, paused);
+                        send(&PeerMessage::StreamPaused { track_id: id, paused });
+                    }
+                }
+                // Sender labels go on under the relay's track IDs
+                PeerMessage::TrackInfo(info) => {
+                    if let Some(id) = relay.lookup(inbound.from, info.track_id) {
+                        let _ = metadata::apply(&track_manager, id, &info, false);
+                        send(&PeerMessage::TrackInfo(TrackInfo { track_id: id, ..info }));
+                    }
+                }
+                // Peers behind NATs meet here and learn each other's public address
+                PeerMessage::Rendezvous { ref session } => {
+                    let Some(ref mut rendezvous) = rendezvous else {
+                        continue;
+                    };
+                    for (to, message) in rendezvous.register(session, inbound.from, Instant::now()) {
+                        if let Err(e) = control.send(&message, to) {
+                            tracing::debug!("Rendezvous reply to {} failed: {}", to, e);
+                        }
+                    }
+                }
+                PeerMessage::EndOfStream => {
+                    tracing::info!("Sender {} ended its stream", inbound.from);
+                    for id in relay.tracks_from(inbound.from) {
+                        let _ = track_manager.stop_track(id);
+                        send(&PeerMessage::StreamPaused { track_id: id, paused: true });
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // Path MTU to each receiver
+        if last_mtu_probe.is_none_or(|t| t.elapsed() >= Duration::from_millis(MTU_PROBE_INTERVAL_MS)) {
+            for &target in &targets {
+                control.probe_mtu(target);
+                control.send_hello(target);
+            }
+            last_mtu_probe = Some(Instant::now());
+        }
+
+        // Per-track counters for the stats API
+        if last_stats_report.elapsed() >= Duration::from_secs(1) {
+            last_stats_report = Instant::now();
+            for id in track_manager.track_ids() {
+                track_manager.sample_stats(id);
+            }
+        }
+
+        // Small sleep to prevent busy-waiting
+        tokio::time::sleep(Duration::from_micros(500)).await;
+    }
+
+    // Receivers see one stream from the relay; end it for all of them
+    service.stopping();
+    send(&PeerMessage::EndOfStream);
+    receiver.stop();
+    tracing::info!("Relay stopped");
+    service.stopped();
+
+    Ok(())
+}
+
+/// Create and start a relay track, logging why when it can't be
+fn open_track(track_manager: &TrackManager, config: TrackConfig) {
+    let id = config.track_id.unwrap_or_default();
+    if let Err(e) = track_manager.create_track(config).and_then(|id| track_manager.start_track(id)) {
+        tracing::warn!("Relay track {} not opened: {}", id, e);
+    }
+}
@@ -0,0 +1,127 @@
+//! Packet Dump Replay
+//!
+//! Sends the datagrams of a packet dump (written with `network.packet_dump`)
+//! to a receiver again, spaced as they were recorded, so a glitch reported
+//! from the field runs through the receive pipeline on any machine:
+//!
+//! ```text
+//! replay <dump> [<receiver>] [--sent] [--track N] [--speed X] [--loop]
+//! ```
+//!
+//! The receiver (`host:port`) defaults to this machine on
+//! `network.udp_port`. Datagrams the dump's receiver took in are replayed
+//! unless `--sent` picks the ones its sender sent instead.
+
+use anyhow::{bail, Context, Result};
+use std::net::{SocketAddr, UdpSocket};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use lan_audio_streamer::{
+    config::AppConfig,
+    network::{
+        dump::{Direction, DumpReader, DumpRecord},
+        resolve::resolve,
+    },
+    protocol::{parse_packet, Packet},
+    service::ServiceArgs,
+};
+
+/// Replay settings from the command line
+struct ReplayArgs {
+    dump: PathBuf,
+    target: Option<String>,
+    direction: Direction,
+    track: Option<u8>,
+    speed: f64,
+    looped: bool,
+}
+
+impl ReplayArgs {
+    fn parse(positional: &[String]) -> Result<Self> {
+        let mut dump = None;
+        let mut parsed = Self {
+            dump: PathBuf::new(),
+            target: None,
+            direction: Direction::Received,
+            track: None,
+            speed: 1.0,
+            looped: false,
+        };
+        let mut args = positional.iter();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().with_context(|| format!("{} needs a value", arg));
+            match arg.as_str() {
+                "--sent" => parsed.direction = Direction::Sent,
+                "--track" => parsed.track = Some(value()?.parse().context("--track")?),
+                "--speed" => parsed.speed = value()?.parse().context("--speed")?,
+                "--loop" => parsed.looped = true,
+                other if other.starts_with("--") => bail!("unknown argument {}", other),
+                _ if dump.is_none() => dump = Some(PathBuf::from(arg)),
+                _ if parsed.target.is_none() => parsed.target = Some(arg.clone()),
+                _ => bail!("unexpected argument {}", arg),
+            }
+        }
+        if parsed.speed <= 0.0 {
+            bail!("--speed must be above 0");
+        }
+        parsed.dump = dump.context("usage: replay <dump> [<receiver>] [--sent] [--track N] [--speed X] [--loop]")?;
+        Ok(parsed)
+    }
+}
+
+/// Track a datagram belongs to, if it is audio
+fn track_of(data: &[u8]) -> Option<u8> {
+    match parse_packet(data).ok()? {
+        Packet::Audio(packet) => Some(packet.track_id),
+        Packet::Fragment(fragment) => Some(fragment.packet.track_id),
+        _ => None,
+    }
+}
+
+fn main() -> Result<()> {
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::new(
+            std::env::var("RUST_LOG").unwrap_or_else(|_| "info".into()),
+        ))
+        .init();
+    let args = ServiceArgs::parse(std::env::args());
+    let replay = ReplayArgs::parse(&args.positional)?;
+    let config = AppConfig::load_or_default(args.config_path.as_ref())?;
+
+    let target: SocketAddr = match replay.target {
+        Some(ref target) => resolve(target)?,
+        None => SocketAddr::from(([127, 0, 0, 1], config.network.udp_port)),
+    };
+
+    let records: Vec<DumpRecord> = DumpReader::open(&replay.dump)?
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .filter(|record| record.direction == replay.direction)
+        .filter(|record| replay.track.is_none() || track_of(&record.data) == replay.track)
+        .collect();
+    let Some(first) = records.first() else {
+        bail!("No matching packets in {}", replay.dump.display());
+    };
+    let span = Duration::from_micros(records.last().map_or(0, |last| last.time_us.saturating_sub(first.time_us)));
+    tracing::info!("Replaying {} packets ({:.1} s) to {}", records.len(), span.as_secs_f64() / replay.speed, target);
+
+    let socket = UdpSocket::bind(if target.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" })?;
+    loop {
+        let start = Instant::now();
+        for record in &records {
+            let offset = Duration::from_micros(record.time_us.saturating_sub(first.time_us)).div_f64(replay.speed);
+            if let Some(wait) = (start + offset).checked_duration_since(Instant::now()) {
+                std::thread::sleep(wait);
+            }
+            if let Err(e) = socket.send_to(&record.data, target) {
+                tracing::warn!("Failed to send packet: {}", e);
+            }
+        }
+        if !replay.looped {
+            break;
+        }
+    }
+    tracing::info!("Replay finished");
+    Ok(())
+}
@@ -3,188 +3,734 @@
 //! Captures audio from multiple devices and streams to receiver over UDP.
 
 use anyhow::Result;
-use std::net::SocketAddr;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 use lan_audio_streamer::{
     audio::{
-        buffer::{create_shared_buffer},
+        buffer::{create_shared_buffer, SharedRingBuffer},
         capture::AudioCapture,
-        device::list_devices,
+        custom::{AudioSource, SourceDriver},
+        device::{default_device_id, get_device_by_id, list_devices},
+        device_id,
+        fade,
+        migration::DeviceMigration,
+        monitor::SignalMonitor,
     },
     codec::OpusEncoder,
-    config::{AppConfig, OpusConfig},
+    dsp::{extract_channel, ProcessorChain, ProcessorConfig},
+    config::{AppConfig, MuteSide, OpusConfig},
     constants::*,
-    network::sender::{MultiTrackSender},
-    protocol::{TrackConfig, TrackType},
-    tracks::TrackManager,
+    events::EventLog,
+    network::{linked, metadata, mqtt, nat::NatTraversal, osc, resolve::Target, sender::MultiTrackSender, Talkback},
+    diagnostics::{self, Diagnostics},
+    hooks,
+    notify,
+    stats_log,
+    recovery::Recovery,
+    reload::ConfigReload,
+    protocol::{AlertKind, HealthSignal, PeerMessage, TrackConfig, TrackType},
+    selftest,
+    service::{Service, ServiceArgs},
+    shutdown::ShutdownCoordinator,
+    tracks::{
+        autostart::{self, CaptureGroup},
+        Counter, LatencyRecorder, ResetSource, Stage, Scenes, TrackManager,
+    },
     ui::WebServer,
+    wake::{Wait, Wakeup},
 };
 
 #[tokio::main]
 async fn main() -> Result<()> {
     // Initialize logging
-    tracing_subscriber::registry()
-        .with(tracing_subscriber::EnvFilter::new(
-            std::env::var("RUST_LOG").unwrap_or_else(|_| "info".into()),
-        ))
-        .with(tracing_subscriber::fmt::layer())
-        .init();
+    let args = ServiceArgs::parse(std::env::args());
+    let event_log = Arc::new(EventLog::new(EVENT_LOG_CAPACITY));
+    let log_control = Arc::new(diagnostics::init_logging(event_log.clone(), !args.service));
     
     tracing::info!("Starting LAN Audio Sender");
     
-    // Load or create config
-    let config = AppConfig::default();
+    // Load the saved config, if any
+    let config = AppConfig::load_or_default(args.config_path.as_ref())?;
+    device_id::set_aliases(&config.device_aliases);
+    
+    // Check devices, codec and ports, then exit
+    if args.self_test {
+        let report = selftest::run(&config, true);
+        println!("{}", report);
+        std::process::exit(if report.passed() { 0 } else { 1 });
+    }
     
-    // List available devices
-    println!("\n=== Available Audio Devices ===");
+    // List available devices (services have no console to print to)
     let devices = list_devices();
-    for device in &devices {
-        let device_type = match (device.is_input, device.is_output) {
-            (true, true) => "Input/Output",
-            (true, false) => "Input",
-            (false, true) => "Output",
-            _ => "Unknown",
-        };
-        let default_marker = if device.is_default { " [DEFAULT]" } else { "" };
-        println!("  {} ({}){}:", device.name, device_type, default_marker);
-        println!("    ID: {}", device.id);
-        println!("    Sample rates: {:?}", device.sample_rates);
-        println!("    Channels: {:?}", device.channels);
+    if !args.service {
+        println!("\n=== Available Audio Devices ===");
+        for device in &devices {
+            let device_type = match (device.is_input, device.is_output) {
+                (true, true) => "Input/Output",
+                (true, false) => "Input",
+                (false, true) => "Output",
+                _ => "Unknown",
+            };
+            let default_marker = if device.is_default { " [DEFAULT]" } else { "" };
+            println!("  {} ({}){}:", device.name, device_type, default_marker);
+            println!("    ID: {}", device.id);
+            println!("    Sample rates: {:?}", device.sample_rates);
+            println!("    Channels: {:?}", device.channels);
+        }
+        println!();
     }
-    println!();
+    
+    // Ctrl+C (or a service stop) ends the loops below instead of killing the process
+    let shutdown = ShutdownCoordinator::new();
+    shutdown.listen_for_ctrl_c();
+    let service = Service::start("lan-audio-sender", args.service, &shutdown);
     
     // Create track manager
-    let track_manager = Arc::new(TrackManager::new());
+    let track_manager = Arc::new(TrackManager::with_max_tracks(config.audio.max_tracks));
+    track_manager.health().configure(&config.health);
+    track_manager.set_profiles(config.profiles.clone());
+    track_manager.set_bandwidth_cap(config.alerts.bandwidth_cap_kbps);
+    
+    // Safe config changes apply without a restart
+    let reload = Arc::new(ConfigReload::start(
+        AppConfig::path_in_use(args.config_path.as_ref()),
+        config.clone(),
+        track_manager.clone(),
+    ));
+    track_manager.set_bitrate_budget(config.network.bitrate_budget);
     
     // Start web UI
     let web_server = WebServer::new(
         config.ui.clone(),
         track_manager.clone(),
         true, // is_sender
-    );
+    )
+    .with_event_log(event_log.clone())
+    .with_scenes(Arc::new(Scenes::new(track_manager.clone(), &config.scenes)))
+    .with_config(reload.clone())
+    .with_diagnostics(Arc::new(Diagnostics::new(
+        log_control,
+        event_log.clone(),
+        reload.clone(),
+        track_manager.clone(),
+        true,
+    )));
+    let ui_state = web_server.state();
     let _web_handle = web_server.start_background();
     
-    tracing::info!("Web UI available at http://{}:{}", config.ui.bind_address, config.ui.http_port);
+    tokio::spawn(notify::run(track_manager.clone(), reload.subscribe(), true));
+    tokio::spawn(hooks::run(track_manager.clone(), reload.subscribe(), true));
+    tokio::spawn(stats_log::run(track_manager.clone(), config.stats_log.clone()));
+    tokio::spawn(osc::run(track_manager.clone(), config.osc.clone()));
+    tokio::spawn(mqtt::run(track_manager.clone(), config.mqtt.clone(), true));
     
-    // Get target address from args or use default
-    let target_addr: SocketAddr = std::env::args()
-        .nth(1)
-        .unwrap_or_else(|| "127.0.0.1:5000".to_string())
-        .parse()
-        .expect("Invalid target address");
+    // Get target from args or use default; a hostname is waited for until it
+    // resolves, then followed as it changes
+    let target_name = args.positional.first().cloned().unwrap_or_else(|| "127.0.0.1:5000".to_string());
+    let mut target = Target::spawn(&target_name)?;
+    let Some(mut target_addr) = target.resolved(&shutdown).await else {
+        service.stopping();
+        service.stopped();
+        return Ok(());
+    };
     
-    tracing::info!("Target receiver: {}", target_addr);
+    tracing::info!("Target receiver: {} ({})", target_name, target_addr);
     
     // Create network sender
     let mut network_sender = MultiTrackSender::new(&config.network, target_addr)?;
-    network_sender.start(config.network.clone())?;
-    
-    tracing::info!("Network sender started");
-    
-    // Example: Create a track from the default input device
-    if let Some(input_device) = devices.iter().find(|d| d.is_input && d.is_default) {
-        let track_config = TrackConfig {
-            track_id: Some(0),
-            name: format!("Default Input - {}", input_device.name),
-            device_id: input_device.id.clone(),
-            bitrate: 128_000,
-            frame_size_ms: 10.0,
-            channels: 2,
-            track_type: TrackType::Music,
-            fec_enabled: false,
-        };
-        
-        let track_id = track_manager.create_track(track_config)?;
-        tracing::info!("Created track {} for device {}", track_id, input_device.name);
-        
-        // Create capture buffer
-        let capture_buffer = create_shared_buffer(RING_BUFFER_CAPACITY);
-        
-        // Create and start audio capture
-        let mut capture = AudioCapture::new(
-            track_id,
-            &input_device.id,
-            Some(DEFAULT_SAMPLE_RATE),
-            Some(DEFAULT_CHANNELS),
-            None,
-            capture_buffer.clone(),
-        )?;
-        
-        capture.start()?;
-        tracing::info!("Audio capture started");
-        
-        // Create Opus encoder for this track
-        let opus_config = OpusConfig::music();
-        let mut encoder = OpusEncoder::new(opus_config)?;
-        let frame_size = encoder.samples_per_frame();
+    
+    // Talkback from the receiver's microphone to this PC's headphones
+    let mut talkback = if config.talkback.enabled {
+        let (talkback_tx, talkback_rx) = crossbeam_channel::bounded(256);
+        network_sender.set_talkback_channel(talkback_tx);
+        match Talkback::start_playback(&config.talkback, talkback_rx) {
+            Ok(talkback) => Some(talkback),
+            Err(e) => {
+                tracing::warn!("Talkback unavailable: {}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+    let mut network_recovery = Recovery::new("Network sender", config.recovery.network.clone(), shutdown.clone());
+    network_recovery.run(|| network_sender.start(config.network.clone())).await?;
+    
+    match network_sender.control().and_then(|c| c.local_port()) {
+        Some(port) => {
+            ui_state.udp_port.store(port, Ordering::Relaxed);
+            tracing::info!("Network sender started on port {}", port);
+        }
+        None => tracing::info!("Network sender started"),
+    }
+    service.ready();
+    
+    // Saved tracks to bring up (the first and any marked auto_start), else
+    // a track from the default input device
+    let mut groups = autostart::plan(&config.tracks);
+    if groups.is_empty() {
+        if let Some(input_device) = devices.iter().find(|d| d.is_input && d.is_default) {
+            groups.push(CaptureGroup {
+                device_id: input_device.id.clone(),
+                tracks: vec![TrackConfig {
+                    track_id: Some(0),
+                    name: format!("Default Input - {}", input_device.name),
+                    device_id: input_device.id.clone(),
+                    bitrate: 128_000,
+                    frame_size_ms: 10.0,
+                    channels: 2,
+                    track_type: TrackType::Music,
+                    fec_enabled: false,
+                    ..Default::default()
+                }],
+            });
+        }
+    }
+    if !groups.is_empty() {
+        // Rung by every capture callback, so the loop sleeps until there is audio
+        let wakeup = Wakeup::new();
+        let mut inputs = Vec::new();
+        for group in groups {
+            let mut lanes = Vec::with_capacity(group.tracks.len());
+            let fallback = group.tracks[0].fallback_device.clone();
+            for track_config in group.tracks {
+                let source_channel = track_config.source_channel;
+                let track_id = track_manager.create_track(track_config)?;
+                tracing::info!("Created track {} for device {}", track_id, group.device_id);
+                lanes.push(Lane::new(track_id, source_channel, &config)?);
+            }
+            let input = Input::new(group.device_id, lanes, fallback, &config, &shutdown);
+            input.buffer.set_wakeup(wakeup.clone());
+            inputs.push(input);
+        }
         
-        tracing::info!(
-            "Opus encoder initialized: {}Hz, {} channels, {} samples/frame ({:.1}ms)",
-            DEFAULT_SAMPLE_RATE,
-            DEFAULT_CHANNELS,
-            frame_size,
-            encoder.frame_duration_ms()
-        );
+        // Devices that are not plugged in yet are retried from the loop
+        for input in inputs.iter_mut() {
+            input.open(&track_manager);
+        }
         
         // Main encoding/sending loop
-        let mut sample_buffer: Vec<f32> = Vec::with_capacity(frame_size * 2);
-        let mut sequence: u32 = 0;
-        let start_time = Instant::now();
+        let frame_budget = Duration::from_secs_f32(inputs[0].lanes[0].encoder.frame_duration_ms() / 1000.0);
+        let counters = track_manager.counters();
+        let mut last_stats_report = Instant::now();
+        let mut last_linked_report: Option<Instant> = None;
+        let mut last_track_info: Option<Instant> = None;
+        let mut nat = NatTraversal::new(&config.network);
+        let mut introduced = None;
         
         tracing::info!("Starting main loop - press Ctrl+C to stop");
         
-        loop {
-            // Check for captured audio
-            while let Some(frame) = capture_buffer.try_pop() {
-                // Accumulate samples
-                sample_buffer.extend_from_slice(&frame.samples);
+        while !shutdown.is_triggered() {
+            for input in inputs.iter_mut() {
+                let track_id = input.lanes[0].track_id;
+                
+                // A custom source set through the track manager takes over from the device
+                if let Some(source) = track_manager.take_source(track_id) {
+                    input.use_source(source, &track_manager);
+                }
+                if let Some(driver) = input.source.as_mut() {
+                    if let Some(e) = driver.check_errors() {
+                        tracing::warn!("Custom source error on track {}: {}", track_id, e);
+                        input.recovery.fail(&e);
+                    }
+                    if !driver.is_running() && input.recovery.due() {
+                        driver.stop();
+                        match driver.start() {
+                            Ok(()) => tracing::info!("Track {} custom source restarted", track_id),
+                            Err(e) => {
+                                input.recovery.fail(&e);
+                            }
+                        }
+                    }
+                    continue;
+                }
+                
+                let Some(capture) = input.capture.as_mut() else {
+                    if input.recovery.due() {
+                        input.open(&track_manager);
+                    }
+                    continue;
+                };
                 
-                // Process complete frames
-                while sample_buffer.len() >= frame_size {
-                    let samples: Vec<f32> = sample_buffer.drain(..frame_size).collect();
+                // Follow device changes made through the API, staying on a fallback while migrated
+                let device_id = track_manager.get_track(track_id).map(|t| t.config.device_id.clone());
+                if let Some(device_id) = device_id {
+                    let device_id = input.migration.active(&device_id).map_or(device_id.clone(), str::to_string);
+                    if device_id != capture.device_id() {
+                        match capture.set_device(&device_id) {
+                            Ok(()) => {
+                                for lane in &input.lanes {
+                                    track_manager.set_alert(lane.track_id, AlertKind::DeviceLost, false);
+                                }
+                            }
+                            Err(e) => {
+                                tracing::warn!("Failed to switch track {} to {}: {}", track_id, device_id, e);
+                            }
+                        }
+                    }
+                }
+                
+                // Device errors usually mean it was unplugged: carry on from
+                // the fallback device, else restart this one
+                if let Some(e) = capture.check_errors() {
+                    tracing::warn!("Capture error on track {}: {}", track_id, e);
+                    track_manager.health().record(HealthSignal::DeviceError, track_id, 1);
+                    for lane in &input.lanes {
+                        track_manager.set_alert(lane.track_id, AlertKind::DeviceLost, true);
+                    }
+                    let original = capture.device_id().to_string();
+                    let fallback = config
+                        .audio
+                        .migrate_devices
+                        .then(|| input.migration.lost(&original, default_device_id(true)))
+                        .flatten();
+                    match fallback.map(|fallback| capture.set_device(&fallback).map(|()| fallback)) {
+                        Some(Ok(fallback)) => {
+                            input.migration.moved(&original, &fallback);
+                            for lane in &input.lanes {
+                                track_manager.notify_device_migrated(lane.track_id, &fallback, true);
+                            }
+                        }
+                        Some(Err(move_error)) => {
+                            tracing::warn!("Track {} could not move to its fallback device: {}", track_id, move_error);
+                            input.recovery.fail(&e);
+                        }
+                        None => {
+                            input.recovery.fail(&e);
+                        }
+                    }
+                }
+                if input.recovery.due() {
+                    match capture.restart() {
+                        Ok(()) => {
+                            tracing::info!("Track {} capture restarted", track_id);
+                            for lane in &input.lanes {
+                                track_manager.set_alert(lane.track_id, AlertKind::DeviceLost, false);
+                            }
+                        }
+                        Err(e) => {
+                            input.recovery.fail(&e);
+                        }
+                    }
+                }
+                
+                // Move back once the original device is present again
+                if let Some(original) = input.migration.return_due().map(str::to_string) {
+                    if get_device_by_id(&original).is_ok() {
+                        match capture.set_device(&original) {
+                            Ok(()) => {
+                                input.migration.returned();
+                                for lane in &input.lanes {
+                                    track_manager.notify_device_migrated(lane.track_id, &original, false);
+                                    track_manager.set_alert(lane.track_id, AlertKind::DeviceLost, false);
+                                }
+                            }
+                            Err(e) => {
+                                tracing::debug!("Track {} could not return to {}: {}", track_id, original, e);
+                            }
+                        }
+                    }
+                }
+            }
+            
+            // The receiver's name moved to a new address, or the rendezvous
+            // introduced a receiver: reconnect there
+            if let Some(addr) = target.changed().or_else(|| introduced.take().filter(|&a| a != target_addr)) {
+                tracing::info!("Reconnecting to the receiver at {}", addr);
+                target_addr = addr;
+                network_sender.stop();
+                network_sender.set_target(addr);
+                if let Err(e) = network_sender.start(config.network.clone()) {
+                    network_recovery.fail(&e);
+                }
+            }
+            
+            // A stalled send queue: restart the network sender
+            if network_recovery.due() {
+                network_sender.stop();
+                match network_sender.start(config.network.clone()) {
+                    Ok(()) => tracing::info!("Network sender restarted"),
+                    Err(e) => {
+                        network_recovery.fail(&e);
+                    }
+                }
+            }
+            
+            // Track subscriptions and linked-control changes from the receiver
+            if let Some(control) = network_sender.control() {
+                // A restart may have bound another port in the range
+                ui_state.udp_port.store(control.local_port().unwrap_or(0), Ordering::Relaxed);
+                nat.tick(&control);
+                while let Some(inbound) = control.try_recv() {
+                    if network_sender.handle_control(&inbound) {
+                        continue;
+                    }
+                    if let Some(peer) = nat.handle(&inbound) {
+                        introduced = Some(peer);
+                        continue;
+                    }
+                    // The receiver reset its decoder
+                    if let PeerMessage::ResetTrack { track_id, by_sender: false } = inbound.message {
+                        if inbound.from.ip() == target_addr.ip() {
+                            let _ = track_manager.request_reset(track_id, ResetSource::Peer);
+                        }
+                        continue;
+                    }
+                    if let PeerMessage::Capabilities(ref capabilities) = inbound.message {
+                        if inbound.from.ip() == target_addr.ip() {
+                            track_manager.apply_receiver_capabilities(capabilities.clone(), DEFAULT_SAMPLE_RATE, |_| true);
+                        }
+                        continue;
+                    }
+                    let PeerMessage::ConfigureTrack { track_id, ref update } = inbound.message else {
+                        continue;
+                    };
+                    if !config.linked_control.enabled || inbound.from.ip() != target_addr.ip() {
+                        tracing::debug!("Ignored track change from {}", inbound.from);
+                        continue;
+                    }
+                    tracing::info!("Receiver changed track {}: {:?}", track_id, update);
+                    if let Err(e) = linked::apply(&track_manager, track_id, update) {
+                        tracing::warn!("Track change from the receiver failed: {}", e);
+                    }
+                    last_linked_report = None;
+                }
+            }
+            
+            // Report track settings to the receiver's linked control
+            if config.linked_control.enabled
+                && last_linked_report.is_none_or(|t| t.elapsed() >= Duration::from_millis(LINKED_STATE_INTERVAL_MS))
+            {
+                for message in linked::track_states(&track_manager) {
+                    let _ = network_sender.send_control(&message);
+                }
+                last_linked_report = Some(Instant::now());
+            }
+            
+            // Track names, colors and types for the receiver's UI
+            if last_track_info.is_none_or(|t| t.elapsed() >= Duration::from_millis(TRACK_INFO_INTERVAL_MS)) {
+                for message in metadata::announcements(&track_manager) {
+                    let _ = network_sender.send_control(&message);
+                }
+                last_track_info = Some(Instant::now());
+            }
+            
+            for input in inputs.iter_mut() {
+                let Input { capture, source, buffer: capture_buffer, lanes, overflow_mark, .. } = input;
+                if capture.is_none() && source.is_none() {
+                    continue;
+                }
+                let track_id = lanes[0].track_id;
+                
+                if let Some(capture) = capture.as_mut() {
+                    for xrun in capture.drain_xruns() {
+                        track_manager.notify_xrun(xrun);
+                    }
+                }
+                
+                // Capture frames dropped because encoding fell behind
+                let overflows = capture_buffer.overflow_count();
+                if overflows > *overflow_mark {
+                    track_manager.health().record(HealthSignal::Xrun, track_id, (overflows - *overflow_mark) as u64);
+                    *overflow_mark = overflows;
+                }
+                for lane in lanes.iter() {
+                    counters.store(lane.track_id, Counter::Overflows, overflows as u64);
+                }
+                
+                // Check for captured audio
+                while let Some(frame) = capture_buffer.try_pop() {
+                    // Device buffer is about one callback; then time spent in the ring
+                    let frames = frame.samples.len() / frame.channels.max(1) as usize;
+                    let capture_delay = Duration::from_secs_f64(frames as f64 / DEFAULT_SAMPLE_RATE as f64);
+                    let captured_at = match (capture.as_ref(), source.as_ref()) {
+                        (Some(capture), _) => capture.captured_at(&frame),
+                        (None, Some(source)) => source.started_at() + Duration::from_micros(frame.timestamp),
+                        (None, None) => Instant::now(),
+                    };
+                    let ring_delay = Instant::now().saturating_duration_since(captured_at);
                     
-                    // Encode
-                    match encoder.encode(&samples) {
-                        Ok(encoded) => {
-                            // Calculate timestamp
-                            let timestamp = start_time.elapsed().as_micros() as u64;
+                    for lane in lanes.iter_mut() {
+                        let track_id = lane.track_id;
+                        let channels = lane.channels;
+                        lane.latency.record(Stage::Capture, capture_delay);
+                        lane.latency.record(Stage::Ring, ring_delay);
+                        
+                        // Accumulate samples, just this lane's channel when split
+                        match lane.source_channel {
+                            Some(channel) => lane.sample_buffer.extend(extract_channel(&frame.samples, frame.channels, channel)),
+                            None => lane.sample_buffer.extend_from_slice(&frame.samples),
+                        }
+                        
+                        // Process complete frames
+                        while lane.sample_buffer.len() >= lane.frame_size {
+                            let mut samples: Vec<f32> = lane.sample_buffer.drain(..lane.frame_size).collect();
+                            let frame_started = Instant::now();
+                            lane.latency.record(Stage::Frame, frame_budget);
+                            
+                            // Pause/resume keeps capture and encoder running; the
+                            // frame at each transition is faded to avoid a click
+                            let now_paused = track_manager.is_paused(track_id);
+                            let resuming = lane.paused && !now_paused;
+                            let pausing = !lane.paused && now_paused;
+                            lane.paused = now_paused;
                             
-                            // Send over network
-                            if let Err(e) = network_sender.send_audio(
-                                track_id,
-                                encoded,
-                                timestamp,
-                                DEFAULT_CHANNELS == 2,
-                            ) {
-                                tracing::warn!("Failed to send packet: {}", e);
+                            if resuming {
+                                tracing::info!("Track {} resumed", track_id);
+                                let _ = network_sender.send_control(&PeerMessage::StreamPaused {
+                                    track_id,
+                                    paused: false,
+                                });
+                                fade::fade_in(&mut samples, channels);
+                            } else if pausing {
+                                fade::fade_out(&mut samples, channels);
+                            } else if lane.paused {
+                                // The media clock runs on through the pause
+                                network_sender.skip_audio(track_id, lane.encoder.frame_ticks());
+                                continue;
                             }
                             
-                            sequence = sequence.wrapping_add(1);
-                        }
-                        Err(e) => {
-                            tracing::warn!("Encoding failed: {}", e);
+                            // The receiver did not subscribe to this track: skip encoding it
+                            if !network_sender.is_subscribed(track_id) {
+                                continue;
+                            }
+                            
+                            // Garbled track: start the encoder over, and the receiver's decoder with it
+                            if let Some(source) = track_manager.take_reset(track_id) {
+                                tracing::info!("Track {}: encoder reset", track_id);
+                                if let Err(e) = lane.encoder.reset() {
+                                    tracing::warn!("Failed to reset encoder on track {}: {}", track_id, e);
+                                }
+                                if source == ResetSource::Local {
+                                    let _ = network_sender.send_control(&PeerMessage::ResetTrack { track_id, by_sender: true });
+                                }
+                            }
+                            
+                            if let Some(track) = track_manager.get_track(track_id) {
+                                network_sender.set_priority(track_id, track.config.send_priority());
+                                
+                                // Follow the bitrate budget
+                                let bitrate = track.effective_bitrate();
+                                if bitrate != lane.encoder.config().bitrate {
+                                    if let Err(e) = lane.encoder.set_bitrate(bitrate) {
+                                        tracing::warn!("Failed to set bitrate on track {}: {}", track_id, e);
+                                    }
+                                }
+                                
+                                // FEC only adds redundancy when some loss is expected
+                                if track.config.fec_enabled != lane.encoder.config().fec {
+                                    let loss = lane.encoder.config().packet_loss_perc.max(5);
+                                    if let Err(e) = lane.encoder.set_fec(track.config.fec_enabled, loss) {
+                                        tracing::warn!("Failed to set FEC on track {}: {}", track_id, e);
+                                    }
+                                }
+                                
+                                // Rebuild the simulcast encoders when their bitrates change
+                                if lane.simulcast != track.config.simulcast_bitrates {
+                                    lane.simulcast = track.config.simulcast_bitrates.clone();
+                                    let base = lane.encoder.config().clone();
+                                    lane.layers = match lane.simulcast
+                                        .iter()
+                                        .map(|&bitrate| OpusEncoder::new(OpusConfig { bitrate, ..base.clone() }))
+                                        .collect()
+                                    {
+                                        Ok(layers) => layers,
+                                        Err(e) => {
+                                            tracing::warn!("Failed to create simulcast encoders on track {}: {}", track_id, e);
+                                            Vec::new()
+                                        }
+                                    };
+                                }
+                                
+                                // Rebuild the processing chain when its configuration changes
+                                if lane.processors.as_ref() != Some(&track.config.processors) {
+                                    let configs = lane.processors.insert(track.config.processors.clone());
+                                    lane.chain = ProcessorChain::with_meters(
+                                        configs,
+                                        DEFAULT_SAMPLE_RATE,
+                                        track.meters(),
+                                        track_manager.meter_bus(),
+                                    );
+                                }
+                            }
+                            let chain = &mut lane.chain;
+                            lane.latency.time(Stage::Process, track_id, || chain.process(&mut samples, channels));
+                            
+                            let meters = lane.chain.meters();
+                            if meters.gate_open() != lane.gate_open {
+                                lane.gate_open = meters.gate_open();
+                                track_manager.notify_gate(track_id, lane.gate_open);
+                            }
+                            
+                            if let Some(monitor) = lane.monitor.as_mut() {
+                                for change in monitor.process(&samples, channels) {
+                                    track_manager.notify_alert(track_id, change.kind, change.active);
+                                }
+                            }
+                            
+                            // Muted or not soloed: keep the stream going with silence
+                            if config.audio.mute_side == MuteSide::Sender
+                                && !track_manager.should_output(track_id)
+                            {
+                                samples.fill(0.0);
+                            }
+                            
+                            // Encode in front of the header room the packet is sent from,
+                            // noting frames that took longer than they last
+                            let encoder = &mut lane.encoder;
+                            let encoded = lane.latency.time(Stage::Encode, track_id, || encoder.encode_into(&samples));
+                            if frame_started.elapsed() > frame_budget {
+                                track_manager.health().record(HealthSignal::DeadlineMiss, track_id, 1);
+                            }
+                            match encoded {
+                                Ok(encoded) => {
+                                    counters.add(track_id, Counter::Frames, 1);
+                                    
+                                    // The packet is stamped from the track's media clock,
+                                    // which suppressed frames still advance
+                                    let ticks = lane.encoder.frame_ticks();
+                                    
+                                    // DTX: flag the first silent packet, suppress the rest
+                                    let dtx_packet = OpusEncoder::is_dtx_packet(encoded.payload());
+                                    if dtx_packet && lane.in_dtx && !pausing {
+                                        network_sender.skip_audio(track_id, ticks);
+                                        continue;
+                                    }
+                                    track_manager.session().add_bytes(track_id, encoded.len());
+                                    counters.add(track_id, Counter::Packets, 1);
+                                    counters.add(track_id, Counter::Bytes, encoded.len() as u64);
+                                    let result = if dtx_packet {
+                                        network_sender.send_audio_dtx(track_id, encoded, ticks, channels == 2)
+                                    } else {
+                                        network_sender.send_audio(track_id, encoded, ticks, channels == 2)
+                                    };
+                                    lane.in_dtx = dtx_packet;
+                                    
+                                    // Send over network
+                                    if let Err(e) = result {
+                                        tracing::warn!("Failed to send packet: {}", e);
+                                        network_recovery.fail(&e);
+                                    }
+                                    
+                                    // Simulcast layers of the same frame, if the receiver takes them
+                                    for (index, layer_encoder) in lane.layers.iter_mut().enumerate() {
+                                        let layer = index as u8 + 1;
+                                        if !network_sender.wants_layer(layer) {
+                                            continue;
+                                        }
+                                        match layer_encoder.encode_into(&samples) {
+                                            Ok(payload) => {
+                                                let _ = network_sender.send_layer(track_id, layer, payload, channels == 2, dtx_packet);
+                                            }
+                                            Err(e) => {
+                                                tracing::warn!("Encoding layer {} of track {} failed: {}", layer, track_id, e);
+                                            }
+                                        }
+                                    }
+                                    
+                                    lane.sequence = lane.sequence.wrapping_add(1);
+                                    
+                                    if pausing {
+                                        tracing::info!("Track {} paused", track_id);
+                                        let _ = network_sender.send_control(&PeerMessage::StreamPaused {
+                                            track_id,
+                                            paused: true,
+                                        });
+                                    }
+                                }
+                                Err(e) => {
+                                    tracing::warn!("Encoding failed: {}", e);
+                                }
+                            }
                         }
                     }
                 }
             }
             
-            // Small sleep to prevent busy-waiting
-            tokio::time::sleep(Duration::from_micros(500)).await;
+            if last_stats_report.elapsed() >= Duration::from_secs(1) {
+                last_stats_report = Instant::now();
+                for input in inputs.iter_mut() {
+                    for lane in input.lanes.iter_mut() {
+                        track_manager.sample_stats(lane.track_id);
+                        lane.latency.merge(Stage::Send, network_sender.take_queue_delay(lane.track_id));
+                        track_manager.report_latency(lane.track_id, lane.latency.take());
+                    }
+                }
+            }
+            
+            // Sleep until audio is captured or the receiver says something
+            let inbound = network_sender.control().map(|control| control.inbound());
+            tokio::task::block_in_place(|| {
+                let wait = Wait::new().wakeup(&wakeup);
+                let wait = match inbound {
+                    Some(ref inbound) => wait.channel(inbound),
+                    None => wait,
+                };
+                wait.until(Duration::from_millis(LOOP_IDLE_MS))
+            });
             
             // Periodic stats logging
-            if sequence > 0 && sequence % 1000 == 0 {
-                let stats = encoder.stats();
+            let lane = &inputs[0].lanes[0];
+            if lane.sequence > 0 && lane.sequence.is_multiple_of(1000) {
+                let stats = lane.encoder.stats();
                 let sender_stats = network_sender.stats();
                 tracing::info!(
-                    "Stats: {} frames encoded, {} packets sent, {:.1} KB sent, avg frame {:.0} bytes",
+                    "Stats: {} frames encoded, {} packets sent, {:.1} KB sent, avg frame {:.0} bytes, gain reduction {:.1} dB",
                     stats.frames_encoded,
                     sender_stats.packets_sent,
                     sender_stats.bytes_sent as f64 / 1024.0,
-                    stats.average_frame_size
+                    stats.average_frame_size,
+                    lane.chain.meters().gain_reduction_db()
                 );
+                if let Some(drift) = inputs[0].capture.as_ref().and_then(|c| c.clock_drift_ppm()) {
+                    tracing::info!("Capture clock drift: {:+.1} ppm", drift);
+                }
+            }
+        }
+        
+        // Graceful shutdown: stop capture, encode what is left, then end the stream
+        service.stopping();
+        for input in inputs.iter_mut() {
+            let mut remaining = Vec::new();
+            if let Some(capture) = input.capture.as_mut() {
+                capture.stop();
+            }
+            if let Some(source) = input.source.as_mut() {
+                source.stop();
+            }
+            while let Some(frame) = input.buffer.try_pop() {
+                remaining.push(frame);
+            }
+            for lane in input.lanes.iter_mut() {
+                let track_id = lane.track_id;
+                let stereo = lane.channels == 2;
+                let _ = track_manager.stop_track(track_id);
+                if lane.paused {
+                    continue;
+                }
+                for frame in &remaining {
+                    match lane.source_channel {
+                        Some(channel) => lane.sample_buffer.extend(extract_channel(&frame.samples, frame.channels, channel)),
+                        None => lane.sample_buffer.extend_from_slice(&frame.samples),
+                    }
+                }
+                
+                while lane.sample_buffer.len() >= lane.frame_size {
+                    let samples: Vec<f32> = lane.sample_buffer.drain(..lane.frame_size).collect();
+                    if let Ok(encoded) = lane.encoder.encode(&samples) {
+                        let _ = network_sender.send_audio(track_id, encoded, lane.encoder.frame_ticks(), stereo);
+                    }
+                }
+                
+                match lane.encoder.flush(&lane.sample_buffer) {
+                    Ok(Some(encoded)) => {
+                        let _ = network_sender.send_audio(track_id, encoded, lane.encoder.frame_ticks(), stereo);
+                    }
+                    Ok(None) => {}
+                    Err(e) => tracing::warn!("Encoder flush failed: {}", e),
+                }
             }
         }
     } else {
@@ -192,8 +738,179 @@ async fn main() -> Result<()> {
         
         // Keep running for web UI
         tracing::info!("Running in UI-only mode. Configure tracks via web interface.");
-        loop {
-            tokio::time::sleep(Duration::from_secs(1)).await;
+        shutdown.wait().await;
+        service.stopping();
+    }
+    
+    network_sender.finish();
+    if let Some(ref mut talkback) = talkback {
+        talkback.stop();
+    }
+    tracing::info!("Sender stopped");
+    service.stopped();
+    
+    Ok(())
+}
+
+/// One capture device and the tracks published from it
+struct Input {
+    device_id: String,
+    /// None until the device opens
+    capture: Option<AudioCapture>,
+    /// Custom source from the track manager, used instead of the device
+    source: Option<SourceDriver>,
+    buffer: SharedRingBuffer,
+    lanes: Vec<Lane>,
+    overflow_mark: usize,
+    recovery: Recovery,
+    migration: DeviceMigration,
+}
+
+impl Input {
+    fn new(
+        device_id: String,
+        lanes: Vec<Lane>,
+        fallback: Option<String>,
+        config: &AppConfig,
+        shutdown: &ShutdownCoordinator,
+    ) -> Self {
+        let recovery = Recovery::new(
+            format!("Track {} capture", lanes[0].track_id),
+            config.recovery.capture.clone(),
+            shutdown.clone(),
+        );
+        Self {
+            device_id,
+            capture: None,
+            source: None,
+            buffer: create_shared_buffer(RING_BUFFER_CAPACITY),
+            lanes,
+            overflow_mark: 0,
+            recovery,
+            migration: DeviceMigration::new(fallback),
+        }
+    }
+    
+    /// Open the device and start its tracks; a failure schedules a retry
+    fn open(&mut self, track_manager: &TrackManager) {
+        if let Some(source) = track_manager.take_source(self.lanes[0].track_id) {
+            self.use_source(source, track_manager);
+            return;
+        }
+        let capture = match self.start_capture() {
+            Ok(capture) => capture,
+            Err(e) => {
+                for lane in &self.lanes {
+                    track_manager.set_alert(lane.track_id, AlertKind::DeviceLost, true);
+                }
+                self.recovery.fail(&e);
+                return;
+            }
+        };
+        self.capture = Some(capture);
+        self.started(track_manager);
+        tracing::info!("Audio capture started on {}", self.device_id);
+    }
+    
+    /// Close the device and run the tracks from a custom source instead
+    fn use_source(&mut self, source: Box<dyn AudioSource>, track_manager: &TrackManager) {
+        if let Some(mut capture) = self.capture.take() {
+            capture.stop();
         }
+        let track_id = self.lanes[0].track_id;
+        let mut driver = SourceDriver::new(track_id, source, self.buffer.clone());
+        let started = driver.start();
+        self.source = Some(driver);
+        match started {
+            Ok(()) => {
+                self.started(track_manager);
+                tracing::info!("Track {} capturing from a custom source", track_id);
+            }
+            Err(e) => {
+                self.recovery.fail(&e);
+            }
+        }
+    }
+    
+    /// Start the tracks once audio is flowing
+    fn started(&mut self, track_manager: &TrackManager) {
+        self.overflow_mark = self.buffer.overflow_count();
+        for lane in &self.lanes {
+            let _ = track_manager.start_track(lane.track_id);
+            track_manager.set_alert(lane.track_id, AlertKind::DeviceLost, false);
+            tracing::info!(
+                "Opus encoder for track {} initialized: {}Hz, {} channels, {} samples/frame ({:.1}ms)",
+                lane.track_id,
+                DEFAULT_SAMPLE_RATE,
+                lane.channels,
+                lane.frame_size,
+                lane.encoder.frame_duration_ms()
+            );
+        }
+    }
+    
+    fn start_capture(&self) -> Result<AudioCapture> {
+        let mut capture = AudioCapture::new(
+            self.lanes[0].track_id,
+            &self.device_id,
+            Some(DEFAULT_SAMPLE_RATE),
+            Some(DEFAULT_CHANNELS),
+            None,
+            self.buffer.clone(),
+        )?;
+        if let Some(channel) = self.lanes.iter().filter_map(|lane| lane.source_channel).find(|&c| c >= capture.channels()) {
+            anyhow::bail!("Device {} has no channel {} ({} captured)", self.device_id, channel, capture.channels());
+        }
+        capture.start()?;
+        Ok(capture)
+    }
+}
+
+/// One track published from the captured device, with its own encoder and processing
+struct Lane {
+    track_id: u8,
+    /// Device channel this track takes (None = all of them)
+    source_channel: Option<u16>,
+    channels: u16,
+    encoder: OpusEncoder,
+    /// Bitrates the simulcast encoders were built for
+    simulcast: Vec<u32>,
+    /// Encoders of simulcast layers 1, 2, ...
+    layers: Vec<OpusEncoder>,
+    frame_size: usize,
+    sample_buffer: Vec<f32>,
+    sequence: u32,
+    paused: bool,
+    processors: Option<Vec<ProcessorConfig>>,
+    chain: ProcessorChain,
+    gate_open: bool,
+    in_dtx: bool,
+    monitor: Option<SignalMonitor>,
+    latency: LatencyRecorder,
+}
+
+impl Lane {
+    fn new(track_id: u8, source_channel: Option<u16>, config: &AppConfig) -> Result<Self> {
+        let channels = if source_channel.is_some() { 1 } else { DEFAULT_CHANNELS };
+        let encoder = OpusEncoder::new(OpusConfig { channels, ..OpusConfig::music() })?;
+        let frame_size = encoder.samples_per_frame();
+        Ok(Self {
+            track_id,
+            source_channel,
+            channels,
+            encoder,
+            simulcast: Vec::new(),
+            layers: Vec::new(),
+            frame_size,
+            sample_buffer: Vec::with_capacity(frame_size * 2),
+            sequence: 0,
+            paused: false,
+            processors: None,
+            chain: ProcessorChain::new(),
+            gate_open: true,
+            in_dtx: false,
+            monitor: config.alerts.enabled.then(|| SignalMonitor::new(DEFAULT_SAMPLE_RATE, &config.alerts)),
+            latency: LatencyRecorder::new(),
+        })
     }
 }
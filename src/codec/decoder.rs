@@ -1,18 +1,30 @@
 //! Opus decoder wrapper
 //!
-//! Provides Opus decoding with packet loss concealment.
+//! Provides Opus decoding with packet loss concealment, optional output
+//! gain (applied inside libopus) and soft clipping. Decoded frames come
+//! out in buffers from the decoder's [`SamplePool`], which return to it once
+//! the jitter buffer and playback are done with them.
 
-use opus::{Channels, Decoder};
+use opus::{Channels, Decoder, SoftClip};
+use std::sync::Arc;
+use crate::audio::samples::{SamplePool, Samples};
 use crate::error::CodecError;
 
 /// Opus decoder wrapper
 pub struct OpusDecoder {
     decoder: Decoder,
+    opus_channels: Channels,
+    /// Output gain in dB
+    gain_db: f32,
+    /// Soft clipper, None when disabled
+    soft_clip: Option<SoftClip>,
     sample_rate: u32,
     channels: u16,
     frame_size: usize,
     /// Decoding buffer (reused to avoid allocations)
     decode_buffer: Vec<f32>,
+    /// Buffers decoded frames are handed out in
+    pool: Arc<SamplePool>,
     /// Frames decoded
     frames_decoded: u64,
     /// Frames lost (PLC used)
@@ -41,10 +53,14 @@ impl OpusDecoder {
         
         Ok(Self {
             decoder,
+            opus_channels,
+            gain_db: 0.0,
+            soft_clip: None,
             sample_rate,
             channels,
             frame_size,
             decode_buffer,
+            pool: SamplePool::new(),
             frames_decoded: 0,
             frames_lost: 0,
             samples_produced: 0,
@@ -53,44 +69,75 @@ impl OpusDecoder {
     
     /// Decode Opus packet to audio samples
     /// Returns interleaved f32 samples
-    pub fn decode(&mut self, data: &[u8]) -> Result<Vec<f32>, CodecError> {
+    pub fn decode(&mut self, data: &[u8]) -> Result<Samples, CodecError> {
         let samples = self.decoder
             .decode_float(data, &mut self.decode_buffer, false)
             .map_err(|e| CodecError::DecodingFailed(e.to_string()))?;
         
-        let total_samples = samples * self.channels as usize;
         self.frames_decoded += 1;
-        self.samples_produced += total_samples as u64;
-        
-        Ok(self.decode_buffer[..total_samples].to_vec())
+        Ok(self.output(samples))
     }
     
     /// Decode with FEC (Forward Error Correction)
     /// Use when the previous packet was lost
-    pub fn decode_fec(&mut self, data: &[u8]) -> Result<Vec<f32>, CodecError> {
+    pub fn decode_fec(&mut self, data: &[u8]) -> Result<Samples, CodecError> {
         let samples = self.decoder
             .decode_float(data, &mut self.decode_buffer, true)
             .map_err(|e| CodecError::DecodingFailed(e.to_string()))?;
         
-        let total_samples = samples * self.channels as usize;
         self.frames_decoded += 1;
-        self.samples_produced += total_samples as u64;
-        
-        Ok(self.decode_buffer[..total_samples].to_vec())
+        Ok(self.output(samples))
     }
     
     /// Generate packet loss concealment samples
     /// Use when a packet is lost and no FEC is available
-    pub fn decode_plc(&mut self) -> Result<Vec<f32>, CodecError> {
+    pub fn decode_plc(&mut self) -> Result<Samples, CodecError> {
         let samples = self.decoder
             .decode_float(&[], &mut self.decode_buffer, false)
             .map_err(|e| CodecError::DecodingFailed(e.to_string()))?;
         
-        let total_samples = samples * self.channels as usize;
         self.frames_lost += 1;
+        Ok(self.output(samples))
+    }
+    
+    /// Soft-clip and copy out decoded samples into a pooled buffer
+    fn output(&mut self, samples: usize) -> Samples {
+        let total_samples = samples * self.channels as usize;
         self.samples_produced += total_samples as u64;
         
-        Ok(self.decode_buffer[..total_samples].to_vec())
+        let output = &mut self.decode_buffer[..total_samples];
+        if let Some(ref mut clip) = self.soft_clip {
+            clip.apply(output);
+        }
+        self.pool.copy_from(output)
+    }
+    
+    /// Set output gain in dB, applied by the decoder itself
+    pub fn set_gain_db(&mut self, gain_db: f32) -> Result<(), CodecError> {
+        // Opus takes Q8 dB
+        let q8 = (gain_db * 256.0).round().clamp(-32768.0, 32767.0) as i32;
+        self.decoder
+            .set_gain(q8)
+            .map_err(|e| CodecError::DecoderInit(e.to_string()))?;
+        self.gain_db = gain_db;
+        Ok(())
+    }
+    
+    /// Get output gain in dB
+    pub fn gain_db(&self) -> f32 {
+        self.gain_db
+    }
+    
+    /// Enable or disable soft clipping of decoded audio
+    pub fn set_soft_clip(&mut self, enabled: bool) {
+        if enabled != self.soft_clip.is_some() {
+            self.soft_clip = enabled.then(|| SoftClip::new(self.opus_channels));
+        }
+    }
+    
+    /// Check if soft clipping is enabled
+    pub fn soft_clip(&self) -> bool {
+        self.soft_clip.is_some()
     }
     
     /// Reset decoder state
@@ -181,6 +228,28 @@ mod tests {
         assert_eq!(decoded.len(), frame_size);
     }
     
+    #[test]
+    fn test_gain_and_soft_clip() {
+        let mut encoder = OpusEncoder::music(48000, 2).unwrap();
+        let mut decoder = OpusDecoder::new(48000, 2, encoder.frame_size()).unwrap();
+        decoder.set_gain_db(18.0).unwrap();
+        decoder.set_soft_clip(true);
+        assert_eq!(decoder.gain_db(), 18.0);
+        
+        let samples: Vec<f32> = (0..encoder.samples_per_frame())
+            .map(|i| ((i / 2) as f32 * 0.05).sin() * 0.5)
+            .collect();
+        
+        // Boosted well past full scale, yet the clipper keeps it in range
+        let mut peak = 0.0f32;
+        for _ in 0..5 {
+            let encoded = encoder.encode(&samples).unwrap();
+            let decoded = decoder.decode(&encoded).unwrap();
+            peak = decoded.iter().fold(peak, |m, s| m.max(s.abs()));
+        }
+        assert!(peak > 0.9 && peak <= 1.0, "peak {}", peak);
+    }
+    
     #[test]
     fn test_plc() {
         let mut decoder = OpusDecoder::new(48000, 2, 480).unwrap();
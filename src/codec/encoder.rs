@@ -6,7 +6,11 @@ use bytes::Bytes;
 use opus::{Application, Channels, Encoder};
 use crate::config::{OpusConfig, OpusBandwidth, OpusSignal};
 use crate::error::CodecError;
-use crate::protocol::TrackType;
+use crate::protocol::{PacketBuffer, PacketPool, TrackType};
+use crate::sync::media;
+
+/// Largest packet the encoder is given room for (one Opus frame is at most 1275 bytes)
+const MAX_PACKET_SIZE: usize = 4000;
 
 /// Opus encoder wrapper with optimized settings
 pub struct OpusEncoder {
@@ -14,6 +18,8 @@ pub struct OpusEncoder {
     config: OpusConfig,
     /// Encoding buffer (reused to avoid allocations)
     encode_buffer: Vec<u8>,
+    /// Memory `encode_into` writes packets to
+    pool: PacketPool,
     /// Frame counter for statistics
     frames_encoded: u64,
     /// Total bytes produced
@@ -43,13 +49,14 @@ impl OpusEncoder {
         // Configure encoder
         Self::configure_encoder(&mut encoder, &config)?;
         
-        // Pre-allocate encoding buffer (max Opus frame is about 1275 bytes)
-        let encode_buffer = vec![0u8; 4000];
+        // Pre-allocate encoding buffer
+        let encode_buffer = vec![0u8; MAX_PACKET_SIZE];
         
         Ok(Self {
             encoder,
             config,
             encode_buffer,
+            pool: PacketPool::default(),
             frames_encoded: 0,
             bytes_produced: 0,
         })
@@ -141,11 +148,7 @@ impl OpusEncoder {
     /// 
     /// Input must be interleaved f32 samples with length = frame_size * channels
     pub fn encode(&mut self, samples: &[f32]) -> Result<Bytes, CodecError> {
-        let expected_len = self.config.frame_size * self.config.channels as usize;
-        if samples.len() != expected_len {
-            return Err(CodecError::InvalidFrameSize(samples.len()));
-        }
-        
+        self.check_frame(samples)?;
         let size = self.encoder
             .encode_float(samples, &mut self.encode_buffer)
             .map_err(|e| CodecError::EncodingFailed(e.to_string()))?;
@@ -156,6 +159,58 @@ impl OpusEncoder {
         Ok(Bytes::copy_from_slice(&self.encode_buffer[..size]))
     }
     
+    /// Encode audio samples straight into a packet buffer, after its header room
+    /// 
+    /// Saves copying the packet out of the encoder and again into a datagram;
+    /// the memory comes from a [`PacketPool`] and is recycled.
+    pub fn encode_into(&mut self, samples: &[f32]) -> Result<PacketBuffer, CodecError> {
+        self.check_frame(samples)?;
+        let encoder = &mut self.encoder;
+        let packet = self
+            .pool
+            .fill(MAX_PACKET_SIZE, |out| encoder.encode_float(samples, out))
+            .map_err(|e| CodecError::EncodingFailed(e.to_string()))?;
+        
+        self.frames_encoded += 1;
+        self.bytes_produced += packet.len() as u64;
+        
+        Ok(packet)
+    }
+    
+    /// Reject input that isn't exactly one frame
+    fn check_frame(&self, samples: &[f32]) -> Result<(), CodecError> {
+        let expected_len = self.config.frame_size * self.config.channels as usize;
+        if samples.len() != expected_len {
+            return Err(CodecError::InvalidFrameSize(samples.len()));
+        }
+        Ok(())
+    }
+    
+    /// Check if an encoded packet is a DTX frame
+    /// 
+    /// With DTX enabled Opus emits 1-2 byte packets during silence; they
+    /// carry no audio and need not be transmitted.
+    pub fn is_dtx_packet(packet: &[u8]) -> bool {
+        packet.len() <= 2
+    }
+    
+    /// Encode the final partial frame, padded with silence
+    /// 
+    /// The pending samples are faded out so the stream ends without a click.
+    /// Returns None if nothing is pending.
+    pub fn flush(&mut self, pending: &[f32]) -> Result<Option<Bytes>, CodecError> {
+        if pending.is_empty() {
+            return Ok(None);
+        }
+        
+        let frame_len = self.samples_per_frame();
+        let mut samples = pending[..pending.len().min(frame_len)].to_vec();
+        crate::audio::fade::fade_out(&mut samples, self.config.channels);
+        samples.resize(frame_len, 0.0);
+        
+        self.encode(&samples).map(Some)
+    }
+    
     /// Update bitrate dynamically
     pub fn set_bitrate(&mut self, bitrate: u32) -> Result<(), CodecError> {
         self.encoder.set_bitrate(opus::Bitrate::Bits(bitrate as i32))
@@ -179,6 +234,12 @@ impl OpusEncoder {
         Ok(())
     }
     
+    /// Reset encoder state, keeping its settings
+    pub fn reset(&mut self) -> Result<(), CodecError> {
+        self.encoder.reset_state()
+            .map_err(|e| CodecError::EncoderInit(e.to_string()))
+    }
+    
     /// Get current configuration
     pub fn config(&self) -> &OpusConfig {
         &self.config
@@ -194,6 +255,11 @@ impl OpusEncoder {
         self.config.frame_size * self.config.channels as usize
     }
     
+    /// Get frame duration in media clock ticks
+    pub fn frame_ticks(&self) -> u32 {
+        media::frames_to_ticks(self.config.frame_size, self.config.sample_rate)
+    }
+    
     /// Get frame duration in milliseconds
     pub fn frame_duration_ms(&self) -> f32 {
         self.config.frame_duration_ms()
@@ -257,6 +323,32 @@ mod tests {
         assert!(encoded.len() < frame_size * 4); // Should be compressed
     }
     
+    #[test]
+    fn test_encode_into() {
+        let mut copying = OpusEncoder::music(48000, 2).unwrap();
+        let mut in_place = OpusEncoder::music(48000, 2).unwrap();
+        let samples: Vec<f32> = (0..copying.samples_per_frame()).map(|i| (i as f32 * 0.05).sin() * 0.3).collect();
+        
+        // Same packets either way, counted the same
+        for _ in 0..3 {
+            let expected = copying.encode(&samples).unwrap();
+            let packet = in_place.encode_into(&samples).unwrap();
+            assert_eq!(packet.payload(), &expected[..]);
+        }
+        assert_eq!(in_place.stats().bytes_produced, copying.stats().bytes_produced);
+        assert!(in_place.encode_into(&samples[1..]).is_err());
+    }
+    
+    #[test]
+    fn test_flush() {
+        let mut encoder = OpusEncoder::music(48000, 2).unwrap();
+        assert!(encoder.flush(&[]).unwrap().is_none());
+        
+        // Partial frame gets padded to a full one
+        let pending = vec![0.5f32; encoder.samples_per_frame() / 3];
+        assert!(encoder.flush(&pending).unwrap().is_some());
+    }
+    
     #[test]
     fn test_voice_encoder() {
         let mut encoder = OpusEncoder::voice(48000, 1).unwrap();
@@ -1,10 +1,17 @@
 //! Opus codec wrapper
 //!
 //! Provides per-track Opus encoding and decoding with
-//! configuration optimized for different audio types.
+//! configuration optimized for different audio types (with the `opus`
+//! feature); the Ogg framing in [`ogg`] is plain Rust and always built.
 
+#[cfg(feature = "opus")]
 pub mod encoder;
+#[cfg(feature = "opus")]
 pub mod decoder;
+pub mod ogg;
 
+#[cfg(feature = "opus")]
 pub use encoder::OpusEncoder;
+#[cfg(feature = "opus")]
 pub use decoder::OpusDecoder;
+pub use ogg::OggOpusWriter;
@@ -0,0 +1,226 @@
+//! Ogg Opus muxing (RFC 7845)
+//!
+//! Wraps Opus packets as they came off the network into Ogg pages, so a
+//! stream can be handed to ordinary players without decoding and
+//! re-encoding it. Each listener gets a logical stream of its own, starting
+//! with the `OpusHead` and `OpusTags` header pages.
+
+use crate::protocol::CRC32_OGG;
+
+/// Samples per second the granule position counts in
+const GRANULE_RATE: u32 = 48_000;
+
+/// Samples a player drops at the start; 80 ms lets a decoder joining
+/// mid-stream converge (RFC 7845 section 4.6)
+const PRE_SKIP: u16 = 3840;
+
+/// Vendor string in `OpusTags`
+const VENDOR: &str = concat!("lan-audio-streamer ", env!("CARGO_PKG_VERSION"));
+
+/// Page header flags
+const FLAG_BOS: u8 = 0x02;
+
+/// Lacing values one page can hold
+const MAX_SEGMENTS: usize = 255;
+
+/// Samples per channel in an Opus packet at 48 kHz, from its TOC byte
+/// (RFC 6716 section 3.1)
+pub fn packet_samples(packet: &[u8]) -> Option<u32> {
+    let toc = *packet.first()?;
+    let config = toc >> 3;
+    let frame = match config {
+        // SILK: 10, 20, 40, 60 ms
+        0..=11 => [480, 960, 1920, 2880][config as usize % 4],
+        // Hybrid: 10, 20 ms
+        12..=15 => [480, 960][config as usize % 2],
+        // CELT: 2.5, 5, 10, 20 ms
+        _ => [120, 240, 480, 960][config as usize % 4],
+    };
+    let frames = match toc & 0x03 {
+        0 => 1,
+        1 | 2 => 2,
+        _ => (*packet.get(1)? & 0x3F) as u32,
+    };
+    Some(frame * frames)
+}
+
+/// Builds one Ogg Opus logical stream
+pub struct OggOpusWriter {
+    serial: u32,
+    channels: u8,
+    page_sequence: u32,
+    /// Samples muxed so far
+    granule: u64,
+    /// Packets per audio page; fewer means lower latency, more overhead
+    packets_per_page: usize,
+    /// Packets waiting for the next page
+    packets: usize,
+    lacing: Vec<u8>,
+    data: Vec<u8>,
+}
+
+impl OggOpusWriter {
+    /// A stream with serial number `serial` declaring `channels` output channels
+    pub fn new(serial: u32, channels: u8, packets_per_page: usize) -> Self {
+        Self {
+            serial,
+            channels: channels.clamp(1, 2),
+            page_sequence: 0,
+            granule: 0,
+            packets_per_page: packets_per_page.max(1),
+            packets: 0,
+            lacing: Vec::new(),
+            data: Vec::new(),
+        }
+    }
+
+    /// The `OpusHead` and `OpusTags` pages that start the stream
+    pub fn headers(&mut self) -> Vec<u8> {
+        let mut head = Vec::with_capacity(19);
+        head.extend_from_slice(b"OpusHead");
+        head.push(1);
+        head.push(self.channels);
+        head.extend_from_slice(&PRE_SKIP.to_le_bytes());
+        head.extend_from_slice(&GRANULE_RATE.to_le_bytes());
+        // Output gain, channel mapping family 0
+        head.extend_from_slice(&0i16.to_le_bytes());
+        head.push(0);
+
+        let mut tags = Vec::new();
+        tags.extend_from_slice(b"OpusTags");
+        tags.extend_from_slice(&(VENDOR.len() as u32).to_le_bytes());
+        tags.extend_from_slice(VENDOR.as_bytes());
+        tags.extend_from_slice(&0u32.to_le_bytes());
+
+        let mut pages = self.page(FLAG_BOS, 0, &lacing(head.len()), &head);
+        pages.extend(self.page(0, 0, &lacing(tags.len()), &tags));
+        pages
+    }
+
+    /// Add an Opus packet; returns a page once enough packets are queued
+    pub fn push(&mut self, packet: &[u8]) -> Option<Vec<u8>> {
+        let samples = packet_samples(packet)?;
+        let segments = lacing(packet.len());
+        if segments.len() > MAX_SEGMENTS {
+            return None;
+        }
+        // Out of lacing values: the queued packets go out first
+        let full = self.lacing.len() + segments.len() > MAX_SEGMENTS;
+        let page = if full { self.flush() } else { None };
+        self.lacing.extend_from_slice(&segments);
+        self.data.extend_from_slice(packet);
+        self.granule += samples as u64;
+        self.packets += 1;
+        // After an early flush only this packet is queued, so at most one page comes out
+        if self.packets >= self.packets_per_page {
+            return self.flush();
+        }
+        page
+    }
+
+    /// A page with whatever packets are queued, if any
+    pub fn flush(&mut self) -> Option<Vec<u8>> {
+        if self.packets == 0 {
+            return None;
+        }
+        let lacing = std::mem::take(&mut self.lacing);
+        let data = std::mem::take(&mut self.data);
+        self.packets = 0;
+        Some(self.page(0, self.granule, &lacing, &data))
+    }
+
+    fn page(&mut self, flags: u8, granule: u64, lacing: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut page = Vec::with_capacity(27 + lacing.len() + data.len());
+        page.extend_from_slice(b"OggS");
+        page.push(0);
+        page.push(flags);
+        page.extend_from_slice(&granule.to_le_bytes());
+        page.extend_from_slice(&self.serial.to_le_bytes());
+        page.extend_from_slice(&self.page_sequence.to_le_bytes());
+        page.extend_from_slice(&[0; 4]);
+        page.push(lacing.len() as u8);
+        page.extend_from_slice(lacing);
+        page.extend_from_slice(data);
+        let crc = CRC32_OGG.checksum(&page);
+        page[22..26].copy_from_slice(&crc.to_le_bytes());
+        self.page_sequence += 1;
+        page
+    }
+}
+
+/// Lacing values for a packet of `len` bytes
+fn lacing(len: usize) -> Vec<u8> {
+    let mut values = vec![255; len / 255];
+    values.push((len % 255) as u8);
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Split pages back into (flags, granule, sequence, packets)
+    fn parse(mut data: &[u8]) -> Vec<(u8, u64, u32, Vec<Vec<u8>>)> {
+        let mut pages = Vec::new();
+        while !data.is_empty() {
+            assert_eq!(&data[..4], b"OggS");
+            let count = data[26] as usize;
+            let lacing = &data[27..27 + count];
+            let len = 27 + count + lacing.iter().map(|&l| l as usize).sum::<usize>();
+            let mut zeroed = data[..len].to_vec();
+            zeroed[22..26].fill(0);
+            assert_eq!(CRC32_OGG.checksum(&zeroed).to_le_bytes(), data[22..26]);
+
+            let mut packets = Vec::new();
+            let mut packet = Vec::new();
+            let mut body = &data[27 + count..len];
+            for &l in lacing {
+                packet.extend_from_slice(&body[..l as usize]);
+                body = &body[l as usize..];
+                if l < 255 {
+                    packets.push(std::mem::take(&mut packet));
+                }
+            }
+            let granule = u64::from_le_bytes(data[6..14].try_into().unwrap());
+            let sequence = u32::from_le_bytes(data[18..22].try_into().unwrap());
+            pages.push((data[5], granule, sequence, packets));
+            data = &data[len..];
+        }
+        pages
+    }
+
+    #[test]
+    fn test_ogg_opus_pages() {
+        assert_eq!(CRC32_OGG.checksum(b"123456789"), 0x89A1_897F);
+        // 20 ms CELT, 10 ms SILK, two 2.5 ms CELT frames, three 20 ms SILK frames
+        assert_eq!(packet_samples(&[0xF8]), Some(960));
+        assert_eq!(packet_samples(&[0x00]), Some(480));
+        assert_eq!(packet_samples(&[0x81]), Some(240));
+        assert_eq!(packet_samples(&[0x0B, 0x03]), Some(2880));
+        // Two 10 ms CELT frames, three 60 ms SILK frames
+        assert_eq!(packet_samples(&[0xF1, 0, 0]), Some(960));
+        assert_eq!(packet_samples(&[0x1B, 0x03]), Some(8640));
+        assert_eq!(packet_samples(&[]), None);
+
+        let mut writer = OggOpusWriter::new(7, 2, 2);
+        let headers = writer.headers();
+        let pages = parse(&headers);
+        assert_eq!(pages.len(), 2);
+        assert_eq!(pages[0].0, FLAG_BOS);
+        assert_eq!(&pages[0].3[0][..8], b"OpusHead");
+        assert_eq!(&pages[1].3[0][..8], b"OpusTags");
+
+        // A page every two packets; a 300-byte packet spans two lacing values
+        let big = [0xF8; 300];
+        assert!(writer.push(&[0xF8, 1]).is_none());
+        let page = writer.push(&big).unwrap();
+        assert!(writer.push(&[0xF8, 2]).is_none());
+        let last = writer.flush().unwrap();
+        assert_eq!(writer.flush(), None);
+
+        let pages = parse(&[page, last].concat());
+        assert_eq!((pages[0].1, pages[0].2), (1920, 2));
+        assert_eq!(pages[0].3, vec![vec![0xF8, 1], big.to_vec()]);
+        assert_eq!((pages[1].1, pages[1].2, pages[1].3.len()), (2880, 3, 1));
+    }
+}
@@ -1,12 +1,19 @@
 //! Configuration management
 
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
+use crate::audio::buffer::OverflowPolicy;
 use crate::constants::*;
-use crate::protocol::{TrackConfig, TrackType};
+use crate::hooks::HookEvent;
+use crate::notify::NotifyEvent;
+use crate::protocol::{TrackConfig, TrackProfile, TrackType};
+
+/// Prefix of environment variables overriding config fields
+pub const ENV_PREFIX: &str = "LAS_";
 
 /// Application configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct AppConfig {
     /// Network configuration
     pub network: NetworkConfig,
@@ -19,18 +26,110 @@ pub struct AppConfig {
     
     /// Pre-configured tracks
     pub tracks: Vec<TrackConfig>,
+    
+    /// Named track profiles tracks can refer to with `profile`
+    #[serde(default)]
+    pub profiles: BTreeMap<String, TrackProfile>,
+    
+    /// Browser (WebRTC) output configuration
+    #[serde(default)]
+    pub webrtc: WebRtcConfig,
+    
+    /// Clock synchronization configuration
+    #[serde(default)]
+    pub sync: SyncConfig,
+    
+    /// Receiver mixdown bus
+    #[serde(default)]
+    pub mix: MixConfig,
+    
+    /// Ogg Opus streams of received tracks for LAN players and Icecast
+    #[serde(default)]
+    pub http_stream: HttpStreamConfig,
+    
+    /// Clipping and silence alerts
+    #[serde(default)]
+    pub alerts: AlertConfig,
+    
+    /// Which senders the receiver accepts
+    #[serde(default)]
+    pub access: AccessConfig,
+    
+    /// Stream health webhooks
+    #[serde(default)]
+    pub notify: NotifyConfig,
+    
+    /// Commands and webhooks run on track start, stop and error
+    #[serde(default)]
+    pub hooks: Vec<TrackHook>,
+    
+    /// Thresholds for `/api/health`
+    #[serde(default)]
+    pub health: HealthConfig,
+    
+    /// Periodic per-track stats written to disk
+    #[serde(default)]
+    pub stats_log: StatsLogConfig,
+    
+    /// Retry policies for capture, playback and network failures
+    #[serde(default)]
+    pub recovery: RecoveryConfig,
+    
+    /// OSC control server
+    #[serde(default)]
+    pub osc: OscConfig,
+    
+    /// MQTT state publishing and commands
+    #[serde(default)]
+    pub mqtt: MqttConfig,
+    
+    /// Combined sender and receiver (`duplex` binary)
+    #[serde(default)]
+    pub duplex: DuplexConfig,
+    
+    /// Push-to-talk from the receiver to the sender
+    #[serde(default)]
+    pub talkback: TalkbackConfig,
+    
+    /// Relay/mixer node (`relay` binary)
+    #[serde(default)]
+    pub relay: RelayConfig,
+    
+    /// Sender settings changed from the receiver's web UI
+    #[serde(default)]
+    pub linked_control: LinkedControlConfig,
+    
+    /// Named mixer scenes
+    #[serde(default)]
+    pub scenes: SceneConfig,
+    
+    /// Wake-on-LAN for the receiver PC
+    #[serde(default)]
+    pub wake: WakeConfig,
+    
+    /// Names usable as a `device_id`, each standing for a device ID or stable ID
+    #[serde(default)]
+    pub device_aliases: BTreeMap<String, String>,
+    
+    /// Virtual cables receiver tracks play into
+    #[serde(default)]
+    pub virtual_devices: VirtualDeviceConfig,
 }
 
-impl Default for AppConfig {
-    fn default() -> Self {
-        Self {
-            network: NetworkConfig::default(),
-            audio: AudioConfig::default(),
-            ui: UiConfig::default(),
-            tracks: Vec::new(),
-        }
-    }
-}
+/// Fields that can hold credentials, as dotted paths (`[]` for every entry
+/// of a list): keys and passwords, but also webhook URLs, which carry their
+/// token in the path, and hook commands. Add new ones here.
+pub const SECRET_FIELDS: &[&str] = &[
+    "network.control_key",
+    "ui.auth_token",
+    "http_stream.icecast.url",
+    "http_stream.icecast.password",
+    "alerts.webhook_url",
+    "notify.webhooks[].url",
+    "hooks[].command",
+    "hooks[].url",
+    "mqtt.password",
+];
 
 /// Network configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -50,8 +149,121 @@ pub struct NetworkConfig {
     /// Socket receive buffer size
     pub recv_buffer_size: usize,
     
-    /// Enable SO_REUSEADDR
+    /// Enable SO_REUSEADDR (only with `port_range = 0`)
     pub reuse_addr: bool,
+    
+    /// Further ports tried after `udp_port` when it is taken (0 = fail instead)
+    #[serde(default = "default_port_range")]
+    pub port_range: u16,
+    
+    /// Total bitrate shared by all tracks in bits per second (0 = unlimited)
+    #[serde(default)]
+    pub bitrate_budget: u32,
+    
+    /// Minimum gap between outgoing packets in microseconds (0 = no pacing)
+    #[serde(default)]
+    pub pacing_us: u64,
+    
+    /// Append a CRC32 of the payload to every audio packet
+    #[serde(default)]
+    pub payload_crc: bool,
+    
+    /// Shared secret signing sender↔receiver control messages (None = unsigned)
+    ///
+    /// Both machines' clocks must agree to within two minutes.
+    #[serde(default)]
+    pub control_key: Option<String>,
+    
+    /// Track IDs a receiver asks its senders for (empty = all)
+    #[serde(default)]
+    pub subscribe: Vec<u8>,
+    
+    /// Stamp received packets in the kernel where supported (Linux)
+    #[serde(default = "default_kernel_timestamps")]
+    pub kernel_timestamps: bool,
+    
+    /// STUN server to learn this machine's public address from (`host:port`)
+    #[serde(default)]
+    pub stun_server: Option<String>,
+    
+    /// Relay running `relay.rendezvous` to meet remote peers at (`host:port`);
+    /// both ends need the relay's `control_key`
+    #[serde(default)]
+    pub rendezvous: Option<String>,
+    
+    /// Session name both peers register under at the rendezvous
+    #[serde(default)]
+    pub rendezvous_session: String,
+    
+    /// Public addresses of remote peers to punch through the NAT to
+    #[serde(default)]
+    pub punch_peers: Vec<String>,
+    
+    /// Tracks below this ID each use their own port, `udp_port + track_id` (0 = all on `udp_port`)
+    ///
+    /// Needs `port_range = 0`: a fallback port would land on a track's port.
+    #[serde(default)]
+    pub track_ports: u8,
+    
+    /// Append every audio datagram sent and received to this file, for `replay`
+    #[serde(default)]
+    pub packet_dump: Option<PathBuf>,
+    
+    /// Also send every packet from this local address, e.g. the WiFi adapter's (None = one link)
+    #[serde(default)]
+    pub redundant_bind: Option<String>,
+    
+    /// Receiver address on the second link, `ip` or `ip:port` (None = the same target)
+    #[serde(default)]
+    pub redundant_target: Option<String>,
+    
+    /// How the receiver reads its sockets
+    #[serde(default)]
+    pub receive_backend: ReceiveBackend,
+}
+
+/// Socket read path of the receiver
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiveBackend {
+    /// Non-blocking reads of every socket in turn (all platforms)
+    #[default]
+    Poll,
+    /// Queued reads completed through io_uring (Linux 5.11 or later)
+    IoUring,
+    /// Overlapped reads completed through an I/O completion port (Windows)
+    Overlapped,
+}
+
+impl NetworkConfig {
+    /// Port a track's audio uses on a peer whose base port is `base`
+    pub fn track_port(&self, base: u16, track_id: u8) -> u16 {
+        if track_id < self.track_ports {
+            base.saturating_add(track_id as u16)
+        } else {
+            base
+        }
+    }
+}
+
+fn default_kernel_timestamps() -> bool {
+    true
+}
+
+fn default_migrate_devices() -> bool {
+    true
+}
+
+fn default_jitter_late_threshold() -> usize {
+    DEFAULT_LATE_THRESHOLD
+}
+
+fn default_max_tracks() -> usize {
+    DEFAULT_MAX_TRACKS
+}
+
+fn default_port_range() -> u16 {
+    10
 }
 
 impl Default for NetworkConfig {
@@ -63,6 +275,22 @@ impl Default for NetworkConfig {
             send_buffer_size: 2 * 1024 * 1024, // 2 MB
             recv_buffer_size: 2 * 1024 * 1024, // 2 MB
             reuse_addr: true,
+            port_range: default_port_range(),
+            bitrate_budget: 0,
+            pacing_us: 0,
+            payload_crc: false,
+            control_key: None,
+            subscribe: Vec::new(),
+            kernel_timestamps: true,
+            stun_server: None,
+            rendezvous: None,
+            rendezvous_session: String::new(),
+            punch_peers: Vec::new(),
+            track_ports: 0,
+            packet_dump: None,
+            redundant_bind: None,
+            redundant_target: None,
+            receive_backend: ReceiveBackend::Poll,
         }
     }
 }
@@ -90,6 +318,26 @@ pub struct AudioConfig {
     
     /// Use low-latency WASAPI shared mode
     pub wasapi_low_latency: bool,
+    
+    /// Move a track whose device disappears to its `fallback_device` (or the default) until it returns
+    #[serde(default = "default_migrate_devices")]
+    pub migrate_devices: bool,
+    
+    /// Where mute/solo are applied to the audio
+    #[serde(default)]
+    pub mute_side: MuteSide,
+    
+    /// What the jitter buffer drops when a burst overfills it
+    #[serde(default)]
+    pub jitter_overflow: OverflowPolicy,
+    
+    /// Packets after its playout slot a missing packet counts as late rather than lost
+    #[serde(default = "default_jitter_late_threshold")]
+    pub jitter_late_threshold: usize,
+    
+    /// Most tracks open at once (up to 64); per-track resources are sized for it at startup
+    #[serde(default = "default_max_tracks")]
+    pub max_tracks: usize,
 }
 
 impl Default for AudioConfig {
@@ -102,41 +350,740 @@ impl Default for AudioConfig {
             jitter_buffer_ms: DEFAULT_JITTER_BUFFER_MS,
             wasapi_exclusive: false,
             wasapi_low_latency: true,
+            migrate_devices: true,
+            mute_side: MuteSide::default(),
+            jitter_overflow: OverflowPolicy::default(),
+            jitter_late_threshold: DEFAULT_LATE_THRESHOLD,
+            max_tracks: DEFAULT_MAX_TRACKS,
         }
     }
 }
 
+/// Side of the link that silences muted / non-soloed tracks
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum MuteSide {
+    /// Sender encodes silence, so every receiver hears the same mix
+    Sender,
+    /// Receiver silences playback
+    #[default]
+    Receiver,
+}
+
 /// UI configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UiConfig {
     /// HTTP server port
     pub http_port: u16,
     
+    /// Further ports tried after `http_port` when it is taken (0 = fail instead)
+    #[serde(default = "default_port_range")]
+    pub port_range: u16,
+    
     /// WebSocket port (usually same as HTTP)
     pub ws_port: u16,
     
     /// Bind address for web server
     pub bind_address: String,
     
-    /// Enable CORS
+    /// Let pages from other origins call the API (only with `auth_token`)
     pub enable_cors: bool,
     
     /// Static files directory
     pub static_dir: Option<PathBuf>,
+    
+    /// Bearer token required by the API and WebSocket (None = open)
+    ///
+    /// Stick to URL-safe characters; browsers pass it as `?token=`.
+    #[serde(default)]
+    pub auth_token: Option<String>,
+    
+    /// Serve over HTTPS/WSS
+    #[serde(default)]
+    pub tls: TlsConfig,
 }
 
 impl Default for UiConfig {
     fn default() -> Self {
         Self {
             http_port: DEFAULT_WS_PORT,
+            port_range: default_port_range(),
             ws_port: DEFAULT_WS_PORT,
             bind_address: "127.0.0.1".to_string(),
-            enable_cors: true,
+            enable_cors: false,
             static_dir: None,
+            auth_token: None,
+            tls: TlsConfig::default(),
+        }
+    }
+}
+
+/// Web UI TLS configuration (needs the `tls` feature)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TlsConfig {
+    /// Serve HTTPS/WSS instead of plain HTTP
+    pub enabled: bool,
+    
+    /// PEM certificate chain (None with no key = self-signed)
+    pub cert_path: Option<PathBuf>,
+    
+    /// PEM private key
+    pub key_path: Option<PathBuf>,
+    
+    /// Extra names for the self-signed certificate
+    pub hostnames: Vec<String>,
+}
+
+/// WebRTC output configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebRtcConfig {
+    /// Allow browsers to connect as receivers
+    pub enabled: bool,
+    
+    /// STUN/TURN server URLs handed to peer connections (empty for LAN-only)
+    pub ice_servers: Vec<String>,
+    
+    /// Maximum concurrent browser sessions
+    pub max_sessions: usize,
+    
+    /// Simulcast layer for browsers that don't ask for one (0 = primary encoding)
+    #[serde(default)]
+    pub layer: u8,
+}
+
+impl Default for WebRtcConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ice_servers: Vec::new(),
+            max_sessions: 4,
+            layer: 0,
+        }
+    }
+}
+
+/// Receiver mixdown bus configuration
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MixConfig {
+    /// Sum tracks into one extra output device
+    pub enabled: bool,
+    
+    /// Output device for the mix (None for the default output)
+    pub device_id: Option<String>,
+    
+    /// Tracks to include (empty for all)
+    pub tracks: Vec<u8>,
+}
+
+/// Ogg Opus stream output configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HttpStreamConfig {
+    /// Serve `/stream/<track>.ogg` and `/stream/mix.ogg` on the web UI port
+    pub enabled: bool,
+    
+    /// Maximum concurrent listeners
+    pub max_listeners: usize,
+    
+    /// Opus bitrate of the mixdown stream (tracks are passed through as sent)
+    pub mix_bitrate: u32,
+    
+    /// Push one stream to an Icecast server
+    pub icecast: Option<IcecastConfig>,
+}
+
+impl Default for HttpStreamConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_listeners: 8,
+            mix_bitrate: DEFAULT_BITRATE,
+            icecast: None,
+        }
+    }
+}
+
+/// Icecast source connection
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IcecastConfig {
+    /// Mount point URL, e.g. `http://icecast.lan:8000/live.ogg`
+    pub url: String,
+    
+    /// Source user
+    #[serde(default = "default_icecast_user")]
+    pub user: String,
+    
+    /// Source password
+    pub password: String,
+    
+    /// Track to push (None for the mixdown)
+    #[serde(default)]
+    pub track: Option<u8>,
+    
+    /// Stream name shown by the server
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+fn default_icecast_user() -> String {
+    "source".to_string()
+}
+
+/// Virtual audio cable (VB-Cable, VoiceMeeter, VAC) configuration
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VirtualDeviceConfig {
+    /// Friendly name of a virtual output -> receiver track, e.g. `"OBS Mic" = 0`
+    pub tracks: BTreeMap<String, u8>,
+    
+    /// Virtual outputs the receiver needs at startup, on top of the mapped ones
+    pub min_outputs: usize,
+}
+
+/// Signal and stream health alert configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AlertConfig {
+    /// Enable signal monitoring
+    pub enabled: bool,
+    
+    /// Sustained clipping needed before alerting, in milliseconds
+    pub clip_ms: u32,
+    
+    /// Level below which a track counts as silent (dBFS)
+    pub silence_threshold_db: f32,
+    
+    /// Silence needed before alerting, in seconds
+    pub silence_secs: u32,
+    
+    /// Packet loss over a stats interval that raises an alert (percent)
+    pub loss_threshold_pct: f32,
+    
+    /// Receiver alerts after this long without packets, in seconds (0 = never)
+    pub receive_timeout_secs: u32,
+    
+    /// Total bandwidth in kbit/s, headers included, that raises an alert (0 = none)
+    pub bandwidth_cap_kbps: u32,
+    
+    /// URL receiving a JSON POST for each alert; same as a `notify.webhooks`
+    /// entry with the `json` format and the alert events (kept for older configs)
+    pub webhook_url: Option<String>,
+}
+
+impl Default for AlertConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            clip_ms: 500,
+            silence_threshold_db: -60.0,
+            silence_secs: 10,
+            loss_threshold_pct: 5.0,
+            receive_timeout_secs: 5,
+            bandwidth_cap_kbps: 0,
+            webhook_url: None,
+        }
+    }
+}
+
+/// Health scoring thresholds, counted over a sliding window
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HealthConfig {
+    /// Window length in seconds
+    pub window_secs: u32,
+    
+    /// Xruns per track that make it degraded / critical
+    pub xruns_degraded: u64,
+    pub xruns_critical: u64,
+    
+    /// Packet loss percent that makes a track degraded / critical
+    pub loss_degraded_pct: f32,
+    pub loss_critical_pct: f32,
+    
+    /// Encode deadline misses per track that make it degraded / critical
+    pub deadline_misses_degraded: u64,
+    pub deadline_misses_critical: u64,
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        Self {
+            window_secs: 60,
+            xruns_degraded: 5,
+            xruns_critical: 50,
+            loss_degraded_pct: 2.0,
+            loss_critical_pct: 10.0,
+            deadline_misses_degraded: 5,
+            deadline_misses_critical: 50,
+        }
+    }
+}
+
+/// Stats log file format
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StatsLogFormat {
+    #[default]
+    Csv,
+    /// One JSON object per line
+    Jsonl,
+}
+
+/// What to do once a component runs out of retries
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GiveUpAction {
+    /// Leave the component stopped; the rest keeps running
+    #[default]
+    Stop,
+    /// Shut the process down
+    Exit,
+}
+
+/// How a component retries after failing
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RecoveryPolicy {
+    /// Retries before giving up (unset = retry forever)
+    pub max_retries: Option<u32>,
+    
+    /// Wait before the first retry
+    pub initial_backoff_ms: u64,
+    
+    /// Longest wait between retries
+    pub max_backoff_ms: u64,
+    
+    /// Backoff growth per retry
+    pub multiplier: f64,
+    
+    /// Seconds without failures after which the retry count starts over
+    pub reset_after_secs: u64,
+    
+    pub give_up: GiveUpAction,
+}
+
+impl RecoveryPolicy {
+    /// Keep restarting with backoff (unattended installs)
+    pub fn restart_forever() -> Self {
+        Self {
+            max_retries: None,
+            initial_backoff_ms: 500,
+            max_backoff_ms: 30_000,
+            multiplier: 2.0,
+            reset_after_secs: 60,
+            give_up: GiveUpAction::Stop,
+        }
+    }
+    
+    /// Exit on the first failure (debugging)
+    pub fn fail_fast() -> Self {
+        Self {
+            max_retries: Some(0),
+            give_up: GiveUpAction::Exit,
+            ..Self::restart_forever()
         }
     }
 }
 
+impl Default for RecoveryPolicy {
+    fn default() -> Self {
+        Self::restart_forever()
+    }
+}
+
+/// Recovery policy per subsystem
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RecoveryConfig {
+    /// Input device errors (sender)
+    pub capture: RecoveryPolicy,
+    
+    /// Output device errors (receiver)
+    pub playback: RecoveryPolicy,
+    
+    /// Socket setup and a stalled send queue
+    pub network: RecoveryPolicy,
+}
+
+/// Per-track stats logging to disk
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StatsLogConfig {
+    /// Write stats rows
+    pub enabled: bool,
+    
+    /// File to append to; rotated files get `.1`, `.2`, ... appended
+    pub path: PathBuf,
+    
+    /// File format
+    pub format: StatsLogFormat,
+    
+    /// Seconds between rows
+    pub interval_secs: u32,
+    
+    /// Rotate once the file reaches this size in bytes
+    pub max_file_bytes: u64,
+    
+    /// Rotated files to keep
+    pub retention: usize,
+}
+
+impl Default for StatsLogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: PathBuf::from("stats.csv"),
+            format: StatsLogFormat::Csv,
+            interval_secs: 5,
+            max_file_bytes: 10 * 1024 * 1024,
+            retention: 5,
+        }
+    }
+}
+
+/// OSC control (TouchOSC, QLab and similar)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OscConfig {
+    /// Listen for OSC messages
+    pub enabled: bool,
+    
+    /// Local bind address
+    pub bind_address: String,
+    
+    /// UDP port for incoming messages
+    pub port: u16,
+    
+    /// `host:port` targets sent track state changes (e.g. a TouchOSC layout)
+    pub feedback: Vec<String>,
+}
+
+impl Default for OscConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bind_address: "0.0.0.0".to_string(),
+            port: 9000,
+            feedback: Vec::new(),
+        }
+    }
+}
+
+/// MQTT client configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MqttConfig {
+    /// Connect to the broker
+    pub enabled: bool,
+    
+    /// Broker `host:port` (plain TCP)
+    pub broker: String,
+    
+    /// Client ID (empty = derived from the role and process ID)
+    pub client_id: String,
+    
+    pub username: Option<String>,
+    
+    pub password: Option<String>,
+    
+    /// Topics go under `<topic_prefix>/sender` or `<topic_prefix>/receiver`
+    pub topic_prefix: String,
+    
+    /// Interval between level and health updates (ms)
+    pub interval_ms: u64,
+}
+
+impl Default for MqttConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            broker: "localhost:1883".to_string(),
+            client_id: String::new(),
+            username: None,
+            password: None,
+            topic_prefix: "lan_audio".to_string(),
+            interval_ms: 1000,
+        }
+    }
+}
+
+/// Full-duplex operation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DuplexConfig {
+    /// Received track N is managed as track `remote_track_base + N`
+    pub remote_track_base: u8,
+    
+    /// Mute capture while it is picking up received audio
+    pub loop_guard: bool,
+    
+    /// Envelope correlation (0..1) at which capture counts as a loop
+    pub loop_threshold: f32,
+    
+    /// Longest playback-to-capture delay checked
+    pub loop_max_delay_ms: u32,
+}
+
+impl Default for DuplexConfig {
+    fn default() -> Self {
+        Self {
+            remote_track_base: 128,
+            loop_guard: true,
+            loop_threshold: 0.9,
+            loop_max_delay_ms: 500,
+        }
+    }
+}
+
+/// Talkback from the receiver's microphone to the sender's headphones
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TalkbackConfig {
+    /// Open the microphone (receiver) and talkback output (sender)
+    pub enabled: bool,
+    
+    /// Receiver microphone (None = default input)
+    pub input_device: Option<String>,
+    
+    /// Sender headphones (None = default output)
+    pub output_device: Option<String>,
+    
+    /// Opus bitrate of the mono talkback stream
+    pub bitrate: u32,
+}
+
+impl Default for TalkbackConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            input_device: None,
+            output_device: None,
+            bitrate: 24_000,
+        }
+    }
+}
+
+/// Relay/mixer node
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RelayConfig {
+    /// Receivers everything is sent on to (`host:port`)
+    pub targets: Vec<String>,
+    
+    /// Relay track IDs forwarded untouched (empty for all that are not mixed)
+    pub forward: Vec<u8>,
+    
+    /// Relay track IDs decoded and mixed into one track (empty for no mix)
+    pub mix: Vec<u8>,
+    
+    /// Track ID of the mix
+    pub mix_track_id: u8,
+    
+    /// Opus bitrate of the mix
+    pub mix_bitrate: u32,
+    
+    /// Ogg/Opus files sent on as relay tracks, without re-encoding
+    pub files: Vec<RelayFile>,
+    
+    /// Introduce peers registering under the same session, for NAT hole
+    /// punching (needs `network.control_key`, shared with the peers)
+    pub rendezvous: bool,
+}
+
+impl Default for RelayConfig {
+    fn default() -> Self {
+        Self {
+            targets: Vec::new(),
+            forward: Vec::new(),
+            mix: Vec::new(),
+            mix_track_id: 200,
+            mix_bitrate: 128_000,
+            files: Vec::new(),
+            rendezvous: false,
+        }
+    }
+}
+
+/// An Ogg/Opus file played into the relay as one track
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RelayFile {
+    /// Path of the `.opus` file
+    pub path: PathBuf,
+    
+    /// Relay track ID it is sent as
+    pub track_id: u8,
+    
+    /// Start over at the end of the file
+    #[serde(default)]
+    pub looped: bool,
+}
+
+/// Linked control of the sender from the receiver
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LinkedControlConfig {
+    /// Accept changes from the target (sender) or offer them in the web UI (receiver)
+    pub enabled: bool,
+}
+
+/// Named mixer scenes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SceneConfig {
+    /// JSON file scenes are kept in (None = until restart)
+    pub path: Option<PathBuf>,
+    
+    /// Crossfade when a recall does not give one, in milliseconds
+    pub crossfade_ms: u32,
+}
+
+impl Default for SceneConfig {
+    fn default() -> Self {
+        Self {
+            path: Some(PathBuf::from("scenes.json")),
+            crossfade_ms: 0,
+        }
+    }
+}
+
+/// Wake-on-LAN target
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WakeConfig {
+    /// MAC address of the receiver PC (`aa:bb:cc:dd:ee:ff`)
+    pub mac: Option<String>,
+    
+    /// Where the magic packet goes (`host:port`, usually a broadcast address)
+    pub broadcast: String,
+}
+
+impl Default for WakeConfig {
+    fn default() -> Self {
+        Self {
+            mac: None,
+            broadcast: "255.255.255.255:9".to_string(),
+        }
+    }
+}
+
+/// Webhook payload style
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookFormat {
+    /// Full event as JSON
+    #[default]
+    Json,
+    /// Discord `content` message
+    Discord,
+    /// Slack `text` message
+    Slack,
+}
+
+/// One webhook receiving stream health notifications
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookTarget {
+    /// http:// or https:// URL (https needs the `https` feature, on by default)
+    pub url: String,
+    
+    /// Payload style
+    #[serde(default)]
+    pub format: WebhookFormat,
+    
+    /// Events to send (empty = all)
+    #[serde(default)]
+    pub events: Vec<NotifyEvent>,
+}
+
+/// Stream health notification configuration
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotifyConfig {
+    /// Webhooks to notify
+    pub webhooks: Vec<WebhookTarget>,
+}
+
+/// Command or webhook run when tracks start, stop or fail
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrackHook {
+    /// Tracks the hook runs for (empty = all)
+    #[serde(default)]
+    pub tracks: Vec<u8>,
+    
+    /// Events the hook runs on (empty = all)
+    #[serde(default)]
+    pub events: Vec<HookEvent>,
+    
+    /// Shell command, given the event in `LAS_*` environment variables
+    #[serde(default)]
+    pub command: Option<String>,
+    
+    /// http:// or https:// URL to POST the event to as JSON
+    #[serde(default)]
+    pub url: Option<String>,
+}
+
+/// Receiver access control
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AccessConfig {
+    /// Sender IPs or CIDR networks allowed to stream (empty = anyone)
+    #[serde(default)]
+    pub allowed_sources: Vec<String>,
+    
+    /// Packets per second accepted from one source (0 = unlimited, None =
+    /// enough for `audio.max_tracks` tracks of the shortest frames)
+    #[serde(default)]
+    pub max_packets_per_sec: Option<u32>,
+    
+    /// Most tracks created automatically from incoming streams, at most
+    /// `audio.max_tracks` (None = `audio.max_tracks`)
+    #[serde(default)]
+    pub max_auto_tracks: Option<usize>,
+}
+
+/// Clock synchronization configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncConfig {
+    /// Where the sender/receiver clock relationship comes from
+    pub clock_source: ClockSource,
+    
+    /// Interval between clock exchanges in milliseconds
+    pub interval_ms: u64,
+    
+    /// Number of exchanges kept for the minimum-delay filter
+    pub window: usize,
+    
+    /// Tracks whose playout is aligned on the receiver (empty to disable)
+    pub align_tracks: Vec<u8>,
+    
+    /// Misalignment tolerated before correcting, in microseconds
+    pub align_tolerance_us: u64,
+    
+    /// Maximum samples inserted/dropped per frame while aligning
+    pub align_max_step: usize,
+}
+
+impl Default for SyncConfig {
+    fn default() -> Self {
+        Self {
+            clock_source: ClockSource::Estimated,
+            interval_ms: 1000,
+            window: 8,
+            align_tracks: Vec::new(),
+            align_tolerance_us: 500,
+            align_max_step: 4,
+        }
+    }
+}
+
+/// Clock source for cross-device timestamps
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ClockSource {
+    /// Trust the system clocks (already PTP/NTP-disciplined)
+    System,
+    /// Estimate the offset over the control channel
+    Estimated,
+}
+
 /// Opus encoder configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OpusConfig {
@@ -284,9 +1231,207 @@ impl AppConfig {
         let content = std::fs::read_to_string(path)?;
         let config: Self = toml::from_str(&content)
             .map_err(|e| crate::Error::Config(e.to_string()))?;
+        config.validate()?;
+        Ok(config)
+    }
+    
+    /// Load `path`, else the default path if it exists, else the defaults;
+    /// then apply environment overrides
+    pub fn load_or_default(path: Option<&PathBuf>) -> crate::Result<Self> {
+        let config = match Self::path_in_use(path) {
+            Some(path) => Self::load(&path)?,
+            None => Self::default(),
+        };
+        let config = config.with_env_overrides()?;
+        config.validate()?;
         Ok(config)
     }
     
+    /// Webhooks to notify: `notify.webhooks`, then `alerts.webhook_url`
+    pub fn notify_webhooks(&self) -> Vec<WebhookTarget> {
+        let alerts = self.alerts.webhook_url.iter().map(|url| WebhookTarget {
+            url: url.clone(),
+            format: WebhookFormat::Json,
+            events: NotifyEvent::ALERTS.to_vec(),
+        });
+        self.notify.webhooks.iter().cloned().chain(alerts).collect()
+    }
+    
+    /// Most tracks a receiver creates from incoming streams
+    pub fn max_auto_tracks(&self) -> usize {
+        self.access.max_auto_tracks.unwrap_or(self.audio.max_tracks).min(self.audio.max_tracks)
+    }
+    
+    /// Packets per second a receiver accepts from one source
+    /// 
+    /// Unless configured, every track at 2.5 ms frames with as many packets
+    /// again for FEC, simulcast and control traffic.
+    pub fn max_packets_per_sec(&self) -> u32 {
+        self.access.max_packets_per_sec.unwrap_or_else(|| {
+            let per_track = (1000.0 / MIN_FRAME_SIZE_MS) as u32;
+            self.audio.max_tracks as u32 * per_track * 2
+        })
+    }
+    
+    /// Check the track limit against itself and everything sized by it
+    pub fn validate(&self) -> crate::Result<()> {
+        let max_tracks = self.audio.max_tracks;
+        if !(1..=MAX_TRACKS).contains(&max_tracks) {
+            return Err(crate::Error::Config(format!(
+                "audio.max_tracks {} out of range (1 to {})",
+                max_tracks, MAX_TRACKS
+            )));
+        }
+        if self.tracks.len() > max_tracks {
+            return Err(crate::Error::Config(format!(
+                "{} tracks configured but audio.max_tracks is {}",
+                self.tracks.len(), max_tracks
+            )));
+        }
+        if self.network.track_ports as usize > max_tracks {
+            return Err(crate::Error::Config(format!(
+                "network.track_ports {} exceeds audio.max_tracks ({})",
+                self.network.track_ports, max_tracks
+            )));
+        }
+        // A fallback base port would put track 0 on another track's port
+        if self.network.track_ports > 1 && self.network.port_range > 0 {
+            return Err(crate::Error::Config(format!(
+                "network.track_ports {} uses UDP ports {}-{}, which network.port_range {} also falls back to; set port_range = 0",
+                self.network.track_ports,
+                self.network.udp_port,
+                self.network.udp_port as u32 + self.network.track_ports as u32 - 1,
+                self.network.port_range
+            )));
+        }
+        if let Some(auto) = self.access.max_auto_tracks.filter(|&auto| auto > max_tracks) {
+            return Err(crate::Error::Config(format!(
+                "access.max_auto_tracks {} exceeds audio.max_tracks ({})",
+                auto, max_tracks
+            )));
+        }
+        if let Some(index) = self.hooks.iter().position(|h| h.command.is_none() && h.url.is_none()) {
+            return Err(crate::Error::Config(format!("hooks[{}] has neither a command nor a url", index)));
+        }
+        
+        // Anyone could register or be introduced otherwise
+        let keyed = self.network.control_key.as_ref().is_some_and(|key| !key.is_empty());
+        if !keyed && (self.relay.rendezvous || self.network.rendezvous.is_some()) {
+            return Err(crate::Error::Config(
+                "rendezvous needs network.control_key to sign registrations".to_string(),
+            ));
+        }
+        Ok(())
+    }
+    
+    /// Override fields from `LAS_`-prefixed environment variables
+    ///
+    /// `__` separates the path, so `LAS_NETWORK__UDP_PORT=6000` sets
+    /// `network.udp_port` and `LAS_TRACKS__0__BITRATE` the first track's
+    /// bitrate. Values are read as TOML (`true`, `64000`, `[0, 2]`), or as
+    /// plain text where the field is a string. Variables naming no setting
+    /// are logged and skipped; a value a setting can't take is an error.
+    pub fn with_env_overrides(self) -> crate::Result<Self> {
+        self.apply_env(std::env::vars())
+    }
+    
+    fn apply_env(self, vars: impl IntoIterator<Item = (String, String)>) -> crate::Result<Self> {
+        let mut overrides: Vec<(String, String)> = vars
+            .into_iter()
+            .filter(|(key, _)| key.starts_with(ENV_PREFIX))
+            .collect();
+        if overrides.is_empty() {
+            return Ok(self);
+        }
+        overrides.sort();
+        
+        let invalid = |key: &str, reason: String| crate::Error::Config(format!("{}: {}", key, reason));
+        let mut root = toml::Value::try_from(&self).map_err(|e| crate::Error::Config(e.to_string()))?;
+        for (key, raw) in overrides {
+            let path: Vec<String> = key[ENV_PREFIX.len()..].split("__").map(str::to_lowercase).collect();
+            
+            // Strings take the text as is; unset optional fields have no type to go by
+            let text = toml::Value::String(raw.clone());
+            let candidates = match env_lookup(&root, &path) {
+                Some(toml::Value::String(_)) => vec![text],
+                _ => format!("v = {}", raw)
+                    .parse::<toml::Table>()
+                    .ok()
+                    .and_then(|mut t| t.remove("v"))
+                    .into_iter()
+                    .chain([text])
+                    .collect(),
+            };
+            let mut error = None;
+            let mut applied = false;
+            let mut unknown = false;
+            for value in candidates {
+                let mut next = root.clone();
+                if !env_insert(&mut next, &path, value) {
+                    unknown = true;
+                    break;
+                }
+                match next.clone().try_into::<Self>() {
+                    // Unknown keys deserialize fine but do not survive the round trip
+                    Ok(config) => {
+                        unknown = !toml::Value::try_from(&config).is_ok_and(|v| env_lookup(&v, &path).is_some());
+                        if !unknown {
+                            root = next;
+                            applied = true;
+                        }
+                        break;
+                    }
+                    Err(e) => {
+                        error.get_or_insert(e);
+                    }
+                }
+            }
+            // Other programs' variables can share the prefix (hooks set `LAS_EVENT`)
+            if unknown {
+                tracing::warn!("Ignoring {}: no such setting", key);
+                continue;
+            }
+            if !applied {
+                return Err(invalid(&key, error.map(|e| e.message().to_string()).unwrap_or_default()));
+            }
+            tracing::info!("Config override from {}", key);
+        }
+        root.try_into().map_err(|e: toml::de::Error| crate::Error::Config(e.to_string()))
+    }
+    
+    /// Undo `with_env_overrides` before saving: each field a `LAS_` variable
+    /// sets goes back to its value in `file`, the config as read from disk,
+    /// so env-only settings like `LAS_UI__AUTH_TOKEN` never get written out
+    pub fn without_env_overrides(self, file: &AppConfig) -> crate::Result<Self> {
+        self.restore_env(file, std::env::vars())
+    }
+    
+    fn restore_env(self, file: &AppConfig, vars: impl IntoIterator<Item = (String, String)>) -> crate::Result<Self> {
+        let to_value = |config: &AppConfig| toml::Value::try_from(config).map_err(|e| crate::Error::Config(e.to_string()));
+        let mut root = to_value(&self)?;
+        let file = to_value(file)?;
+        for (key, _) in vars.into_iter().filter(|(key, _)| key.starts_with(ENV_PREFIX)) {
+            let path: Vec<String> = key[ENV_PREFIX.len()..].split("__").map(str::to_lowercase).collect();
+            match env_lookup(&file, &path) {
+                Some(value) => {
+                    env_insert(&mut root, &path, value.clone());
+                }
+                // Unset in the file (an empty `Option`) unless the whole parent is new
+                None if env_lookup(&file, &path[..path.len() - 1]).is_some() => env_remove(&mut root, &path),
+                None => {}
+            }
+        }
+        root.try_into().map_err(|e: toml::de::Error| crate::Error::Config(e.to_string()))
+    }
+    
+    /// The file `load_or_default` reads, if any
+    pub fn path_in_use(path: Option<&PathBuf>) -> Option<PathBuf> {
+        match path {
+            Some(path) => Some(path.clone()),
+            None => Self::default_path().filter(|p| p.exists()),
+        }
+    }
+    
     /// Save configuration to file
     pub fn save(&self, path: &PathBuf) -> crate::Result<()> {
         let content = toml::to_string_pretty(self)
@@ -301,3 +1446,189 @@ impl AppConfig {
             .map(|dirs| dirs.config_dir().join("config.toml"))
     }
 }
+
+/// The value at an environment override path, if set
+fn env_lookup<'a>(root: &'a toml::Value, path: &[String]) -> Option<&'a toml::Value> {
+    path.iter().try_fold(root, |node, part| match node {
+        toml::Value::Array(items) => items.get(part.parse::<usize>().ok()?),
+        toml::Value::Table(table) => table.get(part),
+        _ => None,
+    })
+}
+
+/// Remove the value at an environment override path, if there is one
+fn env_remove(root: &mut toml::Value, path: &[String]) {
+    let Some((field, parents)) = path.split_last() else {
+        return;
+    };
+    let parent = parents.iter().try_fold(root, |node, part| match node {
+        toml::Value::Array(items) => items.get_mut(part.parse::<usize>().ok()?),
+        toml::Value::Table(table) => table.get_mut(part),
+        _ => None,
+    });
+    if let Some(toml::Value::Table(table)) = parent {
+        table.remove(field);
+    }
+}
+
+/// Set the value at an environment override path, adding missing tables;
+/// false if the path runs into a value or past the end of an array
+fn env_insert(root: &mut toml::Value, path: &[String], value: toml::Value) -> bool {
+    let Some((field, parents)) = path.split_last() else {
+        return false;
+    };
+    let mut node = root;
+    for part in parents {
+        let next = match node {
+            toml::Value::Array(items) => part.parse::<usize>().ok().and_then(|i| items.get_mut(i)),
+            toml::Value::Table(table) => {
+                Some(table.entry(part.clone()).or_insert_with(|| toml::Value::Table(toml::Table::new())))
+            }
+            _ => None,
+        };
+        match next {
+            Some(next) => node = next,
+            None => return false,
+        }
+    }
+    match node {
+        toml::Value::Table(table) => {
+            table.insert(field.clone(), value);
+            true
+        }
+        toml::Value::Array(items) => match field.parse::<usize>().ok().and_then(|i| items.get_mut(i)) {
+            Some(item) => {
+                *item = value;
+                true
+            }
+            None => false,
+        },
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_env_overrides() {
+        let vars = |pairs: &[(&str, &str)]| -> Vec<(String, String)> {
+            pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+        };
+        let mut config = AppConfig::default();
+        config.tracks.push(TrackConfig::default());
+        let config = config
+            .apply_env(vars(&[
+                ("LAS_NETWORK__UDP_PORT", "6000"),
+                ("LAS_NETWORK__REMOTE_ADDRESS", "192.168.1.20:6000"),
+                ("LAS_NETWORK__SUBSCRIBE", "[0, 2]"),
+                ("LAS_UI__AUTH_TOKEN", "1234"),
+                ("LAS_UI__BIND_ADDRESS", "127.0.0.1"),
+                ("LAS_TRACKS__0__FEC_ENABLED", "true"),
+                ("PATH", "/usr/bin"),
+            ]))
+            .unwrap();
+        assert_eq!(config.network.udp_port, 6000);
+        assert_eq!(config.network.remote_address.as_deref(), Some("192.168.1.20:6000"));
+        assert_eq!(config.network.subscribe, vec![0, 2]);
+        assert_eq!(config.ui.auth_token.as_deref(), Some("1234"));
+        assert_eq!(config.ui.bind_address, "127.0.0.1");
+        assert!(config.tracks[0].fec_enabled);
+
+        assert!(AppConfig::default().apply_env(vars(&[("LAS_NETWORK__UDP_PORT", "lots")])).is_err());
+
+        // Unknown settings are skipped, the rest still apply
+        let config = AppConfig::default()
+            .apply_env(vars(&[
+                ("LAS_NETWRK__UDP_PORT", "6000"),
+                ("LAS_TRACKS__3__BITRATE", "1"),
+                ("LAS_EVENT", "start"),
+                ("LAS_UI__HTTP_PORT", "9000"),
+            ]))
+            .unwrap();
+        assert_eq!(config.network.udp_port, AppConfig::default().network.udp_port);
+        assert!(config.tracks.is_empty());
+        assert_eq!(config.ui.http_port, 9000);
+    }
+
+    #[test]
+    fn test_restore_env() {
+        let vars = vec![
+            ("LAS_UI__AUTH_TOKEN".to_string(), "secret".to_string()),
+            ("LAS_NETWORK__UDP_PORT".to_string(), "6000".to_string()),
+        ];
+        let file = AppConfig::default();
+        let mut live = file.clone().apply_env(vars.clone()).unwrap();
+        assert_eq!(live.ui.auth_token.as_deref(), Some("secret"));
+
+        // Edits made on top of the live config survive, the overrides do not
+        live.ui.http_port = 9000;
+        let saved = live.restore_env(&file, vars).unwrap();
+        assert_eq!(saved.ui.auth_token, None);
+        assert_eq!(saved.network.udp_port, file.network.udp_port);
+        assert_eq!(saved.ui.http_port, 9000);
+    }
+
+    #[test]
+    fn test_validate_max_tracks() {
+        let mut config = AppConfig::default();
+        assert!(config.validate().is_ok());
+        config.audio.max_tracks = 65;
+        assert!(config.validate().is_err());
+
+        config.audio.max_tracks = 2;
+        config.tracks = vec![TrackConfig { track_id: Some(1), ..Default::default() }];
+        config.tracks.push(TrackConfig { track_id: Some(128), ..Default::default() });
+        assert!(config.validate().is_ok());
+        config.tracks.push(TrackConfig { track_id: Some(2), ..Default::default() });
+        assert!(config.validate().is_err());
+        config.tracks.pop();
+        config.network.track_ports = 4;
+        assert!(config.validate().is_err());
+        
+        // Per-track ports and a fallback port range overlap
+        config.network.track_ports = 2;
+        assert!(config.validate().is_err());
+        config.network.port_range = 0;
+        assert!(config.validate().is_ok());
+        config.network.port_range = default_port_range();
+        config.network.track_ports = 0;
+        
+        // Auto-created tracks follow the track limit unless capped below it
+        assert_eq!(config.max_auto_tracks(), 2);
+        config.access.max_auto_tracks = Some(1);
+        assert!(config.validate().is_ok());
+        assert_eq!(config.max_auto_tracks(), 1);
+        config.access.max_auto_tracks = Some(3);
+        assert!(config.validate().is_err());
+        config.access.max_auto_tracks = None;
+        
+        // The packet rate limit scales with the track limit
+        assert_eq!(config.max_packets_per_sec(), 2 * 400 * 2);
+        config.audio.max_tracks = MAX_TRACKS;
+        assert_eq!(config.max_packets_per_sec(), MAX_TRACKS as u32 * 800);
+        config.access.max_packets_per_sec = Some(0);
+        assert_eq!(config.max_packets_per_sec(), 0);
+        
+        // The alert webhook is one more notify webhook
+        let mut config = AppConfig::default();
+        assert!(config.notify_webhooks().is_empty());
+        config.alerts.webhook_url = Some("http://hub.lan/alerts".to_string());
+        let webhooks = config.notify_webhooks();
+        assert_eq!(webhooks.len(), 1);
+        assert!(webhooks[0].events.contains(&NotifyEvent::Silence) && !webhooks[0].events.contains(&NotifyEvent::StreamStarted));
+        
+        // A hook has to do something
+        let mut config = AppConfig::default();
+        config.hooks.push(TrackHook { tracks: vec![0], events: Vec::new(), command: None, url: None });
+        assert!(config.validate().is_err());
+        
+        // Rendezvous registrations are signed
+        let mut config = AppConfig::default();
+        config.relay.rendezvous = true;
+        assert!(config.validate().is_err());
+        config.network.control_key = Some("gig secret".to_string());
+        assert!(config.validate().is_ok());
+    }
+}
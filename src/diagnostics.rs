@@ -0,0 +1,400 @@
+//! Remote log level and diagnostics bundle
+//!
+//! [`init_logging`] installs the binaries' subscriber, and [`LogControl`]
+//! swaps its filter in the running process, so a support session can turn
+//! on `debug` for one module without a restart. The event log sits behind
+//! the same filter, so whatever it lets through ends up in `events.json`.
+//! [`Diagnostics::bundle`] collects what a support request needs into one
+//! zip archive:
+//!
+//! - `info.json`: version, platform, uptime and the current log filter
+//! - `config.json`: the running config, with the [`SECRET_FIELDS`] redacted
+//! - `devices.json`: the audio devices this machine sees
+//! - `events.json`: the most recent entries of the event log
+//! - `stats.json`: per-track stats, session totals, health and bandwidth
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Instant;
+use tracing_subscriber::{layer::SubscriberExt, reload, util::SubscriberInitExt, EnvFilter};
+
+use crate::config::{AppConfig, SECRET_FIELDS};
+use crate::error::{Error, Result};
+use crate::events::{EventLog, EventLogLayer, Severity};
+use crate::protocol::{crc32, AudioDeviceInfo};
+use crate::reload::ConfigReload;
+use crate::sync::now_us;
+use crate::tracks::TrackManager;
+
+/// Events included in a bundle
+pub const BUNDLE_EVENTS: usize = 1000;
+
+/// What a redacted secret reads as
+pub const REDACTED: &str = "<redacted>";
+
+/// Install the process-wide subscriber: console output (colored if `ansi`)
+/// and `events`, behind a filter from `RUST_LOG` (default `info`) that the
+/// returned [`LogControl`] changes
+pub fn init_logging(events: Arc<EventLog>, ansi: bool) -> LogControl {
+    let filter = std::env::var("RUST_LOG").unwrap_or_else(|_| "info".into());
+    let (filter_layer, handle) = reload::Layer::new(EnvFilter::new(&filter));
+    tracing_subscriber::registry()
+        .with(filter_layer)
+        .with(tracing_subscriber::fmt::layer().with_ansi(ansi))
+        .with(EventLogLayer::new(events, Severity::Trace))
+        .init();
+    LogControl::new(handle, filter)
+}
+
+/// Runtime control over the tracing filter
+pub struct LogControl {
+    filter: Mutex<String>,
+    reload: Box<dyn Fn(EnvFilter) -> std::result::Result<(), reload::Error> + Send + Sync>,
+}
+
+impl LogControl {
+    /// Control the filter behind a reload layer installed with `filter`
+    pub fn new<S: tracing::Subscriber + 'static>(handle: reload::Handle<EnvFilter, S>, filter: impl Into<String>) -> Self {
+        Self {
+            filter: Mutex::new(filter.into()),
+            reload: Box::new(move |filter| handle.reload(filter)),
+        }
+    }
+
+    /// Current filter, in `RUST_LOG` syntax
+    pub fn filter(&self) -> String {
+        self.filter.lock().clone()
+    }
+
+    /// Replace the filter, e.g. `info,lan_audio_streamer::network=debug`
+    pub fn set_filter(&self, filter: &str) -> Result<()> {
+        let filter = filter.trim();
+        let parsed = EnvFilter::try_new(filter)
+            .map_err(|e| Error::Config(format!("Invalid log filter '{}': {}", filter, e)))?;
+        (self.reload)(parsed).map_err(|e| Error::Config(format!("Cannot change log filter: {}", e)))?;
+        *self.filter.lock() = filter.to_string();
+        tracing::info!("Log filter set to '{}'", filter);
+        Ok(())
+    }
+}
+
+/// Everything a diagnostics bundle is collected from
+pub struct Diagnostics {
+    log: Arc<LogControl>,
+    events: Arc<EventLog>,
+    reload: Arc<ConfigReload>,
+    track_manager: Arc<TrackManager>,
+    is_sender: bool,
+    started: Instant,
+}
+
+impl Diagnostics {
+    pub fn new(
+        log: Arc<LogControl>,
+        events: Arc<EventLog>,
+        reload: Arc<ConfigReload>,
+        track_manager: Arc<TrackManager>,
+        is_sender: bool,
+    ) -> Self {
+        Self { log, events, reload, track_manager, is_sender, started: Instant::now() }
+    }
+
+    /// Log filter control
+    pub fn log(&self) -> &LogControl {
+        &self.log
+    }
+
+    /// "sender" or "receiver"
+    pub fn role(&self) -> &'static str {
+        if self.is_sender { "sender" } else { "receiver" }
+    }
+
+    /// Zip archive of the current state, given the audio devices seen
+    pub fn bundle(&self, devices: &[AudioDeviceInfo]) -> Vec<u8> {
+        let tm = &self.track_manager;
+        let mut ids = tm.track_ids();
+        ids.sort_unstable();
+        let tracks: Vec<Value> = ids
+            .iter()
+            .filter_map(|&id| tm.stats(id).ok().map(|stats| json!({ "track_id": id, "stats": stats })))
+            .collect();
+
+        let info = json!({
+            "version": env!("CARGO_PKG_VERSION"),
+            "role": self.role(),
+            "os": std::env::consts::OS,
+            "arch": std::env::consts::ARCH,
+            "uptime_seconds": self.started.elapsed().as_secs(),
+            "generated_us": now_us(),
+            "log_filter": self.log.filter(),
+        });
+        let stats = json!({
+            "tracks": tracks,
+            "status": tm.get_all_statuses(),
+            "alerts": tm.active_alerts(),
+            "session": tm.session().stats(),
+            "health": tm.health_report(),
+            "bandwidth": tm.bandwidth(),
+            "threads": tm.thread_cpu(),
+        });
+
+        zip(&[
+            ("info.json", to_json(&info)),
+            ("config.json", to_json(&redacted_config(&self.reload.current()))),
+            ("devices.json", to_json(&devices)),
+            ("events.json", to_json(&self.events.query(Severity::Trace, None, BUNDLE_EVENTS))),
+            ("stats.json", to_json(&stats)),
+        ])
+    }
+}
+
+fn to_json<T: Serialize + ?Sized>(value: &T) -> Vec<u8> {
+    serde_json::to_vec_pretty(value).unwrap_or_default()
+}
+
+/// A [`SECRET_FIELDS`] path part: the key, and whether it names every entry of an array
+fn secret_key(part: &str) -> (&str, bool) {
+    match part.strip_suffix("[]") {
+        Some(key) => (key, true),
+        None => (part, false),
+    }
+}
+
+/// The config as JSON with every one of [`SECRET_FIELDS`] that is set replaced by [`REDACTED`]
+pub fn redacted_config(config: &AppConfig) -> Value {
+    fn redact(value: &mut Value, path: &[&str]) {
+        let Some((first, rest)) = path.split_first() else {
+            if !value.is_null() {
+                *value = Value::String(REDACTED.to_string());
+            }
+            return;
+        };
+        let (key, each) = secret_key(first);
+        match value.get_mut(key) {
+            Some(Value::Array(items)) if each => items.iter_mut().for_each(|item| redact(item, rest)),
+            Some(child) if !each => redact(child, rest),
+            _ => {}
+        }
+    }
+
+    let mut value = serde_json::to_value(config).unwrap_or(Value::Null);
+    for field in SECRET_FIELDS {
+        redact(&mut value, &field.split('.').collect::<Vec<_>>());
+    }
+    value
+}
+
+/// Undo [`redacted_config`] on a config sent back: secrets still reading
+/// [`REDACTED`] keep their value in `current` (array entries by position)
+pub fn restore_redacted(config: AppConfig, current: &AppConfig) -> Result<AppConfig> {
+    fn restore(value: &mut Value, current: Option<&Value>, path: &[&str]) {
+        let Some((first, rest)) = path.split_first() else {
+            if value.as_str() == Some(REDACTED) {
+                *value = current.cloned().unwrap_or(Value::Null);
+            }
+            return;
+        };
+        let (key, each) = secret_key(first);
+        let current = current.and_then(|c| c.get(key));
+        match value.get_mut(key) {
+            Some(Value::Array(items)) if each => {
+                for (i, item) in items.iter_mut().enumerate() {
+                    restore(item, current.and_then(|c| c.get(i)), rest);
+                }
+            }
+            Some(child) if !each => restore(child, current, rest),
+            _ => {}
+        }
+    }
+
+    let to_value = |config: &AppConfig| serde_json::to_value(config).map_err(|e| Error::Config(e.to_string()));
+    let mut value = to_value(&config)?;
+    let current = to_value(current)?;
+    for field in SECRET_FIELDS {
+        restore(&mut value, Some(&current), &field.split('.').collect::<Vec<_>>());
+    }
+    serde_json::from_value(value).map_err(|e| Error::Config(e.to_string()))
+}
+
+/// Store files, uncompressed, in a zip archive
+pub fn zip(files: &[(&str, Vec<u8>)]) -> Vec<u8> {
+    let (time, date) = dos_time(chrono::Local::now().naive_local());
+    let mut out = Vec::new();
+    let mut central = Vec::new();
+
+    for (name, data) in files {
+        let offset = out.len() as u32;
+        let crc = crc32(data);
+        let size = data.len() as u32;
+
+        // Fields shared by the local header and the central directory entry
+        let mut common = Vec::with_capacity(26);
+        common.extend_from_slice(&20u16.to_le_bytes()); // version needed
+        common.extend_from_slice(&0u16.to_le_bytes()); // flags
+        common.extend_from_slice(&0u16.to_le_bytes()); // stored
+        common.extend_from_slice(&time.to_le_bytes());
+        common.extend_from_slice(&date.to_le_bytes());
+        common.extend_from_slice(&crc.to_le_bytes());
+        common.extend_from_slice(&size.to_le_bytes());
+        common.extend_from_slice(&size.to_le_bytes());
+        common.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        common.extend_from_slice(&0u16.to_le_bytes()); // extra length
+
+        out.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+        out.extend_from_slice(&common);
+        out.extend_from_slice(name.as_bytes());
+        out.extend_from_slice(data);
+
+        central.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+        central.extend_from_slice(&20u16.to_le_bytes()); // version made by
+        central.extend_from_slice(&common);
+        central.extend_from_slice(&0u16.to_le_bytes()); // comment length
+        central.extend_from_slice(&0u16.to_le_bytes()); // disk number
+        central.extend_from_slice(&0u16.to_le_bytes()); // internal attributes
+        central.extend_from_slice(&0u32.to_le_bytes()); // external attributes
+        central.extend_from_slice(&offset.to_le_bytes());
+        central.extend_from_slice(name.as_bytes());
+    }
+
+    let central_offset = out.len() as u32;
+    out.extend_from_slice(&central);
+    out.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+    out.extend_from_slice(&0u16.to_le_bytes()); // this disk
+    out.extend_from_slice(&0u16.to_le_bytes()); // central directory disk
+    out.extend_from_slice(&(files.len() as u16).to_le_bytes());
+    out.extend_from_slice(&(files.len() as u16).to_le_bytes());
+    out.extend_from_slice(&(central.len() as u32).to_le_bytes());
+    out.extend_from_slice(&central_offset.to_le_bytes());
+    out.extend_from_slice(&0u16.to_le_bytes()); // comment length
+    out
+}
+
+/// MS-DOS time and date fields
+fn dos_time(now: chrono::NaiveDateTime) -> (u16, u16) {
+    use chrono::{Datelike, Timelike};
+    let time = ((now.hour() << 11) | (now.minute() << 5) | (now.second() / 2)) as u16;
+    let date = (((now.year().clamp(1980, 2107) - 1980) as u32) << 9) | (now.month() << 5) | now.day();
+    (time, date as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{TrackHook, WebhookTarget};
+
+    #[test]
+    fn test_log_control() {
+        let (layer, handle) = reload::Layer::new(EnvFilter::new("info"));
+        let _subscriber = tracing_subscriber::registry().with(layer);
+        let control = LogControl::new(handle, "info");
+
+        control.set_filter("warn,lan_audio_streamer::network=debug").unwrap();
+        assert_eq!(control.filter(), "warn,lan_audio_streamer::network=debug");
+        assert!(control.set_filter("lan_audio_streamer=loud").is_err());
+        assert_eq!(control.filter(), "warn,lan_audio_streamer::network=debug");
+    }
+
+    #[test]
+    fn test_debug_reaches_events() {
+        // Set up as in `init_logging`, without installing it globally
+        let events = Arc::new(EventLog::new(16));
+        let (layer, handle) = reload::Layer::new(EnvFilter::new("info"));
+        let subscriber = tracing_subscriber::registry()
+            .with(layer)
+            .with(EventLogLayer::new(events.clone(), Severity::Trace));
+        let control = LogControl::new(handle, "info");
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::debug!("hidden");
+            control.set_filter("debug").unwrap();
+            tracing::debug!("shown");
+        });
+        let messages: Vec<_> = events.query(Severity::Trace, None, 16).into_iter().map(|e| e.message).collect();
+        assert!(!messages.iter().any(|m| m == "hidden"));
+        assert!(messages.iter().any(|m| m == "shown"));
+    }
+
+    #[test]
+    fn test_redaction() {
+        let mut config = AppConfig::default();
+        config.ui.auth_token = Some("1234".to_string());
+        config.network.control_key = Some("secret".to_string());
+        config.alerts.webhook_url = Some("https://discord.com/api/webhooks/1/secret".to_string());
+        config.notify.webhooks.push(WebhookTarget {
+            url: "https://hooks.slack.com/services/secret".to_string(),
+            format: Default::default(),
+            events: Vec::new(),
+        });
+        config.hooks.push(TrackHook {
+            tracks: Vec::new(),
+            events: Vec::new(),
+            command: Some("curl -H 'Authorization: secret' http://lamp.lan".to_string()),
+            url: Some("http://homeassistant.lan:8123/api/webhook/secret".to_string()),
+        });
+        let value = redacted_config(&config);
+        assert_eq!(value["ui"]["auth_token"], "<redacted>");
+        assert_eq!(value["network"]["control_key"], "<redacted>");
+        assert_eq!(value["notify"]["webhooks"][0]["url"], "<redacted>");
+        assert_eq!(value["hooks"][0]["command"], "<redacted>");
+        assert_eq!(value["mqtt"]["password"], Value::Null);
+        assert!(!value.to_string().contains("secret"));
+
+        // Sent back as is, the secrets stay what they were
+        let mut sent: AppConfig = serde_json::from_value(value.clone()).unwrap();
+        sent.network.udp_port = 6000;
+        let restored = restore_redacted(sent, &config).unwrap();
+        assert_eq!(restored.ui.auth_token.as_deref(), Some("1234"));
+        assert_eq!(restored.notify.webhooks[0].url, config.notify.webhooks[0].url);
+        assert_eq!(restored.hooks[0].command, config.hooks[0].command);
+        assert_eq!(restored.mqtt.password, None);
+        assert_eq!(restored.network.udp_port, 6000);
+
+        // The downloaded bundle carries the redacted config
+        let (layer, handle) = reload::Layer::new(EnvFilter::new("info"));
+        let _subscriber = tracing_subscriber::registry().with(layer);
+        let track_manager = Arc::new(TrackManager::new());
+        let diagnostics = Diagnostics::new(
+            Arc::new(LogControl::new(handle, "info")),
+            Arc::new(EventLog::new(16)),
+            Arc::new(ConfigReload::start(None, config, track_manager.clone())),
+            track_manager,
+            true,
+        );
+        let bundle = diagnostics.bundle(&[]);
+        let text = String::from_utf8_lossy(&bundle);
+        assert!(text.contains("config.json") && text.contains("<redacted>"));
+        assert!(!text.contains("secret"), "{}", text);
+    }
+
+    #[test]
+    fn test_zip() {
+        let archive = zip(&[("a.json", b"{}".to_vec()), ("b.txt", b"hello".to_vec())]);
+        let u16_at = |at: usize| u16::from_le_bytes([archive[at], archive[at + 1]]);
+        let u32_at = |at: usize| u32::from_le_bytes(archive[at..at + 4].try_into().unwrap());
+
+        // First local header and its data
+        assert_eq!(u32_at(0), 0x0403_4b50);
+        assert_eq!(u32_at(14), crc32(b"{}"));
+        assert_eq!(u32_at(18), 2);
+        assert_eq!(&archive[30..36], b"a.json");
+        assert_eq!(&archive[36..38], b"{}");
+
+        // End of central directory points back at both entries
+        let end = archive.len() - 22;
+        assert_eq!(u32_at(end), 0x0605_4b50);
+        assert_eq!(u16_at(end + 10), 2);
+        let central = u32_at(end + 16) as usize;
+        assert_eq!(u32_at(central), 0x0201_4b50);
+        assert_eq!(&archive[central + 46..central + 52], b"a.json");
+        let second = central + 46 + 6;
+        assert_eq!(u32_at(second), 0x0201_4b50);
+        assert_eq!(u32_at(second + 42) as usize, 38);
+        assert_eq!(&archive[38 + 30..38 + 35], b"b.txt");
+
+        let (time, date) = dos_time(chrono::NaiveDate::from_ymd_opt(2024, 3, 9).unwrap().and_hms_opt(13, 45, 20).unwrap());
+        assert_eq!((time >> 11, (time >> 5) & 63, (time & 31) * 2), (13, 45, 20));
+        assert_eq!((date >> 9, (date >> 5) & 15, date & 31), (44, 3, 9));
+    }
+}
@@ -0,0 +1,101 @@
+//! Automatic gain control
+
+use std::sync::Arc;
+
+use crate::audio::fade::apply_ramp;
+use crate::dsp::{db_to_linear, DspMeters, Processor};
+
+/// Blocks quieter than this are treated as silence (dBFS RMS)
+const SILENCE_FLOOR_DB: f32 = -60.0;
+
+/// Slow RMS-based level normaliser
+///
+/// Gain is adapted once per block towards `target - level`, limited to
+/// `max_gain_db` of boost (and the same amount of cut). Adaptation is
+/// frozen while a preceding gate is closed or the block is near silence,
+/// so background noise is not pumped up between phrases.
+pub struct Agc {
+    target_db: f32,
+    max_gain_db: f32,
+    response_frames: f32,
+    gain_db: f32,
+    meters: Arc<DspMeters>,
+}
+
+impl Agc {
+    /// Create an AGC
+    pub fn new(
+        sample_rate: u32,
+        target_db: f32,
+        max_gain_db: f32,
+        response_ms: f32,
+        meters: Arc<DspMeters>,
+    ) -> Self {
+        Self {
+            target_db,
+            max_gain_db: max_gain_db.max(0.0),
+            response_frames: (response_ms * sample_rate as f32 / 1000.0).max(1.0),
+            gain_db: 0.0,
+            meters,
+        }
+    }
+
+    /// Current gain in dB
+    pub fn gain_db(&self) -> f32 {
+        self.gain_db
+    }
+}
+
+impl Processor for Agc {
+    fn process(&mut self, samples: &mut [f32], channels: u16) {
+        if samples.is_empty() {
+            return;
+        }
+
+        let previous_gain = db_to_linear(self.gain_db);
+        let rms = (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt();
+        let level_db = if rms > 0.0 { 20.0 * rms.log10() } else { f32::MIN };
+
+        if self.meters.gate_open() && level_db > SILENCE_FLOOR_DB {
+            let desired = (self.target_db - level_db).clamp(-self.max_gain_db, self.max_gain_db);
+            let frames = (samples.len() / channels.max(1) as usize) as f32;
+            let coef = 1.0 - (-frames / self.response_frames).exp();
+            self.gain_db += (desired - self.gain_db) * coef;
+        }
+
+        // Ramp across the block so gain changes do not zipper
+        apply_ramp(samples, channels, previous_gain, db_to_linear(self.gain_db));
+    }
+
+    fn reset(&mut self) {
+        self.gain_db = 0.0;
+    }
+
+    fn name(&self) -> &'static str {
+        "agc"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_agc_boosts_quiet_input() {
+        let meters = Arc::new(DspMeters::new());
+        let mut agc = Agc::new(48000, -20.0, 30.0, 50.0, meters.clone());
+
+        // Steady -40 dBFS input converges towards +20 dB of gain
+        for _ in 0..200 {
+            let mut samples = vec![0.01f32; 960];
+            agc.process(&mut samples, 2);
+        }
+        assert!((agc.gain_db() - 20.0).abs() < 0.5);
+
+        // Closed gate freezes adaptation
+        meters.set_gate_open(false);
+        let mut samples = vec![0.5f32; 960];
+        agc.process(&mut samples, 2);
+        assert!((agc.gain_db() - 20.0).abs() < 0.5);
+    }
+}
@@ -0,0 +1,103 @@
+//! Channel utilities: polarity, swap, width, mono summing and splitting
+
+use crate::dsp::Processor;
+
+/// Stereo channel fixes
+///
+/// Applied in order: polarity invert, L/R swap, then width (mid/side).
+/// Mono summing is width 0; Opus codes the resulting identical channels
+/// very cheaply, so a mono mic summed here costs little extra bitrate.
+pub struct ChannelTools {
+    mono: bool,
+    swap: bool,
+    invert_left: bool,
+    invert_right: bool,
+    width: f32,
+}
+
+impl ChannelTools {
+    /// Create channel tools
+    pub fn new(mono: bool, swap: bool, invert_left: bool, invert_right: bool, width: f32) -> Self {
+        Self {
+            mono,
+            swap,
+            invert_left,
+            invert_right,
+            width: width.clamp(0.0, 2.0),
+        }
+    }
+}
+
+impl Processor for ChannelTools {
+    fn process(&mut self, samples: &mut [f32], channels: u16) {
+        if channels < 2 {
+            if self.invert_left {
+                for sample in samples {
+                    *sample = -*sample;
+                }
+            }
+            return;
+        }
+
+        let width = if self.mono { 0.0 } else { self.width };
+        for frame in samples.chunks_mut(channels as usize) {
+            let mut left = if self.invert_left { -frame[0] } else { frame[0] };
+            let mut right = if self.invert_right { -frame[1] } else { frame[1] };
+
+            if self.swap {
+                std::mem::swap(&mut left, &mut right);
+            }
+
+            if width != 1.0 {
+                let mid = (left + right) * 0.5;
+                let side = (left - right) * 0.5 * width;
+                left = mid + side;
+                right = mid - side;
+            }
+
+            frame[0] = left;
+            frame[1] = right;
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "channel_tools"
+    }
+}
+
+/// One channel of interleaved audio, as mono samples
+///
+/// Splits a stereo device into independent mono tracks, e.g. a hardware
+/// mixer with the host mic on the left and the guest mic on the right.
+pub fn extract_channel(samples: &[f32], channels: u16, channel: u16) -> Vec<f32> {
+    samples
+        .chunks_exact(channels.max(1) as usize)
+        .map(|frame| frame.get(channel as usize).copied().unwrap_or(0.0))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_channel_tools() {
+        let mut swap = ChannelTools::new(false, true, false, false, 1.0);
+        let mut samples = vec![0.2, 0.8];
+        swap.process(&mut samples, 2);
+        assert_eq!(samples, vec![0.8, 0.2]);
+
+        let mut mono = ChannelTools::new(true, false, false, true, 1.0);
+        let mut samples = vec![0.5, -0.5];
+        mono.process(&mut samples, 2);
+        assert_eq!(samples, vec![0.5, 0.5]);
+    }
+
+    #[test]
+    fn test_extract_channel() {
+        let samples = [0.1, 0.9, 0.2, 0.8, 0.3];
+        assert_eq!(extract_channel(&samples, 2, 0), vec![0.1, 0.2]);
+        assert_eq!(extract_channel(&samples, 2, 1), vec![0.9, 0.8]);
+        assert_eq!(extract_channel(&samples, 2, 2), vec![0.0, 0.0]);
+    }
+}
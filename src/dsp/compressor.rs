@@ -0,0 +1,108 @@
+//! Feed-forward compressor
+
+use std::sync::Arc;
+
+use crate::dsp::{db_to_linear, DspMeters, Processor};
+
+/// Level floor for the detector (dB)
+const MIN_LEVEL_DB: f32 = -120.0;
+
+/// Peak compressor with makeup gain
+///
+/// Gain reduction is computed in the log domain from each frame's peak
+/// and smoothed with separate attack and release times.
+pub struct Compressor {
+    threshold_db: f32,
+    ratio: f32,
+    makeup_db: f32,
+    attack_coef: f32,
+    release_coef: f32,
+    /// Smoothed gain change (dB, <= 0)
+    reduction_db: f32,
+    meters: Arc<DspMeters>,
+}
+
+impl Compressor {
+    /// Create a compressor
+    pub fn new(
+        sample_rate: u32,
+        threshold_db: f32,
+        ratio: f32,
+        attack_ms: f32,
+        release_ms: f32,
+        makeup_db: f32,
+        meters: Arc<DspMeters>,
+    ) -> Self {
+        let frames = |ms: f32| (ms * sample_rate as f32 / 1000.0).max(1.0);
+        Self {
+            threshold_db,
+            ratio: ratio.max(1.0),
+            makeup_db,
+            attack_coef: 1.0 - (-1.0 / frames(attack_ms)).exp(),
+            release_coef: 1.0 - (-1.0 / frames(release_ms)).exp(),
+            reduction_db: 0.0,
+            meters,
+        }
+    }
+
+    /// Static gain change for an input level (dB, <= 0)
+    fn target_reduction(&self, level_db: f32) -> f32 {
+        if level_db <= self.threshold_db {
+            0.0
+        } else {
+            let output = self.threshold_db + (level_db - self.threshold_db) / self.ratio;
+            output - level_db
+        }
+    }
+}
+
+impl Processor for Compressor {
+    fn process(&mut self, samples: &mut [f32], channels: u16) {
+        let channels = channels.max(1) as usize;
+
+        for frame in samples.chunks_mut(channels) {
+            let peak = frame.iter().fold(0.0f32, |max, s| max.max(s.abs()));
+            let level_db = if peak > 0.0 { (20.0 * peak.log10()).max(MIN_LEVEL_DB) } else { MIN_LEVEL_DB };
+
+            let target = self.target_reduction(level_db);
+            let coef = if target < self.reduction_db { self.attack_coef } else { self.release_coef };
+            self.reduction_db += (target - self.reduction_db) * coef;
+
+            let gain = db_to_linear(self.reduction_db + self.makeup_db);
+            for sample in frame.iter_mut() {
+                *sample *= gain;
+            }
+        }
+
+        self.meters.set_gain_reduction_db(-self.reduction_db);
+    }
+
+    fn reset(&mut self) {
+        self.reduction_db = 0.0;
+    }
+
+    fn name(&self) -> &'static str {
+        "compressor"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_static_curve() {
+        let meters = Arc::new(DspMeters::new());
+        let mut comp = Compressor::new(48000, -20.0, 4.0, 0.0, 100.0, 0.0, meters.clone());
+
+        // 0 dBFS input, 20 dB over threshold at 4:1 -> 15 dB reduction
+        assert!((comp.target_reduction(0.0) + 15.0).abs() < 1e-4);
+        assert_eq!(comp.target_reduction(-30.0), 0.0);
+
+        // Instant attack settles on the static curve
+        let mut samples = vec![1.0f32; 16];
+        comp.process(&mut samples, 2);
+        assert!((meters.gain_reduction_db() - 15.0).abs() < 0.01);
+        assert!((samples[15] - db_to_linear(-15.0)).abs() < 1e-3);
+    }
+}
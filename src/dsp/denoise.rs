@@ -0,0 +1,152 @@
+//! RNNoise-based noise suppression
+//!
+//! Meant for voice tracks. RNNoise works on 10 ms blocks at 48 kHz, so
+//! frames that are a multiple of 10 ms are denoised in place; any other
+//! size switches to buffered mode, which adds one block of latency.
+
+use std::collections::VecDeque;
+
+use nnnoiseless::DenoiseState;
+
+use crate::dsp::Processor;
+
+/// Samples per RNNoise block (per channel)
+const BLOCK: usize = DenoiseState::FRAME_SIZE;
+
+/// RNNoise works on 16-bit sample scale
+const SCALE: f32 = 32768.0;
+
+/// Per-channel noise suppressor
+pub struct Denoise {
+    states: Vec<Box<DenoiseState<'static>>>,
+    /// Pending input per channel (buffered mode)
+    input: Vec<Vec<f32>>,
+    /// Denoised output per channel (buffered mode)
+    output: Vec<VecDeque<f32>>,
+    buffered: bool,
+    block_in: Vec<f32>,
+    block_out: Vec<f32>,
+}
+
+impl Denoise {
+    /// Create a denoiser
+    pub fn new() -> Self {
+        Self {
+            states: Vec::new(),
+            input: Vec::new(),
+            output: Vec::new(),
+            buffered: false,
+            block_in: vec![0.0; BLOCK],
+            block_out: vec![0.0; BLOCK],
+        }
+    }
+
+    fn ensure_channels(&mut self, channels: usize) {
+        if self.states.len() != channels {
+            self.states = (0..channels).map(|_| DenoiseState::new()).collect();
+            self.input = vec![Vec::new(); channels];
+            self.output = vec![VecDeque::new(); channels];
+            self.buffered = false;
+        }
+    }
+
+    /// Denoise `block_in` into `block_out` for one channel
+    fn run_block(&mut self, channel: usize) {
+        for sample in &mut self.block_in {
+            *sample *= SCALE;
+        }
+        self.states[channel].process_frame(&mut self.block_out, &self.block_in);
+        for sample in &mut self.block_out {
+            *sample /= SCALE;
+        }
+    }
+
+    fn process_in_place(&mut self, samples: &mut [f32], channels: usize) {
+        for block in samples.chunks_mut(BLOCK * channels) {
+            for ch in 0..channels {
+                for (i, frame) in block.chunks(channels).enumerate() {
+                    self.block_in[i] = frame[ch];
+                }
+                self.run_block(ch);
+                for (i, frame) in block.chunks_mut(channels).enumerate() {
+                    frame[ch] = self.block_out[i];
+                }
+            }
+        }
+    }
+
+    fn process_buffered(&mut self, samples: &mut [f32], channels: usize) {
+        if !self.buffered {
+            // One block of silence keeps the output fed while input accumulates
+            for output in &mut self.output {
+                output.extend(std::iter::repeat_n(0.0, BLOCK));
+            }
+            self.buffered = true;
+        }
+
+        for frame in samples.chunks(channels) {
+            for (ch, sample) in frame.iter().enumerate() {
+                self.input[ch].push(*sample);
+            }
+        }
+
+        for ch in 0..channels {
+            while self.input[ch].len() >= BLOCK {
+                self.block_in.copy_from_slice(&self.input[ch][..BLOCK]);
+                self.input[ch].drain(..BLOCK);
+                self.run_block(ch);
+                self.output[ch].extend(self.block_out.iter().copied());
+            }
+        }
+
+        for frame in samples.chunks_mut(channels) {
+            for (ch, sample) in frame.iter_mut().enumerate() {
+                *sample = self.output[ch].pop_front().unwrap_or(0.0);
+            }
+        }
+    }
+}
+
+impl Default for Denoise {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Processor for Denoise {
+    fn process(&mut self, samples: &mut [f32], channels: u16) {
+        let channels = channels.max(1) as usize;
+        self.ensure_channels(channels);
+
+        let frames = samples.len() / channels;
+        if !self.buffered && frames.is_multiple_of(BLOCK) {
+            self.process_in_place(samples, channels);
+        } else {
+            self.process_buffered(samples, channels);
+        }
+    }
+
+    fn reset(&mut self) {
+        self.states.clear();
+    }
+
+    fn name(&self) -> &'static str {
+        "denoise"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buffered_latency() {
+        let mut denoise = Denoise::new();
+
+        // Odd frame size forces buffered mode: the first block is silence
+        let mut samples = vec![0.1f32; 100 * 2];
+        denoise.process(&mut samples, 2);
+        assert!(samples.iter().all(|s| *s == 0.0));
+        assert_eq!(samples.len(), 200);
+    }
+}
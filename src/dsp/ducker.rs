@@ -0,0 +1,108 @@
+//! Sidechain ducking
+
+use std::sync::Arc;
+
+use crate::audio::fade::apply_ramp;
+use crate::dsp::{db_to_linear, MeterBus, Processor};
+
+/// Attack time for pulling the level down (ms)
+const DUCK_ATTACK_MS: f32 = 10.0;
+
+/// Lowers a track while another track is active
+///
+/// The key signal is the sidechain track's output level read from the
+/// meter bus, so e.g. music dips whenever the mic track is speaking.
+pub struct Ducker {
+    sidechain: u8,
+    threshold_db: f32,
+    amount_db: f32,
+    attack_frames: f32,
+    release_frames: f32,
+    gain_db: f32,
+    bus: Arc<MeterBus>,
+}
+
+impl Ducker {
+    /// Create a ducker keyed by `sidechain`'s level
+    pub fn new(
+        sample_rate: u32,
+        sidechain: u8,
+        threshold_db: f32,
+        amount_db: f32,
+        release_ms: f32,
+        bus: Arc<MeterBus>,
+    ) -> Self {
+        let frames = |ms: f32| (ms * sample_rate as f32 / 1000.0).max(1.0);
+        Self {
+            sidechain,
+            threshold_db,
+            amount_db: amount_db.abs(),
+            attack_frames: frames(DUCK_ATTACK_MS),
+            release_frames: frames(release_ms),
+            gain_db: 0.0,
+            bus,
+        }
+    }
+
+    /// Current gain change in dB (<= 0)
+    pub fn gain_db(&self) -> f32 {
+        self.gain_db
+    }
+}
+
+impl Processor for Ducker {
+    fn process(&mut self, samples: &mut [f32], channels: u16) {
+        if samples.is_empty() {
+            return;
+        }
+
+        let key_db = self.bus.level_db(self.sidechain).unwrap_or(f32::MIN);
+        let target = if key_db > self.threshold_db { -self.amount_db } else { 0.0 };
+        let time = if target < self.gain_db { self.attack_frames } else { self.release_frames };
+
+        let previous_gain = db_to_linear(self.gain_db);
+        let frames = (samples.len() / channels.max(1) as usize) as f32;
+        self.gain_db += (target - self.gain_db) * (1.0 - (-frames / time).exp());
+
+        apply_ramp(samples, channels, previous_gain, db_to_linear(self.gain_db));
+    }
+
+    fn reset(&mut self) {
+        self.gain_db = 0.0;
+    }
+
+    fn name(&self) -> &'static str {
+        "ducker"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dsp::DspMeters;
+
+    #[test]
+    fn test_ducks_while_key_active() {
+        let bus = Arc::new(MeterBus::new());
+        let mic = Arc::new(DspMeters::new());
+        bus.register(1, mic.clone());
+
+        let mut ducker = Ducker::new(48000, 1, -30.0, 12.0, 100.0, bus);
+
+        // Mic at -10 dBFS pulls the music down by the full amount
+        mic.set_level(db_to_linear(-10.0));
+        for _ in 0..20 {
+            let mut samples = vec![0.5f32; 960];
+            ducker.process(&mut samples, 2);
+        }
+        assert!((ducker.gain_db() + 12.0).abs() < 0.1);
+
+        // Mic silent: music recovers over the release time
+        mic.set_level(0.0);
+        for _ in 0..100 {
+            let mut samples = vec![0.5f32; 960];
+            ducker.process(&mut samples, 2);
+        }
+        assert!(ducker.gain_db() > -0.1);
+    }
+}
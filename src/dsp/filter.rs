@@ -0,0 +1,87 @@
+//! Biquad filters
+
+use std::f32::consts::PI;
+
+use crate::dsp::Processor;
+
+/// Per-channel biquad history
+#[derive(Debug, Clone, Copy, Default)]
+struct BiquadState {
+    x1: f32,
+    x2: f32,
+    y1: f32,
+    y2: f32,
+}
+
+/// Second-order Butterworth high-pass (12 dB/octave)
+pub struct HighPass {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+    state: Vec<BiquadState>,
+}
+
+impl HighPass {
+    /// Create a high-pass filter
+    pub fn new(sample_rate: u32, cutoff_hz: f32) -> Self {
+        let w0 = 2.0 * PI * cutoff_hz / sample_rate as f32;
+        let (sin, cos) = w0.sin_cos();
+        let alpha = sin / (2.0 * std::f32::consts::FRAC_1_SQRT_2);
+        let a0 = 1.0 + alpha;
+
+        Self {
+            b0: (1.0 + cos) / 2.0 / a0,
+            b1: -(1.0 + cos) / a0,
+            b2: (1.0 + cos) / 2.0 / a0,
+            a1: -2.0 * cos / a0,
+            a2: (1.0 - alpha) / a0,
+            state: Vec::new(),
+        }
+    }
+}
+
+impl Processor for HighPass {
+    fn process(&mut self, samples: &mut [f32], channels: u16) {
+        let channels = channels.max(1) as usize;
+        if self.state.len() != channels {
+            self.state = vec![BiquadState::default(); channels];
+        }
+
+        for frame in samples.chunks_mut(channels) {
+            for (sample, s) in frame.iter_mut().zip(self.state.iter_mut()) {
+                let x = *sample;
+                let y = self.b0 * x + self.b1 * s.x1 + self.b2 * s.x2 - self.a1 * s.y1 - self.a2 * s.y2;
+                s.x2 = s.x1;
+                s.x1 = x;
+                s.y2 = s.y1;
+                s.y1 = y;
+                *sample = y;
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.state.clear();
+    }
+
+    fn name(&self) -> &'static str {
+        "high_pass"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_high_pass_removes_dc() {
+        let mut filter = HighPass::new(48000, 100.0);
+        let mut samples = vec![0.5f32; 48000];
+        filter.process(&mut samples, 1);
+
+        // DC offset decays to nothing after a few time constants
+        assert!(samples[47999].abs() < 1e-3);
+    }
+}
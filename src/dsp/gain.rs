@@ -0,0 +1,32 @@
+//! Static gain
+
+use crate::dsp::{db_to_linear, Processor};
+
+/// Multiplies every sample by a fixed gain
+pub struct Gain {
+    gain: f32,
+}
+
+impl Gain {
+    /// Create a gain stage
+    pub fn new(gain_db: f32) -> Self {
+        Self { gain: db_to_linear(gain_db) }
+    }
+
+    /// Get linear gain
+    pub fn linear(&self) -> f32 {
+        self.gain
+    }
+}
+
+impl Processor for Gain {
+    fn process(&mut self, samples: &mut [f32], _channels: u16) {
+        for sample in samples {
+            *sample *= self.gain;
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "gain"
+    }
+}
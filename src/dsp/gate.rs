@@ -0,0 +1,118 @@
+//! Noise gate
+
+use std::sync::Arc;
+
+use crate::dsp::{db_to_linear, DspMeters, Processor};
+
+/// Downward gate with attack, hold and release
+///
+/// The gate opens as soon as a frame peaks above the threshold, stays
+/// open for the hold time after the signal drops, then fades out over
+/// the release time.
+pub struct Gate {
+    threshold: f32,
+    attack_coef: f32,
+    release_coef: f32,
+    hold_frames: usize,
+    hold_remaining: usize,
+    open: bool,
+    gain: f32,
+    meters: Arc<DspMeters>,
+}
+
+impl Gate {
+    /// Create a gate
+    pub fn new(
+        sample_rate: u32,
+        threshold_db: f32,
+        attack_ms: f32,
+        hold_ms: f32,
+        release_ms: f32,
+        meters: Arc<DspMeters>,
+    ) -> Self {
+        let frames = |ms: f32| (ms * sample_rate as f32 / 1000.0).max(1.0);
+        Self {
+            threshold: db_to_linear(threshold_db),
+            attack_coef: 1.0 - (-1.0 / frames(attack_ms)).exp(),
+            release_coef: 1.0 - (-1.0 / frames(release_ms)).exp(),
+            hold_frames: frames(hold_ms) as usize,
+            hold_remaining: 0,
+            open: false,
+            gain: 0.0,
+            meters,
+        }
+    }
+
+    /// Check if the gate is open
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+}
+
+impl Processor for Gate {
+    fn process(&mut self, samples: &mut [f32], channels: u16) {
+        let channels = channels.max(1) as usize;
+
+        for frame in samples.chunks_mut(channels) {
+            let peak = frame.iter().fold(0.0f32, |max, s| max.max(s.abs()));
+
+            if peak >= self.threshold {
+                self.open = true;
+                self.hold_remaining = self.hold_frames;
+            } else if self.hold_remaining > 0 {
+                self.hold_remaining -= 1;
+            } else {
+                self.open = false;
+            }
+
+            let (target, coef) = if self.open {
+                (1.0, self.attack_coef)
+            } else {
+                (0.0, self.release_coef)
+            };
+            self.gain += (target - self.gain) * coef;
+
+            for sample in frame.iter_mut() {
+                *sample *= self.gain;
+            }
+        }
+
+        self.meters.set_gate_open(self.open);
+    }
+
+    fn reset(&mut self) {
+        self.open = false;
+        self.gain = 0.0;
+        self.hold_remaining = 0;
+    }
+
+    fn name(&self) -> &'static str {
+        "gate"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gate_open_hold_close() {
+        let meters = Arc::new(DspMeters::new());
+        // 1 kHz rate keeps the numbers small: 5 ms hold = 5 frames
+        let mut gate = Gate::new(1000, -20.0, 1.0, 5.0, 1.0, meters.clone());
+
+        let mut loud = vec![0.5f32; 4];
+        gate.process(&mut loud, 1);
+        assert!(gate.is_open());
+        assert!(meters.gate_open());
+
+        // Quiet signal: held open, then closed
+        let mut quiet = vec![0.01f32; 4];
+        gate.process(&mut quiet, 1);
+        assert!(gate.is_open());
+        let mut quiet = vec![0.01f32; 4];
+        gate.process(&mut quiet, 1);
+        assert!(!gate.is_open());
+        assert!(!meters.gate_open());
+    }
+}
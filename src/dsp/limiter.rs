@@ -0,0 +1,59 @@
+//! Brickwall peak limiter
+
+use crate::dsp::{db_to_linear, Processor};
+
+/// Peak limiter with instant attack and exponential release
+///
+/// Output never exceeds the ceiling: the gain drops immediately on a
+/// peak and recovers over the release time.
+pub struct Limiter {
+    ceiling: f32,
+    release_coef: f32,
+    gain: f32,
+}
+
+impl Limiter {
+    /// Create a limiter
+    pub fn new(sample_rate: u32, ceiling_db: f32, release_ms: f32) -> Self {
+        let release_samples = (release_ms * sample_rate as f32 / 1000.0).max(1.0);
+        Self {
+            ceiling: db_to_linear(ceiling_db),
+            release_coef: 1.0 - (-1.0 / release_samples).exp(),
+            gain: 1.0,
+        }
+    }
+
+    /// Current gain reduction in dB (0 when idle)
+    pub fn reduction_db(&self) -> f32 {
+        20.0 * self.gain.log10()
+    }
+}
+
+impl Processor for Limiter {
+    fn process(&mut self, samples: &mut [f32], channels: u16) {
+        let channels = channels.max(1) as usize;
+
+        for frame in samples.chunks_mut(channels) {
+            let peak = frame.iter().fold(0.0f32, |max, s| max.max(s.abs()));
+            let target = if peak > self.ceiling { self.ceiling / peak } else { 1.0 };
+
+            if target < self.gain {
+                self.gain = target;
+            } else {
+                self.gain += (target - self.gain) * self.release_coef;
+            }
+
+            for sample in frame.iter_mut() {
+                *sample = (*sample * self.gain).clamp(-self.ceiling, self.ceiling);
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.gain = 1.0;
+    }
+
+    fn name(&self) -> &'static str {
+        "limiter"
+    }
+}
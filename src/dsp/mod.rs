@@ -0,0 +1,425 @@
+//! Audio processing chain
+//!
+//! Each track can run an ordered chain of processors over its interleaved
+//! samples, configured from `TrackConfig::processors`. On the sender the
+//! chain runs before encoding, on the receiver before playback.
+
+pub mod agc;
+pub mod channels;
+pub mod compressor;
+#[cfg(feature = "denoise")]
+pub mod denoise;
+pub mod ducker;
+pub mod filter;
+pub mod gain;
+pub mod gate;
+pub mod limiter;
+
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Arc;
+
+pub use agc::Agc;
+pub use channels::{extract_channel, ChannelTools};
+pub use compressor::Compressor;
+#[cfg(feature = "denoise")]
+pub use denoise::Denoise;
+pub use ducker::Ducker;
+pub use filter::HighPass;
+pub use gain::Gain;
+pub use gate::Gate;
+pub use limiter::Limiter;
+
+/// In-place processor for interleaved f32 samples
+pub trait Processor: Send {
+    /// Process samples in place
+    fn process(&mut self, samples: &mut [f32], channels: u16);
+
+    /// Clear internal state (filter history, envelopes)
+    fn reset(&mut self) {}
+
+    /// Short name for logging
+    fn name(&self) -> &'static str;
+}
+
+/// Serializable processor description
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ProcessorConfig {
+    /// Static gain
+    Gain { gain_db: f32 },
+    /// 12 dB/octave high-pass filter
+    HighPass { cutoff_hz: f32 },
+    /// Brickwall peak limiter
+    Limiter { ceiling_db: f32, release_ms: f32 },
+    /// RNNoise noise suppression (voice tracks, `denoise` feature)
+    Denoise,
+    /// Noise gate
+    Gate { threshold_db: f32, attack_ms: f32, hold_ms: f32, release_ms: f32 },
+    /// Feed-forward compressor
+    Compressor {
+        threshold_db: f32,
+        ratio: f32,
+        attack_ms: f32,
+        release_ms: f32,
+        makeup_db: f32,
+    },
+    /// Automatic gain control towards an RMS target (voice tracks)
+    Agc { target_db: f32, max_gain_db: f32, response_ms: f32 },
+    /// Duck this track while the sidechain track is above the threshold
+    Duck { sidechain: u8, threshold_db: f32, amount_db: f32, release_ms: f32 },
+    /// Mono summing, L/R swap, polarity invert and stereo width
+    ChannelTools {
+        #[serde(default)]
+        mono: bool,
+        #[serde(default)]
+        swap: bool,
+        #[serde(default)]
+        invert_left: bool,
+        #[serde(default)]
+        invert_right: bool,
+        #[serde(default = "default_width")]
+        width: f32,
+    },
+}
+
+fn default_width() -> f32 {
+    1.0
+}
+
+impl ProcessorConfig {
+    /// Check parameters, returning a description of the first problem
+    pub fn validate(&self, sample_rate: u32) -> Result<(), String> {
+        match *self {
+            ProcessorConfig::Gain { gain_db } => {
+                if !(-96.0..=24.0).contains(&gain_db) {
+                    return Err(format!("Gain {} dB out of range (-96 to +24)", gain_db));
+                }
+            }
+            ProcessorConfig::HighPass { cutoff_hz } => {
+                let nyquist = sample_rate as f32 / 2.0;
+                if cutoff_hz <= 0.0 || cutoff_hz >= nyquist {
+                    return Err(format!("High-pass cutoff {} Hz out of range (0 to {})", cutoff_hz, nyquist));
+                }
+            }
+            ProcessorConfig::Limiter { ceiling_db, release_ms } => {
+                if ceiling_db > 0.0 {
+                    return Err(format!("Limiter ceiling {} dB above full scale", ceiling_db));
+                }
+                if release_ms <= 0.0 {
+                    return Err(format!("Limiter release {} ms must be positive", release_ms));
+                }
+            }
+            ProcessorConfig::Gate { threshold_db, attack_ms, hold_ms, release_ms } => {
+                if threshold_db > 0.0 {
+                    return Err(format!("Gate threshold {} dB above full scale", threshold_db));
+                }
+                if attack_ms < 0.0 || hold_ms < 0.0 || release_ms < 0.0 {
+                    return Err("Gate times must not be negative".to_string());
+                }
+            }
+            ProcessorConfig::Compressor { threshold_db, ratio, attack_ms, release_ms, makeup_db } => {
+                if threshold_db > 0.0 {
+                    return Err(format!("Compressor threshold {} dB above full scale", threshold_db));
+                }
+                if ratio < 1.0 {
+                    return Err(format!("Compressor ratio {} must be at least 1", ratio));
+                }
+                if attack_ms < 0.0 || release_ms < 0.0 {
+                    return Err("Compressor times must not be negative".to_string());
+                }
+                if !(0.0..=24.0).contains(&makeup_db) {
+                    return Err(format!("Makeup gain {} dB out of range (0 to +24)", makeup_db));
+                }
+            }
+            ProcessorConfig::Agc { target_db, max_gain_db, response_ms } => {
+                if target_db > 0.0 {
+                    return Err(format!("AGC target {} dB above full scale", target_db));
+                }
+                if !(0.0..=40.0).contains(&max_gain_db) {
+                    return Err(format!("AGC max gain {} dB out of range (0 to 40)", max_gain_db));
+                }
+                if response_ms <= 0.0 {
+                    return Err(format!("AGC response {} ms must be positive", response_ms));
+                }
+            }
+            ProcessorConfig::Duck { threshold_db, amount_db, release_ms, .. } => {
+                if threshold_db > 0.0 {
+                    return Err(format!("Duck threshold {} dB above full scale", threshold_db));
+                }
+                if !(0.0..=60.0).contains(&amount_db) {
+                    return Err(format!("Duck amount {} dB out of range (0 to 60)", amount_db));
+                }
+                if release_ms <= 0.0 {
+                    return Err(format!("Duck release {} ms must be positive", release_ms));
+                }
+            }
+            ProcessorConfig::ChannelTools { width, .. } => {
+                if !(0.0..=2.0).contains(&width) {
+                    return Err(format!("Stereo width {} out of range (0 to 2)", width));
+                }
+            }
+            ProcessorConfig::Denoise => {
+                if !cfg!(feature = "denoise") {
+                    return Err("Noise suppression not available in this build".to_string());
+                }
+                if sample_rate != 48000 {
+                    return Err(format!("Noise suppression requires 48000 Hz, got {}", sample_rate));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Build the processor (None if unavailable in this build)
+    /// 
+    /// Processors that report state write it to `meters`; sidechain
+    /// processors read other tracks through `bus`.
+    pub fn build(
+        &self,
+        sample_rate: u32,
+        meters: &Arc<DspMeters>,
+        bus: &Arc<MeterBus>,
+    ) -> Option<Box<dyn Processor>> {
+        let processor: Box<dyn Processor> = match *self {
+            ProcessorConfig::Gain { gain_db } => Box::new(Gain::new(gain_db)),
+            ProcessorConfig::HighPass { cutoff_hz } => Box::new(HighPass::new(sample_rate, cutoff_hz)),
+            ProcessorConfig::Limiter { ceiling_db, release_ms } => {
+                Box::new(Limiter::new(sample_rate, ceiling_db, release_ms))
+            }
+            ProcessorConfig::Gate { threshold_db, attack_ms, hold_ms, release_ms } => Box::new(
+                Gate::new(sample_rate, threshold_db, attack_ms, hold_ms, release_ms, meters.clone())
+            ),
+            ProcessorConfig::Compressor { threshold_db, ratio, attack_ms, release_ms, makeup_db } => {
+                Box::new(Compressor::new(
+                    sample_rate,
+                    threshold_db,
+                    ratio,
+                    attack_ms,
+                    release_ms,
+                    makeup_db,
+                    meters.clone(),
+                ))
+            }
+            ProcessorConfig::Agc { target_db, max_gain_db, response_ms } => {
+                Box::new(Agc::new(sample_rate, target_db, max_gain_db, response_ms, meters.clone()))
+            }
+            ProcessorConfig::Duck { sidechain, threshold_db, amount_db, release_ms } => Box::new(
+                Ducker::new(sample_rate, sidechain, threshold_db, amount_db, release_ms, bus.clone())
+            ),
+            ProcessorConfig::ChannelTools { mono, swap, invert_left, invert_right, width } => {
+                Box::new(ChannelTools::new(mono, swap, invert_left, invert_right, width))
+            }
+            #[cfg(feature = "denoise")]
+            ProcessorConfig::Denoise => Box::new(Denoise::new()),
+            #[cfg(not(feature = "denoise"))]
+            ProcessorConfig::Denoise => {
+                tracing::warn!("Noise suppression requested but not available in this build");
+                return None;
+            }
+        };
+        Some(processor)
+    }
+}
+
+/// Live processor state, shared with whoever reports it
+#[derive(Debug)]
+pub struct DspMeters {
+    gate_open: AtomicBool,
+    /// Compressor gain reduction (dB, f32 bits)
+    gain_reduction: AtomicU32,
+    /// Output peak of the last processed block (linear, f32 bits)
+    level: AtomicU32,
+}
+
+impl DspMeters {
+    /// Create meters in their idle state
+    pub fn new() -> Self {
+        Self {
+            gate_open: AtomicBool::new(true),
+            gain_reduction: AtomicU32::new(0),
+            level: AtomicU32::new(0),
+        }
+    }
+
+    /// Check if the gate is open (always true without a gate)
+    pub fn gate_open(&self) -> bool {
+        self.gate_open.load(Ordering::Relaxed)
+    }
+
+    /// Set gate state
+    pub fn set_gate_open(&self, open: bool) {
+        self.gate_open.store(open, Ordering::Relaxed);
+    }
+
+    /// Get compressor gain reduction in dB (positive)
+    pub fn gain_reduction_db(&self) -> f32 {
+        f32::from_bits(self.gain_reduction.load(Ordering::Relaxed))
+    }
+
+    /// Set compressor gain reduction
+    pub fn set_gain_reduction_db(&self, db: f32) {
+        self.gain_reduction.store(db.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Get output peak level (linear)
+    pub fn level(&self) -> f32 {
+        f32::from_bits(self.level.load(Ordering::Relaxed))
+    }
+
+    /// Set output peak level
+    pub fn set_level(&self, level: f32) {
+        self.level.store(level.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Return to the idle state
+    pub fn reset(&self) {
+        self.set_gate_open(true);
+        self.set_gain_reduction_db(0.0);
+        self.set_level(0.0);
+    }
+}
+
+impl Default for DspMeters {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Meters of every track, for cross-track processing (sidechains)
+#[derive(Debug, Default)]
+pub struct MeterBus {
+    meters: DashMap<u8, Arc<DspMeters>>,
+}
+
+impl MeterBus {
+    /// Create an empty bus
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Publish a track's meters
+    pub fn register(&self, track_id: u8, meters: Arc<DspMeters>) {
+        self.meters.insert(track_id, meters);
+    }
+
+    /// Withdraw a track's meters
+    pub fn unregister(&self, track_id: u8) {
+        self.meters.remove(&track_id);
+    }
+
+    /// Get a track's meters
+    pub fn get(&self, track_id: u8) -> Option<Arc<DspMeters>> {
+        self.meters.get(&track_id).map(|m| m.clone())
+    }
+
+    /// Get a track's output level in dBFS
+    pub fn level_db(&self, track_id: u8) -> Option<f32> {
+        let level = self.meters.get(&track_id)?.level();
+        Some(if level > 0.0 { 20.0 * level.log10() } else { f32::MIN })
+    }
+}
+
+/// Ordered chain of processors
+#[derive(Default)]
+pub struct ProcessorChain {
+    processors: Vec<Box<dyn Processor>>,
+    meters: Arc<DspMeters>,
+}
+
+impl ProcessorChain {
+    /// Create an empty chain
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build a chain from configuration
+    pub fn from_configs(configs: &[ProcessorConfig], sample_rate: u32) -> Self {
+        Self::with_meters(configs, sample_rate, Arc::new(DspMeters::new()), Arc::new(MeterBus::new()))
+    }
+
+    /// Build a chain reporting into existing meters and reading from a bus
+    pub fn with_meters(
+        configs: &[ProcessorConfig],
+        sample_rate: u32,
+        meters: Arc<DspMeters>,
+        bus: Arc<MeterBus>,
+    ) -> Self {
+        meters.reset();
+        Self {
+            processors: configs.iter().filter_map(|c| c.build(sample_rate, &meters, &bus)).collect(),
+            meters,
+        }
+    }
+
+    /// Get the chain's meters
+    pub fn meters(&self) -> Arc<DspMeters> {
+        self.meters.clone()
+    }
+
+    /// Append a processor
+    pub fn push(&mut self, processor: Box<dyn Processor>) {
+        self.processors.push(processor);
+    }
+
+    /// Run every processor in order
+    pub fn process(&mut self, samples: &mut [f32], channels: u16) {
+        for processor in &mut self.processors {
+            processor.process(samples, channels);
+        }
+        
+        let peak = samples.iter().fold(0.0f32, |max, s| max.max(s.abs()));
+        self.meters.set_level(peak);
+    }
+
+    /// Reset every processor
+    pub fn reset(&mut self) {
+        for processor in &mut self.processors {
+            processor.reset();
+        }
+    }
+
+    /// Get number of processors
+    pub fn len(&self) -> usize {
+        self.processors.len()
+    }
+
+    /// Check if the chain does nothing
+    pub fn is_empty(&self) -> bool {
+        self.processors.is_empty()
+    }
+}
+
+/// Convert decibels to linear gain
+pub fn db_to_linear(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chain_order() {
+        // +6 dB then a -6 dB ceiling limiter: limiter must win
+        let configs = vec![
+            ProcessorConfig::Gain { gain_db: 6.0 },
+            ProcessorConfig::Limiter { ceiling_db: -6.0, release_ms: 50.0 },
+        ];
+        let mut chain = ProcessorChain::from_configs(&configs, 48000);
+        assert_eq!(chain.len(), 2);
+
+        let mut samples = vec![0.9f32; 64];
+        chain.process(&mut samples, 2);
+        let ceiling = db_to_linear(-6.0);
+        assert!(samples.iter().all(|s| s.abs() <= ceiling + 1e-6));
+    }
+
+    #[test]
+    fn test_config_validation() {
+        assert!(ProcessorConfig::HighPass { cutoff_hz: 80.0 }.validate(48000).is_ok());
+        assert!(ProcessorConfig::HighPass { cutoff_hz: 30000.0 }.validate(48000).is_err());
+        assert!(ProcessorConfig::Limiter { ceiling_db: 1.0, release_ms: 50.0 }.validate(48000).is_err());
+    }
+}
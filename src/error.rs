@@ -2,6 +2,8 @@
 
 use thiserror::Error;
 
+use crate::tracks::TrackState;
+
 /// Main error type for the application
 #[derive(Error, Debug)]
 pub enum Error {
@@ -66,6 +68,9 @@ pub enum CodecError {
     
     #[error("Invalid frame size: {0}")]
     InvalidFrameSize(usize),
+    
+    #[error("Invalid Ogg/Opus file: {0}")]
+    OggOpus(String),
 }
 
 /// Network errors
@@ -89,10 +94,53 @@ pub enum NetworkError {
     #[error("Invalid packet format")]
     InvalidPacket,
     
+    #[error("Malformed packet: {0}")]
+    Malformed(#[from] PacketError),
+    
+    #[error("Invalid OSC message: {0}")]
+    InvalidOsc(String),
+    
+    #[error("Could not resolve {0}")]
+    ResolveFailed(String),
+    
+    #[error("Invalid MAC address: {0}")]
+    InvalidMac(String),
+    
+    #[error("Invalid packet dump: {0}")]
+    InvalidDump(String),
+    
+    #[error("MQTT error: {0}")]
+    Mqtt(String),
+    
     #[error("Timeout")]
     Timeout,
 }
 
+/// Reasons a received datagram is rejected
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PacketError {
+    #[error("Truncated packet: {0} bytes")]
+    Truncated(usize),
+    
+    #[error("Unknown magic: {0:#06x}")]
+    BadMagic(u16),
+    
+    #[error("Payload too large: {0} bytes")]
+    PayloadTooLarge(usize),
+    
+    #[error("Payload checksum mismatch")]
+    ChecksumMismatch,
+    
+    #[error("Malformed control message")]
+    BadControl,
+    
+    #[error("Unsigned, forged or replayed control message")]
+    BadSignature,
+    
+    #[error("Malformed fragment")]
+    BadFragment,
+}
+
 /// Track management errors
 #[derive(Error, Debug)]
 pub enum TrackError {
@@ -110,6 +158,24 @@ pub enum TrackError {
     
     #[error("Track is not active")]
     NotActive,
+    
+    #[error("Track {track_id} cannot go from {from} to {to}")]
+    InvalidTransition { track_id: u8, from: TrackState, to: TrackState },
+    
+    #[error("Talkback is not enabled")]
+    TalkbackDisabled,
+    
+    #[error("Track profile not found: {0}")]
+    ProfileNotFound(String),
+    
+    #[error("Scene not found: {0}")]
+    SceneNotFound(String),
+    
+    #[error("Track group not found: {0}")]
+    GroupNotFound(String),
+    
+    #[error("Failed to save scenes: {0}")]
+    SceneSave(String),
 }
 
 /// Result type alias for the application
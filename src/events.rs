@@ -0,0 +1,225 @@
+//! Recent event log
+//!
+//! A tracing layer copies log events (device errors, overflows, reconnects,
+//! config changes, ...) into a bounded in-memory ring, so problems from a
+//! few minutes ago can be looked up over the API without console access.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+
+use crate::sync::now_us;
+
+/// Event severity, least to most severe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl From<&Level> for Severity {
+    fn from(level: &Level) -> Self {
+        match *level {
+            Level::TRACE => Self::Trace,
+            Level::DEBUG => Self::Debug,
+            Level::INFO => Self::Info,
+            Level::WARN => Self::Warn,
+            Level::ERROR => Self::Error,
+        }
+    }
+}
+
+impl FromStr for Severity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "trace" => Ok(Self::Trace),
+            "debug" => Ok(Self::Debug),
+            "info" => Ok(Self::Info),
+            "warn" | "warning" => Ok(Self::Warn),
+            "error" => Ok(Self::Error),
+            _ => Err(format!("Unknown severity: {}", s)),
+        }
+    }
+}
+
+/// One recorded event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventRecord {
+    /// Increasing ID, usable as a `since` cursor
+    pub id: u64,
+    /// Wall-clock time in microseconds since the UNIX epoch
+    pub timestamp_us: u64,
+    pub level: Severity,
+    /// Module that emitted the event
+    pub target: String,
+    pub message: String,
+    /// Structured fields other than the message
+    #[serde(skip_serializing_if = "BTreeMap::is_empty", default)]
+    pub fields: BTreeMap<String, String>,
+}
+
+/// Bounded ring of recent events
+pub struct EventLog {
+    events: Mutex<VecDeque<EventRecord>>,
+    capacity: usize,
+    next_id: AtomicU64,
+}
+
+impl EventLog {
+    /// Create a log keeping the last `capacity` events
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            events: Mutex::new(VecDeque::with_capacity(capacity.max(1))),
+            capacity: capacity.max(1),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    /// Append an event, dropping the oldest when full
+    pub fn push(
+        &self,
+        level: Severity,
+        target: &str,
+        message: String,
+        fields: BTreeMap<String, String>,
+    ) -> u64 {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let record = EventRecord {
+            id,
+            timestamp_us: now_us(),
+            level,
+            target: target.to_string(),
+            message,
+            fields,
+        };
+
+        let mut events = self.events.lock();
+        if events.len() == self.capacity {
+            events.pop_front();
+        }
+        events.push_back(record);
+        id
+    }
+
+    /// Events at or above `min_level` with an ID after `since`, newest last
+    ///
+    /// At most `limit` of the most recent matches are returned.
+    pub fn query(&self, min_level: Severity, since: Option<u64>, limit: usize) -> Vec<EventRecord> {
+        let events = self.events.lock();
+        let mut matches: Vec<EventRecord> = events
+            .iter()
+            .rev()
+            .filter(|e| e.level >= min_level && since.is_none_or(|s| e.id > s))
+            .take(limit)
+            .cloned()
+            .collect();
+        matches.reverse();
+        matches
+    }
+
+    /// Get number of events held
+    pub fn len(&self) -> usize {
+        self.events.lock().len()
+    }
+
+    /// Check if no events are held
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Tracing layer feeding an [`EventLog`]
+pub struct EventLogLayer {
+    log: Arc<EventLog>,
+    min_level: Severity,
+}
+
+impl EventLogLayer {
+    /// Record events at `min_level` and above
+    pub fn new(log: Arc<EventLog>, min_level: Severity) -> Self {
+        Self { log, min_level }
+    }
+}
+
+impl<S: Subscriber> Layer<S> for EventLogLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        let level = Severity::from(metadata.level());
+        if level < self.min_level {
+            return;
+        }
+
+        let mut visitor = FieldVisitor::default();
+        event.record(&mut visitor);
+        self.log.push(level, metadata.target(), visitor.message, visitor.fields);
+    }
+}
+
+/// Collects the message and fields of an event
+#[derive(Default)]
+struct FieldVisitor {
+    message: String,
+    fields: BTreeMap<String, String>,
+}
+
+impl Visit for FieldVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message = value.to_string();
+        } else {
+            self.fields.insert(field.name().to_string(), value.to_string());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.message = format!("{:?}", value);
+        } else {
+            self.fields.insert(field.name().to_string(), format!("{:?}", value));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[test]
+    fn test_layer_records_and_filters() {
+        let log = Arc::new(EventLog::new(2));
+        let subscriber = tracing_subscriber::registry()
+            .with(EventLogLayer::new(log.clone(), Severity::Info));
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::debug!("ignored");
+            tracing::info!("first");
+            tracing::warn!(track = 3, "underrun");
+            tracing::error!("device lost");
+        });
+
+        // Capacity 2 keeps the newest two
+        assert_eq!(log.len(), 2);
+        let warnings = log.query(Severity::Warn, None, 10);
+        assert_eq!(warnings[0].message, "underrun");
+        assert_eq!(warnings[0].fields.get("track").map(String::as_str), Some("3"));
+        assert_eq!(warnings[1].level, Severity::Error);
+
+        let newer = log.query(Severity::Trace, Some(warnings[0].id), 10);
+        assert_eq!(newer.len(), 1);
+        assert_eq!("warning".parse::<Severity>(), Ok(Severity::Warn));
+    }
+}
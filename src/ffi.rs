@@ -0,0 +1,984 @@
+//! C bindings
+//!
+//! `extern "C"` functions for embedding the streamer in a C, C++ or C# host
+//! (a desktop app, an OBS plugin). A [`LasSender`] captures tracks from
+//! devices or from audio the host pushes in, encodes them and sends them to
+//! one receiver; a [`LasReceiver`] decodes tracks and plays them on devices
+//! or hands them to a host callback. The matching header is
+//! `include/lan_audio_streamer.h` (regenerate with `cbindgen --config
+//! cbindgen.toml --output include/lan_audio_streamer.h`).
+//!
+//! Functions returning `int32_t` return 0 (or a track ID) on success and -1
+//! on failure; [`las_last_error`] then describes the failure on the calling
+//! thread. Callbacks run on library threads, never the caller's.
+
+use crossbeam_channel::{bounded, Receiver, RecvTimeoutError};
+use std::cell::RefCell;
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::fmt::Display;
+use std::net::SocketAddr;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast::error::TryRecvError;
+
+use crate::audio::buffer::{create_shared_buffer, AudioFrame, JitterBuffer, SharedJitterBuffer, SharedRingBuffer};
+use crate::audio::capture::AudioCapture;
+use crate::audio::custom::{AudioSink, SinkDriver, SourceDriver};
+use crate::audio::device::{get_default_input_device, get_default_output_device};
+use crate::audio::playback::AudioPlayback;
+use crate::config::{NetworkConfig, OpusConfig};
+use crate::constants::{DEFAULT_CHANNELS, DEFAULT_SAMPLE_RATE, RING_BUFFER_CAPACITY};
+use crate::error::{AudioError, Error};
+use crate::network::receiver::{AudioReceiver, ReceivedPacket};
+use crate::network::sender::MultiTrackSender;
+use crate::network::stream::{Decoders, Encoders};
+use crate::protocol::TrackConfig;
+use crate::tracks::counters::{Counter, Gauge};
+use crate::tracks::manager::{TrackEvent, TrackManager};
+use crate::tracks::stats::TrackCounters;
+
+/// How often worker threads report track counters
+const REPORT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Jitter buffer size and start delay in frames
+const JITTER_CAPACITY: usize = 32;
+const JITTER_MIN_DELAY: usize = 2;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_error(error: impl Display) {
+    let message = CString::new(error.to_string().replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|e| *e.borrow_mut() = Some(message));
+}
+
+/// 0 or -1, recording the error
+fn status(result: Result<(), Error>) -> i32 {
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            set_error(e);
+            -1
+        }
+    }
+}
+
+/// Borrow a C string argument; NULL is `None`
+unsafe fn str_arg<'a>(value: *const c_char, name: &str) -> Result<Option<&'a str>, Error> {
+    if value.is_null() {
+        return Ok(None);
+    }
+    CStr::from_ptr(value)
+        .to_str()
+        .map(Some)
+        .map_err(|_| Error::Config(format!("{} is not valid UTF-8", name)))
+}
+
+/// Message describing the last failure on this thread, or NULL
+///
+/// The string stays valid until the next failing call on the same thread.
+#[no_mangle]
+pub extern "C" fn las_last_error() -> *const c_char {
+    LAST_ERROR.with(|e| e.borrow().as_ref().map_or(ptr::null(), |m| m.as_ptr()))
+}
+
+/// Track statistics over the last few seconds
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LasTrackStats {
+    pub track_id: u8,
+    pub packets: u64,
+    pub bytes: u64,
+    pub frames: u64,
+    pub packets_lost: u64,
+    pub overflows: u64,
+    pub underruns: u64,
+    pub packets_per_sec: f64,
+    pub bitrate_kbps: f64,
+    pub loss_pct: f64,
+    /// End-to-end estimate from the stage breakdown
+    pub latency_ms: f64,
+}
+
+/// Kind of event passed to a [`LasEventCallback`]
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LasEventKind {
+    Created = 0,
+    Removed = 1,
+    Started = 2,
+    Stopped = 3,
+    /// Message describes the alert; raised or cleared
+    Alert = 4,
+    Xrun = 5,
+    /// Message describes the error
+    Error = 6,
+    /// Message is the new state ("running", "degraded", ...)
+    StateChanged = 7,
+}
+
+/// Track event callback: user data, kind, track ID and a message or NULL
+///
+/// The message is only valid during the call.
+pub type LasEventCallback =
+    Option<unsafe extern "C" fn(user_data: *mut c_void, kind: LasEventKind, track_id: u8, message: *const c_char)>;
+
+/// Audio callback: user data, track ID, interleaved samples, sample count and channels
+pub type LasAudioCallback = Option<
+    unsafe extern "C" fn(user_data: *mut c_void, track_id: u8, samples: *const f32, len: usize, channels: u16),
+>;
+
+/// Host user data handed back to callbacks on library threads
+#[derive(Clone, Copy)]
+struct UserData(*mut c_void);
+
+// SAFETY: the header documents that callbacks run on library threads; the
+// host is responsible for making its user data usable from them.
+unsafe impl Send for UserData {}
+
+fn event_kind(event: &TrackEvent) -> Option<(LasEventKind, u8, Option<String>)> {
+    Some(match event {
+        TrackEvent::Created(id) => (LasEventKind::Created, *id, None),
+        TrackEvent::Removed(id) => (LasEventKind::Removed, *id, None),
+        TrackEvent::Started(id) => (LasEventKind::Started, *id, None),
+        TrackEvent::Stopped(id) => (LasEventKind::Stopped, *id, None),
+        TrackEvent::Alert(id, kind, active) => {
+            let state = if *active { "raised" } else { "cleared" };
+            (LasEventKind::Alert, *id, Some(format!("{} {}", kind.description(), state)))
+        }
+        TrackEvent::Xrun(xrun) => (LasEventKind::Xrun, xrun.track_id, Some(format!("{:?}", xrun.kind))),
+        TrackEvent::Error(id, message) => (LasEventKind::Error, *id, Some(message.clone())),
+        TrackEvent::StateChanged(transition) => {
+            (LasEventKind::StateChanged, transition.track_id, Some(transition.to.to_string()))
+        }
+        _ => return None,
+    })
+}
+
+/// Thread delivering track events to a C callback
+struct EventForwarder {
+    running: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl EventForwarder {
+    fn start(manager: &TrackManager, callback: LasEventCallback, user_data: *mut c_void) -> Result<Option<Self>, Error> {
+        let Some(callback) = callback else {
+            return Ok(None);
+        };
+        let running = Arc::new(AtomicBool::new(true));
+        let mut events = manager.subscribe();
+        let user_data = UserData(user_data);
+        let flag = running.clone();
+        let handle = thread::Builder::new()
+            .name("ffi-events".to_string())
+            .spawn(move || {
+                let user_data = user_data;
+                while flag.load(Ordering::Relaxed) {
+                    match events.try_recv() {
+                        Ok(event) => {
+                            let Some((kind, track_id, message)) = event_kind(&event) else {
+                                continue;
+                            };
+                            let message = message.and_then(|m| CString::new(m).ok());
+                            let message_ptr = message.as_ref().map_or(ptr::null(), |m| m.as_ptr());
+                            // SAFETY: the host registered this callback for these arguments
+                            unsafe { callback(user_data.0, kind, track_id, message_ptr) };
+                        }
+                        Err(TryRecvError::Empty) => thread::sleep(Duration::from_millis(10)),
+                        Err(TryRecvError::Lagged(_)) => {}
+                        Err(TryRecvError::Closed) => break,
+                    }
+                }
+            })?;
+        Ok(Some(Self { running, handle: Some(handle) }))
+    }
+}
+
+impl Drop for EventForwarder {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn track_stats(manager: &TrackManager, track_id: u8, out: *mut LasTrackStats) -> Result<(), Error> {
+    if out.is_null() {
+        return Err(Error::Config("stats output is NULL".to_string()));
+    }
+    let stats = manager.stats(track_id)?;
+    let stats = LasTrackStats {
+        track_id,
+        packets: stats.packets,
+        bytes: stats.bytes,
+        frames: stats.frames,
+        packets_lost: stats.packets_lost,
+        overflows: stats.overflows,
+        underruns: stats.underruns,
+        packets_per_sec: stats.packets_per_sec,
+        bitrate_kbps: stats.bitrate_kbps,
+        loss_pct: stats.loss_pct,
+        latency_ms: stats.latency_ms,
+    };
+    // SAFETY: checked for NULL; the caller passes a writable LasTrackStats
+    unsafe { out.write(stats) };
+    Ok(())
+}
+
+/// A worker thread with its stop flag
+struct Worker {
+    running: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
+
+impl Worker {
+    fn spawn(name: String, f: impl FnOnce(Arc<AtomicBool>) + Send + 'static) -> Result<Self, Error> {
+        let running = Arc::new(AtomicBool::new(true));
+        let flag = running.clone();
+        let handle = thread::Builder::new().name(name).spawn(move || f(flag))?;
+        Ok(Self { running, handle })
+    }
+
+    fn stop(self) {
+        self.running.store(false, Ordering::SeqCst);
+        let _ = self.handle.join();
+    }
+}
+
+/// Where a sender track gets its audio
+enum SenderInput {
+    Device(AudioCapture),
+    /// Set with [`TrackManager::set_source`]
+    Custom(SourceDriver),
+    /// Fed by [`las_sender_push_audio`]
+    Push { sequence: u32, started: Instant },
+}
+
+struct SenderTrack {
+    id: u8,
+    input: SenderInput,
+    buffer: SharedRingBuffer,
+    channels: u16,
+    bitrate: u32,
+    worker: Option<Worker>,
+}
+
+/// Encoding sender; created with [`las_sender_create`]
+pub struct LasSender {
+    manager: Arc<TrackManager>,
+    network: Arc<MultiTrackSender>,
+    tracks: Vec<SenderTrack>,
+    events: Option<EventForwarder>,
+}
+
+impl LasSender {
+    fn new(target: &str) -> Result<Self, Error> {
+        let target: SocketAddr = target
+            .parse()
+            .map_err(|_| Error::Config(format!("invalid target address: {}", target)))?;
+        let config = NetworkConfig::default();
+        let mut network = MultiTrackSender::new(&config, target)?;
+        network.start(config)?;
+        Ok(Self {
+            manager: Arc::new(TrackManager::new()),
+            network: Arc::new(network),
+            tracks: Vec::new(),
+            events: None,
+        })
+    }
+
+    /// Tracks of this sender, e.g. to [`set_source`](TrackManager::set_source) before starting
+    pub fn manager(&self) -> &TrackManager {
+        &self.manager
+    }
+
+    /// Add a track; `input` is a device ID (None for the default input) or None for a push track
+    fn add_track(&mut self, name: &str, input: Option<Option<&str>>, channels: u16, bitrate: u32) -> Result<u8, Error> {
+        let channels = if channels == 0 { DEFAULT_CHANNELS } else { channels };
+        let bitrate = if bitrate == 0 { OpusConfig::default().bitrate } else { bitrate };
+        let device_id = match input {
+            Some(Some(id)) => Some(id.to_string()),
+            Some(None) => Some(format!("input:{}", get_default_input_device()?.name)),
+            None => None,
+        };
+
+        let id = self.manager.create_track(TrackConfig {
+            name: name.to_string(),
+            device_id: device_id.clone().unwrap_or_default(),
+            bitrate,
+            channels,
+            ..Default::default()
+        })?;
+        let buffer = create_shared_buffer(RING_BUFFER_CAPACITY);
+        let input = match device_id {
+            Some(device_id) => {
+                match AudioCapture::new(id, &device_id, Some(DEFAULT_SAMPLE_RATE), Some(channels), None, buffer.clone()) {
+                    Ok(capture) => SenderInput::Device(capture),
+                    Err(e) => {
+                        let _ = self.manager.remove_track(id);
+                        return Err(e.into());
+                    }
+                }
+            }
+            None => SenderInput::Push { sequence: 0, started: Instant::now() },
+        };
+        self.tracks.push(SenderTrack { id, input, buffer, channels, bitrate, worker: None });
+        Ok(id)
+    }
+
+    fn start(&mut self) -> Result<(), Error> {
+        for track in &mut self.tracks {
+            if track.worker.is_some() {
+                continue;
+            }
+            // A custom source set through the track manager replaces the device or pushed audio
+            if let Some(source) = self.manager.take_source(track.id) {
+                if let SenderInput::Device(capture) = &mut track.input {
+                    capture.stop();
+                }
+                track.input = SenderInput::Custom(SourceDriver::new(track.id, source, track.buffer.clone()));
+            }
+            match &mut track.input {
+                SenderInput::Device(capture) => capture.start()?,
+                SenderInput::Custom(driver) => driver.start()?,
+                SenderInput::Push { .. } => {}
+            }
+
+            let (id, buffer) = (track.id, track.buffer.clone());
+            let (network, manager) = (self.network.clone(), self.manager.clone());
+            let mut encoders = Encoders::new(OpusConfig {
+                bitrate: track.bitrate,
+                channels: track.channels,
+                ..OpusConfig::default()
+            });
+            track.worker = Some(Worker::spawn(format!("ffi-encode-{}", id), move |running| {
+                let counters = manager.counters();
+                let mut last_report = Instant::now();
+                while running.load(Ordering::Relaxed) {
+                    let Some(frame) = buffer.try_pop() else {
+                        thread::sleep(Duration::from_millis(1));
+                        continue;
+                    };
+                    match encoders.push(id, &frame) {
+                        Ok(packets) => {
+                            for payload in packets {
+                                counters.add(id, Counter::Frames, 1);
+                                counters.add(id, Counter::Bytes, payload.len() as u64);
+                                match network.send_audio(id, payload, encoders.frame_ticks(), frame.channels == 2) {
+                                    Ok(_) => {
+                                        counters.add(id, Counter::Packets, 1);
+                                        manager.report_ok(id);
+                                    }
+                                    Err(e) => manager.report_error(id, e.to_string()),
+                                }
+                            }
+                        }
+                        Err(e) => manager.report_error(id, e.to_string()),
+                    }
+                    if last_report.elapsed() >= REPORT_INTERVAL {
+                        counters.store(id, Counter::Overflows, buffer.overflow_count() as u64);
+                        manager.sample_stats(id);
+                        last_report = Instant::now();
+                    }
+                }
+            })?);
+            let _ = self.manager.start_track(track.id);
+        }
+        Ok(())
+    }
+
+    fn stop(&mut self) {
+        for track in &mut self.tracks {
+            match &mut track.input {
+                SenderInput::Device(capture) => capture.stop(),
+                SenderInput::Custom(driver) => driver.stop(),
+                SenderInput::Push { .. } => {}
+            }
+            if let Some(worker) = track.worker.take() {
+                worker.stop();
+                let _ = self.manager.stop_track(track.id);
+            }
+        }
+    }
+
+    fn push_audio(&mut self, track_id: u8, samples: &[f32]) -> Result<(), Error> {
+        let track = self
+            .tracks
+            .iter_mut()
+            .find(|t| t.id == track_id)
+            .ok_or(crate::error::TrackError::NotFound(track_id))?;
+        let SenderInput::Push { sequence, started } = &mut track.input else {
+            return Err(Error::Config(format!("track {} captures from a device or custom source", track_id)));
+        };
+        if !samples.len().is_multiple_of(track.channels as usize) {
+            return Err(Error::Config(format!("sample count is not a multiple of {} channels", track.channels)));
+        }
+        let frame = AudioFrame::new(samples.to_vec(), track.channels, started.elapsed().as_micros() as u64, *sequence);
+        *sequence = sequence.wrapping_add(1);
+        if !track.buffer.push(frame) {
+            return Err(AudioError::BufferOverflow.into());
+        }
+        Ok(())
+    }
+}
+
+impl Drop for LasSender {
+    fn drop(&mut self) {
+        self.events = None;
+        self.stop();
+        // Workers are gone, so this is the only reference left
+        if let Some(network) = Arc::get_mut(&mut self.network) {
+            network.finish();
+        }
+    }
+}
+
+/// Hands decoded frames to a C callback
+struct CallbackSink {
+    track_id: u8,
+    callback: unsafe extern "C" fn(*mut c_void, u8, *const f32, usize, u16),
+    user_data: UserData,
+}
+
+impl AudioSink for CallbackSink {
+    fn write(&mut self, frame: &AudioFrame) -> Result<(), AudioError> {
+        // SAFETY: the host registered this callback; samples outlive the call
+        unsafe {
+            (self.callback)(self.user_data.0, self.track_id, frame.samples.as_ptr(), frame.samples.len(), frame.channels)
+        };
+        Ok(())
+    }
+}
+
+/// Where a receiver track plays to
+enum ReceiverOutput {
+    Device(AudioPlayback),
+    /// A host callback, or a sink set with [`TrackManager::set_sink`]
+    Sink(SinkDriver),
+}
+
+struct ReceiverTrack {
+    id: u8,
+    output: ReceiverOutput,
+    packets: Receiver<ReceivedPacket>,
+    jitter: SharedJitterBuffer,
+    worker: Option<Worker>,
+}
+
+/// Decoding receiver; created with [`las_receiver_create`]
+pub struct LasReceiver {
+    manager: Arc<TrackManager>,
+    receiver: AudioReceiver,
+    config: NetworkConfig,
+    tracks: Vec<ReceiverTrack>,
+    events: Option<EventForwarder>,
+}
+
+impl LasReceiver {
+    fn new(port: u16) -> Self {
+        Self {
+            manager: Arc::new(TrackManager::new()),
+            receiver: AudioReceiver::new(),
+            config: NetworkConfig { udp_port: port, ..NetworkConfig::default() },
+            tracks: Vec::new(),
+            events: None,
+        }
+    }
+
+    /// Tracks of this receiver, e.g. to [`set_sink`](TrackManager::set_sink) before starting
+    pub fn manager(&self) -> &TrackManager {
+        &self.manager
+    }
+
+    fn add_track(&mut self, track_id: u8, name: &str, output: Output) -> Result<(), Error> {
+        let device_id = match &output {
+            Output::Device(Some(id)) => id.to_string(),
+            Output::Device(None) => format!("output:{}", get_default_output_device()?.name),
+            Output::Callback(..) => String::new(),
+        };
+        let id = self.manager.create_track(TrackConfig {
+            track_id: Some(track_id),
+            name: name.to_string(),
+            device_id: device_id.clone(),
+            ..Default::default()
+        })?;
+
+        let jitter = Arc::new(JitterBuffer::new(JITTER_CAPACITY, JITTER_MIN_DELAY));
+        let output = match output {
+            Output::Device(_) => {
+                match AudioPlayback::new(id, &device_id, Some(DEFAULT_SAMPLE_RATE), Some(DEFAULT_CHANNELS), None, jitter.clone()) {
+                    Ok(playback) => ReceiverOutput::Device(playback),
+                    Err(e) => {
+                        let _ = self.manager.remove_track(id);
+                        return Err(e.into());
+                    }
+                }
+            }
+            Output::Callback(callback, user_data) => {
+                let sink = CallbackSink { track_id: id, callback, user_data };
+                ReceiverOutput::Sink(SinkDriver::new(id, Box::new(sink), jitter.clone()))
+            }
+        };
+
+        let (packet_tx, packets) = bounded(RING_BUFFER_CAPACITY);
+        self.receiver.register_track(id, packet_tx);
+        self.tracks.push(ReceiverTrack { id, output, packets, jitter, worker: None });
+        Ok(())
+    }
+
+    fn start(&mut self) -> Result<(), Error> {
+        if !self.receiver.is_running() {
+            self.receiver.start(self.config.clone())?;
+        }
+        for track in &mut self.tracks {
+            if track.worker.is_some() {
+                continue;
+            }
+            // A custom sink set through the track manager replaces the device or callback
+            if let Some(sink) = self.manager.take_sink(track.id) {
+                match &mut track.output {
+                    ReceiverOutput::Device(playback) => playback.stop(),
+                    ReceiverOutput::Sink(driver) => driver.stop(),
+                }
+                track.output = ReceiverOutput::Sink(SinkDriver::new(track.id, sink, track.jitter.clone()));
+            }
+            match &mut track.output {
+                ReceiverOutput::Device(playback) => playback.start()?,
+                ReceiverOutput::Sink(driver) => driver.start()?,
+            }
+
+            let (id, packets, jitter) = (track.id, track.packets.clone(), track.jitter.clone());
+            let manager = self.manager.clone();
+            track.worker = Some(Worker::spawn(format!("ffi-decode-{}", id), move |running| {
+                let mut decoders = Decoders::default();
+                let counters = manager.counters();
+                let mut last_report = Instant::now();
+                while running.load(Ordering::Relaxed) {
+                    match packets.recv_timeout(Duration::from_millis(10)) {
+                        Ok(packet) => {
+                            counters.add(id, Counter::Packets, 1);
+                            counters.add(id, Counter::Bytes, packet.payload.len() as u64);
+                            if jitter.check_duplicate(packet.sequence) {
+                                continue;
+                            }
+                            match decoders.decode(&packet) {
+                                Ok(frame) => {
+                                    counters.add(id, Counter::Frames, 1);
+                                    jitter.insert(frame);
+                                    manager.report_ok(id);
+                                }
+                                Err(e) => manager.report_error(id, e.to_string()),
+                            }
+                        }
+                        Err(RecvTimeoutError::Timeout) => {}
+                        Err(RecvTimeoutError::Disconnected) => break,
+                    }
+                    if last_report.elapsed() >= REPORT_INTERVAL {
+                        let stats = jitter.stats();
+                        counters.store(id, Counter::PacketsLost, stats.lost as u64);
+                        counters.store(id, Counter::LatePackets, stats.late as u64);
+                        counters.store(id, Counter::Overflows, stats.overflowed as u64);
+                        counters.store(id, Counter::DuplicatePackets, stats.duplicates as u64);
+                        counters.store(id, Counter::ReorderedPackets, stats.reordered as u64);
+                        counters.set(id, Gauge::JitterLevel, Some(stats.level as u64));
+                        counters.set(id, Gauge::JitterCapacity, Some(stats.capacity as u64));
+                        manager.report_stats(id, TrackCounters {
+                            jitter_occupancy: jitter.take_occupancy(),
+                            ..counters.snapshot(id)
+                        });
+                        last_report = Instant::now();
+                    }
+                }
+            })?);
+            let _ = self.manager.start_track(track.id);
+        }
+        Ok(())
+    }
+
+    fn stop(&mut self) {
+        for track in &mut self.tracks {
+            if let Some(worker) = track.worker.take() {
+                worker.stop();
+                let _ = self.manager.stop_track(track.id);
+            }
+            match &mut track.output {
+                ReceiverOutput::Device(playback) => playback.stop(),
+                ReceiverOutput::Sink(driver) => driver.stop(),
+            }
+        }
+        self.receiver.stop();
+    }
+}
+
+impl Drop for LasReceiver {
+    fn drop(&mut self) {
+        self.events = None;
+        self.stop();
+    }
+}
+
+/// Output requested for a new receiver track
+enum Output<'a> {
+    /// Device ID, or the default output
+    Device(Option<&'a str>),
+    Callback(unsafe extern "C" fn(*mut c_void, u8, *const f32, usize, u16), UserData),
+}
+
+/// Create a sender streaming to `target` ("host:port"); NULL on failure
+///
+/// # Safety
+/// `target` must be a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn las_sender_create(target: *const c_char) -> *mut LasSender {
+    let result = str_arg(target, "target")
+        .and_then(|t| t.ok_or_else(|| Error::Config("target is NULL".to_string())))
+        .and_then(LasSender::new);
+    match result {
+        Ok(sender) => Box::into_raw(Box::new(sender)),
+        Err(e) => {
+            set_error(e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Add a track captured from `device_id` (NULL for the default input)
+///
+/// `channels` and `bitrate` may be 0 for the defaults. Returns the track ID.
+///
+/// # Safety
+/// `sender` must come from [`las_sender_create`]; strings must be NUL-terminated or NULL.
+#[no_mangle]
+pub unsafe extern "C" fn las_sender_add_track(
+    sender: *mut LasSender,
+    name: *const c_char,
+    device_id: *const c_char,
+    channels: u16,
+    bitrate: u32,
+) -> i32 {
+    let Some(sender) = sender.as_mut() else {
+        set_error("sender is NULL");
+        return -1;
+    };
+    let result = str_arg(name, "name").and_then(|name| {
+        let device_id = str_arg(device_id, "device_id")?;
+        sender.add_track(name.unwrap_or_default(), Some(device_id), channels, bitrate)
+    });
+    match result {
+        Ok(id) => id as i32,
+        Err(e) => {
+            set_error(e);
+            -1
+        }
+    }
+}
+
+/// Add a track fed by [`las_sender_push_audio`] instead of a device
+///
+/// # Safety
+/// `sender` must come from [`las_sender_create`]; `name` must be NUL-terminated or NULL.
+#[no_mangle]
+pub unsafe extern "C" fn las_sender_add_push_track(
+    sender: *mut LasSender,
+    name: *const c_char,
+    channels: u16,
+    bitrate: u32,
+) -> i32 {
+    let Some(sender) = sender.as_mut() else {
+        set_error("sender is NULL");
+        return -1;
+    };
+    let result = str_arg(name, "name").and_then(|name| sender.add_track(name.unwrap_or_default(), None, channels, bitrate));
+    match result {
+        Ok(id) => id as i32,
+        Err(e) => {
+            set_error(e);
+            -1
+        }
+    }
+}
+
+/// Queue interleaved 48 kHz samples on a push track
+///
+/// Any length is accepted; samples are encoded once a full frame is queued.
+///
+/// # Safety
+/// `sender` must come from [`las_sender_create`]; `samples` must point to `len` floats.
+#[no_mangle]
+pub unsafe extern "C" fn las_sender_push_audio(
+    sender: *mut LasSender,
+    track_id: u8,
+    samples: *const f32,
+    len: usize,
+) -> i32 {
+    let Some(sender) = sender.as_mut() else {
+        set_error("sender is NULL");
+        return -1;
+    };
+    if samples.is_null() && len > 0 {
+        set_error("samples is NULL");
+        return -1;
+    }
+    let samples = if len == 0 { &[][..] } else { std::slice::from_raw_parts(samples, len) };
+    status(sender.push_audio(track_id, samples))
+}
+
+/// Start capturing and sending all tracks
+///
+/// # Safety
+/// `sender` must come from [`las_sender_create`].
+#[no_mangle]
+pub unsafe extern "C" fn las_sender_start(sender: *mut LasSender) -> i32 {
+    match sender.as_mut() {
+        Some(sender) => status(sender.start()),
+        None => status(Err(Error::Config("sender is NULL".to_string()))),
+    }
+}
+
+/// Stop all tracks; they can be started again
+///
+/// # Safety
+/// `sender` must come from [`las_sender_create`].
+#[no_mangle]
+pub unsafe extern "C" fn las_sender_stop(sender: *mut LasSender) -> i32 {
+    match sender.as_mut() {
+        Some(sender) => {
+            sender.stop();
+            0
+        }
+        None => status(Err(Error::Config("sender is NULL".to_string()))),
+    }
+}
+
+/// Fill `out` with a track's statistics
+///
+/// # Safety
+/// `sender` must come from [`las_sender_create`]; `out` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn las_sender_get_stats(sender: *const LasSender, track_id: u8, out: *mut LasTrackStats) -> i32 {
+    match sender.as_ref() {
+        Some(sender) => status(track_stats(&sender.manager, track_id, out)),
+        None => status(Err(Error::Config("sender is NULL".to_string()))),
+    }
+}
+
+/// Receive track events through `callback` (NULL to stop)
+///
+/// # Safety
+/// `sender` must come from [`las_sender_create`]; `user_data` must be usable from another thread.
+#[no_mangle]
+pub unsafe extern "C" fn las_sender_set_event_callback(
+    sender: *mut LasSender,
+    callback: LasEventCallback,
+    user_data: *mut c_void,
+) -> i32 {
+    let Some(sender) = sender.as_mut() else {
+        set_error("sender is NULL");
+        return -1;
+    };
+    sender.events = None;
+    status(EventForwarder::start(&sender.manager, callback, user_data).map(|events| sender.events = events))
+}
+
+/// Stop and free a sender, sending end of stream to the receiver
+///
+/// # Safety
+/// `sender` must come from [`las_sender_create`] and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn las_sender_destroy(sender: *mut LasSender) {
+    if !sender.is_null() {
+        drop(Box::from_raw(sender));
+    }
+}
+
+/// Create a receiver listening on UDP `port` (0 for the default)
+#[no_mangle]
+pub extern "C" fn las_receiver_create(port: u16) -> *mut LasReceiver {
+    let port = if port == 0 { NetworkConfig::default().udp_port } else { port };
+    Box::into_raw(Box::new(LasReceiver::new(port)))
+}
+
+/// Play track `track_id` on `device_id` (NULL for the default output)
+///
+/// # Safety
+/// `receiver` must come from [`las_receiver_create`]; strings must be NUL-terminated or NULL.
+#[no_mangle]
+pub unsafe extern "C" fn las_receiver_add_track(
+    receiver: *mut LasReceiver,
+    track_id: u8,
+    name: *const c_char,
+    device_id: *const c_char,
+) -> i32 {
+    let Some(receiver) = receiver.as_mut() else {
+        set_error("receiver is NULL");
+        return -1;
+    };
+    status(str_arg(name, "name").and_then(|name| {
+        let device_id = str_arg(device_id, "device_id")?;
+        receiver.add_track(track_id, name.unwrap_or_default(), Output::Device(device_id))
+    }))
+}
+
+/// Hand track `track_id` to `callback` in real time instead of a device
+///
+/// # Safety
+/// `receiver` must come from [`las_receiver_create`]; `name` must be NUL-terminated or NULL;
+/// `user_data` must be usable from another thread.
+#[no_mangle]
+pub unsafe extern "C" fn las_receiver_add_callback_track(
+    receiver: *mut LasReceiver,
+    track_id: u8,
+    name: *const c_char,
+    callback: LasAudioCallback,
+    user_data: *mut c_void,
+) -> i32 {
+    let Some(receiver) = receiver.as_mut() else {
+        set_error("receiver is NULL");
+        return -1;
+    };
+    let Some(callback) = callback else {
+        set_error("callback is NULL");
+        return -1;
+    };
+    status(str_arg(name, "name").and_then(|name| {
+        receiver.add_track(track_id, name.unwrap_or_default(), Output::Callback(callback, UserData(user_data)))
+    }))
+}
+
+/// Start receiving and playing all tracks
+///
+/// # Safety
+/// `receiver` must come from [`las_receiver_create`].
+#[no_mangle]
+pub unsafe extern "C" fn las_receiver_start(receiver: *mut LasReceiver) -> i32 {
+    match receiver.as_mut() {
+        Some(receiver) => status(receiver.start()),
+        None => status(Err(Error::Config("receiver is NULL".to_string()))),
+    }
+}
+
+/// Stop receiving; can be started again
+///
+/// # Safety
+/// `receiver` must come from [`las_receiver_create`].
+#[no_mangle]
+pub unsafe extern "C" fn las_receiver_stop(receiver: *mut LasReceiver) -> i32 {
+    match receiver.as_mut() {
+        Some(receiver) => {
+            receiver.stop();
+            0
+        }
+        None => status(Err(Error::Config("receiver is NULL".to_string()))),
+    }
+}
+
+/// Fill `out` with a track's statistics
+///
+/// # Safety
+/// `receiver` must come from [`las_receiver_create`]; `out` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn las_receiver_get_stats(
+    receiver: *const LasReceiver,
+    track_id: u8,
+    out: *mut LasTrackStats,
+) -> i32 {
+    match receiver.as_ref() {
+        Some(receiver) => status(track_stats(&receiver.manager, track_id, out)),
+        None => status(Err(Error::Config("receiver is NULL".to_string()))),
+    }
+}
+
+/// Receive track events through `callback` (NULL to stop)
+///
+/// # Safety
+/// `receiver` must come from [`las_receiver_create`]; `user_data` must be usable from another thread.
+#[no_mangle]
+pub unsafe extern "C" fn las_receiver_set_event_callback(
+    receiver: *mut LasReceiver,
+    callback: LasEventCallback,
+    user_data: *mut c_void,
+) -> i32 {
+    let Some(receiver) = receiver.as_mut() else {
+        set_error("receiver is NULL");
+        return -1;
+    };
+    receiver.events = None;
+    status(EventForwarder::start(&receiver.manager, callback, user_data).map(|events| receiver.events = events))
+}
+
+/// Stop and free a receiver
+///
+/// # Safety
+/// `receiver` must come from [`las_receiver_create`] and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn las_receiver_destroy(receiver: *mut LasReceiver) {
+    if !receiver.is_null() {
+        drop(Box::from_raw(receiver));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    unsafe extern "C" fn count_samples(user_data: *mut c_void, _track_id: u8, _samples: *const f32, len: usize, _channels: u16) {
+        (*(user_data as *const AtomicUsize)).fetch_add(len, Ordering::SeqCst);
+    }
+
+    #[test]
+    fn test_header_declares_exports() {
+        let header = include_str!("../include/lan_audio_streamer.h");
+        let exports: Vec<&str> = include_str!("ffi.rs")
+            .split("extern \"C\" fn ")
+            .skip(1)
+            .filter_map(|rest| rest.split('(').next())
+            .filter(|name| name.starts_with("las_"))
+            .collect();
+        assert!(exports.len() >= 15);
+        for name in exports {
+            assert!(header.contains(&format!("{}(", name)), "{} missing from the header", name);
+        }
+    }
+
+    #[test]
+    fn test_push_to_callback_loopback() {
+        let received = Arc::new(AtomicUsize::new(0));
+        unsafe {
+            let receiver = las_receiver_create(47_613);
+            let user_data = Arc::as_ptr(&received) as *mut c_void;
+            assert_eq!(las_receiver_add_callback_track(receiver, 0, c"host".as_ptr(), Some(count_samples), user_data), 0);
+            assert_eq!(las_receiver_start(receiver), 0);
+
+            let sender = las_sender_create(c"127.0.0.1:47613".as_ptr());
+            assert!(!sender.is_null(), "{:?}", CStr::from_ptr(las_last_error()));
+            let track = las_sender_add_push_track(sender, c"host".as_ptr(), 2, 0);
+            assert_eq!(track, 0);
+            assert_eq!(las_sender_start(sender), 0);
+            let samples = vec![0.25f32; 480 * 2];
+            for _ in 0..20 {
+                assert_eq!(las_sender_push_audio(sender, track as u8, samples.as_ptr(), samples.len()), 0);
+            }
+            assert_eq!(las_sender_push_audio(sender, 9, samples.as_ptr(), samples.len()), -1);
+            assert!(!las_last_error().is_null());
+
+            let deadline = Instant::now() + Duration::from_secs(2);
+            while received.load(Ordering::SeqCst) == 0 && Instant::now() < deadline {
+                thread::sleep(Duration::from_millis(10));
+            }
+            assert!(received.load(Ordering::SeqCst) > 0);
+            let mut stats = LasTrackStats::default();
+            assert_eq!(las_receiver_get_stats(receiver, 0, &mut stats), 0);
+            assert_eq!(las_receiver_get_stats(receiver, 7, &mut stats), -1);
+
+            las_sender_destroy(sender);
+            las_receiver_destroy(receiver);
+        }
+    }
+}
@@ -0,0 +1,238 @@
+//! Track start/stop/error hooks
+//!
+//! Each `[[hooks]]` entry runs a shell command, posts to a webhook, or
+//! both, when one of its tracks starts, stops or fails, e.g. to switch an
+//! "ON AIR" light through Home Assistant when the mic track goes live:
+//!
+//! ```toml
+//! [[hooks]]
+//! tracks = [0]
+//! events = ["start", "stop"]
+//! url = "http://homeassistant.lan:8123/api/webhook/on_air"
+//! ```
+//!
+//! Commands run through `sh -c` (`cmd /C` on Windows) with the event in
+//! `LAS_EVENT`, `LAS_TRACK_ID`, `LAS_TRACK_NAME`, `LAS_ROLE` and, for
+//! errors, `LAS_ERROR`; webhooks get the same fields as a JSON [`HookPayload`].
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::TrackHook;
+
+// Following the event bus needs the track manager, tokio and the webhook client
+#[cfg(feature = "network")]
+use {
+    crate::network::webhook::post_json,
+    crate::reload::LiveConfig,
+    crate::sync::now_us,
+    crate::tracks::{manager::TrackEvent, TrackManager},
+    std::process::Stdio,
+    std::sync::Arc,
+    std::time::Duration,
+    tokio::process::Command,
+    tokio::sync::broadcast,
+};
+
+/// Longest a hook command may run before it is killed
+#[cfg(feature = "network")]
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Track events a hook can run on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HookEvent {
+    Start,
+    Stop,
+    Error,
+}
+
+impl HookEvent {
+    /// Map a track event to a hook event, its track and error message
+    #[cfg(feature = "network")]
+    pub fn from_track_event(event: &TrackEvent) -> Option<(u8, Self, Option<String>)> {
+        match event {
+            TrackEvent::Started(id) => Some((*id, Self::Start, None)),
+            TrackEvent::Stopped(id) => Some((*id, Self::Stop, None)),
+            TrackEvent::Error(id, message) => Some((*id, Self::Error, Some(message.clone()))),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Start => "start",
+            Self::Stop => "stop",
+            Self::Error => "error",
+        }
+    }
+}
+
+impl TrackHook {
+    /// Check whether this hook runs for an event on a track
+    pub fn wants(&self, track_id: u8, event: HookEvent) -> bool {
+        (self.tracks.is_empty() || self.tracks.contains(&track_id))
+            && (self.events.is_empty() || self.events.contains(&event))
+    }
+}
+
+/// What a hook is told about the event
+#[derive(Debug, Clone, Serialize)]
+pub struct HookPayload {
+    pub event: HookEvent,
+    pub track_id: u8,
+    pub track_name: String,
+    /// "sender" or "receiver"
+    pub role: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub timestamp_us: u64,
+}
+
+impl HookPayload {
+    /// Environment variables for a hook command
+    pub fn env(&self) -> Vec<(&'static str, String)> {
+        let mut env = vec![
+            ("LAS_EVENT", self.event.name().to_string()),
+            ("LAS_TRACK_ID", self.track_id.to_string()),
+            ("LAS_TRACK_NAME", self.track_name.clone()),
+            ("LAS_ROLE", self.role.to_string()),
+        ];
+        if let Some(ref error) = self.error {
+            env.push(("LAS_ERROR", error.clone()));
+        }
+        env
+    }
+}
+
+/// Run a hook command through the shell, killing it after [`COMMAND_TIMEOUT`]
+#[cfg(feature = "network")]
+pub async fn run_command(command: &str, payload: &HookPayload) -> std::io::Result<()> {
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    let mut child = shell
+        .arg(command)
+        .envs(payload.env())
+        .stdin(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()?;
+
+    let status = tokio::time::timeout(COMMAND_TIMEOUT, child.wait())
+        .await
+        .map_err(|_| std::io::Error::new(std::io::ErrorKind::TimedOut, "hook command timed out"))??;
+    if !status.success() {
+        return Err(std::io::Error::other(format!("hook command exited with {}", status)));
+    }
+    Ok(())
+}
+
+/// Run the configured `hooks` on track start, stop and error events
+#[cfg(feature = "network")]
+pub async fn run(track_manager: Arc<TrackManager>, config: LiveConfig, is_sender: bool) {
+    let mut events = track_manager.subscribe();
+
+    loop {
+        let event = match events.recv().await {
+            Ok(event) => event,
+            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+        let Some((track_id, event, error)) = HookEvent::from_track_event(&event) else {
+            continue;
+        };
+        let hooks: Vec<TrackHook> = config
+            .borrow()
+            .hooks
+            .iter()
+            .filter(|hook| hook.wants(track_id, event))
+            .cloned()
+            .collect();
+        if hooks.is_empty() {
+            continue;
+        }
+
+        let payload = HookPayload {
+            event,
+            track_id,
+            track_name: track_manager
+                .get_track(track_id)
+                .map(|t| t.config.name.clone())
+                .unwrap_or_default(),
+            role: if is_sender { "sender" } else { "receiver" },
+            error,
+            timestamp_us: now_us(),
+        };
+
+        for hook in hooks {
+            let payload = payload.clone();
+            tokio::spawn(async move {
+                if let Some(ref command) = hook.command {
+                    tracing::debug!("Track {}: running {} hook", payload.track_id, payload.event.name());
+                    if let Err(e) = run_command(command, &payload).await {
+                        tracing::warn!("Track {} {} hook failed: {}", payload.track_id, payload.event.name(), e);
+                    }
+                }
+                if let Some(ref url) = hook.url {
+                    if let Err(e) = post_json(url, &payload).await {
+                        tracing::warn!("Track {} {} webhook failed: {}", payload.track_id, payload.event.name(), e);
+                    }
+                }
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hook_matching() {
+        #[cfg(feature = "network")]
+        {
+            assert_eq!(HookEvent::from_track_event(&TrackEvent::Started(1)), Some((1, HookEvent::Start, None)));
+            assert_eq!(
+                HookEvent::from_track_event(&TrackEvent::Error(2, "device lost".to_string())),
+                Some((2, HookEvent::Error, Some("device lost".to_string())))
+            );
+            assert_eq!(HookEvent::from_track_event(&TrackEvent::Paused(1)), None);
+        }
+
+        let hook = TrackHook {
+            tracks: vec![0],
+            events: vec![HookEvent::Start, HookEvent::Stop],
+            command: None,
+            url: Some("http://homeassistant.lan:8123/api/webhook/on_air".to_string()),
+        };
+        assert!(hook.wants(0, HookEvent::Start));
+        assert!(!hook.wants(0, HookEvent::Error));
+        assert!(!hook.wants(1, HookEvent::Start));
+        let any = TrackHook { tracks: Vec::new(), events: Vec::new(), command: Some("true".to_string()), url: None };
+        assert!(any.wants(7, HookEvent::Error));
+    }
+
+    #[cfg(all(feature = "network", unix))]
+    #[tokio::test]
+    async fn test_run_command() {
+        let payload = HookPayload {
+            event: HookEvent::Error,
+            track_id: 3,
+            track_name: "Mic".to_string(),
+            role: "sender",
+            error: Some("device lost".to_string()),
+            timestamp_us: 0,
+        };
+        let env = payload.env();
+        assert!(env.contains(&("LAS_EVENT", "error".to_string())));
+        assert!(env.contains(&("LAS_ERROR", "device lost".to_string())));
+
+        run_command(r#"test "$LAS_TRACK_ID:$LAS_TRACK_NAME" = "3:Mic""#, &payload).await.unwrap();
+        assert!(run_command("exit 3", &payload).await.is_err());
+    }
+}
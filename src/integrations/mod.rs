@@ -0,0 +1,8 @@
+//! Integrations with third-party audio software
+//!
+//! Helpers for the virtual cables and mixers received tracks are usually
+//! played into, so OBS and other apps can pick them up as inputs.
+
+pub mod virtual_device;
+
+pub use virtual_device::{VirtualDevice, VirtualDeviceCheck, VirtualDeviceKind};
@@ -0,0 +1,223 @@
+//! Virtual audio cables (VB-Cable, VoiceMeeter, Virtual Audio Cable)
+//!
+//! OBS and most streaming software only take audio from devices, so on the
+//! receiver each track is played into a virtual cable and picked up from
+//! its other end. Windows names these endpoints
+//! `<endpoint> (<driver>)`, e.g. `CABLE Input (VB-Audio Virtual Cable)`, and
+//! keeps the driver part when the endpoint is renamed in the Sound settings.
+//! That makes the cables easy to spot and lets `[virtual_devices.tracks]`
+//! map tracks onto them by the names given there:
+//!
+//! ```toml
+//! [virtual_devices.tracks]
+//! "OBS Mic" = 0
+//! "OBS Music" = 1
+//! ```
+//!
+//! A track with a `device_id` of its own keeps it.
+
+use std::collections::BTreeMap;
+
+use crate::config::VirtualDeviceConfig;
+use crate::protocol::AudioDeviceInfo;
+
+/// Where to get more virtual outputs
+const INSTALL_HINT: &str = "Install VB-Cable (https://vb-audio.com/Cable/, one free cable; \
+    Cable A+B and C+D add more), VoiceMeeter Banana or Potato (https://vb-audio.com/Voicemeeter/, \
+    3 and 5 inputs) or Virtual Audio Cable (https://vac.muzychenko.net/), then restart the receiver";
+
+/// Virtual cable driver
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VirtualDeviceKind {
+    VbCable,
+    VoiceMeeter,
+    /// Virtual Audio Cable by E. Muzychenko
+    Vac,
+}
+
+impl VirtualDeviceKind {
+    /// The driver a device belongs to, from its name
+    pub fn detect(name: &str) -> Option<Self> {
+        let name = name.to_lowercase();
+        // VoiceMeeter endpoints carry "VB-Audio" too
+        if name.contains("voicemeeter") {
+            Some(Self::VoiceMeeter)
+        } else if name.contains("vb-audio") || name.starts_with("cable") {
+            Some(Self::VbCable)
+        } else if name.contains("virtual audio cable") {
+            Some(Self::Vac)
+        } else {
+            None
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::VbCable => "VB-Cable",
+            Self::VoiceMeeter => "VoiceMeeter",
+            Self::Vac => "Virtual Audio Cable",
+        }
+    }
+}
+
+/// A virtual cable's playback end
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VirtualDevice {
+    pub kind: VirtualDeviceKind,
+    /// ID to play into: the stable ID when there is one
+    pub device_id: String,
+    pub name: String,
+}
+
+impl VirtualDevice {
+    /// Whether the device goes by `friendly`: its whole name or the part
+    /// before the driver name, ignoring case
+    pub fn answers_to(&self, friendly: &str) -> bool {
+        let endpoint = self.name.split(" (").next().unwrap_or(&self.name);
+        self.name.eq_ignore_ascii_case(friendly) || endpoint.trim().eq_ignore_ascii_case(friendly.trim())
+    }
+}
+
+/// Virtual outputs among `devices`
+pub fn virtual_outputs(devices: &[AudioDeviceInfo]) -> Vec<VirtualDevice> {
+    devices
+        .iter()
+        .filter(|d| d.is_output)
+        .filter_map(|d| {
+            let kind = VirtualDeviceKind::detect(&d.name)?;
+            let device_id = if d.stable_id.is_empty() { d.id.clone() } else { d.stable_id.clone() };
+            Some(VirtualDevice { kind, device_id, name: d.name.clone() })
+        })
+        .collect()
+}
+
+/// Outcome of matching `[virtual_devices]` against the devices present
+#[derive(Debug, Clone, Default)]
+pub struct VirtualDeviceCheck {
+    /// Virtual outputs found
+    pub outputs: Vec<VirtualDevice>,
+    /// Track -> virtual output it plays into
+    pub tracks: BTreeMap<u8, VirtualDevice>,
+    /// Friendly names no virtual output answers to
+    pub missing: Vec<String>,
+    /// Virtual outputs needed
+    pub required: usize,
+}
+
+impl VirtualDeviceCheck {
+    /// Find the virtual outputs and map the configured tracks onto them
+    pub fn run(config: &VirtualDeviceConfig, devices: &[AudioDeviceInfo]) -> Self {
+        let outputs = virtual_outputs(devices);
+        let mut tracks = BTreeMap::new();
+        let mut missing = Vec::new();
+        for (friendly, &track_id) in &config.tracks {
+            match outputs.iter().find(|d| d.answers_to(friendly)) {
+                Some(device) => {
+                    tracks.insert(track_id, device.clone());
+                }
+                None => missing.push(friendly.clone()),
+            }
+        }
+        Self {
+            required: config.tracks.len() + config.min_outputs,
+            outputs,
+            tracks,
+            missing,
+        }
+    }
+
+    /// Device ID track `track_id` is mapped to
+    pub fn device_for(&self, track_id: u8) -> Option<&str> {
+        self.tracks.get(&track_id).map(|d| d.device_id.as_str())
+    }
+
+    /// Enough virtual outputs, and every friendly name found
+    pub fn is_ok(&self) -> bool {
+        self.outputs.len() >= self.required && self.missing.is_empty()
+    }
+
+    /// What to do about it, one line each; empty when all is well
+    pub fn guidance(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        if self.outputs.len() < self.required {
+            let found = if self.outputs.is_empty() {
+                "none".to_string()
+            } else {
+                self.outputs.iter().map(|d| d.name.as_str()).collect::<Vec<_>>().join(", ")
+            };
+            lines.push(format!(
+                "{} virtual audio outputs needed, found {} ({})",
+                self.required,
+                self.outputs.len(),
+                found
+            ));
+            lines.push(INSTALL_HINT.to_string());
+        }
+        for friendly in &self.missing {
+            lines.push(format!(
+                "No virtual output named \"{}\": rename a spare cable's playback device to it \
+                 (Sound settings > Playback > Properties) or change [virtual_devices.tracks]",
+                friendly
+            ));
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn output(name: &str, stable_id: &str) -> AudioDeviceInfo {
+        AudioDeviceInfo {
+            id: format!("output:{}", name),
+            stable_id: stable_id.to_string(),
+            aliases: Vec::new(),
+            name: name.to_string(),
+            is_input: false,
+            is_output: true,
+            is_default: false,
+            sample_rates: vec![48000],
+            channels: vec![2],
+        }
+    }
+
+    #[test]
+    fn test_virtual_device_mapping() {
+        use VirtualDeviceKind::*;
+        assert_eq!(VirtualDeviceKind::detect("CABLE Input (VB-Audio Virtual Cable)"), Some(VbCable));
+        assert_eq!(VirtualDeviceKind::detect("VoiceMeeter Aux Input (VB-Audio VoiceMeeter AUX VAIO)"), Some(VoiceMeeter));
+        assert_eq!(VirtualDeviceKind::detect("Line 1 (Virtual Audio Cable)"), Some(Vac));
+        assert_eq!(VirtualDeviceKind::detect("Speakers (Realtek High Definition Audio)"), None);
+
+        let mut capture = output("CABLE Output (VB-Audio Virtual Cable)", "");
+        capture.is_output = false;
+        capture.is_input = true;
+        let devices = vec![
+            output("Speakers (Realtek High Definition Audio)", ""),
+            output("OBS Mic (VB-Audio Virtual Cable)", "output:{0.0.0.00000000}.{a1}"),
+            output("Line 1 (Virtual Audio Cable)", ""),
+            capture,
+        ];
+        assert_eq!(virtual_outputs(&devices).len(), 2);
+
+        let mut config = VirtualDeviceConfig::default();
+        config.tracks.insert("obs mic".to_string(), 0);
+        config.tracks.insert("Line 1 (Virtual Audio Cable)".to_string(), 1);
+        let check = VirtualDeviceCheck::run(&config, &devices);
+        assert!(check.is_ok());
+        assert!(check.guidance().is_empty());
+        assert_eq!(check.device_for(0), Some("output:{0.0.0.00000000}.{a1}"));
+        assert_eq!(check.device_for(1), Some("output:Line 1 (Virtual Audio Cable)"));
+        assert_eq!(check.device_for(2), None);
+
+        // Three cables wanted, two present, one name unknown
+        config.tracks.insert("OBS Music".to_string(), 2);
+        let check = VirtualDeviceCheck::run(&config, &devices);
+        assert!(!check.is_ok());
+        assert_eq!(check.missing, vec!["OBS Music".to_string()]);
+        let guidance = check.guidance();
+        assert_eq!(guidance.len(), 3);
+        assert!(guidance[0].starts_with("3 virtual audio outputs needed, found 2"));
+    }
+}
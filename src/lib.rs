@@ -75,15 +75,62 @@
 //! │  └─────────────┘   └─────────────┘   └─────────────┘   └─────────────┘    │
 //! └─────────────────────────────────────────────────────────────────────────────┘
 //! ```
+//!
+//! ## Features
+//!
+//! - `cpal`: audio device capture, playback and listing
+//! - `opus`: Opus encoder, decoder and encoder pool
+//! - `network`: UDP transport, control channel, [`tracks::TrackManager`] and the
+//!   tokio-based helpers (shutdown, recovery, notifications, hooks, stats log, config
+//!   hot-reload, diagnostics); with `opus` also the async `network::ReceiveStream` /
+//!   `network::SendSink`
+//! - `ui`: web UI and REST API (implies `network` and `cpal`)
+//! - `ffi`: C bindings in [`ffi`] for embedding, built as a cdylib with
+//!   `include/lan_audio_streamer.h` (not on by default)
+//! - `fuzz`: the `protocol::fuzz` entry points for the cargo-fuzz targets in
+//!   `fuzz/` (not on by default)
+//!
+//! The first four are on by default and the binaries need them. With
+//! `default-features = false` the protocol, jitter buffer, DSP and clock sync
+//! build without tokio, axum or cpal, as do the Ogg Opus helpers in [`codec::ogg`];
+//! add `opus` for the encoder and decoder.
+
+// The OpenAPI document is built from large `json!` literals
+#![recursion_limit = "256"]
 
 pub mod audio;
 pub mod codec;
 pub mod config;
+#[cfg(feature = "network")]
+pub mod diagnostics;
+pub mod dsp;
 pub mod error;
+pub mod events;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod hooks;
+pub mod integrations;
+#[cfg(feature = "network")]
 pub mod network;
+pub mod notify;
 pub mod protocol;
+#[cfg(feature = "network")]
+pub mod recovery;
+#[cfg(feature = "network")]
+pub mod reload;
+#[cfg(feature = "network")]
+pub mod selftest;
+#[cfg(feature = "network")]
+pub mod service;
+#[cfg(feature = "network")]
+pub mod shutdown;
+#[cfg(feature = "network")]
+pub mod stats_log;
+pub mod sync;
 pub mod tracks;
+#[cfg(feature = "ui")]
 pub mod ui;
+pub mod wake;
 
 pub use error::{Error, Result};
 
@@ -98,11 +145,23 @@ pub mod constants {
     /// Default Opus bitrate in bits per second
     pub const DEFAULT_BITRATE: u32 = 128_000;
     
+    /// Lowest bitrate the budget manager assigns to a track
+    pub const MIN_TRACK_BITRATE: u32 = 6_000;
+    
     /// Default frame size in milliseconds
     pub const DEFAULT_FRAME_SIZE_MS: f32 = 10.0;
     
-    /// Maximum number of concurrent tracks
-    pub const MAX_TRACKS: usize = 16;
+    /// Shortest Opus frame in milliseconds
+    pub const MIN_FRAME_SIZE_MS: f32 = 2.5;
+    
+    /// Default maximum number of concurrent tracks
+    pub const DEFAULT_MAX_TRACKS: usize = 16;
+    
+    /// Highest configurable `audio.max_tracks`; track IDs stay below it
+    pub const MAX_TRACKS: usize = 64;
+    
+    /// Maximum simulcast layers per track besides the primary encoding
+    pub const MAX_SIMULCAST_LAYERS: usize = 3;
     
     /// Default UDP port for audio streaming
     pub const DEFAULT_UDP_PORT: u16 = 5000;
@@ -113,9 +172,62 @@ pub mod constants {
     /// Default jitter buffer size in milliseconds
     pub const DEFAULT_JITTER_BUFFER_MS: u32 = 20;
     
+    /// Packets past its playout slot a packet may arrive and count as late, not lost
+    pub const DEFAULT_LATE_THRESHOLD: usize = 25;
+    
+    /// Maximum per-track playout delay in milliseconds
+    pub const MAX_PLAYOUT_DELAY_MS: u32 = 2000;
+    
+    /// Playback fade-out on end of stream in milliseconds
+    pub const SHUTDOWN_FADE_MS: u32 = 50;
+    
+    /// Ramp length for start/stop, mute and device switches in milliseconds
+    pub const TRANSITION_FADE_MS: u32 = 10;
+    
+    /// Recent events kept for `/api/events`
+    pub const EVENT_LOG_CAPACITY: usize = 1000;
+    
+    /// Track ID of the talkback stream from receiver to sender
+    pub const TALKBACK_TRACK_ID: u8 = 254;
+    
+    // Track IDs are one byte on the wire, below the reserved ones
+    const _: () = assert!(MAX_TRACKS <= TALKBACK_TRACK_ID as usize);
+    
+    /// How often a receiver repeats its track subscription in milliseconds
+    pub const SUBSCRIBE_INTERVAL_MS: u64 = 5000;
+    
+    /// How often a sender reports its tracks to a linked receiver in milliseconds
+    pub const LINKED_STATE_INTERVAL_MS: u64 = 2000;
+    
+    /// How often a sender announces its track names, colors and types in milliseconds
+    pub const TRACK_INFO_INTERVAL_MS: u64 = 2000;
+    
     /// Maximum packet size for UDP
     pub const MAX_PACKET_SIZE: usize = 1472; // MTU - IP/UDP headers
     
+    /// Datagram sizes tried when probing the path MTU, largest first
+    /// (Ethernet, PPPoE, typical VPN, IPv6 minimum, IPv4 minimum)
+    pub const MTU_PROBE_SIZES: [usize; 5] = [MAX_PACKET_SIZE, 1464, 1400, 1232, 548];
+    
+    /// Longest the application loops sleep between audio, packets and
+    /// messages, for their periodic work, in milliseconds
+    pub const LOOP_IDLE_MS: u64 = 5;
+    
+    /// How often a sender probes the path MTU to each destination in milliseconds
+    pub const MTU_PROBE_INTERVAL_MS: u64 = 10_000;
+    
+    /// How often NAT punches and rendezvous registrations are sent in milliseconds
+    pub const PUNCH_INTERVAL_MS: u64 = 2000;
+    
+    /// How often the public address is looked up over STUN in milliseconds
+    pub const STUN_INTERVAL_MS: u64 = 30_000;
+    
+    /// How often hostname targets are resolved again in milliseconds
+    pub const DNS_REFRESH_INTERVAL_MS: u64 = 30_000;
+    
+    /// How often a migrated track looks for its original device in milliseconds
+    pub const DEVICE_RETURN_CHECK_MS: u64 = 2000;
+    
     /// Lock-free ring buffer capacity (in frames)
     pub const RING_BUFFER_CAPACITY: usize = 256;
 }
@@ -0,0 +1,172 @@
+//! Source filtering for the receiver
+//!
+//! Datagrams are checked against an allowlist of sender addresses and a
+//! per-source token bucket before they are parsed, so a stray or hostile
+//! host on the LAN cannot create tracks or flood the decoder.
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::time::Instant;
+
+use crate::config::AppConfig;
+use crate::error::NetworkError;
+
+/// Forget rate limiter state for sources idle this long (seconds)
+const IDLE_SOURCE_SECS: f64 = 60.0;
+
+/// Address or CIDR network an allowed sender may come from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AllowedSource {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl AllowedSource {
+    /// Parse `192.168.1.20`, `192.168.1.0/24` or an IPv6 equivalent
+    pub fn parse(s: &str) -> Result<Self, NetworkError> {
+        let invalid = || NetworkError::ConnectionFailed(format!("Invalid allowed source: {}", s));
+        let (addr, prefix) = match s.trim().split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (s.trim(), None),
+        };
+
+        let addr: IpAddr = addr.parse().map_err(|_| invalid())?;
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(p) => p.parse::<u8>().ok().filter(|&p| p <= max).ok_or_else(invalid)?,
+            None => max,
+        };
+
+        Ok(Self { addr, prefix })
+    }
+
+    /// Check whether an address falls within this source
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.addr, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                prefix_match(u32::from(net) as u128, u32::from(ip) as u128, self.prefix, 32)
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                prefix_match(u128::from(net), u128::from(ip), self.prefix, 128)
+            }
+            _ => false,
+        }
+    }
+}
+
+fn prefix_match(net: u128, ip: u128, prefix: u8, bits: u32) -> bool {
+    if prefix == 0 {
+        return true;
+    }
+    let shift = bits - prefix as u32;
+    (net >> shift) == (ip >> shift)
+}
+
+/// Why a datagram was turned away
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rejection {
+    /// Source is not on the allowlist
+    NotAllowed,
+    /// Source exceeded its packet rate
+    RateLimited,
+}
+
+/// Token bucket for one source
+struct Bucket {
+    tokens: f64,
+    last: Instant,
+}
+
+/// Allowlist and per-source rate limiter
+pub struct SourceFilter {
+    allowed: Vec<AllowedSource>,
+    max_packets_per_sec: u32,
+    buckets: HashMap<IpAddr, Bucket>,
+    last_prune: Instant,
+}
+
+impl SourceFilter {
+    /// Build a filter from configuration
+    pub fn new(config: &AppConfig) -> Result<Self, NetworkError> {
+        let allowed = config.access.allowed_sources
+            .iter()
+            .map(|s| AllowedSource::parse(s))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            allowed,
+            max_packets_per_sec: config.max_packets_per_sec(),
+            buckets: HashMap::new(),
+            last_prune: Instant::now(),
+        })
+    }
+
+    /// Check whether a source is on the allowlist (empty list allows all)
+    pub fn is_allowed(&self, ip: IpAddr) -> bool {
+        self.allowed.is_empty() || self.allowed.iter().any(|a| a.contains(ip))
+    }
+
+    /// Admit or reject one datagram from `ip`
+    pub fn admit(&mut self, ip: IpAddr, now: Instant) -> Result<(), Rejection> {
+        if !self.is_allowed(ip) {
+            return Err(Rejection::NotAllowed);
+        }
+        if self.max_packets_per_sec == 0 {
+            return Ok(());
+        }
+
+        // Sources come and go; don't let the map grow without bound
+        if now.duration_since(self.last_prune).as_secs_f64() >= IDLE_SOURCE_SECS {
+            self.buckets.retain(|_, b| now.duration_since(b.last).as_secs_f64() < IDLE_SOURCE_SECS);
+            self.last_prune = now;
+        }
+
+        // One second worth of burst
+        let rate = self.max_packets_per_sec as f64;
+        let bucket = self.buckets.entry(ip).or_insert(Bucket { tokens: rate, last: now });
+        let elapsed = now.duration_since(bucket.last).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(rate);
+        bucket.last = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Rejection::RateLimited)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AccessConfig;
+
+    #[test]
+    fn test_allowlist_and_rate_limit() {
+        let config = AppConfig {
+            access: AccessConfig {
+                allowed_sources: vec!["192.168.1.0/24".to_string(), "10.0.0.5".to_string()],
+                max_packets_per_sec: Some(2),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut filter = SourceFilter::new(&config).unwrap();
+        let now = Instant::now();
+
+        let lan: IpAddr = "192.168.1.77".parse().unwrap();
+        assert_eq!(filter.admit("192.168.2.1".parse().unwrap(), now), Err(Rejection::NotAllowed));
+        assert_eq!(filter.admit("10.0.0.6".parse().unwrap(), now), Err(Rejection::NotAllowed));
+        assert!(filter.admit("10.0.0.5".parse().unwrap(), now).is_ok());
+
+        // Burst of two, then limited until tokens refill
+        assert!(filter.admit(lan, now).is_ok());
+        assert!(filter.admit(lan, now).is_ok());
+        assert_eq!(filter.admit(lan, now), Err(Rejection::RateLimited));
+        assert!(filter.admit(lan, now + std::time::Duration::from_millis(500)).is_ok());
+
+        assert!(AllowedSource::parse("10.0.0.0/33").is_err());
+        assert!(AllowedSource::parse("fe80::/10").unwrap().contains("fe80::1".parse().unwrap()));
+    }
+}
@@ -0,0 +1,706 @@
+//! Sender↔receiver control channel
+//!
+//! Control packets travel on the audio socket and are told apart by their
+//! magic number. Clock sync requests are answered inline by whichever end
+//! receives them so the responder timestamps stay tight; all other
+//! messages are forwarded to the application through a channel.
+//!
+//! ## Path MTU
+//!
+//! [`ControlEndpoint::probe_mtu`] sends one padded probe per size in
+//! [`MTU_PROBE_SIZES`]; the peer acks each probe that arrives, and the
+//! largest acked size of the latest round is the path's datagram limit.
+//! Until a probe is acked, [`MAX_PACKET_SIZE`] is assumed.
+//!
+//! ## NAT traversal
+//!
+//! STUN binding responses arrive on the audio socket too; the receive loops
+//! hand them to [`ControlEndpoint::handle_stun`], which records the public
+//! address. [`PeerMessage::Punch`] only opens NAT mappings and is dropped.
+//!
+//! ## Version handshake
+//!
+//! Senders say [`PeerMessage::Hello`] with every MTU probe round and the
+//! endpoint answers hellos inline, recording what each peer speaks.
+//! [`ControlEndpoint::negotiated`] gives the common version and features,
+//! or the legacy ones for a peer that never said hello or has not
+//! repeated it within [`PEER_TIMEOUT`]. With a key set, hellos are signed
+//! like every other message and unsigned ones are refused, so a third
+//! party cannot change what is spoken with a peer.
+//!
+//! ## Redundant links
+//!
+//! A sender on two links says [`PeerMessage::Link`] over each with the
+//! same random session ID; [`ControlEndpoint::primary_link`] then maps
+//! datagrams from any of them to the first link, where the sender reads
+//! replies. Announcements are only believed when signed, since anyone
+//! could otherwise redirect replies, and lapse after [`PEER_TIMEOUT`].
+//!
+//! ## Media clock
+//!
+//! Senders report how each track's media clock relates to the shared
+//! clock with [`PeerMessage::MediaClock`]; the endpoint keeps the latest
+//! report per sender and track, and [`ControlEndpoint::media_to_wall`]
+//! converts packet timestamps through it.
+//!
+//! ## Signing
+//!
+//! With a shared key configured, each datagram carries a counter and an
+//! HMAC-SHA256 tag after the message body:
+//!
+//! ```text
+//! Magic(2) │ bincode PeerMessage │ Counter(8, LE) │ HMAC-SHA256(32)
+//! ```
+//!
+//! The tag covers everything before it. Counters follow the sender's clock
+//! (µs since the epoch), so the two machines' clocks must agree to within
+//! [`REPLAY_WINDOW_US`]. Counters outside that window are refused, and
+//! inside it each counter and tag is accepted once from any source, so
+//! captured messages cannot be replayed from another address either.
+
+use crossbeam_channel::{bounded, Receiver, Sender};
+use std::collections::{BTreeSet, HashMap};
+use std::net::{SocketAddr, UdpSocket as StdUdpSocket};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::constants::{MAX_PACKET_SIZE, MTU_PROBE_INTERVAL_MS, MTU_PROBE_SIZES};
+use crate::error::{NetworkError, PacketError};
+use crate::network::nat;
+use crate::protocol::{
+    sign_control, verify_control, Negotiated, PeerMessage, ProtocolFeatures, PROTOCOL_VERSION, SIGNATURE_SIZE,
+};
+use crate::sync::{now_us, MediaClockRef};
+
+/// Largest difference between a signed message's counter and the local clock (µs)
+pub const REPLAY_WINDOW_US: u64 = 120_000_000;
+
+/// How long a peer's hello is remembered without being repeated
+pub const PEER_TIMEOUT: Duration = Duration::from_millis(6 * MTU_PROBE_INTERVAL_MS);
+
+/// Most peers remembered at once; the longest silent is forgotten first
+pub const MAX_PEERS: usize = 256;
+
+/// Per-peer values and when each was last heard
+type Heard<V> = HashMap<SocketAddr, (V, Instant)>;
+
+/// Insert into a per-peer map, dropping entries older than [`PEER_TIMEOUT`]
+/// and, when full, the least recently seen one
+fn remember<V>(map: &mut Heard<V>, key: SocketAddr, value: V) -> Option<V> {
+    map.retain(|_, (_, seen)| seen.elapsed() < PEER_TIMEOUT);
+    if map.len() >= MAX_PEERS && !map.contains_key(&key) {
+        if let Some(oldest) = map.iter().min_by_key(|(_, (_, seen))| *seen).map(|(k, _)| *k) {
+            map.remove(&oldest);
+        }
+    }
+    map.insert(key, (value, Instant::now())).map(|(old, _)| old)
+}
+
+/// Signs and verifies control datagrams with a shared key
+struct ControlSigner {
+    key: Vec<u8>,
+    /// Last counter used
+    counter: AtomicU64,
+    /// Counter and start of the tag of each message accepted within the window
+    seen: parking_lot::Mutex<BTreeSet<(u64, [u8; 8])>>,
+}
+
+impl ControlSigner {
+    fn new(key: &str) -> Self {
+        Self {
+            key: key.as_bytes().to_vec(),
+            counter: AtomicU64::new(0),
+            seen: parking_lot::Mutex::new(BTreeSet::new()),
+        }
+    }
+
+    /// Append counter and tag to a serialized message
+    fn sign(&self, data: &[u8]) -> Vec<u8> {
+        // The clock, but never the same counter twice
+        let now = now_us();
+        let next = |last: u64| now.max(last + 1);
+        let counter = self
+            .counter
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |last| Some(next(last)))
+            .map_or(now, next);
+        sign_control(data, &self.key, counter)
+    }
+
+    /// Check the tag and counter, returning the unsigned message bytes
+    fn verify<'a>(&self, data: &'a [u8]) -> Result<&'a [u8], PacketError> {
+        let (message, counter) = verify_control(data, &self.key)?;
+        let now = now_us();
+        if counter.abs_diff(now) > REPLAY_WINDOW_US {
+            tracing::debug!("Signed control message {} s off the local clock", counter.abs_diff(now) / 1_000_000);
+            return Err(PacketError::BadSignature);
+        }
+        let mut tag = [0u8; 8];
+        tag.copy_from_slice(&data[data.len() - 32..data.len() - 24]);
+        let mut seen = self.seen.lock();
+        if !seen.insert((counter, tag)) {
+            return Err(PacketError::BadSignature);
+        }
+        // Older messages are refused by their counter alone
+        let oldest = now.saturating_sub(REPLAY_WINDOW_US);
+        if seen.first().is_some_and(|&(first, _)| first < oldest) {
+            *seen = seen.split_off(&(oldest, [0; 8]));
+        }
+        Ok(message)
+    }
+}
+
+/// Control message received from a peer
+#[derive(Debug, Clone)]
+pub struct InboundControl {
+    pub message: PeerMessage,
+    pub from: SocketAddr,
+    /// Local receive time (µs, shared clock)
+    pub receive_time: u64,
+}
+
+/// Path MTU probing state of one peer
+#[derive(Debug, Default, Clone, Copy)]
+struct PathMtu {
+    /// Latest probe round sent
+    round: u32,
+    /// Round `size` was acked in
+    acked_round: u32,
+    /// Largest datagram acked (0 = none yet)
+    size: usize,
+}
+
+/// One end of the control channel
+pub struct ControlEndpoint {
+    socket: Arc<StdUdpSocket>,
+    inbound_tx: Sender<InboundControl>,
+    inbound_rx: Receiver<InboundControl>,
+    messages_sent: AtomicU64,
+    messages_received: AtomicU64,
+    signer: Option<ControlSigner>,
+    paths: parking_lot::Mutex<HashMap<SocketAddr, PathMtu>>,
+    /// Version and features agreed with each peer that said hello, and when
+    peers: parking_lot::Mutex<Heard<Negotiated>>,
+    /// Session and link number of each source address that announced a link, and when
+    links: parking_lot::Mutex<Heard<(u64, u8)>>,
+    /// Latest media clock report of each sender and track
+    media_clocks: parking_lot::Mutex<HashMap<(SocketAddr, u8), MediaClockRef>>,
+    /// Transaction ID of the latest STUN query
+    stun_txn: parking_lot::Mutex<Option<[u8; 12]>>,
+    public_addr: parking_lot::Mutex<Option<SocketAddr>>,
+}
+
+impl ControlEndpoint {
+    /// Create an endpoint sending on the given socket
+    pub fn new(socket: Arc<StdUdpSocket>) -> Self {
+        let (inbound_tx, inbound_rx) = bounded(256);
+        Self {
+            socket,
+            inbound_tx,
+            inbound_rx,
+            messages_sent: AtomicU64::new(0),
+            messages_received: AtomicU64::new(0),
+            signer: None,
+            paths: parking_lot::Mutex::new(HashMap::new()),
+            peers: parking_lot::Mutex::new(HashMap::new()),
+            links: parking_lot::Mutex::new(HashMap::new()),
+            media_clocks: parking_lot::Mutex::new(HashMap::new()),
+            stun_txn: parking_lot::Mutex::new(None),
+            public_addr: parking_lot::Mutex::new(None),
+        }
+    }
+
+    /// Sign outgoing and require signed incoming messages (None = unsigned)
+    pub fn with_key(mut self, key: Option<&str>) -> Self {
+        self.signer = key.filter(|k| !k.is_empty()).map(ControlSigner::new);
+        self
+    }
+
+    /// Send a control message to a peer
+    pub fn send(&self, message: &PeerMessage, to: SocketAddr) -> Result<(), NetworkError> {
+        let data = self.encode(message);
+        self.socket
+            .send_to(&data, to)
+            .map_err(|e| NetworkError::SendFailed(e.to_string()))?;
+        self.messages_sent.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Serialize a message, signed when a key is set, to send on another socket
+    pub fn encode(&self, message: &PeerMessage) -> Vec<u8> {
+        let data = message.serialize();
+        match self.signer {
+            Some(ref signer) => signer.sign(&data),
+            None => data.to_vec(),
+        }
+    }
+
+    /// Start a path MTU probe round to `to`
+    pub fn probe_mtu(&self, to: SocketAddr) {
+        let round = {
+            let mut paths = self.paths.lock();
+            let path = paths.entry(to).or_default();
+            path.round = path.round.wrapping_add(1);
+            path.round
+        };
+        let signature = if self.signer.is_some() { SIGNATURE_SIZE } else { 0 };
+        for size in MTU_PROBE_SIZES {
+            let empty = PeerMessage::MtuProbe { round, size: size as u16, padding: Vec::new() };
+            let padding = vec![0; size.saturating_sub(empty.serialize().len() + signature)];
+            let probe = PeerMessage::MtuProbe { round, size: size as u16, padding };
+            // Larger than the local interface allows: not a usable size either
+            if let Err(e) = self.send(&probe, to) {
+                tracing::trace!("MTU probe of {} bytes to {} failed: {}", size, to, e);
+            }
+        }
+    }
+
+    /// Largest datagram known to reach `to`
+    pub fn path_mtu(&self, to: SocketAddr) -> usize {
+        self.paths
+            .lock()
+            .get(&to)
+            .filter(|path| path.size > 0)
+            .map_or(MAX_PACKET_SIZE, |path| path.size)
+    }
+
+    /// Tell `to` which protocol version and features this end speaks
+    pub fn send_hello(&self, to: SocketAddr) {
+        let hello = PeerMessage::Hello { version: PROTOCOL_VERSION, features: ProtocolFeatures::supported(), reply: false };
+        if let Err(e) = self.send(&hello, to) {
+            tracing::debug!("Hello to {} failed: {}", to, e);
+        }
+    }
+
+    /// Version and features to use with `peer`
+    pub fn negotiated(&self, peer: SocketAddr) -> Negotiated {
+        self.peers
+            .lock()
+            .get(&peer)
+            .filter(|(_, seen)| seen.elapsed() < PEER_TIMEOUT)
+            .map_or_else(Negotiated::legacy, |&(negotiated, _)| negotiated)
+    }
+
+    /// Address of the first link of the sender `addr` belongs to, or `addr`
+    /// itself when it announced no link
+    pub fn primary_link(&self, addr: SocketAddr) -> SocketAddr {
+        let links = self.links.lock();
+        let live = |(_, seen): &&(_, Instant)| seen.elapsed() < PEER_TIMEOUT;
+        let Some(&((session, _), _)) = links.get(&addr).filter(live) else {
+            return addr;
+        };
+        links
+            .iter()
+            .find(|&(_, entry)| live(&entry) && entry.0 == (session, 0))
+            .map_or(addr, |(primary, _)| *primary)
+    }
+
+    /// Session `addr` announced a link of, while the announcement is fresh
+    pub fn link_session(&self, addr: SocketAddr) -> Option<u64> {
+        self.links
+            .lock()
+            .get(&addr)
+            .filter(|(_, seen)| seen.elapsed() < PEER_TIMEOUT)
+            .map(|&((session, _), _)| session)
+    }
+
+    /// Time on the sender's shared clock (µs) of a media timestamp on a
+    /// track from `from`, once the sender has reported that track's clock
+    pub fn media_to_wall(&self, from: SocketAddr, track_id: u8, ticks: u64) -> Option<u64> {
+        let from = self.primary_link(from);
+        self.media_clocks.lock().get(&(from, track_id)).map(|reference| reference.to_wall_us(ticks))
+    }
+
+    /// Ask a STUN server for this socket's public address
+    pub fn query_stun(&self, server: SocketAddr) {
+        let mut txn = [0u8; 12];
+        txn[..8].copy_from_slice(&now_us().to_le_bytes());
+        txn[8..].copy_from_slice(&self.socket.local_addr().map_or(0, |a| a.port() as u32).to_le_bytes());
+        *self.stun_txn.lock() = Some(txn);
+        if let Err(e) = self.socket.send_to(&nat::binding_request(txn), server) {
+            tracing::debug!("STUN query to {} failed: {}", server, e);
+        }
+    }
+
+    /// Take a STUN datagram; false if `data` is not STUN
+    pub fn handle_stun(&self, data: &[u8]) -> bool {
+        if !nat::is_stun(data) {
+            return false;
+        }
+        let Some(txn) = *self.stun_txn.lock() else {
+            return true;
+        };
+        if let Some(addr) = nat::parse_binding_response(data, &txn) {
+            if self.public_addr.lock().replace(addr) != Some(addr) {
+                tracing::info!("Public address: {}", addr);
+            }
+        }
+        true
+    }
+
+    /// Public address found by STUN, if any
+    pub fn public_addr(&self) -> Option<SocketAddr> {
+        *self.public_addr.lock()
+    }
+
+    /// Handle a raw control datagram
+    ///
+    /// Fails if the datagram is malformed or, with a key set, unsigned,
+    /// forged or replayed.
+    pub fn handle_datagram(
+        &self,
+        data: &[u8],
+        from: SocketAddr,
+        receive_time: u64,
+    ) -> Result<(), PacketError> {
+        let data = match self.signer {
+            Some(ref signer) => signer.verify(data)?,
+            None => data,
+        };
+        let message = PeerMessage::deserialize(data).ok_or(PacketError::BadControl)?;
+        self.handle_message(message, from, receive_time);
+        Ok(())
+    }
+
+    /// Handle an already parsed and verified control message
+    fn handle_message(&self, message: PeerMessage, from: SocketAddr, receive_time: u64) {
+        self.messages_received.fetch_add(1, Ordering::Relaxed);
+
+        match message {
+            PeerMessage::ClockRequest { t0 } => {
+                let response = PeerMessage::ClockResponse {
+                    t0,
+                    t1: receive_time,
+                    t2: now_us(),
+                };
+                if let Err(e) = self.send(&response, from) {
+                    tracing::debug!("Failed to answer clock request: {}", e);
+                }
+            }
+            PeerMessage::Punch => {
+                tracing::trace!("Punch from {}", from);
+            }
+            PeerMessage::Hello { version, features, reply } => {
+                let negotiated = Negotiated::with_peer(version, features);
+                if remember(&mut self.peers.lock(), from, negotiated) != Some(negotiated) {
+                    tracing::info!(
+                        "{} speaks protocol v{} ({}); using v{} with {}",
+                        from,
+                        version,
+                        features.names().join(", "),
+                        negotiated.version,
+                        negotiated.features.names().join(", ")
+                    );
+                }
+                if !reply {
+                    let answer = PeerMessage::Hello { version: PROTOCOL_VERSION, features: ProtocolFeatures::supported(), reply: true };
+                    if let Err(e) = self.send(&answer, from) {
+                        tracing::debug!("Failed to answer hello: {}", e);
+                    }
+                }
+            }
+            PeerMessage::Link { .. } if self.signer.is_none() => {
+                tracing::debug!("Ignoring unsigned link announcement from {}", from);
+            }
+            PeerMessage::Rendezvous { .. } | PeerMessage::PeerAddress { .. } if self.signer.is_none() => {
+                tracing::debug!("Ignoring unsigned rendezvous message from {}", from);
+            }
+            PeerMessage::Link { session, link } => {
+                if remember(&mut self.links.lock(), from, (session, link)) != Some((session, link)) {
+                    tracing::info!("Link {} of sender session {:016x} is {}", link, session, from);
+                }
+            }
+            PeerMessage::MediaClock { track_id, reference } => {
+                self.media_clocks.lock().insert((from, track_id), reference);
+            }
+            PeerMessage::MtuProbe { round, size, .. } => {
+                if let Err(e) = self.send(&PeerMessage::MtuAck { round, size }, from) {
+                    tracing::debug!("Failed to answer MTU probe: {}", e);
+                }
+            }
+            PeerMessage::MtuAck { round, size } => {
+                let mut paths = self.paths.lock();
+                let Some(path) = paths.get_mut(&from).filter(|path| path.round == round) else {
+                    return;
+                };
+                let size = size as usize;
+                if path.acked_round != round {
+                    if path.size != size {
+                        tracing::info!("Path MTU to {}: {} byte datagrams", from, size);
+                    }
+                    *path = PathMtu { acked_round: round, size, ..*path };
+                } else if size > path.size {
+                    tracing::info!("Path MTU to {}: {} byte datagrams", from, size);
+                    path.size = size;
+                }
+            }
+            message => {
+                let _ = self.inbound_tx.try_send(InboundControl {
+                    message,
+                    from,
+                    receive_time,
+                });
+            }
+        }
+    }
+
+    /// Get channel of inbound messages
+    pub fn inbound(&self) -> Receiver<InboundControl> {
+        self.inbound_rx.clone()
+    }
+
+    /// Try to get the next inbound message
+    pub fn try_recv(&self) -> Option<InboundControl> {
+        self.inbound_rx.try_recv().ok()
+    }
+
+    /// Get control messages sent count
+    pub fn messages_sent(&self) -> u64 {
+        self.messages_sent.load(Ordering::Relaxed)
+    }
+
+    /// Get control messages received count
+    pub fn messages_received(&self) -> u64 {
+        self.messages_received.load(Ordering::Relaxed)
+    }
+
+    /// Local UDP port the endpoint is bound to
+    pub fn local_port(&self) -> Option<u16> {
+        self.socket.local_addr().ok().map(|addr| addr.port())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signed_control_rejects_forgery_and_replay() {
+        let signer = ControlSigner::new("secret");
+        let message = PeerMessage::StreamPaused { track_id: 1, paused: true }.serialize();
+
+        let signed = signer.sign(&message);
+        assert_eq!(signer.verify(&signed), Ok(message.as_ref()));
+        // Same datagram again is a replay
+        assert_eq!(signer.verify(&signed), Err(PacketError::BadSignature));
+        // Counters keep increasing however fast messages go out
+        let (first, second) = (signer.sign(&message), signer.sign(&message));
+        assert_ne!(verify_control(&first, b"secret").unwrap().1, verify_control(&second, b"secret").unwrap().1);
+
+        // Too old to tell from a replay
+        let stale = sign_control(&message, b"secret", now_us() - REPLAY_WINDOW_US - 1_000_000);
+        assert_eq!(signer.verify(&stale), Err(PacketError::BadSignature));
+
+        // Wrong key or unsigned
+        let other = ControlSigner::new("guess");
+        assert!(signer.verify(&other.sign(&message)).is_err());
+        assert!(signer.verify(&message).is_err());
+    }
+
+    #[test]
+    fn test_replay_from_another_port() {
+        let bind = || {
+            let socket = StdUdpSocket::bind("127.0.0.1:0").unwrap();
+            socket.set_read_timeout(Some(std::time::Duration::from_millis(200))).unwrap();
+            Arc::new(socket)
+        };
+        let (sender, receiver, attacker) = (bind(), bind(), bind());
+        let to = receiver.local_addr().unwrap();
+        let a = ControlEndpoint::new(sender.clone()).with_key(Some("secret"));
+        let b = ControlEndpoint::new(receiver.clone()).with_key(Some("secret"));
+
+        a.send(&PeerMessage::StreamPaused { track_id: 1, paused: true }, to).unwrap();
+        let mut buf = [0u8; 2048];
+        let (len, from) = receiver.recv_from(&mut buf).unwrap();
+        assert!(b.handle_datagram(&buf[..len], from, now_us()).is_ok());
+
+        // The captured datagram, sent again from a new port
+        let captured = buf[..len].to_vec();
+        let from = attacker.local_addr().unwrap();
+        assert!(b.handle_datagram(&captured, from, now_us()).is_err());
+    }
+
+    #[test]
+    fn test_path_mtu_probe() {
+        let bind = || {
+            let socket = StdUdpSocket::bind("127.0.0.1:0").unwrap();
+            socket.set_read_timeout(Some(std::time::Duration::from_secs(1))).unwrap();
+            Arc::new(socket)
+        };
+        let (a_socket, b_socket) = (bind(), bind());
+        let b_addr = b_socket.local_addr().unwrap();
+        let a = ControlEndpoint::new(a_socket.clone()).with_key(Some("secret"));
+        let b = ControlEndpoint::new(b_socket.clone()).with_key(Some("secret"));
+        assert_eq!(a.path_mtu(b_addr), MAX_PACKET_SIZE);
+
+        // Probes are padded to their size; the two largest get lost on the way
+        a.probe_mtu(b_addr);
+        let mut buf = [0u8; 2048];
+        for (i, size) in MTU_PROBE_SIZES.into_iter().enumerate() {
+            let (len, from) = b_socket.recv_from(&mut buf).unwrap();
+            assert_eq!(len, size);
+            if i >= 2 {
+                b.handle_datagram(&buf[..len], from, 0).unwrap();
+            }
+        }
+        for _ in 2..MTU_PROBE_SIZES.len() {
+            let (len, from) = a_socket.recv_from(&mut buf).unwrap();
+            a.handle_datagram(&buf[..len], from, 0).unwrap();
+        }
+        assert_eq!(a.path_mtu(b_addr), MTU_PROBE_SIZES[2]);
+        assert!(a.try_recv().is_none());
+    }
+
+    #[test]
+    fn test_hello_negotiation() {
+        let bind = || {
+            let socket = StdUdpSocket::bind("127.0.0.1:0").unwrap();
+            socket.set_read_timeout(Some(std::time::Duration::from_secs(1))).unwrap();
+            Arc::new(socket)
+        };
+        let (a_socket, b_socket) = (bind(), bind());
+        let (a_addr, b_addr) = (a_socket.local_addr().unwrap(), b_socket.local_addr().unwrap());
+        let a = ControlEndpoint::new(a_socket.clone());
+        let b = ControlEndpoint::new(b_socket.clone());
+        // Silent peers are legacy
+        assert_eq!(a.negotiated(b_addr), Negotiated::legacy());
+
+        // A newer peer without CRC: our version, the shared features
+        let newer = PeerMessage::Hello {
+            version: PROTOCOL_VERSION + 1,
+            features: ProtocolFeatures::from_bits(ProtocolFeatures::FRAGMENTS | ProtocolFeatures::ENCRYPTION),
+            reply: false,
+        };
+        b.send(&newer, a_addr).unwrap();
+        let mut buf = [0u8; 2048];
+        let (len, from) = a_socket.recv_from(&mut buf).unwrap();
+        a.handle_datagram(&buf[..len], from, 0).unwrap();
+        let agreed = a.negotiated(b_addr);
+        assert_eq!(agreed.version, PROTOCOL_VERSION);
+        assert_eq!(agreed.features.names(), vec!["fragments"]);
+
+        // The hello is answered, and the answer is not
+        let (len, from) = b_socket.recv_from(&mut buf).unwrap();
+        b.handle_datagram(&buf[..len], from, 0).unwrap();
+        assert_eq!(b.negotiated(a_addr).features, ProtocolFeatures::supported());
+        assert!(a_socket.recv_from(&mut buf).is_err());
+        assert!(a.try_recv().is_none() && b.try_recv().is_none());
+    }
+
+    #[test]
+    fn test_signed_hello() {
+        let bind = || {
+            let socket = StdUdpSocket::bind("127.0.0.1:0").unwrap();
+            socket.set_read_timeout(Some(std::time::Duration::from_secs(1))).unwrap();
+            Arc::new(socket)
+        };
+        let (a_socket, b_socket) = (bind(), bind());
+        let (a_addr, b_addr) = (a_socket.local_addr().unwrap(), b_socket.local_addr().unwrap());
+        let a = ControlEndpoint::new(a_socket.clone()).with_key(Some("secret"));
+        let b = ControlEndpoint::new(b_socket.clone()).with_key(Some("secret"));
+        let forger = ControlEndpoint::new(b_socket);
+
+        // An unsigned hello changes nothing
+        let downgrade = PeerMessage::Hello { version: crate::protocol::LEGACY_PROTOCOL_VERSION, features: ProtocolFeatures::from_bits(0), reply: true };
+        forger.send(&downgrade, a_addr).unwrap();
+        let mut buf = [0u8; 2048];
+        let (len, from) = a_socket.recv_from(&mut buf).unwrap();
+        assert!(a.handle_datagram(&buf[..len], from, 0).is_err());
+        assert_eq!(a.negotiated(b_addr), Negotiated::legacy());
+
+        b.send_hello(a_addr);
+        let (len, from) = a_socket.recv_from(&mut buf).unwrap();
+        a.handle_datagram(&buf[..len], from, 0).unwrap();
+        assert_eq!(a.negotiated(b_addr).features, ProtocolFeatures::supported());
+    }
+
+    #[test]
+    fn test_peers_bounded() {
+        let mut peers = HashMap::new();
+        let addr = |port: u16| SocketAddr::from(([127, 0, 0, 1], port));
+        for port in 0..MAX_PEERS as u16 {
+            remember(&mut peers, addr(port), port);
+        }
+        // Seen in port order, then 0 again
+        let start = Instant::now() - Duration::from_secs(1);
+        peers.iter_mut().for_each(|(a, (_, seen))| *seen = start + Duration::from_millis(a.port() as u64));
+        assert_eq!(remember(&mut peers, addr(0), 0), Some(0));
+
+        // A new peer pushes out the longest silent one
+        remember(&mut peers, addr(60_000), 60_000);
+        assert_eq!(peers.len(), MAX_PEERS);
+        assert!(!peers.contains_key(&addr(1)) && peers.contains_key(&addr(0)));
+
+        // Expired ones go on the next insert
+        peers.values_mut().for_each(|(_, seen)| *seen -= PEER_TIMEOUT);
+        remember(&mut peers, addr(1), 1);
+        assert_eq!(peers.len(), 1);
+    }
+
+    #[test]
+    fn test_redundant_links() {
+        let bind = || {
+            let socket = StdUdpSocket::bind("127.0.0.1:0").unwrap();
+            socket.set_read_timeout(Some(std::time::Duration::from_secs(1))).unwrap();
+            Arc::new(socket)
+        };
+        let (receiver_socket, first, second) = (bind(), bind(), bind());
+        let receiver_addr = receiver_socket.local_addr().unwrap();
+        let (first_addr, second_addr) = (first.local_addr().unwrap(), second.local_addr().unwrap());
+        let receiver = ControlEndpoint::new(receiver_socket.clone()).with_key(Some("secret"));
+        let sender = ControlEndpoint::new(first).with_key(Some("secret"));
+        assert_eq!(receiver.primary_link(second_addr), second_addr);
+
+        // One link through the endpoint, the other signed and sent beside it
+        sender.send(&PeerMessage::Link { session: 7, link: 0 }, receiver_addr).unwrap();
+        second.send_to(&sender.encode(&PeerMessage::Link { session: 7, link: 1 }), receiver_addr).unwrap();
+        let mut buf = [0u8; 2048];
+        for _ in 0..2 {
+            let (len, from) = receiver_socket.recv_from(&mut buf).unwrap();
+            receiver.handle_datagram(&buf[..len], from, 0).unwrap();
+        }
+        assert_eq!(receiver.primary_link(second_addr), first_addr);
+        assert_eq!(receiver.primary_link(first_addr), first_addr);
+        assert_eq!(receiver.link_session(second_addr), Some(7));
+        let stranger = "127.0.0.1:9".parse().unwrap();
+        assert_eq!(receiver.primary_link(stranger), stranger);
+        assert!(receiver.try_recv().is_none());
+
+        // Without a key, announcements are not believed
+        let open = ControlEndpoint::new(receiver_socket.clone());
+        open.handle_datagram(&PeerMessage::Link { session: 7, link: 1 }.serialize(), second_addr, 0).unwrap();
+        assert_eq!(open.primary_link(second_addr), second_addr);
+        assert_eq!(open.link_session(second_addr), None);
+
+        // Nor remembered past the timeout
+        receiver.links.lock().values_mut().for_each(|(_, seen)| *seen -= PEER_TIMEOUT);
+        assert_eq!(receiver.primary_link(second_addr), second_addr);
+        assert_eq!(receiver.link_session(second_addr), None);
+    }
+
+    #[test]
+    fn test_media_clock_reports() {
+        let bind = || {
+            let socket = StdUdpSocket::bind("127.0.0.1:0").unwrap();
+            socket.set_read_timeout(Some(std::time::Duration::from_secs(1))).unwrap();
+            Arc::new(socket)
+        };
+        let (receiver_socket, sender_socket) = (bind(), bind());
+        let (receiver_addr, sender_addr) = (receiver_socket.local_addr().unwrap(), sender_socket.local_addr().unwrap());
+        let receiver = ControlEndpoint::new(receiver_socket.clone());
+        let sender = ControlEndpoint::new(sender_socket);
+        assert_eq!(receiver.media_to_wall(sender_addr, 2, 48_000), None);
+
+        let reference = MediaClockRef { ticks: 960, wall_us: 1_000_000 };
+        sender.send(&PeerMessage::MediaClock { track_id: 2, reference }, receiver_addr).unwrap();
+        let mut buf = [0u8; 2048];
+        let (len, from) = receiver_socket.recv_from(&mut buf).unwrap();
+        receiver.handle_datagram(&buf[..len], from, 0).unwrap();
+
+        // Only that sender's track converts
+        assert_eq!(receiver.media_to_wall(sender_addr, 2, 960 + 48_000), Some(2_000_000));
+        assert_eq!(receiver.media_to_wall(sender_addr, 3, 960), None);
+        assert_eq!(receiver.media_to_wall(receiver_addr, 2, 960), None);
+        assert!(receiver.try_recv().is_none());
+    }
+}
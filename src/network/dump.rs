@@ -0,0 +1,177 @@
+//! Packet dumps for reproducing glitches offline
+//!
+//! With `network.packet_dump` set, every audio datagram the sender sends
+//! and the receiver takes in (fragments as they are, before reassembly) is
+//! appended to that file with its arrival or send time and peer address.
+//! The `replay` bin sends a dump's datagrams to a receiver again with the
+//! original spacing, so a glitch reported from the field can be played
+//! through the receive pipeline as often as needed.
+//!
+//! The file is [`DUMP_MAGIC`] followed by bincode-encoded [`DumpRecord`]s.
+//! A sender and receiver in one process (duplex) share one file.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+
+use crate::error::NetworkError;
+use crate::sync::now_us;
+
+/// Start of every dump file
+pub const DUMP_MAGIC: &[u8; 8] = b"LASDUMP1";
+
+/// How often buffered records are written out
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Dumps open in this process, by path
+static OPEN: std::sync::Mutex<BTreeMap<PathBuf, Weak<PacketDump>>> = std::sync::Mutex::new(BTreeMap::new());
+
+/// Which way a datagram went
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Direction {
+    Sent,
+    Received,
+}
+
+/// One datagram in a dump
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DumpRecord {
+    /// Send or arrival time on the [`now_us`] clock
+    pub time_us: u64,
+    pub direction: Direction,
+    /// Destination of a sent datagram, source of a received one
+    pub peer: SocketAddr,
+    pub data: Vec<u8>,
+}
+
+struct Writer {
+    file: BufWriter<File>,
+    last_flush: Instant,
+}
+
+/// Appends datagrams to a dump file
+pub struct PacketDump {
+    path: PathBuf,
+    writer: Mutex<Writer>,
+}
+
+impl PacketDump {
+    /// The dump writing to `path`, created (truncating any old file) on first use
+    pub fn open(path: &Path) -> Result<Arc<Self>, NetworkError> {
+        let mut open = OPEN.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(dump) = open.get(path).and_then(Weak::upgrade) {
+            return Ok(dump);
+        }
+        let failed = |e: std::io::Error| NetworkError::InvalidDump(format!("{}: {}", path.display(), e));
+        let mut file = BufWriter::new(File::create(path).map_err(failed)?);
+        file.write_all(DUMP_MAGIC).map_err(failed)?;
+        let dump = Arc::new(Self {
+            path: path.to_path_buf(),
+            writer: Mutex::new(Writer { file, last_flush: Instant::now() }),
+        });
+        open.insert(path.to_path_buf(), Arc::downgrade(&dump));
+        tracing::warn!("Dumping audio packets to {}", path.display());
+        Ok(dump)
+    }
+
+    /// Open the dump `path` names, if any; a dump that can't be created is logged and skipped
+    pub fn open_configured(path: Option<&Path>) -> Option<Arc<Self>> {
+        PacketDump::open(path?).inspect_err(|e| tracing::error!("Packet dump disabled: {}", e)).ok()
+    }
+
+    /// Append one datagram
+    pub fn record(&self, direction: Direction, peer: SocketAddr, data: &[u8]) {
+        let record = DumpRecord { time_us: now_us(), direction, peer, data: data.to_vec() };
+        let mut writer = self.writer.lock();
+        if let Err(e) = bincode::serialize_into(&mut writer.file, &record) {
+            tracing::debug!("Packet dump write to {} failed: {}", self.path.display(), e);
+        }
+        if writer.last_flush.elapsed() >= FLUSH_INTERVAL {
+            let _ = writer.file.flush();
+            writer.last_flush = Instant::now();
+        }
+    }
+}
+
+impl Drop for PacketDump {
+    fn drop(&mut self) {
+        let _ = self.writer.get_mut().file.flush();
+    }
+}
+
+/// Reads the records of a dump in order
+pub struct DumpReader<R> {
+    reader: R,
+}
+
+impl DumpReader<BufReader<File>> {
+    /// Open a dump file
+    pub fn open(path: &Path) -> Result<Self, NetworkError> {
+        let file = File::open(path).map_err(|e| NetworkError::InvalidDump(format!("{}: {}", path.display(), e)))?;
+        Self::new(BufReader::new(file))
+    }
+}
+
+impl<R: Read> DumpReader<R> {
+    /// Check the header of a dump
+    pub fn new(mut reader: R) -> Result<Self, NetworkError> {
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic).map_err(|e| NetworkError::InvalidDump(e.to_string()))?;
+        if &magic != DUMP_MAGIC {
+            return Err(NetworkError::InvalidDump("Not a packet dump".to_string()));
+        }
+        Ok(Self { reader })
+    }
+}
+
+impl<R: Read> Iterator for DumpReader<R> {
+    type Item = Result<DumpRecord, NetworkError>;
+
+    /// The next record; a dump cut off mid-record (the process died) just ends
+    fn next(&mut self) -> Option<Self::Item> {
+        match bincode::deserialize_from(&mut self.reader) {
+            Ok(record) => Some(Ok(record)),
+            Err(e) => match *e {
+                bincode::ErrorKind::Io(ref io) if io.kind() == ErrorKind::UnexpectedEof => None,
+                _ => Some(Err(NetworkError::InvalidDump(e.to_string()))),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dump_round_trip() {
+        let path = std::env::temp_dir().join(format!("las-dump-{}.bin", std::process::id()));
+        let peer: SocketAddr = "192.168.1.20:5000".parse().unwrap();
+        {
+            let dump = PacketDump::open(&path).unwrap();
+            // A second component in the process shares the file
+            assert!(Arc::ptr_eq(&dump, &PacketDump::open(&path).unwrap()));
+            dump.record(Direction::Sent, peer, &[1, 2, 3]);
+            dump.record(Direction::Received, peer, &[4]);
+        }
+
+        let records: Vec<DumpRecord> = DumpReader::open(&path).unwrap().map(Result::unwrap).collect();
+        assert_eq!(records.len(), 2);
+        assert_eq!((records[0].direction, records[0].peer, records[0].data.as_slice()), (Direction::Sent, peer, &[1, 2, 3][..]));
+        assert_eq!((records[1].direction, records[1].data.as_slice()), (Direction::Received, &[4][..]));
+        assert!(records[1].time_us >= records[0].time_us);
+
+        // Cut off mid-record: the complete records still read
+        let mut data = std::fs::read(&path).unwrap();
+        data.truncate(data.len() - 2);
+        assert_eq!(DumpReader::new(data.as_slice()).unwrap().count(), 1);
+        assert!(DumpReader::new(&b"OggS...."[..]).is_err());
+        let _ = std::fs::remove_file(&path);
+    }
+}
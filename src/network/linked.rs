@@ -0,0 +1,138 @@
+//! Linked control: the receiver adjusting the sender
+//!
+//! With `linked_control.enabled` on both PCs, the receiver's web UI can change
+//! a sender track's bitrate, FEC and enabled state without anyone switching
+//! to the sender's UI. Changes go to the sender as
+//! [`PeerMessage::ConfigureTrack`]; the sender applies them to its tracks and
+//! reports each one back as [`PeerMessage::TrackState`], after a change and
+//! every [`LINKED_STATE_INTERVAL_MS`](crate::constants::LINKED_STATE_INTERVAL_MS).
+//! A disabled track is paused, not stopped, so enabling it resumes at once.
+
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use crate::error::{NetworkError, TrackError};
+use crate::network::control::{ControlEndpoint, InboundControl};
+use crate::protocol::{PeerMessage, RemoteTrackState, RemoteTrackUpdate, TrackConfigUpdate};
+use crate::tracks::TrackManager;
+
+/// Receiver side: the sender's tracks and a way to change them
+#[derive(Default)]
+pub struct LinkedControl {
+    /// Control endpoint and sender address, once audio has arrived
+    peer: parking_lot::RwLock<Option<(Arc<ControlEndpoint>, SocketAddr)>>,
+    tracks: parking_lot::RwLock<BTreeMap<u8, RemoteTrackState>>,
+}
+
+impl LinkedControl {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Send changes to `sender`; forgets the tracks of a previous sender
+    pub fn attach(&self, control: Arc<ControlEndpoint>, sender: SocketAddr) {
+        let mut peer = self.peer.write();
+        if peer.as_ref().map(|(_, addr)| *addr) != Some(sender) {
+            self.tracks.write().clear();
+        }
+        *peer = Some((control, sender));
+    }
+
+    /// Take a track report from the attached sender; returns whether `inbound` was one
+    pub fn handle(&self, inbound: &InboundControl) -> bool {
+        let PeerMessage::TrackState(ref state) = inbound.message else {
+            return false;
+        };
+        if self.peer.read().as_ref().map(|(_, addr)| addr.ip()) == Some(inbound.from.ip()) {
+            self.tracks.write().insert(state.track_id, state.clone());
+        }
+        true
+    }
+
+    /// Sender tracks as last reported
+    pub fn tracks(&self) -> Vec<RemoteTrackState> {
+        self.tracks.read().values().cloned().collect()
+    }
+
+    /// Ask the sender to change a track
+    ///
+    /// The sender answers with the track's new state; a sender without
+    /// linked control enabled ignores the request.
+    pub fn configure(&self, track_id: u8, update: RemoteTrackUpdate) -> Result<(), NetworkError> {
+        let peer = self.peer.read();
+        let Some((ref control, sender)) = *peer else {
+            return Err(NetworkError::SendFailed("No sender connected".to_string()));
+        };
+        control.send(&PeerMessage::ConfigureTrack { track_id, update }, sender)
+    }
+}
+
+/// Sender side: apply a requested change to a track
+pub fn apply(track_manager: &TrackManager, track_id: u8, update: &RemoteTrackUpdate) -> Result<(), TrackError> {
+    if update.bitrate.is_some() || update.fec_enabled.is_some() {
+        track_manager.update_track(track_id, TrackConfigUpdate {
+            bitrate: update.bitrate,
+            fec_enabled: update.fec_enabled,
+            ..Default::default()
+        })?;
+    }
+    if let Some(enabled) = update.enabled {
+        track_manager.set_paused(track_id, !enabled)?;
+    }
+    Ok(())
+}
+
+/// Sender side: reports for every track
+pub fn track_states(track_manager: &TrackManager) -> Vec<PeerMessage> {
+    track_manager
+        .track_ids()
+        .into_iter()
+        .filter_map(|id| {
+            let track = track_manager.get_track(id)?;
+            Some(PeerMessage::TrackState(RemoteTrackState {
+                track_id: track.id,
+                name: track.name.clone(),
+                bitrate: track.config.bitrate,
+                fec_enabled: track.config.fec_enabled,
+                enabled: !track.is_paused(),
+            }))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::TrackConfig;
+
+    #[test]
+    fn test_apply_and_report() {
+        let manager = TrackManager::new();
+        let id = manager.create_track(TrackConfig::default()).unwrap();
+        let update = RemoteTrackUpdate { bitrate: Some(64_000), fec_enabled: Some(true), enabled: Some(false) };
+        apply(&manager, id, &update).unwrap();
+        assert!(apply(&manager, 99, &update).is_err());
+
+        let states = track_states(&manager);
+        let PeerMessage::TrackState(ref state) = states[0] else {
+            panic!("expected a track state");
+        };
+        assert_eq!((state.bitrate, state.fec_enabled, state.enabled), (64_000, true, false));
+
+        // Reports count only from the attached sender, and a new sender starts clean
+        let linked = LinkedControl::new();
+        let socket = Arc::new(std::net::UdpSocket::bind("127.0.0.1:0").unwrap());
+        let sender: SocketAddr = "10.0.0.2:5000".parse().unwrap();
+        let inbound = |from: &str| InboundControl { message: states[0].clone(), from: from.parse().unwrap(), receive_time: 0 };
+        assert!(linked.handle(&inbound("10.0.0.2:5000")));
+        assert!(linked.tracks().is_empty());
+        linked.attach(Arc::new(ControlEndpoint::new(socket.clone())), sender);
+        linked.handle(&inbound("10.0.0.9:5000"));
+        assert!(linked.tracks().is_empty());
+        linked.handle(&inbound("10.0.0.2:5000"));
+        assert_eq!(linked.tracks(), vec![state.clone()]);
+        linked.attach(Arc::new(ControlEndpoint::new(socket)), "10.0.0.3:5000".parse().unwrap());
+        assert!(linked.tracks().is_empty());
+    }
+}
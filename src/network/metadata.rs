@@ -0,0 +1,107 @@
+//! Track labels from sender to receiver
+//!
+//! A sender announces each track's name, color and type as
+//! [`PeerMessage::TrackInfo`] every
+//! [`TRACK_INFO_INTERVAL_MS`](crate::constants::TRACK_INFO_INTERVAL_MS), so
+//! the receiver's tracks show the same labels as the sender's instead of
+//! "Track N". Announcements are repeated rather than acknowledged, which
+//! also covers a receiver that starts after the sender.
+
+use crate::error::TrackError;
+use crate::protocol::{PeerMessage, TrackConfigUpdate, TrackInfo};
+use crate::tracks::TrackManager;
+
+/// Longest track name taken from a peer, in characters
+pub const MAX_NAME_CHARS: usize = 64;
+
+/// Sender side: an announcement for every track
+pub fn announcements(track_manager: &TrackManager) -> Vec<PeerMessage> {
+    let mut ids = track_manager.track_ids();
+    ids.sort_unstable();
+    ids.into_iter()
+        .filter_map(|id| {
+            let track = track_manager.get_track(id)?;
+            Some(PeerMessage::TrackInfo(TrackInfo {
+                track_id: id,
+                name: track.name.clone(),
+                color: track.config.color.clone(),
+                track_type: track.config.track_type,
+            }))
+        })
+        .collect()
+}
+
+/// Receiver side: label local track `track_id` from an announcement
+///
+/// With `keep_name` the local name stays (the receiver's config names the
+/// track). Names lose control characters and are cut to [`MAX_NAME_CHARS`];
+/// colors other than `#rrggbb` are ignored. Returns whether anything changed.
+pub fn apply(track_manager: &TrackManager, track_id: u8, info: &TrackInfo, keep_name: bool) -> Result<bool, TrackError> {
+    let name: String = info.name.chars().filter(|c| !c.is_control()).take(MAX_NAME_CHARS).collect();
+    let name = name.trim();
+    let color = info.color.as_ref().filter(|color| {
+        color.strip_prefix('#').is_some_and(|hex| hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()))
+    });
+    let update = {
+        let track = track_manager.get_track(track_id).ok_or(TrackError::NotFound(track_id))?;
+        TrackConfigUpdate {
+            name: (!keep_name && !name.is_empty() && track.name != name).then(|| name.to_string()),
+            color: color.filter(|&color| track.config.color.as_ref() != Some(color)).cloned(),
+            track_type: (track.config.track_type != info.track_type).then_some(info.track_type),
+            ..Default::default()
+        }
+    };
+    if update.name.is_none() && update.color.is_none() && update.track_type.is_none() {
+        return Ok(false);
+    }
+    track_manager.update_track(track_id, update)?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{TrackConfig, TrackType};
+
+    #[test]
+    fn test_announce_and_apply() {
+        let sender = TrackManager::new();
+        sender.create_track(TrackConfig {
+            track_id: Some(2),
+            name: "Mic".to_string(),
+            color: Some("#ff8800".to_string()),
+            track_type: TrackType::Voice,
+            ..Default::default()
+        }).unwrap();
+        let announcements = announcements(&sender);
+        let [PeerMessage::TrackInfo(ref info)] = announcements[..] else {
+            panic!("expected one announcement");
+        };
+
+        let receiver = TrackManager::new();
+        assert!(matches!(apply(&receiver, 2, info, false), Err(TrackError::NotFound(2))));
+        receiver.create_track(TrackConfig { track_id: Some(2), name: "Track 2".to_string(), ..Default::default() }).unwrap();
+        assert!(apply(&receiver, 2, info, false).unwrap());
+        assert!(!apply(&receiver, 2, info, false).unwrap());
+        let status = receiver.get_track(2).unwrap().status();
+        assert_eq!((status.name.as_str(), status.color.as_deref(), status.track_type), ("Mic", Some("#ff8800"), TrackType::Voice));
+
+        // A name from the receiver's own config stays
+        receiver.update_track(2, TrackConfigUpdate { name: Some("Host mic".to_string()), ..Default::default() }).unwrap();
+        assert!(!apply(&receiver, 2, info, true).unwrap());
+        assert_eq!(receiver.get_track(2).unwrap().name, "Host mic");
+
+        // Whatever a peer sends, names stay short and printable
+        let hostile = TrackInfo {
+            track_id: 2,
+            name: format!("<img src=x>\u{1b}[2J{}", "x".repeat(500)),
+            color: Some("red;background:url(x)".to_string()),
+            track_type: TrackType::Voice,
+        };
+        assert!(apply(&receiver, 2, &hostile, false).unwrap());
+        let track = receiver.get_track(2).unwrap();
+        assert_eq!(track.name.chars().count(), MAX_NAME_CHARS);
+        assert!(track.name.starts_with("<img src=x>[2J"));
+        assert_eq!(track.config.color.as_deref(), Some("#ff8800"));
+    }
+}
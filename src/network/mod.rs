@@ -1,9 +1,48 @@
 //! Network subsystem for UDP audio transport
 
 pub mod udp;
+pub mod access;
+pub mod control;
+pub mod dump;
+pub mod linked;
+pub mod metadata;
+pub mod mqtt;
+pub mod nat;
+#[cfg(all(feature = "ui", feature = "opus"))]
+pub mod ogg_stream;
+pub mod osc;
+pub mod passthrough;
 pub mod sender;
 pub mod receiver;
+#[cfg(feature = "opus")]
+pub mod relay;
+pub mod resolve;
+#[cfg(feature = "opus")]
+pub mod stream;
+#[cfg(all(feature = "cpal", feature = "opus"))]
+pub mod talkback;
+pub mod timestamp;
+#[cfg(feature = "https")]
+pub mod tls;
+pub mod webhook;
+pub mod wol;
+#[cfg(feature = "webrtc")]
+pub mod webrtc;
 
 pub use udp::{UdpSocket, create_socket};
+pub use access::SourceFilter;
+pub use control::ControlEndpoint;
+pub use linked::LinkedControl;
+#[cfg(all(feature = "ui", feature = "opus"))]
+pub use ogg_stream::OggStreamer;
+pub use osc::{OscClient, OscMessage};
 pub use sender::AudioSender;
 pub use receiver::AudioReceiver;
+#[cfg(feature = "opus")]
+pub use relay::Relay;
+#[cfg(feature = "opus")]
+pub use stream::{ReceiveStream, SendSink, TrackId};
+#[cfg(all(feature = "cpal", feature = "opus"))]
+pub use talkback::Talkback;
+#[cfg(feature = "webrtc")]
+pub use webrtc::WebRtcPublisher;
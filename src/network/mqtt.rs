@@ -0,0 +1,431 @@
+//! MQTT state publishing
+//!
+//! A small MQTT 3.1.1 client so home-automation dashboards (Home Assistant,
+//! Node-RED) can follow and drive the streamer. Everything lives under
+//! `<topic_prefix>/<sender|receiver>`:
+//!
+//! ```text
+//! status                 "online", or "offline" as the retained last will
+//! health                 HealthReport JSON, every interval
+//! track/<id>/state       TrackStatus JSON, retained, on every change
+//! track/<id>/level       output level in dBFS, every interval
+//! track/<id>/mute/set    command: 1/0, true/false, ON/OFF, or toggle
+//! track/<id>/solo/set    command, as for mute
+//! track/<id>/gain/set    command: mix gain in dB
+//! ```
+//!
+//! Commands run as the matching OSC commands do. Messages go out at QoS 0
+//! over plain TCP; a lost broker is retried with backoff.
+
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::net::tcp::OwnedWriteHalf;
+use tokio::net::TcpStream;
+use tokio::sync::{broadcast, mpsc};
+
+use crate::config::MqttConfig;
+use crate::error::NetworkError;
+use crate::network::osc::{changed_track, OscCommand};
+use crate::tracks::manager::{TrackEvent, TrackManager};
+
+/// Largest packet accepted from the broker
+const MAX_MQTT_PACKET: usize = 64 * 1024;
+
+/// Keep-alive told to the broker; pings go out at half of it
+const KEEP_ALIVE: Duration = Duration::from_secs(30);
+
+/// Time allowed to connect and get the CONNACK
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Longest wait between reconnect attempts
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Level published for silence
+const SILENCE_DB: f32 = -96.0;
+
+const CONNECT: u8 = 0x10;
+const CONNACK: u8 = 0x20;
+const PUBLISH: u8 = 0x30;
+const SUBSCRIBE: u8 = 0x82;
+const PINGREQ: [u8; 2] = [0xC0, 0];
+
+fn error(reason: impl Into<String>) -> NetworkError {
+    NetworkError::Mqtt(reason.into())
+}
+
+/// Append an MQTT variable-length integer
+fn put_length(out: &mut Vec<u8>, mut len: usize) {
+    loop {
+        let mut byte = (len % 128) as u8;
+        len /= 128;
+        if len > 0 {
+            byte |= 0x80;
+        }
+        out.push(byte);
+        if len == 0 {
+            break;
+        }
+    }
+}
+
+/// Append a length-prefixed string or binary field
+fn put_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    out.extend_from_slice(&(bytes.len().min(u16::MAX as usize) as u16).to_be_bytes());
+    out.extend_from_slice(&bytes[..bytes.len().min(u16::MAX as usize)]);
+}
+
+/// Fixed header plus body
+fn packet(header: u8, body: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(body.len() + 5);
+    out.push(header);
+    put_length(&mut out, body.len());
+    out.extend_from_slice(body);
+    out
+}
+
+/// CONNECT with a clean session and a retained "offline" will on `will_topic`
+pub fn connect_packet(client_id: &str, username: Option<&str>, password: Option<&str>, will_topic: &str) -> Vec<u8> {
+    let mut flags = 0x02 | 0x04 | 0x20; // clean session, will, will retain
+    if username.is_some() {
+        flags |= 0x80;
+    }
+    if password.is_some() {
+        flags |= 0x40;
+    }
+    let mut body = Vec::new();
+    put_bytes(&mut body, b"MQTT");
+    body.push(4); // protocol level 3.1.1
+    body.push(flags);
+    body.extend_from_slice(&(KEEP_ALIVE.as_secs() as u16).to_be_bytes());
+    put_bytes(&mut body, client_id.as_bytes());
+    put_bytes(&mut body, will_topic.as_bytes());
+    put_bytes(&mut body, b"offline");
+    if let Some(username) = username {
+        put_bytes(&mut body, username.as_bytes());
+    }
+    if let Some(password) = password {
+        put_bytes(&mut body, password.as_bytes());
+    }
+    packet(CONNECT, &body)
+}
+
+/// QoS 0 PUBLISH
+pub fn publish_packet(topic: &str, payload: &[u8], retain: bool) -> Vec<u8> {
+    let mut body = Vec::with_capacity(topic.len() + payload.len() + 2);
+    put_bytes(&mut body, topic.as_bytes());
+    body.extend_from_slice(payload);
+    packet(PUBLISH | retain as u8, &body)
+}
+
+/// SUBSCRIBE to one filter at QoS 0
+pub fn subscribe_packet(packet_id: u16, filter: &str) -> Vec<u8> {
+    let mut body = packet_id.to_be_bytes().to_vec();
+    put_bytes(&mut body, filter.as_bytes());
+    body.push(0);
+    packet(SUBSCRIBE, &body)
+}
+
+/// Read one packet: its fixed header byte and body
+pub async fn read_packet<R: AsyncRead + Unpin>(reader: &mut R) -> Result<(u8, Vec<u8>), NetworkError> {
+    let read_err = |e: std::io::Error| NetworkError::ReceiveFailed(e.to_string());
+    let header = reader.read_u8().await.map_err(read_err)?;
+    let mut len = 0usize;
+    for shift in 0..4 {
+        let byte = reader.read_u8().await.map_err(read_err)?;
+        len |= ((byte & 0x7F) as usize) << (7 * shift);
+        if byte & 0x80 == 0 {
+            break;
+        }
+        if shift == 3 {
+            return Err(error("bad remaining length"));
+        }
+    }
+    if len > MAX_MQTT_PACKET {
+        return Err(NetworkError::PacketTooLarge(len));
+    }
+    let mut body = vec![0u8; len];
+    reader.read_exact(&mut body).await.map_err(read_err)?;
+    Ok((header, body))
+}
+
+/// Topic and payload of a PUBLISH body
+pub fn parse_publish(header: u8, body: &[u8]) -> Result<(String, Vec<u8>), NetworkError> {
+    let topic_len = body
+        .get(..2)
+        .map(|b| u16::from_be_bytes([b[0], b[1]]) as usize)
+        .ok_or_else(|| error("truncated publish"))?;
+    let topic = body.get(2..2 + topic_len).ok_or_else(|| error("truncated publish"))?;
+    let topic = String::from_utf8(topic.to_vec()).map_err(|_| error("topic is not UTF-8"))?;
+    // QoS 1 and 2 carry a packet ID
+    let skip = if header & 0x06 != 0 { 2 } else { 0 };
+    let payload = body.get(2 + topic_len + skip..).ok_or_else(|| error("truncated publish"))?;
+    Ok((topic, payload.to_vec()))
+}
+
+/// Map a command topic (below the base) and payload onto a track command
+pub fn parse_command(topic: &str, payload: &[u8]) -> Result<OscCommand, NetworkError> {
+    let payload = std::str::from_utf8(payload).map_err(|_| error("payload is not UTF-8"))?.trim();
+    let switch = || match payload.to_ascii_lowercase().as_str() {
+        "1" | "true" | "on" => Ok(Some(true)),
+        "0" | "false" | "off" => Ok(Some(false)),
+        "toggle" | "" => Ok(None),
+        other => Err(error(format!("bad switch value {:?}", other))),
+    };
+
+    let parts: Vec<&str> = topic.split('/').collect();
+    let ["track", id, action, "set"] = parts.as_slice() else {
+        return Err(error(format!("unknown topic {}", topic)));
+    };
+    let id: u8 = id.parse().map_err(|_| error(format!("bad track id {:?}", id)))?;
+    match *action {
+        "mute" => Ok(OscCommand::Mute(id, switch()?)),
+        "solo" => Ok(OscCommand::Solo(id, switch()?)),
+        "gain" => payload
+            .parse()
+            .map(|db| OscCommand::Gain(id, db))
+            .map_err(|_| error("gain needs a number")),
+        _ => Err(error(format!("unknown topic {}", topic))),
+    }
+}
+
+/// Publish MQTT state until the track manager goes away
+pub async fn run(track_manager: Arc<TrackManager>, config: MqttConfig, is_sender: bool) {
+    if !config.enabled {
+        return;
+    }
+    let role = if is_sender { "sender" } else { "receiver" };
+    let base = format!("{}/{}", config.topic_prefix.trim_end_matches('/'), role);
+    let client_id = if config.client_id.is_empty() {
+        format!("lan_audio_{}_{}", role, std::process::id())
+    } else {
+        config.client_id.clone()
+    };
+
+    let mut backoff = Duration::from_secs(1);
+    loop {
+        match session(&track_manager, &config, &client_id, &base, &mut backoff).await {
+            Ok(()) => break,
+            Err(e) => tracing::warn!("MQTT broker {}: {}", config.broker, e),
+        }
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+/// One broker connection; Ok once the track manager is gone
+async fn session(
+    track_manager: &TrackManager,
+    config: &MqttConfig,
+    client_id: &str,
+    base: &str,
+    backoff: &mut Duration,
+) -> Result<(), NetworkError> {
+    let status_topic = format!("{}/status", base);
+    let connect = async {
+        let mut stream = TcpStream::connect(&config.broker)
+            .await
+            .map_err(|e| NetworkError::ConnectionFailed(e.to_string()))?;
+        let connect = connect_packet(client_id, config.username.as_deref(), config.password.as_deref(), &status_topic);
+        stream.write_all(&connect).await.map_err(|e| NetworkError::SendFailed(e.to_string()))?;
+        let (header, body) = read_packet(&mut stream).await?;
+        match (header & 0xF0, body.get(1)) {
+            (CONNACK, Some(0)) => Ok(stream),
+            (CONNACK, Some(code)) => Err(error(format!("connection refused (code {})", code))),
+            _ => Err(error("expected CONNACK")),
+        }
+    };
+    let stream = tokio::time::timeout(CONNECT_TIMEOUT, connect).await.map_err(|_| NetworkError::Timeout)??;
+    let (mut reader, mut writer) = stream.into_split();
+    tracing::info!("MQTT connected to {} as {}", config.broker, base);
+    *backoff = Duration::from_secs(1);
+
+    let mut out = subscribe_packet(1, &format!("{}/track/+/+/set", base));
+    out.extend(publish_packet(&status_topic, b"online", true));
+    for track_id in track_manager.track_ids() {
+        out.extend(track_state(track_manager, base, track_id));
+    }
+    let send_err = |e: std::io::Error| NetworkError::SendFailed(e.to_string());
+    writer.write_all(&out).await.map_err(send_err)?;
+
+    // Packets are read in a task of their own: a read cut short by another
+    // select! branch would drop the bytes it had and leave the stream out of step
+    let (packet_tx, mut packets) = mpsc::channel(16);
+    let read_task = tokio::spawn(async move {
+        loop {
+            let packet = read_packet(&mut reader).await;
+            let failed = packet.is_err();
+            if packet_tx.send(packet).await.is_err() || failed {
+                break;
+            }
+        }
+    });
+    let result = forward(track_manager, &mut writer, &mut packets, config, base).await;
+    read_task.abort();
+    result
+}
+
+/// Apply commands from the broker and publish state until the connection or the track manager goes
+async fn forward(
+    track_manager: &TrackManager,
+    writer: &mut OwnedWriteHalf,
+    packets: &mut mpsc::Receiver<Result<(u8, Vec<u8>), NetworkError>>,
+    config: &MqttConfig,
+    base: &str,
+) -> Result<(), NetworkError> {
+    let send_err = |e: std::io::Error| NetworkError::SendFailed(e.to_string());
+    let mut events = track_manager.subscribe();
+    let mut updates = tokio::time::interval(Duration::from_millis(config.interval_ms.max(100)));
+    let mut pings = tokio::time::interval(KEEP_ALIVE / 2);
+    let command_prefix = format!("{}/", base);
+    loop {
+        let out = tokio::select! {
+            packet = packets.recv() => {
+                let (header, body) = packet.ok_or_else(|| error("connection closed"))??;
+                if header & 0xF0 == PUBLISH {
+                    let (topic, payload) = parse_publish(header, &body)?;
+                    let result = parse_command(topic.strip_prefix(&command_prefix).unwrap_or(&topic), &payload)
+                        .map_err(|e| e.to_string())
+                        .and_then(|command| command.apply(track_manager).map_err(|e| e.to_string()));
+                    if let Err(e) = result {
+                        tracing::warn!("MQTT {}: {}", topic, e);
+                    }
+                }
+                continue;
+            }
+            event = events.recv() => match event {
+                Ok(TrackEvent::Removed(track_id)) => {
+                    // Clear the retained state
+                    publish_packet(&format!("{}/track/{}/state", base, track_id), b"", true)
+                }
+                Ok(event) => match changed_track(&event) {
+                    Some(track_id) => track_state(track_manager, base, track_id),
+                    None => continue,
+                },
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return Ok(()),
+            },
+            _ = updates.tick() => periodic(track_manager, base),
+            _ = pings.tick() => PINGREQ.to_vec(),
+        };
+        writer.write_all(&out).await.map_err(send_err)?;
+    }
+}
+
+/// Retained state of a track
+fn track_state(track_manager: &TrackManager, base: &str, track_id: u8) -> Vec<u8> {
+    let Some(status) = track_manager.get_track(track_id).map(|t| t.status()) else {
+        return Vec::new();
+    };
+    let payload = serde_json::to_vec(&status).unwrap_or_default();
+    publish_packet(&format!("{}/track/{}/state", base, track_id), &payload, true)
+}
+
+/// Health and every track's level
+fn periodic(track_manager: &TrackManager, base: &str) -> Vec<u8> {
+    let health = serde_json::to_vec(&track_manager.health_report()).unwrap_or_default();
+    let mut out = publish_packet(&format!("{}/health", base), &health, false);
+    let meters = track_manager.meter_bus();
+    for track_id in track_manager.track_ids() {
+        let level = meters.level_db(track_id).unwrap_or(SILENCE_DB).max(SILENCE_DB);
+        out.extend(publish_packet(
+            &format!("{}/track/{}/level", base, track_id),
+            format!("{:.1}", level).as_bytes(),
+            false,
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::TrackConfig;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_packets() {
+        let connect = connect_packet("id", Some("user"), None, "lan/sender/status");
+        assert_eq!(connect[0], CONNECT);
+        assert_eq!(&connect[2..8], b"\0\x04MQTT");
+        assert_eq!(connect[9], 0x02 | 0x04 | 0x20 | 0x80);
+
+        // Bodies past 127 bytes take a second length byte
+        let payload = vec![b'x'; 300];
+        let publish = publish_packet("a/b", &payload, true);
+        assert_eq!(&publish[..3], &[PUBLISH | 1, 0xB1, 0x02]);
+        let (header, body) = read_packet(&mut publish.as_slice()).await.unwrap();
+        assert_eq!(parse_publish(header, &body).unwrap(), ("a/b".to_string(), payload));
+
+        assert_eq!(parse_command("track/2/mute/set", b"ON").unwrap(), OscCommand::Mute(2, Some(true)));
+        assert_eq!(parse_command("track/2/solo/set", b"toggle").unwrap(), OscCommand::Solo(2, None));
+        assert_eq!(parse_command("track/0/gain/set", b"-6.5").unwrap(), OscCommand::Gain(0, -6.5));
+        assert!(parse_command("track/0/gain/set", b"loud").is_err());
+        assert!(parse_command("track/0/state", b"").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_session() {
+        let broker = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let manager = Arc::new(TrackManager::new());
+        let id = manager.create_track(TrackConfig::default()).unwrap();
+        let config = MqttConfig {
+            enabled: true,
+            broker: broker.local_addr().unwrap().to_string(),
+            interval_ms: 100,
+            ..Default::default()
+        };
+        tokio::spawn(run(manager.clone(), config, true));
+
+        let (mut client, _) = broker.accept().await.unwrap();
+        let (header, _) = read_packet(&mut client).await.unwrap();
+        assert_eq!(header, CONNECT);
+        client.write_all(&[CONNACK, 2, 0, 0]).await.unwrap();
+
+        // Subscription, then the online status and the track's retained state
+        let (header, body) = read_packet(&mut client).await.unwrap();
+        assert_eq!(header, SUBSCRIBE);
+        assert!(body.ends_with(b"lan_audio/sender/track/+/+/set\0"));
+        let (header, body) = read_packet(&mut client).await.unwrap();
+        assert_eq!(parse_publish(header, &body).unwrap(), ("lan_audio/sender/status".to_string(), b"online".to_vec()));
+        let (header, body) = read_packet(&mut client).await.unwrap();
+        assert_eq!(parse_publish(header, &body).unwrap().0, format!("lan_audio/sender/track/{}/state", id));
+
+        // A command changes the track and its state comes back
+        let topic = format!("lan_audio/sender/track/{}/mute/set", id);
+        client.write_all(&publish_packet(&topic, b"1", false)).await.unwrap();
+        let state = format!("lan_audio/sender/track/{}/state", id);
+        loop {
+            let (header, body) = read_packet(&mut client).await.unwrap();
+            if header & 0xF0 != PUBLISH {
+                continue;
+            }
+            let (topic, payload) = parse_publish(header, &body).unwrap();
+            if topic == state {
+                let status: serde_json::Value = serde_json::from_slice(&payload).unwrap();
+                assert_eq!(status["muted"], true);
+                break;
+            }
+        }
+        assert!(manager.get_track(id).unwrap().is_muted());
+
+        // A command split across TCP segments survives the periodic updates in between
+        let command = publish_packet(&format!("lan_audio/sender/track/{}/solo/set", id), b"1", false);
+        client.write_all(&command[..5]).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        client.write_all(&command[5..]).await.unwrap();
+        let solo = async {
+            loop {
+                let (header, body) = read_packet(&mut client).await.unwrap();
+                if header & 0xF0 != PUBLISH {
+                    continue;
+                }
+                let (topic, payload) = parse_publish(header, &body).unwrap();
+                if topic == state && serde_json::from_slice::<serde_json::Value>(&payload).unwrap()["solo"] == true {
+                    break;
+                }
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(5), solo).await.unwrap();
+    }
+}
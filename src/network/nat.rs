@@ -0,0 +1,302 @@
+//! NAT traversal for remote peers
+//!
+//! Two machines behind home routers can reach each other once both have
+//! sent a datagram towards the other's public address, opening a mapping in
+//! each NAT ("hole punching"). The pieces:
+//!
+//! - A STUN binding request (RFC 5389) to `network.stun_server` tells an end
+//!   its public address, logged so it can be handed to the other side.
+//! - Ends send [`PeerMessage::Punch`] to every address in
+//!   `network.punch_peers` from the audio socket.
+//! - Or both register with a relay running with `relay.rendezvous` under the
+//!   same `network.rendezvous_session`. The relay sees their public
+//!   addresses and tells each about the other with
+//!   [`PeerMessage::PeerAddress`]; they then punch towards each other.
+//!   Registrations and introductions are signed with `network.control_key`,
+//!   which all three share, and the relay keeps only a few per source.
+//!
+//! This covers the common endpoint-independent NATs. Symmetric NATs map
+//! every destination to a new port and need the relay to forward the audio.
+
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::{Duration, Instant};
+
+use crate::config::NetworkConfig;
+use crate::constants::{PUNCH_INTERVAL_MS, STUN_INTERVAL_MS};
+use crate::network::control::{ControlEndpoint, InboundControl};
+use crate::network::resolve::resolve;
+use crate::protocol::PeerMessage;
+
+/// STUN magic cookie, bytes 4..8 of every STUN message
+const MAGIC_COOKIE: u32 = 0x2112_A442;
+
+/// STUN header size
+const STUN_HEADER_SIZE: usize = 20;
+
+const BINDING_REQUEST: u16 = 0x0001;
+const BINDING_RESPONSE: u16 = 0x0101;
+const ATTR_MAPPED_ADDRESS: u16 = 0x0001;
+const ATTR_XOR_MAPPED_ADDRESS: u16 = 0x0020;
+
+/// Rendezvous registrations older than this are forgotten
+const RENDEZVOUS_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Sessions one source IP may be registered in at once
+const MAX_SESSIONS_PER_SOURCE: usize = 4;
+
+/// Peers introduced to each other in one session
+const MAX_SESSION_PEERS: usize = 8;
+
+/// Registrations a rendezvous holds in all
+const MAX_REGISTRATIONS: usize = 1024;
+
+/// A STUN binding request with transaction ID `txn`
+pub fn binding_request(txn: [u8; 12]) -> [u8; STUN_HEADER_SIZE] {
+    let mut request = [0u8; STUN_HEADER_SIZE];
+    request[..2].copy_from_slice(&BINDING_REQUEST.to_be_bytes());
+    request[4..8].copy_from_slice(&MAGIC_COOKIE.to_be_bytes());
+    request[8..].copy_from_slice(&txn);
+    request
+}
+
+/// Whether a datagram looks like a STUN message
+pub fn is_stun(data: &[u8]) -> bool {
+    data.len() >= STUN_HEADER_SIZE && data[0] & 0xC0 == 0 && data[4..8] == MAGIC_COOKIE.to_be_bytes()
+}
+
+/// Public address from a binding response to transaction `txn`
+pub fn parse_binding_response(data: &[u8], txn: &[u8; 12]) -> Option<SocketAddr> {
+    if !is_stun(data) || u16::from_be_bytes([data[0], data[1]]) != BINDING_RESPONSE || data[8..20] != txn[..] {
+        return None;
+    }
+    let length = u16::from_be_bytes([data[2], data[3]]) as usize;
+    let mut attrs = data.get(STUN_HEADER_SIZE..STUN_HEADER_SIZE + length)?;
+    let mut mapped = None;
+    while attrs.len() >= 4 {
+        let kind = u16::from_be_bytes([attrs[0], attrs[1]]);
+        let len = u16::from_be_bytes([attrs[2], attrs[3]]) as usize;
+        let value = attrs.get(4..4 + len)?;
+        match kind {
+            ATTR_XOR_MAPPED_ADDRESS => return parse_address(value, Some(&data[4..20])),
+            ATTR_MAPPED_ADDRESS => mapped = parse_address(value, None),
+            _ => {}
+        }
+        // Attributes are padded to 4 bytes
+        attrs = attrs.get((4 + len).next_multiple_of(4)..).unwrap_or_default();
+    }
+    mapped
+}
+
+/// Decode a (XOR-)MAPPED-ADDRESS value; `xor` is the cookie and transaction ID
+fn parse_address(value: &[u8], xor: Option<&[u8]>) -> Option<SocketAddr> {
+    let mask = |bytes: &[u8]| -> Vec<u8> {
+        match xor {
+            Some(key) => bytes.iter().zip(key).map(|(b, k)| b ^ k).collect(),
+            None => bytes.to_vec(),
+        }
+    };
+    let port = mask(value.get(2..4)?);
+    let port = u16::from_be_bytes([port[0], port[1]]);
+    let ip = match value.get(1)? {
+        0x01 => {
+            let ip: [u8; 4] = mask(value.get(4..8)?).try_into().ok()?;
+            IpAddr::V4(Ipv4Addr::from(ip))
+        }
+        0x02 => {
+            let ip: [u8; 16] = mask(value.get(4..20)?).try_into().ok()?;
+            IpAddr::V6(Ipv6Addr::from(ip))
+        }
+        _ => return None,
+    };
+    Some(SocketAddr::new(ip, port))
+}
+
+/// Relay side: pairs peers registering under the same session name
+#[derive(Default)]
+pub struct Rendezvous {
+    sessions: HashMap<String, Vec<(SocketAddr, Instant)>>,
+}
+
+impl Rendezvous {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register `from` under `session`; returns the introductions to send
+    ///
+    /// A new registration is refused while its source, its session or the
+    /// rendezvous as a whole is full.
+    pub fn register(&mut self, session: &str, from: SocketAddr, now: Instant) -> Vec<(SocketAddr, PeerMessage)> {
+        self.sessions.retain(|_, peers| {
+            peers.retain(|(_, seen)| now.duration_since(*seen) < RENDEZVOUS_TIMEOUT);
+            !peers.is_empty()
+        });
+        let known = self.sessions.get(session).is_some_and(|peers| peers.iter().any(|(addr, _)| *addr == from));
+        if !known {
+            let registrations = || self.sessions.values().flatten();
+            let from_source = registrations().filter(|(addr, _)| addr.ip() == from.ip()).count();
+            let session_full = self.sessions.get(session).is_some_and(|peers| peers.len() >= MAX_SESSION_PEERS);
+            if from_source >= MAX_SESSIONS_PER_SOURCE || session_full || registrations().count() >= MAX_REGISTRATIONS {
+                tracing::debug!("Rendezvous session {}: refusing {}, full", session, from);
+                return Vec::new();
+            }
+        }
+        let peers = self.sessions.entry(session.to_string()).or_default();
+        match peers.iter_mut().find(|(addr, _)| *addr == from) {
+            Some(peer) => peer.1 = now,
+            None => {
+                tracing::info!("Rendezvous session {}: {} joined", session, from);
+                peers.push((from, now));
+            }
+        }
+
+        // Each side learns the other's public address
+        let introduce = |addr: SocketAddr| PeerMessage::PeerAddress { session: session.to_string(), addr };
+        peers
+            .iter()
+            .filter(|(addr, _)| *addr != from)
+            .flat_map(|&(addr, _)| [(from, introduce(addr)), (addr, introduce(from))])
+            .collect()
+    }
+
+    /// Peers currently registered under `session`
+    pub fn peers(&self, session: &str) -> Vec<SocketAddr> {
+        self.sessions.get(session).map_or_else(Vec::new, |peers| peers.iter().map(|(addr, _)| *addr).collect())
+    }
+}
+
+/// Peer side: STUN discovery, rendezvous registration and punching
+pub struct NatTraversal {
+    stun_server: Option<SocketAddr>,
+    rendezvous: Option<(SocketAddr, String)>,
+    peers: Vec<SocketAddr>,
+    last_stun: Option<Instant>,
+    last_punch: Option<Instant>,
+}
+
+impl NatTraversal {
+    /// Set up from `network.stun_server`, `rendezvous` and `punch_peers`
+    ///
+    /// Names that do not resolve are logged and left out.
+    pub fn new(config: &NetworkConfig) -> Self {
+        let lookup = |name: &str| match resolve(name) {
+            Ok(addr) => Some(addr),
+            Err(e) => {
+                tracing::warn!("NAT traversal: {}", e);
+                None
+            }
+        };
+        let rendezvous = config.rendezvous.as_deref().and_then(lookup);
+        if rendezvous.is_some() && config.rendezvous_session.is_empty() {
+            tracing::warn!("network.rendezvous is set without network.rendezvous_session; not registering");
+        }
+        Self {
+            stun_server: config.stun_server.as_deref().and_then(lookup),
+            rendezvous: rendezvous
+                .filter(|_| !config.rendezvous_session.is_empty())
+                .map(|addr| (addr, config.rendezvous_session.clone())),
+            peers: config.punch_peers.iter().filter_map(|peer| lookup(peer)).collect(),
+            last_stun: None,
+            last_punch: None,
+        }
+    }
+
+    /// Whether anything is configured
+    pub fn is_enabled(&self) -> bool {
+        self.stun_server.is_some() || self.rendezvous.is_some() || !self.peers.is_empty()
+    }
+
+    /// Peers being punched through to
+    pub fn peers(&self) -> &[SocketAddr] {
+        &self.peers
+    }
+
+    /// Send whatever is due: STUN queries, registrations and punches
+    pub fn tick(&mut self, control: &ControlEndpoint) {
+        let due = |last: Option<Instant>, interval_ms: u64| last.is_none_or(|t| t.elapsed() >= Duration::from_millis(interval_ms));
+        if let Some(server) = self.stun_server.filter(|_| due(self.last_stun, STUN_INTERVAL_MS)) {
+            control.query_stun(server);
+            self.last_stun = Some(Instant::now());
+        }
+        if due(self.last_punch, PUNCH_INTERVAL_MS) {
+            if let Some((server, ref session)) = self.rendezvous {
+                let _ = control.send(&PeerMessage::Rendezvous { session: session.clone() }, server);
+            }
+            for &peer in &self.peers {
+                if let Err(e) = control.send(&PeerMessage::Punch, peer) {
+                    tracing::debug!("Punch to {} failed: {}", peer, e);
+                }
+            }
+            self.last_punch = Some(Instant::now());
+        }
+    }
+
+    /// Take an introduction from the rendezvous; returns a newly learned peer
+    pub fn handle(&mut self, inbound: &InboundControl) -> Option<SocketAddr> {
+        let PeerMessage::PeerAddress { ref session, addr } = inbound.message else {
+            return None;
+        };
+        let (server, ref expected) = self.rendezvous.as_ref()?.clone();
+        if inbound.from != server || session != expected || self.peers.contains(&addr) {
+            return None;
+        }
+        tracing::info!("Rendezvous: peer {} in session {}", addr, session);
+        self.peers.push(addr);
+        self.last_punch = None;
+        Some(addr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stun_and_rendezvous() {
+        let txn = [7u8; 12];
+        let request = binding_request(txn);
+        assert!(is_stun(&request));
+        assert!(!is_stun(&PeerMessage::Punch.serialize()));
+
+        // Binding response with XOR-MAPPED-ADDRESS 203.0.113.5:40000
+        let mut response = binding_request(txn).to_vec();
+        response[..2].copy_from_slice(&BINDING_RESPONSE.to_be_bytes());
+        response[2..4].copy_from_slice(&12u16.to_be_bytes());
+        response.extend_from_slice(&ATTR_XOR_MAPPED_ADDRESS.to_be_bytes());
+        response.extend_from_slice(&8u16.to_be_bytes());
+        response.extend_from_slice(&[0, 0x01]);
+        let cookie = MAGIC_COOKIE.to_be_bytes();
+        response.extend_from_slice(&(40000u16 ^ (MAGIC_COOKIE >> 16) as u16).to_be_bytes());
+        response.extend([203u8, 0, 113, 5].iter().zip(cookie).map(|(b, k)| b ^ k));
+        assert_eq!(parse_binding_response(&response, &txn), Some("203.0.113.5:40000".parse().unwrap()));
+        assert_eq!(parse_binding_response(&response, &[0u8; 12]), None);
+        assert_eq!(parse_binding_response(&response[..30], &txn), None);
+
+        // Two peers in one session are introduced to each other
+        let (a, b): (SocketAddr, SocketAddr) = ("198.51.100.1:5000".parse().unwrap(), "203.0.113.5:40000".parse().unwrap());
+        let mut rendezvous = Rendezvous::new();
+        let now = Instant::now();
+        assert!(rendezvous.register("gig", a, now).is_empty());
+        assert!(rendezvous.register("other", b, now).is_empty());
+        let replies = rendezvous.register("gig", b, now);
+        assert_eq!(replies, vec![
+            (b, PeerMessage::PeerAddress { session: "gig".to_string(), addr: a }),
+            (a, PeerMessage::PeerAddress { session: "gig".to_string(), addr: b }),
+        ]);
+
+        // Registrations expire
+        let later = now + RENDEZVOUS_TIMEOUT + Duration::from_secs(1);
+        assert!(rendezvous.register("gig", b, later).is_empty());
+        assert_eq!(rendezvous.peers("gig"), vec![b]);
+
+        // One source only holds a few sessions, however many ports it uses
+        for i in 0..MAX_SESSIONS_PER_SOURCE + 2 {
+            rendezvous.register(&format!("spray-{}", i), SocketAddr::new(a.ip(), 6000 + i as u16), later);
+        }
+        let sprayed = (0..MAX_SESSIONS_PER_SOURCE + 2).filter(|i| !rendezvous.peers(&format!("spray-{}", i)).is_empty());
+        assert_eq!(sprayed.count(), MAX_SESSIONS_PER_SOURCE);
+        assert!(rendezvous.register("gig", a, later).is_empty());
+        assert_eq!(rendezvous.peers("gig"), vec![b]);
+    }
+}
@@ -0,0 +1,314 @@
+//! Ogg Opus streams for LAN players and Icecast
+//!
+//! Phones, smart speakers and media players can't join the UDP stream, but
+//! most of them play Ogg Opus over HTTP. With `http_stream.enabled` the
+//! receiver's web UI serves
+//!
+//! ```text
+//! GET /stream/<track_id>.ogg   one track, packets as received (no re-encode)
+//! GET /stream/mix.ogg          the mixdown, encoded once for all listeners
+//! ```
+//!
+//! and with `http_stream.icecast` set it pushes one of them to an Icecast
+//! server as a source. When `ui.auth_token` is set players pass `?token=`.
+//! Every listener gets an Ogg stream of its own starting at the next page,
+//! so it can tune in at any time.
+
+use axum::{
+    body::Body,
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use bytes::Bytes;
+use parking_lot::Mutex;
+use std::convert::Infallible;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::broadcast;
+
+use crate::codec::{OggOpusWriter, OpusEncoder};
+use crate::config::{HttpStreamConfig, IcecastConfig, OpusConfig};
+use crate::constants::{DEFAULT_CHANNELS, DEFAULT_SAMPLE_RATE};
+use crate::error::{CodecError, NetworkError};
+use crate::network::webhook::parse_url;
+
+/// Opus packets per Ogg page (50-100 ms at 10-20 ms frames)
+const PACKETS_PER_PAGE: usize = 5;
+
+/// Packets a slow listener may fall behind before it skips ahead
+const STREAM_QUEUE: usize = 256;
+
+/// Wait before reconnecting to Icecast
+const ICECAST_RETRY: Duration = Duration::from_secs(5);
+
+/// Time Icecast gets to accept the source
+const ICECAST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// What a stream carries
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamSource {
+    Track(u8),
+    Mix,
+}
+
+impl StreamSource {
+    /// Parse a `<track_id>.ogg` or `mix.ogg` path segment
+    pub fn from_path(name: &str) -> Option<Self> {
+        match name.strip_suffix(".ogg")? {
+            "mix" => Some(Self::Mix),
+            id => id.parse().ok().map(Self::Track),
+        }
+    }
+}
+
+/// Fans received Opus packets out to Ogg listeners
+pub struct OggStreamer {
+    config: HttpStreamConfig,
+    packets: broadcast::Sender<(StreamSource, Bytes)>,
+    listeners: Arc<AtomicUsize>,
+    /// Encodes the mixdown while anyone listens
+    mix_encoder: Mutex<OpusEncoder>,
+}
+
+impl OggStreamer {
+    /// Create a streamer whose mixdown arrives in frames of `mix_frame_size` samples per channel
+    pub fn new(config: HttpStreamConfig, mix_frame_size: usize) -> Result<Self, CodecError> {
+        let mix_encoder = OpusEncoder::new(OpusConfig {
+            bitrate: config.mix_bitrate,
+            frame_size: mix_frame_size,
+            sample_rate: DEFAULT_SAMPLE_RATE,
+            channels: DEFAULT_CHANNELS,
+            ..OpusConfig::music()
+        })?;
+        Ok(Self {
+            config,
+            packets: broadcast::channel(STREAM_QUEUE).0,
+            listeners: Arc::new(AtomicUsize::new(0)),
+            mix_encoder: Mutex::new(mix_encoder),
+        })
+    }
+
+    /// Forward a received Opus packet to the track's listeners
+    pub fn publish(&self, track_id: u8, payload: Bytes) {
+        if self.packets.receiver_count() > 0 {
+            let _ = self.packets.send((StreamSource::Track(track_id), payload));
+        }
+    }
+
+    /// Encode one mixdown frame for the mix listeners
+    pub fn publish_mix(&self, samples: &[f32]) {
+        if self.packets.receiver_count() == 0 {
+            return;
+        }
+        match self.mix_encoder.lock().encode(samples) {
+            Ok(packet) => {
+                let _ = self.packets.send((StreamSource::Mix, packet));
+            }
+            Err(e) => tracing::debug!("Mixdown stream encode failed: {}", e),
+        }
+    }
+
+    /// Start a listener, unless `max_listeners` are connected already
+    pub fn listen(&self, source: StreamSource) -> Option<Listener> {
+        let count = self.listeners.fetch_add(1, Ordering::Relaxed);
+        let guard = ListenerGuard(self.listeners.clone());
+        if count >= self.config.max_listeners {
+            return None;
+        }
+        let serial = uuid::Uuid::new_v4().as_u128() as u32;
+        let mut writer = OggOpusWriter::new(serial, DEFAULT_CHANNELS as u8, PACKETS_PER_PAGE);
+        Some(Listener {
+            source,
+            packets: self.packets.subscribe(),
+            headers: Some(writer.headers()),
+            writer,
+            _guard: guard,
+        })
+    }
+
+    /// Connected listeners, the Icecast push included
+    pub fn listener_count(&self) -> usize {
+        self.listeners.load(Ordering::Relaxed)
+    }
+}
+
+/// Counts a listener until dropped
+struct ListenerGuard(Arc<AtomicUsize>);
+
+impl Drop for ListenerGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// One listener's Ogg stream
+pub struct Listener {
+    source: StreamSource,
+    packets: broadcast::Receiver<(StreamSource, Bytes)>,
+    /// Header pages, sent first
+    headers: Option<Vec<u8>>,
+    writer: OggOpusWriter,
+    _guard: ListenerGuard,
+}
+
+impl Listener {
+    /// The next pages of the stream; None once the streamer is gone
+    pub async fn next_pages(&mut self) -> Option<Bytes> {
+        if let Some(headers) = self.headers.take() {
+            return Some(headers.into());
+        }
+        loop {
+            match self.packets.recv().await {
+                Ok((source, packet)) if source == self.source => {
+                    if let Some(page) = self.writer.push(&packet) {
+                        return Some(page.into());
+                    }
+                }
+                Ok(_) => {}
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::debug!("Stream listener skipped {} packets", skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    }
+}
+
+/// `/stream/:name` route, to be merged into the web UI router
+pub fn routes(streamer: Arc<OggStreamer>) -> Router {
+    Router::new()
+        .route("/stream/:name", get(handle_stream))
+        .with_state(streamer)
+}
+
+async fn handle_stream(
+    State(streamer): State<Arc<OggStreamer>>,
+    Path(name): Path<String>,
+) -> Response {
+    let Some(source) = StreamSource::from_path(&name) else {
+        return (StatusCode::NOT_FOUND, "Unknown stream").into_response();
+    };
+    let Some(listener) = streamer.listen(source) else {
+        return (StatusCode::SERVICE_UNAVAILABLE, "Too many listeners").into_response();
+    };
+    tracing::info!("Stream listener joined {} ({} connected)", name, streamer.listener_count());
+
+    let pages = futures_util::stream::unfold(listener, |mut listener| async move {
+        let pages = listener.next_pages().await?;
+        Some((Ok::<_, Infallible>(pages), listener))
+    });
+    (
+        [(header::CONTENT_TYPE, "audio/ogg"), (header::CACHE_CONTROL, "no-cache")],
+        Body::from_stream(pages),
+    )
+        .into_response()
+}
+
+/// Keep pushing a stream to the configured Icecast server, reconnecting on failure
+pub async fn run_icecast(streamer: Arc<OggStreamer>, config: IcecastConfig) {
+    let source = config.track.map_or(StreamSource::Mix, StreamSource::Track);
+    loop {
+        if let Err(e) = push_icecast(&streamer, &config, source).await {
+            tracing::warn!("Icecast push to {} failed: {}", config.url, e);
+        }
+        tokio::time::sleep(ICECAST_RETRY).await;
+    }
+}
+
+/// Log in as a source and send pages until the connection drops
+async fn push_icecast(streamer: &OggStreamer, config: &IcecastConfig, source: StreamSource) -> Result<(), NetworkError> {
+    let url = parse_url(&config.url)?;
+    if url.tls {
+        return Err(NetworkError::ConnectionFailed("Icecast needs an http:// URL".to_string()));
+    }
+    let mut listener = streamer
+        .listen(source)
+        .ok_or_else(|| NetworkError::ConnectionFailed("Too many listeners".to_string()))?;
+
+    let mut stream = TcpStream::connect(&url.addr)
+        .await
+        .map_err(|e| NetworkError::ConnectionFailed(e.to_string()))?;
+    let mut head = format!(
+        "PUT {} HTTP/1.1\r\nHost: {}\r\nAuthorization: Basic {}\r\nContent-Type: audio/ogg\r\nIce-Public: 0\r\n",
+        url.path,
+        url.host,
+        base64(format!("{}:{}", config.user, config.password).as_bytes()),
+    );
+    if let Some(ref name) = config.name {
+        head.push_str(&format!("Ice-Name: {}\r\n", name));
+    }
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes()).await.map_err(|e| NetworkError::SendFailed(e.to_string()))?;
+
+    let mut response = [0u8; 64];
+    let n = tokio::time::timeout(ICECAST_TIMEOUT, stream.read(&mut response))
+        .await
+        .map_err(|_| NetworkError::Timeout)?
+        .map_err(|e| NetworkError::ReceiveFailed(e.to_string()))?;
+    let status_line = String::from_utf8_lossy(&response[..n]);
+    let status = status_line.split_whitespace().nth(1).unwrap_or("");
+    if !status.starts_with('2') {
+        return Err(NetworkError::ConnectionFailed(format!("Icecast returned status {}", status)));
+    }
+    tracing::info!("Pushing {:?} to Icecast at {}", source, config.url);
+
+    while let Some(pages) = listener.next_pages().await {
+        stream.write_all(&pages).await.map_err(|e| NetworkError::SendFailed(e.to_string()))?;
+    }
+    Ok(())
+}
+
+/// Standard base64, for the Basic auth header
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0u32, |bits, (i, &b)| bits | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(bits >> (18 - 6 * i)) as usize & 63] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_stream_listeners() {
+        assert_eq!(StreamSource::from_path("3.ogg"), Some(StreamSource::Track(3)));
+        assert_eq!(StreamSource::from_path("mix.ogg"), Some(StreamSource::Mix));
+        assert_eq!(StreamSource::from_path("3.mp3"), None);
+        assert_eq!(base64(b"source:hackme"), "c291cmNlOmhhY2ttZQ==");
+
+        let config = HttpStreamConfig { enabled: true, max_listeners: 1, ..Default::default() };
+        let streamer = OggStreamer::new(config, 480).unwrap();
+        let mut listener = streamer.listen(StreamSource::Track(1)).unwrap();
+        assert!(streamer.listen(StreamSource::Mix).is_none());
+        assert!(listener.next_pages().await.unwrap().starts_with(b"OggS"));
+
+        // Other tracks are not muxed; a page follows every few packets
+        streamer.publish(2, Bytes::from_static(&[0xF8, 0]));
+        for _ in 0..PACKETS_PER_PAGE {
+            streamer.publish(1, Bytes::from_static(&[0xF8, 1]));
+        }
+        let page = listener.next_pages().await.unwrap();
+        assert_eq!(page[26] as usize, PACKETS_PER_PAGE);
+
+        // Hanging up frees the slot
+        drop(listener);
+        assert_eq!(streamer.listener_count(), 0);
+        assert!(streamer.listen(StreamSource::Mix).is_some());
+    }
+}
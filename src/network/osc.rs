@@ -0,0 +1,458 @@
+//! OSC control
+//!
+//! A UDP server for Open Sound Control so control surfaces (TouchOSC, QLab)
+//! can drive tracks with the same [`TrackManager`] operations as the REST API:
+//!
+//! ```text
+//! /track/<id>/gain  f        mix gain in dB
+//! /track/<id>/mute  [T|F|i|f] set, or toggle without an argument
+//! /track/<id>/solo  [T|F|i|f]
+//! /track/<id>/pause [T|F|i|f]
+//! /track/<id>/start          ignored with a false argument (button release)
+//! /track/<id>/stop
+//! /tracks/pause     [T|F|i|f] all tracks
+//! ```
+//!
+//! Bundles are unpacked and run immediately. With feedback targets
+//! configured, track state changes are sent back on the same paths so
+//! faders and buttons follow changes made elsewhere.
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::net::UdpSocket;
+use tokio::sync::broadcast;
+
+use crate::config::OscConfig;
+use crate::error::{NetworkError, TrackError};
+use crate::protocol::TrackConfigUpdate;
+use crate::tracks::manager::{TrackEvent, TrackManager};
+
+/// Largest datagram accepted
+const MAX_OSC_PACKET: usize = 8192;
+
+/// Nesting limit for bundles inside bundles
+const MAX_BUNDLE_DEPTH: usize = 8;
+
+/// One OSC argument
+#[derive(Debug, Clone, PartialEq)]
+pub enum OscArg {
+    Int(i32),
+    Float(f32),
+    Str(String),
+    Bool(bool),
+}
+
+impl OscArg {
+    /// Read as a switch state; buttons send 1/0 as int or float
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Bool(b) => Some(*b),
+            Self::Int(i) => Some(*i != 0),
+            Self::Float(f) => Some(*f >= 0.5),
+            Self::Str(_) => None,
+        }
+    }
+
+    pub fn as_f32(&self) -> Option<f32> {
+        match self {
+            Self::Int(i) => Some(*i as f32),
+            Self::Float(f) => Some(*f),
+            _ => None,
+        }
+    }
+}
+
+/// An OSC message
+#[derive(Debug, Clone, PartialEq)]
+pub struct OscMessage {
+    pub address: String,
+    pub args: Vec<OscArg>,
+}
+
+impl OscMessage {
+    pub fn new(address: impl Into<String>, args: Vec<OscArg>) -> Self {
+        Self { address: address.into(), args }
+    }
+
+    /// Serialize to an OSC 1.0 packet
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        write_str(&mut out, &self.address);
+        let mut tags = String::from(",");
+        for arg in &self.args {
+            tags.push(match arg {
+                OscArg::Int(_) => 'i',
+                OscArg::Float(_) => 'f',
+                OscArg::Str(_) => 's',
+                OscArg::Bool(true) => 'T',
+                OscArg::Bool(false) => 'F',
+            });
+        }
+        write_str(&mut out, &tags);
+        for arg in &self.args {
+            match arg {
+                OscArg::Int(i) => out.extend_from_slice(&i.to_be_bytes()),
+                OscArg::Float(f) => out.extend_from_slice(&f.to_be_bytes()),
+                OscArg::Str(s) => write_str(&mut out, s),
+                OscArg::Bool(_) => {}
+            }
+        }
+        out
+    }
+}
+
+/// Write a NUL-terminated string padded to 4 bytes
+fn write_str(out: &mut Vec<u8>, s: &str) {
+    out.extend_from_slice(s.as_bytes());
+    let padded = (s.len() / 4 + 1) * 4;
+    out.resize(out.len() + padded - s.len(), 0);
+}
+
+fn invalid(reason: impl Into<String>) -> NetworkError {
+    NetworkError::InvalidOsc(reason.into())
+}
+
+/// Reads OSC fields from a packet
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], NetworkError> {
+        let end = self.pos.checked_add(len).filter(|&end| end <= self.data.len());
+        let end = end.ok_or_else(|| invalid("truncated"))?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn str(&mut self) -> Result<String, NetworkError> {
+        let rest = &self.data[self.pos.min(self.data.len())..];
+        let len = rest.iter().position(|&b| b == 0).ok_or_else(|| invalid("unterminated string"))?;
+        let s = std::str::from_utf8(&rest[..len]).map_err(|_| invalid("string is not UTF-8"))?.to_string();
+        self.take((len / 4 + 1) * 4)?;
+        Ok(s)
+    }
+
+    fn u32(&mut self) -> Result<u32, NetworkError> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+}
+
+/// Parse a packet into its messages, unpacking bundles in order
+pub fn decode(data: &[u8]) -> Result<Vec<OscMessage>, NetworkError> {
+    let mut messages = Vec::new();
+    decode_into(data, 0, &mut messages)?;
+    Ok(messages)
+}
+
+fn decode_into(data: &[u8], depth: usize, messages: &mut Vec<OscMessage>) -> Result<(), NetworkError> {
+    let mut reader = Reader { data, pos: 0 };
+    if data.starts_with(b"#bundle\0") {
+        if depth >= MAX_BUNDLE_DEPTH {
+            return Err(invalid("bundles nested too deeply"));
+        }
+        // Time tag is ignored; everything runs on arrival
+        reader.take(16)?;
+        while reader.pos < data.len() {
+            let len = reader.u32()? as usize;
+            decode_into(reader.take(len)?, depth + 1, messages)?;
+        }
+        return Ok(());
+    }
+
+    let address = reader.str()?;
+    if !address.starts_with('/') {
+        return Err(invalid(format!("bad address {:?}", address)));
+    }
+    // Very old senders omit the type tags; treat as no arguments
+    let tags = if reader.pos < data.len() { reader.str()? } else { ",".to_string() };
+    let tags = tags.strip_prefix(',').ok_or_else(|| invalid("missing type tags"))?;
+
+    let mut args = Vec::new();
+    for tag in tags.chars() {
+        args.push(match tag {
+            'i' => OscArg::Int(reader.u32()? as i32),
+            'f' => OscArg::Float(f32::from_bits(reader.u32()?)),
+            's' | 'S' => OscArg::Str(reader.str()?),
+            'T' => OscArg::Bool(true),
+            'F' => OscArg::Bool(false),
+            'h' | 't' => {
+                let bytes = reader.take(8)?;
+                OscArg::Int(i64::from_be_bytes(bytes.try_into().expect("8 bytes")) as i32)
+            }
+            'd' => {
+                let bytes = reader.take(8)?;
+                OscArg::Float(f64::from_be_bytes(bytes.try_into().expect("8 bytes")) as f32)
+            }
+            'N' | 'I' => continue,
+            other => return Err(invalid(format!("unsupported type tag '{}'", other))),
+        });
+    }
+    messages.push(OscMessage { address, args });
+    Ok(())
+}
+
+/// A control operation addressed by OSC
+#[derive(Debug, Clone, PartialEq)]
+pub enum OscCommand {
+    Gain(u8, f32),
+    /// `None` toggles
+    Mute(u8, Option<bool>),
+    Solo(u8, Option<bool>),
+    Pause(u8, Option<bool>),
+    Start(u8),
+    Stop(u8),
+    PauseAll(bool),
+    /// Button release or other message to ignore
+    None,
+}
+
+impl OscCommand {
+    /// Map a message onto a command
+    pub fn parse(message: &OscMessage) -> Result<Self, NetworkError> {
+        let parts: Vec<&str> = message.address.trim_start_matches('/').split('/').collect();
+        let switch = message.args.first().and_then(OscArg::as_bool);
+
+        match parts.as_slice() {
+            ["tracks", "pause"] => Ok(Self::PauseAll(switch.unwrap_or(true))),
+            ["track", id, action] => {
+                let id: u8 = id.parse().map_err(|_| invalid(format!("bad track id {:?}", id)))?;
+                match *action {
+                    "gain" => message
+                        .args
+                        .first()
+                        .and_then(OscArg::as_f32)
+                        .map(|db| Self::Gain(id, db))
+                        .ok_or_else(|| invalid("gain needs a number")),
+                    "mute" => Ok(Self::Mute(id, switch)),
+                    "solo" => Ok(Self::Solo(id, switch)),
+                    "pause" => Ok(Self::Pause(id, switch)),
+                    "start" if switch != Some(false) => Ok(Self::Start(id)),
+                    "stop" if switch != Some(false) => Ok(Self::Stop(id)),
+                    "start" | "stop" => Ok(Self::None),
+                    _ => Err(invalid(format!("unknown address {}", message.address))),
+                }
+            }
+            _ => Err(invalid(format!("unknown address {}", message.address))),
+        }
+    }
+
+    /// Run the command against the track manager
+    pub fn apply(&self, track_manager: &TrackManager) -> Result<(), TrackError> {
+        let current = |id: u8, get: fn(&crate::tracks::track::Track) -> bool| {
+            track_manager.get_track(id).map(|t| get(&t)).ok_or(TrackError::NotFound(id))
+        };
+        match *self {
+            Self::Gain(id, db) => track_manager.update_track(
+                id,
+                TrackConfigUpdate { mix_gain_db: Some(db), ..Default::default() },
+            ),
+            Self::Mute(id, muted) => {
+                let muted = match muted {
+                    Some(muted) => muted,
+                    None => !current(id, |t| t.is_muted())?,
+                };
+                track_manager.set_muted(id, muted)
+            }
+            Self::Solo(id, solo) => {
+                let solo = match solo {
+                    Some(solo) => solo,
+                    None => !current(id, |t| t.is_solo())?,
+                };
+                track_manager.set_solo(id, solo)
+            }
+            Self::Pause(id, paused) => {
+                let paused = match paused {
+                    Some(paused) => paused,
+                    None => !current(id, |t| t.is_paused())?,
+                };
+                track_manager.set_paused(id, paused)
+            }
+            Self::Start(id) => track_manager.start_track(id),
+            Self::Stop(id) => track_manager.stop_track(id),
+            Self::PauseAll(paused) => {
+                track_manager.set_all_paused(paused);
+                Ok(())
+            }
+            Self::None => Ok(()),
+        }
+    }
+}
+
+/// Current state of a track as feedback messages
+pub fn track_state(track_manager: &TrackManager, track_id: u8) -> Vec<OscMessage> {
+    let Some(track) = track_manager.get_track(track_id) else {
+        return Vec::new();
+    };
+    let path = |action: &str| format!("/track/{}/{}", track_id, action);
+    vec![
+        OscMessage::new(path("gain"), vec![OscArg::Float(track.config.mix_gain_db)]),
+        OscMessage::new(path("mute"), vec![OscArg::Int(track.is_muted() as i32)]),
+        OscMessage::new(path("solo"), vec![OscArg::Int(track.is_solo() as i32)]),
+        OscMessage::new(path("pause"), vec![OscArg::Int(track.is_paused() as i32)]),
+        OscMessage::new(path("running"), vec![OscArg::Int(track.is_running() as i32)]),
+        OscMessage::new(path("state"), vec![OscArg::Str(track.state().to_string())]),
+    ]
+}
+
+/// Sends OSC messages to a fixed set of targets
+pub struct OscClient {
+    socket: UdpSocket,
+    targets: Vec<SocketAddr>,
+}
+
+impl OscClient {
+    /// Resolve `host:port` targets; unresolvable ones are skipped with a warning
+    pub async fn new(targets: &[String]) -> Result<Self, NetworkError> {
+        let socket = UdpSocket::bind("0.0.0.0:0")
+            .await
+            .map_err(|e| NetworkError::BindFailed(e.to_string()))?;
+        let mut resolved = Vec::new();
+        for target in targets {
+            match tokio::net::lookup_host(target).await.map(|mut addrs| addrs.next()) {
+                Ok(Some(addr)) => resolved.push(addr),
+                _ => tracing::warn!("OSC feedback target {} does not resolve", target),
+            }
+        }
+        Ok(Self { socket, targets: resolved })
+    }
+
+    pub async fn send(&self, message: &OscMessage) -> Result<(), NetworkError> {
+        let packet = message.encode();
+        for target in &self.targets {
+            self.socket
+                .send_to(&packet, target)
+                .await
+                .map_err(|e| NetworkError::SendFailed(e.to_string()))?;
+        }
+        Ok(())
+    }
+}
+
+/// Track whose state an event changes, for feedback
+pub(crate) fn changed_track(event: &TrackEvent) -> Option<u8> {
+    match event {
+        TrackEvent::Created(id)
+        | TrackEvent::Started(id)
+        | TrackEvent::Stopped(id)
+        | TrackEvent::ConfigUpdated(id)
+        | TrackEvent::Paused(id)
+        | TrackEvent::Resumed(id) => Some(*id),
+        TrackEvent::StateChanged(transition) => Some(transition.track_id),
+        _ => None,
+    }
+}
+
+/// Serve OSC control until the track manager goes away
+pub async fn run(track_manager: Arc<TrackManager>, config: OscConfig) {
+    if !config.enabled {
+        return;
+    }
+    let addr = format!("{}:{}", config.bind_address, config.port);
+    let socket = match UdpSocket::bind(&addr).await {
+        Ok(socket) => socket,
+        Err(e) => {
+            tracing::error!("OSC server failed to bind {}: {}", addr, e);
+            return;
+        }
+    };
+    let client = match OscClient::new(&config.feedback).await {
+        Ok(client) if !client.targets.is_empty() => Some(client),
+        Ok(_) => None,
+        Err(e) => {
+            tracing::warn!("OSC feedback disabled: {}", e);
+            None
+        }
+    };
+    tracing::info!("OSC control listening on {}", addr);
+
+    let mut events = track_manager.subscribe();
+    let mut buf = vec![0u8; MAX_OSC_PACKET];
+    loop {
+        tokio::select! {
+            received = socket.recv_from(&mut buf) => {
+                let (len, from) = match received {
+                    Ok(received) => received,
+                    Err(e) => {
+                        tracing::warn!("OSC receive failed: {}", e);
+                        continue;
+                    }
+                };
+                let messages = match decode(&buf[..len]) {
+                    Ok(messages) => messages,
+                    Err(e) => {
+                        tracing::debug!("Ignoring OSC packet from {}: {}", from, e);
+                        continue;
+                    }
+                };
+                for message in messages {
+                    let result = OscCommand::parse(&message)
+                        .map_err(|e| e.to_string())
+                        .and_then(|command| command.apply(&track_manager).map_err(|e| e.to_string()));
+                    if let Err(e) = result {
+                        tracing::warn!("OSC {} from {}: {}", message.address, from, e);
+                    }
+                }
+            }
+            event = events.recv(), if client.is_some() => {
+                let track_id = match event {
+                    Ok(event) => changed_track(&event),
+                    Err(broadcast::error::RecvError::Lagged(_)) => None,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                if let (Some(track_id), Some(client)) = (track_id, client.as_ref()) {
+                    for message in track_state(&track_manager, track_id) {
+                        if let Err(e) = client.send(&message).await {
+                            tracing::debug!("OSC feedback failed: {}", e);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::TrackConfig;
+
+    #[test]
+    fn test_decode_and_apply() {
+        let mute = OscMessage::new("/track/0/mute", vec![OscArg::Float(1.0)]);
+        let gain = OscMessage::new("/track/0/gain", vec![OscArg::Float(-6.0)]);
+        assert_eq!(decode(&mute.encode()).unwrap(), vec![mute.clone()]);
+
+        // Bundle of two messages
+        let mut bundle = b"#bundle\0".to_vec();
+        bundle.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 1]);
+        for message in [&gain, &OscMessage::new("/track/0/solo", vec![])] {
+            let packet = message.encode();
+            bundle.extend_from_slice(&(packet.len() as u32).to_be_bytes());
+            bundle.extend_from_slice(&packet);
+        }
+        let messages = decode(&bundle).unwrap();
+        assert_eq!(messages.len(), 2);
+        assert!(decode(&bundle[..bundle.len() - 2]).is_err());
+
+        let manager = TrackManager::new();
+        let id = manager.create_track(TrackConfig::default()).unwrap();
+        for message in std::iter::once(&mute).chain(&messages) {
+            OscCommand::parse(message).unwrap().apply(&manager).unwrap();
+        }
+        let track = manager.get_track(id).unwrap();
+        assert!(track.is_muted() && track.is_solo());
+        assert_eq!(track.config.mix_gain_db, -6.0);
+        drop(track);
+
+        // Button release does nothing; unknown tracks and paths fail
+        let release = OscMessage::new("/track/0/start", vec![OscArg::Int(0)]);
+        assert_eq!(OscCommand::parse(&release).unwrap(), OscCommand::None);
+        assert!(OscCommand::parse(&OscMessage::new("/track/9/mute", vec![])).unwrap().apply(&manager).is_err());
+        assert!(OscCommand::parse(&OscMessage::new("/mixer/fader", vec![])).is_err());
+        assert_eq!(track_state(&manager, id).len(), 6);
+    }
+}
@@ -0,0 +1,190 @@
+//! Opus passthrough from Ogg/Opus files
+//!
+//! Reads the Opus packets of an `.opus` file as they are, without decoding,
+//! and paces them in real time. The relay feeds them into its packet stream
+//! under [`FILE_SOURCE`], so a file goes out to receivers untouched like any
+//! forwarded sender track (or into the mix, when listed in `relay.mix`).
+//! Only mono and stereo files (channel mapping family 0) are supported.
+
+use crossbeam_channel::Sender;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufReader, ErrorKind, Read};
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::codec::ogg::packet_samples;
+use crate::config::RelayFile;
+use crate::error::CodecError;
+use crate::network::receiver::ReceivedPacket;
+use crate::protocol::{AudioPacket, PacketFlags};
+use crate::shutdown::ShutdownCoordinator;
+use crate::sync::now_us;
+
+/// Source address of packets read from files
+pub const FILE_SOURCE: SocketAddr = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0));
+
+/// Opus packets of an Ogg/Opus stream, in order
+pub struct OggOpusReader<R> {
+    reader: R,
+    /// Packets of the current page not handed out yet
+    pending: VecDeque<Vec<u8>>,
+    /// Start of a packet that goes on in the next page
+    partial: Vec<u8>,
+    stereo: bool,
+}
+
+impl OggOpusReader<BufReader<File>> {
+    /// Open an `.opus` file
+    pub fn open(path: &Path) -> Result<Self, CodecError> {
+        let file = File::open(path).map_err(|e| CodecError::OggOpus(format!("{}: {}", path.display(), e)))?;
+        Self::new(BufReader::new(file))
+    }
+}
+
+impl<R: Read> OggOpusReader<R> {
+    /// Read the Opus headers of a stream
+    pub fn new(reader: R) -> Result<Self, CodecError> {
+        let mut ogg = Self {
+            reader,
+            pending: VecDeque::new(),
+            partial: Vec::new(),
+            stereo: false,
+        };
+        let head = ogg.read_packet()?.ok_or_else(|| CodecError::OggOpus("Empty stream".to_string()))?;
+        if head.len() < 19 || !head.starts_with(b"OpusHead") {
+            return Err(CodecError::OggOpus("Not an Opus stream".to_string()));
+        }
+        let (channels, mapping) = (head[9], head[18]);
+        if mapping != 0 || !(1..=2).contains(&channels) {
+            return Err(CodecError::OggOpus(format!("Unsupported channel layout: {} channels, mapping {}", channels, mapping)));
+        }
+        ogg.stereo = channels == 2;
+        match ogg.read_packet()? {
+            Some(tags) if tags.starts_with(b"OpusTags") => Ok(ogg),
+            _ => Err(CodecError::OggOpus("Missing OpusTags header".to_string())),
+        }
+    }
+
+    pub fn is_stereo(&self) -> bool {
+        self.stereo
+    }
+
+    /// Next Opus packet, or None at the end of the stream
+    pub fn read_packet(&mut self) -> Result<Option<Vec<u8>>, CodecError> {
+        loop {
+            if let Some(packet) = self.pending.pop_front() {
+                return Ok(Some(packet));
+            }
+            if !self.read_page()? {
+                return Ok(None);
+            }
+        }
+    }
+
+    /// Split the next page into packets; false at the end of the stream
+    fn read_page(&mut self) -> Result<bool, CodecError> {
+        let mut header = [0u8; 27];
+        match self.reader.read_exact(&mut header) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(false),
+            Err(e) => return Err(CodecError::OggOpus(e.to_string())),
+        }
+        if &header[..4] != b"OggS" {
+            return Err(CodecError::OggOpus("Lost page sync".to_string()));
+        }
+        let truncated = |e: std::io::Error| CodecError::OggOpus(format!("Truncated page: {}", e));
+        let mut lacing = vec![0u8; header[26] as usize];
+        self.reader.read_exact(&mut lacing).map_err(truncated)?;
+        let mut body = vec![0u8; lacing.iter().map(|&l| l as usize).sum()];
+        self.reader.read_exact(&mut body).map_err(truncated)?;
+
+        // A lacing value below 255 ends a packet; 255 at the end carries it on
+        let mut offset = 0;
+        for &len in &lacing {
+            self.partial.extend_from_slice(&body[offset..offset + len as usize]);
+            offset += len as usize;
+            if len < 255 {
+                self.pending.push_back(std::mem::take(&mut self.partial));
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// Send a file's packets to `packets` in real time, until it ends or `shutdown`
+///
+/// A looped file starts over at the end. Packets keep their sequence numbers
+/// across loops so receivers see one continuous stream.
+pub fn stream_file(file: &RelayFile, packets: Sender<ReceivedPacket>, shutdown: ShutdownCoordinator) -> Result<(), CodecError> {
+    let started = Instant::now();
+    let mut played: u64 = 0;
+    let mut sequence: u32 = 0;
+    loop {
+        let mut reader = OggOpusReader::open(&file.path)?;
+        while let Some(payload) = reader.read_packet()? {
+            if shutdown.is_triggered() {
+                return Ok(());
+            }
+            let due = started + Duration::from_micros(played * 1_000_000 / 48_000);
+            std::thread::sleep(due.saturating_duration_since(Instant::now()));
+            played += packet_samples(&payload).unwrap_or(0) as u64;
+
+            let packet = AudioPacket {
+                track_id: file.track_id,
+                layer: 0,
+                flags: PacketFlags::new().set_stereo(reader.is_stereo()),
+                sequence,
+                timestamp: now_us(),
+                payload: payload.into(),
+            };
+            if packets.send(ReceivedPacket::new(packet, FILE_SOURCE)).is_err() {
+                return Ok(());
+            }
+            sequence = sequence.wrapping_add(1);
+        }
+        if !file.looped {
+            return Ok(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One Ogg page holding `packets`
+    fn page(packets: &[&[u8]]) -> Vec<u8> {
+        let mut lacing = Vec::new();
+        for packet in packets {
+            lacing.extend(std::iter::repeat_n(255u8, packet.len() / 255));
+            lacing.push((packet.len() % 255) as u8);
+        }
+        let mut page = b"OggS".to_vec();
+        page.extend_from_slice(&[0u8; 22]);
+        page.push(lacing.len() as u8);
+        page.extend(lacing);
+        for packet in packets {
+            page.extend_from_slice(packet);
+        }
+        page
+    }
+
+    #[test]
+    fn test_read_packets() {
+        let mut head = b"OpusHead".to_vec();
+        head.extend_from_slice(&[1, 2, 0x38, 0x01, 0x80, 0xbb, 0, 0, 0, 0, 0]);
+        let long = vec![0xfc; 600];
+        let mut stream = page(&[&head]);
+        stream.extend(page(&[b"OpusTags"]));
+        stream.extend(page(&[&[0x78, 1, 2], &long]));
+
+        let mut reader = OggOpusReader::new(stream.as_slice()).unwrap();
+        assert!(reader.is_stereo());
+        assert_eq!(reader.read_packet().unwrap(), Some(vec![0x78, 1, 2]));
+        assert_eq!(reader.read_packet().unwrap(), Some(long));
+        assert_eq!(reader.read_packet().unwrap(), None);
+        assert!(OggOpusReader::new(page(&[b"OpusTags"]).as_slice()).is_err());
+    }
+}
@@ -1,43 +1,108 @@
 //! Audio packet receiver
 //!
 //! Handles receiving audio packets and demultiplexing by track ID.
+//! Media clock timestamps are put on the sender's shared clock as packets
+//! come in, through the sender's latest clock report.
 
 use bytes::Bytes;
 use crossbeam_channel::Sender;
 use dashmap::DashMap;
+use std::net::SocketAddr;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::thread::{self, JoinHandle};
+use std::time::Duration;
 
-use crate::error::NetworkError;
-use crate::network::udp::create_socket;
-use crate::protocol::AudioPacket;
-use crate::config::NetworkConfig;
+use crate::error::{NetworkError, PacketError};
+use crate::network::access::{Rejection, SourceFilter};
+use crate::network::control::ControlEndpoint;
+use crate::network::dump::{Direction, PacketDump};
+use crate::network::timestamp;
+use crate::network::udp::{create_socket, BatchReceiver};
+use crate::protocol::{parse_packet, AudioPacket, Packet, Reassembler};
+use crate::config::{NetworkConfig, ReceiveBackend};
+use crate::constants::LOOP_IDLE_MS;
+use crate::sync::media::ticks_to_us;
+use crate::sync::now_us;
 
 /// Received packet ready for decoding
 #[derive(Debug, Clone)]
 pub struct ReceivedPacket {
     pub track_id: u8,
+    /// Simulcast layer (0 = primary encoding)
+    pub layer: u8,
     pub sequence: u32,
+    /// Timestamp as sent: media clock ticks or, from legacy senders, µs
     pub timestamp: u64,
+    /// `timestamp` is on the track's media clock
+    pub media_clock: bool,
+    /// Send time on the sender's shared clock (µs), once known
+    pub sender_us: Option<u64>,
     pub payload: Bytes,
     pub is_stereo: bool,
     pub has_fec: bool,
+    /// Sender entered DTX after this packet
+    pub is_dtx: bool,
     pub receive_time: std::time::Instant,
+    /// Address the packet came from
+    pub source: SocketAddr,
+    /// Arrival on the [`now_us`] clock, like `receive_time`
+    pub arrival_us: u64,
 }
 
-impl From<AudioPacket> for ReceivedPacket {
-    fn from(packet: AudioPacket) -> Self {
+impl ReceivedPacket {
+    /// Create from a parsed packet and its source address
+    pub fn new(packet: AudioPacket, source: SocketAddr) -> Self {
         Self {
             track_id: packet.track_id,
+            layer: packet.layer,
             sequence: packet.sequence,
             timestamp: packet.timestamp,
+            media_clock: packet.flags.has_media_clock(),
+            sender_us: (!packet.flags.has_media_clock()).then_some(packet.timestamp),
             payload: packet.payload,
             is_stereo: packet.flags.is_stereo(),
             has_fec: packet.flags.has_fec(),
+            is_dtx: packet.flags.is_dtx(),
             receive_time: std::time::Instant::now(),
+            source,
+            arrival_us: now_us(),
         }
     }
+    
+    /// Put a media clock timestamp on the sender's shared clock, if the
+    /// sender has reported the track's clock to `control`
+    pub fn with_sender_time(mut self, control: &ControlEndpoint) -> Self {
+        if self.media_clock {
+            self.sender_us = control.media_to_wall(self.source, self.track_id, self.timestamp);
+        }
+        self
+    }
+    
+    /// Position on the track's own time line (µs), for spacing between packets
+    pub fn stream_us(&self) -> u64 {
+        if self.media_clock {
+            ticks_to_us(self.timestamp)
+        } else {
+            self.timestamp
+        }
+    }
+    
+    /// Send time (µs): on the sender's shared clock once known, on the
+    /// track's own time line until then
+    ///
+    /// Media clock references follow the time packets are sent, not
+    /// captured, so this trails capture by the sender's encode and queue time.
+    pub fn send_us(&self) -> u64 {
+        self.sender_us.unwrap_or_else(|| self.stream_us())
+    }
+    
+    /// Move the arrival back by `age`, for a packet the kernel stamped earlier
+    pub fn received_ago(mut self, age: std::time::Duration) -> Self {
+        self.receive_time = self.receive_time.checked_sub(age).unwrap_or(self.receive_time);
+        self.arrival_us = self.arrival_us.saturating_sub(age.as_micros() as u64);
+        self
+    }
 }
 
 /// Callback type for received packets
@@ -57,14 +122,32 @@ pub struct AudioReceiver {
     /// Bytes received counter
     bytes_received: Arc<AtomicU64>,
     
-    /// Invalid packets counter
-    invalid_packets: Arc<AtomicU64>,
+    /// Invalid packets, by rejection cause
+    invalid_packets: Arc<InvalidPacketCounters>,
+    
+    /// Datagrams from sources not on the allowlist
+    rejected_packets: Arc<AtomicU64>,
+    
+    /// Datagrams dropped by the per-source rate limiter
+    rate_limited_packets: Arc<AtomicU64>,
+    
+    /// Allowlist and rate limiter, moved into the thread on start
+    source_filter: Option<SourceFilter>,
     
     /// Per-track packet channels
     track_channels: Arc<DashMap<u8, Sender<ReceivedPacket>>>,
     
     /// Global packet channel (for all tracks)
     global_tx: Option<Sender<ReceivedPacket>>,
+    
+    /// Control channel endpoint (available once started)
+    control: Option<Arc<ControlEndpoint>>,
+    
+    /// Bound socket (available once started)
+    socket: Option<Arc<std::net::UdpSocket>>,
+    
+    /// Packets carry kernel receive timestamps
+    kernel_timestamps: bool,
 }
 
 impl AudioReceiver {
@@ -75,9 +158,15 @@ impl AudioReceiver {
             running: Arc::new(AtomicBool::new(false)),
             packets_received: Arc::new(AtomicU64::new(0)),
             bytes_received: Arc::new(AtomicU64::new(0)),
-            invalid_packets: Arc::new(AtomicU64::new(0)),
+            invalid_packets: Arc::new(InvalidPacketCounters::default()),
+            rejected_packets: Arc::new(AtomicU64::new(0)),
+            rate_limited_packets: Arc::new(AtomicU64::new(0)),
+            source_filter: None,
             track_channels: Arc::new(DashMap::new()),
             global_tx: None,
+            control: None,
+            socket: None,
+            kernel_timestamps: false,
         }
     }
     
@@ -86,6 +175,11 @@ impl AudioReceiver {
         self.global_tx = Some(tx);
     }
     
+    /// Only accept datagrams the filter admits (set before starting)
+    pub fn set_source_filter(&mut self, filter: SourceFilter) {
+        self.source_filter = Some(filter);
+    }
+    
     /// Register a channel for a specific track
     pub fn register_track(&self, track_id: u8, tx: Sender<ReceivedPacket>) {
         self.track_channels.insert(track_id, tx);
@@ -102,58 +196,165 @@ impl AudioReceiver {
             return Ok(());
         }
         
-        let socket = create_socket(&config)?;
+        let socket = Arc::new(create_socket(&config)?);
+        let control = Arc::new(
+            ControlEndpoint::new(socket.clone()).with_key(config.control_key.as_deref())
+        );
+        self.control = Some(control.clone());
+        self.socket = Some(socket.clone());
+        self.kernel_timestamps = config.kernel_timestamps && timestamp::enable(&socket);
+        if config.kernel_timestamps && !self.kernel_timestamps {
+            tracing::info!("Kernel receive timestamps unavailable; stamping packets on read");
+        }
+        
+        // Tracks with a port of their own; control stays on the base port
+        let mut sockets = vec![socket.clone()];
+        let base = socket.local_addr().map_or(0, |addr| addr.port());
+        for track_id in (1..config.track_ports).filter(|_| base != 0) {
+            let port = config.track_port(base, track_id);
+            let extra = create_socket(&NetworkConfig { udp_port: port, port_range: 0, ..config.clone() })?;
+            if self.kernel_timestamps {
+                timestamp::enable(&extra);
+            }
+            sockets.push(Arc::new(extra));
+        }
+        if sockets.len() > 1 {
+            let ports: Vec<u16> = sockets.iter().filter_map(|s| s.local_addr().ok()).map(|a| a.port()).collect();
+            tracing::info!("Tracks 0-{} on UDP ports {:?}", sockets.len() - 1, ports);
+        }
+        
+        let batch = match config.receive_backend {
+            ReceiveBackend::Poll => None,
+            backend => match BatchReceiver::new(backend, &sockets) {
+                Ok(batch) => {
+                    tracing::info!("Receiving through {:?}", backend);
+                    Some(batch)
+                }
+                Err(e) => {
+                    tracing::warn!("{:?} receive backend unavailable, polling instead: {}", backend, e);
+                    for socket in &sockets {
+                        let _ = socket.set_nonblocking(true);
+                    }
+                    None
+                }
+            },
+        };
         
         let running = self.running.clone();
         let packets_received = self.packets_received.clone();
         let bytes_received = self.bytes_received.clone();
         let invalid_packets = self.invalid_packets.clone();
+        let rejected_packets = self.rejected_packets.clone();
+        let rate_limited_packets = self.rate_limited_packets.clone();
+        let mut source_filter = self.source_filter.take();
         let track_channels = self.track_channels.clone();
         let global_tx = self.global_tx.clone();
+        let dump = PacketDump::open_configured(config.packet_dump.as_deref());
         
         running.store(true, Ordering::SeqCst);
         
         let handle = thread::Builder::new()
             .name("audio-receiver".to_string())
             .spawn(move || {
-                let mut recv_buffer = vec![0u8; 2048];
+                let mut reassembler = Reassembler::new();
                 
-                while running.load(Ordering::Relaxed) {
-                    // Try to receive with timeout via non-blocking + sleep
-                    match socket.recv_from(&mut recv_buffer) {
-                        Ok((size, _addr)) => {
-                            bytes_received.fetch_add(size as u64, Ordering::Relaxed);
-                            
-                            // Parse packet
-                            let data = Bytes::copy_from_slice(&recv_buffer[..size]);
-                            if let Some(packet) = AudioPacket::deserialize(data) {
-                                packets_received.fetch_add(1, Ordering::Relaxed);
-                                
-                                let received = ReceivedPacket::from(packet);
-                                let track_id = received.track_id;
-                                
-                                // Send to track-specific channel
-                                if let Some(tx) = track_channels.get(&track_id) {
-                                    let _ = tx.try_send(received.clone());
-                                }
-                                
-                                // Send to global channel
-                                if let Some(ref tx) = global_tx {
-                                    let _ = tx.try_send(received);
-                                }
-                            } else {
-                                invalid_packets.fetch_add(1, Ordering::Relaxed);
+                // Everything done with one datagram, however it was read
+                let mut handle_datagram = |data: &[u8], addr: SocketAddr, age: Duration| {
+                    bytes_received.fetch_add(data.len() as u64, Ordering::Relaxed);
+                    
+                    // Turn away unknown or flooding sources before parsing
+                    if let Some(ref mut filter) = source_filter {
+                        match filter.admit(addr.ip(), std::time::Instant::now()) {
+                            Ok(()) => {}
+                            Err(Rejection::NotAllowed) => {
+                                rejected_packets.fetch_add(1, Ordering::Relaxed);
+                                return;
+                            }
+                            Err(Rejection::RateLimited) => {
+                                rate_limited_packets.fetch_add(1, Ordering::Relaxed);
+                                return;
                             }
                         }
-                        Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {
-                            // No data available, sleep briefly
-                            thread::sleep(std::time::Duration::from_micros(100));
+                    }
+                    
+                    let parsed = parse_packet(data);
+                    if let (Some(dump), Ok(Packet::Audio(_) | Packet::Fragment(_))) = (&dump, &parsed) {
+                        dump.record(Direction::Received, addr, data);
+                    }
+                    let packet = match parsed {
+                        Ok(Packet::Audio(packet)) => packet,
+                        // Joined once the last fragment is in
+                        Ok(Packet::Fragment(fragment)) => match reassembler.push(addr, fragment) {
+                            Some(packet) => packet,
+                            None => return,
+                        },
+                        // Control packets are verified and handled by the endpoint
+                        Ok(Packet::Control(_)) => {
+                            let receive_time = now_us().saturating_sub(age.as_micros() as u64);
+                            if let Err(e) = control.handle_datagram(data, addr, receive_time) {
+                                tracing::debug!("Dropped control message from {}: {}", addr, e);
+                                invalid_packets.record(e);
+                            }
+                            return;
                         }
+                        // STUN replies to this socket's public address query
+                        Err(_) if control.handle_stun(data) => return,
                         Err(e) => {
+                            tracing::trace!("Dropped packet from {}: {}", addr, e);
+                            invalid_packets.record(e);
+                            return;
+                        }
+                    };
+                    
+                    packets_received.fetch_add(1, Ordering::Relaxed);
+                    
+                    let received = ReceivedPacket::new(packet, addr).received_ago(age).with_sender_time(&control);
+                    let track_id = received.track_id;
+                    
+                    // Send to track-specific channel
+                    if let Some(tx) = track_channels.get(&track_id) {
+                        let _ = tx.try_send(received.clone());
+                    }
+                    
+                    // Send to global channel
+                    if let Some(ref tx) = global_tx {
+                        let _ = tx.try_send(received);
+                    }
+                };
+                
+                if let Some(mut batch) = batch {
+                    while running.load(Ordering::Relaxed) {
+                        let read = batch.receive(Duration::from_millis(LOOP_IDLE_MS), |datagram| {
+                            handle_datagram(datagram.data, datagram.from, datagram.age.unwrap_or_default())
+                        });
+                        if let Err(e) = read {
                             tracing::warn!("Receive error: {}", e);
-                            thread::sleep(std::time::Duration::from_millis(1));
+                            thread::sleep(Duration::from_millis(1));
+                        }
+                    }
+                    return;
+                }
+                
+                let mut recv_buffer = vec![0u8; 2048];
+                while running.load(Ordering::Relaxed) {
+                    // Poll every socket; sleep only when all are idle
+                    let mut idle = true;
+                    for socket in &sockets {
+                        match timestamp::recv_from(socket, &mut recv_buffer) {
+                            Ok((size, addr, age)) => {
+                                idle = false;
+                                handle_datagram(&recv_buffer[..size], addr, age.unwrap_or_default());
+                            }
+                            Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {}
+                            Err(e) => {
+                                tracing::warn!("Receive error: {}", e);
+                                thread::sleep(Duration::from_millis(1));
+                            }
                         }
                     }
+                    if idle {
+                        thread::sleep(Duration::from_micros(100));
+                    }
                 }
             })
             .map_err(|e| NetworkError::ReceiveFailed(e.to_string()))?;
@@ -176,6 +377,16 @@ impl AudioReceiver {
         self.running.load(Ordering::SeqCst)
     }
     
+    /// Get the control channel endpoint (None until started)
+    pub fn control(&self) -> Option<Arc<ControlEndpoint>> {
+        self.control.clone()
+    }
+    
+    /// Get the bound socket, to send from the same port (None until started)
+    pub fn socket(&self) -> Option<Arc<std::net::UdpSocket>> {
+        self.socket.clone()
+    }
+    
     /// Get packets received count
     pub fn packets_received(&self) -> u64 {
         self.packets_received.load(Ordering::Relaxed)
@@ -188,7 +399,22 @@ impl AudioReceiver {
     
     /// Get invalid packets count
     pub fn invalid_packets(&self) -> u64 {
-        self.invalid_packets.load(Ordering::Relaxed)
+        self.invalid_packets.snapshot().total()
+    }
+    
+    /// Get invalid packets broken down by cause
+    pub fn invalid_packet_stats(&self) -> InvalidPacketStats {
+        self.invalid_packets.snapshot()
+    }
+    
+    /// Get count of datagrams from sources not on the allowlist
+    pub fn rejected_packets(&self) -> u64 {
+        self.rejected_packets.load(Ordering::Relaxed)
+    }
+    
+    /// Get count of datagrams dropped by the rate limiter
+    pub fn rate_limited_packets(&self) -> u64 {
+        self.rate_limited_packets.load(Ordering::Relaxed)
     }
     
     /// Get statistics
@@ -197,7 +423,11 @@ impl AudioReceiver {
             packets_received: self.packets_received(),
             bytes_received: self.bytes_received(),
             invalid_packets: self.invalid_packets(),
+            invalid: self.invalid_packet_stats(),
+            rejected_packets: self.rejected_packets(),
+            rate_limited_packets: self.rate_limited_packets(),
             registered_tracks: self.track_channels.len(),
+            kernel_timestamps: self.kernel_timestamps,
         }
     }
 }
@@ -220,7 +450,75 @@ pub struct ReceiverStats {
     pub packets_received: u64,
     pub bytes_received: u64,
     pub invalid_packets: u64,
+    pub invalid: InvalidPacketStats,
+    pub rejected_packets: u64,
+    pub rate_limited_packets: u64,
     pub registered_tracks: usize,
+    pub kernel_timestamps: bool,
+}
+
+/// Invalid packet counters, one per [`PacketError`] category
+#[derive(Debug, Default)]
+struct InvalidPacketCounters {
+    truncated: AtomicU64,
+    bad_magic: AtomicU64,
+    too_large: AtomicU64,
+    checksum: AtomicU64,
+    bad_control: AtomicU64,
+    bad_signature: AtomicU64,
+    bad_fragment: AtomicU64,
+}
+
+impl InvalidPacketCounters {
+    fn record(&self, error: PacketError) {
+        let counter = match error {
+            PacketError::Truncated(_) => &self.truncated,
+            PacketError::BadMagic(_) => &self.bad_magic,
+            PacketError::PayloadTooLarge(_) => &self.too_large,
+            PacketError::ChecksumMismatch => &self.checksum,
+            PacketError::BadControl => &self.bad_control,
+            PacketError::BadSignature => &self.bad_signature,
+            PacketError::BadFragment => &self.bad_fragment,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+    
+    fn snapshot(&self) -> InvalidPacketStats {
+        InvalidPacketStats {
+            truncated: self.truncated.load(Ordering::Relaxed),
+            bad_magic: self.bad_magic.load(Ordering::Relaxed),
+            too_large: self.too_large.load(Ordering::Relaxed),
+            checksum: self.checksum.load(Ordering::Relaxed),
+            bad_control: self.bad_control.load(Ordering::Relaxed),
+            bad_signature: self.bad_signature.load(Ordering::Relaxed),
+            bad_fragment: self.bad_fragment.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Invalid packets by rejection cause
+#[derive(Debug, Clone, Copy, Default)]
+pub struct InvalidPacketStats {
+    pub truncated: u64,
+    pub bad_magic: u64,
+    pub too_large: u64,
+    pub checksum: u64,
+    pub bad_control: u64,
+    pub bad_signature: u64,
+    pub bad_fragment: u64,
+}
+
+impl InvalidPacketStats {
+    /// Total invalid packets
+    pub fn total(&self) -> u64 {
+        self.truncated
+            + self.bad_magic
+            + self.too_large
+            + self.checksum
+            + self.bad_control
+            + self.bad_signature
+            + self.bad_fragment
+    }
 }
 
 /// Per-track receiver that processes packets for a single track
@@ -0,0 +1,176 @@
+//! Relay and mixer node
+//!
+//! Takes streams from several senders and passes them on to one or more
+//! receivers, as a central node in a multi-room setup or a WAN relay with a
+//! public address. Each sender's track gets a relay track ID of its own (the
+//! sender's ID when free), so senders that all use track 0 do not collide.
+//! Forwarded tracks go out untouched; tracks listed in `relay.mix` are
+//! decoded, summed and re-encoded into one track.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+
+use crate::audio::buffer::AudioFrame;
+use crate::audio::mixer::Mixer;
+use crate::config::{OpusConfig, RelayConfig};
+use crate::constants::{DEFAULT_CHANNELS, DEFAULT_FRAME_SIZE_MS, DEFAULT_SAMPLE_RATE, TALKBACK_TRACK_ID};
+use crate::error::CodecError;
+use crate::network::receiver::ReceivedPacket;
+use crate::network::stream::{Decoders, Encoders};
+use crate::protocol::{AudioPacket, PacketFlags};
+use crate::sync::now_us;
+
+/// Routing, mixing and re-encoding state of a relay
+pub struct Relay {
+    config: RelayConfig,
+    /// Relay track ID per sender track
+    routes: HashMap<(SocketAddr, u8), u8>,
+    decoders: Decoders,
+    mixer: Option<Mixer>,
+    encoders: Encoders,
+    mix_sequence: u32,
+}
+
+impl Relay {
+    pub fn new(config: RelayConfig) -> Self {
+        let frame_size = (DEFAULT_SAMPLE_RATE as f32 * DEFAULT_FRAME_SIZE_MS / 1000.0) as usize;
+        let mixer = (!config.mix.is_empty()).then(|| Mixer::new(DEFAULT_CHANNELS, frame_size, &config.mix));
+        let encoders = Encoders::new(OpusConfig { bitrate: config.mix_bitrate, ..OpusConfig::music() });
+        Self {
+            config,
+            routes: HashMap::new(),
+            decoders: Decoders::default(),
+            mixer,
+            encoders,
+            mix_sequence: 0,
+        }
+    }
+
+    /// Relay track ID of a sender's track, assigned on first sight
+    ///
+    /// Returns the ID and whether it is new, or None once all IDs are taken.
+    pub fn route(&mut self, source: SocketAddr, track_id: u8) -> Option<(u8, bool)> {
+        if let Some(&id) = self.routes.get(&(source, track_id)) {
+            return Some((id, false));
+        }
+        let reserved = |id: u8| id == self.config.mix_track_id || id == TALKBACK_TRACK_ID;
+        let taken = |id: u8| reserved(id) || self.routes.values().any(|&used| used == id);
+        let id = std::iter::once(track_id)
+            .chain(0..=u8::MAX)
+            .find(|&id| !taken(id))?;
+        self.routes.insert((source, track_id), id);
+        Some((id, true))
+    }
+
+    /// Relay track ID of a sender's track, if it has one
+    pub fn lookup(&self, source: SocketAddr, track_id: u8) -> Option<u8> {
+        self.routes.get(&(source, track_id)).copied()
+    }
+
+    /// Relay track IDs of all tracks from `source`
+    pub fn tracks_from(&self, source: SocketAddr) -> Vec<u8> {
+        self.routes.iter().filter(|((from, _), _)| *from == source).map(|(_, &id)| id).collect()
+    }
+
+    /// Whether relay track `id` is forwarded as-is
+    pub fn forwards(&self, id: u8) -> bool {
+        if self.config.forward.is_empty() {
+            !self.config.mix.contains(&id)
+        } else {
+            self.config.forward.contains(&id)
+        }
+    }
+
+    /// Packets to send on for one received packet of relay track `id`
+    ///
+    /// `gain` (linear) and `pan` apply to the track's share of the mix.
+    /// Only primary encodings are relayed; simulcast layers are dropped.
+    pub fn process(&mut self, id: u8, packet: &ReceivedPacket, gain: f32, pan: f32) -> Result<Vec<AudioPacket>, CodecError> {
+        let mut out = Vec::new();
+        if packet.layer != 0 {
+            return Ok(out);
+        }
+        if self.forwards(id) {
+            out.push(AudioPacket {
+                track_id: id,
+                layer: 0,
+                flags: PacketFlags::new()
+                    .set_stereo(packet.is_stereo)
+                    .set_fec(packet.has_fec)
+                    .set_dtx(packet.is_dtx),
+                sequence: packet.sequence,
+                timestamp: packet.send_us(),
+                payload: packet.payload.clone(),
+            });
+        }
+
+        let Some(ref mut mixer) = self.mixer else {
+            return Ok(out);
+        };
+        if !mixer.includes(id) {
+            return Ok(out);
+        }
+        let frame = self.decoders.decode(&ReceivedPacket { track_id: id, ..packet.clone() })?;
+        mixer.push(id, &frame, gain, pan);
+        while let Some(samples) = mixer.mix() {
+            let frame = AudioFrame::new(samples, mixer.channels(), now_us(), self.mix_sequence);
+            for payload in self.encoders.push(self.config.mix_track_id, &frame)? {
+                out.push(AudioPacket {
+                    track_id: self.config.mix_track_id,
+                    layer: 0,
+                    flags: PacketFlags::new().set_stereo(frame.channels == 2),
+                    sequence: self.mix_sequence,
+                    timestamp: now_us(),
+                    payload,
+                });
+                self.mix_sequence = self.mix_sequence.wrapping_add(1);
+            }
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::OpusEncoder;
+
+    #[test]
+    fn test_route_forward_and_mix() {
+        let config = RelayConfig { mix: vec![1], ..Default::default() };
+        let mut relay = Relay::new(config);
+        let a: SocketAddr = "10.0.0.1:5000".parse().unwrap();
+        let b: SocketAddr = "10.0.0.2:5000".parse().unwrap();
+
+        // Both senders use track 0; the second gets the next free ID
+        assert_eq!(relay.route(a, 0), Some((0, true)));
+        assert_eq!(relay.route(b, 0), Some((1, true)));
+        assert_eq!(relay.route(a, 0), Some((0, false)));
+        assert_eq!(relay.tracks_from(b), vec![1]);
+
+        let mut encoder = OpusEncoder::music(DEFAULT_SAMPLE_RATE, 2).unwrap();
+        let payload = encoder.encode(&vec![0.1; 960]).unwrap();
+        let packet = |source| ReceivedPacket::new(
+            AudioPacket {
+                track_id: 0,
+                layer: 0,
+                flags: PacketFlags::new().set_stereo(true),
+                sequence: 7,
+                timestamp: 100,
+                payload: payload.clone(),
+            },
+            source,
+        );
+
+        // Track 0 is forwarded untouched
+        let out = relay.process(0, &packet(a), 1.0, 0.0).unwrap();
+        assert_eq!(out.len(), 1);
+        assert_eq!((out[0].track_id, out[0].sequence, out[0].timestamp), (0, 7, 100));
+
+        // Track 1 only feeds the re-encoded mix
+        let out = relay.process(1, &packet(b), 1.0, 0.0).unwrap();
+        assert_eq!(out.len(), 1);
+        assert_eq!(out[0].track_id, RelayConfig::default().mix_track_id);
+        assert!(out[0].flags.is_stereo());
+    }
+}
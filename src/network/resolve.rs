@@ -0,0 +1,171 @@
+//! Hostname targets
+//!
+//! A target may be a literal address or a `host:port` name such as
+//! `streampc.local:5000`. Names are resolved at start and then again in the
+//! background every [`DNS_REFRESH_INTERVAL_MS`], so a receiver that gets a new
+//! DHCP lease is followed without a restart. A name that does not resolve
+//! yet (the receiver PC still booting, DNS not up) is retried every
+//! [`RETRY_INTERVAL`] instead of failing startup. Sockets are IPv4, so IPv4
+//! addresses are preferred when a name has both.
+
+use parking_lot::Mutex;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::constants::DNS_REFRESH_INTERVAL_MS;
+use crate::error::NetworkError;
+use crate::shutdown::ShutdownCoordinator;
+
+/// How often a name that has never resolved is tried again
+pub const RETRY_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Resolve `host:port` (or a literal address) once
+pub fn resolve(target: &str) -> Result<SocketAddr, NetworkError> {
+    let addrs: Vec<SocketAddr> = target
+        .to_socket_addrs()
+        .map_err(|e| NetworkError::ResolveFailed(format!("{}: {}", target, e)))?
+        .collect();
+    addrs
+        .iter()
+        .find(|addr| addr.is_ipv4())
+        .or_else(|| addrs.first())
+        .copied()
+        .ok_or_else(|| NetworkError::ResolveFailed(format!("{}: no addresses", target)))
+}
+
+/// A target that keeps following its hostname
+pub struct Target {
+    name: String,
+    /// None until the name first resolves
+    addr: Option<SocketAddr>,
+    /// Latest resolution from the background thread
+    latest: Arc<Mutex<Option<SocketAddr>>>,
+    running: Arc<AtomicBool>,
+}
+
+impl Target {
+    /// Resolve `target` now if it can be; hostnames are re-resolved every
+    /// `interval`, and every [`RETRY_INTERVAL`] until they first resolve
+    ///
+    /// Only a target that is not `host:port` at all is an error.
+    pub fn new(target: &str, interval: Duration) -> Result<Self, NetworkError> {
+        let literal = target.parse::<SocketAddr>().ok();
+        let well_formed = target
+            .rsplit_once(':')
+            .is_some_and(|(host, port)| !host.is_empty() && port.parse::<u16>().is_ok());
+        if literal.is_none() && !well_formed {
+            return Err(NetworkError::ResolveFailed(format!("{}: expected host:port", target)));
+        }
+        let addr = literal.or_else(|| match resolve(target) {
+            Ok(addr) => Some(addr),
+            Err(e) => {
+                tracing::warn!("{}; retrying every {:?}", e, RETRY_INTERVAL);
+                None
+            }
+        });
+        let latest = Arc::new(Mutex::new(addr));
+        let running = Arc::new(AtomicBool::new(true));
+        if literal.is_none() {
+            let (name, latest, running) = (target.to_string(), latest.clone(), running.clone());
+            thread::Builder::new()
+                .name("dns-resolve".to_string())
+                .spawn(move || refresh_loop(&name, interval, &latest, &running))
+                .map_err(|e| NetworkError::ResolveFailed(e.to_string()))?;
+        }
+        Ok(Self { name: target.to_string(), addr, latest, running })
+    }
+
+    /// Resolve `target` now and re-resolve it every [`DNS_REFRESH_INTERVAL_MS`]
+    pub fn spawn(target: &str) -> Result<Self, NetworkError> {
+        Self::new(target, Duration::from_millis(DNS_REFRESH_INTERVAL_MS))
+    }
+
+    /// Current address, once the name has resolved
+    pub fn addr(&self) -> Option<SocketAddr> {
+        self.addr
+    }
+
+    /// The new address, once the name first resolves or resolves somewhere else
+    pub fn changed(&mut self) -> Option<SocketAddr> {
+        let latest = *self.latest.lock();
+        if latest.is_none() || latest == self.addr {
+            return None;
+        }
+        self.addr = latest;
+        latest
+    }
+
+    /// Wait until the name has resolved (None if `shutdown` comes first)
+    pub async fn resolved(&mut self, shutdown: &ShutdownCoordinator) -> Option<SocketAddr> {
+        if self.addr.is_none() {
+            tracing::info!("Waiting for {} to resolve", self.name);
+        }
+        loop {
+            self.changed();
+            if self.addr.is_some() || shutdown.is_triggered() {
+                return self.addr;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    }
+}
+
+impl Drop for Target {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+    }
+}
+
+/// Re-resolve `name` every `interval` (every [`RETRY_INTERVAL`] while it
+/// has no address yet); failures keep the last address
+fn refresh_loop(name: &str, interval: Duration, latest: &Mutex<Option<SocketAddr>>, running: &AtomicBool) {
+    let mut last = Instant::now();
+    while running.load(Ordering::Relaxed) {
+        thread::sleep(Duration::from_millis(100).min(interval));
+        let due = if latest.lock().is_some() { interval } else { RETRY_INTERVAL.min(interval) };
+        if last.elapsed() < due {
+            continue;
+        }
+        last = Instant::now();
+        match resolve(name) {
+            Ok(addr) => {
+                let mut current = latest.lock();
+                match *current {
+                    Some(was) if was != addr => tracing::info!("{} now resolves to {} (was {})", name, addr, was),
+                    None => tracing::info!("{} resolves to {}", name, addr),
+                    _ => {}
+                }
+                *current = Some(addr);
+            }
+            Err(e) => tracing::debug!("Re-resolving failed: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_targets() {
+        assert_eq!(resolve("127.0.0.1:5000").unwrap(), "127.0.0.1:5000".parse().unwrap());
+        let local = resolve("localhost:5001").unwrap();
+        assert!(local.ip().is_loopback() && local.port() == 5001);
+        assert!(resolve("localhost").is_err());
+
+        // A name keeps resolving to the same place: no change reported
+        let mut target = Target::new("localhost:5001", Duration::from_millis(10)).unwrap();
+        thread::sleep(Duration::from_millis(250));
+        assert_eq!(target.changed(), None);
+        assert_eq!(target.addr(), Some(local));
+
+        // A name that doesn't resolve yet is not an error, a missing port is
+        let mut pending = Target::new("no-such-host.invalid:5000", Duration::from_millis(10)).unwrap();
+        assert_eq!(pending.addr(), None);
+        assert_eq!(pending.changed(), None);
+        assert!(Target::new("localhost", Duration::from_millis(10)).is_err());
+    }
+}
@@ -2,26 +2,105 @@
 //!
 //! Handles sending encoded audio packets over UDP with proper
 //! sequencing and timing.
+//!
+//! Each track keeps a media clock ([`TrackClock`]) that packets are
+//! stamped from and reported to receivers that speak it; legacy receivers
+//! get the send time on the shared clock instead.
+//!
+//! Tracks may also send simulcast layers, other encodings of the same
+//! audio; each goes out only to a receiver that subscribed to it.
 
-use bytes::Bytes;
 use crossbeam_channel::Receiver;
-use std::net::SocketAddr;
+use dashmap::DashMap;
+use std::net::{IpAddr, SocketAddr, UdpSocket as StdUdpSocket};
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 
 use crate::error::NetworkError;
-use crate::network::udp::{create_socket, PacketSender};
-use crate::protocol::{AudioPacket, PacketFlags};
+use crate::network::control::{ControlEndpoint, InboundControl};
+use crate::network::dump::{Direction, PacketDump};
+use crate::network::receiver::ReceivedPacket;
+use crate::network::timestamp;
+use crate::network::udp::{create_redundant_socket, create_socket, PacketSender};
+use crate::constants::MTU_PROBE_INTERVAL_MS;
+use crate::protocol::{
+    parse_packet, AudioPacket, Packet, PacketBuffer, PacketFlags, PeerMessage, ProtocolFeatures, Reassembler,
+    TrackPriority, CRC_SIZE, HEADER_SIZE, LAYER_SIZE, MAX_PAYLOAD_SIZE,
+};
 use crate::config::NetworkConfig;
+use crate::sync::{now_us, TrackClock};
+use crate::tracks::latency::StageTotals;
 
 /// Encoded packet ready for sending
 pub struct EncodedPacket {
     pub track_id: u8,
+    /// Simulcast layer (0 = primary encoding)
+    pub layer: u8,
     pub sequence: u32,
+    /// Media clock timestamp (ticks)
     pub timestamp: u64,
-    pub payload: Bytes,
+    /// Send time on the shared clock (µs), the timestamp for legacy receivers
+    pub wall_us: u64,
+    /// Encoded audio, with room for the header in front
+    pub payload: PacketBuffer,
     pub flags: PacketFlags,
+    /// Queue the packet goes through
+    pub priority: TrackPriority,
+    /// When the packet was queued, for send latency
+    pub queued_at: Instant,
+}
+
+/// Take the next packet, high-priority queue first
+/// 
+/// Waits up to `timeout` when both queues are empty. Errors once a queue
+/// is disconnected.
+fn next_packet(
+    queues: &[Receiver<EncodedPacket>; 2],
+    timeout: Duration,
+) -> Result<Option<EncodedPacket>, ()> {
+    let [high, normal] = queues;
+    if let Ok(packet) = high.try_recv() {
+        return Ok(Some(packet));
+    }
+    if let Ok(packet) = normal.try_recv() {
+        return Ok(Some(packet));
+    }
+    if timeout.is_zero() {
+        return Ok(None);
+    }
+    
+    crossbeam_channel::select! {
+        recv(high) -> packet => packet.map(Some).map_err(|_| ()),
+        recv(normal) -> packet => packet.map(Some).map_err(|_| ()),
+        default(timeout) => Ok(None),
+    }
+}
+
+/// Second link every datagram is duplicated over
+struct RedundantLink {
+    socket: StdUdpSocket,
+    /// Receiver address on this link
+    target: SocketAddr,
+}
+
+impl RedundantLink {
+    /// Open the link `redundant_bind` asks for, towards `redundant_target`
+    /// or else `target`
+    fn open(config: &NetworkConfig, target: SocketAddr) -> Result<Option<Self>, NetworkError> {
+        let Some(socket) = create_redundant_socket(config)? else {
+            return Ok(None);
+        };
+        let target = match config.redundant_target.as_deref() {
+            None => target,
+            Some(addr) => addr
+                .parse::<SocketAddr>()
+                .or_else(|_| addr.parse::<IpAddr>().map(|ip| SocketAddr::new(ip, target.port())))
+                .map_err(|_| NetworkError::ResolveFailed(addr.to_string()))?,
+        };
+        Ok(Some(Self { socket, target }))
+    }
 }
 
 /// Audio sender for multiple tracks
@@ -38,11 +117,26 @@ pub struct AudioSender {
     /// Bytes sent counter
     bytes_sent: Arc<AtomicU64>,
     
+    /// Queue-to-socket time per track since last taken
+    queue_delay: Arc<DashMap<u8, StageTotals>>,
+    
     /// Input channel for packets
     packet_tx: crossbeam_channel::Sender<EncodedPacket>,
     
+    /// Input channel for high-priority packets
+    priority_tx: crossbeam_channel::Sender<EncodedPacket>,
+    
     /// Target address
     target_addr: SocketAddr,
+    
+    /// Control channel endpoint (available once started)
+    control: Option<Arc<ControlEndpoint>>,
+    
+    /// Control reader thread handle
+    control_handle: Option<JoinHandle<()>>,
+    
+    /// Talkback audio from the target, if wanted
+    talkback_tx: Option<crossbeam_channel::Sender<ReceivedPacket>>,
 }
 
 impl AudioSender {
@@ -54,6 +148,7 @@ impl AudioSender {
         let _socket = create_socket(config)?;
         
         let (packet_tx, _packet_rx) = crossbeam_channel::bounded::<EncodedPacket>(1024);
+        let (priority_tx, _priority_rx) = crossbeam_channel::bounded::<EncodedPacket>(1024);
         
         let running = Arc::new(AtomicBool::new(false));
         let packets_sent = Arc::new(AtomicU64::new(0));
@@ -64,11 +159,21 @@ impl AudioSender {
             running,
             packets_sent,
             bytes_sent,
+            queue_delay: Arc::new(DashMap::new()),
             packet_tx,
+            priority_tx,
             target_addr,
+            control: None,
+            control_handle: None,
+            talkback_tx: None,
         })
     }
     
+    /// Forward audio packets from the target (talkback) to `tx` (set before starting)
+    pub fn set_talkback_channel(&mut self, tx: crossbeam_channel::Sender<ReceivedPacket>) {
+        self.talkback_tx = Some(tx);
+    }
+    
     /// Start the sender thread
     pub fn start(&mut self, config: NetworkConfig) -> Result<(), NetworkError> {
         if self.running.load(Ordering::SeqCst) {
@@ -76,21 +181,88 @@ impl AudioSender {
         }
         
         let socket = create_socket(&config)?;
+        let control_socket = Arc::new(
+            socket.try_clone().map_err(|e| NetworkError::BindFailed(e.to_string()))?
+        );
         let sender = PacketSender::new(socket, self.target_addr);
+        let control = Arc::new(
+            ControlEndpoint::new(control_socket.clone()).with_key(config.control_key.as_deref())
+        );
+        self.control = Some(control.clone());
+        if config.kernel_timestamps {
+            timestamp::enable(&control_socket);
+        }
+        self.spawn_sender(sender, control.clone(), config)?;
+        
+        // Control replies and talkback arrive on the same socket
+        let running = self.running.clone();
+        let talkback = self.talkback_tx.clone().map(|tx| (self.target_addr, tx));
+        let control_handle = thread::Builder::new()
+            .name("sender-control".to_string())
+            .spawn(move || {
+                Self::control_loop(control_socket, control, talkback, running);
+            })
+            .map_err(|e| NetworkError::SendFailed(e.to_string()))?;
+        
+        self.control_handle = Some(control_handle);
+        Ok(())
+    }
+    
+    /// Start sending from a socket another component already reads
+    /// 
+    /// Whoever reads `socket` must hand control datagrams to `control`, as
+    /// [`AudioReceiver`](crate::network::receiver::AudioReceiver) does.
+    pub fn start_shared(
+        &mut self,
+        config: NetworkConfig,
+        socket: &std::net::UdpSocket,
+        control: Arc<ControlEndpoint>,
+    ) -> Result<(), NetworkError> {
+        if self.running.load(Ordering::SeqCst) {
+            return Ok(());
+        }
         
+        let socket = socket.try_clone().map_err(|e| NetworkError::BindFailed(e.to_string()))?;
+        self.control = Some(control.clone());
+        self.spawn_sender(PacketSender::new(socket, self.target_addr), control, config)
+    }
+    
+    /// Spawn the sender thread with fresh queues
+    fn spawn_sender(
+        &mut self,
+        sender: PacketSender,
+        control: Arc<ControlEndpoint>,
+        config: NetworkConfig,
+    ) -> Result<(), NetworkError> {
         let (packet_tx, packet_rx) = crossbeam_channel::bounded::<EncodedPacket>(1024);
+        let (priority_tx, priority_rx) = crossbeam_channel::bounded::<EncodedPacket>(1024);
         self.packet_tx = packet_tx;
-        
+        self.priority_tx = priority_tx;
         let running = self.running.clone();
         let packets_sent = self.packets_sent.clone();
         let bytes_sent = self.bytes_sent.clone();
+        let queue_delay = self.queue_delay.clone();
+        let redundant = RedundantLink::open(&config, self.target_addr)?;
+        if let Some(ref link) = redundant {
+            tracing::info!("Duplicating packets from {:?} to {}", link.socket.local_addr().ok(), link.target);
+        }
         
         running.store(true, Ordering::SeqCst);
         
         let handle = thread::Builder::new()
             .name("audio-sender".to_string())
             .spawn(move || {
-                Self::sender_loop(sender, packet_rx, running, packets_sent, bytes_sent);
+                Self::sender_loop(
+                    sender,
+                    redundant,
+                    control,
+                    [priority_rx, packet_rx],
+                    running,
+                    packets_sent,
+                    bytes_sent,
+                    queue_delay,
+                    &config,
+                );
             })
             .map_err(|e| NetworkError::SendFailed(e.to_string()))?;
         
@@ -98,48 +270,204 @@ impl AudioSender {
         Ok(())
     }
     
+    /// Control reader loop
+    /// 
+    /// Audio packets from the target's IP go to the talkback channel, if any.
+    fn control_loop(
+        socket: Arc<std::net::UdpSocket>,
+        control: Arc<ControlEndpoint>,
+        talkback: Option<(SocketAddr, crossbeam_channel::Sender<ReceivedPacket>)>,
+        running: Arc<AtomicBool>,
+    ) {
+        let mut recv_buffer = vec![0u8; 2048];
+        let mut reassembler = Reassembler::new();
+        
+        while running.load(Ordering::Relaxed) {
+            match timestamp::recv_from(&socket, &mut recv_buffer) {
+                Ok((size, addr, age)) => {
+                    let age = age.unwrap_or_default();
+                    let data = &recv_buffer[..size];
+                    
+                    // Audio here is talkback; fragments are joined first
+                    let audio = match parse_packet(data) {
+                        Ok(Packet::Audio(packet)) => Some(packet),
+                        Ok(Packet::Fragment(fragment)) => match reassembler.push(addr, fragment) {
+                            Some(packet) => Some(packet),
+                            None => continue,
+                        },
+                        _ => None,
+                    };
+                    if let Some(packet) = audio {
+                        if let Some((_, tx)) = talkback.as_ref().filter(|(target, _)| addr.ip() == target.ip()) {
+                            let _ = tx.try_send(ReceivedPacket::new(packet, addr).received_ago(age).with_sender_time(&control));
+                        }
+                        continue;
+                    }
+                    if control.handle_stun(data) {
+                        continue;
+                    }
+                    let receive_time = now_us().saturating_sub(age.as_micros() as u64);
+                    if let Err(e) = control.handle_datagram(data, addr, receive_time) {
+                        tracing::debug!("Dropped control message from {}: {}", addr, e);
+                    }
+                }
+                Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                    thread::sleep(std::time::Duration::from_millis(1));
+                }
+                Err(e) => {
+                    tracing::debug!("Control receive error: {}", e);
+                    thread::sleep(std::time::Duration::from_millis(10));
+                }
+            }
+        }
+    }
+    
     /// Sender loop
+    /// 
+    /// High-priority packets always go out first. With pacing enabled,
+    /// consecutive packets are spaced at least `config.pacing_us` apart.
+    /// With `config.payload_crc`, every packet carries a payload checksum.
+    /// Packets larger than the probed path MTU go out as fragments, and
+    /// either is left out for a receiver that doesn't speak it; and
+    /// tracks below `config.track_ports` go to their own port. With
+    /// `config.packet_dump`, every datagram sent is dumped. With a
+    /// redundant link, every datagram also goes out over it.
+    #[allow(clippy::too_many_arguments)]
     fn sender_loop(
         sender: PacketSender,
-        packet_rx: Receiver<EncodedPacket>,
+        redundant: Option<RedundantLink>,
+        control: Arc<ControlEndpoint>,
+        queues: [Receiver<EncodedPacket>; 2],
         running: Arc<AtomicBool>,
         packets_sent: Arc<AtomicU64>,
         bytes_sent: Arc<AtomicU64>,
+        queue_delay: Arc<DashMap<u8, StageTotals>>,
+        config: &NetworkConfig,
     ) {
-        while running.load(Ordering::Relaxed) {
-            // Try to receive packet with timeout
-            match packet_rx.recv_timeout(std::time::Duration::from_millis(10)) {
-                Ok(encoded) => {
-                    // Create audio packet
-                    let packet = AudioPacket {
-                        track_id: encoded.track_id,
-                        flags: encoded.flags,
-                        sequence: encoded.sequence,
-                        timestamp: encoded.timestamp,
-                        payload: encoded.payload,
-                    };
-                    
-                    // Serialize and send
-                    let data = packet.serialize();
-                    match sender.send(&data) {
-                        Ok(sent) => {
-                            packets_sent.fetch_add(1, Ordering::Relaxed);
-                            bytes_sent.fetch_add(sent as u64, Ordering::Relaxed);
-                        }
-                        Err(e) => {
-                            tracing::warn!("Failed to send packet: {}", e);
+        let target = sender.target();
+        let ports: Vec<SocketAddr> = (0..config.track_ports)
+            .map(|track_id| SocketAddr::new(target.ip(), config.track_port(target.port(), track_id)))
+            .collect();
+        let redundant_ports: Vec<SocketAddr> = redundant.as_ref().map_or_else(Vec::new, |link| {
+            (0..=config.track_ports)
+                .map(|track_id| SocketAddr::new(link.target.ip(), config.track_port(link.target.port(), track_id)))
+                .collect()
+        });
+        // Ties the links together at the receiver; unguessable, unlike a timestamp
+        let session = uuid::Uuid::new_v4().as_u64_pair().0;
+        let pacing_us = config.pacing_us;
+        let payload_crc = config.payload_crc;
+        let pacing = Duration::from_micros(pacing_us);
+        let mut last_send = Instant::now();
+        let dump = PacketDump::open_configured(config.packet_dump.as_deref());
+        
+        let mut send = |encoded: EncodedPacket| {
+            if pacing_us > 0 {
+                let elapsed = last_send.elapsed();
+                if elapsed < pacing {
+                    thread::sleep(pacing - elapsed);
+                }
+                last_send = Instant::now();
+            }
+            queue_delay.entry(encoded.track_id).or_default().record(encoded.queued_at.elapsed());
+            
+            // Features the receiver doesn't speak are left out
+            let features = control.negotiated(target).features;
+            if encoded.layer != 0 && !features.contains(ProtocolFeatures::SIMULCAST) {
+                return;
+            }
+            
+            let track_id = encoded.track_id;
+            let media_clock = features.contains(ProtocolFeatures::MEDIA_CLOCK);
+            let flags = encoded.flags
+                .set_crc(payload_crc && features.contains(ProtocolFeatures::PAYLOAD_CRC))
+                .set_media_clock(media_clock);
+            let timestamp = if media_clock { encoded.timestamp } else { encoded.wall_us };
+            
+            // The header goes in front of the payload in place when the packet
+            // fits the path; otherwise it is split into fragments
+            let max_datagram = if features.contains(ProtocolFeatures::FRAGMENTS) { control.path_mtu(target) } else { usize::MAX };
+            let crc = if flags.has_crc() { CRC_SIZE } else { 0 };
+            let layer = if encoded.layer != 0 { LAYER_SIZE } else { 0 };
+            let size = encoded.payload.len();
+            let datagrams = if size <= MAX_PAYLOAD_SIZE && HEADER_SIZE + layer + crc + size <= max_datagram {
+                vec![encoded.payload.finish(track_id, encoded.layer, flags, encoded.sequence, timestamp)]
+            } else {
+                let packet = AudioPacket {
+                    track_id,
+                    layer: encoded.layer,
+                    flags,
+                    sequence: encoded.sequence,
+                    timestamp,
+                    payload: encoded.payload.into_payload(),
+                };
+                match packet.serialize_fragments(max_datagram) {
+                    Ok(datagrams) => datagrams,
+                    Err(e) => {
+                        tracing::warn!("Dropped packet on track {}: {}", track_id, e);
+                        return;
+                    }
+                }
+            };
+            let to = ports.get(track_id as usize).copied().unwrap_or(target);
+            let redundant_to = redundant_ports
+                .get(track_id as usize)
+                .or(redundant_ports.last())
+                .copied();
+            for data in datagrams {
+                if let (Some(link), Some(redundant_to)) = (redundant.as_ref(), redundant_to) {
+                    if let Err(e) = link.socket.send_to(&data, redundant_to) {
+                        tracing::debug!("Failed to send packet on the redundant link: {}", e);
+                    }
+                }
+                match sender.send_to(&data, to) {
+                    Ok(sent) => {
+                        packets_sent.fetch_add(1, Ordering::Relaxed);
+                        bytes_sent.fetch_add(sent as u64, Ordering::Relaxed);
+                        if let Some(ref dump) = dump {
+                            dump.record(Direction::Sent, to, &data);
                         }
                     }
+                    Err(e) => {
+                        tracing::warn!("Failed to send packet: {}", e);
+                    }
+                }
+            }
+        };
+        
+        let mut last_probe: Option<Instant> = None;
+        while running.load(Ordering::Relaxed) {
+            if last_probe.is_none_or(|t| t.elapsed() >= Duration::from_millis(MTU_PROBE_INTERVAL_MS)) {
+                control.probe_mtu(target);
+                control.send_hello(target);
+                if let Some(ref link) = redundant {
+                    if let Err(e) = control.send(&PeerMessage::Link { session, link: 0 }, target) {
+                        tracing::debug!("Link announcement failed: {}", e);
+                    }
+                    let announcement = control.encode(&PeerMessage::Link { session, link: 1 });
+                    if let Err(e) = link.socket.send_to(&announcement, link.target) {
+                        tracing::debug!("Link announcement on the redundant link failed: {}", e);
+                    }
                 }
-                Err(crossbeam_channel::RecvTimeoutError::Timeout) => {
+                last_probe = Some(Instant::now());
+            }
+            
+            match next_packet(&queues, Duration::from_millis(10)) {
+                Ok(Some(encoded)) => send(encoded),
+                Ok(None) => {
                     // No packet available, continue
                 }
-                Err(crossbeam_channel::RecvTimeoutError::Disconnected) => {
+                Err(()) => {
                     // Channel closed, exit
                     break;
                 }
             }
         }
+        
+        // Drain packets queued before stop so the stream ends cleanly
+        while let Ok(Some(encoded)) = next_packet(&queues, Duration::ZERO) {
+            send(encoded);
+        }
     }
     
     /// Stop the sender
@@ -149,16 +477,42 @@ impl AudioSender {
         if let Some(handle) = self.thread_handle.take() {
             let _ = handle.join();
         }
+        
+        if let Some(handle) = self.control_handle.take() {
+            let _ = handle.join();
+        }
+    }
+    
+    /// Drain queued packets, signal end of stream and stop
+    pub fn finish(&mut self) {
+        if !self.is_running() {
+            return;
+        }
+        
+        self.running.store(false, Ordering::SeqCst);
+        if let Some(handle) = self.thread_handle.take() {
+            let _ = handle.join();
+        }
+        
+        if let Err(e) = self.send_control(&PeerMessage::EndOfStream) {
+            tracing::warn!("Failed to send end of stream: {}", e);
+        }
+        
+        self.stop();
     }
     
     /// Send an encoded packet
     pub fn send(&self, packet: EncodedPacket) -> Result<(), NetworkError> {
-        self.packet_tx
+        let queue = match packet.priority {
+            TrackPriority::High => &self.priority_tx,
+            TrackPriority::Normal => &self.packet_tx,
+        };
+        queue
             .try_send(packet)
             .map_err(|_| NetworkError::SendFailed("Channel full".to_string()))
     }
     
-    /// Get channel for sending packets
+    /// Get channel for sending normal-priority packets
     pub fn sender(&self) -> crossbeam_channel::Sender<EncodedPacket> {
         self.packet_tx.clone()
     }
@@ -168,6 +522,11 @@ impl AudioSender {
         self.running.load(Ordering::SeqCst)
     }
     
+    /// Take a track's queue-to-socket times collected since the last call
+    pub fn take_queue_delay(&self, track_id: u8) -> StageTotals {
+        self.queue_delay.remove(&track_id).map(|(_, totals)| totals).unwrap_or_default()
+    }
+    
     /// Get packets sent count
     pub fn packets_sent(&self) -> u64 {
         self.packets_sent.load(Ordering::Relaxed)
@@ -182,6 +541,24 @@ impl AudioSender {
     pub fn set_target(&mut self, addr: SocketAddr) {
         self.target_addr = addr;
     }
+    
+    /// Get target address
+    pub fn target(&self) -> SocketAddr {
+        self.target_addr
+    }
+    
+    /// Get the control channel endpoint (None until started)
+    pub fn control(&self) -> Option<Arc<ControlEndpoint>> {
+        self.control.clone()
+    }
+    
+    /// Send a control message to the target
+    pub fn send_control(&self, message: &PeerMessage) -> Result<(), NetworkError> {
+        match self.control {
+            Some(ref control) => control.send(message, self.target_addr),
+            None => Err(NetworkError::SendFailed("Sender not started".to_string())),
+        }
+    }
 }
 
 impl Drop for AudioSender {
@@ -195,6 +572,16 @@ pub struct MultiTrackSender {
     inner: AudioSender,
     /// Per-track sequence counters
     sequences: dashmap::DashMap<u8, u32>,
+    /// Per-track media clocks
+    clocks: dashmap::DashMap<u8, TrackClock>,
+    /// Sequence counters of simulcast layers, by track and layer
+    layer_sequences: dashmap::DashMap<(u8, u8), u32>,
+    /// Simulcast layers the receiver subscribed to besides layer 0
+    layers: parking_lot::RwLock<Vec<u8>>,
+    /// Per-track send priority (Normal if unset)
+    priorities: dashmap::DashMap<u8, TrackPriority>,
+    /// Tracks the receiver subscribed to (None = all)
+    subscription: parking_lot::RwLock<Option<Vec<u8>>>,
 }
 
 impl MultiTrackSender {
@@ -202,6 +589,11 @@ impl MultiTrackSender {
         Ok(Self {
             inner: AudioSender::new(config, target_addr)?,
             sequences: dashmap::DashMap::new(),
+            clocks: dashmap::DashMap::new(),
+            layer_sequences: dashmap::DashMap::new(),
+            layers: parking_lot::RwLock::new(Vec::new()),
+            priorities: dashmap::DashMap::new(),
+            subscription: parking_lot::RwLock::new(None),
         })
     }
     
@@ -210,19 +602,101 @@ impl MultiTrackSender {
         self.inner.start(config)
     }
     
+    /// Forward talkback audio from the receiver to `tx` (set before starting)
+    pub fn set_talkback_channel(&mut self, tx: crossbeam_channel::Sender<ReceivedPacket>) {
+        self.inner.set_talkback_channel(tx);
+    }
+    
+    /// Start sender on a socket another component already reads
+    pub fn start_shared(
+        &mut self,
+        config: NetworkConfig,
+        socket: &std::net::UdpSocket,
+        control: Arc<ControlEndpoint>,
+    ) -> Result<(), NetworkError> {
+        self.inner.start_shared(config, socket, control)
+    }
+    
     /// Stop sender
     pub fn stop(&mut self) {
         self.inner.stop();
     }
     
+    /// Change the receiver (takes effect on the next start)
+    pub fn set_target(&mut self, addr: SocketAddr) {
+        self.inner.set_target(addr);
+    }
+    
+    /// Drain queued packets, signal end of stream and stop
+    pub fn finish(&mut self) {
+        self.inner.finish();
+    }
+    
     /// Send encoded audio for a track
+    /// 
+    /// `ticks` is the length of the audio in the packet on the media clock
+    /// (sample frames at [`MEDIA_CLOCK_RATE`](crate::sync::MEDIA_CLOCK_RATE)).
     pub fn send_audio(
         &self,
         track_id: u8,
-        payload: Bytes,
-        timestamp: u64,
+        payload: impl Into<PacketBuffer>,
+        ticks: u32,
+        stereo: bool,
+    ) -> Result<u32, NetworkError> {
+        self.send_with_flags(track_id, payload, ticks, PacketFlags::new().set_stereo(stereo))
+    }
+    
+    /// Send the packet that starts a DTX (silence) period
+    /// 
+    /// Frames encoded during the period should not be sent at all, only
+    /// passed to [`skip_audio`](Self::skip_audio); the receiver fills the
+    /// gap with comfort noise.
+    pub fn send_audio_dtx(
+        &self,
+        track_id: u8,
+        payload: impl Into<PacketBuffer>,
+        ticks: u32,
         stereo: bool,
     ) -> Result<u32, NetworkError> {
+        let flags = PacketFlags::new().set_stereo(stereo).set_dtx(true);
+        self.send_with_flags(track_id, payload, ticks, flags)
+    }
+    
+    /// Advance a track's media clock over audio that is not sent
+    pub fn skip_audio(&self, track_id: u8, ticks: u32) {
+        self.clocks.entry(track_id).or_default().skip(ticks);
+    }
+    
+    /// Packets for tracks the receiver has not subscribed to are dropped
+    /// without using up a sequence number.
+    fn send_with_flags(
+        &self,
+        track_id: u8,
+        payload: impl Into<PacketBuffer>,
+        ticks: u32,
+        flags: PacketFlags,
+    ) -> Result<u32, NetworkError> {
+        if !self.is_subscribed(track_id) {
+            return Ok(self.sequences.get(&track_id).map(|s| *s).unwrap_or(0));
+        }
+        
+        // Stamp from the media clock, reporting it ahead of the packet when due
+        let wall_us = now_us();
+        let control = self.inner.control();
+        let reports = control
+            .as_ref()
+            .is_some_and(|c| c.negotiated(self.inner.target()).features.contains(ProtocolFeatures::MEDIA_CLOCK));
+        let (timestamp, report) = {
+            let mut clock = self.clocks.entry(track_id).or_default();
+            let timestamp = clock.stamp(ticks, wall_us);
+            (timestamp, reports.then(|| clock.report(wall_us)).flatten())
+        };
+        if let Some(reference) = report {
+            if let Err(e) = self.send_control(&PeerMessage::MediaClock { track_id, reference }) {
+                tracing::debug!("Media clock report for track {} failed: {}", track_id, e);
+            }
+        }
+        
         // Get and increment sequence
         let sequence = {
             let mut entry = self.sequences.entry(track_id).or_insert(0);
@@ -233,24 +707,139 @@ impl MultiTrackSender {
         
         let packet = EncodedPacket {
             track_id,
+            layer: 0,
             sequence,
             timestamp,
-            payload,
-            flags: PacketFlags::new().set_stereo(stereo),
+            wall_us,
+            payload: payload.into(),
+            flags,
+            priority: self.priority(track_id),
+            queued_at: Instant::now(),
+        };
+        
+        self.inner.send(packet)?;
+        Ok(sequence)
+    }
+    
+    /// Send a simulcast layer's encoding of the audio last sent on a track
+    /// 
+    /// The packet shares the primary packet's timestamp and counts sequence
+    /// numbers of its own. Dropped unless the receiver subscribed to the
+    /// layer, or before anything was sent on the track.
+    pub fn send_layer(
+        &self,
+        track_id: u8,
+        layer: u8,
+        payload: impl Into<PacketBuffer>,
+        stereo: bool,
+        dtx: bool,
+    ) -> Result<u32, NetworkError> {
+        let reference = self.clocks.get(&track_id).and_then(|clock| clock.reference());
+        let Some(reference) = reference.filter(|_| self.is_subscribed(track_id) && self.wants_layer(layer)) else {
+            return Ok(self.layer_sequences.get(&(track_id, layer)).map(|s| *s).unwrap_or(0));
+        };
+        
+        let sequence = {
+            let mut entry = self.layer_sequences.entry((track_id, layer)).or_insert(0);
+            let seq = *entry;
+            *entry = entry.wrapping_add(1);
+            seq
+        };
+        
+        let packet = EncodedPacket {
+            track_id,
+            layer,
+            sequence,
+            timestamp: reference.ticks,
+            wall_us: reference.wall_us,
+            payload: payload.into(),
+            flags: PacketFlags::new().set_stereo(stereo).set_dtx(dtx),
+            priority: self.priority(track_id),
+            queued_at: Instant::now(),
         };
         
         self.inner.send(packet)?;
         Ok(sequence)
     }
     
+    /// Whether the receiver wants a simulcast layer (layer 0 always)
+    /// 
+    /// Callers can skip encoding layers nobody is subscribed to.
+    pub fn wants_layer(&self, layer: u8) -> bool {
+        layer == 0 || self.layers.read().contains(&layer)
+    }
+    
+    /// Send these simulcast layers besides layer 0
+    pub fn set_layers(&self, layers: &[u8]) {
+        *self.layers.write() = layers.to_vec();
+    }
+    
     /// Reset sequence counter for a track
     pub fn reset_sequence(&self, track_id: u8) {
         self.sequences.insert(track_id, 0);
+        self.layer_sequences.retain(|&(id, _), _| id != track_id);
+    }
+    
+    /// Set a track's send priority
+    pub fn set_priority(&self, track_id: u8, priority: TrackPriority) {
+        self.priorities.insert(track_id, priority);
+    }
+    
+    /// Get a track's send priority
+    pub fn priority(&self, track_id: u8) -> TrackPriority {
+        self.priorities
+            .get(&track_id)
+            .map(|p| *p)
+            .unwrap_or_default()
+    }
+    
+    /// Only send the given tracks (empty = all)
+    pub fn set_subscription(&self, tracks: &[u8]) {
+        *self.subscription.write() = (!tracks.is_empty()).then(|| tracks.to_vec());
+    }
+    
+    /// Whether the receiver wants a track
+    /// 
+    /// Callers can skip encoding tracks nobody is subscribed to.
+    pub fn is_subscribed(&self, track_id: u8) -> bool {
+        self.subscription
+            .read()
+            .as_ref()
+            .is_none_or(|tracks| tracks.contains(&track_id))
+    }
+    
+    /// Apply a subscription from the target; returns whether `inbound` was one
+    pub fn handle_control(&self, inbound: &InboundControl) -> bool {
+        if inbound.from.ip() != self.inner.target().ip() {
+            return false;
+        }
+        if let PeerMessage::SubscribeLayers { ref layers } = inbound.message {
+            if *self.layers.read() != *layers {
+                tracing::info!("Receiver subscribed to simulcast layers {:?}", layers);
+            }
+            self.set_layers(layers);
+            return true;
+        }
+        let PeerMessage::Subscribe { ref tracks } = inbound.message else {
+            return false;
+        };
+        if self.subscription.read().as_deref().unwrap_or_default() != tracks.as_slice() {
+            if tracks.is_empty() {
+                tracing::info!("Receiver subscribed to all tracks");
+            } else {
+                tracing::info!("Receiver subscribed to tracks {:?}", tracks);
+            }
+        }
+        self.set_subscription(tracks);
+        true
     }
     
     /// Remove track
     pub fn remove_track(&self, track_id: u8) {
         self.sequences.remove(&track_id);
+        self.clocks.remove(&track_id);
+        self.layer_sequences.retain(|&(id, _), _| id != track_id);
+        self.priorities.remove(&track_id);
     }
     
     /// Get sender channel
@@ -258,6 +847,21 @@ impl MultiTrackSender {
         self.inner.sender()
     }
     
+    /// Get the control channel endpoint (None until started)
+    pub fn control(&self) -> Option<Arc<ControlEndpoint>> {
+        self.inner.control()
+    }
+    
+    /// Send a control message to the receiver
+    pub fn send_control(&self, message: &PeerMessage) -> Result<(), NetworkError> {
+        self.inner.send_control(message)
+    }
+    
+    /// Take a track's queue-to-socket times collected since the last call
+    pub fn take_queue_delay(&self, track_id: u8) -> StageTotals {
+        self.inner.take_queue_delay(track_id)
+    }
+    
     /// Get statistics
     pub fn stats(&self) -> SenderStats {
         SenderStats {
@@ -275,3 +879,184 @@ pub struct SenderStats {
     pub bytes_sent: u64,
     pub active_tracks: usize,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    
+    fn packet(track_id: u8, priority: TrackPriority) -> EncodedPacket {
+        EncodedPacket {
+            track_id,
+            layer: 0,
+            sequence: 0,
+            timestamp: 0,
+            wall_us: 0,
+            payload: PacketBuffer::default(),
+            flags: PacketFlags::new(),
+            priority,
+            queued_at: Instant::now(),
+        }
+    }
+    
+    #[test]
+    fn test_priority_queue_first() {
+        let (high_tx, high_rx) = crossbeam_channel::bounded(4);
+        let (normal_tx, normal_rx) = crossbeam_channel::bounded(4);
+        let queues = [high_rx, normal_rx];
+        
+        normal_tx.send(packet(1, TrackPriority::Normal)).unwrap();
+        high_tx.send(packet(0, TrackPriority::High)).unwrap();
+        
+        let first = next_packet(&queues, Duration::ZERO).unwrap().unwrap();
+        let second = next_packet(&queues, Duration::ZERO).unwrap().unwrap();
+        assert_eq!(first.track_id, 0);
+        assert_eq!(second.track_id, 1);
+        assert!(next_packet(&queues, Duration::ZERO).unwrap().is_none());
+    }
+    
+    #[test]
+    fn test_subscription_from_target_only() {
+        let target: SocketAddr = "10.0.0.2:5000".parse().unwrap();
+        let sender = MultiTrackSender::new(&NetworkConfig { udp_port: 0, ..Default::default() }, target).unwrap();
+        let subscribe = |from: &str, tracks: Vec<u8>| InboundControl {
+            message: PeerMessage::Subscribe { tracks },
+            from: from.parse().unwrap(),
+            receive_time: 0,
+        };
+        assert!(sender.is_subscribed(3));
+        
+        // Another host cannot narrow the target's stream
+        assert!(!sender.handle_control(&subscribe("10.0.0.9:5000", vec![1])));
+        assert!(sender.is_subscribed(3));
+        
+        assert!(sender.handle_control(&subscribe("10.0.0.2:6000", vec![1])));
+        assert!(sender.is_subscribed(1));
+        assert!(!sender.is_subscribed(3));
+        // Unsubscribed tracks are dropped before queueing and keep their sequence
+        assert_eq!(sender.send_audio(3, Bytes::from_static(&[1]), 0, true).unwrap(), 0);
+        assert_eq!(sender.stats().active_tracks, 0);
+        
+        assert!(sender.handle_control(&subscribe("10.0.0.2:5000", vec![])));
+        assert!(sender.is_subscribed(3));
+    }
+    
+    #[test]
+    fn test_simulcast_layers() {
+        let peer = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let target = peer.local_addr().unwrap();
+        let config = NetworkConfig { udp_port: 0, ..Default::default() };
+        let mut sender = MultiTrackSender::new(&config, target).unwrap();
+        sender.start(config).unwrap();
+        let layers = |layers: Vec<u8>| InboundControl {
+            message: PeerMessage::SubscribeLayers { layers },
+            from: target,
+            receive_time: 0,
+        };
+        assert!(sender.wants_layer(0));
+        assert!(!sender.wants_layer(1));
+        
+        // Unsubscribed layers and layers of tracks not yet sent are dropped
+        assert_eq!(sender.send_layer(2, 1, Bytes::from_static(&[1]), true, false).unwrap(), 0);
+        assert!(sender.handle_control(&layers(vec![1])));
+        assert!(sender.wants_layer(1));
+        assert_eq!(sender.send_layer(2, 1, Bytes::from_static(&[1]), true, false).unwrap(), 0);
+        
+        // Layers count sequences of their own
+        sender.send_audio(2, Bytes::from_static(&[0]), 480, true).unwrap();
+        sender.send_audio(2, Bytes::from_static(&[0]), 480, true).unwrap();
+        assert_eq!(sender.send_layer(2, 1, Bytes::from_static(&[1]), true, false).unwrap(), 0);
+        assert_eq!(sender.send_layer(2, 1, Bytes::from_static(&[1]), true, false).unwrap(), 1);
+        
+        assert!(sender.handle_control(&layers(vec![])));
+        assert!(!sender.wants_layer(1));
+        sender.stop();
+    }
+    
+    #[test]
+    fn test_talkback_from_target_forwarded() {
+        let config = NetworkConfig { udp_port: 47620, ..Default::default() };
+        let peer = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut sender = AudioSender::new(&config, peer.local_addr().unwrap()).unwrap();
+        let (tx, rx) = crossbeam_channel::bounded(4);
+        sender.set_talkback_channel(tx);
+        sender.start(config).unwrap();
+        
+        let talkback = AudioPacket {
+            track_id: crate::constants::TALKBACK_TRACK_ID,
+            layer: 0,
+            flags: PacketFlags::new(),
+            sequence: 5,
+            timestamp: 0,
+            payload: Bytes::from_static(&[1, 2, 3]),
+        };
+        peer.send_to(&talkback.serialize(), "127.0.0.1:47620").unwrap();
+        let received = rx.recv_timeout(Duration::from_secs(2)).unwrap();
+        assert_eq!((received.track_id, received.sequence), (crate::constants::TALKBACK_TRACK_ID, 5));
+        sender.stop();
+    }
+    
+    #[test]
+    fn test_track_ports() {
+        // Tracks 0 and 1 on ports of their own, the rest on the base port
+        let tracks = std::net::UdpSocket::bind("127.0.0.1:47641").unwrap();
+        let base = std::net::UdpSocket::bind("127.0.0.1:47640").unwrap();
+        let config = NetworkConfig { udp_port: 0, track_ports: 2, ..Default::default() };
+        assert_eq!((config.track_port(47640, 1), config.track_port(47640, 2)), (47641, 47640));
+        
+        let mut sender = MultiTrackSender::new(&config, base.local_addr().unwrap()).unwrap();
+        sender.start(config).unwrap();
+        sender.send_audio(1, Bytes::from_static(&[1]), 0, false).unwrap();
+        sender.send_audio(5, Bytes::from_static(&[5]), 0, false).unwrap();
+        
+        let mut buf = [0u8; 2048];
+        let track = |socket: &std::net::UdpSocket, buf: &mut [u8]| loop {
+            socket.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+            let (size, _) = socket.recv_from(buf).unwrap();
+            if let Ok(Packet::Audio(packet)) = parse_packet(&buf[..size]) {
+                return packet.track_id;
+            }
+        };
+        assert_eq!(track(&tracks, &mut buf), 1);
+        assert_eq!(track(&base, &mut buf), 5);
+        sender.stop();
+    }
+    
+    #[test]
+    fn test_redundant_link() {
+        let first = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let second = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let config = NetworkConfig {
+            udp_port: 0,
+            redundant_bind: Some("127.0.0.1".to_string()),
+            redundant_target: Some(second.local_addr().unwrap().to_string()),
+            ..Default::default()
+        };
+        
+        let mut sender = MultiTrackSender::new(&config, first.local_addr().unwrap()).unwrap();
+        sender.start(config).unwrap();
+        sender.send_audio(3, Bytes::from_static(&[1, 2, 3]), 960, false).unwrap();
+        
+        // The same packet on both links, from different sockets, each link announced
+        let mut buf = [0u8; 2048];
+        let mut receive = |socket: &std::net::UdpSocket| {
+            socket.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+            let mut link = None;
+            loop {
+                let (size, from) = socket.recv_from(&mut buf).unwrap();
+                match parse_packet(&buf[..size]) {
+                    Ok(Packet::Audio(packet)) => return (packet.track_id, packet.sequence, from, link),
+                    Ok(Packet::Control(PeerMessage::Link { link: n, .. })) => link = Some(n),
+                    _ => {}
+                }
+            }
+        };
+        let (track, sequence, first_from, first_link) = receive(&first);
+        let (track2, sequence2, second_from, second_link) = receive(&second);
+        assert_eq!((track, sequence), (track2, sequence2));
+        assert_eq!(track, 3);
+        assert_ne!(first_from, second_from);
+        assert_eq!((first_link, second_link), (Some(0), Some(1)));
+        sender.stop();
+    }
+}
@@ -0,0 +1,230 @@
+//! Async receive stream and send sink
+//!
+//! [`ReceiveStream`] decodes incoming packets and yields `(TrackId,
+//! AudioFrame)` pairs as a `futures` [`Stream`]; [`SendSink`] takes the same
+//! pairs, encodes them and queues them on the sender. Async applications can
+//! use these instead of the crossbeam channels the binaries wire up by hand.
+//!
+//! Frames come out in arrival order with their sequence and sender
+//! timestamp; put them through a [`JitterBuffer`](crate::audio::buffer::JitterBuffer)
+//! for reordering and playout timing.
+
+use bytes::Bytes;
+use futures_util::{Sink, Stream};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::thread;
+use tokio::sync::mpsc;
+
+use crate::audio::buffer::AudioFrame;
+use crate::codec::{OpusDecoder, OpusEncoder};
+use crate::config::{NetworkConfig, OpusConfig};
+use crate::constants::{DEFAULT_FRAME_SIZE_MS, DEFAULT_SAMPLE_RATE};
+use crate::error::{CodecError, Error, NetworkError};
+use crate::network::receiver::{AudioReceiver, ReceivedPacket};
+use crate::network::sender::MultiTrackSender;
+use crate::sync::media;
+
+/// Track identifier as carried in packets
+pub type TrackId = u8;
+
+/// Packets buffered between the network thread and the stream
+const STREAM_QUEUE: usize = 1024;
+
+/// One decoder per track, recreated if the channel count changes
+#[derive(Default)]
+pub(crate) struct Decoders {
+    tracks: HashMap<TrackId, OpusDecoder>,
+}
+
+impl Decoders {
+    pub(crate) fn decode(&mut self, packet: &ReceivedPacket) -> Result<AudioFrame, CodecError> {
+        let channels = if packet.is_stereo { 2 } else { 1 };
+        if self.tracks.get(&packet.track_id).is_none_or(|d| d.channels() != channels) {
+            let frame_size = (DEFAULT_SAMPLE_RATE as f32 * DEFAULT_FRAME_SIZE_MS / 1000.0) as usize;
+            let decoder = OpusDecoder::new(DEFAULT_SAMPLE_RATE, channels, frame_size)?;
+            self.tracks.insert(packet.track_id, decoder);
+        }
+
+        let decoder = self.tracks.get_mut(&packet.track_id).expect("decoder just inserted");
+        let samples = decoder.decode(&packet.payload)?;
+        Ok(AudioFrame::new(samples, channels, packet.send_us(), packet.sequence))
+    }
+}
+
+/// Decoded audio from all tracks as an async stream
+pub struct ReceiveStream {
+    receiver: AudioReceiver,
+    packets: mpsc::Receiver<ReceivedPacket>,
+    decoders: Decoders,
+}
+
+impl ReceiveStream {
+    /// Bind the receive socket and start streaming
+    pub fn bind(config: NetworkConfig) -> Result<Self, NetworkError> {
+        let (packet_tx, packet_rx) = crossbeam_channel::bounded::<ReceivedPacket>(STREAM_QUEUE);
+        let (stream_tx, stream_rx) = mpsc::channel(STREAM_QUEUE);
+
+        let mut receiver = AudioReceiver::new();
+        receiver.set_global_channel(packet_tx);
+        receiver.start(config)?;
+
+        // Hand packets over to the async side; ends with either side
+        thread::Builder::new()
+            .name("receive-stream".to_string())
+            .spawn(move || {
+                while let Ok(packet) = packet_rx.recv() {
+                    if stream_tx.blocking_send(packet).is_err() {
+                        break;
+                    }
+                }
+            })
+            .map_err(|e| NetworkError::ReceiveFailed(e.to_string()))?;
+
+        Ok(Self {
+            receiver,
+            packets: stream_rx,
+            decoders: Decoders::default(),
+        })
+    }
+
+    /// Underlying receiver, for statistics and the control channel
+    pub fn receiver(&self) -> &AudioReceiver {
+        &self.receiver
+    }
+}
+
+impl Stream for ReceiveStream {
+    type Item = (TrackId, AudioFrame);
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            let Some(packet) = std::task::ready!(self.packets.poll_recv(cx)) else {
+                return Poll::Ready(None);
+            };
+            match self.decoders.decode(&packet) {
+                Ok(frame) => return Poll::Ready(Some((packet.track_id, frame))),
+                Err(e) => tracing::warn!("Decode error on track {}: {}", packet.track_id, e),
+            }
+        }
+    }
+}
+
+/// One encoder per track and the samples waiting for a full frame
+pub(crate) struct Encoders {
+    config: OpusConfig,
+    tracks: HashMap<TrackId, (OpusEncoder, Vec<f32>)>,
+}
+
+impl Encoders {
+    pub(crate) fn new(config: OpusConfig) -> Self {
+        Self { config, tracks: HashMap::new() }
+    }
+
+    /// Media clock ticks each packet covers
+    pub(crate) fn frame_ticks(&self) -> u32 {
+        media::frames_to_ticks(self.config.frame_size, self.config.sample_rate)
+    }
+
+    /// Add a frame of any length; returns the packets it completed
+    pub(crate) fn push(&mut self, track_id: TrackId, frame: &AudioFrame) -> Result<Vec<Bytes>, CodecError> {
+        if self.tracks.get(&track_id).is_none_or(|(e, _)| e.config().channels != frame.channels) {
+            let encoder = OpusEncoder::new(OpusConfig { channels: frame.channels, ..self.config.clone() })?;
+            self.tracks.insert(track_id, (encoder, Vec::new()));
+        }
+
+        let (encoder, pending) = self.tracks.get_mut(&track_id).expect("encoder just inserted");
+        pending.extend_from_slice(&frame.samples);
+        let frame_len = encoder.frame_size() * frame.channels as usize;
+        let mut packets = Vec::new();
+        while pending.len() >= frame_len {
+            let samples: Vec<f32> = pending.drain(..frame_len).collect();
+            packets.push(encoder.encode(&samples)?);
+        }
+        Ok(packets)
+    }
+}
+
+/// Encodes and sends `(TrackId, AudioFrame)` pairs as an async sink
+///
+/// Frames may be any length; samples are held until a full encoder frame is
+/// available. Closing the sink sends end of stream to the receiver.
+pub struct SendSink {
+    sender: MultiTrackSender,
+    encoders: Encoders,
+}
+
+impl SendSink {
+    /// Start a sender to `target`, encoding with `opus` (channels follow the frames)
+    pub fn connect(config: NetworkConfig, target: SocketAddr, opus: OpusConfig) -> Result<Self, NetworkError> {
+        let mut sender = MultiTrackSender::new(&config, target)?;
+        sender.start(config)?;
+        Ok(Self { sender, encoders: Encoders::new(opus) })
+    }
+
+    /// Underlying sender, for statistics, priorities and the control channel
+    pub fn sender(&self) -> &MultiTrackSender {
+        &self.sender
+    }
+}
+
+impl Sink<(TrackId, AudioFrame)> for SendSink {
+    type Error = Error;
+
+    fn poll_ready(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, (track_id, frame): (TrackId, AudioFrame)) -> Result<(), Self::Error> {
+        let stereo = frame.channels == 2;
+        let ticks = self.encoders.frame_ticks();
+        for payload in self.encoders.push(track_id, &frame)? {
+            self.sender.send_audio(track_id, payload, ticks, stereo)?;
+        }
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // Packets are queued to the sender thread as soon as they are encoded
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.sender.finish();
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{AudioPacket, PacketFlags};
+
+    #[test]
+    fn test_encode_decode_round_trip() {
+        let mut encoders = Encoders::new(OpusConfig::music());
+        let mut decoders = Decoders::default();
+
+        // 15 ms of stereo: one 10 ms packet now, the rest waits
+        let frame = AudioFrame::new(vec![0.1; 720 * 2], 2, 0, 0);
+        let packets = encoders.push(3, &frame).unwrap();
+        assert_eq!(packets.len(), 1);
+        assert_eq!(encoders.push(3, &frame).unwrap().len(), 2);
+
+        let packet = AudioPacket {
+            track_id: 3,
+            layer: 0,
+            flags: PacketFlags::new().set_stereo(true),
+            sequence: 9,
+            timestamp: 1234,
+            payload: packets[0].clone(),
+        };
+        let received = ReceivedPacket::new(packet, "127.0.0.1:5000".parse().unwrap());
+        let decoded = decoders.decode(&received).unwrap();
+        assert_eq!(decoded.channels, 2);
+        assert_eq!(decoded.samples.len(), 480 * 2);
+        assert_eq!((decoded.sequence, decoded.timestamp), (9, 1234));
+    }
+}
@@ -0,0 +1,234 @@
+//! Talkback from the receiver back to the sender
+//!
+//! The receiver PC's microphone goes to the sender PC's headphones as one
+//! low-bitrate mono track, [`TALKBACK_TRACK_ID`], sent from the receiver's
+//! socket to the address the audio arrives from. Capture runs continuously
+//! but packets only go out while push-to-talk is held
+//! ([`TrackManager::set_talkback`]); each press and release is faded.
+//!
+//! On the sender, the control reader hands talkback packets from the target
+//! to [`Talkback::start_playback`].
+
+use crossbeam_channel::{Receiver, RecvTimeoutError};
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::audio::buffer::{create_shared_buffer, AudioFrame, JitterBuffer};
+use crate::audio::capture::AudioCapture;
+use crate::audio::device::list_devices;
+use crate::audio::fade;
+use crate::audio::playback::NetworkPlayback;
+use crate::codec::{OpusDecoder, OpusEncoder};
+use crate::config::TalkbackConfig;
+use crate::constants::{DEFAULT_CHANNELS, DEFAULT_SAMPLE_RATE, RING_BUFFER_CAPACITY, TALKBACK_TRACK_ID};
+use crate::error::{AudioError, Error};
+use crate::network::receiver::ReceivedPacket;
+use crate::protocol::{AudioPacket, PacketFlags};
+use crate::sync::now_us;
+use crate::tracks::TrackManager;
+
+/// Where talkback goes: the sender's address, once audio has arrived from it
+pub type TalkbackTarget = Arc<parking_lot::Mutex<Option<SocketAddr>>>;
+
+/// A running talkback capture or playback thread
+pub struct Talkback {
+    running: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Talkback {
+    /// Receiver side: capture the microphone and send it while push-to-talk is held
+    pub fn start_capture(
+        config: &TalkbackConfig,
+        socket: Arc<UdpSocket>,
+        target: TalkbackTarget,
+        track_manager: Arc<TrackManager>,
+    ) -> Result<Self, Error> {
+        let device_id = resolve_device(config.input_device.as_deref(), true)?;
+        let bitrate = config.bitrate;
+        Self::spawn("talkback-capture", move |running, ready| {
+            let buffer = create_shared_buffer(RING_BUFFER_CAPACITY);
+            let opened = AudioCapture::new(
+                TALKBACK_TRACK_ID,
+                &device_id,
+                Some(DEFAULT_SAMPLE_RATE),
+                Some(DEFAULT_CHANNELS),
+                None,
+                buffer.clone(),
+            )
+            .and_then(|mut capture| capture.start().map(|()| capture));
+            let mut encoder = match OpusEncoder::voice(DEFAULT_SAMPLE_RATE, 1) {
+                Ok(encoder) => encoder,
+                Err(e) => {
+                    let _ = ready.send(Err(e.into()));
+                    return;
+                }
+            };
+            if let Err(e) = encoder.set_bitrate(bitrate) {
+                tracing::warn!("Talkback bitrate {} rejected: {}", bitrate, e);
+            }
+            let mut capture = match opened {
+                Ok(capture) => {
+                    let _ = ready.send(Ok(()));
+                    capture
+                }
+                Err(e) => {
+                    let _ = ready.send(Err(e.into()));
+                    return;
+                }
+            };
+            tracing::info!("Talkback microphone open on {}", device_id);
+
+            let frame_size = encoder.samples_per_frame();
+            let mut pending: Vec<f32> = Vec::with_capacity(frame_size * 2);
+            let mut talking = false;
+            let mut sequence: u32 = 0;
+            while running.load(Ordering::Relaxed) {
+                let Some(frame) = buffer.try_pop() else {
+                    thread::sleep(Duration::from_millis(2));
+                    continue;
+                };
+                pending.extend(downmix(&frame.samples, frame.channels));
+                while pending.len() >= frame_size {
+                    let mut samples: Vec<f32> = pending.drain(..frame_size).collect();
+                    let active = track_manager.is_talkback_active();
+                    match (talking, active) {
+                        (false, false) => continue,
+                        (false, true) => fade::fade_in(&mut samples, 1),
+                        (true, false) => fade::fade_out(&mut samples, 1),
+                        (true, true) => {}
+                    }
+                    talking = active;
+
+                    let Some(target) = *target.lock() else {
+                        continue;
+                    };
+                    match encoder.encode(&samples) {
+                        Ok(payload) => {
+                            let packet = AudioPacket {
+                                track_id: TALKBACK_TRACK_ID,
+                                layer: 0,
+                                flags: PacketFlags::new(),
+                                sequence,
+                                timestamp: now_us(),
+                                payload,
+                            };
+                            sequence = sequence.wrapping_add(1);
+                            if let Err(e) = socket.send_to(&packet.serialize(), target) {
+                                tracing::debug!("Talkback send to {} failed: {}", target, e);
+                            }
+                        }
+                        Err(e) => tracing::warn!("Talkback encoding failed: {}", e),
+                    }
+                }
+            }
+            capture.stop();
+        })
+    }
+
+    /// Sender side: play talkback packets on the configured output
+    pub fn start_playback(config: &TalkbackConfig, packets: Receiver<ReceivedPacket>) -> Result<Self, Error> {
+        let device_id = resolve_device(config.output_device.as_deref(), false)?;
+        Self::spawn("talkback-play", move |running, ready| {
+            let frame_size = (DEFAULT_SAMPLE_RATE / 100) as usize;
+            let jitter_buffer = Arc::new(JitterBuffer::new(16, 2));
+            let opened = OpusDecoder::new(DEFAULT_SAMPLE_RATE, DEFAULT_CHANNELS, frame_size)
+                .map_err(Error::from)
+                .and_then(|decoder| {
+                    let mut playback = NetworkPlayback::with_jitter_buffer(
+                        TALKBACK_TRACK_ID,
+                        &device_id,
+                        Some(DEFAULT_SAMPLE_RATE),
+                        Some(DEFAULT_CHANNELS),
+                        jitter_buffer.clone(),
+                    )?;
+                    playback.start()?;
+                    Ok((decoder, playback))
+                });
+            let (mut decoder, mut playback) = match opened {
+                Ok(opened) => {
+                    let _ = ready.send(Ok(()));
+                    opened
+                }
+                Err(e) => {
+                    let _ = ready.send(Err(e));
+                    return;
+                }
+            };
+            tracing::info!("Talkback playing on {}", device_id);
+
+            while running.load(Ordering::Relaxed) {
+                match packets.recv_timeout(Duration::from_millis(5)) {
+                    // Mono packets decode to the output's channel count
+                    Ok(packet) => match decoder.decode(&packet.payload) {
+                        Ok(samples) => {
+                            jitter_buffer.insert(AudioFrame::new(samples, DEFAULT_CHANNELS, packet.send_us(), packet.sequence));
+                        }
+                        Err(e) => tracing::warn!("Talkback decode error: {}", e),
+                    },
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+            }
+            playback.stop();
+        })
+    }
+
+    /// Run `f` on a named thread, waiting for it to report that its device opened
+    fn spawn(
+        name: &str,
+        f: impl FnOnce(Arc<AtomicBool>, crossbeam_channel::Sender<Result<(), Error>>) + Send + 'static,
+    ) -> Result<Self, Error> {
+        let running = Arc::new(AtomicBool::new(true));
+        let flag = running.clone();
+        let (ready_tx, ready_rx) = crossbeam_channel::bounded(1);
+        let handle = thread::Builder::new().name(name.to_string()).spawn(move || f(flag, ready_tx))?;
+        let mut talkback = Self { running, handle: Some(handle) };
+        match ready_rx.recv() {
+            Ok(Ok(())) => Ok(talkback),
+            Ok(Err(e)) => {
+                talkback.stop();
+                Err(e)
+            }
+            Err(_) => {
+                talkback.stop();
+                Err(AudioError::StreamError(format!("{} thread exited", name)).into())
+            }
+        }
+    }
+
+    /// Stop the thread and close the device
+    pub fn stop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for Talkback {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// The configured device ID, else the default input or output
+fn resolve_device(configured: Option<&str>, input: bool) -> Result<String, Error> {
+    if let Some(id) = configured {
+        return Ok(id.to_string());
+    }
+    list_devices()
+        .into_iter()
+        .find(|d| d.is_default && if input { d.is_input } else { d.is_output })
+        .map(|d| if input { format!("input:{}", d.name) } else { format!("output:{}", d.name) })
+        .ok_or_else(|| AudioError::DeviceNotFound(if input { "default input" } else { "default output" }.to_string()).into())
+}
+
+/// Average interleaved channels to mono
+fn downmix(samples: &[f32], channels: u16) -> impl Iterator<Item = f32> + '_ {
+    let channels = channels.max(1) as usize;
+    samples.chunks(channels).map(move |frame| frame.iter().sum::<f32>() / channels as f32)
+}
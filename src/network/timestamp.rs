@@ -0,0 +1,140 @@
+//! Kernel receive timestamps
+//!
+//! With `network.kernel_timestamps` on Linux, the receive socket asks for
+//! `SO_TIMESTAMPING` software RX stamps: the time the kernel took the packet
+//! from the NIC driver, before it waited in the socket buffer for the
+//! receiver thread. Jitter, transit and clock exchange times then leave out
+//! that wait. Elsewhere, or when the option is refused, packets are stamped
+//! when read.
+
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+use std::time::{Duration, SystemTime};
+
+/// Turn on kernel receive timestamps; false where unsupported
+pub fn enable(socket: &UdpSocket) -> bool {
+    imp::enable(socket)
+}
+
+/// Receive one datagram and, if enabled, how long ago the kernel received it
+pub fn recv_from(socket: &UdpSocket, buf: &mut [u8]) -> io::Result<(usize, SocketAddr, Option<Duration>)> {
+    imp::recv_from(socket, buf)
+}
+
+#[cfg(target_os = "linux")]
+pub(crate) use imp::control_age;
+
+/// Time since a kernel timestamp (zero if the clock stepped back since)
+fn age(stamp: SystemTime) -> Duration {
+    SystemTime::now().duration_since(stamp).unwrap_or_default()
+}
+
+#[cfg(target_os = "linux")]
+mod imp {
+    use super::*;
+    use std::mem::{size_of, size_of_val, zeroed};
+    use std::os::fd::AsRawFd;
+
+    pub fn enable(socket: &UdpSocket) -> bool {
+        let flags = (libc::SOF_TIMESTAMPING_RX_SOFTWARE | libc::SOF_TIMESTAMPING_SOFTWARE) as libc::c_int;
+        // SAFETY: the option value is a c_int that outlives the call
+        let result = unsafe {
+            libc::setsockopt(
+                socket.as_raw_fd(),
+                libc::SOL_SOCKET,
+                libc::SO_TIMESTAMPING,
+                &flags as *const libc::c_int as *const libc::c_void,
+                size_of::<libc::c_int>() as libc::socklen_t,
+            )
+        };
+        result == 0
+    }
+
+    pub fn recv_from(socket: &UdpSocket, buf: &mut [u8]) -> io::Result<(usize, SocketAddr, Option<Duration>)> {
+        // u64s keep the control buffer aligned for cmsghdr
+        let mut control = [0u64; 16];
+        // SAFETY: every pointer in `msg` refers to a live local buffer of the
+        // given length, and control messages are only read within the buffer
+        // the kernel filled, through the CMSG macros
+        unsafe {
+            let mut addr: libc::sockaddr_storage = zeroed();
+            let mut iov = libc::iovec { iov_base: buf.as_mut_ptr().cast(), iov_len: buf.len() };
+            let mut msg: libc::msghdr = zeroed();
+            msg.msg_name = (&mut addr as *mut libc::sockaddr_storage).cast();
+            msg.msg_namelen = size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+            msg.msg_iov = &mut iov;
+            msg.msg_iovlen = 1;
+            msg.msg_control = control.as_mut_ptr().cast();
+            msg.msg_controllen = size_of_val(&control) as _;
+
+            let size = libc::recvmsg(socket.as_raw_fd(), &mut msg, 0);
+            if size < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let from = socket2::SockAddr::new(addr, msg.msg_namelen)
+                .as_socket()
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Datagram from a non-IP address"))?;
+            Ok((size as usize, from, control_age(&msg)))
+        }
+    }
+
+    /// Age of the kernel receive stamp among the control messages of `msg`
+    ///
+    /// # Safety
+    ///
+    /// `msg` must describe a control buffer the kernel filled in by `recvmsg`.
+    pub unsafe fn control_age(msg: &libc::msghdr) -> Option<Duration> {
+        // Software stamp first; the other two are hardware stamps on the NIC's own clock
+        let mut stamp = None;
+        let mut cmsg = libc::CMSG_FIRSTHDR(msg);
+        while !cmsg.is_null() {
+            if (*cmsg).cmsg_level == libc::SOL_SOCKET && (*cmsg).cmsg_type == libc::SCM_TIMESTAMPING {
+                let times = std::ptr::read_unaligned(libc::CMSG_DATA(cmsg) as *const [libc::timespec; 3]);
+                let software = times[0];
+                if software.tv_sec > 0 {
+                    stamp = Some(SystemTime::UNIX_EPOCH + Duration::new(software.tv_sec as u64, software.tv_nsec as u32));
+                }
+            }
+            cmsg = libc::CMSG_NXTHDR(msg, cmsg);
+        }
+        stamp.map(age)
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod imp {
+    use super::*;
+
+    pub fn enable(_socket: &UdpSocket) -> bool {
+        false
+    }
+
+    pub fn recv_from(socket: &UdpSocket, buf: &mut [u8]) -> io::Result<(usize, SocketAddr, Option<Duration>)> {
+        let (size, from) = socket.recv_from(buf)?;
+        Ok((size, from, None))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recv_with_timestamp() {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let enabled = enable(&socket);
+        let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+        sender.send_to(b"ping", socket.local_addr().unwrap()).unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+
+        let mut buf = [0u8; 64];
+        let (size, from, age) = recv_from(&socket, &mut buf).unwrap();
+        assert_eq!((&buf[..size], from), (&b"ping"[..], sender.local_addr().unwrap()));
+        assert_eq!(age.is_some(), enabled);
+
+        // The packet waited in the socket buffer; the kernel stamp says so
+        if let Some(age) = age {
+            assert!(age >= Duration::from_millis(15) && age < Duration::from_secs(5), "{:?}", age);
+        }
+    }
+}
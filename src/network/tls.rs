@@ -0,0 +1,278 @@
+//! Outgoing HTTPS
+//!
+//! Webhooks and hooks with `https://` URLs are posted over rustls,
+//! verified against the platform's trusted roots (the Windows and macOS
+//! certificate stores, or the CA bundle on Linux; `SSL_CERT_FILE`
+//! overrides it). The handshake is finished within [`HANDSHAKE_TIMEOUT`]
+//! before the stream is handed out. [`TlsStream`] also carries the web
+//! UI's HTTPS connections with the `tls` feature.
+
+use rustls::pki_types::ServerName;
+use rustls::{ClientConfig, ClientConnection, Connection, RootCertStore};
+use std::io::{self, Read, Write};
+use std::pin::Pin;
+use std::sync::{Arc, OnceLock};
+use std::task::{ready, Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::TcpStream;
+
+/// Longest a server may take to complete the TLS handshake
+pub const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Client configuration trusting the platform's root certificates
+fn client_config() -> io::Result<Arc<ClientConfig>> {
+    static CONFIG: OnceLock<Arc<ClientConfig>> = OnceLock::new();
+    if let Some(config) = CONFIG.get() {
+        return Ok(config.clone());
+    }
+
+    let native = rustls_native_certs::load_native_certs();
+    for e in &native.errors {
+        tracing::debug!("Skipped part of the system certificate store: {}", e);
+    }
+    let mut roots = RootCertStore::empty();
+    roots.add_parsable_certificates(native.certs);
+    if roots.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "No CA certificates found; set SSL_CERT_FILE",
+        ));
+    }
+
+    let config = with_roots(roots)?;
+    Ok(CONFIG.get_or_init(|| config).clone())
+}
+
+/// Client configuration trusting `roots`
+fn with_roots(roots: RootCertStore) -> io::Result<Arc<ClientConfig>> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let config = ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .map_err(io::Error::other)?
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    Ok(Arc::new(config))
+}
+
+/// Open a verified TLS connection to `host` over an established TCP stream
+pub async fn connect(tcp: TcpStream, host: &str) -> io::Result<TlsStream> {
+    connect_with(tcp, host, client_config()?, HANDSHAKE_TIMEOUT).await
+}
+
+async fn connect_with(
+    tcp: TcpStream,
+    host: &str,
+    config: Arc<ClientConfig>,
+    timeout: Duration,
+) -> io::Result<TlsStream> {
+    let name = ServerName::try_from(host.to_string())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let conn = ClientConnection::new(config, name).map_err(io::Error::other)?;
+    let mut stream = TlsStream::new(tcp, conn);
+    tokio::time::timeout(timeout, std::future::poll_fn(|cx| stream.poll_handshake(cx)))
+        .await
+        .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, format!("TLS handshake with {} timed out", host)))??;
+    Ok(stream)
+}
+
+/// TLS over a tokio TCP stream, client or server side
+pub struct TlsStream {
+    io: TcpStream,
+    conn: Connection,
+    closing: bool,
+}
+
+impl TlsStream {
+    pub(crate) fn new(io: TcpStream, conn: impl Into<Connection>) -> Self {
+        Self { io, conn: conn.into(), closing: false }
+    }
+
+    /// Drive the handshake to completion
+    fn poll_handshake(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.conn.is_handshaking() {
+            ready!(self.poll_write_tls(cx))?;
+            if self.conn.is_handshaking() && self.conn.wants_read() && ready!(self.poll_read_tls(cx))? == 0 {
+                return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into()));
+            }
+        }
+        self.poll_write_tls(cx)
+    }
+
+    /// Write out pending TLS records
+    fn poll_write_tls(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.conn.wants_write() {
+            let mut io = SyncIo { io: &mut self.io, cx };
+            match self.conn.write_tls(&mut io) {
+                Ok(0) => return Poll::Ready(Err(io::ErrorKind::WriteZero.into())),
+                Ok(_) => {}
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Poll::Pending,
+                Err(e) => return Poll::Ready(Err(e)),
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    /// Read and process incoming TLS records, returning bytes read (0 = EOF)
+    fn poll_read_tls(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        let mut io = SyncIo { io: &mut self.io, cx };
+        match self.conn.read_tls(&mut io) {
+            Ok(n) => {
+                self.conn
+                    .process_new_packets()
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                Poll::Ready(Ok(n))
+            }
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => Poll::Pending,
+            Err(e) => Poll::Ready(Err(e)),
+        }
+    }
+}
+
+impl AsyncRead for TlsStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            match this.conn.reader().read(buf.initialize_unfilled()) {
+                Ok(n) => {
+                    buf.advance(n);
+                    return Poll::Ready(Ok(()));
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                Err(e) => return Poll::Ready(Err(e)),
+            }
+
+            // Handshake and alert records must go out before more comes in
+            ready!(this.poll_write_tls(cx))?;
+            if ready!(this.poll_read_tls(cx))? == 0 {
+                return Poll::Ready(Ok(()));
+            }
+        }
+    }
+}
+
+impl AsyncWrite for TlsStream {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        loop {
+            let written = this.conn.writer().write(buf)?;
+            let flushed = this.poll_write_tls(cx);
+            if written > 0 || buf.is_empty() {
+                if let Poll::Ready(Err(e)) = flushed {
+                    return Poll::Ready(Err(e));
+                }
+                return Poll::Ready(Ok(written));
+            }
+            // Plaintext buffer was full; retry once records are out
+            ready!(flushed)?;
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        this.conn.writer().flush()?;
+        ready!(this.poll_write_tls(cx))?;
+        Pin::new(&mut this.io).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if !this.closing {
+            this.conn.send_close_notify();
+            this.closing = true;
+        }
+        ready!(this.poll_write_tls(cx))?;
+        Pin::new(&mut this.io).poll_shutdown(cx)
+    }
+}
+
+/// Blocking-style view of the socket for rustls; `Pending` maps to `WouldBlock`
+struct SyncIo<'a, 'b> {
+    io: &'a mut TcpStream,
+    cx: &'a mut Context<'b>,
+}
+
+impl Read for SyncIo<'_, '_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut buf = ReadBuf::new(buf);
+        match Pin::new(&mut *self.io).poll_read(self.cx, &mut buf) {
+            Poll::Ready(Ok(())) => Ok(buf.filled().len()),
+            Poll::Ready(Err(e)) => Err(e),
+            Poll::Pending => Err(io::ErrorKind::WouldBlock.into()),
+        }
+    }
+}
+
+impl Write for SyncIo<'_, '_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match Pin::new(&mut *self.io).poll_write(self.cx, buf) {
+            Poll::Ready(result) => result,
+            Poll::Pending => Err(io::ErrorKind::WouldBlock.into()),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match Pin::new(&mut *self.io).poll_flush(self.cx) {
+            Poll::Ready(result) => result,
+            Poll::Pending => Err(io::ErrorKind::WouldBlock.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+    use rustls::{ServerConfig, ServerConnection};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_loopback_handshake() {
+        let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let cert = certified.cert.der().clone();
+        let key = PrivateKeyDer::Pkcs8(certified.key_pair.serialize_der().into());
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let server = Arc::new(
+            ServerConfig::builder_with_provider(provider)
+                .with_safe_default_protocol_versions()
+                .unwrap()
+                .with_no_client_auth()
+                .with_single_cert(vec![cert.clone()], key)
+                .unwrap(),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        // Echo one line back
+        tokio::spawn(async move {
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut stream = TlsStream::new(tcp, ServerConnection::new(server).unwrap());
+            let mut buf = [0u8; 5];
+            stream.read_exact(&mut buf).await.unwrap();
+            stream.write_all(&buf).await.unwrap();
+            stream.flush().await.unwrap();
+        });
+
+        let mut roots = RootCertStore::empty();
+        roots.add(CertificateDer::from(cert.to_vec())).unwrap();
+        let config = with_roots(roots).unwrap();
+        let tcp = TcpStream::connect(addr).await.unwrap();
+        let mut stream = connect_with(tcp, "localhost", config.clone(), HANDSHAKE_TIMEOUT).await.unwrap();
+        stream.write_all(b"hello").await.unwrap();
+        stream.flush().await.unwrap();
+        let mut buf = [0u8; 5];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"hello");
+
+        // A server that never answers is given up on
+        let silent = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let tcp = TcpStream::connect(silent.local_addr().unwrap()).await.unwrap();
+        let err = connect_with(tcp, "localhost", config, Duration::from_millis(100)).await.err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    }
+}
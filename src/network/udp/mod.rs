@@ -0,0 +1,351 @@
+//! Low-level UDP socket handling
+//!
+//! Optimized for low-latency audio streaming with configurable
+//! buffer sizes and non-blocking I/O. [`BatchReceiver`] reads many
+//! sockets through the platform's completion API (io_uring, overlapped
+//! I/O) for receivers with lots of tracks at short frame sizes.
+
+use socket2::{Domain, Protocol, Socket, Type};
+use std::net::{SocketAddr, UdpSocket as StdUdpSocket};
+use std::io;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::UdpSocket as TokioUdpSocket;
+
+use crate::config::{NetworkConfig, ReceiveBackend};
+use crate::error::NetworkError;
+
+#[cfg(target_os = "linux")]
+mod uring;
+#[cfg(windows)]
+mod overlapped;
+
+/// Re-export for convenience
+pub type UdpSocket = TokioUdpSocket;
+
+/// Ports to try in order: `port`, then up to `range` more (port 0 is only itself)
+pub fn port_candidates(port: u16, range: u16) -> impl Iterator<Item = u16> {
+    let range = if port == 0 { 0 } else { range };
+    (0..=range).map_while(move |offset| port.checked_add(offset))
+}
+
+/// Create a configured UDP socket for audio streaming
+///
+/// When `udp_port` is taken the next ports in `port_range` are tried.
+pub fn create_socket(config: &NetworkConfig) -> Result<StdUdpSocket, NetworkError> {
+    let mut last_error = None;
+    let mut candidates = port_candidates(config.udp_port, config.port_range);
+    let socket = loop {
+        let Some(port) = candidates.next() else {
+            let e = last_error.map_or_else(String::new, |e: io::Error| e.to_string());
+            return Err(NetworkError::BindFailed(format!("No free UDP port from {}: {}", config.udp_port, e)));
+        };
+        let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))
+            .map_err(|e| NetworkError::BindFailed(e.to_string()))?;
+        
+        // Set socket options for low latency
+        configure_socket(&socket, config)?;
+        
+        // Bind to address
+        let addr: SocketAddr = format!("{}:{}", config.bind_address, port)
+            .parse()
+            .map_err(|e: std::net::AddrParseError| NetworkError::BindFailed(e.to_string()))?;
+        
+        match socket.bind(&addr.into()) {
+            Ok(()) => {
+                if port != config.udp_port {
+                    tracing::warn!("UDP port {} is in use; using port {}", config.udp_port, port);
+                }
+                break socket;
+            }
+            Err(e) if e.kind() == io::ErrorKind::AddrInUse => last_error = Some(e),
+            Err(e) => return Err(NetworkError::BindFailed(e.to_string())),
+        }
+    };
+    
+    // Convert to std socket
+    let std_socket: StdUdpSocket = socket.into();
+    std_socket.set_nonblocking(true)
+        .map_err(|e| NetworkError::BindFailed(e.to_string()))?;
+    
+    Ok(std_socket)
+}
+
+/// Create the socket for the second link of redundant sending, bound to
+/// `redundant_bind` on any free port (None without one)
+pub fn create_redundant_socket(config: &NetworkConfig) -> Result<Option<StdUdpSocket>, NetworkError> {
+    let Some(ref bind_address) = config.redundant_bind else {
+        return Ok(None);
+    };
+    let config = NetworkConfig { bind_address: bind_address.clone(), udp_port: 0, port_range: 0, ..config.clone() };
+    create_socket(&config).map(Some)
+}
+
+/// Create async UDP socket for tokio
+pub async fn create_async_socket(config: &NetworkConfig) -> Result<TokioUdpSocket, NetworkError> {
+    let std_socket = create_socket(config)?;
+    TokioUdpSocket::from_std(std_socket)
+        .map_err(|e| NetworkError::BindFailed(e.to_string()))
+}
+
+/// Configure socket options for low-latency audio
+fn configure_socket(socket: &Socket, config: &NetworkConfig) -> Result<(), NetworkError> {
+    // Allow address reuse; a shared port would never look taken, so not with a port range
+    if config.reuse_addr && config.port_range == 0 {
+        socket.set_reuse_address(true)
+            .map_err(|e| NetworkError::BindFailed(format!("Failed to set SO_REUSEADDR: {}", e)))?;
+    }
+    
+    // Set send buffer size
+    socket.set_send_buffer_size(config.send_buffer_size)
+        .map_err(|e| NetworkError::BindFailed(format!("Failed to set send buffer: {}", e)))?;
+    
+    // Set receive buffer size
+    socket.set_recv_buffer_size(config.recv_buffer_size)
+        .map_err(|e| NetworkError::BindFailed(format!("Failed to set recv buffer: {}", e)))?;
+    
+    // Disable Nagle's algorithm (though UDP doesn't have it, this is for consistency)
+    // Set broadcast (useful for local network discovery)
+    socket.set_broadcast(true)
+        .map_err(|e| NetworkError::BindFailed(format!("Failed to set broadcast: {}", e)))?;
+    
+    // Don't fragment, so MTU probes larger than the path are lost instead of
+    // split by IP; oversized audio is fragmented by the protocol instead
+    #[cfg(target_os = "linux")]
+    {
+        use std::os::fd::AsRawFd;
+        let probe = libc::IP_PMTUDISC_PROBE;
+        // SAFETY: the option value is a c_int that outlives the call
+        let result = unsafe {
+            libc::setsockopt(
+                socket.as_raw_fd(),
+                libc::IPPROTO_IP,
+                libc::IP_MTU_DISCOVER,
+                &probe as *const libc::c_int as *const libc::c_void,
+                std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+            )
+        };
+        if result != 0 {
+            tracing::debug!("Failed to set IP_MTU_DISCOVER: {}", io::Error::last_os_error());
+        }
+    }
+    
+    #[cfg(target_os = "windows")]
+    {
+        // Windows-specific: Set exclusive address use to prevent port hijacking
+        // This is the opposite of SO_REUSEADDR behavior on Windows
+        // socket.set_exclusive_address_use(true)?;
+    }
+    
+    Ok(())
+}
+
+/// High-performance packet sender
+pub struct PacketSender {
+    socket: StdUdpSocket,
+    target: SocketAddr,
+    packets_sent: std::sync::atomic::AtomicU64,
+    bytes_sent: std::sync::atomic::AtomicU64,
+}
+
+impl PacketSender {
+    pub fn new(socket: StdUdpSocket, target: SocketAddr) -> Self {
+        Self {
+            socket,
+            target,
+            packets_sent: std::sync::atomic::AtomicU64::new(0),
+            bytes_sent: std::sync::atomic::AtomicU64::new(0),
+        }
+    }
+    
+    /// Send packet to target
+    pub fn send(&self, data: &[u8]) -> io::Result<usize> {
+        let sent = self.socket.send_to(data, self.target)?;
+        self.packets_sent.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        self.bytes_sent.fetch_add(sent as u64, std::sync::atomic::Ordering::Relaxed);
+        Ok(sent)
+    }
+    
+    /// Send packet to another port or host than the target
+    pub fn send_to(&self, data: &[u8], to: SocketAddr) -> io::Result<usize> {
+        let sent = self.socket.send_to(data, to)?;
+        self.packets_sent.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        self.bytes_sent.fetch_add(sent as u64, std::sync::atomic::Ordering::Relaxed);
+        Ok(sent)
+    }
+    
+    /// Get packets sent count
+    pub fn packets_sent(&self) -> u64 {
+        self.packets_sent.load(std::sync::atomic::Ordering::Relaxed)
+    }
+    
+    /// Get bytes sent count
+    pub fn bytes_sent(&self) -> u64 {
+        self.bytes_sent.load(std::sync::atomic::Ordering::Relaxed)
+    }
+    
+    /// Update target address
+    pub fn set_target(&mut self, target: SocketAddr) {
+        self.target = target;
+    }
+    
+    /// Get target address
+    pub fn target(&self) -> SocketAddr {
+        self.target
+    }
+}
+
+/// High-performance packet receiver
+pub struct PacketReceiver {
+    socket: StdUdpSocket,
+    recv_buffer: Vec<u8>,
+    packets_received: std::sync::atomic::AtomicU64,
+    bytes_received: std::sync::atomic::AtomicU64,
+}
+
+impl PacketReceiver {
+    pub fn new(socket: StdUdpSocket, buffer_size: usize) -> Self {
+        Self {
+            socket,
+            recv_buffer: vec![0u8; buffer_size],
+            packets_received: std::sync::atomic::AtomicU64::new(0),
+            bytes_received: std::sync::atomic::AtomicU64::new(0),
+        }
+    }
+    
+    /// Receive packet (blocking)
+    pub fn recv(&mut self) -> io::Result<(&[u8], SocketAddr)> {
+        let (size, addr) = self.socket.recv_from(&mut self.recv_buffer)?;
+        self.packets_received.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        self.bytes_received.fetch_add(size as u64, std::sync::atomic::Ordering::Relaxed);
+        Ok((&self.recv_buffer[..size], addr))
+    }
+    
+    /// Try to receive packet (non-blocking)
+    pub fn try_recv(&mut self) -> io::Result<Option<(&[u8], SocketAddr)>> {
+        match self.socket.recv_from(&mut self.recv_buffer) {
+            Ok((size, addr)) => {
+                self.packets_received.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                self.bytes_received.fetch_add(size as u64, std::sync::atomic::Ordering::Relaxed);
+                Ok(Some((&self.recv_buffer[..size], addr)))
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+    
+    /// Get packets received count
+    pub fn packets_received(&self) -> u64 {
+        self.packets_received.load(std::sync::atomic::Ordering::Relaxed)
+    }
+    
+    /// Get bytes received count
+    pub fn bytes_received(&self) -> u64 {
+        self.bytes_received.load(std::sync::atomic::Ordering::Relaxed)
+    }
+}
+
+/// One datagram read by a [`BatchReceiver`]
+pub struct Datagram<'a> {
+    pub data: &'a [u8],
+    pub from: SocketAddr,
+    /// Time since the kernel stamped it, where it was
+    pub age: Option<Duration>,
+}
+
+enum Backend {
+    #[cfg(target_os = "linux")]
+    IoUring(uring::Ring),
+    #[cfg(windows)]
+    Overlapped(overlapped::Port),
+}
+
+/// Completion-based reads of a set of sockets
+///
+/// Reads stay queued in the kernel for every socket, so one wait returns
+/// whatever arrived on any of them. The sockets must not be read any
+/// other way once handed over.
+pub struct BatchReceiver {
+    backend: Backend,
+}
+
+impl BatchReceiver {
+    /// Start reading `sockets` through `backend`; fails where it isn't available
+    pub fn new(backend: ReceiveBackend, sockets: &[Arc<StdUdpSocket>]) -> io::Result<Self> {
+        let backend = match backend {
+            #[cfg(target_os = "linux")]
+            ReceiveBackend::IoUring => Backend::IoUring(uring::Ring::new(sockets)?),
+            #[cfg(windows)]
+            ReceiveBackend::Overlapped => Backend::Overlapped(overlapped::Port::new(sockets)?),
+            other => {
+                let _ = sockets;
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    format!("{:?} receive backend is not available on this platform", other),
+                ));
+            }
+        };
+        Ok(Self { backend })
+    }
+
+    /// Wait up to `timeout` and hand every datagram that arrived to `f`; returns how many
+    pub fn receive(&mut self, timeout: Duration, f: impl FnMut(Datagram<'_>)) -> io::Result<usize> {
+        match &mut self.backend {
+            #[cfg(target_os = "linux")]
+            Backend::IoUring(ring) => ring.receive(timeout, f),
+            #[cfg(windows)]
+            Backend::Overlapped(port) => port.receive(timeout, f),
+        }
+    }
+}
+
+/// Network statistics
+#[derive(Debug, Clone, Default)]
+pub struct NetworkStats {
+    pub packets_sent: u64,
+    pub packets_received: u64,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    pub packets_lost: u64,
+    pub rtt_ms: f32,
+    pub jitter_ms: f32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_socket_creation() {
+        let config = NetworkConfig {
+            bind_address: "127.0.0.1".to_string(),
+            udp_port: 0, // Let OS assign port
+            ..Default::default()
+        };
+        
+        let socket = create_socket(&config);
+        assert!(socket.is_ok());
+    }
+    
+    #[test]
+    fn test_port_fallback() {
+        assert_eq!(port_candidates(5000, 2).collect::<Vec<_>>(), vec![5000, 5001, 5002]);
+        assert_eq!(port_candidates(0, 5).collect::<Vec<_>>(), vec![0]);
+        assert_eq!(port_candidates(u16::MAX, 3).collect::<Vec<_>>(), vec![u16::MAX]);
+        
+        let taken = StdUdpSocket::bind("127.0.0.1:0").unwrap();
+        let port = taken.local_addr().unwrap().port();
+        let mut config = NetworkConfig {
+            bind_address: "127.0.0.1".to_string(),
+            udp_port: port,
+            port_range: 0,
+            ..Default::default()
+        };
+        assert!(create_socket(&config).is_err());
+        
+        config.port_range = 10;
+        let socket = create_socket(&config).unwrap();
+        let chosen = socket.local_addr().unwrap().port();
+        assert!(chosen > port && chosen <= port + 10);
+    }
+}
@@ -0,0 +1,194 @@
+//! Overlapped receive backend (Windows)
+//!
+//! Every socket keeps [`SLOTS_PER_SOCKET`] overlapped `WSARecvFrom` calls
+//! outstanding, all completing to one I/O completion port. A single
+//! `GetQueuedCompletionStatusEx` then collects whatever arrived on any
+//! track socket, and each finished slot is reissued straight away.
+
+use std::io;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6, UdpSocket};
+use std::os::windows::io::AsRawSocket;
+use std::sync::Arc;
+use std::time::Duration;
+
+use windows::core::PSTR;
+use windows::Win32::Foundation::{CloseHandle, HANDLE, INVALID_HANDLE_VALUE, WAIT_TIMEOUT};
+use windows::Win32::Networking::WinSock::{WSAGetLastError, WSARecvFrom, SOCKADDR, SOCKET, WSABUF, WSA_IO_PENDING};
+use windows::Win32::System::IO::{CancelIoEx, CreateIoCompletionPort, GetQueuedCompletionStatusEx, OVERLAPPED, OVERLAPPED_ENTRY};
+
+use super::Datagram;
+
+/// Reads kept outstanding per socket
+const SLOTS_PER_SOCKET: usize = 64;
+
+/// Largest datagram a slot takes
+const SLOT_SIZE: usize = 2048;
+
+/// Completions collected per wait
+const BATCH: usize = 128;
+
+/// One outstanding read with the buffers Windows fills
+///
+/// `overlapped` comes first so a completion's OVERLAPPED pointer is the slot.
+#[repr(C)]
+struct Slot {
+    overlapped: OVERLAPPED,
+    socket: usize,
+    buf: [u8; SLOT_SIZE],
+    addr: [u8; 128],
+    addr_len: i32,
+    flags: u32,
+}
+
+/// Sockets read through one I/O completion port
+pub struct Port {
+    port: HANDLE,
+    sockets: Vec<Arc<UdpSocket>>,
+    /// Boxed once and never resized, so the pointers handed to Windows stay valid
+    slots: Box<[Slot]>,
+    /// Reads issued and not yet completed
+    outstanding: usize,
+}
+
+// SAFETY: the slots and port are owned by the Port alone, wherever it is moved
+unsafe impl Send for Port {}
+
+impl Port {
+    /// Associate `sockets` with a new completion port and issue every read
+    ///
+    /// The sockets are switched to blocking mode, as overlapped reads on a
+    /// non-blocking socket fail at once instead of pending.
+    pub fn new(sockets: &[Arc<UdpSocket>]) -> io::Result<Self> {
+        // SAFETY: creates a fresh port owned by the Port
+        let port = unsafe { CreateIoCompletionPort(INVALID_HANDLE_VALUE, None, 0, 1) }?;
+        let slots = (0..sockets.len() * SLOTS_PER_SOCKET)
+            .map(|index| Slot {
+                overlapped: OVERLAPPED::default(),
+                socket: index / SLOTS_PER_SOCKET,
+                buf: [0; SLOT_SIZE],
+                addr: [0; 128],
+                addr_len: 0,
+                flags: 0,
+            })
+            .collect();
+        let mut this = Self { port, sockets: sockets.to_vec(), slots, outstanding: 0 };
+        for (index, socket) in sockets.iter().enumerate() {
+            socket.set_nonblocking(false)?;
+            // SAFETY: the socket outlives the port through the Arc held here
+            unsafe { CreateIoCompletionPort(HANDLE(socket.as_raw_socket() as isize), this.port, index, 0) }?;
+        }
+        for index in 0..this.slots.len() {
+            this.issue(index)?;
+        }
+        Ok(this)
+    }
+
+    /// Start slot `index` reading its socket's next datagram
+    fn issue(&mut self, index: usize) -> io::Result<()> {
+        let socket = SOCKET(self.sockets[self.slots[index].socket].as_raw_socket() as usize);
+        let slot = &mut self.slots[index];
+        slot.overlapped = OVERLAPPED::default();
+        slot.addr_len = slot.addr.len() as i32;
+        slot.flags = 0;
+        let buffer = WSABUF { len: SLOT_SIZE as u32, buf: PSTR(slot.buf.as_mut_ptr()) };
+        // SAFETY: the slot stays put until its completion is dequeued
+        let result = unsafe {
+            WSARecvFrom(
+                socket,
+                &[buffer],
+                None,
+                &mut slot.flags,
+                Some(slot.addr.as_mut_ptr().cast::<SOCKADDR>()),
+                Some(&mut slot.addr_len),
+                Some(&mut slot.overlapped),
+                None,
+            )
+        };
+        // Immediate success still posts a completion
+        if result != 0 && unsafe { WSAGetLastError() } != WSA_IO_PENDING {
+            return Err(io::Error::last_os_error());
+        }
+        self.outstanding += 1;
+        Ok(())
+    }
+
+    /// Wait up to `timeout` for completed reads and hand each datagram to
+    /// `f`; returns how many there were
+    pub fn receive(&mut self, timeout: Duration, mut f: impl FnMut(Datagram<'_>)) -> io::Result<usize> {
+        let mut entries = [OVERLAPPED_ENTRY::default(); BATCH];
+        let mut count = 0u32;
+        // SAFETY: entries and count outlive the call
+        let waited = unsafe {
+            GetQueuedCompletionStatusEx(self.port, &mut entries, &mut count, timeout.as_millis() as u32, false)
+        };
+        if let Err(e) = waited {
+            if e.code() == WAIT_TIMEOUT.to_hresult() {
+                return Ok(0);
+            }
+            return Err(e.into());
+        }
+
+        let base = self.slots.as_ptr() as usize;
+        let mut received = 0;
+        for entry in &entries[..count as usize] {
+            self.outstanding -= 1;
+            let index = (entry.lpOverlapped as usize - base) / std::mem::size_of::<Slot>();
+            // Internal holds the NTSTATUS; zero is success
+            if entry.Internal == 0 {
+                let slot = &self.slots[index];
+                if let Some(from) = parse_addr(&slot.addr[..slot.addr_len.max(0) as usize]) {
+                    let size = entry.dwNumberOfBytesTransferred as usize;
+                    f(Datagram { data: &slot.buf[..size], from, age: None });
+                    received += 1;
+                }
+            }
+            // A reset from an ICMP unreachable fails one read; keep the slot going
+            if let Err(e) = self.issue(index) {
+                tracing::debug!("Overlapped receive failed: {}", e);
+            }
+        }
+        Ok(received)
+    }
+}
+
+impl Drop for Port {
+    fn drop(&mut self) {
+        // Cancel every read and wait for them to finish with the slots
+        for socket in &self.sockets {
+            // SAFETY: cancels only reads this port issued on the socket
+            let _ = unsafe { CancelIoEx(HANDLE(socket.as_raw_socket() as isize), None) };
+        }
+        let mut entries = [OVERLAPPED_ENTRY::default(); BATCH];
+        while self.outstanding > 0 {
+            let mut count = 0u32;
+            // SAFETY: as in receive
+            if unsafe { GetQueuedCompletionStatusEx(self.port, &mut entries, &mut count, 1000, false) }.is_err() {
+                break;
+            }
+            self.outstanding -= count as usize;
+        }
+        // SAFETY: the port handle is owned here and closed once
+        let _ = unsafe { CloseHandle(self.port) };
+    }
+}
+
+/// Address from a SOCKADDR_IN / SOCKADDR_IN6 in wire layout
+fn parse_addr(raw: &[u8]) -> Option<SocketAddr> {
+    const AF_INET: u16 = 2;
+    const AF_INET6: u16 = 23;
+    let family = u16::from_ne_bytes(raw.get(0..2)?.try_into().ok()?);
+    let port = u16::from_be_bytes(raw.get(2..4)?.try_into().ok()?);
+    match family {
+        AF_INET => {
+            let ip: [u8; 4] = raw.get(4..8)?.try_into().ok()?;
+            Some(SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::from(ip), port)))
+        }
+        AF_INET6 => {
+            let flow = u32::from_ne_bytes(raw.get(4..8)?.try_into().ok()?);
+            let ip: [u8; 16] = raw.get(8..24)?.try_into().ok()?;
+            let scope = u32::from_ne_bytes(raw.get(24..28)?.try_into().ok()?);
+            Some(SocketAddr::V6(SocketAddrV6::new(Ipv6Addr::from(ip), port, flow, scope)))
+        }
+        _ => None,
+    }
+}
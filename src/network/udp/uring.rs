@@ -0,0 +1,504 @@
+//! io_uring receive backend (Linux 5.11 or later)
+//!
+//! Every socket keeps [`SLOTS_PER_SOCKET`] `recvmsg` requests queued in the
+//! ring, each with buffers of its own. One `io_uring_enter` call both
+//! hands the slots read since the last call back to the kernel and waits
+//! for the next datagrams, so a burst across many track sockets costs one
+//! system call rather than one per datagram and per idle socket.
+//!
+//! The ring is driven through the raw system calls; the structures below
+//! mirror `<linux/io_uring.h>`.
+
+use std::io;
+use std::mem::{size_of, zeroed};
+use std::net::UdpSocket;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use super::Datagram;
+use crate::network::timestamp;
+
+const IORING_OFF_SQ_RING: libc::off_t = 0;
+const IORING_OFF_CQ_RING: libc::off_t = 0x800_0000;
+const IORING_OFF_SQES: libc::off_t = 0x1000_0000;
+const IORING_FEAT_SINGLE_MMAP: u32 = 1 << 0;
+const IORING_FEAT_EXT_ARG: u32 = 1 << 8;
+const IORING_ENTER_GETEVENTS: libc::c_uint = 1 << 0;
+const IORING_ENTER_EXT_ARG: libc::c_uint = 1 << 3;
+const IORING_OP_RECVMSG: u8 = 10;
+const IORING_OP_ASYNC_CANCEL: u8 = 14;
+
+/// `user_data` of the cancel requests sent when the ring is dropped
+const CANCEL_TAG: u64 = u64::MAX;
+
+/// Longest a dropped ring waits for the kernel to give its slots back
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Requests kept queued per socket
+const SLOTS_PER_SOCKET: usize = 64;
+
+/// Largest datagram a slot takes
+const SLOT_SIZE: usize = 2048;
+
+#[repr(C)]
+#[derive(Default)]
+struct SqRingOffsets {
+    head: u32,
+    tail: u32,
+    ring_mask: u32,
+    ring_entries: u32,
+    flags: u32,
+    dropped: u32,
+    array: u32,
+    resv1: u32,
+    user_addr: u64,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct CqRingOffsets {
+    head: u32,
+    tail: u32,
+    ring_mask: u32,
+    ring_entries: u32,
+    overflow: u32,
+    cqes: u32,
+    flags: u32,
+    resv1: u32,
+    user_addr: u64,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct Params {
+    sq_entries: u32,
+    cq_entries: u32,
+    flags: u32,
+    sq_thread_cpu: u32,
+    sq_thread_idle: u32,
+    features: u32,
+    wq_fd: u32,
+    resv: [u32; 3],
+    sq_off: SqRingOffsets,
+    cq_off: CqRingOffsets,
+}
+
+/// Submission queue entry, laid out for `recvmsg`
+#[repr(C)]
+#[derive(Default)]
+struct Sqe {
+    opcode: u8,
+    flags: u8,
+    ioprio: u16,
+    fd: i32,
+    off: u64,
+    /// The `msghdr`
+    addr: u64,
+    /// Number of `msghdr`s (1)
+    len: u32,
+    msg_flags: u32,
+    user_data: u64,
+    buf_index: u16,
+    personality: u16,
+    file_index: u32,
+    addr3: u64,
+    pad: u64,
+}
+
+#[repr(C)]
+struct Cqe {
+    user_data: u64,
+    res: i32,
+    flags: u32,
+}
+
+#[repr(C)]
+struct GetEventsArg {
+    sigmask: u64,
+    sigmask_sz: u32,
+    pad: u32,
+    ts: u64,
+}
+
+#[repr(C)]
+struct KernelTimespec {
+    tv_sec: i64,
+    tv_nsec: i64,
+}
+
+/// A shared mapping of ring memory
+struct Mapping {
+    ptr: *mut u8,
+    len: usize,
+}
+
+impl Mapping {
+    fn new(fd: &OwnedFd, len: usize, offset: libc::off_t) -> io::Result<Self> {
+        // SAFETY: a fresh shared mapping of the ring fd; the kernel checks
+        // offset and length
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED | libc::MAP_POPULATE,
+                fd.as_raw_fd(),
+                offset,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self { ptr: ptr.cast(), len })
+    }
+
+    /// Pointer `offset` bytes in, as the kernel's offsets give them
+    fn at<T>(&self, offset: u32) -> *mut T {
+        // SAFETY: offsets come from the kernel and lie within the mapping
+        unsafe { self.ptr.add(offset as usize).cast() }
+    }
+}
+
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        // SAFETY: unmaps exactly what `new` mapped, once
+        unsafe {
+            libc::munmap(self.ptr.cast(), self.len);
+        }
+    }
+}
+
+/// One queued `recvmsg` with the buffers the kernel fills
+struct Slot {
+    /// Index into the ring's sockets
+    socket: usize,
+    buf: [u8; SLOT_SIZE],
+    addr: libc::sockaddr_storage,
+    /// u64s keep the control buffer aligned for cmsghdr
+    control: [u64; 16],
+    iov: libc::iovec,
+    msg: libc::msghdr,
+}
+
+/// Sockets read through one io_uring instance
+pub struct Ring {
+    sockets: Vec<Arc<UdpSocket>>,
+    /// Sockets that were non-blocking before the ring took them
+    nonblocking: Vec<bool>,
+    sq_head: *const AtomicU32,
+    sq_tail: *const AtomicU32,
+    sq_mask: u32,
+    sq_array: *mut u32,
+    sqes: *mut Sqe,
+    cq_head: *const AtomicU32,
+    cq_tail: *const AtomicU32,
+    cq_mask: u32,
+    cqes: *const Cqe,
+    /// Entries queued since the last `io_uring_enter`
+    unsubmitted: u32,
+    // Unmapped before the fd closes
+    _sqe_mapping: Mapping,
+    _cq_mapping: Option<Mapping>,
+    _sq_mapping: Mapping,
+    fd: OwnedFd,
+    /// Boxed once and never resized, so the pointers handed to the kernel
+    /// stay valid; dropped last, after `Drop` has taken them back
+    slots: Box<[Slot]>,
+}
+
+// SAFETY: the raw pointers only refer to the ring mappings and slots the
+// Ring owns; nothing else touches them, wherever the Ring is moved
+unsafe impl Send for Ring {}
+
+impl Ring {
+    /// Set up a ring with every slot of every socket queued
+    ///
+    /// The sockets are switched to blocking mode, or the kernel would
+    /// complete each request at once with `EAGAIN` instead of waiting for
+    /// a datagram; they must only be read through the ring from then on.
+    /// Dropping the ring puts back the mode each socket had.
+    pub fn new(sockets: &[Arc<UdpSocket>]) -> io::Result<Self> {
+        let slots = sockets.len() * SLOTS_PER_SOCKET;
+        let mut params = Params::default();
+        // SAFETY: params is a valid io_uring_params the kernel fills in
+        let fd = unsafe { libc::syscall(libc::SYS_io_uring_setup, slots as u32, &mut params as *mut Params) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: the kernel just returned this descriptor to us alone
+        let fd = unsafe { OwnedFd::from_raw_fd(fd as libc::c_int) };
+        if params.features & IORING_FEAT_EXT_ARG == 0 {
+            return Err(io::Error::new(io::ErrorKind::Unsupported, "io_uring without wait timeouts (Linux 5.11 or later needed)"));
+        }
+
+        let sq_len = params.sq_off.array as usize + params.sq_entries as usize * size_of::<u32>();
+        let cq_len = params.cq_off.cqes as usize + params.cq_entries as usize * size_of::<Cqe>();
+        let single = params.features & IORING_FEAT_SINGLE_MMAP != 0;
+        let sq_mapping = Mapping::new(&fd, if single { sq_len.max(cq_len) } else { sq_len }, IORING_OFF_SQ_RING)?;
+        let cq_mapping = if single { None } else { Some(Mapping::new(&fd, cq_len, IORING_OFF_CQ_RING)?) };
+        let sqe_mapping = Mapping::new(&fd, params.sq_entries as usize * size_of::<Sqe>(), IORING_OFF_SQES)?;
+        let cq = cq_mapping.as_ref().unwrap_or(&sq_mapping);
+
+        let nonblocking = sockets
+            .iter()
+            .map(|socket| {
+                // SAFETY: F_GETFL only reads the flags of a socket we hold
+                let flags = unsafe { libc::fcntl(socket.as_raw_fd(), libc::F_GETFL) };
+                flags >= 0 && flags & libc::O_NONBLOCK != 0
+            })
+            .collect();
+        for socket in sockets {
+            socket.set_nonblocking(false)?;
+        }
+
+        let slots = (0..slots)
+            .map(|index| Slot {
+                socket: index / SLOTS_PER_SOCKET,
+                buf: [0; SLOT_SIZE],
+                // SAFETY: all-zero is a valid value for these C structs
+                addr: unsafe { zeroed() },
+                control: [0; 16],
+                iov: libc::iovec { iov_base: std::ptr::null_mut(), iov_len: 0 },
+                msg: unsafe { zeroed() },
+            })
+            .collect();
+        // SAFETY: each pointer is derived from the kernel's offsets into its mapping
+        let (sq_mask, cq_mask) = unsafe {
+            (*sq_mapping.at::<u32>(params.sq_off.ring_mask), *cq.at::<u32>(params.cq_off.ring_mask))
+        };
+        let mut ring = Self {
+            sockets: sockets.to_vec(),
+            nonblocking,
+            sq_head: sq_mapping.at(params.sq_off.head),
+            sq_tail: sq_mapping.at(params.sq_off.tail),
+            sq_mask,
+            sq_array: sq_mapping.at(params.sq_off.array),
+            sqes: sqe_mapping.at(0),
+            cq_head: cq.at(params.cq_off.head),
+            cq_tail: cq.at(params.cq_off.tail),
+            cq_mask,
+            cqes: cq.at(params.cq_off.cqes),
+            unsubmitted: 0,
+            _sqe_mapping: sqe_mapping,
+            _cq_mapping: cq_mapping,
+            _sq_mapping: sq_mapping,
+            fd,
+            slots,
+        };
+        for index in 0..ring.slots.len() {
+            ring.queue(index);
+        }
+        Ok(ring)
+    }
+
+    /// Queue slot `index` for its socket's next datagram
+    fn queue(&mut self, index: usize) {
+        let fd = self.sockets[self.slots[index].socket].as_raw_fd();
+        let slot = &mut self.slots[index];
+        slot.iov = libc::iovec { iov_base: slot.buf.as_mut_ptr().cast(), iov_len: SLOT_SIZE };
+        slot.msg.msg_name = (&mut slot.addr as *mut libc::sockaddr_storage).cast();
+        slot.msg.msg_namelen = size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+        slot.msg.msg_iov = &mut slot.iov;
+        slot.msg.msg_iovlen = 1;
+        slot.msg.msg_control = slot.control.as_mut_ptr().cast();
+        slot.msg.msg_controllen = size_of::<[u64; 16]>() as _;
+        slot.msg.msg_flags = 0;
+        let sqe = Sqe {
+            opcode: IORING_OP_RECVMSG,
+            fd,
+            addr: &mut slot.msg as *mut libc::msghdr as u64,
+            len: 1,
+            user_data: index as u64,
+            ..Default::default()
+        };
+        // There are as many entries as slots, so one is always free
+        self.push(sqe);
+    }
+
+    /// Put an entry on the submission queue
+    fn push(&mut self, sqe: Sqe) {
+        // SAFETY: the tail index is masked into the submission arrays, and
+        // only this thread writes the tail
+        unsafe {
+            let tail = (*self.sq_tail).load(Ordering::Relaxed);
+            let entry = tail & self.sq_mask;
+            self.sqes.add(entry as usize).write(sqe);
+            self.sq_array.add(entry as usize).write(entry);
+            (*self.sq_tail).store(tail.wrapping_add(1), Ordering::Release);
+        }
+        self.unsubmitted += 1;
+    }
+
+    /// Give back the slots read last time, wait up to `timeout` for
+    /// datagrams and hand each to `f`; returns how many there were
+    pub fn receive(&mut self, timeout: Duration, mut f: impl FnMut(Datagram<'_>)) -> io::Result<usize> {
+        self.enter(timeout)?;
+
+        let mut received = 0;
+        // SAFETY: completions between head and the kernel's tail are
+        // written; each names a slot the kernel has finished with
+        unsafe {
+            let mut head = (*self.cq_head).load(Ordering::Relaxed);
+            let tail = (*self.cq_tail).load(Ordering::Acquire);
+            while head != tail {
+                let cqe = self.cqes.add((head & self.cq_mask) as usize).read();
+                head = head.wrapping_add(1);
+                let index = cqe.user_data as usize;
+                if cqe.res >= 0 {
+                    let slot = &self.slots[index];
+                    let from = socket2::SockAddr::new(slot.addr, slot.msg.msg_namelen).as_socket();
+                    if let Some(from) = from {
+                        let age = timestamp::control_age(&slot.msg);
+                        f(Datagram { data: &slot.buf[..cqe.res as usize], from, age });
+                        received += 1;
+                    }
+                } else if cqe.res != -libc::EINTR {
+                    tracing::debug!("io_uring receive failed: {}", io::Error::from_raw_os_error(-cqe.res));
+                }
+                self.queue(index);
+            }
+            (*self.cq_head).store(head, Ordering::Release);
+        }
+        Ok(received)
+    }
+
+    /// Submit the queued entries and wait up to `timeout` for a completion
+    fn enter(&mut self, timeout: Duration) -> io::Result<()> {
+        let ts = KernelTimespec { tv_sec: timeout.as_secs() as i64, tv_nsec: timeout.subsec_nanos() as i64 };
+        let arg = GetEventsArg { sigmask: 0, sigmask_sz: 0, pad: 0, ts: &ts as *const KernelTimespec as u64 };
+        // SAFETY: arg and ts outlive the call; the queued entries point into
+        // slots the ring owns
+        let submitted = unsafe {
+            libc::syscall(
+                libc::SYS_io_uring_enter,
+                self.fd.as_raw_fd(),
+                self.unsubmitted,
+                1 as libc::c_uint,
+                IORING_ENTER_GETEVENTS | IORING_ENTER_EXT_ARG,
+                &arg as *const GetEventsArg,
+                size_of::<GetEventsArg>(),
+            )
+        };
+        if submitted < 0 {
+            let e = io::Error::last_os_error();
+            if !matches!(e.raw_os_error(), Some(libc::ETIME) | Some(libc::EINTR)) {
+                return Err(e);
+            }
+        } else {
+            self.unsubmitted -= submitted as u32;
+        }
+        // SAFETY: the kernel fills in sq_head; read only to stay in step
+        let _ = unsafe { (*self.sq_head).load(Ordering::Acquire) };
+        Ok(())
+    }
+}
+
+impl Drop for Ring {
+    /// Cancel every queued `recvmsg` and wait until the kernel has finished
+    /// with the slots, so it never writes into freed buffers
+    fn drop(&mut self) {
+        // Submit anything still queued first: all slots are then in flight
+        // and the submission queue has room for a cancel per slot
+        let mut pending = self.slots.len();
+        let drained = self.enter(Duration::ZERO).is_ok() && {
+            for index in 0..self.slots.len() {
+                self.push(Sqe {
+                    opcode: IORING_OP_ASYNC_CANCEL,
+                    fd: -1,
+                    addr: index as u64,
+                    user_data: CANCEL_TAG,
+                    ..Default::default()
+                });
+            }
+            let deadline = Instant::now() + DRAIN_TIMEOUT;
+            while pending > 0 && Instant::now() < deadline {
+                if self.enter(Duration::from_millis(100)).is_err() {
+                    break;
+                }
+                // SAFETY: as in `receive`; slots are not queued again
+                unsafe {
+                    let mut head = (*self.cq_head).load(Ordering::Relaxed);
+                    let tail = (*self.cq_tail).load(Ordering::Acquire);
+                    while head != tail {
+                        let cqe = self.cqes.add((head & self.cq_mask) as usize).read();
+                        head = head.wrapping_add(1);
+                        if cqe.user_data != CANCEL_TAG {
+                            pending -= 1;
+                        }
+                    }
+                    (*self.cq_head).store(head, Ordering::Release);
+                }
+            }
+            pending == 0
+        };
+        if !drained {
+            // Better to leak the buffers than let the kernel write into freed memory
+            tracing::warn!("io_uring kept {} receive slots at shutdown; leaking them", pending);
+            std::mem::forget(std::mem::take(&mut self.slots));
+        }
+
+        for (socket, &nonblocking) in self.sockets.iter().zip(&self.nonblocking) {
+            if nonblocking {
+                let _ = socket.set_nonblocking(true);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ring_receive() {
+        let sockets: Vec<_> = (0..2).map(|_| Arc::new(UdpSocket::bind("127.0.0.1:0").unwrap())).collect();
+        // Kernels built or configured without io_uring have nothing to test
+        let Ok(mut ring) = Ring::new(&sockets) else {
+            return;
+        };
+        let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+
+        // Nothing queued: the wait times out
+        assert_eq!(ring.receive(Duration::from_millis(10), |_| {}).unwrap(), 0);
+
+        // More datagrams than slots, spread over both sockets
+        let total = SLOTS_PER_SOCKET * 3;
+        let mut seen = Vec::new();
+        for i in 0..total {
+            let to = sockets[i % 2].local_addr().unwrap();
+            sender.send_to(&(i as u32).to_le_bytes(), to).unwrap();
+            if i % SLOTS_PER_SOCKET == SLOTS_PER_SOCKET - 1 {
+                while seen.len() <= i {
+                    ring.receive(Duration::from_secs(1), |datagram| {
+                        assert_eq!(datagram.from, sender.local_addr().unwrap());
+                        seen.push(u32::from_le_bytes(datagram.data.try_into().unwrap()));
+                    })
+                    .unwrap();
+                }
+            }
+        }
+        seen.sort_unstable();
+        assert_eq!(seen, (0..total as u32).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_ring_drop() {
+        let socket = Arc::new(UdpSocket::bind("127.0.0.1:0").unwrap());
+        socket.set_nonblocking(true).unwrap();
+        let Ok(ring) = Ring::new(std::slice::from_ref(&socket)) else {
+            return;
+        };
+        drop(ring);
+
+        // The socket is non-blocking again, and no request is left to take datagrams
+        let mut buf = [0u8; 16];
+        assert_eq!(socket.recv_from(&mut buf).unwrap_err().kind(), io::ErrorKind::WouldBlock);
+        let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+        sender.send_to(b"after", socket.local_addr().unwrap()).unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+        let (len, _) = socket.recv_from(&mut buf).unwrap();
+        assert_eq!(&buf[..len], b"after");
+    }
+}
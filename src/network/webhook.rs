@@ -0,0 +1,156 @@
+//! Webhook client
+//!
+//! Posts JSON documents for [`crate::notify`] and [`crate::hooks`].
+//!
+//! Plain `http://` URLs always work; `https://` (Discord, Slack) needs the
+//! `https` feature, on by default, and verifies against the system's
+//! trusted roots.
+
+use serde::Serialize;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use crate::error::NetworkError;
+
+/// Request timeout for a single webhook call
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Parsed webhook URL
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct WebhookUrl {
+    /// `https://`
+    pub(crate) tls: bool,
+    /// host:port to connect to
+    pub(crate) addr: String,
+    /// Host header value
+    pub(crate) host: String,
+    pub(crate) path: String,
+}
+
+impl WebhookUrl {
+    /// Host name without the port, for certificate checks
+    fn server_name(&self) -> &str {
+        match self.host.rsplit_once(':') {
+            Some((name, port)) if port.parse::<u16>().is_ok() => name,
+            _ => &self.host,
+        }
+    }
+}
+
+/// Split an `http[s]://host[:port]/path` URL
+pub(crate) fn parse_url(url: &str) -> Result<WebhookUrl, NetworkError> {
+    let (tls, rest) = if let Some(rest) = url.strip_prefix("http://") {
+        (false, rest)
+    } else if let Some(rest) = url.strip_prefix("https://") {
+        (true, rest)
+    } else {
+        return Err(NetworkError::ConnectionFailed(format!("Unsupported URL: {}", url)));
+    };
+
+    let (authority, path) = match rest.find('/') {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, "/"),
+    };
+    if authority.is_empty() {
+        return Err(NetworkError::ConnectionFailed(format!("Missing host in URL: {}", url)));
+    }
+
+    let host = authority.to_string();
+    let addr = if authority.rsplit_once(':').is_some_and(|(_, port)| port.parse::<u16>().is_ok()) {
+        host.clone()
+    } else {
+        format!("{}:{}", authority, if tls { 443 } else { 80 })
+    };
+    Ok(WebhookUrl { tls, addr, host, path: path.to_string() })
+}
+
+/// Write the request and check the response status
+async fn send_request<S>(mut stream: S, url: &WebhookUrl, body: &[u8]) -> Result<(), NetworkError>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let head = format!(
+        "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        url.path,
+        url.host,
+        body.len()
+    );
+    stream.write_all(head.as_bytes()).await.map_err(|e| NetworkError::SendFailed(e.to_string()))?;
+    stream.write_all(body).await.map_err(|e| NetworkError::SendFailed(e.to_string()))?;
+    stream.flush().await.map_err(|e| NetworkError::SendFailed(e.to_string()))?;
+
+    // Only the status line matters
+    let mut response = [0u8; 64];
+    let n = stream
+        .read(&mut response)
+        .await
+        .map_err(|e| NetworkError::ReceiveFailed(e.to_string()))?;
+    let status_line = String::from_utf8_lossy(&response[..n]);
+    let status = status_line.split_whitespace().nth(1).unwrap_or("");
+    if !status.starts_with('2') {
+        return Err(NetworkError::SendFailed(format!("Webhook returned status {}", status)));
+    }
+    Ok(())
+}
+
+/// POST a JSON body to an HTTP(S) URL
+pub async fn post_json<T: Serialize>(url: &str, body: &T) -> Result<(), NetworkError> {
+    let url = parse_url(url)?;
+    let body = serde_json::to_vec(body).map_err(|e| NetworkError::SendFailed(e.to_string()))?;
+
+    let request = async {
+        let stream = TcpStream::connect(&url.addr)
+            .await
+            .map_err(|e| NetworkError::ConnectionFailed(e.to_string()))?;
+
+        if !url.tls {
+            return send_request(stream, &url, &body).await;
+        }
+
+        #[cfg(feature = "https")]
+        {
+            let stream = crate::network::tls::connect(stream, url.server_name())
+                .await
+                .map_err(|e| NetworkError::ConnectionFailed(e.to_string()))?;
+            send_request(stream, &url, &body).await
+        }
+        #[cfg(not(feature = "https"))]
+        {
+            drop(stream);
+            Err(NetworkError::ConnectionFailed(format!(
+                "https webhook {} needs the https feature",
+                url.server_name()
+            )))
+        }
+    };
+
+    tokio::time::timeout(WEBHOOK_TIMEOUT, request)
+        .await
+        .map_err(|_| NetworkError::Timeout)?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_url() {
+        let url = parse_url("http://10.0.0.5:8123/api/hook").unwrap();
+        assert_eq!(url.addr, "10.0.0.5:8123");
+        assert_eq!(url.host, "10.0.0.5:8123");
+        assert_eq!(url.path, "/api/hook");
+        assert_eq!(url.server_name(), "10.0.0.5");
+
+        let url = parse_url("http://alerts.lan").unwrap();
+        assert_eq!(url.addr, "alerts.lan:80");
+        assert_eq!(url.path, "/");
+
+        let url = parse_url("https://discord.com/api/webhooks/1/abc").unwrap();
+        assert!(url.tls);
+        assert_eq!(url.addr, "discord.com:443");
+        assert_eq!(url.server_name(), "discord.com");
+
+        assert!(parse_url("ftp://example.com/").is_err());
+    }
+}
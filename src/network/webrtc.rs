@@ -0,0 +1,330 @@
+//! WebRTC output for browser receivers
+//!
+//! Publishes selected tracks to browsers so a machine with nothing but
+//! a web browser can monitor the stream. Received Opus packets are
+//! forwarded as-is over SRTP, no decode/re-encode is involved.
+//!
+//! ## Signaling
+//!
+//! A single HTTP exchange on the web UI server:
+//!
+//! ```text
+//! POST   /api/webrtc/offer          { "sdp": "<offer>", "tracks": [0, 2], "layer": 1 }
+//!                                -> { "session_id": "...", "sdp": "<answer>" }
+//! DELETE /api/webrtc/sessions/:id
+//! ```
+//!
+//! The browser should add one `recvonly` audio transceiver per requested
+//! track before creating its offer and wait for ICE gathering to finish,
+//! since candidates are not trickled.
+//!
+//! ## Simulcast
+//!
+//! A session can take a lower-bitrate simulcast layer instead of the
+//! primary encoding (`layer`, defaulting to the configured one). Tracks the
+//! sender doesn't encode that layer for fall back to the primary encoding.
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    routing::{delete, post},
+    Json, Router,
+};
+use bytes::Bytes;
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use webrtc::api::media_engine::{MediaEngine, MIME_TYPE_OPUS};
+use webrtc::api::{APIBuilder, API};
+use webrtc::ice_transport::ice_server::RTCIceServer;
+use webrtc::media::Sample;
+use webrtc::peer_connection::configuration::RTCConfiguration;
+use webrtc::peer_connection::peer_connection_state::RTCPeerConnectionState;
+use webrtc::peer_connection::sdp::session_description::RTCSessionDescription;
+use webrtc::peer_connection::RTCPeerConnection;
+use webrtc::rtp_transceiver::rtp_codec::RTCRtpCodecCapability;
+use webrtc::track::track_local::track_local_static_sample::TrackLocalStaticSample;
+use webrtc::track::track_local::TrackLocal;
+
+use crate::config::WebRtcConfig;
+use crate::constants::DEFAULT_SAMPLE_RATE;
+use crate::error::NetworkError;
+use crate::tracks::TrackManager;
+use crate::ui::handlers::ApiResponse;
+
+/// A connected browser
+struct WebRtcSession {
+    peer_connection: Arc<RTCPeerConnection>,
+    tracks: HashMap<u8, Arc<TrackLocalStaticSample>>,
+    /// Simulcast layer the browser takes
+    layer: u8,
+    /// One of `max_sessions`, given back when the session is dropped
+    _slot: OwnedSemaphorePermit,
+}
+
+/// Time after its last packet that a simulcast layer is considered gone
+const LAYER_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Publishes received tracks to browser peers
+pub struct WebRtcPublisher {
+    api: API,
+    config: WebRtcConfig,
+    sessions: Arc<DashMap<String, WebRtcSession>>,
+    /// Free session slots, taken before an offer is answered
+    slots: Arc<Semaphore>,
+    /// Tracks being received, for their channel count
+    track_manager: Arc<TrackManager>,
+    /// When a packet of each simulcast layer last arrived, by track and layer
+    layer_seen: DashMap<(u8, u8), Instant>,
+}
+
+impl WebRtcPublisher {
+    /// Create a new publisher
+    pub fn new(config: WebRtcConfig, track_manager: Arc<TrackManager>) -> Result<Self, NetworkError> {
+        let mut media_engine = MediaEngine::default();
+        media_engine
+            .register_default_codecs()
+            .map_err(|e| NetworkError::ConnectionFailed(e.to_string()))?;
+
+        let api = APIBuilder::new().with_media_engine(media_engine).build();
+
+        Ok(Self {
+            api,
+            slots: Arc::new(Semaphore::new(config.max_sessions)),
+            config,
+            sessions: Arc::new(DashMap::new()),
+            track_manager,
+            layer_seen: DashMap::new(),
+        })
+    }
+
+    /// Answer a browser offer, returning the session ID and SDP answer
+    pub async fn accept_offer(
+        &self,
+        offer_sdp: String,
+        track_ids: &[u8],
+        layer: Option<u8>,
+    ) -> Result<(String, String), NetworkError> {
+        let layer = layer.unwrap_or(self.config.layer);
+        if track_ids.is_empty() {
+            return Err(NetworkError::ConnectionFailed("No tracks requested".to_string()));
+        }
+
+        // Held from here on, so offers answered concurrently can't overshoot the limit
+        let slot = self.slots.clone().try_acquire_owned().map_err(|_| {
+            NetworkError::ConnectionFailed(format!("Maximum sessions reached: {}", self.config.max_sessions))
+        })?;
+
+        let rtc_config = RTCConfiguration {
+            ice_servers: if self.config.ice_servers.is_empty() {
+                Vec::new()
+            } else {
+                vec![RTCIceServer {
+                    urls: self.config.ice_servers.clone(),
+                    ..Default::default()
+                }]
+            },
+            ..Default::default()
+        };
+
+        let peer_connection = Arc::new(
+            self.api
+                .new_peer_connection(rtc_config)
+                .await
+                .map_err(|e| NetworkError::ConnectionFailed(e.to_string()))?,
+        );
+
+        let mut tracks = HashMap::new();
+        for &track_id in track_ids {
+            // Stereo for a track that has not arrived yet
+            let channels = self.track_manager.get_track(track_id).map_or(2, |track| track.config.channels);
+            let track = Arc::new(TrackLocalStaticSample::new(
+                RTCRtpCodecCapability {
+                    mime_type: MIME_TYPE_OPUS.to_owned(),
+                    clock_rate: DEFAULT_SAMPLE_RATE,
+                    channels,
+                    ..Default::default()
+                },
+                format!("track-{}", track_id),
+                "lan-audio".to_owned(),
+            ));
+
+            peer_connection
+                .add_track(Arc::clone(&track) as Arc<dyn TrackLocal + Send + Sync>)
+                .await
+                .map_err(|e| NetworkError::ConnectionFailed(e.to_string()))?;
+
+            tracks.insert(track_id, track);
+        }
+
+        let session_id = uuid::Uuid::new_v4().to_string();
+
+        // Drop the session once the browser goes away
+        let sessions = self.sessions.clone();
+        let id = session_id.clone();
+        peer_connection.on_peer_connection_state_change(Box::new(move |state| {
+            if matches!(
+                state,
+                RTCPeerConnectionState::Failed | RTCPeerConnectionState::Closed
+            ) {
+                tracing::info!("WebRTC session {} ended ({})", id, state);
+                sessions.remove(&id);
+            }
+            Box::pin(async {})
+        }));
+
+        let offer = RTCSessionDescription::offer(offer_sdp)
+            .map_err(|e| NetworkError::ConnectionFailed(e.to_string()))?;
+        peer_connection
+            .set_remote_description(offer)
+            .await
+            .map_err(|e| NetworkError::ConnectionFailed(e.to_string()))?;
+
+        let answer = peer_connection
+            .create_answer(None)
+            .await
+            .map_err(|e| NetworkError::ConnectionFailed(e.to_string()))?;
+
+        // Non-trickle ICE: wait for all candidates to land in the local description
+        let mut gather_complete = peer_connection.gathering_complete_promise().await;
+        peer_connection
+            .set_local_description(answer)
+            .await
+            .map_err(|e| NetworkError::ConnectionFailed(e.to_string()))?;
+        let _ = gather_complete.recv().await;
+
+        let local = peer_connection
+            .local_description()
+            .await
+            .ok_or_else(|| NetworkError::ConnectionFailed("No local description".to_string()))?;
+
+        self.sessions.insert(
+            session_id.clone(),
+            WebRtcSession {
+                peer_connection,
+                tracks,
+                layer,
+                _slot: slot,
+            },
+        );
+
+        tracing::info!("WebRTC session {} started for tracks {:?} (layer {})", session_id, track_ids, layer);
+
+        Ok((session_id, local.sdp))
+    }
+
+    /// Forward an Opus packet of a simulcast layer to every session
+    /// subscribed to the track and layer
+    pub async fn publish(&self, track_id: u8, layer: u8, payload: Bytes) {
+        if self.sessions.is_empty() {
+            return;
+        }
+        if layer != 0 {
+            self.layer_seen.insert((track_id, layer), Instant::now());
+        }
+        let live = |wanted: u8| {
+            self.layer_seen
+                .get(&(track_id, wanted))
+                .is_some_and(|seen| seen.elapsed() < LAYER_TIMEOUT)
+        };
+
+        let samples = opus::packet::get_nb_samples(&payload, DEFAULT_SAMPLE_RATE).unwrap_or(0);
+        let duration = Duration::from_micros(samples as u64 * 1_000_000 / DEFAULT_SAMPLE_RATE as u64);
+
+        // Collect first so no map guard is held across an await
+        let targets: Vec<Arc<TrackLocalStaticSample>> = self.sessions
+            .iter()
+            .filter(|entry| entry.layer == layer || (layer == 0 && !live(entry.layer)))
+            .filter_map(|entry| entry.tracks.get(&track_id).cloned())
+            .collect();
+
+        for track in targets {
+            let sample = Sample {
+                data: payload.clone(),
+                timestamp: SystemTime::now(),
+                duration,
+                ..Default::default()
+            };
+
+            if let Err(e) = track.write_sample(&sample).await {
+                tracing::debug!("WebRTC write failed on track {}: {}", track_id, e);
+            }
+        }
+    }
+
+    /// Close a session
+    pub async fn close_session(&self, session_id: &str) -> bool {
+        if let Some((_, session)) = self.sessions.remove(session_id) {
+            let _ = session.peer_connection.close().await;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Simulcast layers other than 0 that sessions take
+    pub fn layers(&self) -> Vec<u8> {
+        let mut layers: Vec<u8> = self.sessions.iter().map(|entry| entry.layer).filter(|&l| l != 0).collect();
+        layers.sort_unstable();
+        layers.dedup();
+        layers
+    }
+
+    /// Get number of connected browsers
+    pub fn session_count(&self) -> usize {
+        self.sessions.len()
+    }
+}
+
+/// Offer request body
+#[derive(Deserialize)]
+pub struct OfferRequest {
+    pub sdp: String,
+    pub tracks: Vec<u8>,
+    /// Simulcast layer (the configured one if absent)
+    #[serde(default)]
+    pub layer: Option<u8>,
+}
+
+/// Answer response body
+#[derive(Serialize)]
+pub struct OfferResponse {
+    pub session_id: String,
+    pub sdp: String,
+}
+
+/// Signaling routes, to be merged into the web UI router
+pub fn routes(publisher: Arc<WebRtcPublisher>) -> Router {
+    Router::new()
+        .route("/api/webrtc/offer", post(handle_offer))
+        .route("/api/webrtc/sessions/:id", delete(handle_close))
+        .with_state(publisher)
+}
+
+async fn handle_offer(
+    State(publisher): State<Arc<WebRtcPublisher>>,
+    Json(req): Json<OfferRequest>,
+) -> (StatusCode, Json<ApiResponse<OfferResponse>>) {
+    match publisher.accept_offer(req.sdp, &req.tracks, req.layer).await {
+        Ok((session_id, sdp)) => {
+            (StatusCode::CREATED, Json(ApiResponse::ok(OfferResponse { session_id, sdp })))
+        }
+        Err(e) => {
+            (StatusCode::BAD_REQUEST, Json(ApiResponse::error(e.to_string())))
+        }
+    }
+}
+
+async fn handle_close(
+    State(publisher): State<Arc<WebRtcPublisher>>,
+    Path(id): Path<String>,
+) -> (StatusCode, Json<ApiResponse<()>>) {
+    if publisher.close_session(&id).await {
+        (StatusCode::OK, Json(ApiResponse::ok(())))
+    } else {
+        (StatusCode::NOT_FOUND, Json(ApiResponse::error(format!("Session not found: {}", id))))
+    }
+}
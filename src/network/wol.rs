@@ -0,0 +1,74 @@
+//! Wake-on-LAN
+//!
+//! Powers up the receiver PC from the sender before going live: a magic
+//! packet (six 0xFF bytes, then the MAC 16 times) broadcast to UDP port 9,
+//! or to `wake.broadcast` when the PCs are on different subnets.
+
+use std::net::UdpSocket;
+
+use crate::config::WakeConfig;
+use crate::error::NetworkError;
+use crate::network::resolve::resolve;
+
+/// Magic packet size
+pub const MAGIC_PACKET_SIZE: usize = 6 + 16 * 6;
+
+/// Parse `aa:bb:cc:dd:ee:ff` (or with `-` separators)
+pub fn parse_mac(text: &str) -> Result<[u8; 6], NetworkError> {
+    let invalid = || NetworkError::InvalidMac(text.to_string());
+    let bytes: Vec<u8> = text
+        .trim()
+        .split([':', '-'])
+        .map(|part| if part.len() == 2 { u8::from_str_radix(part, 16).map_err(|_| invalid()) } else { Err(invalid()) })
+        .collect::<Result<_, _>>()?;
+    bytes.try_into().map_err(|_| invalid())
+}
+
+/// The magic packet waking `mac`
+pub fn magic_packet(mac: [u8; 6]) -> [u8; MAGIC_PACKET_SIZE] {
+    let mut packet = [0xFF; MAGIC_PACKET_SIZE];
+    for chunk in packet[6..].chunks_exact_mut(6) {
+        chunk.copy_from_slice(&mac);
+    }
+    packet
+}
+
+/// Send the magic packet for `config.mac`; returns the MAC woken
+pub fn wake(config: &WakeConfig) -> Result<String, NetworkError> {
+    let mac = config.mac.as_deref().ok_or_else(|| NetworkError::InvalidMac("wake.mac is not set".to_string()))?;
+    let packet = magic_packet(parse_mac(mac)?);
+    let to = resolve(&config.broadcast)?;
+    let socket = UdpSocket::bind("0.0.0.0:0").map_err(|e| NetworkError::BindFailed(e.to_string()))?;
+    socket.set_broadcast(true).map_err(|e| NetworkError::BindFailed(e.to_string()))?;
+    socket.send_to(&packet, to).map_err(|e| NetworkError::SendFailed(e.to_string()))?;
+    tracing::info!("Sent Wake-on-LAN packet for {} to {}", mac, to);
+    Ok(mac.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_magic_packet() {
+        let mac = parse_mac("00:1A:2b:3c:4D:5e").unwrap();
+        assert_eq!(mac, [0x00, 0x1A, 0x2B, 0x3C, 0x4D, 0x5E]);
+        assert_eq!(parse_mac("00-1a-2b-3c-4d-5e").unwrap(), mac);
+        for bad in ["", "00:1a:2b:3c:4d", "00:1a:2b:3c:4d:5e:6f", "00:1a:2b:3c:4d:zz", "001:a2:b3:c4:d5:e"] {
+            assert!(parse_mac(bad).is_err(), "{}", bad);
+        }
+
+        let packet = magic_packet(mac);
+        assert_eq!(packet[..6], [0xFF; 6]);
+        assert!(packet[6..].chunks(6).all(|chunk| chunk == mac));
+
+        // Sent to a local listener standing in for the broadcast address
+        let listener = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let config = WakeConfig { mac: Some("00:1a:2b:3c:4d:5e".to_string()), broadcast: listener.local_addr().unwrap().to_string() };
+        wake(&config).unwrap();
+        let mut buf = [0u8; 256];
+        let (size, _) = listener.recv_from(&mut buf).unwrap();
+        assert_eq!(buf[..size], packet);
+        assert!(wake(&WakeConfig::default()).is_err());
+    }
+}
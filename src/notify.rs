@@ -0,0 +1,230 @@
+//! Stream health notifications
+//!
+//! Follows the track manager's event bus and posts stream started/stopped
+//! and alert events (packet loss, lost device, receiver timeout, clipping,
+//! ...) to the configured webhooks, either as a generic JSON document or
+//! as a Discord/Slack chat message. The old `alerts.webhook_url` is one
+//! more JSON webhook for the alert events.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::config::{WebhookFormat, WebhookTarget};
+use crate::protocol::AlertKind;
+
+// Following the event bus needs the track manager and webhook client
+#[cfg(feature = "network")]
+use {
+    crate::network::webhook::post_json,
+    crate::reload::LiveConfig,
+    crate::sync::now_us,
+    crate::tracks::{manager::TrackEvent, TrackManager},
+    std::sync::Arc,
+    tokio::sync::broadcast,
+};
+
+/// Events a webhook can subscribe to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotifyEvent {
+    StreamStarted,
+    StreamStopped,
+    PacketLoss,
+    DeviceLost,
+    ReceiverTimeout,
+    Clipping,
+    Silence,
+    Feedback,
+    BandwidthCap,
+    Incompatible,
+}
+
+impl From<AlertKind> for NotifyEvent {
+    fn from(kind: AlertKind) -> Self {
+        match kind {
+            AlertKind::PacketLoss => Self::PacketLoss,
+            AlertKind::DeviceLost => Self::DeviceLost,
+            AlertKind::ReceiverTimeout => Self::ReceiverTimeout,
+            AlertKind::Clipping => Self::Clipping,
+            AlertKind::Silence => Self::Silence,
+            AlertKind::Feedback => Self::Feedback,
+            AlertKind::BandwidthCap => Self::BandwidthCap,
+            AlertKind::Incompatible => Self::Incompatible,
+        }
+    }
+}
+
+impl NotifyEvent {
+    /// Map a track event to a notification event and whether it is active
+    #[cfg(feature = "network")]
+    pub fn from_track_event(event: &TrackEvent) -> Option<(u8, Self, bool)> {
+        match *event {
+            TrackEvent::Started(id) => Some((id, Self::StreamStarted, true)),
+            TrackEvent::Stopped(id) => Some((id, Self::StreamStopped, true)),
+            TrackEvent::Alert(id, kind, active) => Some((id, kind.into(), active)),
+            _ => None,
+        }
+    }
+
+    /// The alert events
+    pub const ALERTS: [Self; 8] = [
+        Self::PacketLoss,
+        Self::DeviceLost,
+        Self::ReceiverTimeout,
+        Self::Clipping,
+        Self::Silence,
+        Self::Feedback,
+        Self::BandwidthCap,
+        Self::Incompatible,
+    ];
+
+    /// Human-readable description
+    pub fn description(&self, active: bool) -> &'static str {
+        match (self, active) {
+            (Self::StreamStarted, _) => "stream started",
+            (Self::StreamStopped, _) => "stream stopped",
+            (Self::PacketLoss, true) => "packet loss above threshold",
+            (Self::PacketLoss, false) => "packet loss back to normal",
+            (Self::DeviceLost, true) => "audio device lost",
+            (Self::DeviceLost, false) => "audio device recovered",
+            (Self::ReceiverTimeout, true) => "receiver timed out waiting for packets",
+            (Self::ReceiverTimeout, false) => "packets arriving again",
+            (Self::Clipping, true) => "sustained clipping",
+            (Self::Silence, true) => "unexpected silence",
+            (Self::Feedback, true) => "audio feedback loop",
+            (Self::BandwidthCap, true) => "bandwidth over cap",
+            (Self::Incompatible, true) => "receiver can't play track as sent",
+            (Self::Clipping | Self::Silence | Self::Feedback | Self::BandwidthCap | Self::Incompatible, false) => {
+                "alert cleared"
+            }
+        }
+    }
+}
+
+/// One notification, as posted in the generic JSON format
+#[derive(Debug, Clone, Serialize)]
+pub struct Notification {
+    pub event: NotifyEvent,
+    /// False when a loss/device/timeout condition clears
+    pub active: bool,
+    pub track_id: u8,
+    pub track_name: String,
+    /// "sender" or "receiver"
+    pub role: &'static str,
+    pub timestamp_us: u64,
+}
+
+impl Notification {
+    /// One-line summary for chat webhooks
+    pub fn text(&self) -> String {
+        format!(
+            "[{}] Track {} ({}): {}",
+            self.role,
+            self.track_id,
+            self.track_name,
+            self.event.description(self.active)
+        )
+    }
+
+    /// Request body for a webhook format
+    pub fn body(&self, format: WebhookFormat) -> Value {
+        match format {
+            WebhookFormat::Json => serde_json::to_value(self).unwrap_or(Value::Null),
+            WebhookFormat::Discord => json!({ "content": self.text() }),
+            WebhookFormat::Slack => json!({ "text": self.text() }),
+        }
+    }
+}
+
+/// Check whether a webhook wants an event
+#[cfg_attr(not(feature = "network"), allow(dead_code))]
+fn wants(target: &WebhookTarget, event: NotifyEvent) -> bool {
+    target.events.is_empty() || target.events.contains(&event)
+}
+
+/// Post stream health events from the track manager to the webhooks in
+/// `notify` and `alerts.webhook_url`
+#[cfg(feature = "network")]
+pub async fn run(track_manager: Arc<TrackManager>, config: LiveConfig, is_sender: bool) {
+    let mut events = track_manager.subscribe();
+
+    loop {
+        let event = match events.recv().await {
+            Ok(event) => event,
+            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+        let Some((track_id, event, active)) = NotifyEvent::from_track_event(&event) else {
+            continue;
+        };
+        let webhooks = config.borrow().notify_webhooks();
+        if webhooks.is_empty() {
+            continue;
+        }
+
+        let notification = Notification {
+            event,
+            active,
+            track_id,
+            track_name: track_manager
+                .get_track(track_id)
+                .map(|t| t.config.name.clone())
+                .unwrap_or_default(),
+            role: if is_sender { "sender" } else { "receiver" },
+            timestamp_us: now_us(),
+        };
+
+        for target in webhooks.iter().filter(|t| wants(t, event)) {
+            let url = target.url.clone();
+            let body = notification.body(target.format);
+            tokio::spawn(async move {
+                if let Err(e) = post_json(&url, &body).await {
+                    tracing::warn!("Notification webhook failed: {}", e);
+                }
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_mapping_and_formats() {
+        #[cfg(feature = "network")]
+        {
+            assert_eq!(
+                NotifyEvent::from_track_event(&TrackEvent::Alert(2, AlertKind::PacketLoss, true)),
+                Some((2, NotifyEvent::PacketLoss, true))
+            );
+            assert_eq!(
+                NotifyEvent::from_track_event(&TrackEvent::Alert(2, AlertKind::Silence, true)),
+                Some((2, NotifyEvent::Silence, true))
+            );
+            assert_eq!(NotifyEvent::from_track_event(&TrackEvent::Removed(2)), None);
+        }
+
+        let notification = Notification {
+            event: NotifyEvent::ReceiverTimeout,
+            active: true,
+            track_id: 1,
+            track_name: "Mic".to_string(),
+            role: "receiver",
+            timestamp_us: 0,
+        };
+        let text = notification.text();
+        assert!(text.contains("Track 1 (Mic)") && text.contains("timed out"));
+        assert_eq!(notification.body(WebhookFormat::Slack)["text"], text);
+        assert_eq!(notification.body(WebhookFormat::Discord)["content"], text);
+        assert_eq!(notification.body(WebhookFormat::Json)["event"], "receiver_timeout");
+
+        let target = WebhookTarget {
+            url: "http://hub.lan/hook".to_string(),
+            format: WebhookFormat::Json,
+            events: vec![NotifyEvent::DeviceLost],
+        };
+        assert!(wants(&target, NotifyEvent::DeviceLost));
+        assert!(!wants(&target, NotifyEvent::StreamStarted));
+    }
+}
@@ -7,7 +7,7 @@
 //! │                        Audio Packet Header (16 bytes)                  │
 //! ├──────────┬──────────┬──────────┬──────────┬────────────────────────────┤
 //! │ Magic(2) │TrackID(1)│ Flags(1) │  Seq(4)  │      Timestamp(8)          │
-//! │  0xAF01  │   0-255  │ See below│ u32 LE   │      u64 LE (µs)           │
+//! │  0xAF01  │   0-255  │ See below│ u32 LE   │  u64 LE (ticks or µs)      │
 //! ├──────────┴──────────┴──────────┴──────────┴────────────────────────────┤
 //! │                        Opus Payload (variable)                         │
 //! │                        Max: 1456 bytes                                 │
@@ -16,22 +16,189 @@
 //! Flags byte:
 //! ┌─────┬─────┬─────┬─────┬─────┬─────┬─────┬─────┐
 //! │  7  │  6  │  5  │  4  │  3  │  2  │  1  │  0  │
-//! │ RSV │ RSV │ RSV │ RSV │ RSV │ FEC │STEREO│KEYF│
+//! │ LYR │MCLK │FRAG │ CRC │ DTX │ FEC │STEREO│KEYF│
 //! └─────┴─────┴─────┴─────┴─────┴─────┴─────┴─────┘
 //! ```
+//!
+//! With MCLK set, the timestamp is the track's media clock: sample frames
+//! at 48 kHz since the stream started, related to the shared clock by
+//! [`PeerMessage::MediaClock`] reports (see [`crate::sync::media`]).
+//! Without it, it is the capture time in µs on the shared clock, as
+//! legacy senders send it.
+//!
+//! With CRC set, a CRC32 (IEEE, u32 LE) of the payload sits between the
+//! header and the payload. Packets failing the check are dropped.
+//!
+//! ## Simulcast
+//!
+//! A track may be encoded more than once, at different bitrates. The
+//! primary encoding is layer 0 and is sent as above; every other layer
+//! sets LYR and puts its layer ID (one byte) right after the header, in
+//! front of the fragment index and the CRC. Each layer counts sequence
+//! numbers of its own but shares the track's timestamps. Layers other
+//! than 0 only go to receivers that negotiated
+//! [`ProtocolFeatures::SIMULCAST`] and asked for them with
+//! [`PeerMessage::SubscribeLayers`].
+//!
+//! ## Fragments
+//!
+//! A packet too large for the path MTU is sent as up to [`MAX_FRAGMENTS`]
+//! fragments. Each repeats the header with FRAG set, followed by its index
+//! and the fragment count (one byte each), then the CRC if any (over the
+//! fragment's piece) and the piece. [`Reassembler`] joins them back into
+//! the original packet once all have arrived.
+//!
+//! DTX marks the last packet before the sender stops transmitting during
+//! silence. Sequence numbers are not advanced for suppressed frames, so the
+//! gap is not counted as loss; the receiver plays comfort noise until the
+//! next packet arrives.
+//!
+//! ## Control Packets
+//!
+//! Sender and receiver also exchange control packets on the same socket.
+//! They start with `CONTROL_MAGIC` (0xAF02, LE) followed by a
+//! bincode-encoded [`PeerMessage`]. With a shared key, [`sign_control`]
+//! appends a counter and HMAC-SHA256 tag that [`verify_control`] checks.
+//!
+//! ## Versions
+//!
+//! Each end that sends audio says [`PeerMessage::Hello`] with its
+//! [`PROTOCOL_VERSION`] and [`ProtocolFeatures`], and the other end answers
+//! in kind. Both then use the lower version and the features they share. A
+//! peer from before the handshake drops the unknown message and never
+//! answers; it is taken to speak [`LEGACY_PROTOCOL_VERSION`] with
+//! [`ProtocolFeatures::legacy`].
+//!
+//! ## Web UI channel
+//!
+//! The web UI's WebSocket speaks [`ControlMessage`] as JSON text frames.
+//! A client that opens it with the [`WS_SUBPROTOCOL`] subprotocol says
+//! [`ControlMessage::Hello`] first, with its [`WS_PROTOCOL_VERSION`] and the
+//! features it wants; the server answers [`ControlMessage::Welcome`] with
+//! the version both speak and the features granted. With
+//! [`WS_FEATURE_BINARY_METERS`] the server also pushes every track's levels
+//! as binary frames ([`encode_meter_frame`]) at the agreed rate. Clients
+//! that never say hello get JSON only.
+//!
+//! Everything here works on plain bytes with no socket behind it, so the
+//! parsers can be property-tested and fuzzed directly (see `fuzz`, built
+//! for tests and with the `fuzz` feature).
 
+#[cfg(any(test, feature = "fuzz"))]
+pub mod fuzz;
+
+use bincode::Options;
 use bytes::{Buf, BufMut, Bytes, BytesMut};
+use hmac::{Hmac, Mac};
 use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::hash::Hash;
+use std::net::SocketAddr;
+
+use crate::dsp::ProcessorConfig;
+use crate::error::PacketError;
+use crate::sync::MediaClockRef;
+use crate::tracks::stats::{BandwidthReport, GroupStats};
+use crate::tracks::{TrackState, TrackTransition};
 
 /// Magic number for packet identification
 pub const PACKET_MAGIC: u16 = 0xAF01;
 
+/// Magic number for control packets
+pub const CONTROL_MAGIC: u16 = 0xAF02;
+
 /// Maximum payload size (MTU - IP header - UDP header - our header)
 pub const MAX_PAYLOAD_SIZE: usize = 1456;
 
 /// Header size in bytes
 pub const HEADER_SIZE: usize = 16;
 
+/// Size of the optional payload checksum
+pub const CRC_SIZE: usize = 4;
+
+/// Size of the simulcast layer ID of layers other than 0
+pub const LAYER_SIZE: usize = 1;
+
+/// Room kept in front of a [`PacketBuffer`] payload for the header, layer and checksum
+pub const HEADER_ROOM: usize = HEADER_SIZE + LAYER_SIZE + CRC_SIZE;
+
+/// Size of the fragment index and count
+pub const FRAGMENT_HEADER_SIZE: usize = 2;
+
+/// Size of a control signature: counter and HMAC-SHA256 tag
+pub const SIGNATURE_SIZE: usize = 8 + 32;
+
+/// Most bytes a control message body may decode from
+///
+/// Larger than any datagram, so only forged length prefixes hit it.
+const MAX_CONTROL_SIZE: u64 = 64 * 1024;
+
+/// Most fragments one packet may be split into
+pub const MAX_FRAGMENTS: usize = 64;
+
+/// Packets being reassembled at once; the oldest is dropped beyond this
+const MAX_PENDING_REASSEMBLY: usize = 64;
+
+/// A CRC-32 variant over polynomial 0x04C11DB7
+pub struct Crc32 {
+    table: [u32; 256],
+    /// Bytes enter least significant bit first
+    reflected: bool,
+    init: u32,
+    xor_out: u32,
+}
+
+impl Crc32 {
+    const POLY: u32 = 0x04C1_1DB7;
+
+    const fn new(reflected: bool, init: u32, xor_out: u32) -> Self {
+        let poly = if reflected { Self::POLY.reverse_bits() } else { Self::POLY };
+        let mut table = [0u32; 256];
+        let mut i = 0;
+        while i < 256 {
+            let mut crc = if reflected { i as u32 } else { (i as u32) << 24 };
+            let mut bit = 0;
+            while bit < 8 {
+                crc = if reflected {
+                    if crc & 1 != 0 { (crc >> 1) ^ poly } else { crc >> 1 }
+                } else if crc & 0x8000_0000 != 0 {
+                    (crc << 1) ^ poly
+                } else {
+                    crc << 1
+                };
+                bit += 1;
+            }
+            table[i] = crc;
+            i += 1;
+        }
+        Self { table, reflected, init, xor_out }
+    }
+
+    /// Checksum of `data`
+    pub fn checksum(&self, data: &[u8]) -> u32 {
+        let crc = data.iter().fold(self.init, |crc, &byte| {
+            if self.reflected {
+                self.table[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8)
+            } else {
+                self.table[((crc >> 24) ^ byte as u32) as usize] ^ (crc << 8)
+            }
+        });
+        crc ^ self.xor_out
+    }
+}
+
+/// IEEE CRC-32 (Ethernet, zip): reflected, all-ones init and final xor
+pub static CRC32_IEEE: Crc32 = Crc32::new(true, !0, !0);
+
+/// Ogg page CRC-32: not reflected, zero init, no final xor
+pub static CRC32_OGG: Crc32 = Crc32::new(false, 0, 0);
+
+/// CRC32 (IEEE) checksum
+pub fn crc32(data: &[u8]) -> u32 {
+    CRC32_IEEE.checksum(data)
+}
+
 /// Packet flags
 #[derive(Debug, Clone, Copy, Default)]
 pub struct PacketFlags(u8);
@@ -40,6 +207,11 @@ impl PacketFlags {
     pub const KEYFRAME: u8 = 0x01;
     pub const STEREO: u8 = 0x02;
     pub const FEC: u8 = 0x04;
+    pub const DTX: u8 = 0x08;
+    pub const CRC: u8 = 0x10;
+    pub const FRAGMENT: u8 = 0x20;
+    pub const MEDIA_CLOCK: u8 = 0x40;
+    pub const LAYER: u8 = 0x80;
     
     pub fn new() -> Self {
         Self(0)
@@ -72,6 +244,51 @@ impl PacketFlags {
         self
     }
     
+    pub fn set_dtx(mut self, value: bool) -> Self {
+        if value {
+            self.0 |= Self::DTX;
+        } else {
+            self.0 &= !Self::DTX;
+        }
+        self
+    }
+    
+    pub fn set_crc(mut self, value: bool) -> Self {
+        if value {
+            self.0 |= Self::CRC;
+        } else {
+            self.0 &= !Self::CRC;
+        }
+        self
+    }
+    
+    pub fn set_fragment(mut self, value: bool) -> Self {
+        if value {
+            self.0 |= Self::FRAGMENT;
+        } else {
+            self.0 &= !Self::FRAGMENT;
+        }
+        self
+    }
+    
+    pub fn set_media_clock(mut self, value: bool) -> Self {
+        if value {
+            self.0 |= Self::MEDIA_CLOCK;
+        } else {
+            self.0 &= !Self::MEDIA_CLOCK;
+        }
+        self
+    }
+    
+    pub fn set_layer(mut self, value: bool) -> Self {
+        if value {
+            self.0 |= Self::LAYER;
+        } else {
+            self.0 &= !Self::LAYER;
+        }
+        self
+    }
+    
     pub fn is_keyframe(&self) -> bool {
         self.0 & Self::KEYFRAME != 0
     }
@@ -84,6 +301,26 @@ impl PacketFlags {
         self.0 & Self::FEC != 0
     }
     
+    pub fn is_dtx(&self) -> bool {
+        self.0 & Self::DTX != 0
+    }
+    
+    pub fn has_crc(&self) -> bool {
+        self.0 & Self::CRC != 0
+    }
+    
+    pub fn is_fragment(&self) -> bool {
+        self.0 & Self::FRAGMENT != 0
+    }
+    
+    pub fn has_media_clock(&self) -> bool {
+        self.0 & Self::MEDIA_CLOCK != 0
+    }
+    
+    pub fn has_layer(&self) -> bool {
+        self.0 & Self::LAYER != 0
+    }
+    
     pub fn as_byte(&self) -> u8 {
         self.0
     }
@@ -99,13 +336,16 @@ pub struct AudioPacket {
     /// Track identifier (0-255)
     pub track_id: u8,
     
-    /// Packet flags
+    /// Simulcast layer (0 = the track's primary encoding)
+    pub layer: u8,
+    
+    /// Packet flags (LYR is added when sent for layers other than 0)
     pub flags: PacketFlags,
     
     /// Sequence number for reordering
     pub sequence: u32,
     
-    /// Capture timestamp in microseconds
+    /// Media clock ticks with the MEDIA_CLOCK flag, capture time in µs otherwise
     pub timestamp: u64,
     
     /// Opus-encoded audio data
@@ -117,6 +357,7 @@ impl AudioPacket {
     pub fn new(track_id: u8, sequence: u32, timestamp: u64, payload: Bytes) -> Self {
         Self {
             track_id,
+            layer: 0,
             flags: PacketFlags::new(),
             sequence,
             timestamp,
@@ -126,57 +367,554 @@ impl AudioPacket {
     
     /// Serialize packet to bytes for network transmission
     pub fn serialize(&self) -> Bytes {
-        let mut buf = BytesMut::with_capacity(HEADER_SIZE + self.payload.len());
+        let mut buf = BytesMut::with_capacity(self.total_size());
+        let flags = self.wire_flags();
         
         // Magic number
         buf.put_u16_le(PACKET_MAGIC);
         // Track ID
         buf.put_u8(self.track_id);
         // Flags
-        buf.put_u8(self.flags.as_byte());
+        buf.put_u8(flags.as_byte());
         // Sequence number
         buf.put_u32_le(self.sequence);
         // Timestamp
         buf.put_u64_le(self.timestamp);
+        // Simulcast layer
+        if flags.has_layer() {
+            buf.put_u8(self.layer);
+        }
+        // Payload checksum
+        if self.flags.has_crc() {
+            buf.put_u32_le(crc32(&self.payload));
+        }
         // Payload
         buf.put_slice(&self.payload);
         
         buf.freeze()
     }
     
+    /// Serialize to datagrams of at most `max_datagram` bytes, fragmenting if needed
+    /// 
+    /// No datagram carries more than [`MAX_PAYLOAD_SIZE`], however large
+    /// `max_datagram` is, since receivers refuse anything larger.
+    pub fn serialize_fragments(&self, max_datagram: usize) -> Result<Vec<Bytes>, PacketError> {
+        if self.total_size() <= max_datagram && self.payload.len() <= MAX_PAYLOAD_SIZE {
+            return Ok(vec![self.serialize()]);
+        }
+        let flags = self.wire_flags().set_fragment(true);
+        let crc = if flags.has_crc() { CRC_SIZE } else { 0 };
+        let layer = if flags.has_layer() { LAYER_SIZE } else { 0 };
+        let header = HEADER_SIZE + layer + FRAGMENT_HEADER_SIZE + crc;
+        let piece = max_datagram.saturating_sub(header).min(MAX_PAYLOAD_SIZE);
+        let count = if piece == 0 { usize::MAX } else { self.payload.len().div_ceil(piece) };
+        if count > MAX_FRAGMENTS {
+            return Err(PacketError::PayloadTooLarge(self.payload.len()));
+        }
+        
+        Ok((0..count)
+            .map(|index| {
+                let chunk = self.payload.slice(index * piece..((index + 1) * piece).min(self.payload.len()));
+                let mut buf = BytesMut::with_capacity(header + chunk.len());
+                buf.put_u16_le(PACKET_MAGIC);
+                buf.put_u8(self.track_id);
+                buf.put_u8(flags.as_byte());
+                buf.put_u32_le(self.sequence);
+                buf.put_u64_le(self.timestamp);
+                if flags.has_layer() {
+                    buf.put_u8(self.layer);
+                }
+                buf.put_u8(index as u8);
+                buf.put_u8(count as u8);
+                if flags.has_crc() {
+                    buf.put_u32_le(crc32(&chunk));
+                }
+                buf.put_slice(&chunk);
+                buf.freeze()
+            })
+            .collect())
+    }
+    
     /// Deserialize packet from bytes
-    pub fn deserialize(mut data: Bytes) -> Option<Self> {
+    pub fn deserialize(data: Bytes) -> Option<Self> {
+        Self::parse(data).ok()
+    }
+    
+    /// Parse packet from bytes, reporting why it was rejected
+    /// 
+    /// The payload is sliced out of `data` without copying. Fragments are
+    /// rejected; [`parse_packet`] hands them out for reassembly.
+    pub fn parse(data: Bytes) -> Result<Self, PacketError> {
+        match Self::parse_datagram(data)? {
+            Packet::Audio(packet) => Ok(packet),
+            _ => Err(PacketError::BadFragment),
+        }
+    }
+    
+    /// Parse an audio datagram: a whole packet or one fragment
+    fn parse_datagram(mut data: Bytes) -> Result<Packet, PacketError> {
         if data.len() < HEADER_SIZE {
-            return None;
+            return Err(PacketError::Truncated(data.len()));
         }
         
         // Check magic number
         let magic = data.get_u16_le();
         if magic != PACKET_MAGIC {
-            return None;
+            return Err(PacketError::BadMagic(magic));
         }
         
         let track_id = data.get_u8();
         let flags = PacketFlags::from_byte(data.get_u8());
         let sequence = data.get_u32_le();
         let timestamp = data.get_u64_le();
-        let payload = data; // Remaining bytes are payload
         
-        Some(Self {
+        let layer = if flags.has_layer() {
+            if data.len() < LAYER_SIZE {
+                return Err(PacketError::Truncated(HEADER_SIZE + data.len()));
+            }
+            data.get_u8()
+        } else {
+            0
+        };
+        
+        let fragment = if flags.is_fragment() {
+            if data.len() < FRAGMENT_HEADER_SIZE {
+                return Err(PacketError::Truncated(HEADER_SIZE + data.len()));
+            }
+            let (index, count) = (data.get_u8(), data.get_u8());
+            if index >= count || count as usize > MAX_FRAGMENTS {
+                return Err(PacketError::BadFragment);
+            }
+            Some((index, count))
+        } else {
+            None
+        };
+        
+        let checksum = if flags.has_crc() {
+            if data.len() < CRC_SIZE {
+                return Err(PacketError::Truncated(HEADER_SIZE + data.len()));
+            }
+            Some(data.get_u32_le())
+        } else {
+            None
+        };
+        
+        // Remaining bytes are payload
+        let payload = data;
+        if payload.len() > MAX_PAYLOAD_SIZE {
+            return Err(PacketError::PayloadTooLarge(payload.len()));
+        }
+        if checksum.is_some_and(|crc| crc != crc32(&payload)) {
+            return Err(PacketError::ChecksumMismatch);
+        }
+        
+        let packet = Self {
             track_id,
-            flags,
+            layer,
+            flags: flags.set_fragment(false),
             sequence,
             timestamp,
             payload,
+        };
+        Ok(match fragment {
+            Some((index, count)) => Packet::Fragment(Fragment { packet, index, count }),
+            None => Packet::Audio(packet),
         })
     }
     
     /// Get packet size including header
     pub fn total_size(&self) -> usize {
-        HEADER_SIZE + self.payload.len()
+        let crc = if self.flags.has_crc() { CRC_SIZE } else { 0 };
+        let layer = if self.wire_flags().has_layer() { LAYER_SIZE } else { 0 };
+        HEADER_SIZE + layer + crc + self.payload.len()
+    }
+    
+    /// Flags as sent: LYR set for layers other than 0
+    fn wire_flags(&self) -> PacketFlags {
+        if self.layer != 0 {
+            self.flags.set_layer(true)
+        } else {
+            self.flags
+        }
+    }
+}
+
+/// A payload written after room for its header
+/// 
+/// The encoder writes straight into the buffer and the header is filled
+/// in in front of the payload at send time, so the datagram is the same
+/// allocation the encoder wrote to. A payload that is already [`Bytes`] is
+/// kept as is; it is copied once, when its datagram is built.
+#[derive(Debug, Clone)]
+pub struct PacketBuffer {
+    inner: Inner,
+}
+
+#[derive(Debug, Clone)]
+enum Inner {
+    /// [`HEADER_ROOM`] bytes, then the payload
+    Room(BytesMut),
+    /// The payload alone
+    Shared(Bytes),
+}
+
+impl PacketBuffer {
+    /// Empty payload with room for `capacity` bytes
+    pub fn with_capacity(capacity: usize) -> Self {
+        let mut buf = BytesMut::with_capacity(HEADER_ROOM + capacity);
+        buf.resize(HEADER_ROOM, 0);
+        Self { inner: Inner::Room(buf) }
+    }
+    
+    /// Let `write` fill up to `max` payload bytes, keeping as many as it returns
+    pub fn fill<E>(&mut self, max: usize, write: impl FnOnce(&mut [u8]) -> Result<usize, E>) -> Result<usize, E> {
+        let buf = self.room();
+        buf.resize(HEADER_ROOM + max, 0);
+        let written = write(&mut buf[HEADER_ROOM..]);
+        buf.truncate(HEADER_ROOM + *written.as_ref().unwrap_or(&0));
+        written
+    }
+    
+    pub fn payload(&self) -> &[u8] {
+        match self.inner {
+            Inner::Room(ref buf) => &buf[HEADER_ROOM..],
+            Inner::Shared(ref payload) => payload,
+        }
+    }
+    
+    pub fn len(&self) -> usize {
+        self.payload().len()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    
+    /// The payload alone, without copying
+    pub fn into_payload(self) -> Bytes {
+        match self.inner {
+            Inner::Room(buf) => {
+                let mut payload = buf.freeze();
+                payload.advance(HEADER_ROOM);
+                payload
+            }
+            Inner::Shared(payload) => payload,
+        }
+    }
+    
+    /// The buffer with its header room, copying a shared payload into one
+    fn room(&mut self) -> &mut BytesMut {
+        if let Inner::Shared(ref payload) = self.inner {
+            let mut buf = BytesMut::with_capacity(HEADER_ROOM + payload.len());
+            buf.resize(HEADER_ROOM, 0);
+            buf.extend_from_slice(payload);
+            self.inner = Inner::Room(buf);
+        }
+        match self.inner {
+            Inner::Room(ref mut buf) => buf,
+            Inner::Shared(_) => unreachable!(),
+        }
+    }
+    
+    /// Write the header in front of the payload and hand out the datagram,
+    /// as [`AudioPacket::serialize`] would lay it out
+    pub fn finish(mut self, track_id: u8, layer: u8, flags: PacketFlags, sequence: u32, timestamp: u64) -> Bytes {
+        let flags = if layer != 0 { flags.set_layer(true) } else { flags }.set_fragment(false);
+        let buf = self.room();
+        let mut start = HEADER_ROOM;
+        if flags.has_crc() {
+            let crc = crc32(&buf[HEADER_ROOM..]);
+            start -= CRC_SIZE;
+            buf[start..HEADER_ROOM].copy_from_slice(&crc.to_le_bytes());
+        }
+        if flags.has_layer() {
+            start -= LAYER_SIZE;
+            buf[start] = layer;
+        }
+        start -= HEADER_SIZE;
+        let mut header = &mut buf[start..start + HEADER_SIZE];
+        header.put_u16_le(PACKET_MAGIC);
+        header.put_u8(track_id);
+        header.put_u8(flags.as_byte());
+        header.put_u32_le(sequence);
+        header.put_u64_le(timestamp);
+        
+        let mut datagram = std::mem::take(buf).freeze();
+        datagram.advance(start);
+        datagram
+    }
+}
+
+impl Default for PacketBuffer {
+    fn default() -> Self {
+        Self { inner: Inner::Shared(Bytes::new()) }
+    }
+}
+
+impl From<&[u8]> for PacketBuffer {
+    fn from(payload: &[u8]) -> Self {
+        let mut buffer = Self::with_capacity(payload.len());
+        buffer.room().extend_from_slice(payload);
+        buffer
+    }
+}
+
+impl From<Bytes> for PacketBuffer {
+    fn from(payload: Bytes) -> Self {
+        Self { inner: Inner::Shared(payload) }
+    }
+}
+
+/// Recycled memory that payloads are written into
+/// 
+/// Payloads are written into one zero-filled block and split off it, so a
+/// frame costs neither an allocation nor clearing the largest packet's
+/// worth of bytes. Once every packet split off a block has been sent and
+/// dropped, the block is reused.
+#[derive(Debug, Default)]
+pub struct PacketPool {
+    block: BytesMut,
+}
+
+impl PacketPool {
+    /// Smallest block allocated at a time
+    const BLOCK_SIZE: usize = 64 * 1024;
+    
+    /// Let `write` fill up to `max` payload bytes, returning a buffer with as many as it wrote
+    pub fn fill<E>(&mut self, max: usize, write: impl FnOnce(&mut [u8]) -> Result<usize, E>) -> Result<PacketBuffer, E> {
+        let needed = HEADER_ROOM + max;
+        if self.block.len() < needed {
+            // Reclaims the block when nothing split off it is still alive
+            self.block.clear();
+            self.block.reserve(needed.max(Self::BLOCK_SIZE));
+            let capacity = self.block.capacity();
+            self.block.resize(capacity, 0);
+        }
+        let written = write(&mut self.block[HEADER_ROOM..needed])?;
+        Ok(PacketBuffer { inner: Inner::Room(self.block.split_to(HEADER_ROOM + written)) })
     }
 }
 
+/// A parsed datagram
+#[derive(Debug, Clone)]
+pub enum Packet {
+    Audio(AudioPacket),
+    /// Part of an audio packet larger than the path MTU
+    Fragment(Fragment),
+    Control(PeerMessage),
+}
+
+/// One fragment of an audio packet
+#[derive(Debug, Clone)]
+pub struct Fragment {
+    /// Header of the whole packet, with this fragment's piece as payload
+    pub packet: AudioPacket,
+    pub index: u8,
+    pub count: u8,
+}
+
+/// Joins fragments from any number of sources back into packets
+///
+/// Fragments may arrive in any order. Packets missing a fragment are
+/// dropped once 64 newer ones are in progress.
+pub struct Reassembler<K> {
+    pending: HashMap<(K, u8, u8, u32), Vec<Option<Bytes>>>,
+    /// Pending packets, oldest first
+    order: VecDeque<(K, u8, u8, u32)>,
+}
+
+impl<K: Hash + Eq + Copy> Reassembler<K> {
+    pub fn new() -> Self {
+        Self { pending: HashMap::new(), order: VecDeque::new() }
+    }
+    
+    /// Add a fragment from `source`; returns the packet once it is complete
+    pub fn push(&mut self, source: K, fragment: Fragment) -> Option<AudioPacket> {
+        let Fragment { mut packet, index, count } = fragment;
+        let key = (source, packet.track_id, packet.layer, packet.sequence);
+        let pieces = self.pending.entry(key).or_insert_with(|| {
+            self.order.push_back(key);
+            vec![None; count as usize]
+        });
+        if pieces.len() != count as usize {
+            return None;
+        }
+        pieces[index as usize] = Some(packet.payload);
+        if pieces.iter().any(Option::is_none) {
+            if self.order.len() > MAX_PENDING_REASSEMBLY {
+                if let Some(oldest) = self.order.pop_front() {
+                    self.pending.remove(&oldest);
+                }
+            }
+            return None;
+        }
+        
+        let pieces = self.pending.remove(&key)?;
+        self.order.retain(|pending| *pending != key);
+        let mut payload = BytesMut::new();
+        for piece in pieces.into_iter().flatten() {
+            payload.put_slice(&piece);
+        }
+        packet.payload = payload.freeze();
+        Some(packet)
+    }
+    
+    /// Packets waiting for fragments
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+}
+
+impl<K: Hash + Eq + Copy> Default for Reassembler<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Parse any datagram received on the audio socket
+/// 
+/// Never panics on arbitrary input; every rejection names its cause.
+pub fn parse_packet(data: &[u8]) -> Result<Packet, PacketError> {
+    match packet_magic(data) {
+        None => Err(PacketError::Truncated(data.len())),
+        Some(CONTROL_MAGIC) => PeerMessage::deserialize(data)
+            .map(Packet::Control)
+            .ok_or(PacketError::BadControl),
+        Some(PACKET_MAGIC) => AudioPacket::parse_datagram(Bytes::copy_from_slice(data)),
+        Some(magic) => Err(PacketError::BadMagic(magic)),
+    }
+}
+
+/// Read the magic number of a raw datagram
+pub fn packet_magic(data: &[u8]) -> Option<u16> {
+    if data.len() < 2 {
+        return None;
+    }
+    Some(u16::from_le_bytes([data[0], data[1]]))
+}
+
+/// Messages exchanged between sender and receiver on the control channel
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum PeerMessage {
+    /// Clock sync request, carrying the requester's transmit time (µs)
+    ClockRequest { t0: u64 },
+    
+    /// Clock sync response: echoed t0, responder receive (t1) and transmit (t2) times
+    ClockResponse { t0: u64, t1: u64, t2: u64 },
+    
+    /// Sender paused or resumed a track; no packets follow while paused
+    StreamPaused { track_id: u8, paused: bool },
+    
+    /// Sender is shutting down; no packets follow on any track
+    EndOfStream,
+    
+    /// Receiver wants only these tracks (empty = all)
+    Subscribe { tracks: Vec<u8> },
+    
+    /// Receiver asks the sender to change a track (linked control)
+    ConfigureTrack { track_id: u8, update: RemoteTrackUpdate },
+    
+    /// Sender's current settings for a track (linked control)
+    TrackState(RemoteTrackState),
+    
+    /// Sender's labels for a track, announced periodically
+    TrackInfo(TrackInfo),
+    
+    /// Path MTU probe, padded to `size` bytes on the wire
+    MtuProbe { round: u32, size: u16, padding: Vec<u8> },
+    
+    /// A probe of `size` bytes arrived
+    MtuAck { round: u32, size: u16 },
+    
+    /// Opens NAT mappings towards the peer; carries nothing
+    Punch,
+    
+    /// Register with a rendezvous relay under a session name
+    Rendezvous { session: String },
+    
+    /// From a rendezvous relay: public address of another peer in the session
+    PeerAddress { session: String, addr: SocketAddr },
+    
+    /// What the receiver plays without resampling or dropping, sent with its subscription
+    Capabilities(ReceiverCapabilities),
+    
+    /// Protocol version and optional features; `reply` is set on the answer to one
+    Hello { version: u16, features: ProtocolFeatures, reply: bool },
+    
+    /// A track's codec state was reset, by the sender (encoder) or the receiver
+    /// (decoder and jitter buffer); the other end resets its side
+    ResetTrack { track_id: u8, by_sender: bool },
+    
+    /// Sent over each link of a redundant sender, so the receiver takes
+    /// datagrams from all of them as one session
+    Link { session: u64, link: u8 },
+    
+    /// Sender report relating a track's media clock to the shared clock
+    MediaClock { track_id: u8, reference: MediaClockRef },
+    
+    /// Receiver wants these simulcast layers of every track besides layer 0
+    SubscribeLayers { layers: Vec<u8> },
+}
+
+impl PeerMessage {
+    /// Serialize to a control packet
+    pub fn serialize(&self) -> Bytes {
+        let body = bincode::serialize(self).unwrap_or_default();
+        let mut buf = BytesMut::with_capacity(2 + body.len());
+        buf.put_u16_le(CONTROL_MAGIC);
+        buf.put_slice(&body);
+        buf.freeze()
+    }
+    
+    /// Deserialize from a control packet
+    /// 
+    /// Length prefixes are not trusted: decoding stops at [`MAX_CONTROL_SIZE`].
+    pub fn deserialize(data: &[u8]) -> Option<Self> {
+        if packet_magic(data)? != CONTROL_MAGIC {
+            return None;
+        }
+        bincode::DefaultOptions::new()
+            .with_fixint_encoding()
+            .allow_trailing_bytes()
+            .with_limit(MAX_CONTROL_SIZE)
+            .deserialize(&data[2..])
+            .ok()
+    }
+}
+
+fn control_mac(key: &[u8]) -> Hmac<Sha256> {
+    Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key length")
+}
+
+/// Append `counter` and an HMAC-SHA256 tag over everything to a serialized control message
+pub fn sign_control(message: &[u8], key: &[u8], counter: u64) -> Vec<u8> {
+    let mut signed = Vec::with_capacity(message.len() + SIGNATURE_SIZE);
+    signed.extend_from_slice(message);
+    signed.extend_from_slice(&counter.to_le_bytes());
+    
+    let mut mac = control_mac(key);
+    mac.update(&signed);
+    signed.extend_from_slice(&mac.finalize().into_bytes());
+    signed
+}
+
+/// Check the tag of a signed control datagram, returning the message bytes and counter
+/// 
+/// Replay protection (counters must increase per peer) is up to the caller.
+pub fn verify_control<'a>(data: &'a [u8], key: &[u8]) -> Result<(&'a [u8], u64), PacketError> {
+    if data.len() < 2 + SIGNATURE_SIZE {
+        return Err(PacketError::BadSignature);
+    }
+    let (signed, tag) = data.split_at(data.len() - 32);
+    let mut mac = control_mac(key);
+    mac.update(signed);
+    mac.verify_slice(tag).map_err(|_| PacketError::BadSignature)?;
+    
+    let (message, counter) = signed.split_at(signed.len() - 8);
+    let counter = u64::from_le_bytes(counter.try_into().unwrap_or_default());
+    Ok((message, counter))
+}
+
 /// Control message types for WebSocket communication
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", content = "data")]
@@ -196,6 +934,39 @@ pub enum ControlMessage {
     /// Solo a track
     SetSolo { track_id: u8, solo: bool },
     
+    /// Set a track's playout delay (receiver)
+    SetDelay { track_id: u8, delay_ms: u32 },
+    
+    /// Enable or disable noise suppression on a track
+    SetDenoise { track_id: u8, enabled: bool },
+    
+    /// Set the total bitrate shared by all tracks (0 = unlimited)
+    SetBitrateBudget { bitrate: u32 },
+    
+    /// Pause or resume a track (all tracks if no ID is given)
+    SetPaused { track_id: Option<u8>, paused: bool },
+    
+    /// Mute/unmute every track of a group
+    SetGroupMute { group: String, muted: bool },
+    
+    /// Solo a group
+    SetGroupSolo { group: String, solo: bool },
+    
+    /// Set a group's gain, added to each track's receiver trim
+    SetGroupGain { group: String, gain_db: f32 },
+    
+    /// List track groups
+    GetGroups,
+    
+    /// Group list response
+    Groups(Vec<TrackGroup>),
+    
+    /// Get a group's statistics
+    GetGroupStats { group: String },
+    
+    /// Group statistics response
+    GroupStats(GroupStats),
+    
     /// Get track status
     GetStatus,
     
@@ -208,6 +979,36 @@ pub enum ControlMessage {
     /// Device list response
     Devices(Vec<AudioDeviceInfo>),
     
+    /// Get session statistics
+    GetSessionStats,
+    
+    /// Session statistics response
+    SessionStats(SessionStats),
+    
+    /// Ask for a full state snapshot (after missed messages)
+    Resync,
+    
+    /// Full state, sent to a client on connect and on resync
+    Snapshot(StateSnapshot),
+    
+    /// Gate state changed on a track (open = mic live)
+    GateState { track_id: u8, open: bool },
+    
+    /// Signal alert raised or cleared on a track
+    Alert { track_id: u8, kind: AlertKind, active: bool },
+    
+    /// Audio callback over/underran
+    Xrun(XrunEvent),
+    
+    /// A track moved to a fallback device (`fallback`) or back to its own
+    DeviceMigrated { track_id: u8, device_id: String, fallback: bool },
+    
+    /// A track changed lifecycle state
+    TrackState(TrackTransition),
+    
+    /// Current bandwidth per track and in total, pushed every second
+    Bandwidth(BandwidthReport),
+    
     /// Error response
     Error { message: String },
     
@@ -216,10 +1017,95 @@ pub enum ControlMessage {
     
     /// Pong response
     Pong,
+    
+    /// First message of a client on the versioned channel: its version and
+    /// the features it wants (meter rate in Hz with binary meters)
+    Hello {
+        version: u16,
+        #[serde(default)]
+        features: Vec<String>,
+        #[serde(default)]
+        meter_hz: Option<u32>,
+    },
+    
+    /// Answer to Hello: the version both speak and the features granted
+    Welcome { version: u16, features: Vec<String>, meter_hz: u32 },
+}
+
+/// WebSocket subprotocol of the versioned web UI channel
+pub const WS_SUBPROTOCOL: &str = "las.v1";
+
+/// Web UI channel version spoken here
+pub const WS_PROTOCOL_VERSION: u16 = 1;
+
+/// Feature: track levels pushed as binary frames
+pub const WS_FEATURE_BINARY_METERS: &str = "binary_meters";
+
+/// Default binary meter rate (Hz)
+pub const DEFAULT_METER_HZ: u32 = 30;
+
+/// Highest binary meter rate a client can ask for (Hz)
+pub const MAX_METER_HZ: u32 = 60;
+
+/// First byte of a binary meter frame
+pub const METER_FRAME_KIND: u8 = 1;
+
+/// Bytes per track in a meter frame
+const METER_ENTRY_SIZE: usize = 9;
+
+/// One track's levels in a binary meter frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrackMeter {
+    pub track_id: u8,
+    /// Output level (dBFS)
+    pub level_db: f32,
+    /// Compressor/limiter gain reduction (dB)
+    pub gain_reduction_db: f32,
+}
+
+/// Pack track levels into a binary WebSocket frame
+///
+/// ```text
+/// kind (u8, 1) | count (u8) | count × [ track ID (u8) | level dBFS (f32 LE) | gain reduction dB (f32 LE) ]
+/// ```
+pub fn encode_meter_frame(meters: &[TrackMeter]) -> Vec<u8> {
+    let meters = &meters[..meters.len().min(u8::MAX as usize)];
+    let mut frame = Vec::with_capacity(2 + meters.len() * METER_ENTRY_SIZE);
+    frame.push(METER_FRAME_KIND);
+    frame.push(meters.len() as u8);
+    for meter in meters {
+        frame.push(meter.track_id);
+        frame.extend_from_slice(&meter.level_db.to_le_bytes());
+        frame.extend_from_slice(&meter.gain_reduction_db.to_le_bytes());
+    }
+    frame
+}
+
+/// Unpack a binary meter frame
+pub fn decode_meter_frame(frame: &[u8]) -> Result<Vec<TrackMeter>, PacketError> {
+    let [kind, count, entries @ ..] = frame else {
+        return Err(PacketError::Truncated(frame.len()));
+    };
+    if *kind != METER_FRAME_KIND {
+        return Err(PacketError::BadMagic(*kind as u16));
+    }
+    if entries.len() != *count as usize * METER_ENTRY_SIZE {
+        return Err(PacketError::Truncated(frame.len()));
+    }
+    let f32_at = |entry: &[u8], at: usize| f32::from_le_bytes(entry[at..at + 4].try_into().expect("4 bytes"));
+    Ok(entries
+        .chunks_exact(METER_ENTRY_SIZE)
+        .map(|entry| TrackMeter {
+            track_id: entry[0],
+            level_db: f32_at(entry, 1),
+            gain_reduction_db: f32_at(entry, 5),
+        })
+        .collect())
 }
 
 /// Track configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(remote = "Self", default)]
 pub struct TrackConfig {
     /// Track ID (optional, auto-assigned if not provided)
     pub track_id: Option<u8>,
@@ -244,6 +1130,89 @@ pub struct TrackConfig {
     
     /// Enable FEC (Forward Error Correction)
     pub fec_enabled: bool,
+    
+    /// Extra playout delay on the receiver in milliseconds
+    #[serde(default)]
+    pub playout_delay_ms: u32,
+    
+    /// Gain into the receiver mixdown bus in dB
+    #[serde(default)]
+    pub mix_gain_db: f32,
+    
+    /// Pan in the receiver mixdown bus (-1.0 left .. 1.0 right)
+    #[serde(default)]
+    pub mix_pan: f32,
+    
+    /// Send queue priority (None to derive it from the track type)
+    #[serde(default)]
+    pub priority: Option<TrackPriority>,
+    
+    /// Share of the bitrate budget relative to other tracks
+    #[serde(default = "default_bitrate_weight")]
+    pub bitrate_weight: f32,
+    
+    /// Receiver trim applied by the Opus decoder in dB
+    #[serde(default)]
+    pub decoder_gain_db: f32,
+    
+    /// Soft-clip decoded audio instead of letting it hard clip
+    #[serde(default)]
+    pub soft_clip: bool,
+    
+    /// Processing chain, applied in order
+    #[serde(default)]
+    pub processors: Vec<ProcessorConfig>,
+    
+    /// Profile (from `[profiles]` in the config) filling in unset settings
+    #[serde(default)]
+    pub profile: Option<String>,
+    
+    /// Label color in the UIs (`#rrggbb`)
+    #[serde(default)]
+    pub color: Option<String>,
+    
+    /// Publish only this channel of the device (0 = left), as a mono track
+    #[serde(default)]
+    pub source_channel: Option<u16>,
+    
+    /// Bring the track up when the sender boots (the first track always is)
+    #[serde(default)]
+    pub auto_start: bool,
+    
+    /// Device used while `device_id` is missing (None = the system default)
+    #[serde(default)]
+    pub fallback_device: Option<String>,
+    
+    /// Bitrates of extra encodings sent as simulcast layers 1, 2, ...
+    #[serde(default)]
+    pub simulcast_bitrates: Vec<u32>,
+    
+    /// Track group ("Guests", "Game") sharing mute, solo and gain
+    #[serde(default)]
+    pub group: Option<String>,
+    
+    /// Settings given in the config file or API request, which a profile
+    /// leaves alone (None when built in code: settings at their default
+    /// count as unset)
+    #[serde(skip)]
+    pub explicit: Option<BTreeSet<String>>,
+}
+
+impl Serialize for TrackConfig {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        TrackConfig::serialize(self, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for TrackConfig {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let fields = serde_json::Map::deserialize(deserializer)?;
+        let explicit = fields.keys().cloned().collect();
+        let mut config = TrackConfig::deserialize(serde_json::Value::Object(fields))
+            .map_err(serde::de::Error::custom)?;
+        config.explicit = Some(explicit);
+        Ok(config)
+    }
 }
 
 impl Default for TrackConfig {
@@ -257,6 +1226,72 @@ impl Default for TrackConfig {
             channels: 2,
             track_type: TrackType::Music,
             fec_enabled: false,
+            playout_delay_ms: 0,
+            mix_gain_db: 0.0,
+            mix_pan: 0.0,
+            priority: None,
+            bitrate_weight: 1.0,
+            decoder_gain_db: 0.0,
+            soft_clip: false,
+            processors: Vec::new(),
+            profile: None,
+            color: None,
+            source_channel: None,
+            auto_start: false,
+            fallback_device: None,
+            simulcast_bitrates: Vec::new(),
+            group: None,
+            explicit: None,
+        }
+    }
+}
+
+impl TrackConfig {
+    /// Effective send priority: explicit, or High for voice tracks
+    pub fn send_priority(&self) -> TrackPriority {
+        self.priority.unwrap_or(match self.track_type {
+            TrackType::Voice => TrackPriority::High,
+            _ => TrackPriority::Normal,
+        })
+    }
+    
+    /// Fill in settings from a profile
+    ///
+    /// A profile value replaces a setting the track did not give (see
+    /// [`explicit`](Self::explicit)), so anything set on the track itself
+    /// wins, even when that is the default.
+    pub fn with_profile(mut self, profile: &TrackProfile) -> Self {
+        let default = Self::default();
+        let explicit = self.explicit.take();
+        let given = |name: &str| explicit.as_ref().map(|fields| fields.contains(name));
+        fill(&mut self.bitrate, given("bitrate"), &default.bitrate, &profile.bitrate);
+        fill(&mut self.frame_size_ms, given("frame_size_ms"), &default.frame_size_ms, &profile.frame_size_ms);
+        fill(&mut self.channels, given("channels"), &default.channels, &profile.channels);
+        fill(&mut self.track_type, given("track_type"), &default.track_type, &profile.track_type);
+        fill(&mut self.fec_enabled, given("fec_enabled"), &default.fec_enabled, &profile.fec_enabled);
+        fill(&mut self.playout_delay_ms, given("playout_delay_ms"), &default.playout_delay_ms, &profile.playout_delay_ms);
+        fill(&mut self.mix_gain_db, given("mix_gain_db"), &default.mix_gain_db, &profile.mix_gain_db);
+        fill(&mut self.mix_pan, given("mix_pan"), &default.mix_pan, &profile.mix_pan);
+        fill(&mut self.bitrate_weight, given("bitrate_weight"), &default.bitrate_weight, &profile.bitrate_weight);
+        fill(&mut self.decoder_gain_db, given("decoder_gain_db"), &default.decoder_gain_db, &profile.decoder_gain_db);
+        fill(&mut self.soft_clip, given("soft_clip"), &default.soft_clip, &profile.soft_clip);
+        fill(&mut self.processors, given("processors"), &default.processors, &profile.processors);
+        fill(&mut self.priority, given("priority"), &default.priority, &profile.priority.map(Some));
+        self.explicit = explicit;
+        self
+    }
+}
+
+fn default_bitrate_weight() -> f32 {
+    1.0
+}
+
+/// Set `field` to `value` unless the track gave it (or, not knowing, it
+/// differs from `default`)
+fn fill<T: Clone + PartialEq>(field: &mut T, given: Option<bool>, default: &T, value: &Option<T>) {
+    if let Some(value) = value {
+        if !given.unwrap_or(field != default) {
+            *field = value.clone();
         }
     }
 }
@@ -269,48 +1304,507 @@ pub struct TrackConfigUpdate {
     pub bitrate: Option<u32>,
     pub frame_size_ms: Option<f32>,
     pub fec_enabled: Option<bool>,
+    pub playout_delay_ms: Option<u32>,
+    pub mix_gain_db: Option<f32>,
+    pub mix_pan: Option<f32>,
+    pub priority: Option<TrackPriority>,
+    pub bitrate_weight: Option<f32>,
+    pub decoder_gain_db: Option<f32>,
+    pub soft_clip: Option<bool>,
+    pub processors: Option<Vec<ProcessorConfig>>,
+    pub color: Option<String>,
+    pub track_type: Option<TrackType>,
+    pub auto_start: Option<bool>,
+    pub simulcast_bitrates: Option<Vec<u32>>,
+    /// Join a group (empty to leave it)
+    pub group: Option<String>,
+}
+
+/// Reusable codec and DSP defaults for tracks, referenced by name
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct TrackProfile {
+    pub bitrate: Option<u32>,
+    pub frame_size_ms: Option<f32>,
+    pub channels: Option<u16>,
+    pub track_type: Option<TrackType>,
+    pub fec_enabled: Option<bool>,
+    pub playout_delay_ms: Option<u32>,
+    pub mix_gain_db: Option<f32>,
+    pub mix_pan: Option<f32>,
+    pub priority: Option<TrackPriority>,
+    pub bitrate_weight: Option<f32>,
+    pub decoder_gain_db: Option<f32>,
+    pub soft_clip: Option<bool>,
+    pub processors: Option<Vec<ProcessorConfig>>,
+}
+
+/// A named group of tracks and its mixer state
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TrackGroup {
+    pub name: String,
+    pub tracks: Vec<u8>,
+    pub muted: bool,
+    pub solo: bool,
+    pub gain_db: f32,
+}
+
+/// Named snapshot of the mixer
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Scene {
+    pub name: String,
+    pub tracks: Vec<SceneTrack>,
+}
+
+/// One track's mixer state in a scene
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SceneTrack {
+    pub track_id: u8,
+    pub muted: bool,
+    pub solo: bool,
+    pub paused: bool,
+    pub decoder_gain_db: f32,
+    pub mix_gain_db: f32,
+    pub mix_pan: f32,
+    pub bitrate: u32,
+    /// Input (sender) or output (receiver) device
+    pub device_id: String,
+}
+
+/// Sender track settings the receiver may change (linked control)
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct RemoteTrackUpdate {
+    pub bitrate: Option<u32>,
+    pub fec_enabled: Option<bool>,
+    /// False pauses the track
+    pub enabled: Option<bool>,
+}
+
+/// A sender track as seen by the receiver's linked control
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RemoteTrackState {
+    pub track_id: u8,
+    pub name: String,
+    /// Configured bitrate (the budget may assign less)
+    pub bitrate: u32,
+    pub fec_enabled: bool,
+    pub enabled: bool,
+}
+
+/// A sender track's labels, carried to the receiver
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TrackInfo {
+    pub track_id: u8,
+    pub name: String,
+    pub color: Option<String>,
+    pub track_type: TrackType,
+}
+
+/// Protocol version spoken here
+pub const PROTOCOL_VERSION: u16 = 2;
+
+/// Version of peers that predate the handshake
+pub const LEGACY_PROTOCOL_VERSION: u16 = 1;
+
+/// Optional protocol features, as a bitmap
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProtocolFeatures(u32);
+
+impl ProtocolFeatures {
+    /// Opus in-band FEC in audio payloads
+    pub const OPUS_FEC: u32 = 0x01;
+    /// CRC flag and payload checksum
+    pub const PAYLOAD_CRC: u32 = 0x02;
+    /// Packets split into fragments to fit the path MTU
+    pub const FRAGMENTS: u32 = 0x04;
+    /// Several frames per datagram (reserved)
+    pub const AGGREGATION: u32 = 0x08;
+    /// Encrypted audio payloads (reserved)
+    pub const ENCRYPTION: u32 = 0x10;
+    /// Media clock timestamps and sender clock reports
+    pub const MEDIA_CLOCK: u32 = 0x20;
+    /// Simulcast layers beside a track's primary encoding
+    pub const SIMULCAST: u32 = 0x40;
+    
+    const NAMES: [(u32, &'static str); 7] = [
+        (Self::OPUS_FEC, "opus_fec"),
+        (Self::PAYLOAD_CRC, "payload_crc"),
+        (Self::FRAGMENTS, "fragments"),
+        (Self::AGGREGATION, "aggregation"),
+        (Self::ENCRYPTION, "encryption"),
+        (Self::MEDIA_CLOCK, "media_clock"),
+        (Self::SIMULCAST, "simulcast"),
+    ];
+    
+    pub const fn from_bits(bits: u32) -> Self {
+        Self(bits)
+    }
+    
+    pub fn bits(&self) -> u32 {
+        self.0
+    }
+    
+    /// Features this build speaks
+    pub const fn supported() -> Self {
+        Self(Self::OPUS_FEC | Self::PAYLOAD_CRC | Self::FRAGMENTS | Self::MEDIA_CLOCK | Self::SIMULCAST)
+    }
+    
+    /// Features of peers that predate the handshake
+    pub const fn legacy() -> Self {
+        Self(Self::OPUS_FEC)
+    }
+    
+    pub fn contains(&self, feature: u32) -> bool {
+        self.0 & feature == feature
+    }
+    
+    /// Features both sides have
+    pub fn common(&self, other: Self) -> Self {
+        Self(self.0 & other.0)
+    }
+    
+    /// Names of the known features set
+    pub fn names(&self) -> Vec<&'static str> {
+        Self::NAMES.iter().filter(|(bit, _)| self.contains(*bit)).map(|(_, name)| *name).collect()
+    }
+}
+
+/// What two ends agreed to speak
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Negotiated {
+    pub version: u16,
+    pub features: ProtocolFeatures,
+}
+
+impl Negotiated {
+    /// Agreement with a peer that said hello
+    pub fn with_peer(version: u16, features: ProtocolFeatures) -> Self {
+        Self {
+            version: version.min(PROTOCOL_VERSION),
+            features: ProtocolFeatures::supported().common(features),
+        }
+    }
+    
+    /// Assumed for a peer that hasn't said hello
+    pub fn legacy() -> Self {
+        Self {
+            version: LEGACY_PROTOCOL_VERSION,
+            features: ProtocolFeatures::supported().common(ProtocolFeatures::legacy()),
+        }
+    }
+}
+
+/// Codec name in [`ReceiverCapabilities::codecs`] for Opus tracks
+pub const CODEC_OPUS: &str = "opus";
+
+/// What a receiver can play as sent
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ReceiverCapabilities {
+    /// Sample rates its output plays natively; others are resampled
+    pub sample_rates: Vec<u32>,
+    /// Channel counts its output takes; others are up- or downmixed (mono
+    /// on a stereo output plays on both sides and is not a mismatch)
+    pub channels: Vec<u16>,
+    /// Codecs it decodes
+    pub codecs: Vec<String>,
+    /// Tracks it opens; packets for more are dropped
+    pub max_tracks: u16,
+}
+
+impl ReceiverCapabilities {
+    /// What a receiver playing to `output` that opens `max_tracks` tracks can take
+    pub fn for_output(output: Option<&AudioDeviceInfo>, max_tracks: usize) -> Self {
+        Self {
+            sample_rates: output.map(|d| d.sample_rates.clone()).unwrap_or_default(),
+            channels: output.map(|d| d.channels.clone()).unwrap_or_default(),
+            codecs: vec![CODEC_OPUS.to_string()],
+            max_tracks: max_tracks.min(u16::MAX as usize) as u16,
+        }
+    }
+    
+    /// Ways the receiver would not play a track as sent
+    /// 
+    /// `index` is the track's position among the sender's tracks by ID, and
+    /// `sample_rate` the rate it is sent at. Empty lists are not checked.
+    pub fn check(&self, track: &TrackConfig, index: usize, sample_rate: u32) -> Vec<CapabilityMismatch> {
+        let mut mismatches = Vec::new();
+        if !self.codecs.is_empty() && !self.codecs.iter().any(|c| c == CODEC_OPUS) {
+            mismatches.push(CapabilityMismatch::Codec { codec: CODEC_OPUS.to_string() });
+        }
+        if !self.sample_rates.is_empty() && !self.sample_rates.contains(&sample_rate) {
+            mismatches.push(CapabilityMismatch::SampleRate { sent: sample_rate, supported: self.sample_rates.clone() });
+        }
+        let channels = if track.source_channel.is_some() { 1 } else { track.channels };
+        // Mono goes out to both sides of a stereo output unchanged
+        let plays = self.channels.contains(&channels) || (channels == 1 && self.channels.contains(&2));
+        if !self.channels.is_empty() && !plays {
+            mismatches.push(CapabilityMismatch::Channels { sent: channels, supported: self.channels.clone() });
+        }
+        if index >= self.max_tracks as usize {
+            mismatches.push(CapabilityMismatch::TooManyTracks { max_tracks: self.max_tracks });
+        }
+        mismatches
+    }
+}
+
+/// Receiver capabilities with the sender tracks that don't fit them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CapabilityReport {
+    pub capabilities: ReceiverCapabilities,
+    /// Track -> what doesn't fit; tracks that fit are left out
+    pub tracks: BTreeMap<u8, Vec<CapabilityMismatch>>,
+}
+
+/// A track setting the receiver cannot play as sent
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CapabilityMismatch {
+    /// The receiver does not decode the codec; the track is dropped
+    Codec { codec: String },
+    /// Resampled on the receiver
+    SampleRate { sent: u32, supported: Vec<u32> },
+    /// Up- or downmixed on the receiver
+    Channels { sent: u16, supported: Vec<u16> },
+    /// Beyond the receiver's track limit; the track is dropped
+    TooManyTracks { max_tracks: u16 },
+}
+
+impl std::fmt::Display for CapabilityMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Codec { codec } => write!(f, "receiver does not decode {}", codec),
+            Self::SampleRate { sent, supported } => {
+                write!(f, "sent at {} Hz, receiver plays {:?} Hz (resampled)", sent, supported)
+            }
+            Self::Channels { sent, supported } => {
+                write!(f, "{} channels sent, receiver outputs {:?} (remixed)", sent, supported)
+            }
+            Self::TooManyTracks { max_tracks } => write!(f, "receiver opens only {} tracks (dropped)", max_tracks),
+        }
+    }
 }
 
 /// Track type for Opus optimization
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
 pub enum TrackType {
     /// Voice/speech - optimized for intelligibility
     Voice,
     /// Music - optimized for audio quality
+    #[default]
     Music,
     /// Low latency - minimal algorithmic delay
     LowLatency,
 }
 
-impl Default for TrackType {
-    fn default() -> Self {
-        Self::Music
+/// Network send priority
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TrackPriority {
+    /// Sent ahead of normal packets queued in the same tick
+    High,
+    #[default]
+    Normal,
+}
+
+/// Signal problem detected on a track
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertKind {
+    /// Sustained samples at or above 0 dBFS
+    Clipping,
+    /// Level below the silence threshold for too long
+    Silence,
+    /// Packet loss above the configured threshold
+    PacketLoss,
+    /// Audio device reported an error or went away
+    DeviceLost,
+    /// Receiver stopped getting packets without the sender pausing
+    ReceiverTimeout,
+    /// Capture is picking up received audio (full duplex)
+    Feedback,
+    /// Total bandwidth above `alerts.bandwidth_cap_kbps`
+    BandwidthCap,
+    /// Receiver resamples, remixes or drops the track as sent
+    Incompatible,
+}
+
+impl AlertKind {
+    /// Human-readable description
+    pub fn description(&self) -> &'static str {
+        match self {
+            AlertKind::Clipping => "sustained clipping",
+            AlertKind::Silence => "unexpected silence",
+            AlertKind::PacketLoss => "high packet loss",
+            AlertKind::DeviceLost => "audio device lost",
+            AlertKind::ReceiverTimeout => "no packets from sender",
+            AlertKind::Feedback => "audio feedback loop",
+            AlertKind::BandwidthCap => "bandwidth over cap",
+            AlertKind::Incompatible => "receiver can't play track as sent",
+        }
     }
 }
 
+/// Which way a buffer ran over
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum XrunKind {
+    /// Capture buffer full; captured audio was dropped
+    Overflow,
+    /// Playback ran out of audio and played silence
+    Underrun,
+}
+
+/// Start of an xrun in an audio callback
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct XrunEvent {
+    pub track_id: u8,
+    pub kind: XrunKind,
+    /// Wall-clock time in microseconds since the UNIX epoch
+    pub timestamp_us: u64,
+    /// Frames buffered when it happened
+    pub buffer_fill: usize,
+    pub buffer_capacity: usize,
+    /// Sample frames the device asked for or delivered in the callback
+    pub callback_frames: usize,
+}
+
 /// Track status information
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TrackStatus {
     pub track_id: u8,
     pub name: String,
+    #[serde(default)]
+    pub color: Option<String>,
+    #[serde(default)]
+    pub track_type: TrackType,
     pub device_id: String,
     pub active: bool,
+    #[serde(default)]
+    pub state: TrackState,
     pub muted: bool,
     pub solo: bool,
+    pub paused: bool,
+    pub gate_open: bool,
+    pub gain_reduction_db: f32,
     pub bitrate: u32,
     pub frame_size_ms: f32,
+    pub playout_delay_ms: u32,
     pub packets_sent: u64,
     pub packets_received: u64,
     pub packets_lost: u64,
     pub current_latency_ms: f32,
     pub jitter_ms: f32,
     pub level_db: f32,
+    /// Why the receiver can't play the track as sent (sender)
+    #[serde(default)]
+    pub receiver_mismatches: Vec<String>,
+}
+
+/// Track configuration together with its live status
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrackSnapshot {
+    pub config: TrackConfig,
+    pub status: TrackStatus,
+}
+
+/// Alert currently raised on a track
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ActiveAlert {
+    pub track_id: u8,
+    pub kind: AlertKind,
+}
+
+/// Everything a UI needs to render from scratch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateSnapshot {
+    pub is_sender: bool,
+    pub tracks: Vec<TrackSnapshot>,
+    pub devices: Vec<AudioDeviceInfo>,
+    pub bitrate_budget: u32,
+    pub all_paused: bool,
+    pub alerts: Vec<ActiveAlert>,
+    pub session: SessionStats,
+    #[serde(default)]
+    pub groups: Vec<TrackGroup>,
+    /// Most tracks this instance opens (`audio.max_tracks`)
+    #[serde(default)]
+    pub max_tracks: usize,
+}
+
+/// Statistics since the process started
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SessionStats {
+    pub uptime_seconds: u64,
+    /// Audio payload bytes sent or received across all tracks
+    pub bytes_total: u64,
+    /// Streams that resumed after a timeout or end of stream
+    pub reconnects: u64,
+    /// Most tracks active at the same time
+    pub peak_tracks: usize,
+    pub tracks: Vec<TrackSessionStats>,
+}
+
+/// Overall health, least to most severe
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum HealthLevel {
+    #[default]
+    Ok,
+    Degraded,
+    Critical,
+}
+
+/// Problem that feeds into the health level
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthSignal {
+    /// Capture overflows or playback underruns
+    Xrun,
+    /// Network packet loss
+    PacketLoss,
+    /// Frames that took longer to process and encode than they last
+    DeadlineMiss,
+    /// Audio device errors
+    DeviceError,
+}
+
+/// One contributor to a degraded or critical health level
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthReason {
+    pub signal: HealthSignal,
+    pub track_id: Option<u8>,
+    pub level: HealthLevel,
+    /// Count over the window, or percent for packet loss
+    pub value: f64,
+    pub message: String,
+}
+
+/// Health over the recent window
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HealthReport {
+    pub status: HealthLevel,
+    pub window_secs: u32,
+    pub reasons: Vec<HealthReason>,
+}
+
+/// Per-track session statistics
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TrackSessionStats {
+    pub track_id: u8,
+    /// Total time the track has been running
+    pub active_seconds: f64,
+    pub bytes: u64,
 }
 
 /// Audio device information
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AudioDeviceInfo {
     pub id: String,
+    /// Survives renames: the endpoint ID on Windows
+    #[serde(default)]
+    pub stable_id: String,
+    /// Names from `[device_aliases]` pointing at this device
+    #[serde(default)]
+    pub aliases: Vec<String>,
     pub name: String,
     pub is_input: bool,
     pub is_output: bool,
@@ -322,11 +1816,13 @@ pub struct AudioDeviceInfo {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::constants::MAX_PACKET_SIZE;
     
     #[test]
     fn test_packet_serialization() {
         let packet = AudioPacket {
             track_id: 5,
+            layer: 0,
             flags: PacketFlags::new().set_stereo(true).set_keyframe(true),
             sequence: 12345,
             timestamp: 9876543210,
@@ -344,6 +1840,180 @@ mod tests {
         assert_eq!(deserialized.payload.as_ref(), &[1, 2, 3, 4, 5]);
     }
     
+    #[test]
+    fn test_peer_message_roundtrip() {
+        let msg = PeerMessage::ClockResponse { t0: 1, t1: 2, t2: 3 };
+        let data = msg.serialize();
+        
+        assert_eq!(packet_magic(&data), Some(CONTROL_MAGIC));
+        assert_eq!(PeerMessage::deserialize(&data), Some(msg));
+        
+        // Audio packets are not control packets
+        let audio = AudioPacket::new(0, 0, 0, Bytes::from_static(&[1, 2, 3])).serialize();
+        assert!(PeerMessage::deserialize(&audio).is_none());
+    }
+    
+    #[test]
+    fn test_parse_packet_checks() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        
+        let mut packet = AudioPacket::new(1, 7, 100, Bytes::from_static(&[9, 8, 7]));
+        packet.flags = packet.flags.set_crc(true);
+        let data = packet.serialize();
+        assert_eq!(data.len(), packet.total_size());
+        
+        match parse_packet(&data) {
+            Ok(Packet::Audio(parsed)) => assert_eq!(parsed.payload.as_ref(), &[9, 8, 7]),
+            other => panic!("unexpected {:?}", other),
+        }
+        
+        // Corrupted payload
+        let mut corrupt = data.to_vec();
+        *corrupt.last_mut().unwrap() ^= 0xFF;
+        assert_eq!(parse_packet(&corrupt).unwrap_err(), PacketError::ChecksumMismatch);
+        
+        // Every truncation is rejected without panicking
+        for len in 0..HEADER_SIZE + CRC_SIZE {
+            assert!(parse_packet(&data[..len]).is_err());
+        }
+        assert_eq!(parse_packet(&[0x00, 0x00, 0x00]).unwrap_err(), PacketError::BadMagic(0));
+        assert_eq!(parse_packet(&[0x02, 0xAF, 0xFF]).unwrap_err(), PacketError::BadControl);
+    }
+    
+    #[test]
+    fn test_receiver_capabilities_check() {
+        let capabilities = ReceiverCapabilities {
+            sample_rates: vec![44100],
+            channels: vec![2],
+            codecs: vec![CODEC_OPUS.to_string()],
+            max_tracks: 2,
+        };
+        let stereo = TrackConfig { channels: 2, ..Default::default() };
+        assert!(capabilities.check(&stereo, 0, 44100).is_empty());
+        assert_eq!(
+            capabilities.check(&stereo, 1, 48000),
+            vec![CapabilityMismatch::SampleRate { sent: 48000, supported: vec![44100] }]
+        );
+        
+        // A split channel goes out mono, which a stereo output plays as is;
+        // the third track is past the limit
+        let split = TrackConfig { channels: 2, source_channel: Some(1), ..Default::default() };
+        let mismatches = capabilities.check(&split, 2, 44100);
+        assert_eq!(mismatches, vec![CapabilityMismatch::TooManyTracks { max_tracks: 2 }]);
+        assert_eq!(mismatches[0].to_string(), "receiver opens only 2 tracks (dropped)");
+        
+        // Stereo on a mono output is downmixed
+        let mono_out = ReceiverCapabilities { channels: vec![1], ..capabilities.clone() };
+        assert_eq!(
+            mono_out.check(&stereo, 0, 44100),
+            vec![CapabilityMismatch::Channels { sent: 2, supported: vec![1] }]
+        );
+        
+        // Nothing reported is nothing checked, but a missing codec is
+        let opus_less = ReceiverCapabilities { codecs: vec!["pcm".to_string()], max_tracks: 16, ..Default::default() };
+        assert_eq!(
+            opus_less.check(&stereo, 0, 48000),
+            vec![CapabilityMismatch::Codec { codec: CODEC_OPUS.to_string() }]
+        );
+    }
+    
+    #[test]
+    fn test_packet_buffer_in_place() {
+        let mut buffer = PacketBuffer::with_capacity(64);
+        let written = buffer.fill(64, |out| {
+            out[..3].copy_from_slice(&[7, 8, 9]);
+            Ok::<_, ()>(3)
+        });
+        assert_eq!(written, Ok(3));
+        assert_eq!(buffer.payload(), &[7, 8, 9]);
+        let address = buffer.payload().as_ptr();
+        
+        // Same bytes as serialize, with and without a checksum and layer, in the same allocation
+        for (crc, layer) in [(false, 0), (true, 0), (false, 2), (true, 1)] {
+            let flags = PacketFlags::new().set_stereo(true).set_crc(crc);
+            let expected = AudioPacket { layer, flags, ..AudioPacket::new(5, 11, 123_456, Bytes::from_static(&[7, 8, 9])) };
+            let datagram = buffer.clone().finish(5, layer, flags, 11, 123_456);
+            assert_eq!(datagram, expected.serialize());
+            assert_eq!(datagram.len(), expected.total_size());
+            assert_eq!(PacketFlags::from_byte(datagram[3]).has_layer(), layer != 0);
+            let parsed = AudioPacket::parse(datagram).unwrap();
+            assert_eq!((parsed.track_id, parsed.layer, parsed.sequence, &parsed.payload[..]), (5, layer, 11, &[7u8, 8, 9][..]));
+        }
+        let datagram = buffer.finish(5, 0, PacketFlags::new(), 11, 0);
+        assert_eq!(datagram[HEADER_SIZE..].as_ptr(), address);
+        
+        // A failed write leaves an empty payload
+        let mut buffer = PacketBuffer::from(&[1u8, 2][..]);
+        assert!(buffer.fill(16, |_| Err("full")).is_err());
+        assert!(buffer.is_empty());
+        assert!(buffer.into_payload().is_empty());
+        
+        // Bytes are kept as they are until the datagram is built
+        let payload = Bytes::from_static(&[7, 8, 9]);
+        let shared = PacketBuffer::from(payload.clone());
+        assert_eq!(shared.payload().as_ptr(), payload.as_ptr());
+        assert_eq!(shared.clone().into_payload().as_ptr(), payload.as_ptr());
+        let expected = AudioPacket::new(5, 11, 0, payload).serialize();
+        assert_eq!(shared.finish(5, 0, PacketFlags::new(), 11, 0), expected);
+    }
+    
+    #[test]
+    fn test_packet_pool() {
+        let mut pool = PacketPool::default();
+        let write = |bytes: &'static [u8]| move |out: &mut [u8]| {
+            out[..bytes.len()].copy_from_slice(bytes);
+            Ok::<_, ()>(bytes.len())
+        };
+        let a = pool.fill(MAX_PACKET_SIZE, write(&[1, 2, 3])).unwrap();
+        let b = pool.fill(MAX_PACKET_SIZE, write(&[4, 5])).unwrap();
+        assert_eq!((a.payload(), b.payload()), (&[1u8, 2, 3][..], &[4u8, 5][..]));
+        // Packets take only what they use, one after the other in the block
+        assert_eq!(b.payload().as_ptr(), a.payload()[3..].as_ptr().wrapping_add(HEADER_ROOM));
+        assert!(pool.fill(MAX_PACKET_SIZE, |_| Err(())).is_err());
+        
+        let datagram = a.finish(1, 0, PacketFlags::new(), 0, 0);
+        assert_eq!(AudioPacket::parse(datagram).unwrap().payload, Bytes::from_static(&[1, 2, 3]));
+        assert_eq!(b.into_payload(), Bytes::from_static(&[4, 5]));
+    }
+    
+    #[test]
+    fn test_fragment_roundtrip() {
+        let payload: Vec<u8> = (0..4000).map(|i| i as u8).collect();
+        let mut packet = AudioPacket::new(3, 42, 1000, Bytes::from(payload.clone()));
+        packet.flags = packet.flags.set_crc(true).set_stereo(true);
+        let datagrams = packet.serialize_fragments(MAX_PACKET_SIZE).unwrap();
+        assert_eq!(datagrams.len(), 3);
+        
+        // Reverse order still reassembles
+        let mut reassembler = Reassembler::new();
+        let mut joined = None;
+        for data in datagrams.iter().rev() {
+            assert!(data.len() <= MAX_PACKET_SIZE);
+            assert!(PacketFlags::from_byte(data[3]).is_fragment());
+            assert!(AudioPacket::parse(data.clone()).is_err());
+            match parse_packet(data) {
+                Ok(Packet::Fragment(fragment)) => joined = reassembler.push(1u8, fragment),
+                other => panic!("unexpected {:?}", other),
+            }
+        }
+        let joined = joined.unwrap();
+        assert_eq!(joined.payload.as_ref(), payload.as_slice());
+        assert!(joined.flags.is_stereo() && !joined.flags.is_fragment());
+        assert_eq!(reassembler.pending(), 0);
+        
+        // Small packets go out whole; too many fragments are refused
+        let small = AudioPacket::new(3, 43, 1000, Bytes::from_static(&[1; 100]));
+        assert_eq!(small.serialize_fragments(8000).unwrap().len(), 1);
+        // Never more payload per datagram than receivers accept
+        assert_eq!(packet.serialize_fragments(8000).unwrap().len(), 3);
+        assert!(matches!(packet.serialize_fragments(60), Err(PacketError::PayloadTooLarge(_))));
+        
+        // Index past the count
+        let mut bad = datagrams[0].to_vec();
+        bad[HEADER_SIZE] = 3;
+        assert_eq!(parse_packet(&bad).unwrap_err(), PacketError::BadFragment);
+    }
+    
     #[test]
     fn test_flags() {
         let flags = PacketFlags::new()
@@ -356,4 +2026,203 @@ mod tests {
         assert!(flags.has_fec());
         assert_eq!(flags.as_byte(), 0x07);
     }
+    
+    #[test]
+    fn test_meter_frame() {
+        let meters = vec![
+            TrackMeter { track_id: 0, level_db: -12.5, gain_reduction_db: 0.0 },
+            TrackMeter { track_id: 7, level_db: -96.0, gain_reduction_db: 3.25 },
+        ];
+        let frame = encode_meter_frame(&meters);
+        assert_eq!(frame.len(), 2 + 2 * 9);
+        assert_eq!(&frame[..3], &[METER_FRAME_KIND, 2, 0]);
+        assert_eq!(decode_meter_frame(&frame).unwrap(), meters);
+        assert_eq!(decode_meter_frame(&encode_meter_frame(&[])).unwrap(), vec![]);
+        assert!(decode_meter_frame(&frame[..frame.len() - 1]).is_err());
+        assert!(decode_meter_frame(&[2, 0]).is_err());
+        
+        // The handshake is plain JSON
+        let hello: ControlMessage = serde_json::from_str(r#"{"type":"Hello","data":{"version":1,"features":["binary_meters"]}}"#).unwrap();
+        assert!(matches!(hello, ControlMessage::Hello { version: 1, meter_hz: None, ref features } if features == &[WS_FEATURE_BINARY_METERS]));
+    }
+    
+    #[test]
+    fn test_profile_fills_unset() {
+        let profile = TrackProfile {
+            bitrate: Some(64_000),
+            channels: Some(1),
+            ..Default::default()
+        };
+        
+        // A setting given at its default value still wins over the profile
+        let given: TrackConfig = toml::from_str("name = \"Mic\"\nbitrate = 128000\nprofile = \"Voice\"").unwrap();
+        let track = given.with_profile(&profile);
+        assert_eq!((track.bitrate, track.channels), (128_000, 1));
+        assert!(track.explicit.as_ref().is_some_and(|fields| fields.contains("bitrate")));
+        
+        // Built in code, a setting at its default counts as unset
+        let built = TrackConfig { channels: 2, bitrate: 96_000, ..Default::default() }.with_profile(&profile);
+        assert_eq!((built.bitrate, built.channels), (96_000, 1));
+        
+        // The round trip is unchanged
+        let json = serde_json::to_string(&track).unwrap();
+        let back: TrackConfig = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.name, "Mic");
+        assert!(!json.contains("explicit"));
+    }
+    
+    #[test]
+    fn test_control_signature() {
+        let message = PeerMessage::EndOfStream.serialize();
+        let signed = sign_control(&message, b"secret", 7);
+        assert_eq!(signed.len(), message.len() + SIGNATURE_SIZE);
+        assert_eq!(verify_control(&signed, b"secret"), Ok((message.as_ref(), 7)));
+        assert_eq!(verify_control(&signed, b"guess"), Err(PacketError::BadSignature));
+        
+        // A forged length prefix is refused instead of trusted
+        let mut probe = PeerMessage::MtuProbe { round: 1, size: 1400, padding: Vec::new() }.serialize().to_vec();
+        let len = probe.len();
+        probe[len - 8..].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(PeerMessage::deserialize(&probe).is_none());
+    }
+    
+    mod properties {
+        use super::*;
+        use proptest::prelude::*;
+        
+        /// Fixed seed so every run checks the same cases
+        fn config() -> ProptestConfig {
+            ProptestConfig {
+                rng_seed: proptest::test_runner::RngSeed::Fixed(0x1A5),
+                failure_persistence: None,
+                ..ProptestConfig::default()
+            }
+        }
+        
+        fn audio_packet(max_payload: usize) -> impl Strategy<Value = AudioPacket> {
+            // Any flags but FRAG, which only fragments carry
+            (any::<u8>(), any::<u8>(), any::<u8>(), any::<u32>(), any::<u64>(), prop::collection::vec(any::<u8>(), 0..=max_payload))
+                .prop_map(|(track_id, layer, flags, sequence, timestamp, payload)| AudioPacket {
+                    track_id,
+                    layer: layer % 4,
+                    flags: PacketFlags::from_byte(flags).set_fragment(false),
+                    sequence,
+                    timestamp,
+                    payload: Bytes::from(payload),
+                })
+        }
+        
+        proptest! {
+            #![proptest_config(config())]
+            
+            #[test]
+            fn packet_roundtrip(packet in audio_packet(MAX_PAYLOAD_SIZE)) {
+                let data = packet.serialize();
+                prop_assert_eq!(data.len(), packet.total_size());
+                let parsed = match parse_packet(&data) {
+                    Ok(Packet::Audio(parsed)) => parsed,
+                    other => return Err(TestCaseError::fail(format!("unexpected {:?}", other))),
+                };
+                prop_assert_eq!(parsed.serialize(), data);
+            }
+            
+            #[test]
+            fn fragments_reassemble_in_any_order(
+                (packet, mtu, order) in (audio_packet(8000), 200usize..1500)
+                    .prop_flat_map(|(packet, mtu)| {
+                        let count = packet.serialize_fragments(mtu).map_or(0, |d| d.len());
+                        (Just(packet), Just(mtu), Just((0..count).collect::<Vec<_>>()).prop_shuffle())
+                    })
+            ) {
+                let Ok(datagrams) = packet.serialize_fragments(mtu) else {
+                    return Ok(());
+                };
+                let mut reassembler = Reassembler::new();
+                let mut joined = None;
+                for index in order {
+                    prop_assert!(datagrams[index].len() <= mtu.max(packet.total_size()));
+                    match parse_packet(&datagrams[index]) {
+                        Ok(Packet::Audio(whole)) => joined = Some(whole),
+                        Ok(Packet::Fragment(fragment)) => joined = joined.or(reassembler.push(0u8, fragment)),
+                        other => return Err(TestCaseError::fail(format!("unexpected {:?}", other))),
+                    }
+                }
+                let joined = joined.expect("all fragments in");
+                prop_assert_eq!(joined.serialize(), packet.serialize());
+                prop_assert_eq!(reassembler.pending(), 0);
+            }
+            
+            #[test]
+            fn peer_message_roundtrip(
+                message in prop_oneof![
+                    any::<u64>().prop_map(|t0| PeerMessage::ClockRequest { t0 }),
+                    (any::<u64>(), any::<u64>(), any::<u64>()).prop_map(|(t0, t1, t2)| PeerMessage::ClockResponse { t0, t1, t2 }),
+                    (any::<u8>(), any::<bool>()).prop_map(|(track_id, paused)| PeerMessage::StreamPaused { track_id, paused }),
+                    prop::collection::vec(any::<u8>(), 0..32).prop_map(|tracks| PeerMessage::Subscribe { tracks }),
+                    prop::collection::vec(any::<u8>(), 0..8).prop_map(|layers| PeerMessage::SubscribeLayers { layers }),
+                    (any::<u32>(), any::<u16>(), prop::collection::vec(any::<u8>(), 0..1500))
+                        .prop_map(|(round, size, padding)| PeerMessage::MtuProbe { round, size, padding }),
+                    ".{0,64}".prop_map(|session| PeerMessage::Rendezvous { session }),
+                    (prop::collection::vec(any::<u32>(), 0..8), prop::collection::vec(any::<u16>(), 0..8), any::<u16>())
+                        .prop_map(|(sample_rates, channels, max_tracks)| PeerMessage::Capabilities(ReceiverCapabilities {
+                            sample_rates,
+                            channels,
+                            codecs: vec![CODEC_OPUS.to_string()],
+                            max_tracks,
+                        })),
+                    (any::<u8>(), any::<bool>()).prop_map(|(track_id, by_sender)| PeerMessage::ResetTrack { track_id, by_sender }),
+                    (any::<u64>(), any::<u8>()).prop_map(|(session, link)| PeerMessage::Link { session, link }),
+                    (any::<u8>(), any::<u64>(), any::<u64>()).prop_map(|(track_id, ticks, wall_us)| PeerMessage::MediaClock {
+                        track_id,
+                        reference: MediaClockRef { ticks, wall_us },
+                    }),
+                    (any::<u16>(), any::<u32>(), any::<bool>()).prop_map(|(version, features, reply)| PeerMessage::Hello {
+                        version,
+                        features: ProtocolFeatures::from_bits(features),
+                        reply,
+                    }),
+                ]
+            ) {
+                let data = message.serialize();
+                prop_assert_eq!(packet_magic(&data), Some(CONTROL_MAGIC));
+                prop_assert_eq!(PeerMessage::deserialize(&data), Some(message));
+            }
+            
+            #[test]
+            fn signed_control_detects_any_bit_flip(
+                message in prop::collection::vec(any::<u8>(), 2..256),
+                key in prop::collection::vec(any::<u8>(), 0..64),
+                counter in any::<u64>(),
+                bit in any::<prop::sample::Index>(),
+            ) {
+                let signed = sign_control(&message, &key, counter);
+                prop_assert_eq!(verify_control(&signed, &key), Ok((message.as_slice(), counter)));
+                let mut flipped = signed.clone();
+                let bit = bit.index(flipped.len() * 8);
+                flipped[bit / 8] ^= 1 << (bit % 8);
+                prop_assert!(verify_control(&flipped, &key).is_err());
+            }
+            
+            #[test]
+            fn arbitrary_datagrams_uphold_invariants(data in prop::collection::vec(any::<u8>(), 0..2048)) {
+                fuzz::packet(&data);
+                fuzz::control(&data);
+                fuzz::reassembly(&data);
+            }
+            
+            #[test]
+            fn mutated_packets_uphold_invariants(
+                packet in audio_packet(256),
+                mutations in prop::collection::vec((any::<prop::sample::Index>(), any::<u8>()), 1..8),
+            ) {
+                // Valid packets with a few bytes changed get past the magic check far more often
+                let mut data = packet.serialize().to_vec();
+                for (index, value) in mutations {
+                    let index = index.index(data.len());
+                    data[index] = value;
+                }
+                fuzz::packet(&data);
+            }
+        }
+    }
 }
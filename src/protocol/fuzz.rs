@@ -0,0 +1,105 @@
+//! Fuzz entry points
+//!
+//! Each function takes arbitrary bytes, runs them through a parser and
+//! panics if an invariant breaks; a clean return means the input was
+//! handled, accepted or not. The cargo-fuzz targets in `fuzz/` call them
+//! through the `fuzz` feature, and the tests run them over the checked-in
+//! corpus in `fuzz/corpus/`, so inputs found by fuzzing become regression
+//! tests by being added there.
+
+use super::*;
+
+/// Key the control target signs and verifies with
+const FUZZ_KEY: &[u8] = b"fuzz";
+
+/// One datagram from the audio socket
+///
+/// Audio packets must serialize back to the same bytes, fragments must be
+/// in range and control messages must survive a round trip.
+pub fn packet(data: &[u8]) {
+    match parse_packet(data) {
+        Ok(Packet::Audio(packet)) => {
+            assert!(packet.payload.len() <= MAX_PAYLOAD_SIZE);
+            assert!(!packet.flags.is_fragment());
+            assert_eq!(packet.serialize().as_ref(), data);
+        }
+        Ok(Packet::Fragment(fragment)) => {
+            assert!(fragment.index < fragment.count);
+            assert!(fragment.count as usize <= MAX_FRAGMENTS);
+            assert!(fragment.packet.payload.len() <= MAX_PAYLOAD_SIZE);
+        }
+        Ok(Packet::Control(message)) => {
+            // Compared as bytes: float fields may decode to NaN
+            let serialized = message.serialize();
+            let again = PeerMessage::deserialize(&serialized).expect("serialized message parses");
+            assert_eq!(again.serialize(), serialized);
+        }
+        Err(_) => {}
+    }
+}
+
+/// One signed control datagram
+///
+/// Verification must not panic, and signing any bytes must verify back to them.
+pub fn control(data: &[u8]) {
+    if let Ok((message, _)) = verify_control(data, FUZZ_KEY) {
+        assert_eq!(message.len() + SIGNATURE_SIZE, data.len());
+    }
+    let counter = data.get(..8).map_or(0, |bytes| u64::from_le_bytes(bytes.try_into().unwrap_or_default()));
+    let signed = sign_control(data, FUZZ_KEY, counter);
+    if data.len() >= 2 {
+        assert_eq!(verify_control(&signed, FUZZ_KEY), Ok((data, counter)));
+    }
+}
+
+/// A run of datagrams from several sources into one [`Reassembler`]
+///
+/// `data` is records of source (1 byte), length (1 byte) and datagram.
+/// Reassembled packets are bounded and the pending set stays capped.
+pub fn reassembly(data: &[u8]) {
+    let mut reassembler = Reassembler::new();
+    let mut rest = data;
+    while let [source, len, tail @ ..] = rest {
+        let (datagram, next) = tail.split_at((*len as usize).min(tail.len()));
+        rest = next;
+        if let Ok(Packet::Fragment(fragment)) = parse_packet(datagram) {
+            if let Some(packet) = reassembler.push(*source, fragment) {
+                assert!(packet.payload.len() <= MAX_FRAGMENTS * MAX_PAYLOAD_SIZE);
+                assert!(!packet.flags.is_fragment());
+            }
+        }
+        assert!(reassembler.pending() <= MAX_PENDING_REASSEMBLY);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    /// Run `target` over every file in `fuzz/corpus/<name>`
+    fn run_corpus(name: &str, target: fn(&[u8])) -> usize {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("fuzz/corpus").join(name);
+        let mut count = 0;
+        for entry in std::fs::read_dir(&dir).unwrap() {
+            let path = entry.unwrap().path();
+            target(&std::fs::read(&path).unwrap());
+            count += 1;
+        }
+        count
+    }
+
+    #[test]
+    fn test_fuzz_corpus() {
+        assert!(run_corpus("packet", packet) > 0);
+        assert!(run_corpus("control", control) > 0);
+        assert!(run_corpus("reassembly", reassembly) > 0);
+
+        // Edge inputs every target must take
+        for data in [&[][..], &[0x01], &[0x01, 0xAF], &[0x02, 0xAF], &[0xFF; 64]] {
+            packet(data);
+            control(data);
+            reassembly(data);
+        }
+    }
+}
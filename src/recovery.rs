@@ -0,0 +1,166 @@
+//! Error recovery
+//!
+//! Capture, playback and network components each follow a
+//! [`RecoveryPolicy`]: a failure schedules a restart after an exponential
+//! backoff, and once the retries run out the component is either left
+//! stopped or the whole process shuts down. The audio loops poll
+//! [`Recovery::due`] so a pending restart never blocks them.
+
+use std::fmt::Display;
+use std::time::{Duration, Instant};
+
+use crate::config::{GiveUpAction, RecoveryPolicy};
+use crate::shutdown::ShutdownCoordinator;
+
+/// Wait before retry number `attempt` (0-based)
+pub fn backoff(policy: &RecoveryPolicy, attempt: u32) -> Duration {
+    let ms = policy.initial_backoff_ms as f64 * policy.multiplier.max(1.0).powi(attempt.min(64) as i32);
+    Duration::from_millis(ms.min(policy.max_backoff_ms.max(policy.initial_backoff_ms) as f64) as u64)
+}
+
+/// Retry state of one component
+pub struct Recovery {
+    name: String,
+    policy: RecoveryPolicy,
+    shutdown: ShutdownCoordinator,
+    /// Retries since the last quiet period
+    attempts: u32,
+    retry_at: Option<Instant>,
+    last_failure: Option<Instant>,
+    gave_up: bool,
+}
+
+impl Recovery {
+    /// Track recovery of the component called `name` in logs
+    pub fn new(name: impl Into<String>, policy: RecoveryPolicy, shutdown: ShutdownCoordinator) -> Self {
+        Self {
+            name: name.into(),
+            policy,
+            shutdown,
+            attempts: 0,
+            retry_at: None,
+            last_failure: None,
+            gave_up: false,
+        }
+    }
+
+    /// Report a failure; schedules a retry or gives up
+    ///
+    /// Returns false once the component has given up. Failures reported
+    /// while a retry is already pending are ignored.
+    pub fn fail(&mut self, error: &dyn Display) -> bool {
+        self.fail_at(error, Instant::now())
+    }
+
+    fn fail_at(&mut self, error: &dyn Display, now: Instant) -> bool {
+        if self.gave_up {
+            return false;
+        }
+        if self.retry_at.is_some() {
+            return true;
+        }
+
+        let quiet = Duration::from_secs(self.policy.reset_after_secs);
+        if self.last_failure.is_some_and(|at| now.duration_since(at) >= quiet) {
+            self.attempts = 0;
+        }
+        self.last_failure = Some(now);
+
+        if self.policy.max_retries.is_some_and(|max| self.attempts >= max) {
+            self.gave_up = true;
+            match self.policy.give_up {
+                GiveUpAction::Stop => {
+                    tracing::error!("{} failed ({}); giving up after {} retries", self.name, error, self.attempts);
+                }
+                GiveUpAction::Exit => {
+                    tracing::error!("{} failed ({}); shutting down", self.name, error);
+                    self.shutdown.trigger();
+                }
+            }
+            return false;
+        }
+
+        let wait = backoff(&self.policy, self.attempts);
+        self.attempts += 1;
+        self.retry_at = Some(now + wait);
+        tracing::warn!("{} failed ({}); retry {} in {:?}", self.name, error, self.attempts, wait);
+        true
+    }
+
+    /// Check whether a scheduled retry should run now (clears it)
+    pub fn due(&mut self) -> bool {
+        self.due_at(Instant::now())
+    }
+
+    fn due_at(&mut self, now: Instant) -> bool {
+        if self.retry_at.is_some_and(|at| now >= at) {
+            self.retry_at = None;
+            return true;
+        }
+        false
+    }
+
+    /// Check if a retry is scheduled
+    pub fn is_pending(&self) -> bool {
+        self.retry_at.is_some()
+    }
+
+    /// Check if the retries ran out
+    pub fn gave_up(&self) -> bool {
+        self.gave_up
+    }
+
+    /// Run `f` until it succeeds, sleeping between attempts
+    pub async fn run<T, E: Display>(&mut self, mut f: impl FnMut() -> Result<T, E>) -> Result<T, E> {
+        loop {
+            match f() {
+                Ok(value) => return Ok(value),
+                Err(e) => {
+                    if !self.fail(&e) {
+                        return Err(e);
+                    }
+                    if let Some(at) = self.retry_at.take() {
+                        tokio::time::sleep_until(at.into()).await;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_and_give_up() {
+        let policy = RecoveryPolicy {
+            max_retries: Some(2),
+            initial_backoff_ms: 100,
+            max_backoff_ms: 300,
+            ..RecoveryPolicy::restart_forever()
+        };
+        assert_eq!(backoff(&policy, 0), Duration::from_millis(100));
+        assert_eq!(backoff(&policy, 1), Duration::from_millis(200));
+        assert_eq!(backoff(&policy, 5), Duration::from_millis(300));
+
+        let shutdown = ShutdownCoordinator::new();
+        let mut recovery = Recovery::new("capture", policy, shutdown.clone());
+        let start = Instant::now();
+        assert!(recovery.fail_at(&"unplugged", start));
+        // Repeated errors while waiting don't use up retries
+        assert!(recovery.fail_at(&"unplugged", start));
+        assert!(!recovery.due_at(start + Duration::from_millis(50)));
+        assert!(recovery.due_at(start + Duration::from_millis(100)));
+
+        assert!(recovery.fail_at(&"unplugged", start + Duration::from_millis(150)));
+        assert!(recovery.due_at(start + Duration::from_millis(350)));
+        assert!(!recovery.fail_at(&"unplugged", start + Duration::from_millis(400)));
+        assert!(recovery.gave_up() && !shutdown.is_triggered());
+
+        // Fail fast exits on the first error
+        let mut recovery = Recovery::new("network", RecoveryPolicy::fail_fast(), shutdown.clone());
+        assert!(!recovery.fail(&"bind failed"));
+        assert!(shutdown.is_triggered());
+    }
+}
@@ -0,0 +1,391 @@
+//! Config hot-reload
+//!
+//! Watches the config file and applies what can change without restarting
+//! a stream: track name, color, bitrate, FEC, gains, pan, playout delay,
+//! priority, processors and auto-start (tracks are matched by `track_id`; the first
+//! track is track 0 without one), the bitrate budget, profiles, device aliases, health
+//! thresholds, the jitter buffer overflow policy and the alert and
+//! notification webhooks. Anything else is logged as needing a restart. A file that does not parse is
+//! ignored and the last good config stays in effect.
+//!
+//! [`ConfigReload::replace`] does the same for a config sent over the REST
+//! API, and saves it.
+
+use fs_notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use parking_lot::Mutex;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, watch};
+
+use crate::config::AppConfig;
+use crate::diagnostics::restore_redacted;
+use crate::error::{Error, Result, TrackError};
+use crate::protocol::{TrackConfig, TrackConfigUpdate};
+use crate::tracks::TrackManager;
+
+/// Time for an editor to finish writing before the file is read
+const SETTLE_MS: u64 = 200;
+
+/// The config in effect, updated on every reload
+pub type LiveConfig = watch::Receiver<AppConfig>;
+
+/// A watched config file
+pub struct ConfigReload {
+    _watcher: Option<RecommendedWatcher>,
+    /// File the config was loaded from
+    path: Option<PathBuf>,
+    /// The config as in the file, without environment overrides
+    file: Arc<Mutex<AppConfig>>,
+    sender: Arc<watch::Sender<AppConfig>>,
+    config: LiveConfig,
+    track_manager: Arc<TrackManager>,
+}
+
+impl ConfigReload {
+    /// Watch `path` for changes; with None, or if watching fails, `config` stays as is
+    ///
+    /// Must be called on the tokio runtime.
+    pub fn start(path: Option<PathBuf>, config: AppConfig, track_manager: Arc<TrackManager>) -> Self {
+        let file = path.as_ref().and_then(|path| AppConfig::load(path).ok()).unwrap_or_default();
+        let file = Arc::new(Mutex::new(file));
+        let (tx, rx) = watch::channel(config);
+        let sender = Arc::new(tx);
+        let watcher = path.as_ref().and_then(|path| match watch_file(path, file.clone(), sender.clone(), track_manager.clone()) {
+            Ok(watcher) => {
+                tracing::info!("Watching {} for config changes", path.display());
+                Some(watcher)
+            }
+            Err(e) => {
+                tracing::warn!("Config hot-reload disabled for {}: {}", path.display(), e);
+                None
+            }
+        });
+        Self { _watcher: watcher, path, file, sender, config: rx, track_manager }
+    }
+
+    /// The config in effect
+    pub fn subscribe(&self) -> LiveConfig {
+        self.config.clone()
+    }
+
+    /// The config in effect, with the tracks as they are now
+    pub fn current(&self) -> AppConfig {
+        let mut config = self.config.borrow().clone();
+        let mut ids = self.track_manager.track_ids();
+        ids.sort_unstable();
+        config.tracks = ids
+            .into_iter()
+            .filter_map(|id| self.track_manager.get_track(id).map(|track| track.config.clone()))
+            .collect();
+        config
+    }
+
+    /// Save `config` and apply what can change at runtime
+    ///
+    /// Secrets sent back as `"<redacted>"` keep their value. Writes the file
+    /// the config was loaded from, else the default path.
+    /// Fields set by environment overrides keep their value from the file,
+    /// and the overrides still apply on top. Returns the path and the
+    /// config sections that need a restart.
+    pub fn replace(&self, config: AppConfig) -> Result<(PathBuf, Vec<String>)> {
+        let config = restore_redacted(config, &self.config.borrow())?;
+        config.validate()?;
+        if let Some(track) = config.tracks.iter().find(|t| t.profile.as_ref().is_some_and(|p| !config.profiles.contains_key(p))) {
+            return Err(TrackError::ProfileNotFound(track.profile.clone().unwrap_or_default()).into());
+        }
+        // A hook command runs in a shell, so only the file may add one
+        let known = self.config.borrow().hooks.iter().filter_map(|h| h.command.clone()).collect::<Vec<_>>();
+        if config.hooks.iter().filter_map(|h| h.command.as_ref()).any(|c| !known.contains(c)) {
+            return Err(Error::Config("hooks[].command can only be changed in the config file".to_string()));
+        }
+        let path = self
+            .path
+            .clone()
+            .or_else(AppConfig::default_path)
+            .ok_or_else(|| Error::Config("No location for the config file".to_string()))?;
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        let file = config.without_env_overrides(&self.file.lock())?;
+        file.save(&path)?;
+        let config = file.clone().with_env_overrides()?;
+        *self.file.lock() = file;
+
+        let restart = apply(&self.track_manager, &self.current(), &config);
+        tracing::info!("Config replaced over the API and saved to {}", path.display());
+        if !restart.is_empty() {
+            tracing::warn!("Config changes to {} take effect after a restart", restart.join(", "));
+        }
+        self.sender.send_replace(config);
+        Ok((path, restart))
+    }
+}
+
+/// Reload `path` whenever it changes until the watcher is dropped
+fn watch_file(
+    path: &Path,
+    file: Arc<Mutex<AppConfig>>,
+    config: Arc<watch::Sender<AppConfig>>,
+    track_manager: Arc<TrackManager>,
+) -> Result<RecommendedWatcher> {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let name = path.file_name().map(|name| name.to_os_string());
+    let mut watcher = fs_notify::recommended_watcher(move |event: fs_notify::Result<fs_notify::Event>| {
+        let Ok(event) = event else {
+            return;
+        };
+        let relevant = event.kind.is_create() || event.kind.is_modify();
+        if relevant && event.paths.iter().any(|p| p.file_name() == name.as_deref()) {
+            let _ = tx.send(());
+        }
+    })
+    .map_err(|e| Error::Config(e.to_string()))?;
+
+    // Editors often replace the file rather than write it, so watch its directory
+    let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    watcher
+        .watch(dir, RecursiveMode::NonRecursive)
+        .map_err(|e| Error::Config(e.to_string()))?;
+
+    let path = path.to_path_buf();
+    tokio::spawn(async move {
+        while rx.recv().await.is_some() {
+            tokio::time::sleep(Duration::from_millis(SETTLE_MS)).await;
+            while rx.try_recv().is_ok() {}
+
+            let loaded = AppConfig::load(&path).and_then(|loaded| Ok((loaded.clone(), loaded.with_env_overrides()?)));
+            let new = match loaded {
+                Ok((loaded, new)) => {
+                    *file.lock() = loaded;
+                    new
+                }
+                Err(e) => {
+                    tracing::warn!("Ignoring changed config {}: {}", path.display(), e);
+                    continue;
+                }
+            };
+            let restart = apply(&track_manager, &config.borrow(), &new);
+            if !restart.is_empty() {
+                tracing::warn!("Config changes to {} take effect after a restart", restart.join(", "));
+            }
+            config.send_replace(new);
+        }
+    });
+    Ok(watcher)
+}
+
+/// Apply the changes from `old` to `new` that take effect at runtime
+///
+/// Returns the config sections with other changes, which need a restart.
+pub fn apply(track_manager: &TrackManager, old: &AppConfig, new: &AppConfig) -> Vec<String> {
+    if new.network.bitrate_budget != old.network.bitrate_budget {
+        tracing::info!("Bitrate budget now {}", new.network.bitrate_budget);
+        track_manager.set_bitrate_budget(new.network.bitrate_budget);
+    }
+    if new.alerts.bandwidth_cap_kbps != old.alerts.bandwidth_cap_kbps {
+        tracing::info!("Bandwidth cap now {} kbit/s", new.alerts.bandwidth_cap_kbps);
+        track_manager.set_bandwidth_cap(new.alerts.bandwidth_cap_kbps);
+    }
+    if new.device_aliases != old.device_aliases {
+        crate::audio::device_id::set_aliases(&new.device_aliases);
+    }
+    if new.profiles != old.profiles {
+        track_manager.set_profiles(new.profiles.clone());
+    }
+    if differs(&old.health, &new.health) {
+        track_manager.health().configure(&new.health);
+    }
+
+    let before = tracks(old);
+    for (id, track) in tracks(new) {
+        let Some(previous) = before.get(&id) else {
+            continue;
+        };
+        let update = track_update(previous, &track);
+        if !differs(&update, &TrackConfigUpdate::default()) {
+            continue;
+        }
+        match track_manager.update_track(id, update) {
+            Ok(()) => tracing::info!("Track {} updated from the config", id),
+            Err(TrackError::NotFound(_)) => {}
+            Err(e) => tracing::warn!("Config change to track {} rejected: {}", id, e),
+        }
+    }
+
+    let (old, new) = (fixed(old), fixed(new));
+    old.as_object()
+        .into_iter()
+        .flatten()
+        .filter(|(section, value)| new.get(section.as_str()) != Some(value))
+        .map(|(section, _)| section.clone())
+        .collect()
+}
+
+/// Configured tracks by ID, with their profiles filled in
+fn tracks(config: &AppConfig) -> BTreeMap<u8, TrackConfig> {
+    config
+        .tracks
+        .iter()
+        .enumerate()
+        .filter_map(|(index, track)| {
+            let id = track.track_id.or((index == 0).then_some(0))?;
+            Some((id, resolve(config, track)))
+        })
+        .collect()
+}
+
+/// A track with its profile filled in, if the profile exists
+fn resolve(config: &AppConfig, track: &TrackConfig) -> TrackConfig {
+    match track.profile.as_ref().and_then(|name| config.profiles.get(name)) {
+        Some(profile) => track.clone().with_profile(profile),
+        None => track.clone(),
+    }
+}
+
+/// The runtime-changeable settings that differ between two versions of a track
+fn track_update(old: &TrackConfig, new: &TrackConfig) -> TrackConfigUpdate {
+    fn changed<T: Clone + PartialEq>(old: &T, new: &T) -> Option<T> {
+        (old != new).then(|| new.clone())
+    }
+    TrackConfigUpdate {
+        name: changed(&old.name, &new.name),
+        bitrate: changed(&old.bitrate, &new.bitrate),
+        fec_enabled: changed(&old.fec_enabled, &new.fec_enabled),
+        playout_delay_ms: changed(&old.playout_delay_ms, &new.playout_delay_ms),
+        mix_gain_db: changed(&old.mix_gain_db, &new.mix_gain_db),
+        mix_pan: changed(&old.mix_pan, &new.mix_pan),
+        priority: changed(&old.priority, &new.priority).flatten(),
+        bitrate_weight: changed(&old.bitrate_weight, &new.bitrate_weight),
+        decoder_gain_db: changed(&old.decoder_gain_db, &new.decoder_gain_db),
+        soft_clip: changed(&old.soft_clip, &new.soft_clip),
+        processors: changed(&old.processors, &new.processors),
+        color: changed(&old.color, &new.color).flatten(),
+        auto_start: changed(&old.auto_start, &new.auto_start),
+        simulcast_bitrates: changed(&old.simulcast_bitrates, &new.simulcast_bitrates),
+        group: changed(&old.group, &new.group).map(Option::unwrap_or_default),
+        ..Default::default()
+    }
+}
+
+/// The config as JSON with everything that applies at runtime blanked out
+fn fixed(config: &AppConfig) -> Value {
+    let default = TrackConfig::default();
+    let mut config = config.clone();
+    config.tracks = config
+        .tracks
+        .iter()
+        .map(|track| {
+            let track = resolve(&config, track);
+            TrackConfig {
+                name: default.name.clone(),
+                bitrate: default.bitrate,
+                fec_enabled: default.fec_enabled,
+                playout_delay_ms: default.playout_delay_ms,
+                mix_gain_db: default.mix_gain_db,
+                mix_pan: default.mix_pan,
+                priority: default.priority,
+                bitrate_weight: default.bitrate_weight,
+                decoder_gain_db: default.decoder_gain_db,
+                soft_clip: default.soft_clip,
+                processors: Vec::new(),
+                profile: None,
+                color: None,
+                auto_start: default.auto_start,
+                simulcast_bitrates: Vec::new(),
+                group: None,
+                ..track
+            }
+        })
+        .collect();
+    config.profiles.clear();
+    config.device_aliases.clear();
+    config.network.bitrate_budget = 0;
+    config.audio.jitter_overflow = Default::default();
+    config.audio.jitter_late_threshold = 0;
+    config.alerts.webhook_url = None;
+    config.alerts.bandwidth_cap_kbps = 0;
+    config.notify = Default::default();
+    config.hooks.clear();
+    config.health = Default::default();
+    config.wake = Default::default();
+    serde_json::to_value(config).unwrap_or_default()
+}
+
+fn differs<T: Serialize>(a: &T, b: &T) -> bool {
+    serde_json::to_value(a).ok() != serde_json::to_value(b).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::TrackHook;
+    use crate::protocol::TrackProfile;
+
+    #[test]
+    fn test_apply_runtime_changes() {
+        let manager = TrackManager::new();
+        let mut old = AppConfig::default();
+        old.tracks.push(TrackConfig { track_id: Some(3), profile: Some("Voice".to_string()), ..Default::default() });
+        old.profiles.insert("Voice".to_string(), TrackProfile { bitrate: Some(64_000), ..Default::default() });
+        manager.set_profiles(old.profiles.clone());
+        manager.create_track(old.tracks[0].clone()).unwrap();
+
+        // The profile's bitrate changes and FEC goes on: both apply, no restart
+        let mut new = old.clone();
+        new.profiles.get_mut("Voice").unwrap().bitrate = Some(48_000);
+        new.tracks[0].fec_enabled = true;
+        new.network.bitrate_budget = 256_000;
+        new.alerts.webhook_url = Some("http://localhost/hook".to_string());
+        assert!(apply(&manager, &old, &new).is_empty());
+        let track = manager.get_track(3).unwrap();
+        assert_eq!((track.config.bitrate, track.config.fec_enabled), (48_000, true));
+        drop(track);
+        assert_eq!(manager.bitrate_budget(), 256_000);
+
+        // Ports and devices need a restart
+        let mut moved = new.clone();
+        moved.ui.http_port += 1;
+        moved.tracks[0].device_id = "input:USB".to_string();
+        assert_eq!(apply(&manager, &new, &moved), vec!["tracks".to_string(), "ui".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_replace_saves_and_applies() {
+        let path = std::env::temp_dir().join(format!("las-config-{}.toml", std::process::id()));
+        let manager = Arc::new(TrackManager::new());
+        let mut config = AppConfig::default();
+        config.tracks.push(TrackConfig { track_id: Some(0), ..Default::default() });
+        manager.create_track(config.tracks[0].clone()).unwrap();
+        let reload = ConfigReload::start(Some(path.clone()), config, manager.clone());
+
+        // Runtime changes to a track show up in the export
+        manager.update_track(0, TrackConfigUpdate { bitrate: Some(96_000), ..Default::default() }).unwrap();
+        let mut exported = reload.current();
+        assert_eq!(exported.tracks[0].bitrate, 96_000);
+
+        exported.tracks[0].fec_enabled = true;
+        exported.network.bitrate_budget = 192_000;
+        let (saved, restart) = reload.replace(exported).unwrap();
+        assert_eq!((saved, restart), (path.clone(), Vec::<String>::new()));
+        assert!(manager.get_track(0).unwrap().config.fec_enabled);
+        assert_eq!(reload.subscribe().borrow().network.bitrate_budget, 192_000);
+        assert_eq!(AppConfig::load(&path).unwrap().network.bitrate_budget, 192_000);
+
+        let mut unknown = reload.current();
+        unknown.tracks[0].profile = Some("Desktop music".to_string());
+        assert!(reload.replace(unknown).is_err());
+
+        // Hook commands only come from the file
+        let mut hooked = reload.current();
+        hooked.hooks.push(TrackHook { tracks: vec![], events: vec![], command: Some("touch /tmp/owned".to_string()), url: None });
+        assert!(matches!(reload.replace(hooked), Err(Error::Config(_))));
+        let mut hooked = reload.current();
+        hooked.hooks.push(TrackHook { tracks: vec![], events: vec![], command: None, url: Some("http://127.0.0.1:1/".to_string()) });
+        assert!(reload.replace(hooked).is_ok());
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,319 @@
+//! Built-in self-test (`--self-test`)
+//!
+//! Runs through what a stream needs, one step at a time, and prints a
+//! pass/fail line for each: the audio devices are listed, a capture and a
+//! playback stream are opened on the default devices and closed again, a
+//! tone goes through an Opus encode/decode round trip, and the UDP and HTTP
+//! ports from the config are bound and released. It takes a second or two,
+//! so "it doesn't work" reports can start from its output.
+
+use std::fmt;
+use std::net::{SocketAddr, TcpListener};
+
+use crate::config::AppConfig;
+use crate::error::NetworkError;
+use crate::network::udp::{create_socket, port_candidates};
+
+#[cfg(feature = "cpal")]
+use {
+    crate::audio::buffer::create_shared_buffer,
+    crate::audio::capture::AudioCapture,
+    crate::audio::device::{default_device_id, list_devices},
+    crate::audio::playback::AudioPlayback,
+    crate::constants::RING_BUFFER_CAPACITY,
+    std::time::Duration,
+};
+
+/// How long a test stream stays open
+#[cfg(feature = "cpal")]
+const STREAM_TEST_TIME: Duration = Duration::from_millis(300);
+
+/// Outcome of one check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Pass,
+    Fail,
+    /// Not applicable here (no such device, feature not built)
+    Skip,
+}
+
+/// One line of the report
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Check {
+    pub name: &'static str,
+    pub outcome: Outcome,
+    pub detail: String,
+}
+
+impl Check {
+    fn pass(name: &'static str, detail: impl Into<String>) -> Self {
+        Self { name, outcome: Outcome::Pass, detail: detail.into() }
+    }
+
+    fn fail(name: &'static str, detail: impl Into<String>) -> Self {
+        Self { name, outcome: Outcome::Fail, detail: detail.into() }
+    }
+
+    fn skip(name: &'static str, detail: impl Into<String>) -> Self {
+        Self { name, outcome: Outcome::Skip, detail: detail.into() }
+    }
+}
+
+/// Results of a self-test run
+#[derive(Debug, Clone)]
+pub struct Report {
+    /// "sender" or "receiver"
+    pub role: &'static str,
+    pub checks: Vec<Check>,
+}
+
+impl Report {
+    /// No check failed
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|c| c.outcome != Outcome::Fail)
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "LAN Audio Streamer {} self-test ({})", self.role, env!("CARGO_PKG_VERSION"))?;
+        let width = self.checks.iter().map(|c| c.name.len()).max().unwrap_or(0);
+        for check in &self.checks {
+            let tag = match check.outcome {
+                Outcome::Pass => "PASS",
+                Outcome::Fail => "FAIL",
+                Outcome::Skip => "SKIP",
+            };
+            writeln!(f, "  [{}] {:width$}  {}", tag, check.name, check.detail, width = width)?;
+        }
+        let failed = self.checks.iter().filter(|c| c.outcome == Outcome::Fail).count();
+        if failed == 0 {
+            write!(f, "All checks passed")
+        } else {
+            write!(f, "{} of {} checks failed", failed, self.checks.len())
+        }
+    }
+}
+
+/// Run every check against `config`
+///
+/// A missing default device fails the side the role needs (capture for the
+/// sender, playback for the receiver) and skips the other.
+pub fn run(config: &AppConfig, is_sender: bool) -> Report {
+    Report {
+        role: if is_sender { "sender" } else { "receiver" },
+        checks: vec![
+            check_devices(),
+            check_capture(is_sender),
+            check_playback(!is_sender),
+            check_codec(),
+            check_udp(config),
+            check_http(config),
+        ],
+    }
+}
+
+#[cfg(feature = "cpal")]
+fn check_devices() -> Check {
+    let devices = list_devices();
+    let inputs = devices.iter().filter(|d| d.is_input).count();
+    let outputs = devices.iter().filter(|d| d.is_output).count();
+    let detail = format!("{} inputs, {} outputs", inputs, outputs);
+    if devices.is_empty() {
+        Check::fail("devices", "no audio devices found")
+    } else {
+        Check::pass("devices", detail)
+    }
+}
+
+#[cfg(not(feature = "cpal"))]
+fn check_devices() -> Check {
+    Check::skip("devices", "built without cpal")
+}
+
+#[cfg(feature = "cpal")]
+fn check_capture(required: bool) -> Check {
+    let Some(device_id) = default_device_id(true) else {
+        return missing_device("capture", "input", required);
+    };
+    let result = AudioCapture::new(0, &device_id, None, None, None, create_shared_buffer(RING_BUFFER_CAPACITY))
+        .and_then(|mut capture| {
+            capture.start()?;
+            std::thread::sleep(STREAM_TEST_TIME);
+            let error = capture.check_errors();
+            let samples = capture.samples_captured();
+            capture.stop();
+            error.map_or(Ok(samples), Err)
+        });
+    match result {
+        Ok(0) => Check::fail("capture", format!("{}: no audio in {} ms", device_id, STREAM_TEST_TIME.as_millis())),
+        Ok(samples) => Check::pass("capture", format!("{}: {} samples", device_id, samples)),
+        Err(e) => Check::fail("capture", format!("{}: {}", device_id, e)),
+    }
+}
+
+#[cfg(feature = "cpal")]
+fn check_playback(required: bool) -> Check {
+    let Some(device_id) = default_device_id(false) else {
+        return missing_device("playback", "output", required);
+    };
+    let result = AudioPlayback::new(0, &device_id, None, None, None, create_shared_buffer(RING_BUFFER_CAPACITY))
+        .and_then(|mut playback| {
+            playback.start()?;
+            std::thread::sleep(STREAM_TEST_TIME);
+            let error = playback.check_errors();
+            playback.stop();
+            error.map_or(Ok(()), Err)
+        });
+    match result {
+        Ok(()) => Check::pass("playback", format!("{}: opened and closed", device_id)),
+        Err(e) => Check::fail("playback", format!("{}: {}", device_id, e)),
+    }
+}
+
+#[cfg(feature = "cpal")]
+fn missing_device(name: &'static str, kind: &str, required: bool) -> Check {
+    let detail = format!("no default {} device", kind);
+    if required { Check::fail(name, detail) } else { Check::skip(name, detail) }
+}
+
+#[cfg(not(feature = "cpal"))]
+fn check_capture(_required: bool) -> Check {
+    Check::skip("capture", "built without cpal")
+}
+
+#[cfg(not(feature = "cpal"))]
+fn check_playback(_required: bool) -> Check {
+    Check::skip("playback", "built without cpal")
+}
+
+#[cfg(feature = "opus")]
+fn check_codec() -> Check {
+    use crate::codec::{OpusDecoder, OpusEncoder};
+    use crate::config::OpusConfig;
+    use crate::error::CodecError;
+
+    let result = (|| -> Result<(usize, f32), CodecError> {
+        let config = OpusConfig::default();
+        let mut encoder = OpusEncoder::new(config.clone())?;
+        let mut decoder = OpusDecoder::new(config.sample_rate, config.channels, config.frame_size)?;
+        let channels = config.channels as usize;
+
+        // 100 ms of a 1 kHz tone; the last frame is past the codec's start-up delay
+        let mut bytes = 0;
+        let mut rms = 0.0;
+        for frame in 0..10 {
+            let samples: Vec<f32> = (0..config.frame_size * channels)
+                .map(|i| {
+                    let t = (frame * config.frame_size + i / channels) as f32 / config.sample_rate as f32;
+                    0.5 * (2.0 * std::f32::consts::PI * 1000.0 * t).sin()
+                })
+                .collect();
+            let packet = encoder.encode(&samples)?;
+            bytes += packet.len();
+            let decoded = decoder.decode(&packet)?;
+            if decoded.len() != samples.len() {
+                return Err(CodecError::DecodingFailed(format!(
+                    "decoded {} samples, expected {}",
+                    decoded.len(),
+                    samples.len()
+                )));
+            }
+            rms = (decoded.iter().map(|s| s * s).sum::<f32>() / decoded.len() as f32).sqrt();
+        }
+        Ok((bytes / 10, rms))
+    })();
+
+    match result {
+        Ok((_, rms)) if rms < 0.1 => Check::fail("codec", format!("round trip lost the signal (RMS {:.3})", rms)),
+        Ok((bytes, _)) => Check::pass("codec", format!("Opus round trip, {} bytes per frame", bytes)),
+        Err(e) => Check::fail("codec", e.to_string()),
+    }
+}
+
+#[cfg(not(feature = "opus"))]
+fn check_codec() -> Check {
+    Check::skip("codec", "built without opus")
+}
+
+/// Bind the audio port the way the streams do
+fn check_udp(config: &AppConfig) -> Check {
+    let bound = create_socket(&config.network)
+        .and_then(|socket| socket.local_addr().map_err(|e| NetworkError::BindFailed(e.to_string())));
+    match bound {
+        Ok(addr) if addr.port() != config.network.udp_port && config.network.udp_port != 0 => {
+            Check::pass("udp", format!("{} (port {} is taken)", addr, config.network.udp_port))
+        }
+        Ok(addr) => Check::pass("udp", addr.to_string()),
+        Err(e) => Check::fail("udp", e.to_string()),
+    }
+}
+
+/// Bind the web UI port the way the server does
+fn check_http(config: &AppConfig) -> Check {
+    let ui = &config.ui;
+    let mut last_error = None;
+    for port in port_candidates(ui.http_port, ui.port_range) {
+        let addr: SocketAddr = match format!("{}:{}", ui.bind_address, port).parse() {
+            Ok(addr) => addr,
+            Err(e) => return Check::fail("http", format!("{}: {}", ui.bind_address, e)),
+        };
+        match TcpListener::bind(addr) {
+            Ok(_) if port != ui.http_port => {
+                return Check::pass("http", format!("{} (port {} is taken)", addr, ui.http_port));
+            }
+            Ok(listener) => {
+                let addr = listener.local_addr().unwrap_or(addr);
+                return Check::pass("http", addr.to_string());
+            }
+            Err(e) => last_error = Some(e),
+        }
+    }
+    let e = last_error.map_or_else(String::new, |e| e.to_string());
+    Check::fail("http", format!("no free port from {}: {}", ui.http_port, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_port_checks() {
+        let mut config = AppConfig::default();
+        config.network.bind_address = "127.0.0.1".to_string();
+        config.network.udp_port = 0;
+        config.ui.bind_address = "127.0.0.1".to_string();
+        config.ui.http_port = 0;
+        assert_eq!(check_udp(&config).outcome, Outcome::Pass);
+        assert_eq!(check_http(&config).outcome, Outcome::Pass);
+
+        // A port held by another process fails without a range to fall back on
+        let taken = TcpListener::bind("127.0.0.1:0").unwrap();
+        config.ui.http_port = taken.local_addr().unwrap().port();
+        config.ui.port_range = 0;
+        let check = check_http(&config);
+        assert_eq!(check.outcome, Outcome::Fail, "{:?}", check);
+        config.ui.port_range = 5;
+        assert_eq!(check_http(&config).outcome, Outcome::Pass);
+    }
+
+    #[test]
+    fn test_report() {
+        let mut report = Report {
+            role: "receiver",
+            checks: vec![
+                Check::pass("udp", "0.0.0.0:5000"),
+                Check::skip("capture", "no default input device"),
+            ],
+        };
+        assert!(report.passed());
+        assert!(report.to_string().ends_with("All checks passed"));
+
+        report.checks.push(Check::fail("playback", "output:Speakers: device not found"));
+        assert!(!report.passed());
+        let text = report.to_string();
+        assert!(text.contains("  [FAIL] playback  output:Speakers: device not found"), "{}", text);
+        assert!(text.contains("  [SKIP] capture   no default input device"), "{}", text);
+        assert!(text.ends_with("1 of 3 checks failed"));
+    }
+}
@@ -0,0 +1,329 @@
+//! Running unattended as a system service
+//!
+//! `--service` runs a binary without console output at boot. On Linux the
+//! process reports readiness, watchdog pings and shutdown to systemd over
+//! `sd_notify` (for a `Type=notify` unit) and stops cleanly on SIGTERM. On
+//! Windows it registers with the Service Control Manager, whose stop and
+//! shutdown requests go through the same [`ShutdownCoordinator`] as Ctrl+C.
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::shutdown::ShutdownCoordinator;
+
+/// Command line of the binaries
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ServiceArgs {
+    /// `--service`
+    pub service: bool,
+    /// `--self-test`: check devices, codec and ports, then exit
+    pub self_test: bool,
+    /// `--config <path>`
+    pub config_path: Option<PathBuf>,
+    /// Remaining arguments, in order
+    pub positional: Vec<String>,
+}
+
+impl ServiceArgs {
+    /// Parse `std::env::args()` (the program name is skipped)
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Self {
+        let mut parsed = Self::default();
+        let mut args = args.into_iter().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--service" => parsed.service = true,
+                "--self-test" => parsed.self_test = true,
+                "--config" => parsed.config_path = args.next().map(PathBuf::from),
+                _ => match arg.strip_prefix("--config=") {
+                    Some(path) => parsed.config_path = Some(PathBuf::from(path)),
+                    None => parsed.positional.push(arg),
+                },
+            }
+        }
+        parsed
+    }
+}
+
+/// Service manager integration for one process
+pub struct Service {
+    enabled: bool,
+}
+
+impl Service {
+    /// Hook into the service manager if `enabled`
+    ///
+    /// Must be called inside the tokio runtime.
+    pub fn start(name: &'static str, enabled: bool, shutdown: &ShutdownCoordinator) -> Self {
+        if enabled {
+            tracing::info!("Running as service {}", name);
+            #[cfg(unix)]
+            listen_for_sigterm(shutdown.clone());
+            #[cfg(windows)]
+            scm::start(name, shutdown.clone());
+            #[cfg(not(any(unix, windows)))]
+            let _ = shutdown;
+        }
+        Self { enabled }
+    }
+
+    /// Startup finished; the service is running
+    pub fn ready(&self) {
+        if !self.enabled {
+            return;
+        }
+        #[cfg(unix)]
+        {
+            sd_notify("READY=1");
+            if let Some(interval) = watchdog_interval() {
+                tokio::spawn(async move {
+                    let mut ticker = tokio::time::interval(interval);
+                    loop {
+                        ticker.tick().await;
+                        sd_notify("WATCHDOG=1");
+                    }
+                });
+            }
+        }
+        #[cfg(windows)]
+        scm::set_running();
+    }
+
+    /// Shutdown has begun
+    pub fn stopping(&self) {
+        if !self.enabled {
+            return;
+        }
+        #[cfg(unix)]
+        sd_notify("STOPPING=1");
+        #[cfg(windows)]
+        scm::set_stop_pending();
+    }
+
+    /// Everything is stopped; the process is about to exit
+    pub fn stopped(self) {
+        #[cfg(windows)]
+        if self.enabled {
+            scm::set_stopped();
+        }
+    }
+}
+
+/// Trigger shutdown on SIGTERM, which systemd sends to stop a unit
+#[cfg(unix)]
+fn listen_for_sigterm(shutdown: ShutdownCoordinator) {
+    use tokio::signal::unix::{signal, SignalKind};
+    match signal(SignalKind::terminate()) {
+        Ok(mut sigterm) => {
+            tokio::spawn(async move {
+                if sigterm.recv().await.is_some() {
+                    tracing::info!("SIGTERM received");
+                    shutdown.trigger();
+                }
+            });
+        }
+        Err(e) => tracing::warn!("Cannot listen for SIGTERM: {}", e),
+    }
+}
+
+/// Half the systemd watchdog timeout, if the unit sets `WatchdogSec`
+#[cfg(unix)]
+fn watchdog_interval() -> Option<Duration> {
+    let usec: u64 = std::env::var("WATCHDOG_USEC").ok()?.parse().ok()?;
+    (usec > 0).then(|| Duration::from_micros(usec / 2))
+}
+
+/// Send a state line to systemd if it is listening
+#[cfg(unix)]
+fn sd_notify(state: &str) {
+    let Ok(socket) = std::env::var("NOTIFY_SOCKET") else {
+        return;
+    };
+    if let Err(e) = notify_socket(&socket, state) {
+        tracing::warn!("sd_notify {} failed: {}", state, e);
+    }
+}
+
+#[cfg(unix)]
+fn notify_socket(path: &str, state: &str) -> std::io::Result<()> {
+    use std::os::unix::net::UnixDatagram;
+    let socket = UnixDatagram::unbound()?;
+    // A leading '@' names a Linux abstract socket
+    #[cfg(target_os = "linux")]
+    if let Some(name) = path.strip_prefix('@') {
+        use std::os::linux::net::SocketAddrExt;
+        let addr = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+        socket.send_to_addr(state.as_bytes(), &addr)?;
+        return Ok(());
+    }
+    socket.send_to(state.as_bytes(), path)?;
+    Ok(())
+}
+
+/// Windows Service Control Manager
+#[cfg(windows)]
+mod scm {
+    use std::ffi::c_void;
+    use std::sync::{Condvar, Mutex, OnceLock};
+    use windows::core::{PCWSTR, PWSTR};
+    use windows::Win32::Foundation::{ERROR_CALL_NOT_IMPLEMENTED, NO_ERROR};
+    use windows::Win32::System::Services::{
+        RegisterServiceCtrlHandlerExW, SetServiceStatus, StartServiceCtrlDispatcherW, SERVICE_ACCEPT_SHUTDOWN,
+        SERVICE_ACCEPT_STOP, SERVICE_CONTROL_INTERROGATE, SERVICE_CONTROL_SHUTDOWN, SERVICE_CONTROL_STOP,
+        SERVICE_RUNNING, SERVICE_START_PENDING, SERVICE_STATUS, SERVICE_STATUS_CURRENT_STATE, SERVICE_STATUS_HANDLE,
+        SERVICE_STOPPED, SERVICE_STOP_PENDING, SERVICE_TABLE_ENTRYW, SERVICE_WIN32_OWN_PROCESS,
+    };
+
+    use crate::shutdown::ShutdownCoordinator;
+
+    static NAME: OnceLock<&'static str> = OnceLock::new();
+    static SHUTDOWN: OnceLock<ShutdownCoordinator> = OnceLock::new();
+    static HANDLE: OnceLock<SERVICE_STATUS_HANDLE> = OnceLock::new();
+    /// Last state set before the control handler was registered
+    static PENDING: Mutex<Option<SERVICE_STATUS_CURRENT_STATE>> = Mutex::new(None);
+    /// Set once the process has stopped; releases `service_main`
+    static STOPPED: (Mutex<bool>, Condvar) = (Mutex::new(false), Condvar::new());
+
+    fn wide(s: &str) -> Vec<u16> {
+        s.encode_utf16().chain(std::iter::once(0)).collect()
+    }
+
+    /// Connect to the SCM on a dedicated thread; the application keeps running on its own
+    pub fn start(name: &'static str, shutdown: ShutdownCoordinator) {
+        let _ = NAME.set(name);
+        let _ = SHUTDOWN.set(shutdown);
+        let spawned = std::thread::Builder::new()
+            .name("service-dispatcher".to_string())
+            .spawn(move || {
+                let mut name = wide(name);
+                let table = [
+                    SERVICE_TABLE_ENTRYW { lpServiceName: PWSTR(name.as_mut_ptr()), lpServiceProc: Some(service_main) },
+                    SERVICE_TABLE_ENTRYW::default(),
+                ];
+                // Blocks until service_main returns
+                if let Err(e) = unsafe { StartServiceCtrlDispatcherW(table.as_ptr()) } {
+                    tracing::error!("Not started by the Service Control Manager: {}", e);
+                }
+            });
+        if let Err(e) = spawned {
+            tracing::error!("Cannot start the service dispatcher: {}", e);
+        }
+    }
+
+    unsafe extern "system" fn service_main(_argc: u32, _argv: *mut PWSTR) {
+        let name = wide(NAME.get().copied().unwrap_or_default());
+        let pending = match RegisterServiceCtrlHandlerExW(PCWSTR(name.as_ptr()), Some(control_handler), None) {
+            Ok(handle) => {
+                // Under the lock, so a state set meanwhile is either pending or reported
+                let mut pending = PENDING.lock().unwrap_or_else(|e| e.into_inner());
+                let _ = HANDLE.set(handle);
+                pending.take()
+            }
+            Err(e) => {
+                tracing::error!("Cannot register the service control handler: {}", e);
+                return;
+            }
+        };
+        set_state(SERVICE_START_PENDING);
+        // The application may already be running (or stopping)
+        if let Some(state) = pending {
+            set_state(state);
+        }
+
+        let (lock, condvar) = &STOPPED;
+        let mut stopped = lock.lock().unwrap_or_else(|e| e.into_inner());
+        while !*stopped {
+            stopped = condvar.wait(stopped).unwrap_or_else(|e| e.into_inner());
+        }
+        set_state(SERVICE_STOPPED);
+    }
+
+    unsafe extern "system" fn control_handler(
+        control: u32,
+        _event_type: u32,
+        _event_data: *mut c_void,
+        _context: *mut c_void,
+    ) -> u32 {
+        match control {
+            SERVICE_CONTROL_STOP | SERVICE_CONTROL_SHUTDOWN => {
+                set_state(SERVICE_STOP_PENDING);
+                if let Some(shutdown) = SHUTDOWN.get() {
+                    shutdown.trigger();
+                }
+                NO_ERROR.0
+            }
+            SERVICE_CONTROL_INTERROGATE => NO_ERROR.0,
+            _ => ERROR_CALL_NOT_IMPLEMENTED.0,
+        }
+    }
+
+    /// Report `state`, or keep it for `service_main` to report once registered
+    fn set_state(state: SERVICE_STATUS_CURRENT_STATE) {
+        let handle = {
+            let mut pending = PENDING.lock().unwrap_or_else(|e| e.into_inner());
+            match HANDLE.get() {
+                Some(handle) => *handle,
+                None => {
+                    *pending = Some(state);
+                    return;
+                }
+            }
+        };
+        let status = SERVICE_STATUS {
+            dwServiceType: SERVICE_WIN32_OWN_PROCESS,
+            dwCurrentState: state,
+            dwControlsAccepted: if state == SERVICE_RUNNING { SERVICE_ACCEPT_STOP | SERVICE_ACCEPT_SHUTDOWN } else { 0 },
+            dwWin32ExitCode: NO_ERROR.0,
+            dwServiceSpecificExitCode: 0,
+            dwCheckPoint: 0,
+            dwWaitHint: if state == SERVICE_STOP_PENDING { 5000 } else { 0 },
+        };
+        if let Err(e) = unsafe { SetServiceStatus(handle, &status) } {
+            tracing::warn!("SetServiceStatus failed: {}", e);
+        }
+    }
+
+    pub fn set_running() {
+        set_state(SERVICE_RUNNING);
+    }
+
+    pub fn set_stop_pending() {
+        set_state(SERVICE_STOP_PENDING);
+    }
+
+    pub fn set_stopped() {
+        let (lock, condvar) = &STOPPED;
+        *lock.lock().unwrap_or_else(|e| e.into_inner()) = true;
+        condvar.notify_all();
+        // Give service_main a moment to report before the process exits
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_args_and_notify() {
+        let args = |list: &[&str]| ServiceArgs::parse(list.iter().map(|s| s.to_string()));
+        assert_eq!(args(&["sender"]), ServiceArgs::default());
+        let parsed = args(&["sender", "--service", "10.0.0.2:5000", "--config", "/etc/las.toml"]);
+        assert!(parsed.service);
+        assert_eq!(parsed.config_path, Some(PathBuf::from("/etc/las.toml")));
+        assert_eq!(parsed.positional, vec!["10.0.0.2:5000".to_string()]);
+        assert_eq!(args(&["receiver", "--config=a.toml"]).config_path, Some(PathBuf::from("a.toml")));
+        assert!(args(&["receiver", "--self-test"]).self_test);
+
+        #[cfg(unix)]
+        {
+            let path = std::env::temp_dir().join(format!("las-notify-{}", std::process::id()));
+            let _ = std::fs::remove_file(&path);
+            let listener = std::os::unix::net::UnixDatagram::bind(&path).unwrap();
+            notify_socket(path.to_str().unwrap(), "READY=1").unwrap();
+            let mut buf = [0u8; 32];
+            let len = listener.recv(&mut buf).unwrap();
+            assert_eq!(&buf[..len], b"READY=1");
+            let _ = std::fs::remove_file(&path);
+        }
+    }
+}
@@ -0,0 +1,74 @@
+//! Graceful shutdown coordination
+//!
+//! Ctrl+C (or [`ShutdownCoordinator::trigger`]) only flags the shutdown;
+//! the application loops notice it, stop capture, flush their encoders and
+//! send an end-of-stream control packet, so the receiver fades out instead
+//! of cutting off mid-buffer.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+
+/// Shared shutdown flag with async notification
+#[derive(Clone, Default)]
+pub struct ShutdownCoordinator {
+    triggered: Arc<AtomicBool>,
+    notify: Arc<Notify>,
+}
+
+impl ShutdownCoordinator {
+    /// Create a new coordinator
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request shutdown
+    pub fn trigger(&self) {
+        if !self.triggered.swap(true, Ordering::SeqCst) {
+            self.notify.notify_waiters();
+        }
+    }
+
+    /// Check if shutdown was requested
+    pub fn is_triggered(&self) -> bool {
+        self.triggered.load(Ordering::SeqCst)
+    }
+
+    /// Wait until shutdown is requested
+    pub async fn wait(&self) {
+        while !self.is_triggered() {
+            let notified = self.notify.notified();
+            if self.is_triggered() {
+                break;
+            }
+            notified.await;
+        }
+    }
+
+    /// Trigger shutdown on Ctrl+C
+    pub fn listen_for_ctrl_c(&self) -> JoinHandle<()> {
+        let coordinator = self.clone();
+        tokio::spawn(async move {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                tracing::info!("Shutdown requested");
+                coordinator.trigger();
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trigger_shared_between_clones() {
+        let coordinator = ShutdownCoordinator::new();
+        let handle = coordinator.clone();
+        assert!(!handle.is_triggered());
+
+        coordinator.trigger();
+        assert!(handle.is_triggered());
+    }
+}
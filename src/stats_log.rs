@@ -0,0 +1,197 @@
+//! Stats logging to disk
+//!
+//! Appends one row of per-track statistics per interval to a CSV or JSONL
+//! file for analysis after a session. Once the file reaches
+//! `max_file_bytes` it is rotated logrotate-style (`stats.csv.1` is the
+//! newest) and only `retention` rotated files are kept.
+
+use serde::Serialize;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::config::{StatsLogConfig, StatsLogFormat};
+use crate::error::Result;
+use crate::sync::now_us;
+use crate::tracks::stats::TrackStats;
+use crate::tracks::TrackManager;
+
+const CSV_HEADER: &str = "timestamp_us,track_id,packets,bytes,frames,packets_lost,late_packets,\
+overflows,underruns,packets_per_sec,bitrate_kbps,loss_pct,jitter_level,jitter_capacity,\
+transit_ms,jitter_p50_us,jitter_p95_us,jitter_p99_us,gap_p99_packets,latency_ms,duplicate_packets,reordered_packets";
+
+/// One logged row
+#[derive(Serialize)]
+struct Row<'a> {
+    timestamp_us: u64,
+    #[serde(flatten)]
+    stats: &'a TrackStats,
+}
+
+/// Writes stats rows and rotates the file
+pub struct StatsLogger {
+    config: StatsLogConfig,
+    file: Option<File>,
+    size: u64,
+}
+
+impl StatsLogger {
+    pub fn new(config: StatsLogConfig) -> Self {
+        Self { config, file: None, size: 0 }
+    }
+
+    /// Append rows for the given tracks
+    pub fn write(&mut self, timestamp_us: u64, stats: &[TrackStats]) -> Result<()> {
+        if stats.is_empty() {
+            return Ok(());
+        }
+        if self.file.is_some() && self.size >= self.config.max_file_bytes {
+            self.file = None;
+            rotate(&self.config.path, self.config.retention)?;
+        }
+
+        let mut out = String::new();
+        if self.file.is_none() {
+            let file = OpenOptions::new().create(true).append(true).open(&self.config.path)?;
+            self.size = file.metadata()?.len();
+            if self.size == 0 && self.config.format == StatsLogFormat::Csv {
+                out.push_str(CSV_HEADER);
+                out.push('\n');
+            }
+            self.file = Some(file);
+        }
+
+        for track in stats {
+            match self.config.format {
+                StatsLogFormat::Csv => out.push_str(&csv_row(timestamp_us, track)),
+                StatsLogFormat::Jsonl => {
+                    let row = serde_json::to_string(&Row { timestamp_us, stats: track })
+                        .map_err(std::io::Error::from)?;
+                    out.push_str(&row)
+                }
+            }
+            out.push('\n');
+        }
+
+        if let Some(file) = self.file.as_mut() {
+            file.write_all(out.as_bytes())?;
+            self.size += out.len() as u64;
+        }
+        Ok(())
+    }
+}
+
+/// Format one CSV row (empty cells for missing gauges)
+fn csv_row(timestamp_us: u64, s: &TrackStats) -> String {
+    let opt = |v: Option<String>| v.unwrap_or_default();
+    format!(
+        "{},{},{},{},{},{},{},{},{},{:.2},{:.2},{:.3},{},{},{},{},{},{},{},{:.2},{},{}",
+        timestamp_us,
+        s.track_id,
+        s.packets,
+        s.bytes,
+        s.frames,
+        s.packets_lost,
+        s.late_packets,
+        s.overflows,
+        s.underruns,
+        s.packets_per_sec,
+        s.bitrate_kbps,
+        s.loss_pct,
+        opt(s.jitter_level.map(|v| v.to_string())),
+        opt(s.jitter_capacity.map(|v| v.to_string())),
+        opt(s.transit_ms.map(|v| format!("{:.2}", v))),
+        opt(s.jitter_us.map(|p| p.p50.to_string())),
+        opt(s.jitter_us.map(|p| p.p95.to_string())),
+        opt(s.jitter_us.map(|p| p.p99.to_string())),
+        opt(s.gap_packets.map(|p| p.p99.to_string())),
+        s.latency_ms,
+        s.duplicate_packets,
+        s.reordered_packets,
+    )
+}
+
+/// `stats.csv` -> `stats.csv.<n>`
+fn rotated_path(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", n));
+    PathBuf::from(name)
+}
+
+/// Shift rotated files up by one and move the current file to `.1`
+fn rotate(path: &Path, retention: usize) -> Result<()> {
+    if retention == 0 {
+        fs::remove_file(path)?;
+        return Ok(());
+    }
+    let _ = fs::remove_file(rotated_path(path, retention));
+    for n in (1..retention).rev() {
+        let from = rotated_path(path, n);
+        if from.exists() {
+            fs::rename(&from, rotated_path(path, n + 1))?;
+        }
+    }
+    fs::rename(path, rotated_path(path, 1))?;
+    Ok(())
+}
+
+/// Log per-track statistics every `interval_secs` while enabled
+pub async fn run(track_manager: Arc<TrackManager>, config: StatsLogConfig) {
+    if !config.enabled {
+        return;
+    }
+    tracing::info!("Logging track stats to {}", config.path.display());
+    let mut interval = tokio::time::interval(Duration::from_secs(config.interval_secs.max(1) as u64));
+    let mut logger = StatsLogger::new(config);
+
+    loop {
+        interval.tick().await;
+        let stats: Vec<TrackStats> = track_manager
+            .track_ids()
+            .into_iter()
+            .filter_map(|id| track_manager.stats(id).ok())
+            .collect();
+        if let Err(e) = logger.write(now_us(), &stats) {
+            tracing::warn!("Stats log write failed: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_and_rotate() {
+        let dir = std::env::temp_dir().join(format!("stats-log-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("stats.csv");
+        let mut logger = StatsLogger::new(StatsLogConfig {
+            enabled: true,
+            path: path.clone(),
+            format: StatsLogFormat::Csv,
+            interval_secs: 1,
+            max_file_bytes: 1,
+            retention: 2,
+        });
+        let stats = [TrackStats { track_id: 3, packets: 10, ..Default::default() }];
+
+        logger.write(1, &stats).unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        assert!(text.starts_with("timestamp_us,track_id"));
+        assert!(text.lines().nth(1).unwrap().starts_with("1,3,10,"));
+
+        // Every write now rotates; only two rotated files are kept
+        for t in 2..=4 {
+            logger.write(t, &stats).unwrap();
+        }
+        assert!(fs::read_to_string(&path).unwrap().contains("\n4,3,"));
+        assert!(fs::read_to_string(rotated_path(&path, 1)).unwrap().contains("\n3,3,"));
+        assert!(rotated_path(&path, 2).exists());
+        assert!(!rotated_path(&path, 3).exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
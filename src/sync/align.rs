@@ -0,0 +1,215 @@
+//! Inter-track playout alignment
+//!
+//! Tracks captured on the same sender share the timestamp clock, so the
+//! time between a frame's capture timestamp and its release to playback
+//! (plus whatever is already queued) is that track's playout delay.
+//! Tracks in a sync group are nudged towards the group's mean delay by
+//! inserting or dropping a few samples per frame, small enough steps to
+//! stay inaudible.
+
+use std::collections::HashMap;
+
+use crate::audio::buffer::AudioFrame;
+
+/// Smoothing factor for delay measurements
+const DELAY_SMOOTHING: f64 = 0.05;
+
+/// Per-track alignment state
+#[derive(Debug, Clone, Default)]
+struct MemberState {
+    /// Smoothed release delay (µs), None until first observation
+    delay_us: Option<f64>,
+    /// Net samples inserted (positive) or dropped (negative), in µs
+    applied_us: f64,
+}
+
+impl MemberState {
+    fn effective_delay_us(&self) -> Option<f64> {
+        self.delay_us.map(|d| d + self.applied_us)
+    }
+}
+
+/// A group of tracks played out on a common timeline
+pub struct SyncGroup {
+    members: HashMap<u8, MemberState>,
+    sample_rate: u32,
+    tolerance_us: u64,
+    max_step: usize,
+}
+
+impl SyncGroup {
+    /// Create a sync group
+    ///
+    /// `max_step` is the most samples (per channel) inserted or dropped
+    /// in a single frame.
+    pub fn new(track_ids: &[u8], sample_rate: u32, tolerance_us: u64, max_step: usize) -> Self {
+        Self {
+            members: track_ids.iter().map(|&id| (id, MemberState::default())).collect(),
+            sample_rate,
+            tolerance_us,
+            max_step,
+        }
+    }
+
+    /// Check if a track belongs to the group
+    pub fn contains(&self, track_id: u8) -> bool {
+        self.members.contains_key(&track_id)
+    }
+
+    /// Record a frame release for a track
+    pub fn observe(&mut self, track_id: u8, capture_ts_us: u64, now_us: u64, queued_us: u64) {
+        if let Some(member) = self.members.get_mut(&track_id) {
+            let delay = now_us.saturating_sub(capture_ts_us) as f64 + queued_us as f64;
+            member.delay_us = Some(match member.delay_us {
+                Some(prev) => prev + (delay - prev) * DELAY_SMOOTHING,
+                None => delay,
+            });
+        }
+    }
+
+    /// Mean playout delay over tracks observed so far
+    pub fn reference_delay_us(&self) -> Option<f64> {
+        let delays: Vec<f64> = self.members
+            .values()
+            .filter_map(|m| m.effective_delay_us())
+            .collect();
+
+        if delays.is_empty() {
+            None
+        } else {
+            Some(delays.iter().sum::<f64>() / delays.len() as f64)
+        }
+    }
+
+    /// Get a track's deviation from the reference (µs, positive = played early)
+    pub fn deviation_us(&self, track_id: u8) -> Option<f64> {
+        let reference = self.reference_delay_us()?;
+        let delay = self.members.get(&track_id)?.effective_delay_us()?;
+        Some(reference - delay)
+    }
+
+    /// Samples to insert (positive) or drop (negative) for a track's next frame
+    pub fn correction_samples(&self, track_id: u8) -> i32 {
+        let Some(deviation) = self.deviation_us(track_id) else {
+            return 0;
+        };
+
+        if deviation.abs() <= self.tolerance_us as f64 {
+            return 0;
+        }
+
+        let samples = (deviation * self.sample_rate as f64 / 1_000_000.0).round() as i32;
+        samples.clamp(-(self.max_step as i32), self.max_step as i32)
+    }
+
+    /// Observe a frame being released and adjust its length in place
+    ///
+    /// Returns the applied correction in samples per channel.
+    pub fn align_frame(
+        &mut self,
+        track_id: u8,
+        frame: &mut AudioFrame,
+        now_us: u64,
+        queued_frames: usize,
+    ) -> i32 {
+        if !self.contains(track_id) {
+            return 0;
+        }
+
+        let queued_us = queued_frames as u64 * frame.duration_us(self.sample_rate);
+        self.observe(track_id, frame.timestamp, now_us, queued_us);
+
+        let correction = self.correction_samples(track_id);
+        if correction != 0 {
+            let applied = adjust_length(frame.samples.make_mut(), frame.channels as usize, correction);
+            if let Some(member) = self.members.get_mut(&track_id) {
+                member.applied_us += applied as f64 * 1_000_000.0 / self.sample_rate as f64;
+            }
+            return applied;
+        }
+
+        0
+    }
+}
+
+/// Insert (positive delta) or drop (negative delta) sample frames
+///
+/// Changes are spread evenly across the buffer; inserted frames repeat
+/// their neighbour. Returns the change actually applied.
+pub fn adjust_length(samples: &mut Vec<f32>, channels: usize, delta: i32) -> i32 {
+    if channels == 0 || delta == 0 {
+        return 0;
+    }
+
+    let frames = samples.len() / channels;
+    if frames < 2 {
+        return 0;
+    }
+
+    let count = (delta.unsigned_abs() as usize).min(frames - 1);
+    let stride = frames / (count + 1);
+    let mut output = Vec::with_capacity((frames + count) * channels);
+    let mut changed = 0;
+
+    for i in 0..frames {
+        let frame = &samples[i * channels..(i + 1) * channels];
+        let at_step = changed < count && (i + 1) % stride == 0;
+
+        if delta < 0 && at_step {
+            changed += 1;
+            continue;
+        }
+
+        output.extend_from_slice(frame);
+
+        if delta > 0 && at_step {
+            output.extend_from_slice(frame);
+            changed += 1;
+        }
+    }
+
+    *samples = output;
+    if delta > 0 { changed as i32 } else { -(changed as i32) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_adjust_length() {
+        let mut samples: Vec<f32> = (0..20).map(|i| i as f32).collect();
+        assert_eq!(adjust_length(&mut samples, 2, 2), 2);
+        assert_eq!(samples.len(), 24);
+
+        let mut samples: Vec<f32> = (0..20).map(|i| i as f32).collect();
+        assert_eq!(adjust_length(&mut samples, 2, -3), -3);
+        assert_eq!(samples.len(), 14);
+        // Channel pairs stay intact
+        assert!(samples.chunks(2).all(|c| c[1] == c[0] + 1.0));
+    }
+
+    #[test]
+    fn test_group_correction() {
+        let mut group = SyncGroup::new(&[0, 1], 48000, 100, 8);
+
+        // Track 0 plays 2 ms earlier than track 1
+        group.observe(0, 10_000, 20_000, 0);
+        group.observe(1, 8_000, 20_000, 0);
+
+        // Early track gets samples inserted, late track gets samples dropped
+        assert_eq!(group.correction_samples(0), 8);
+        assert_eq!(group.correction_samples(1), -8);
+        assert_eq!(group.correction_samples(2), 0);
+    }
+
+    #[test]
+    fn test_aligned_within_tolerance() {
+        let mut group = SyncGroup::new(&[0, 1], 48000, 500, 8);
+        group.observe(0, 10_000, 20_000, 0);
+        group.observe(1, 10_200, 20_000, 0);
+
+        assert_eq!(group.correction_samples(0), 0);
+        assert_eq!(group.correction_samples(1), 0);
+    }
+}
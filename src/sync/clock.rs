@@ -0,0 +1,166 @@
+//! Shared clock and NTP-style offset estimation
+//!
+//! ## Exchange
+//!
+//! ```text
+//!   local (requester)                    remote (responder)
+//!        t0 ──── ClockRequest ────────────▶ t1
+//!        t3 ◀─── ClockResponse ─────────── t2
+//!
+//!   offset = ((t1 - t0) + (t2 - t3)) / 2     (remote - local)
+//!   delay  = (t3 - t0) - (t2 - t1)           (round trip)
+//! ```
+//!
+//! Samples with the lowest round trip are the least affected by queuing,
+//! so the estimate uses the minimum-delay sample of a sliding window.
+
+use std::collections::VecDeque;
+use std::sync::OnceLock;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use crate::config::ClockSource;
+
+/// Process-wide clock anchor (monotonic instant, epoch µs at that instant)
+static CLOCK_ANCHOR: OnceLock<(Instant, u64)> = OnceLock::new();
+
+/// Current time in microseconds since the UNIX epoch
+///
+/// Anchored to the system clock once and advanced monotonically afterwards,
+/// so later wall-clock steps do not make timestamps jump.
+pub fn now_us() -> u64 {
+    let (instant, epoch_us) = CLOCK_ANCHOR.get_or_init(|| {
+        let epoch_us = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_micros() as u64)
+            .unwrap_or(0);
+        (Instant::now(), epoch_us)
+    });
+    epoch_us + instant.elapsed().as_micros() as u64
+}
+
+/// A single clock exchange result
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClockSample {
+    /// Remote clock minus local clock (µs)
+    pub offset_us: i64,
+    /// Round-trip delay (µs)
+    pub delay_us: u64,
+}
+
+impl ClockSample {
+    /// Compute a sample from the four exchange timestamps
+    pub fn from_exchange(t0: u64, t1: u64, t2: u64, t3: u64) -> Self {
+        let (t0, t1, t2, t3) = (t0 as i64, t1 as i64, t2 as i64, t3 as i64);
+        let offset_us = ((t1 - t0) + (t2 - t3)) / 2;
+        let delay_us = ((t3 - t0) - (t2 - t1)).max(0) as u64;
+        Self { offset_us, delay_us }
+    }
+}
+
+/// Clock offset estimator for one remote peer
+pub struct ClockSync {
+    source: ClockSource,
+    samples: VecDeque<ClockSample>,
+    window: usize,
+}
+
+impl ClockSync {
+    /// Create a new estimator
+    pub fn new(source: ClockSource, window: usize) -> Self {
+        Self {
+            source,
+            samples: VecDeque::with_capacity(window.max(1)),
+            window: window.max(1),
+        }
+    }
+
+    /// Add a completed exchange
+    pub fn add_exchange(&mut self, t0: u64, t1: u64, t2: u64, t3: u64) -> ClockSample {
+        let sample = ClockSample::from_exchange(t0, t1, t2, t3);
+        if self.samples.len() == self.window {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+        sample
+    }
+
+    /// Best (minimum delay) sample in the window
+    pub fn best_sample(&self) -> Option<ClockSample> {
+        self.samples.iter().min_by_key(|s| s.delay_us).copied()
+    }
+
+    /// Estimated remote-minus-local offset in microseconds
+    ///
+    /// Always zero when the system clocks are trusted.
+    pub fn offset_us(&self) -> i64 {
+        match self.source {
+            ClockSource::System => 0,
+            ClockSource::Estimated => self.best_sample().map(|s| s.offset_us).unwrap_or(0),
+        }
+    }
+
+    /// Round-trip delay of the best sample
+    pub fn round_trip_us(&self) -> Option<u64> {
+        self.best_sample().map(|s| s.delay_us)
+    }
+
+    /// Convert a remote timestamp to the local clock
+    pub fn remote_to_local(&self, remote_us: u64) -> u64 {
+        (remote_us as i64 - self.offset_us()).max(0) as u64
+    }
+
+    /// Check whether the offset can be relied on
+    pub fn is_synchronized(&self) -> bool {
+        match self.source {
+            ClockSource::System => true,
+            ClockSource::Estimated => !self.samples.is_empty(),
+        }
+    }
+
+    /// Get the clock source
+    pub fn source(&self) -> ClockSource {
+        self.source
+    }
+
+    /// Discard all samples
+    pub fn reset(&mut self) {
+        self.samples.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exchange_offset() {
+        // Remote is 1000 µs ahead, 100 µs each way, 10 µs processing
+        let sample = ClockSample::from_exchange(0, 1100, 1110, 210);
+        assert_eq!(sample.offset_us, 1000);
+        assert_eq!(sample.delay_us, 200);
+    }
+
+    #[test]
+    fn test_min_delay_selection() {
+        let mut sync = ClockSync::new(ClockSource::Estimated, 4);
+        assert!(!sync.is_synchronized());
+
+        // Queued exchange: asymmetric delay skews the offset
+        sync.add_exchange(0, 5100, 5110, 5210);
+        // Clean exchange
+        sync.add_exchange(10_000, 11_050, 11_060, 10_110);
+
+        assert!(sync.is_synchronized());
+        assert_eq!(sync.offset_us(), 1000);
+        assert_eq!(sync.round_trip_us(), Some(100));
+        assert_eq!(sync.remote_to_local(21_000), 20_000);
+    }
+
+    #[test]
+    fn test_system_source_trusts_clock() {
+        let mut sync = ClockSync::new(ClockSource::System, 4);
+        sync.add_exchange(0, 1100, 1110, 210);
+        assert_eq!(sync.offset_us(), 0);
+        assert!(now_us() > 0);
+    }
+}
@@ -0,0 +1,182 @@
+//! Media clock timestamps on the wire
+//!
+//! Audio packets from senders speaking [`ProtocolFeatures::MEDIA_CLOCK`]
+//! carry a per-track media timestamp, like RTP: the number of sample
+//! frames at [`MEDIA_CLOCK_RATE`] between the start of the track's stream
+//! and the first sample of the packet. It advances by exactly the audio
+//! each packet covers (suppressed DTX frames and pauses included), so
+//! packet spacing is never disturbed by when the encoder happened to run.
+//!
+//! To put a media timestamp on the shared clock ([`now_us`](super::now_us)),
+//! the sender periodically reports a pair of the two, like an RTCP sender
+//! report ([`PeerMessage::MediaClock`]). The receiver converts through the
+//! latest report for the track.
+//!
+//! [`ProtocolFeatures::MEDIA_CLOCK`]: crate::protocol::ProtocolFeatures::MEDIA_CLOCK
+//! [`PeerMessage::MediaClock`]: crate::protocol::PeerMessage::MediaClock
+
+use serde::{Deserialize, Serialize};
+
+/// Media clock ticks per second (Opus always runs at 48 kHz)
+pub const MEDIA_CLOCK_RATE: u32 = 48_000;
+
+/// Interval between clock reports for a track (µs)
+pub const REPORT_INTERVAL_US: u64 = 1_000_000;
+
+/// Difference between the shared clock and the media clock beyond which
+/// the sender re-anchors instead of slewing (µs)
+const MAX_SLEW_US: i64 = 200_000;
+
+/// Fraction of the difference corrected per packet
+const SLEW_FACTOR: i64 = 16;
+
+/// Ticks covering `us` microseconds
+pub fn us_to_ticks(us: u64) -> u64 {
+    (us as u128 * MEDIA_CLOCK_RATE as u128 / 1_000_000) as u64
+}
+
+/// Microseconds covered by `ticks`
+pub fn ticks_to_us(ticks: u64) -> u64 {
+    (ticks as u128 * 1_000_000 / MEDIA_CLOCK_RATE as u128) as u64
+}
+
+/// Ticks covering `frames` sample frames at `sample_rate`
+pub fn frames_to_ticks(frames: usize, sample_rate: u32) -> u32 {
+    (frames as u64 * MEDIA_CLOCK_RATE as u64 / sample_rate.max(1) as u64) as u32
+}
+
+/// A media timestamp and the shared-clock time it corresponds to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MediaClockRef {
+    pub ticks: u64,
+    pub wall_us: u64,
+}
+
+impl MediaClockRef {
+    /// Shared-clock time (µs) of a media timestamp of the same track
+    pub fn to_wall_us(&self, ticks: u64) -> u64 {
+        if ticks >= self.ticks {
+            self.wall_us + ticks_to_us(ticks - self.ticks)
+        } else {
+            self.wall_us.saturating_sub(ticks_to_us(self.ticks - ticks))
+        }
+    }
+
+    /// Media timestamp at a shared-clock time
+    pub fn to_ticks(&self, wall_us: u64) -> u64 {
+        if wall_us >= self.wall_us {
+            self.ticks + us_to_ticks(wall_us - self.wall_us)
+        } else {
+            self.ticks.saturating_sub(us_to_ticks(self.wall_us - wall_us))
+        }
+    }
+}
+
+/// Sender side of one track's media clock
+///
+/// Timestamps count the audio sent; the reference to the shared clock
+/// follows the time packets are sent at, slewed so scheduling jitter
+/// doesn't reach it. A stall or pause longer than the slew allows moves
+/// the media clock forward over the gap.
+#[derive(Debug, Default)]
+pub struct TrackClock {
+    /// Timestamp of the next packet
+    next: u64,
+    reference: Option<MediaClockRef>,
+    /// When the reference was last reported (µs), None to report it now
+    reported_us: Option<u64>,
+}
+
+impl TrackClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Timestamp for a packet of `ticks` sent at `send_us`; advances the clock past it
+    pub fn stamp(&mut self, ticks: u32, send_us: u64) -> u64 {
+        let reference = match self.reference {
+            None => MediaClockRef { ticks: self.next, wall_us: send_us },
+            Some(reference) => {
+                let predicted = reference.to_wall_us(self.next);
+                let error = send_us as i64 - predicted as i64;
+                if error > MAX_SLEW_US {
+                    // The gap wasn't sent: skip the media clock over it
+                    self.next = reference.to_ticks(send_us);
+                    self.reported_us = None;
+                    MediaClockRef { ticks: self.next, wall_us: send_us }
+                } else if error < -MAX_SLEW_US {
+                    self.reported_us = None;
+                    MediaClockRef { ticks: self.next, wall_us: send_us }
+                } else {
+                    let wall_us = (predicted as i64 + error / SLEW_FACTOR).max(0) as u64;
+                    MediaClockRef { ticks: self.next, wall_us }
+                }
+            }
+        };
+        self.reference = Some(reference);
+
+        let timestamp = self.next;
+        self.next += ticks as u64;
+        timestamp
+    }
+
+    /// Advance over audio that is not sent (suppressed DTX frames)
+    pub fn skip(&mut self, ticks: u32) {
+        self.next += ticks as u64;
+    }
+
+    /// Current reference to the shared clock, once a packet was stamped
+    pub fn reference(&self) -> Option<MediaClockRef> {
+        self.reference
+    }
+
+    /// The reference, if it is due to be reported at `now_us`
+    pub fn report(&mut self, now_us: u64) -> Option<MediaClockRef> {
+        let due = self.reported_us.is_none_or(|at| now_us.saturating_sub(at) >= REPORT_INTERVAL_US);
+        let reference = self.reference.filter(|_| due)?;
+        self.reported_us = Some(now_us);
+        Some(reference)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conversions() {
+        assert_eq!(us_to_ticks(10_000), 480);
+        assert_eq!(ticks_to_us(480), 10_000);
+        assert_eq!(frames_to_ticks(441, 44_100), 480);
+        assert_eq!(frames_to_ticks(960, 48_000), 960);
+
+        let reference = MediaClockRef { ticks: 48_000, wall_us: 5_000_000 };
+        assert_eq!(reference.to_wall_us(96_000), 6_000_000);
+        assert_eq!(reference.to_wall_us(24_000), 4_500_000);
+        assert_eq!(reference.to_ticks(5_010_000), 48_480);
+        assert_eq!(reference.to_ticks(4_990_000), 47_520);
+    }
+
+    #[test]
+    fn test_track_clock() {
+        let mut clock = TrackClock::new();
+        assert_eq!(clock.report(0), None);
+
+        // Packets sent with jitter still advance by the audio they cover
+        let sent = [1_000_000u64, 1_012_000, 1_019_500, 1_031_000];
+        let stamps: Vec<u64> = sent.iter().map(|&us| clock.stamp(480, us)).collect();
+        assert_eq!(stamps, vec![0, 480, 960, 1440]);
+        let reference = clock.report(1_031_000).unwrap();
+        assert!(reference.to_wall_us(1440).abs_diff(1_030_000) < 1_000, "{:?}", reference);
+        assert_eq!(clock.report(1_500_000), None);
+
+        // Suppressed frames keep their place
+        clock.skip(480 * 3);
+        assert_eq!(clock.stamp(480, 1_070_000), 1440 + 480 * 4);
+
+        // A pause skips the clock forward and reports at once
+        let resumed = clock.stamp(480, 11_070_000);
+        assert!(resumed.abs_diff(us_to_ticks(10_070_000)) < 480, "{}", resumed);
+        assert_eq!(clock.report(11_070_000), Some(MediaClockRef { ticks: resumed, wall_us: 11_070_000 }));
+    }
+}
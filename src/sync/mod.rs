@@ -0,0 +1,21 @@
+//! Clock synchronization between sender and receiver
+//!
+//! Timestamps are taken from a shared epoch-based clock so that
+//! sender and receiver timestamps are comparable. When the system clocks
+//! are already disciplined (PTP/NTP), they can be trusted directly;
+//! otherwise the receiver estimates the offset NTP-style over the
+//! control channel.
+//!
+//! Audio packets carry a per-track media clock rather than the shared
+//! clock itself; sender reports relate the two (see [`media`]).
+//!
+//! On the receiver, tracks from one sender can additionally be aligned
+//! to a common playout timeline.
+
+pub mod align;
+pub mod clock;
+pub mod media;
+
+pub use align::SyncGroup;
+pub use clock::{now_us, ClockSync, ClockSample};
+pub use media::{MediaClockRef, TrackClock, MEDIA_CLOCK_RATE};
@@ -0,0 +1,94 @@
+//! Track auto-start
+//!
+//! On boot the sender brings up the first saved track, as it always has, and
+//! every other saved track with `auto_start` set, so tracks created over the
+//! API and saved with the config come back without being started by hand.
+//! Tracks on the same device share one capture, and a track taking one
+//! channel of a split device brings its sibling channels along. The sender
+//! opens each group's device and keeps retrying under the capture recovery
+//! policy while it is not plugged in yet.
+
+use std::collections::BTreeSet;
+
+use crate::protocol::TrackConfig;
+
+/// Saved tracks published from one capture device
+#[derive(Debug, Clone)]
+pub struct CaptureGroup {
+    pub device_id: String,
+    /// In saved order, each with its track ID filled in
+    pub tracks: Vec<TrackConfig>,
+}
+
+/// The saved tracks to start on boot, grouped by device
+///
+/// The first track is track 0 without an ID (as for config reloads); other
+/// tracks without one get the lowest ID no saved track uses.
+pub fn plan(saved: &[TrackConfig]) -> Vec<CaptureGroup> {
+    let mut used: BTreeSet<u8> = saved.iter().filter_map(|t| t.track_id).collect();
+    if saved.first().is_some_and(|t| t.track_id.is_none()) {
+        used.insert(0);
+    }
+    let tracks: Vec<TrackConfig> = saved
+        .iter()
+        .enumerate()
+        .map(|(index, track)| {
+            let id = track.track_id.or((index == 0).then_some(0)).or_else(|| {
+                let free = (0..=u8::MAX).find(|id| !used.contains(id))?;
+                used.insert(free);
+                Some(free)
+            });
+            TrackConfig { track_id: id, ..track.clone() }
+        })
+        .collect();
+
+    let wanted = |index: usize, track: &TrackConfig| index == 0 || track.auto_start;
+    let mut groups: Vec<CaptureGroup> = Vec::new();
+    for (index, track) in tracks.iter().enumerate() {
+        // Split channels of a device come up together
+        let sibling = track.source_channel.is_some()
+            && tracks.iter().enumerate().any(|(i, t)| {
+                wanted(i, t) && t.device_id == track.device_id && t.source_channel.is_some()
+            });
+        if !wanted(index, track) && !sibling {
+            continue;
+        }
+        match groups.iter_mut().find(|g| g.device_id == track.device_id) {
+            Some(group) => group.tracks.push(track.clone()),
+            None => groups.push(CaptureGroup { device_id: track.device_id.clone(), tracks: vec![track.clone()] }),
+        }
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_groups_flagged_tracks() {
+        let track = |id: Option<u8>, device: &str, channel: Option<u16>, auto_start: bool| TrackConfig {
+            track_id: id,
+            device_id: device.to_string(),
+            source_channel: channel,
+            auto_start,
+            ..Default::default()
+        };
+        assert!(plan(&[]).is_empty());
+
+        let saved = [
+            track(None, "input:Mic", None, false),
+            track(Some(1), "input:Interface", Some(0), false),
+            track(None, "input:Interface", Some(1), true),
+            track(Some(4), "input:Desktop", None, false),
+            track(Some(5), "input:Mic", None, true),
+        ];
+        let groups = plan(&saved);
+        let ids = |group: &CaptureGroup| group.tracks.iter().map(|t| t.track_id.unwrap()).collect::<Vec<_>>();
+        assert_eq!(groups.len(), 2);
+        // The first track always starts; flagged tracks join their device
+        assert_eq!((groups[0].device_id.as_str(), ids(&groups[0])), ("input:Mic", vec![0, 5]));
+        // One flagged channel brings its sibling along; a free ID is assigned
+        assert_eq!((groups[1].device_id.as_str(), ids(&groups[1])), ("input:Interface", vec![1, 2]));
+    }
+}
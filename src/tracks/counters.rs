@@ -0,0 +1,195 @@
+//! Lock-free per-track counters
+//!
+//! Every track ID owns a fixed slot of atomics, padded to its own cache
+//! lines, so the capture, encode, network and playback threads can bump a
+//! track's counters with one relaxed add and no lock or lookup, and
+//! threads working on different tracks never touch the same line. The
+//! stats sampler and the API read a [`TrackCounters`] snapshot of a slot.
+
+use crossbeam::utils::CachePadded;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::tracks::stats::TrackCounters;
+
+/// Cumulative per-track counters
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Counter {
+    /// Packets sent (sender) or received (receiver)
+    Packets,
+    /// Audio payload bytes sent or received
+    Bytes,
+    /// Frames encoded or decoded
+    Frames,
+    PacketsLost,
+    LatePackets,
+    ReorderedPackets,
+    Overflows,
+    DuplicatePackets,
+    Underruns,
+}
+
+const COUNTERS: usize = Counter::Underruns as usize + 1;
+
+/// Current per-track values, unset until first written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Gauge {
+    /// Jitter buffer fill in frames
+    JitterLevel,
+    /// Jitter buffer size in frames
+    JitterCapacity,
+    /// Sender-to-receiver transit time in microseconds
+    TransitUs,
+}
+
+const GAUGES: usize = Gauge::TransitUs as usize + 1;
+
+/// Gauge value meaning "never set"
+const UNSET: u64 = u64::MAX;
+
+/// Counters of one track
+struct Slot {
+    counters: [AtomicU64; COUNTERS],
+    gauges: [AtomicU64; GAUGES],
+}
+
+impl Default for Slot {
+    fn default() -> Self {
+        Self {
+            counters: std::array::from_fn(|_| AtomicU64::new(0)),
+            gauges: std::array::from_fn(|_| AtomicU64::new(UNSET)),
+        }
+    }
+}
+
+/// Counters of every possible track ID
+pub struct CounterRegistry {
+    slots: Box<[CachePadded<Slot>]>,
+}
+
+impl CounterRegistry {
+    pub fn new() -> Self {
+        Self { slots: (0..=u8::MAX).map(|_| CachePadded::new(Slot::default())).collect() }
+    }
+
+    /// Add `n` to a counter of a track
+    #[inline]
+    pub fn add(&self, track_id: u8, counter: Counter, n: u64) {
+        self.slots[track_id as usize].counters[counter as usize].fetch_add(n, Ordering::Relaxed);
+    }
+
+    /// Overwrite a counter kept cumulatively elsewhere
+    #[inline]
+    pub fn store(&self, track_id: u8, counter: Counter, value: u64) {
+        self.slots[track_id as usize].counters[counter as usize].store(value, Ordering::Relaxed);
+    }
+
+    /// Current value of a counter
+    pub fn get(&self, track_id: u8, counter: Counter) -> u64 {
+        self.slots[track_id as usize].counters[counter as usize].load(Ordering::Relaxed)
+    }
+
+    /// Set a gauge of a track (None unsets it)
+    #[inline]
+    pub fn set(&self, track_id: u8, gauge: Gauge, value: Option<u64>) {
+        // A real value of u64::MAX would read back as unset; clamp it below
+        let value = value.map_or(UNSET, |v| v.min(UNSET - 1));
+        self.slots[track_id as usize].gauges[gauge as usize].store(value, Ordering::Relaxed);
+    }
+
+    /// Current value of a gauge
+    pub fn gauge(&self, track_id: u8, gauge: Gauge) -> Option<u64> {
+        let value = self.slots[track_id as usize].gauges[gauge as usize].load(Ordering::Relaxed);
+        (value != UNSET).then_some(value)
+    }
+
+    /// Everything a track's slot holds
+    ///
+    /// Each value is read on its own, so a snapshot taken during updates
+    /// may mix values a few increments apart; the histogram and occupancy
+    /// fields are left for the caller.
+    pub fn snapshot(&self, track_id: u8) -> TrackCounters {
+        let get = |counter| self.get(track_id, counter);
+        TrackCounters {
+            packets: get(Counter::Packets),
+            bytes: get(Counter::Bytes),
+            frames: get(Counter::Frames),
+            packets_lost: get(Counter::PacketsLost),
+            late_packets: get(Counter::LatePackets),
+            reordered_packets: get(Counter::ReorderedPackets),
+            overflows: get(Counter::Overflows),
+            duplicate_packets: get(Counter::DuplicatePackets),
+            underruns: get(Counter::Underruns),
+            jitter_level: self.gauge(track_id, Gauge::JitterLevel).map(|v| v as usize),
+            jitter_capacity: self.gauge(track_id, Gauge::JitterCapacity).map(|v| v as usize),
+            transit_ms: self.gauge(track_id, Gauge::TransitUs).map(|v| v as f64 / 1000.0),
+            ..Default::default()
+        }
+    }
+
+    /// Zero a track's counters and unset its gauges, for a removed or reused ID
+    pub fn reset(&self, track_id: u8) {
+        let slot = &self.slots[track_id as usize];
+        for counter in &slot.counters {
+            counter.store(0, Ordering::Relaxed);
+        }
+        for gauge in &slot.gauges {
+            gauge.store(UNSET, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Default for CounterRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn test_counters_and_gauges() {
+        let registry = CounterRegistry::new();
+        registry.add(3, Counter::Packets, 2);
+        registry.add(3, Counter::Bytes, 400);
+        registry.store(3, Counter::Overflows, 7);
+        registry.set(3, Gauge::JitterLevel, Some(4));
+        registry.set(3, Gauge::TransitUs, Some(2500));
+
+        let snapshot = registry.snapshot(3);
+        assert_eq!((snapshot.packets, snapshot.bytes, snapshot.overflows), (2, 400, 7));
+        assert_eq!(snapshot.jitter_level, Some(4));
+        assert_eq!(snapshot.jitter_capacity, None);
+        assert_eq!(snapshot.transit_ms, Some(2.5));
+        assert_eq!(registry.snapshot(4), TrackCounters::default());
+
+        registry.set(3, Gauge::JitterLevel, None);
+        assert_eq!(registry.gauge(3, Gauge::JitterLevel), None);
+        registry.reset(3);
+        assert_eq!(registry.snapshot(3), TrackCounters::default());
+    }
+
+    #[test]
+    fn test_concurrent_adds() {
+        let registry = Arc::new(CounterRegistry::new());
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let registry = registry.clone();
+                thread::spawn(move || {
+                    for track_id in 0..16 {
+                        for _ in 0..1000 {
+                            registry.add(track_id, Counter::Packets, 1);
+                        }
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        assert!((0..16).all(|track_id| registry.get(track_id, Counter::Packets) == 4000));
+    }
+}
@@ -0,0 +1,175 @@
+//! Per-thread CPU usage
+//!
+//! Reads CPU time for every thread of the process from `/proc/self/task` and
+//! groups it by thread name, so capture, playback, encoder and network
+//! threads can be told apart. Threads the audio backend spawns inherit the
+//! name of the thread that opened the stream, so a track's callbacks are
+//! counted with its `capture-track-N` / `play-track-N` thread. Only Linux
+//! exposes this; elsewhere the report is empty.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
+
+/// Minimum time between samples; faster callers get the previous report
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Kernel clock ticks per second for `/proc` CPU times (USER_HZ)
+#[cfg(target_os = "linux")]
+const CLOCK_TICKS: f64 = 100.0;
+
+/// CPU usage of the threads sharing a name
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ThreadCpu {
+    pub name: String,
+    /// Threads with this name
+    pub threads: usize,
+    /// User plus system time since the threads started
+    pub cpu_seconds: f64,
+    /// Share of one core over the last sample interval
+    pub cpu_pct: f64,
+}
+
+/// Previous sample, for rates
+struct Sample {
+    at: Instant,
+    ticks: HashMap<u32, u64>,
+    report: Vec<ThreadCpu>,
+}
+
+/// Samples per-thread CPU time
+#[derive(Default)]
+pub struct ThreadCpuMonitor {
+    last: Mutex<Option<Sample>>,
+}
+
+impl ThreadCpuMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// CPU usage grouped by thread name, busiest first
+    pub fn report(&self) -> Vec<ThreadCpu> {
+        let mut last = self.last.lock();
+        if let Some(sample) = last.as_ref() {
+            if sample.at.elapsed() < SAMPLE_INTERVAL {
+                return sample.report.clone();
+            }
+        }
+
+        let now = Instant::now();
+        let threads = read_threads();
+        let report = summarize(&threads, last.as_ref().map(|s| (now - s.at, &s.ticks)));
+        *last = Some(Sample {
+            at: now,
+            ticks: threads.iter().map(|t| (t.tid, t.ticks)).collect(),
+            report: report.clone(),
+        });
+        report
+    }
+}
+
+/// One thread's cumulative CPU time
+struct ThreadTicks {
+    tid: u32,
+    name: String,
+    ticks: u64,
+}
+
+/// Group threads by name, with rates against a previous sample
+fn summarize(threads: &[ThreadTicks], previous: Option<(Duration, &HashMap<u32, u64>)>) -> Vec<ThreadCpu> {
+    let mut groups: BTreeMap<&str, (usize, u64, u64)> = BTreeMap::new();
+    for thread in threads {
+        let group = groups.entry(thread.name.as_str()).or_default();
+        group.0 += 1;
+        group.1 += thread.ticks;
+        if let Some((_, ticks)) = previous {
+            // New threads count from zero
+            group.2 += thread.ticks.saturating_sub(ticks.get(&thread.tid).copied().unwrap_or(0));
+        }
+    }
+
+    let elapsed = previous.map_or(0.0, |(elapsed, _)| elapsed.as_secs_f64());
+    let mut report: Vec<ThreadCpu> = groups
+        .into_iter()
+        .map(|(name, (threads, total, delta))| ThreadCpu {
+            name: name.to_string(),
+            threads,
+            cpu_seconds: ticks_to_secs(total),
+            cpu_pct: if elapsed > 0.0 { ticks_to_secs(delta) * 100.0 / elapsed } else { 0.0 },
+        })
+        .collect();
+    report.sort_by(|a, b| b.cpu_pct.total_cmp(&a.cpu_pct).then(b.cpu_seconds.total_cmp(&a.cpu_seconds)));
+    report
+}
+
+#[cfg(target_os = "linux")]
+fn ticks_to_secs(ticks: u64) -> f64 {
+    ticks as f64 / CLOCK_TICKS
+}
+
+#[cfg(not(target_os = "linux"))]
+fn ticks_to_secs(_ticks: u64) -> f64 {
+    0.0
+}
+
+/// Parse name and utime + stime from a `/proc/<pid>/task/<tid>/stat` line
+fn parse_stat(line: &str) -> Option<(String, u64)> {
+    // The name is in parentheses and may itself contain spaces or ')'
+    let open = line.find('(')?;
+    let close = line.rfind(')')?;
+    let name = line.get(open + 1..close)?.to_string();
+    let mut fields = line.get(close + 1..)?.split_whitespace();
+    // Fields after the name start at state (3); utime and stime are 14 and 15
+    let utime: u64 = fields.nth(11)?.parse().ok()?;
+    let stime: u64 = fields.next()?.parse().ok()?;
+    Some((name, utime + stime))
+}
+
+#[cfg(target_os = "linux")]
+fn read_threads() -> Vec<ThreadTicks> {
+    let Ok(entries) = std::fs::read_dir("/proc/self/task") else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let tid = entry.file_name().to_str()?.parse().ok()?;
+            let stat = std::fs::read_to_string(entry.path().join("stat")).ok()?;
+            let (name, ticks) = parse_stat(&stat)?;
+            Some(ThreadTicks { tid, name, ticks })
+        })
+        .collect()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read_threads() -> Vec<ThreadTicks> {
+    Vec::new()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_group() {
+        let stat = "4242 (encoder-0) S 1 1 1 0 -1 4194368 0 0 0 0 150 50 0 0 20 0 9 0 100 0 0";
+        assert_eq!(parse_stat(stat), Some(("encoder-0".to_string(), 200)));
+        assert_eq!(parse_stat("7 (a) b) R 1 1 1 0 -1 0 0 0 0 0 3 4").map(|p| p.0), Some("a) b".to_string()));
+
+        let thread = |tid, name: &str, ticks| ThreadTicks { tid, name: name.to_string(), ticks };
+        let previous: HashMap<u32, u64> = [(1, 100), (2, 100)].into_iter().collect();
+        let threads = [thread(1, "play-track-0", 150), thread(2, "play-track-0", 110), thread(3, "encoder-0", 20)];
+        let report = summarize(&threads, Some((Duration::from_secs(2), &previous)));
+
+        assert_eq!(report.len(), 2);
+        assert_eq!(report[0].name, "play-track-0");
+        assert_eq!(report[0].threads, 2);
+        if cfg!(target_os = "linux") {
+            assert!((report[0].cpu_seconds - 2.6).abs() < 1e-9);
+            assert!((report[0].cpu_pct - 30.0).abs() < 1e-9);
+            assert!((report[1].cpu_pct - 10.0).abs() < 1e-9);
+        }
+    }
+}
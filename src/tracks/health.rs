@@ -0,0 +1,193 @@
+//! Health scoring
+//!
+//! The audio loops report xruns, packet loss, encode deadline misses and
+//! device errors here; a report scores what happened over the last window
+//! as OK, DEGRADED or CRITICAL with the reasons that contributed.
+
+use parking_lot::Mutex;
+use std::collections::{BTreeMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use crate::config::HealthConfig;
+use crate::protocol::{HealthLevel, HealthReason, HealthReport, HealthSignal};
+
+/// One report from the audio path
+struct Sample {
+    at: Instant,
+    signal: HealthSignal,
+    track_id: u8,
+    count: u64,
+    /// Packets expected, for loss
+    total: u64,
+}
+
+/// Sliding-window health monitor
+pub struct HealthMonitor {
+    config: Mutex<HealthConfig>,
+    samples: Mutex<VecDeque<Sample>>,
+}
+
+impl HealthMonitor {
+    /// Create a monitor with default thresholds
+    pub fn new() -> Self {
+        Self {
+            config: Mutex::new(HealthConfig::default()),
+            samples: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Replace the thresholds
+    pub fn configure(&self, config: &HealthConfig) {
+        *self.config.lock() = config.clone();
+    }
+
+    /// Record `count` events of a signal on a track
+    pub fn record(&self, signal: HealthSignal, track_id: u8, count: u64) {
+        if count > 0 {
+            self.push(Sample { at: Instant::now(), signal, track_id, count, total: 0 });
+        }
+    }
+
+    /// Record packets lost out of those expected on a track
+    pub fn record_loss(&self, track_id: u8, lost: u64, expected: u64) {
+        if expected > 0 {
+            self.push(Sample {
+                at: Instant::now(),
+                signal: HealthSignal::PacketLoss,
+                track_id,
+                count: lost,
+                total: expected,
+            });
+        }
+    }
+
+    fn push(&self, sample: Sample) {
+        let window = self.window();
+        let mut samples = self.samples.lock();
+        prune(&mut samples, sample.at, window);
+        samples.push_back(sample);
+    }
+
+    fn window(&self) -> Duration {
+        Duration::from_secs(self.config.lock().window_secs.max(1) as u64)
+    }
+
+    /// Score the recent window
+    ///
+    /// `lost_devices` are tracks whose device is currently gone; they make
+    /// the report critical regardless of the window.
+    pub fn report(&self, lost_devices: &[u8]) -> HealthReport {
+        let config = self.config.lock().clone();
+        let now = Instant::now();
+
+        // Sum counts per signal and track
+        let mut totals: BTreeMap<(HealthSignal, u8), (u64, u64)> = BTreeMap::new();
+        {
+            let mut samples = self.samples.lock();
+            prune(&mut samples, now, Duration::from_secs(config.window_secs.max(1) as u64));
+            for sample in samples.iter() {
+                let entry = totals.entry((sample.signal, sample.track_id)).or_default();
+                entry.0 += sample.count;
+                entry.1 += sample.total;
+            }
+        }
+
+        let mut reasons = Vec::new();
+        for (&(signal, track_id), &(count, total)) in &totals {
+            let (value, level, message) = match signal {
+                HealthSignal::Xrun => (
+                    count as f64,
+                    grade(count as f64, config.xruns_degraded as f64, config.xruns_critical as f64),
+                    format!("{} xruns", count),
+                ),
+                HealthSignal::PacketLoss => {
+                    let pct = count as f64 * 100.0 / total.max(1) as f64;
+                    (
+                        pct,
+                        grade(pct, config.loss_degraded_pct as f64, config.loss_critical_pct as f64),
+                        format!("{:.1}% packet loss", pct),
+                    )
+                }
+                HealthSignal::DeadlineMiss => (
+                    count as f64,
+                    grade(
+                        count as f64,
+                        config.deadline_misses_degraded as f64,
+                        config.deadline_misses_critical as f64,
+                    ),
+                    format!("{} encode deadline misses", count),
+                ),
+                HealthSignal::DeviceError => {
+                    (count as f64, HealthLevel::Degraded, format!("{} device errors", count))
+                }
+            };
+            if level != HealthLevel::Ok {
+                reasons.push(HealthReason { signal, track_id: Some(track_id), level, value, message });
+            }
+        }
+
+        for &track_id in lost_devices {
+            reasons.push(HealthReason {
+                signal: HealthSignal::DeviceError,
+                track_id: Some(track_id),
+                level: HealthLevel::Critical,
+                value: 1.0,
+                message: "audio device lost".to_string(),
+            });
+        }
+
+        HealthReport {
+            status: reasons.iter().map(|r| r.level).max().unwrap_or_default(),
+            window_secs: config.window_secs,
+            reasons,
+        }
+    }
+}
+
+impl Default for HealthMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Drop samples older than the window
+fn prune(samples: &mut VecDeque<Sample>, now: Instant, window: Duration) {
+    while samples.front().is_some_and(|s| now.duration_since(s.at) > window) {
+        samples.pop_front();
+    }
+}
+
+/// Grade a value against degraded/critical thresholds (0 disables a threshold)
+fn grade(value: f64, degraded: f64, critical: f64) -> HealthLevel {
+    if critical > 0.0 && value >= critical {
+        HealthLevel::Critical
+    } else if degraded > 0.0 && value >= degraded {
+        HealthLevel::Degraded
+    } else {
+        HealthLevel::Ok
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_health_scoring() {
+        let health = HealthMonitor::new();
+        assert_eq!(health.report(&[]).status, HealthLevel::Ok);
+
+        // Below the xrun threshold, 3% loss is degraded
+        health.record(HealthSignal::Xrun, 0, 2);
+        health.record_loss(1, 3, 100);
+        let report = health.report(&[]);
+        assert_eq!(report.status, HealthLevel::Degraded);
+        assert_eq!(report.reasons.len(), 1);
+        assert_eq!(report.reasons[0].signal, HealthSignal::PacketLoss);
+        assert_eq!(report.reasons[0].track_id, Some(1));
+
+        health.record(HealthSignal::DeadlineMiss, 0, 60);
+        assert_eq!(health.report(&[]).status, HealthLevel::Critical);
+        assert_eq!(HealthMonitor::new().report(&[2]).status, HealthLevel::Critical);
+    }
+}
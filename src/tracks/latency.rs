@@ -0,0 +1,158 @@
+//! Packet path latency
+//!
+//! The sender (capture → ring → frame → process → encode → send) and receiver
+//! (network → receive → decode → jitter → playback) stages are timed or
+//! estimated per track; the stage means add up to an end-to-end estimate in
+//! the track stats. Timed stages run inside a `stage` trace span so they can
+//! also be followed with a tracing subscriber.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+/// Pipeline stage, in packet path order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    /// Device buffer before the capture callback
+    Capture,
+    /// Capture callback to the encode loop
+    Ring,
+    /// Collecting samples for a full encoder frame
+    Frame,
+    /// Processing chain
+    Process,
+    Encode,
+    /// Send queue and pacing up to the socket
+    Send,
+    /// Sender timestamp to socket arrival (needs clock sync)
+    Network,
+    /// Socket to the receive loop
+    Receive,
+    Decode,
+    /// Waiting in the jitter buffer
+    Jitter,
+    /// Playout delay and output queue
+    Playback,
+}
+
+impl Stage {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Capture => "capture",
+            Self::Ring => "ring",
+            Self::Frame => "frame",
+            Self::Process => "process",
+            Self::Encode => "encode",
+            Self::Send => "send",
+            Self::Network => "network",
+            Self::Receive => "receive",
+            Self::Decode => "decode",
+            Self::Jitter => "jitter",
+            Self::Playback => "playback",
+        }
+    }
+}
+
+/// Count, sum and maximum of one stage's samples
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StageTotals {
+    pub count: u64,
+    pub sum_us: u64,
+    pub max_us: u64,
+}
+
+impl StageTotals {
+    pub fn record(&mut self, duration: Duration) {
+        let us = duration.as_micros() as u64;
+        self.count += 1;
+        self.sum_us += us;
+        self.max_us = self.max_us.max(us);
+    }
+
+    pub fn merge(&mut self, other: StageTotals) {
+        self.count += other.count;
+        self.sum_us += other.sum_us;
+        self.max_us = self.max_us.max(other.max_us);
+    }
+}
+
+/// Latency of one stage over a stats interval
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StageLatency {
+    pub stage: Stage,
+    pub mean_ms: f64,
+    pub max_ms: f64,
+    pub samples: u64,
+}
+
+/// Collects stage latencies for one track between stats reports
+#[derive(Debug, Default)]
+pub struct LatencyRecorder {
+    stages: BTreeMap<Stage, StageTotals>,
+}
+
+impl LatencyRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record one measurement or estimate of a stage
+    pub fn record(&mut self, stage: Stage, duration: Duration) {
+        self.stages.entry(stage).or_default().record(duration);
+    }
+
+    /// Add totals collected elsewhere (e.g. on the network thread)
+    pub fn merge(&mut self, stage: Stage, totals: StageTotals) {
+        if totals.count > 0 {
+            self.stages.entry(stage).or_default().merge(totals);
+        }
+    }
+
+    /// Run `f` in a trace span for the stage and record how long it took
+    pub fn time<R>(&mut self, stage: Stage, track_id: u8, f: impl FnOnce() -> R) -> R {
+        let span = tracing::trace_span!("stage", track_id, stage = stage.name());
+        let started = Instant::now();
+        let result = span.in_scope(f);
+        self.record(stage, started.elapsed());
+        result
+    }
+
+    /// Per-stage latency since the last call, in pipeline order
+    pub fn take(&mut self) -> Vec<StageLatency> {
+        std::mem::take(&mut self.stages)
+            .into_iter()
+            .map(|(stage, totals)| StageLatency {
+                stage,
+                mean_ms: totals.sum_us as f64 / totals.count as f64 / 1000.0,
+                max_ms: totals.max_us as f64 / 1000.0,
+                samples: totals.count,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stage_breakdown() {
+        let mut recorder = LatencyRecorder::new();
+        recorder.record(Stage::Send, Duration::from_micros(500));
+        recorder.record(Stage::Capture, Duration::from_millis(10));
+        recorder.record(Stage::Capture, Duration::from_millis(20));
+        recorder.merge(Stage::Send, StageTotals { count: 1, sum_us: 1500, max_us: 1500 });
+        recorder.merge(Stage::Decode, StageTotals::default());
+        assert_eq!(recorder.time(Stage::Encode, 0, || 7), 7);
+
+        let stages = recorder.take();
+        let order: Vec<Stage> = stages.iter().map(|s| s.stage).collect();
+        assert_eq!(order, vec![Stage::Capture, Stage::Encode, Stage::Send]);
+        assert!((stages[0].mean_ms - 15.0).abs() < 1e-9);
+        assert!((stages[0].max_ms - 20.0).abs() < 1e-9);
+        assert!((stages[2].mean_ms - 1.0).abs() < 1e-9);
+        assert_eq!(stages[2].samples, 2);
+        assert!(recorder.take().is_empty());
+    }
+}
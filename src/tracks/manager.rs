@@ -1,13 +1,31 @@
 //! Track manager for handling multiple audio tracks
 
-use dashmap::DashMap;
-use std::sync::atomic::{AtomicU8, Ordering};
+use dashmap::{DashMap, DashSet};
+use parking_lot::Mutex;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU32, AtomicU8, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::broadcast;
 
+use crate::audio::custom::{AudioSink, AudioSource};
+use crate::dsp::{MeterBus, ProcessorConfig};
 use crate::error::TrackError;
-use crate::protocol::{TrackConfig, TrackConfigUpdate, TrackStatus};
-use crate::tracks::track::Track;
-use crate::constants::MAX_TRACKS;
+use crate::protocol::{
+    ActiveAlert, AlertKind, CapabilityReport, HealthReport, ReceiverCapabilities, TrackConfig, TrackConfigUpdate,
+    TrackGroup, TrackProfile, TrackSnapshot, TrackStatus, XrunEvent,
+};
+use crate::tracks::counters::CounterRegistry;
+use crate::tracks::cpu::{ThreadCpu, ThreadCpuMonitor};
+use crate::tracks::health::HealthMonitor;
+use crate::tracks::latency::StageLatency;
+use crate::tracks::session::SessionCollector;
+use crate::tracks::stats::{BandwidthReport, GroupStats, OccupancyTrend, StatsRegistry, TrackCounters, TrackStats};
+use crate::tracks::track::{Track, TrackState, TrackTransition};
+use crate::constants::{DEFAULT_MAX_TRACKS, MAX_TRACKS, MIN_TRACK_BITRATE};
+
+/// Track ID alerts about the whole stream are raised on
+pub const STREAM_ALERT_ID: u8 = u8::MAX;
 
 /// Events emitted by the track manager
 #[derive(Debug, Clone)]
@@ -17,7 +35,33 @@ pub enum TrackEvent {
     Started(u8),
     Stopped(u8),
     ConfigUpdated(u8),
+    Paused(u8),
+    Resumed(u8),
+    GateChanged(u8, bool),
+    Alert(u8, AlertKind, bool),
+    Xrun(XrunEvent),
+    /// Device now in use; true while on a fallback
+    DeviceMigrated(u8, String, bool),
     Error(u8, String),
+    /// Lifecycle state changed; sent for every step, before Started/Stopped
+    StateChanged(TrackTransition),
+}
+
+/// Who asked for a track's codec reset
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResetSource {
+    /// This end (REST); the peer is told to reset its side too
+    Local,
+    /// The peer reset its side
+    Peer,
+}
+
+/// Mixer state shared by the tracks of a group
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct GroupState {
+    muted: bool,
+    solo: bool,
+    gain_db: f32,
 }
 
 /// Track manager for sender or receiver
@@ -25,7 +69,7 @@ pub struct TrackManager {
     /// All tracks indexed by ID
     tracks: DashMap<u8, Track>,
     
-    /// Next available track ID
+    /// Where the search for a free track ID starts
     next_id: AtomicU8,
     
     /// Event broadcaster
@@ -34,43 +78,346 @@ pub struct TrackManager {
     /// Event receiver (for cloning)
     _event_rx: broadcast::Receiver<TrackEvent>,
     
-    /// Maximum tracks allowed
+    /// Maximum tracks allowed; track IDs stay below it
     max_tracks: usize,
     
-    /// Solo mode active (any track soloed)
+    /// Mute, solo and gain of track groups, by name (kept while a group is empty)
+    groups: parking_lot::RwLock<BTreeMap<String, GroupState>>,
+    
+    /// Solo mode active (any track or group soloed)
     solo_active: std::sync::atomic::AtomicBool,
+    
+    /// All tracks paused
+    all_paused: std::sync::atomic::AtomicBool,
+    
+    /// Talkback is set up on this end; push-to-talk is held
+    talkback_available: std::sync::atomic::AtomicBool,
+    talkback_active: std::sync::atomic::AtomicBool,
+    
+    /// Per-track meters for sidechain processing
+    meter_bus: Arc<MeterBus>,
+    
+    /// Total bitrate shared by unpaused tracks (0 = unlimited)
+    bitrate_budget: AtomicU32,
+    
+    /// Total bandwidth, headers included, that raises an alert (0 = none)
+    bandwidth_cap: AtomicU32,
+    
+    /// Alerts currently raised
+    active_alerts: DashSet<(u8, AlertKind)>,
+    
+    /// Uptime, running time and traffic statistics
+    session: SessionCollector,
+    
+    /// Xrun, loss, deadline and device error scoring
+    health: HealthMonitor,
+    
+    /// Per-track counters bumped by the audio path
+    counters: Arc<CounterRegistry>,
+    
+    /// Sampled counters with rates over the recent window
+    stats: StatsRegistry,
+    
+    /// CPU time per named thread
+    cpu: ThreadCpuMonitor,
+    
+    /// Custom inputs used instead of a capture device
+    sources: Mutex<HashMap<u8, Box<dyn AudioSource>>>,
+    
+    /// Custom outputs used instead of a playback device
+    sinks: Mutex<HashMap<u8, Box<dyn AudioSink>>>,
+    
+    /// Track profiles new tracks may name
+    profiles: parking_lot::RwLock<BTreeMap<String, TrackProfile>>,
+    
+    /// What the receiver last said it can play
+    receiver_capabilities: parking_lot::RwLock<Option<ReceiverCapabilities>>,
+    
+    /// Codec resets the audio path has yet to carry out
+    pending_resets: DashMap<u8, ResetSource>,
 }
 
 impl TrackManager {
-    /// Create a new track manager
+    /// Create a new track manager for the default number of tracks
     pub fn new() -> Self {
+        Self::with_max_tracks(DEFAULT_MAX_TRACKS)
+    }
+    
+    /// Create a track manager for up to `max_tracks` tracks (at most
+    /// [`MAX_TRACKS`]), with its per-track tables allocated up front
+    pub fn with_max_tracks(max_tracks: usize) -> Self {
+        let max_tracks = max_tracks.clamp(1, MAX_TRACKS);
         let (event_tx, event_rx) = broadcast::channel(256);
         
         Self {
-            tracks: DashMap::new(),
+            tracks: DashMap::with_capacity(max_tracks),
             next_id: AtomicU8::new(0),
             event_tx,
             _event_rx: event_rx,
-            max_tracks: MAX_TRACKS,
+            max_tracks,
+            groups: parking_lot::RwLock::new(BTreeMap::new()),
             solo_active: std::sync::atomic::AtomicBool::new(false),
+            all_paused: std::sync::atomic::AtomicBool::new(false),
+            talkback_available: std::sync::atomic::AtomicBool::new(false),
+            talkback_active: std::sync::atomic::AtomicBool::new(false),
+            meter_bus: Arc::new(MeterBus::new()),
+            bitrate_budget: AtomicU32::new(0),
+            bandwidth_cap: AtomicU32::new(0),
+            active_alerts: DashSet::new(),
+            session: SessionCollector::new(),
+            health: HealthMonitor::new(),
+            counters: Arc::new(CounterRegistry::new()),
+            stats: StatsRegistry::with_capacity(max_tracks),
+            cpu: ThreadCpuMonitor::new(),
+            sources: Mutex::new(HashMap::new()),
+            sinks: Mutex::new(HashMap::new()),
+            profiles: parking_lot::RwLock::new(BTreeMap::new()),
+            receiver_capabilities: parking_lot::RwLock::new(None),
+            pending_resets: DashMap::with_capacity(max_tracks),
         }
     }
     
+    /// Most tracks open at once
+    pub fn max_tracks(&self) -> usize {
+        self.max_tracks
+    }
+    
+    /// Use a custom source for a track instead of its device
+    pub fn set_source(&self, track_id: u8, source: Box<dyn AudioSource>) -> Result<(), TrackError> {
+        if !self.tracks.contains_key(&track_id) {
+            return Err(TrackError::NotFound(track_id));
+        }
+        self.sources.lock().insert(track_id, source);
+        Ok(())
+    }
+    
+    /// Take the custom source of a track, to hand to a [`SourceDriver`](crate::audio::SourceDriver)
+    pub fn take_source(&self, track_id: u8) -> Option<Box<dyn AudioSource>> {
+        self.sources.lock().remove(&track_id)
+    }
+    
+    /// Use a custom sink for a track instead of its device
+    pub fn set_sink(&self, track_id: u8, sink: Box<dyn AudioSink>) -> Result<(), TrackError> {
+        if !self.tracks.contains_key(&track_id) {
+            return Err(TrackError::NotFound(track_id));
+        }
+        self.sinks.lock().insert(track_id, sink);
+        Ok(())
+    }
+    
+    /// Take the custom sink of a track, to hand to a [`SinkDriver`](crate::audio::SinkDriver)
+    pub fn take_sink(&self, track_id: u8) -> Option<Box<dyn AudioSink>> {
+        self.sinks.lock().remove(&track_id)
+    }
+    
+    /// Check if a track has a custom source or sink waiting
+    pub fn has_custom_io(&self, track_id: u8) -> bool {
+        self.sources.lock().contains_key(&track_id) || self.sinks.lock().contains_key(&track_id)
+    }
+    
+    /// Get the session statistics collector
+    pub fn session(&self) -> &SessionCollector {
+        &self.session
+    }
+    
+    /// Get the live counters the audio path updates
+    pub fn counters(&self) -> Arc<CounterRegistry> {
+        self.counters.clone()
+    }
+    
+    /// Sample a track's live counters into its statistics
+    pub fn sample_stats(&self, track_id: u8) {
+        self.report_stats(track_id, self.counters.snapshot(track_id));
+    }
+    
+    /// Record the current counters of a track
+    pub fn report_stats(&self, track_id: u8, counters: TrackCounters) {
+        if self.tracks.contains_key(&track_id) {
+            self.stats.report(track_id, counters);
+            self.check_bandwidth();
+        }
+    }
+    
+    /// Set the total bandwidth in kbit/s that raises an alert (0 = none)
+    pub fn set_bandwidth_cap(&self, kbps: u32) {
+        self.bandwidth_cap.store(kbps, Ordering::Relaxed);
+        self.check_bandwidth();
+    }
+    
+    /// Current bandwidth of every track against the cap
+    pub fn bandwidth(&self) -> BandwidthReport {
+        let mut ids = self.track_ids();
+        ids.sort_unstable();
+        let stats: Vec<TrackStats> = ids.into_iter().map(|id| self.stats.get(id)).collect();
+        BandwidthReport::new(&stats, self.bandwidth_cap.load(Ordering::Relaxed))
+    }
+    
+    /// Raise or clear the bandwidth cap alert on [`STREAM_ALERT_ID`]
+    fn check_bandwidth(&self) {
+        let over_cap = self.bandwidth().over_cap;
+        self.set_alert(STREAM_ALERT_ID, AlertKind::BandwidthCap, over_cap);
+    }
+    
+    /// Record the latency breakdown of a track's packet path
+    pub fn report_latency(&self, track_id: u8, latency: Vec<StageLatency>) {
+        if self.tracks.contains_key(&track_id) {
+            self.stats.report_latency(track_id, latency);
+        }
+    }
+    
+    /// Get statistics for a track, with rates over the recent window
+    pub fn stats(&self, track_id: u8) -> Result<TrackStats, TrackError> {
+        if !self.tracks.contains_key(&track_id) {
+            return Err(TrackError::NotFound(track_id));
+        }
+        Ok(self.stats.get(track_id))
+    }
+    
+    /// Jitter buffer fill of a track over the last `window`
+    pub fn jitter_trend(&self, track_id: u8, window: Duration) -> Result<OccupancyTrend, TrackError> {
+        if !self.tracks.contains_key(&track_id) {
+            return Err(TrackError::NotFound(track_id));
+        }
+        Ok(self.stats.trend(track_id, window))
+    }
+    
+    /// CPU usage per named thread (capture, playback, encoders, network)
+    pub fn thread_cpu(&self) -> Vec<ThreadCpu> {
+        self.cpu.report()
+    }
+    
+    /// Get the health monitor
+    pub fn health(&self) -> &HealthMonitor {
+        &self.health
+    }
+    
+    /// Score recent health, including tracks whose device is gone
+    pub fn health_report(&self) -> HealthReport {
+        let lost_devices: Vec<u8> = self.active_alerts()
+            .into_iter()
+            .filter(|a| a.kind == AlertKind::DeviceLost)
+            .map(|a| a.track_id)
+            .collect();
+        self.health.report(&lost_devices)
+    }
+    
+    /// Get the meter bus shared by all tracks
+    pub fn meter_bus(&self) -> Arc<MeterBus> {
+        self.meter_bus.clone()
+    }
+    
     /// Subscribe to track events
     pub fn subscribe(&self) -> broadcast::Receiver<TrackEvent> {
         self.event_tx.subscribe()
     }
     
-    /// Create a new track
+    /// Set the track profiles new tracks may name
+    pub fn set_profiles(&self, profiles: BTreeMap<String, TrackProfile>) {
+        *self.profiles.write() = profiles;
+    }
+    
+    /// Track profiles, by name
+    pub fn profiles(&self) -> BTreeMap<String, TrackProfile> {
+        self.profiles.read().clone()
+    }
+    
+    /// Ask the audio path to reset a track's encoder or decoder and jitter buffer
+    pub fn request_reset(&self, track_id: u8, source: ResetSource) -> Result<(), TrackError> {
+        if !self.tracks.contains_key(&track_id) {
+            return Err(TrackError::NotFound(track_id));
+        }
+        // A local request still has to reach the peer
+        let mut pending = self.pending_resets.entry(track_id).or_insert(source);
+        if source == ResetSource::Local {
+            *pending = source;
+        }
+        Ok(())
+    }
+    
+    /// Take a pending reset of a track, if one was asked for
+    pub fn take_reset(&self, track_id: u8) -> Option<ResetSource> {
+        self.pending_resets.remove(&track_id).map(|(_, source)| source)
+    }
+    
+    /// Check the tracks `sent` to the receiver against its capabilities
+    /// 
+    /// Tracks it would resample, remix or drop get their mismatches and an
+    /// `Incompatible` alert; tracks that fit again have them cleared.
+    pub fn apply_receiver_capabilities(
+        &self,
+        capabilities: ReceiverCapabilities,
+        sample_rate: u32,
+        sent: impl Fn(u8) -> bool,
+    ) {
+        let previous = self.receiver_capabilities.write().replace(capabilities.clone());
+        if previous.as_ref() != Some(&capabilities) {
+            tracing::info!("Receiver capabilities: {:?}", capabilities);
+        }
+        
+        let mut ids: Vec<u8> = self.tracks.iter().map(|t| *t.key()).filter(|&id| sent(id)).collect();
+        ids.sort_unstable();
+        for (index, track_id) in ids.into_iter().enumerate() {
+            let Some(mut track) = self.tracks.get_mut(&track_id) else {
+                continue;
+            };
+            let mismatches = capabilities.check(&track.config, index, sample_rate);
+            if mismatches == track.receiver_mismatches() {
+                continue;
+            }
+            for mismatch in &mismatches {
+                tracing::warn!("Track {}: {}", track_id, mismatch);
+            }
+            let incompatible = !mismatches.is_empty();
+            track.set_receiver_mismatches(mismatches);
+            drop(track);
+            self.set_alert(track_id, AlertKind::Incompatible, incompatible);
+            let _ = self.event_tx.send(TrackEvent::ConfigUpdated(track_id));
+        }
+    }
+    
+    /// The receiver's capabilities and the tracks it can't play as sent
+    pub fn receiver_capabilities(&self) -> Option<CapabilityReport> {
+        let capabilities = self.receiver_capabilities.read().clone()?;
+        let tracks = self
+            .tracks
+            .iter()
+            .filter(|t| !t.receiver_mismatches().is_empty())
+            .map(|t| (*t.key(), t.receiver_mismatches().to_vec()))
+            .collect();
+        Some(CapabilityReport { capabilities, tracks })
+    }
+    
+    /// Create a new track, filling in settings from its profile
     pub fn create_track(&self, mut config: TrackConfig) -> Result<u8, TrackError> {
         if self.tracks.len() >= self.max_tracks {
             return Err(TrackError::MaxTracksReached(self.max_tracks));
         }
         
-        // Assign ID if not provided
-        let id = config.track_id.unwrap_or_else(|| {
-            self.next_id.fetch_add(1, Ordering::SeqCst)
-        });
+        if let Some(ref name) = config.profile {
+            let profile = self.profiles.read().get(name).cloned()
+                .ok_or_else(|| TrackError::ProfileNotFound(name.clone()))?;
+            config = config.with_profile(&profile);
+        }
+        
+        // One channel of the device: always a mono track
+        if config.source_channel.is_some() {
+            config.channels = 1;
+        }
+        
+        // Assign ID if not provided: the next free one, wrapping below the limit.
+        // Explicit IDs may be anywhere (remote tracks at 128+, the relay's mix bus)
+        let id = match config.track_id {
+            Some(id) => id,
+            None => {
+                let start = self.next_id.load(Ordering::SeqCst) as usize;
+                let id = (0..self.max_tracks)
+                    .map(|i| ((start + i) % self.max_tracks) as u8)
+                    .find(|id| !self.tracks.contains_key(id))
+                    .ok_or(TrackError::MaxTracksReached(self.max_tracks))?;
+                self.next_id.store(((id as usize + 1) % self.max_tracks) as u8, Ordering::SeqCst);
+                id
+            }
+        };
         
         // Check if ID already exists
         if self.tracks.contains_key(&id) {
@@ -79,9 +426,15 @@ impl TrackManager {
         
         config.track_id = Some(id);
         let track = Track::new(id, config);
+        self.meter_bus.register(id, track.meters());
         
+        let grouped = track.config.group.is_some();
         self.tracks.insert(id, track);
         let _ = self.event_tx.send(TrackEvent::Created(id));
+        if grouped {
+            self.update_solo_state();
+        }
+        self.rebalance_bitrates();
         
         Ok(id)
     }
@@ -94,11 +447,19 @@ impl TrackManager {
         
         // Stop track if running
         track.stop();
+        self.meter_bus.unregister(track_id);
+        self.active_alerts.retain(|(id, _)| *id != track_id);
+        self.session.track_removed(track_id);
+        self.stats.remove(track_id);
+        self.counters.reset(track_id);
+        self.sources.lock().remove(&track_id);
+        self.sinks.lock().remove(&track_id);
         
         let _ = self.event_tx.send(TrackEvent::Removed(track_id));
         
         // Update solo state
         self.update_solo_state();
+        self.rebalance_bitrates();
         
         Ok(track)
     }
@@ -119,7 +480,17 @@ impl TrackManager {
             .get_mut(&track_id)
             .ok_or(TrackError::NotFound(track_id))?;
         
-        track.start()?;
+        let transitions = track.start()?;
+        if transitions.is_empty() {
+            return Ok(());
+        }
+        // Alerts raised while stopped still hold
+        let degraded = self.active_alerts.iter().any(|alert| alert.0 == track_id);
+        let transitions = transitions.into_iter().chain(track.set_degraded(degraded));
+        drop(track);
+        
+        self.emit_transitions(transitions);
+        self.session.track_started(track_id);
         let _ = self.event_tx.send(TrackEvent::Started(track_id));
         
         Ok(())
@@ -131,27 +502,49 @@ impl TrackManager {
             .get_mut(&track_id)
             .ok_or(TrackError::NotFound(track_id))?;
         
-        track.stop();
+        let transitions = track.stop();
+        drop(track);
+        if transitions.is_empty() {
+            return Ok(());
+        }
+        
+        self.emit_transitions(transitions);
+        self.session.track_stopped(track_id);
         let _ = self.event_tx.send(TrackEvent::Stopped(track_id));
         
         Ok(())
     }
     
+    /// Current lifecycle state of a track
+    pub fn track_state(&self, track_id: u8) -> Option<TrackState> {
+        self.tracks.get(&track_id).map(|track| track.state())
+    }
+    
+    /// Log and announce state changes
+    fn emit_transitions(&self, transitions: impl IntoIterator<Item = TrackTransition>) {
+        for transition in transitions {
+            tracing::debug!("Track {}: {} -> {}", transition.track_id, transition.from, transition.to);
+            let _ = self.event_tx.send(TrackEvent::StateChanged(transition));
+        }
+    }
+    
     /// Start all tracks
     pub fn start_all(&self) -> Vec<Result<(), TrackError>> {
-        self.tracks
-            .iter()
-            .map(|entry| {
-                let id = *entry.key();
-                self.start_track(id)
-            })
-            .collect()
+        // IDs first: `start_track` locks the entry, which an iterator holds
+        let mut ids = self.track_ids();
+        ids.sort_unstable();
+        ids.into_iter().map(|id| self.start_track(id)).collect()
     }
     
     /// Stop all tracks
     pub fn stop_all(&self) {
         for mut entry in self.tracks.iter_mut() {
-            entry.stop();
+            let transitions = entry.stop();
+            if transitions.is_empty() {
+                continue;
+            }
+            self.emit_transitions(transitions);
+            self.session.track_stopped(*entry.key());
             let _ = self.event_tx.send(TrackEvent::Stopped(*entry.key()));
         }
     }
@@ -163,8 +556,16 @@ impl TrackManager {
             .ok_or(TrackError::NotFound(track_id))?;
         
         track.update_config(&update)?;
+        drop(track);
         let _ = self.event_tx.send(TrackEvent::ConfigUpdated(track_id));
         
+        if update.bitrate.is_some() || update.bitrate_weight.is_some() {
+            self.rebalance_bitrates();
+        }
+        if update.group.is_some() {
+            self.update_solo_state();
+        }
+        
         Ok(())
     }
     
@@ -175,6 +576,8 @@ impl TrackManager {
             .ok_or(TrackError::NotFound(track_id))?;
         
         track.set_muted(muted);
+        let _ = self.event_tx.send(TrackEvent::ConfigUpdated(track_id));
+        
         Ok(())
     }
     
@@ -185,29 +588,287 @@ impl TrackManager {
             .ok_or(TrackError::NotFound(track_id))?;
         
         track.set_solo(solo);
+        drop(track);
         self.update_solo_state();
+        let _ = self.event_tx.send(TrackEvent::ConfigUpdated(track_id));
         
         Ok(())
     }
     
+    /// Set track paused state
+    pub fn set_paused(&self, track_id: u8, paused: bool) -> Result<(), TrackError> {
+        let track = self.tracks
+            .get(&track_id)
+            .ok_or(TrackError::NotFound(track_id))?;
+        
+        track.set_paused(paused);
+        drop(track);
+        self.send_pause_event(track_id, paused);
+        self.rebalance_bitrates();
+        
+        Ok(())
+    }
+    
+    /// Pause or resume all tracks
+    pub fn set_all_paused(&self, paused: bool) {
+        self.all_paused.store(paused, Ordering::Relaxed);
+        
+        for entry in self.tracks.iter() {
+            if !entry.is_paused() {
+                self.send_pause_event(*entry.key(), paused);
+            }
+        }
+        self.rebalance_bitrates();
+    }
+    
+    /// Check if track is paused (individually or globally)
+    pub fn is_paused(&self, track_id: u8) -> bool {
+        if self.all_paused.load(Ordering::Relaxed) {
+            return true;
+        }
+        
+        self.tracks
+            .get(&track_id)
+            .map(|track| track.is_paused())
+            .unwrap_or(false)
+    }
+    
+    /// Check if global pause is active
+    pub fn is_all_paused(&self) -> bool {
+        self.all_paused.load(Ordering::Relaxed)
+    }
+    
+    /// Mark talkback as set up (its microphone is open)
+    pub fn set_talkback_available(&self, available: bool) {
+        self.talkback_available.store(available, Ordering::Relaxed);
+        if !available {
+            self.talkback_active.store(false, Ordering::Relaxed);
+        }
+    }
+    
+    /// Whether talkback is set up
+    pub fn is_talkback_available(&self) -> bool {
+        self.talkback_available.load(Ordering::Relaxed)
+    }
+    
+    /// Press or release push-to-talk
+    pub fn set_talkback(&self, active: bool) -> Result<(), TrackError> {
+        if !self.is_talkback_available() {
+            return Err(TrackError::TalkbackDisabled);
+        }
+        self.talkback_active.store(active, Ordering::Relaxed);
+        Ok(())
+    }
+    
+    /// Whether push-to-talk is held
+    pub fn is_talkback_active(&self) -> bool {
+        self.talkback_active.load(Ordering::Relaxed)
+    }
+    
+    fn send_pause_event(&self, track_id: u8, paused: bool) {
+        let event = if paused {
+            TrackEvent::Paused(track_id)
+        } else {
+            TrackEvent::Resumed(track_id)
+        };
+        let _ = self.event_tx.send(event);
+    }
+    
+    /// Set the total bitrate shared by all tracks (0 = unlimited)
+    pub fn set_bitrate_budget(&self, bitrate: u32) {
+        self.bitrate_budget.store(bitrate, Ordering::Relaxed);
+        self.rebalance_bitrates();
+    }
+    
+    /// Get the total bitrate budget (0 = unlimited)
+    pub fn bitrate_budget(&self) -> u32 {
+        self.bitrate_budget.load(Ordering::Relaxed)
+    }
+    
+    /// Redistribute the bitrate budget over unpaused tracks by weight
+    /// 
+    /// A track never gets more than its configured bitrate; what it cannot
+    /// use goes to the others. Tracks whose allocation changes get a
+    /// `ConfigUpdated` event so encoders can follow.
+    pub fn rebalance_bitrates(&self) {
+        let budget = self.bitrate_budget();
+        let all_paused = self.is_all_paused();
+        
+        let candidates: Vec<(u8, f32, u32)> = self.tracks
+            .iter()
+            .filter(|t| budget > 0 && !all_paused && !t.is_paused())
+            .map(|t| (*t.key(), t.config.bitrate_weight, t.config.bitrate))
+            .collect();
+        let allocations = allocate_bitrates(budget, &candidates);
+        
+        for entry in self.tracks.iter() {
+            let id = *entry.key();
+            let allocated = allocations
+                .iter()
+                .find(|(track_id, _)| *track_id == id)
+                .map(|(_, bitrate)| *bitrate)
+                .unwrap_or(0);
+            
+            let before = entry.effective_bitrate();
+            entry.set_allocated_bitrate(allocated);
+            if entry.effective_bitrate() != before {
+                let _ = self.event_tx.send(TrackEvent::ConfigUpdated(id));
+            }
+        }
+    }
+    
+    /// Enable or disable noise suppression on a track
+    /// 
+    /// The denoiser always runs first in the chain.
+    pub fn set_denoise(&self, track_id: u8, enabled: bool) -> Result<(), TrackError> {
+        let mut processors = self.tracks
+            .get(&track_id)
+            .ok_or(TrackError::NotFound(track_id))?
+            .config
+            .processors
+            .clone();
+        
+        processors.retain(|p| *p != ProcessorConfig::Denoise);
+        if enabled {
+            processors.insert(0, ProcessorConfig::Denoise);
+        }
+        
+        self.update_track(track_id, TrackConfigUpdate {
+            processors: Some(processors),
+            ..Default::default()
+        })
+    }
+    
+    /// Report a gate state change from the audio path
+    pub fn notify_gate(&self, track_id: u8, open: bool) {
+        let _ = self.event_tx.send(TrackEvent::GateChanged(track_id, open));
+    }
+    
+    /// Report a signal alert being raised or cleared
+    pub fn notify_alert(&self, track_id: u8, kind: AlertKind, active: bool) {
+        if active {
+            tracing::warn!("Track {}: {}", track_id, kind.description());
+            self.active_alerts.insert((track_id, kind));
+        } else {
+            tracing::info!("Track {}: {} cleared", track_id, kind.description());
+            self.active_alerts.remove(&(track_id, kind));
+        }
+        let _ = self.event_tx.send(TrackEvent::Alert(track_id, kind, active));
+        
+        // A running track is degraded while any of its alerts is raised
+        let degraded = self.active_alerts.iter().any(|alert| alert.0 == track_id);
+        let transition = self.tracks.get_mut(&track_id).and_then(|mut track| track.set_degraded(degraded));
+        self.emit_transitions(transition);
+    }
+    
+    /// Report a pipeline error on a track to event subscribers; the track
+    /// stays in the error state until [`report_ok`](Self::report_ok) or it
+    /// is started or stopped again
+    ///
+    /// Errors of one track are logged and sent to subscribers at most once
+    /// per [`ERROR_LOG_INTERVAL`](crate::tracks::track::ERROR_LOG_INTERVAL), so a worker failing every frame can't
+    /// flood the log.
+    pub fn report_error(&self, track_id: u8, message: impl Into<String>) {
+        let message = message.into();
+        let (transition, due) = match self.tracks.get_mut(&track_id) {
+            Some(mut track) => (track.set_error(message.clone()), track.error_log_due()),
+            None => (None, Some(0)),
+        };
+        self.emit_transitions(transition);
+        let Some(suppressed) = due else {
+            return;
+        };
+        if suppressed > 0 {
+            tracing::error!("Track {}: {} ({} more errors)", track_id, message, suppressed);
+        } else {
+            tracing::error!("Track {}: {}", track_id, message);
+        }
+        let _ = self.event_tx.send(TrackEvent::Error(track_id, message));
+    }
+    
+    /// Report a frame that went through: a track in the error state runs again
+    pub fn report_ok(&self, track_id: u8) {
+        if self.tracks.get(&track_id).is_none_or(|track| track.state() != TrackState::Error) {
+            return;
+        }
+        let transition = self.tracks.get_mut(&track_id).and_then(|mut track| track.recover());
+        if let Some(transition) = transition {
+            tracing::info!("Track {}: recovered", track_id);
+            self.emit_transitions([transition]);
+        }
+    }
+    
+    /// Report the start of a capture overflow or playback underrun
+    pub fn notify_xrun(&self, xrun: XrunEvent) {
+        tracing::debug!(
+            "Track {}: {:?} with {}/{} frames buffered ({} frame callback)",
+            xrun.track_id,
+            xrun.kind,
+            xrun.buffer_fill,
+            xrun.buffer_capacity,
+            xrun.callback_frames
+        );
+        let _ = self.event_tx.send(TrackEvent::Xrun(xrun));
+    }
+    
+    /// Report a track moving to a fallback device, or back to its own
+    pub fn notify_device_migrated(&self, track_id: u8, device_id: &str, fallback: bool) {
+        if fallback {
+            tracing::warn!("Track {}: device lost, moved to {}", track_id, device_id);
+        } else {
+            tracing::info!("Track {}: back on {}", track_id, device_id);
+        }
+        let _ = self.event_tx.send(TrackEvent::DeviceMigrated(track_id, device_id.to_string(), fallback));
+    }
+    
+    /// Raise or clear an alert only if its state changes
+    ///
+    /// Returns true when an event was sent.
+    pub fn set_alert(&self, track_id: u8, kind: AlertKind, active: bool) -> bool {
+        if self.active_alerts.contains(&(track_id, kind)) == active {
+            return false;
+        }
+        self.notify_alert(track_id, kind, active);
+        true
+    }
+    
+    /// Set track playout delay
+    pub fn set_playout_delay(&self, track_id: u8, delay_ms: u32) -> Result<(), TrackError> {
+        self.update_track(track_id, TrackConfigUpdate {
+            playout_delay_ms: Some(delay_ms),
+            ..Default::default()
+        })
+    }
+    
     /// Update global solo state
     fn update_solo_state(&self) {
         let any_solo = self.tracks
             .iter()
-            .any(|entry| entry.is_solo());
+            .any(|entry| entry.is_solo() || self.group_state(entry.config.group.as_deref()).solo);
         
         self.solo_active.store(any_solo, Ordering::Relaxed);
     }
     
+    /// Check if any track is soloed
+    pub fn is_solo_active(&self) -> bool {
+        self.solo_active.load(Ordering::Relaxed)
+    }
+    
     /// Check if track should output audio (considering solo/mute)
+    /// 
+    /// While any track is soloed only soloed tracks play; a soloed track
+    /// that is also muted stays silent. Clearing the last solo restores
+    /// each track's own mute state. A group's mute and solo count as the
+    /// mute and solo of each of its tracks.
     pub fn should_output(&self, track_id: u8) -> bool {
         if let Some(track) = self.tracks.get(&track_id) {
-            if track.is_muted() {
+            let group = self.group_state(track.config.group.as_deref());
+            if track.is_muted() || group.muted {
                 return false;
             }
             
             if self.solo_active.load(Ordering::Relaxed) {
-                return track.is_solo();
+                return track.is_solo() || group.solo;
             }
             
             true
@@ -216,6 +877,92 @@ impl TrackManager {
         }
     }
     
+    /// All groups with tracks in them, by name
+    pub fn groups(&self) -> Vec<TrackGroup> {
+        let mut members: BTreeMap<String, Vec<u8>> = BTreeMap::new();
+        for entry in self.tracks.iter() {
+            if let Some(ref group) = entry.config.group {
+                members.entry(group.clone()).or_default().push(*entry.key());
+            }
+        }
+        members
+            .into_iter()
+            .map(|(name, mut tracks)| {
+                tracks.sort_unstable();
+                let state = self.group_state(Some(&name));
+                TrackGroup { name, tracks, muted: state.muted, solo: state.solo, gain_db: state.gain_db }
+            })
+            .collect()
+    }
+    
+    /// Tracks of a group, ordered by ID
+    pub fn group_tracks(&self, name: &str) -> Result<Vec<u8>, TrackError> {
+        let mut tracks: Vec<u8> = self.tracks
+            .iter()
+            .filter(|entry| entry.config.group.as_deref() == Some(name))
+            .map(|entry| *entry.key())
+            .collect();
+        if tracks.is_empty() {
+            return Err(TrackError::GroupNotFound(name.to_string()));
+        }
+        tracks.sort_unstable();
+        Ok(tracks)
+    }
+    
+    /// Mute or unmute a whole group
+    pub fn set_group_muted(&self, name: &str, muted: bool) -> Result<(), TrackError> {
+        self.update_group(name, |state| state.muted = muted)
+    }
+    
+    /// Solo a whole group
+    pub fn set_group_solo(&self, name: &str, solo: bool) -> Result<(), TrackError> {
+        self.update_group(name, |state| state.solo = solo)?;
+        self.update_solo_state();
+        Ok(())
+    }
+    
+    /// Set a group's gain, added to the receiver trim of each of its tracks
+    pub fn set_group_gain(&self, name: &str, gain_db: f32) -> Result<(), TrackError> {
+        if !(-96.0..=12.0).contains(&gain_db) {
+            return Err(TrackError::InvalidConfig(format!(
+                "Group gain {} dB out of range (-96 to +12)",
+                gain_db
+            )));
+        }
+        self.update_group(name, |state| state.gain_db = gain_db)
+    }
+    
+    /// Counters and rates of a group's tracks, summed
+    pub fn group_stats(&self, name: &str) -> Result<GroupStats, TrackError> {
+        let stats: Vec<TrackStats> = self.group_tracks(name)?
+            .into_iter()
+            .map(|track_id| self.stats.get(track_id))
+            .collect();
+        Ok(GroupStats::new(name, &stats))
+    }
+    
+    /// Decoder gain of a track: its own trim plus its group's gain
+    pub fn output_gain_db(&self, track_id: u8) -> f32 {
+        let Some(track) = self.tracks.get(&track_id) else {
+            return 0.0;
+        };
+        track.config.decoder_gain_db + self.group_state(track.config.group.as_deref()).gain_db
+    }
+    
+    fn group_state(&self, name: Option<&str>) -> GroupState {
+        name.and_then(|name| self.groups.read().get(name).copied()).unwrap_or_default()
+    }
+    
+    /// Change a group's state and tell subscribers about each of its tracks
+    fn update_group(&self, name: &str, change: impl FnOnce(&mut GroupState)) -> Result<(), TrackError> {
+        let tracks = self.group_tracks(name)?;
+        change(self.groups.write().entry(name.to_string()).or_default());
+        for track_id in tracks {
+            let _ = self.event_tx.send(TrackEvent::ConfigUpdated(track_id));
+        }
+        Ok(())
+    }
+    
     /// Get all track statuses
     pub fn get_all_statuses(&self) -> Vec<TrackStatus> {
         self.tracks
@@ -224,6 +971,29 @@ impl TrackManager {
             .collect()
     }
     
+    /// Get alerts currently raised, ordered by track
+    pub fn active_alerts(&self) -> Vec<ActiveAlert> {
+        let mut alerts: Vec<(u8, AlertKind)> = self.active_alerts.iter().map(|a| *a).collect();
+        alerts.sort();
+        alerts
+            .into_iter()
+            .map(|(track_id, kind)| ActiveAlert { track_id, kind })
+            .collect()
+    }
+    
+    /// Get every track's configuration and status, ordered by ID
+    pub fn snapshot_tracks(&self) -> Vec<TrackSnapshot> {
+        let mut tracks: Vec<TrackSnapshot> = self.tracks
+            .iter()
+            .map(|entry| TrackSnapshot {
+                config: entry.config.clone(),
+                status: entry.status(),
+            })
+            .collect();
+        tracks.sort_by_key(|t| t.status.track_id);
+        tracks
+    }
+    
     /// Get track count
     pub fn track_count(&self) -> usize {
         self.tracks.len()
@@ -261,11 +1031,98 @@ impl Default for TrackManager {
     }
 }
 
+/// Split a bitrate budget over (track, weight, cap) by weight
+/// 
+/// Tracks whose share exceeds their cap are capped and the surplus is
+/// shared among the rest.
+fn allocate_bitrates(budget: u32, tracks: &[(u8, f32, u32)]) -> Vec<(u8, u32)> {
+    let mut allocations = Vec::with_capacity(tracks.len());
+    let mut remaining: Vec<(u8, f32, u32)> = tracks.to_vec();
+    let mut left = budget as f64;
+    
+    loop {
+        let total_weight: f64 = remaining.iter().map(|(_, w, _)| *w as f64).sum();
+        if remaining.is_empty() || total_weight <= 0.0 {
+            break;
+        }
+        
+        let capped: Vec<(u8, f32, u32)> = remaining
+            .iter()
+            .filter(|(_, w, cap)| left * *w as f64 / total_weight >= *cap as f64)
+            .copied()
+            .collect();
+        
+        if capped.is_empty() {
+            for (id, weight, _) in remaining.drain(..) {
+                let share = (left * weight as f64 / total_weight) as u32;
+                allocations.push((id, share.max(MIN_TRACK_BITRATE)));
+            }
+            break;
+        }
+        
+        for (id, _, cap) in capped {
+            left -= cap as f64;
+            allocations.push((id, cap));
+            remaining.retain(|(track_id, _, _)| *track_id != id);
+        }
+    }
+    
+    allocations
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::protocol::TrackType;
     
+    #[test]
+    fn test_bitrate_budget() {
+        // Voice capped at 64k, its surplus goes to the two music tracks by weight
+        let allocations = allocate_bitrates(
+            400_000,
+            &[(0, 1.0, 64_000), (1, 2.0, 510_000), (2, 1.0, 510_000)],
+        );
+        assert_eq!(allocations.len(), 3);
+        assert!(allocations.contains(&(0, 64_000)));
+        assert!(allocations.contains(&(1, 224_000)));
+        assert!(allocations.contains(&(2, 112_000)));
+        
+        let manager = TrackManager::new();
+        let id = manager.create_track(TrackConfig::default()).unwrap();
+        assert_eq!(manager.get_track(id).unwrap().effective_bitrate(), 128_000);
+        
+        manager.set_bitrate_budget(96_000);
+        assert_eq!(manager.get_track(id).unwrap().effective_bitrate(), 96_000);
+        
+        manager.set_bitrate_budget(0);
+        assert_eq!(manager.get_track(id).unwrap().effective_bitrate(), 128_000);
+    }
+    
+    #[test]
+    fn test_create_track_with_profile() {
+        let manager = TrackManager::new();
+        let profile = TrackProfile {
+            bitrate: Some(64_000),
+            channels: Some(1),
+            track_type: Some(TrackType::Voice),
+            ..Default::default()
+        };
+        manager.set_profiles([("Voice".to_string(), profile)].into_iter().collect());
+        
+        // Profile values fill in defaults; the track's own bitrate wins
+        let id = manager.create_track(TrackConfig {
+            bitrate: 96_000,
+            profile: Some("Voice".to_string()),
+            ..Default::default()
+        }).unwrap();
+        let track = manager.get_track(id).unwrap();
+        assert_eq!((track.config.bitrate, track.config.channels, track.config.track_type), (96_000, 1, TrackType::Voice));
+        drop(track);
+        
+        let unknown = TrackConfig { profile: Some("Desktop music".to_string()), ..Default::default() };
+        assert!(matches!(manager.create_track(unknown), Err(TrackError::ProfileNotFound(_))));
+    }
+    
     #[test]
     fn test_create_track() {
         let manager = TrackManager::new();
@@ -279,6 +1136,22 @@ mod tests {
             channels: 2,
             track_type: TrackType::Music,
             fec_enabled: false,
+            playout_delay_ms: 0,
+            mix_gain_db: 0.0,
+            mix_pan: 0.0,
+            priority: None,
+            bitrate_weight: 1.0,
+            decoder_gain_db: 0.0,
+            soft_clip: false,
+            processors: Vec::new(),
+            profile: None,
+            color: None,
+            source_channel: None,
+            auto_start: false,
+            fallback_device: None,
+            simulcast_bitrates: Vec::new(),
+            group: None,
+            explicit: None,
         };
         
         let id = manager.create_track(config).unwrap();
@@ -297,6 +1170,127 @@ mod tests {
         assert_eq!(manager.track_count(), 0);
     }
     
+    #[test]
+    fn test_max_tracks() {
+        let manager = TrackManager::with_max_tracks(3);
+        assert_eq!(manager.max_tracks(), 3);
+        assert_eq!(TrackManager::with_max_tracks(1000).max_tracks(), MAX_TRACKS);
+        
+        let ids: Vec<u8> = (0..3).map(|_| manager.create_track(TrackConfig::default()).unwrap()).collect();
+        assert_eq!(ids, vec![0, 1, 2]);
+        assert!(matches!(manager.create_track(TrackConfig::default()), Err(TrackError::MaxTracksReached(3))));
+        
+        // A freed ID is handed out again once the others are taken
+        manager.remove_track(1).unwrap();
+        assert_eq!(manager.create_track(TrackConfig::default()).unwrap(), 1);
+        
+        // The limit counts tracks; reserved IDs above it still work
+        let manager = TrackManager::new();
+        for id in [128, 200] {
+            let config = TrackConfig { track_id: Some(id), ..Default::default() };
+            assert_eq!(manager.create_track(config).unwrap(), id);
+            manager.start_track(id).unwrap();
+        }
+        assert_eq!(manager.create_track(TrackConfig::default()).unwrap(), 0);
+    }
+    
+    #[test]
+    fn test_lifecycle() {
+        use TrackState::*;
+        
+        let manager = TrackManager::new();
+        let id = manager.create_track(TrackConfig::default()).unwrap();
+        let mut events = manager.subscribe();
+        let mut steps = || {
+            std::iter::from_fn(|| events.try_recv().ok())
+                .filter_map(|event| match event {
+                    TrackEvent::StateChanged(t) => Some((t.from, t.to)),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(manager.track_state(id), Some(Created));
+        
+        manager.start_track(id).unwrap();
+        manager.start_track(id).unwrap();
+        assert_eq!(steps(), vec![(Created, Starting), (Starting, Running)]);
+        
+        // Alerts degrade a running track until the last one clears
+        manager.notify_alert(id, AlertKind::Clipping, true);
+        manager.notify_alert(id, AlertKind::Silence, true);
+        manager.notify_alert(id, AlertKind::Clipping, false);
+        assert_eq!(manager.track_state(id), Some(Degraded));
+        manager.notify_alert(id, AlertKind::Silence, false);
+        assert_eq!(steps(), vec![(Running, Degraded), (Degraded, Running)]);
+        
+        // A transient error clears on the next good frame
+        let mut errors = manager.subscribe();
+        manager.report_error(id, "send failed");
+        manager.report_error(id, "send failed");
+        manager.report_ok(id);
+        manager.report_ok(id);
+        assert_eq!(steps(), vec![(Running, Error), (Error, Running)]);
+        let errors = std::iter::from_fn(|| errors.try_recv().ok())
+            .filter(|event| matches!(event, TrackEvent::Error(..)))
+            .count();
+        
+        manager.report_error(id, "device vanished");
+        assert_eq!(manager.get_track(id).unwrap().last_error(), Some("device vanished"));
+        manager.stop_track(id).unwrap();
+        manager.stop_track(id).unwrap();
+        assert_eq!(steps(), vec![(Running, Error), (Error, Stopping), (Stopping, Stopped)]);
+        // Only the first of a burst reaches subscribers
+        assert_eq!(errors, 1);
+        
+        // Only allowed transitions go through
+        let mut track = manager.get_track_mut(id).unwrap();
+        assert!(matches!(
+            track.transition(Running),
+            Err(TrackError::InvalidTransition { from: Stopped, to: Running, .. })
+        ));
+        assert!(track.set_degraded(true).is_none());
+        assert_eq!(track.state(), Stopped);
+    }
+    
+    #[test]
+    fn test_start_stop_all() {
+        let manager = TrackManager::new();
+        let a = manager.create_track(TrackConfig::default()).unwrap();
+        let b = manager.create_track(TrackConfig::default()).unwrap();
+        
+        let results = manager.start_all();
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(Result::is_ok));
+        assert_eq!(manager.track_state(a), Some(TrackState::Running));
+        assert_eq!(manager.track_state(b), Some(TrackState::Running));
+        
+        manager.stop_all();
+        assert_eq!(manager.track_state(a), Some(TrackState::Stopped));
+        assert_eq!(manager.track_state(b), Some(TrackState::Stopped));
+    }
+    
+    #[test]
+    fn test_snapshot_and_alerts() {
+        let manager = TrackManager::new();
+        let a = manager.create_track(TrackConfig::default()).unwrap();
+        let b = manager.create_track(TrackConfig::default()).unwrap();
+        
+        manager.notify_alert(b, AlertKind::Silence, true);
+        manager.notify_alert(a, AlertKind::Clipping, true);
+        manager.notify_alert(a, AlertKind::Clipping, false);
+        assert_eq!(manager.active_alerts(), vec![ActiveAlert { track_id: b, kind: AlertKind::Silence }]);
+        assert!(!manager.set_alert(b, AlertKind::Silence, true));
+        assert!(manager.set_alert(a, AlertKind::PacketLoss, true));
+        assert!(manager.set_alert(a, AlertKind::PacketLoss, false));
+        
+        let tracks = manager.snapshot_tracks();
+        assert_eq!(tracks.iter().map(|t| t.status.track_id).collect::<Vec<_>>(), vec![a, b]);
+        
+        // Removing a track drops its alerts
+        manager.remove_track(b).unwrap();
+        assert!(manager.active_alerts().is_empty());
+    }
+    
     #[test]
     fn test_mute_solo() {
         let manager = TrackManager::new();
@@ -318,4 +1312,112 @@ mod tests {
         assert!(manager.should_output(id1));
         assert!(!manager.should_output(id2));
     }
+    
+    #[test]
+    fn test_multiple_solos_and_restore() {
+        let manager = TrackManager::new();
+        
+        let id1 = manager.create_track(TrackConfig::default()).unwrap();
+        let id2 = manager.create_track(TrackConfig::default()).unwrap();
+        let id3 = manager.create_track(TrackConfig::default()).unwrap();
+        manager.set_muted(id3, true).unwrap();
+        
+        manager.set_solo(id1, true).unwrap();
+        manager.set_solo(id2, true).unwrap();
+        assert!(manager.should_output(id1));
+        assert!(manager.should_output(id2));
+        assert!(!manager.should_output(id3));
+        
+        // Still soloed by track 2
+        manager.set_solo(id1, false).unwrap();
+        assert!(!manager.should_output(id1));
+        assert!(manager.is_solo_active());
+        
+        // Removing the last soloed track restores normal output
+        manager.remove_track(id2).unwrap();
+        assert!(!manager.is_solo_active());
+        assert!(manager.should_output(id1));
+        assert!(!manager.should_output(id3));
+    }
+    
+    #[test]
+    fn test_groups() {
+        let manager = TrackManager::new();
+        let guest = |name: &str| TrackConfig { group: Some(name.to_string()), ..Default::default() };
+        let a = manager.create_track(guest("Guests")).unwrap();
+        let b = manager.create_track(guest("Guests")).unwrap();
+        let game = manager.create_track(guest("Game")).unwrap();
+        let other = manager.create_track(TrackConfig::default()).unwrap();
+        
+        let groups = manager.groups();
+        assert_eq!(groups.iter().map(|g| g.name.as_str()).collect::<Vec<_>>(), vec!["Game", "Guests"]);
+        assert_eq!(groups[1].tracks, vec![a, b]);
+        assert!(matches!(manager.set_group_muted("Nobody", true), Err(TrackError::GroupNotFound(_))));
+        
+        // Group mute silences its tracks on top of their own state
+        manager.set_group_muted("Guests", true).unwrap();
+        assert!(!manager.should_output(a) && !manager.should_output(b));
+        assert!(manager.should_output(game));
+        manager.set_group_muted("Guests", false).unwrap();
+        
+        // Group solo counts as solo on each of its tracks
+        manager.set_group_solo("Game", true).unwrap();
+        assert!(manager.is_solo_active());
+        assert!(manager.should_output(game));
+        assert!(!manager.should_output(a) && !manager.should_output(other));
+        manager.set_solo(a, true).unwrap();
+        assert!(manager.should_output(a) && !manager.should_output(b));
+        manager.set_solo(a, false).unwrap();
+        
+        // Leaving the only soloed group ends solo mode
+        manager.update_track(game, TrackConfigUpdate { group: Some(String::new()), ..Default::default() }).unwrap();
+        assert!(!manager.is_solo_active());
+        assert!(manager.group_tracks("Game").is_err());
+        
+        // Group gain adds to each track's trim
+        manager.update_track(b, TrackConfigUpdate { decoder_gain_db: Some(3.0), ..Default::default() }).unwrap();
+        manager.set_group_gain("Guests", -6.0).unwrap();
+        assert_eq!((manager.output_gain_db(a), manager.output_gain_db(b)), (-6.0, -3.0));
+        assert_eq!(manager.output_gain_db(other), 0.0);
+        assert!(manager.set_group_gain("Guests", 20.0).is_err());
+        
+        let stats = manager.group_stats("Guests").unwrap();
+        assert_eq!((stats.name.as_str(), stats.tracks.clone()), ("Guests", vec![a, b]));
+    }
+    
+    #[test]
+    fn test_pause() {
+        let manager = TrackManager::new();
+        
+        let id1 = manager.create_track(TrackConfig::default()).unwrap();
+        let id2 = manager.create_track(TrackConfig::default()).unwrap();
+        
+        manager.set_paused(id1, true).unwrap();
+        assert!(manager.is_paused(id1));
+        assert!(!manager.is_paused(id2));
+        
+        // Global pause covers every track, individual state is kept
+        manager.set_all_paused(true);
+        assert!(manager.is_paused(id2));
+        manager.set_all_paused(false);
+        assert!(manager.is_paused(id1));
+        assert!(!manager.is_paused(id2));
+    }
+    
+    #[test]
+    fn test_reset_requests() {
+        let manager = TrackManager::new();
+        let id = manager.create_track(TrackConfig::default()).unwrap();
+        assert!(manager.request_reset(id + 1, ResetSource::Local).is_err());
+        assert_eq!(manager.take_reset(id), None);
+        
+        // A local request is not downgraded by the peer's before it is carried out
+        manager.request_reset(id, ResetSource::Local).unwrap();
+        manager.request_reset(id, ResetSource::Peer).unwrap();
+        assert_eq!(manager.take_reset(id), Some(ResetSource::Local));
+        assert_eq!(manager.take_reset(id), None);
+        
+        manager.request_reset(id, ResetSource::Peer).unwrap();
+        assert_eq!(manager.take_reset(id), Some(ResetSource::Peer));
+    }
 }
@@ -1,7 +1,26 @@
 //! Track management module
 
+pub mod autostart;
+pub mod counters;
+pub mod cpu;
+pub mod health;
+pub mod latency;
+#[cfg(feature = "network")]
 pub mod manager;
+#[cfg(feature = "network")]
+pub mod scenes;
+pub mod session;
+pub mod stats;
 pub mod track;
 
-pub use manager::TrackManager;
-pub use track::{Track, TrackState};
+pub use counters::{Counter, CounterRegistry, Gauge};
+pub use cpu::{ThreadCpu, ThreadCpuMonitor};
+pub use health::HealthMonitor;
+pub use latency::{LatencyRecorder, Stage, StageLatency};
+#[cfg(feature = "network")]
+pub use manager::{ResetSource, TrackManager};
+#[cfg(feature = "network")]
+pub use scenes::Scenes;
+pub use session::SessionCollector;
+pub use stats::{GroupStats, TrackCounters, TrackStats};
+pub use track::{Track, TrackState, TrackTransition};
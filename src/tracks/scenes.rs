@@ -0,0 +1,266 @@
+//! Scenes: named snapshots of the mixer
+//!
+//! A scene holds each track's mute, solo, pause, gains, pan, bitrate and
+//! device. Recalling one applies every track in one go. With a crossfade,
+//! gains and pan glide to the scene's values; tracks being unmuted or resumed
+//! come in at the start and tracks being muted or paused drop out at the end,
+//! so nothing is cut mid-fade. Scenes are kept in `scenes.path` when set.
+
+use parking_lot::RwLock;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::config::SceneConfig;
+use crate::error::TrackError;
+use crate::protocol::{Scene, SceneTrack, TrackConfigUpdate};
+use crate::tracks::TrackManager;
+
+/// Crossfade step length in milliseconds
+const STEP_MS: u32 = 20;
+
+/// Stored scenes of one track manager
+pub struct Scenes {
+    track_manager: Arc<TrackManager>,
+    path: Option<PathBuf>,
+    /// Crossfade when a recall does not give one
+    crossfade_ms: u32,
+    scenes: RwLock<BTreeMap<String, Scene>>,
+    /// Bumped by every recall so an older crossfade stops
+    generation: Arc<AtomicU64>,
+}
+
+impl Scenes {
+    /// Load the scenes saved at `config.path`, if any
+    pub fn new(track_manager: Arc<TrackManager>, config: &SceneConfig) -> Self {
+        let scenes = config
+            .path
+            .as_ref()
+            .filter(|path| path.exists())
+            .and_then(|path| match std::fs::read_to_string(path) {
+                Ok(text) => serde_json::from_str::<Vec<Scene>>(&text)
+                    .map_err(|e| tracing::warn!("Ignoring scenes in {}: {}", path.display(), e))
+                    .ok(),
+                Err(e) => {
+                    tracing::warn!("Failed to read scenes from {}: {}", path.display(), e);
+                    None
+                }
+            })
+            .unwrap_or_default();
+        Self {
+            track_manager,
+            path: config.path.clone(),
+            crossfade_ms: config.crossfade_ms,
+            scenes: RwLock::new(scenes.into_iter().map(|s| (s.name.clone(), s)).collect()),
+            generation: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// All scenes, by name
+    pub fn list(&self) -> Vec<Scene> {
+        self.scenes.read().values().cloned().collect()
+    }
+
+    /// Store the current mixer state as `name`, replacing any scene of that name
+    pub fn store(&self, name: &str) -> Result<Scene, TrackError> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(TrackError::InvalidConfig("Scene name is empty".to_string()));
+        }
+        let scene = Scene { name: name.to_string(), tracks: self.capture() };
+        self.scenes.write().insert(scene.name.clone(), scene.clone());
+        self.save()?;
+        Ok(scene)
+    }
+
+    /// Delete a scene
+    pub fn delete(&self, name: &str) -> Result<(), TrackError> {
+        self.scenes
+            .write()
+            .remove(name)
+            .ok_or_else(|| TrackError::SceneNotFound(name.to_string()))?;
+        self.save()
+    }
+
+    /// Apply a scene, fading gains and pan over `crossfade_ms` (None = configured)
+    ///
+    /// Tracks in the scene that no longer exist are skipped. The crossfade
+    /// runs on the tokio runtime; without one the scene applies at once.
+    pub fn recall(&self, name: &str, crossfade_ms: Option<u32>) -> Result<Scene, TrackError> {
+        let crossfade_ms = crossfade_ms.unwrap_or(self.crossfade_ms);
+        let scene = self
+            .scenes
+            .read()
+            .get(name)
+            .cloned()
+            .ok_or_else(|| TrackError::SceneNotFound(name.to_string()))?;
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        tracing::info!("Recalling scene {} ({} ms crossfade)", scene.name, crossfade_ms);
+
+        let runtime = tokio::runtime::Handle::try_current().ok().filter(|_| crossfade_ms >= STEP_MS);
+        let Some(runtime) = runtime else {
+            for target in &scene.tracks {
+                apply(&self.track_manager, target, None, Edge::Start);
+                apply(&self.track_manager, target, None, Edge::End);
+            }
+            return Ok(scene);
+        };
+
+        // Starting points of the glide; tracks gone since are dropped here
+        let fades: Vec<(SceneTrack, SceneTrack)> = scene
+            .tracks
+            .iter()
+            .filter_map(|target| {
+                let current = self.track(target.track_id)?;
+                apply(&self.track_manager, target, Some(&current), Edge::Start);
+                Some((current, target.clone()))
+            })
+            .collect();
+        let track_manager = self.track_manager.clone();
+        let current_generation = self.generation.clone();
+        runtime.spawn(async move {
+            let steps = crossfade_ms / STEP_MS;
+            let mut interval = tokio::time::interval(Duration::from_millis(STEP_MS as u64));
+            for step in 1..=steps {
+                interval.tick().await;
+                if current_generation.load(Ordering::SeqCst) != generation {
+                    return;
+                }
+                let t = step as f32 / steps as f32;
+                for (from, to) in &fades {
+                    let _ = track_manager.update_track(to.track_id, TrackConfigUpdate {
+                        decoder_gain_db: Some(lerp(from.decoder_gain_db, to.decoder_gain_db, t)),
+                        mix_gain_db: Some(lerp(from.mix_gain_db, to.mix_gain_db, t)),
+                        mix_pan: Some(lerp(from.mix_pan, to.mix_pan, t)),
+                        ..Default::default()
+                    });
+                }
+            }
+            for (_, to) in &fades {
+                apply(&track_manager, to, None, Edge::End);
+            }
+        });
+        Ok(scene)
+    }
+
+    /// Current state of every track
+    fn capture(&self) -> Vec<SceneTrack> {
+        self.track_manager
+            .track_ids()
+            .into_iter()
+            .filter_map(|id| self.track(id))
+            .collect()
+    }
+
+    /// Current state of one track
+    fn track(&self, track_id: u8) -> Option<SceneTrack> {
+        let track = self.track_manager.get_track(track_id)?;
+        Some(SceneTrack {
+            track_id,
+            muted: track.is_muted(),
+            solo: track.is_solo(),
+            paused: track.is_paused(),
+            decoder_gain_db: track.config.decoder_gain_db,
+            mix_gain_db: track.config.mix_gain_db,
+            mix_pan: track.config.mix_pan,
+            bitrate: track.config.bitrate,
+            device_id: track.config.device_id.clone(),
+        })
+    }
+
+    /// Write the scenes to `path`, if set
+    fn save(&self) -> Result<(), TrackError> {
+        let Some(ref path) = self.path else {
+            return Ok(());
+        };
+        let json = serde_json::to_string_pretty(&self.list()).map_err(|e| TrackError::SceneSave(e.to_string()))?;
+        std::fs::write(path, json).map_err(|e| TrackError::SceneSave(format!("{}: {}", path.display(), e)))
+    }
+}
+
+/// Which part of a recall to apply
+#[derive(Clone, Copy, PartialEq)]
+enum Edge {
+    /// Device, bitrate and anything switching on; gains too without a fade
+    Start,
+    /// Anything switching off
+    End,
+}
+
+/// Apply one edge of a scene track; `fading` is the state a crossfade starts from
+fn apply(track_manager: &TrackManager, target: &SceneTrack, fading: Option<&SceneTrack>, edge: Edge) {
+    let id = target.track_id;
+    let state = track_manager.get_track(id).map(|t| (t.is_muted(), t.is_solo(), t.is_paused()));
+    let Some((muted, solo, paused)) = state else {
+        tracing::debug!("Scene track {} no longer exists", id);
+        return;
+    };
+    if edge == Edge::Start {
+        let gains = fading.is_none();
+        let _ = track_manager.update_track(id, TrackConfigUpdate {
+            device_id: Some(target.device_id.clone()),
+            bitrate: Some(target.bitrate),
+            decoder_gain_db: gains.then_some(target.decoder_gain_db),
+            mix_gain_db: gains.then_some(target.mix_gain_db),
+            mix_pan: gains.then_some(target.mix_pan),
+            ..Default::default()
+        });
+    }
+
+    // Unmute, solo and resume at the start; mute, unsolo and pause at the end
+    let due = |on: bool| (edge == Edge::Start) == on;
+    if muted != target.muted && due(!target.muted) {
+        let _ = track_manager.set_muted(id, target.muted);
+    }
+    if solo != target.solo && due(target.solo) {
+        let _ = track_manager.set_solo(id, target.solo);
+    }
+    if paused != target.paused && due(!target.paused) {
+        let _ = track_manager.set_paused(id, target.paused);
+    }
+}
+
+fn lerp(from: f32, to: f32, t: f32) -> f32 {
+    from + (to - from) * t
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::TrackConfig;
+
+    #[test]
+    fn test_store_recall_and_persist() {
+        let path = std::env::temp_dir().join(format!("las-scenes-{}.json", std::process::id()));
+        let config = SceneConfig { path: Some(path.clone()), crossfade_ms: 0 };
+        let manager = Arc::new(TrackManager::new());
+        let a = manager.create_track(TrackConfig::default()).unwrap();
+        let b = manager.create_track(TrackConfig::default()).unwrap();
+
+        let scenes = Scenes::new(manager.clone(), &config);
+        manager.set_muted(b, true).unwrap();
+        manager.update_track(a, TrackConfigUpdate { mix_gain_db: Some(-6.0), ..Default::default() }).unwrap();
+        scenes.store("Intro").unwrap();
+        assert!(scenes.store("  ").is_err());
+
+        // Change everything, then recall
+        manager.set_muted(b, false).unwrap();
+        manager.set_paused(a, true).unwrap();
+        manager.update_track(a, TrackConfigUpdate { mix_gain_db: Some(0.0), bitrate: Some(64_000), ..Default::default() }).unwrap();
+        scenes.recall("Intro", None).unwrap();
+        let track = manager.get_track(a).unwrap();
+        assert_eq!((track.config.mix_gain_db, track.config.bitrate, track.is_paused()), (-6.0, 128_000, false));
+        drop(track);
+        assert!(manager.get_track(b).unwrap().is_muted());
+        assert!(matches!(scenes.recall("BRB", Some(500)), Err(TrackError::SceneNotFound(_))));
+
+        // Saved scenes come back with a new store
+        let reloaded = Scenes::new(manager, &config);
+        assert_eq!(reloaded.list(), scenes.list());
+        reloaded.delete("Intro").unwrap();
+        assert!(Scenes::new(Arc::new(TrackManager::new()), &config).list().is_empty());
+        let _ = std::fs::remove_file(path);
+    }
+}
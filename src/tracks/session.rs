@@ -0,0 +1,147 @@
+//! Session statistics
+//!
+//! Uptime, per-track running time, byte counts, reconnects and the peak
+//! number of tracks running at once, collected over the life of the process.
+
+use parking_lot::Mutex;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::protocol::{SessionStats, TrackSessionStats};
+
+/// Running time and traffic of one track
+#[derive(Default)]
+struct TrackSession {
+    running_since: Option<Instant>,
+    active: Duration,
+    bytes: u64,
+}
+
+impl TrackSession {
+    fn active_at(&self, now: Instant) -> Duration {
+        self.active + self.running_since.map_or(Duration::ZERO, |since| now - since)
+    }
+}
+
+/// Collects session statistics
+pub struct SessionCollector {
+    started: Instant,
+    bytes_total: AtomicU64,
+    reconnects: AtomicU64,
+    peak_tracks: AtomicUsize,
+    tracks: Mutex<BTreeMap<u8, TrackSession>>,
+}
+
+impl SessionCollector {
+    /// Start collecting from now
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            bytes_total: AtomicU64::new(0),
+            reconnects: AtomicU64::new(0),
+            peak_tracks: AtomicUsize::new(0),
+            tracks: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Time since the process started
+    pub fn uptime(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// Record a track starting to run
+    pub fn track_started(&self, track_id: u8) {
+        let mut tracks = self.tracks.lock();
+        let track = tracks.entry(track_id).or_default();
+        if track.running_since.is_none() {
+            track.running_since = Some(Instant::now());
+        }
+
+        let running = tracks.values().filter(|t| t.running_since.is_some()).count();
+        self.peak_tracks.fetch_max(running, Ordering::Relaxed);
+    }
+
+    /// Record a track stopping
+    pub fn track_stopped(&self, track_id: u8) {
+        if let Some(track) = self.tracks.lock().get_mut(&track_id) {
+            if let Some(since) = track.running_since.take() {
+                track.active += since.elapsed();
+            }
+        }
+    }
+
+    /// Forget a removed track (its bytes stay in the total)
+    pub fn track_removed(&self, track_id: u8) {
+        self.tracks.lock().remove(&track_id);
+    }
+
+    /// Count audio payload bytes sent or received on a track
+    pub fn add_bytes(&self, track_id: u8, bytes: usize) {
+        self.bytes_total.fetch_add(bytes as u64, Ordering::Relaxed);
+        self.tracks.lock().entry(track_id).or_default().bytes += bytes as u64;
+    }
+
+    /// Count a stream coming back after a timeout or end of stream
+    pub fn record_reconnect(&self) {
+        self.reconnects.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Get current statistics
+    pub fn stats(&self) -> SessionStats {
+        let now = Instant::now();
+        let tracks = self.tracks
+            .lock()
+            .iter()
+            .map(|(&track_id, track)| TrackSessionStats {
+                track_id,
+                active_seconds: track.active_at(now).as_secs_f64(),
+                bytes: track.bytes,
+            })
+            .collect();
+
+        SessionStats {
+            uptime_seconds: now.duration_since(self.started).as_secs(),
+            bytes_total: self.bytes_total.load(Ordering::Relaxed),
+            reconnects: self.reconnects.load(Ordering::Relaxed),
+            peak_tracks: self.peak_tracks.load(Ordering::Relaxed),
+            tracks,
+        }
+    }
+}
+
+impl Default for SessionCollector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_stats() {
+        let session = SessionCollector::new();
+        session.track_started(1);
+        session.track_started(2);
+        session.track_stopped(2);
+        session.track_started(3);
+        session.add_bytes(1, 100);
+        session.add_bytes(3, 50);
+        session.record_reconnect();
+
+        // Tracks 1 and 2, then 1 and 3, were running together
+        let stats = session.stats();
+        assert_eq!(stats.peak_tracks, 2);
+        assert_eq!(stats.bytes_total, 150);
+        assert_eq!(stats.reconnects, 1);
+        assert_eq!(stats.tracks.iter().map(|t| t.track_id).collect::<Vec<_>>(), vec![1, 2, 3]);
+
+        session.track_removed(3);
+        let stats = session.stats();
+        assert_eq!(stats.bytes_total, 150);
+        assert_eq!(stats.tracks.len(), 2);
+        assert_eq!(stats.tracks[0].bytes, 100);
+    }
+}
@@ -0,0 +1,593 @@
+//! Per-track statistics
+//!
+//! Encoder, decoder, sender and jitter buffer counters are reported here as
+//! one [`TrackCounters`] snapshot per track; [`TrackStats`] adds rates over
+//! a sliding window so the API doesn't have to diff counters itself.
+//! Jitter and loss gap lengths are kept in log-linear histograms so the
+//! percentiles can guide jitter buffer sizing. The latest per-stage latency
+//! breakdown is attached as reported.
+//!
+//! Bandwidth is also given over the last report interval alone, with packet,
+//! UDP and IP headers counted, so it tracks what the link carries right now;
+//! [`BandwidthReport`] sums it over tracks.
+//!
+//! Jitter buffer fill is kept per report for [`TREND_WINDOW`], so
+//! [`OccupancyTrend`] can show whether a track's latency is creeping up
+//! or its buffer is starving.
+
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crate::audio::buffer::OccupancySummary;
+use crate::protocol::HEADER_SIZE;
+use crate::tracks::latency::StageLatency;
+
+/// Window rates are computed over
+pub const STATS_WINDOW: Duration = Duration::from_secs(10);
+
+/// Longest span an [`OccupancyTrend`] can cover
+pub const TREND_WINDOW: Duration = Duration::from_secs(300);
+
+/// Bytes each packet adds on the wire: packet header, UDP (8) and IPv4 (20)
+pub const PACKET_OVERHEAD: u64 = HEADER_SIZE as u64 + 8 + 20;
+
+/// Values below this are counted exactly
+const LINEAR_LIMIT: u64 = 32;
+
+/// Buckets per power of two above the linear range (~6% resolution)
+const SUB_BUCKETS: u64 = 16;
+
+/// Enough buckets for any u64
+const BUCKETS: usize = (LINEAR_LIMIT + (64 - 5) * SUB_BUCKETS) as usize;
+
+/// Log-linear histogram in the spirit of HdrHistogram
+///
+/// Exact below 32, then 16 buckets per power of two.
+#[derive(Clone)]
+pub struct Histogram {
+    counts: Vec<u64>,
+    total: u64,
+    max: u64,
+}
+
+impl Histogram {
+    pub fn new() -> Self {
+        Self { counts: vec![0; BUCKETS], total: 0, max: 0 }
+    }
+
+    fn index(value: u64) -> usize {
+        if value < LINEAR_LIMIT {
+            return value as usize;
+        }
+        let msb = 63 - value.leading_zeros() as u64;
+        let sub = (value >> (msb - 4)) & (SUB_BUCKETS - 1);
+        (LINEAR_LIMIT + (msb - 5) * SUB_BUCKETS + sub) as usize
+    }
+
+    /// Highest value that falls in a bucket
+    fn upper_bound(index: usize) -> u64 {
+        let index = index as u64;
+        if index < LINEAR_LIMIT {
+            return index;
+        }
+        let msb = (index - LINEAR_LIMIT) / SUB_BUCKETS + 5;
+        let sub = (index - LINEAR_LIMIT) % SUB_BUCKETS;
+        let low = (SUB_BUCKETS + sub) << (msb - 4);
+        low + ((1u64 << (msb - 4)) - 1)
+    }
+
+    /// Record one value
+    pub fn record(&mut self, value: u64) {
+        self.counts[Self::index(value)] += 1;
+        self.total += 1;
+        self.max = self.max.max(value);
+    }
+
+    /// Number of recorded values
+    pub fn len(&self) -> u64 {
+        self.total
+    }
+
+    /// Check if nothing was recorded
+    pub fn is_empty(&self) -> bool {
+        self.total == 0
+    }
+
+    /// Value at or below which `quantile` (0.0 - 1.0) of samples fall
+    pub fn percentile(&self, quantile: f64) -> u64 {
+        if self.total == 0 {
+            return 0;
+        }
+        let rank = ((quantile.clamp(0.0, 1.0) * self.total as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (index, &count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return Self::upper_bound(index).min(self.max);
+            }
+        }
+        self.max
+    }
+
+    /// Summary for reporting (None when empty)
+    pub fn percentiles(&self) -> Option<Percentiles> {
+        (!self.is_empty()).then(|| Percentiles {
+            count: self.total,
+            p50: self.percentile(0.50),
+            p95: self.percentile(0.95),
+            p99: self.percentile(0.99),
+            max: self.max,
+        })
+    }
+
+    /// Forget all samples
+    pub fn clear(&mut self) {
+        self.counts.fill(0);
+        self.total = 0;
+        self.max = 0;
+    }
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Percentile summary of a histogram
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Percentiles {
+    pub count: u64,
+    pub p50: u64,
+    pub p95: u64,
+    pub p99: u64,
+    pub max: u64,
+}
+
+/// Cumulative counters and current gauges for one track
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TrackCounters {
+    /// Packets sent (sender) or received (receiver)
+    pub packets: u64,
+    /// Audio payload bytes sent or received
+    pub bytes: u64,
+    /// Frames encoded or decoded
+    pub frames: u64,
+    pub packets_lost: u64,
+    /// Packets that arrived after their playout slot
+    pub late_packets: u64,
+    /// Packets that arrived in time but out of order (receiver)
+    pub reordered_packets: u64,
+    /// Capture or jitter buffer overflows
+    pub overflows: u64,
+    /// Second copies of a packet, dropped (receiver)
+    pub duplicate_packets: u64,
+    /// Playback underruns
+    pub underruns: u64,
+    /// Jitter buffer fill (receiver)
+    pub jitter_level: Option<usize>,
+    pub jitter_capacity: Option<usize>,
+    /// Jitter buffer fill since the last report (receiver)
+    pub jitter_occupancy: Option<OccupancySummary>,
+    /// Sender-to-receiver transit time (receiver, clock synced)
+    pub transit_ms: Option<f64>,
+    /// Inter-arrival jitter in microseconds (receiver)
+    pub jitter_us: Option<Percentiles>,
+    /// Length of loss gaps in packets (receiver)
+    pub gap_packets: Option<Percentiles>,
+}
+
+/// Statistics for one track
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TrackStats {
+    pub track_id: u8,
+    pub packets: u64,
+    pub bytes: u64,
+    pub frames: u64,
+    pub packets_lost: u64,
+    pub late_packets: u64,
+    pub reordered_packets: u64,
+    pub overflows: u64,
+    pub duplicate_packets: u64,
+    pub underruns: u64,
+    pub jitter_level: Option<usize>,
+    pub jitter_capacity: Option<usize>,
+    pub transit_ms: Option<f64>,
+    pub jitter_us: Option<Percentiles>,
+    pub gap_packets: Option<Percentiles>,
+    /// Span the rates below cover
+    pub window_secs: f64,
+    pub packets_per_sec: f64,
+    pub bitrate_kbps: f64,
+    /// Lost over lost + received in the window
+    pub loss_pct: f64,
+    /// Payload bitrate over the last report interval
+    pub current_kbps: f64,
+    /// Bitrate over the last report interval with headers included
+    pub wire_kbps: f64,
+    /// Packet path stages over the last report interval
+    pub latency: Vec<StageLatency>,
+    /// Sum of the stage means
+    pub latency_ms: f64,
+}
+
+/// Jitter buffer fill over one report interval
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct TrendPoint {
+    /// Age of the report
+    pub secs_ago: f64,
+    pub min: usize,
+    pub max: usize,
+    pub avg: f32,
+}
+
+/// Jitter buffer fill of one track over a recent window, in frames
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OccupancyTrend {
+    pub track_id: u8,
+    /// Span the reports cover
+    pub window_secs: f64,
+    pub min: usize,
+    pub max: usize,
+    pub avg: f32,
+    /// Release attempts that found the buffer empty
+    pub empty_pct: f64,
+    /// Change in average fill per minute (positive: latency creeping up)
+    pub drift_per_min: f64,
+    /// One point per report, oldest first
+    pub points: Vec<TrendPoint>,
+}
+
+impl OccupancyTrend {
+    /// Trend over `reports` (oldest first) as seen at `now`
+    fn new(track_id: u8, reports: &[(Instant, OccupancySummary)], now: Instant) -> Self {
+        let mut trend = OccupancyTrend { track_id, ..Default::default() };
+        let (Some(first), Some(last)) = (reports.first(), reports.last()) else {
+            return trend;
+        };
+        trend.window_secs = last.0.duration_since(first.0).as_secs_f64();
+        trend.points = reports
+            .iter()
+            .map(|&(at, s)| TrendPoint { secs_ago: now.duration_since(at).as_secs_f64(), min: s.min, max: s.max, avg: s.avg })
+            .collect();
+        trend.min = reports.iter().map(|(_, s)| s.min).min().unwrap_or(0);
+        trend.max = reports.iter().map(|(_, s)| s.max).max().unwrap_or(0);
+        let samples: usize = reports.iter().map(|(_, s)| s.samples).sum();
+        if samples > 0 {
+            let sum: f64 = reports.iter().map(|(_, s)| s.avg as f64 * s.samples as f64).sum();
+            trend.avg = (sum / samples as f64) as f32;
+            trend.empty_pct = reports.iter().map(|(_, s)| s.empty).sum::<usize>() as f64 * 100.0 / samples as f64;
+        }
+
+        // Least-squares slope of the per-report averages
+        let n = reports.len() as f64;
+        let xs: Vec<f64> = reports.iter().map(|(at, _)| at.duration_since(first.0).as_secs_f64() / 60.0).collect();
+        let mean_x = xs.iter().sum::<f64>() / n;
+        let mean_y = reports.iter().map(|(_, s)| s.avg as f64).sum::<f64>() / n;
+        let (mut cov, mut var) = (0.0, 0.0);
+        for (x, (_, s)) in xs.iter().zip(reports) {
+            cov += (x - mean_x) * (s.avg as f64 - mean_y);
+            var += (x - mean_x) * (x - mean_x);
+        }
+        if var > 0.0 {
+            trend.drift_per_min = cov / var;
+        }
+        trend
+    }
+}
+
+/// Counter history for one track
+#[derive(Default)]
+struct History {
+    samples: VecDeque<(Instant, TrackCounters)>,
+    latency: Vec<StageLatency>,
+    /// Jitter buffer fill per report, newest [`TREND_WINDOW`]
+    occupancy: VecDeque<(Instant, OccupancySummary)>,
+}
+
+impl History {
+    fn push(&mut self, at: Instant, counters: TrackCounters) {
+        // Keep one sample at or beyond the window edge as the baseline
+        while self.samples.len() >= 2 && at.duration_since(self.samples[1].0) >= STATS_WINDOW {
+            self.samples.pop_front();
+        }
+        self.samples.push_back((at, counters));
+
+        if let Some(occupancy) = counters.jitter_occupancy {
+            while self.occupancy.front().is_some_and(|(then, _)| at.duration_since(*then) > TREND_WINDOW) {
+                self.occupancy.pop_front();
+            }
+            self.occupancy.push_back((at, occupancy));
+        }
+    }
+
+    /// Jitter buffer fill over the reports in the last `window`
+    fn trend(&self, track_id: u8, window: Duration, now: Instant) -> OccupancyTrend {
+        let start = self.occupancy.partition_point(|(at, _)| now.duration_since(*at) > window);
+        OccupancyTrend::new(track_id, &self.occupancy.range(start..).copied().collect::<Vec<_>>(), now)
+    }
+
+    fn stats(&self, track_id: u8) -> TrackStats {
+        let Some(&(last_at, last)) = self.samples.back() else {
+            return TrackStats { track_id, ..Default::default() };
+        };
+        let latency_ms = self.latency.iter().map(|s| s.mean_ms).sum();
+        let mut stats = TrackStats {
+            track_id,
+            packets: last.packets,
+            bytes: last.bytes,
+            frames: last.frames,
+            packets_lost: last.packets_lost,
+            late_packets: last.late_packets,
+            reordered_packets: last.reordered_packets,
+            overflows: last.overflows,
+            duplicate_packets: last.duplicate_packets,
+            underruns: last.underruns,
+            jitter_level: last.jitter_level,
+            jitter_capacity: last.jitter_capacity,
+            transit_ms: last.transit_ms,
+            jitter_us: last.jitter_us,
+            gap_packets: last.gap_packets,
+            latency: self.latency.clone(),
+            latency_ms,
+            ..Default::default()
+        };
+
+        let (first_at, first) = self.samples[0];
+        let secs = last_at.duration_since(first_at).as_secs_f64();
+        if secs > 0.0 {
+            let packets = last.packets.saturating_sub(first.packets);
+            let lost = last.packets_lost.saturating_sub(first.packets_lost);
+            stats.window_secs = secs;
+            stats.packets_per_sec = packets as f64 / secs;
+            stats.bitrate_kbps = last.bytes.saturating_sub(first.bytes) as f64 * 8.0 / secs / 1000.0;
+            if packets + lost > 0 {
+                stats.loss_pct = lost as f64 * 100.0 / (packets + lost) as f64;
+            }
+        }
+
+        if let Some(&(prev_at, prev)) = self.samples.len().checked_sub(2).and_then(|i| self.samples.get(i)) {
+            let secs = last_at.duration_since(prev_at).as_secs_f64();
+            if secs > 0.0 {
+                let bytes = last.bytes.saturating_sub(prev.bytes);
+                let packets = last.packets.saturating_sub(prev.packets);
+                stats.current_kbps = bytes as f64 * 8.0 / secs / 1000.0;
+                stats.wire_kbps = (bytes + packets * PACKET_OVERHEAD) as f64 * 8.0 / secs / 1000.0;
+            }
+        }
+        stats
+    }
+}
+
+/// Current bandwidth of one track
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TrackBandwidth {
+    pub track_id: u8,
+    /// Encoded audio only
+    pub bitrate_kbps: f64,
+    /// Headers included
+    pub wire_kbps: f64,
+}
+
+/// Statistics of a track group: its tracks' counters and rates summed
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GroupStats {
+    pub name: String,
+    pub tracks: Vec<u8>,
+    pub packets: u64,
+    pub bytes: u64,
+    pub packets_lost: u64,
+    pub late_packets: u64,
+    pub overflows: u64,
+    pub underruns: u64,
+    pub packets_per_sec: f64,
+    pub bitrate_kbps: f64,
+    pub current_kbps: f64,
+    pub wire_kbps: f64,
+    /// Loss of the worst track in the window
+    pub max_loss_pct: f64,
+    /// Transit time of the slowest track
+    pub max_transit_ms: Option<f64>,
+}
+
+impl GroupStats {
+    /// Sum the statistics of a group's tracks
+    pub fn new(name: &str, stats: &[TrackStats]) -> Self {
+        let mut group = Self { name: name.to_string(), ..Default::default() };
+        for s in stats {
+            group.tracks.push(s.track_id);
+            group.packets += s.packets;
+            group.bytes += s.bytes;
+            group.packets_lost += s.packets_lost;
+            group.late_packets += s.late_packets;
+            group.overflows += s.overflows;
+            group.underruns += s.underruns;
+            group.packets_per_sec += s.packets_per_sec;
+            group.bitrate_kbps += s.bitrate_kbps;
+            group.current_kbps += s.current_kbps;
+            group.wire_kbps += s.wire_kbps;
+            group.max_loss_pct = group.max_loss_pct.max(s.loss_pct);
+            group.max_transit_ms = match (group.max_transit_ms, s.transit_ms) {
+                (Some(a), Some(b)) => Some(a.max(b)),
+                (a, b) => a.or(b),
+            };
+        }
+        group
+    }
+}
+
+/// Current bandwidth of all tracks against the configured cap
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BandwidthReport {
+    pub tracks: Vec<TrackBandwidth>,
+    pub total_kbps: f64,
+    pub total_wire_kbps: f64,
+    /// Cap on `total_wire_kbps` (0 = none)
+    pub cap_kbps: u32,
+    pub over_cap: bool,
+}
+
+impl BandwidthReport {
+    /// Sum the current rates of `stats` and compare against `cap_kbps`
+    pub fn new(stats: &[TrackStats], cap_kbps: u32) -> Self {
+        let tracks: Vec<TrackBandwidth> = stats
+            .iter()
+            .map(|s| TrackBandwidth { track_id: s.track_id, bitrate_kbps: s.current_kbps, wire_kbps: s.wire_kbps })
+            .collect();
+        let total_kbps = tracks.iter().map(|t| t.bitrate_kbps).sum();
+        let total_wire_kbps = tracks.iter().map(|t| t.wire_kbps).sum();
+        Self { tracks, total_kbps, total_wire_kbps, cap_kbps, over_cap: cap_kbps > 0 && total_wire_kbps > cap_kbps as f64 }
+    }
+}
+
+/// Per-track statistics store
+#[derive(Default)]
+pub struct StatsRegistry {
+    tracks: DashMap<u8, History>,
+}
+
+impl StatsRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registry with room for `tracks` tracks allocated up front
+    pub fn with_capacity(tracks: usize) -> Self {
+        Self { tracks: DashMap::with_capacity(tracks) }
+    }
+
+    /// Record the current counters of a track
+    pub fn report(&self, track_id: u8, counters: TrackCounters) {
+        self.report_at(track_id, counters, Instant::now());
+    }
+
+    fn report_at(&self, track_id: u8, counters: TrackCounters, at: Instant) {
+        self.tracks.entry(track_id).or_default().push(at, counters);
+    }
+
+    /// Record the latest latency breakdown of a track
+    pub fn report_latency(&self, track_id: u8, latency: Vec<StageLatency>) {
+        self.tracks.entry(track_id).or_default().latency = latency;
+    }
+
+    /// Get statistics for a track (zeroed until the first report)
+    pub fn get(&self, track_id: u8) -> TrackStats {
+        self.tracks
+            .get(&track_id)
+            .map(|h| h.stats(track_id))
+            .unwrap_or(TrackStats { track_id, ..Default::default() })
+    }
+
+    /// Jitter buffer fill of a track over the last `window` (up to [`TREND_WINDOW`])
+    pub fn trend(&self, track_id: u8, window: Duration) -> OccupancyTrend {
+        self.trend_at(track_id, window, Instant::now())
+    }
+
+    fn trend_at(&self, track_id: u8, window: Duration, now: Instant) -> OccupancyTrend {
+        self.tracks
+            .get(&track_id)
+            .map(|h| h.trend(track_id, window, now))
+            .unwrap_or(OccupancyTrend { track_id, ..Default::default() })
+    }
+
+    /// Forget a removed track
+    pub fn remove(&self, track_id: u8) {
+        self.tracks.remove(&track_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_window_rates() {
+        let registry = StatsRegistry::new();
+        let start = Instant::now();
+        let counters = |packets, bytes, lost| TrackCounters {
+            packets,
+            bytes,
+            packets_lost: lost,
+            ..Default::default()
+        };
+
+        registry.report_at(1, counters(0, 0, 0), start);
+        registry.report_at(1, counters(500, 50_000, 0), start + Duration::from_secs(5));
+        registry.report_at(1, counters(990, 99_000, 10), start + Duration::from_secs(10));
+
+        let stats = registry.get(1);
+        assert_eq!(stats.packets, 990);
+        assert!((stats.packets_per_sec - 99.0).abs() < 1e-9);
+        assert!((stats.bitrate_kbps - 79.2).abs() < 1e-9);
+        assert!((stats.loss_pct - 1.0).abs() < 1e-9);
+
+        // Current rates cover the last interval: 490 packets of 49 kB in 5 s
+        assert!((stats.current_kbps - 78.4).abs() < 1e-9);
+        let wire = (49_000 + 490 * PACKET_OVERHEAD) as f64 * 8.0 / 5.0 / 1000.0;
+        assert!((stats.wire_kbps - wire).abs() < 1e-9);
+        let report = BandwidthReport::new(&[stats.clone(), stats.clone()], 200);
+        assert!((report.total_wire_kbps - 2.0 * wire).abs() < 1e-9);
+        assert!(report.over_cap);
+        assert!(!BandwidthReport::new(&[stats], 0).over_cap);
+
+        // Older samples leave the window
+        registry.report_at(1, counters(1490, 149_000, 10), start + Duration::from_secs(15));
+        let stats = registry.get(1);
+        assert!((stats.window_secs - 10.0).abs() < 1e-9);
+        assert!((stats.packets_per_sec - 99.0).abs() < 1e-9);
+        assert_eq!(registry.get(2).packets, 0);
+    }
+
+    #[test]
+    fn test_occupancy_trend() {
+        let registry = StatsRegistry::new();
+        let start = Instant::now();
+        // Fill creeps up by one frame a second, starving in the first second
+        for secs in 0..=6u64 {
+            let avg = secs as f32 + 1.0;
+            let occupancy = OccupancySummary { samples: 100, min: secs as usize, max: secs as usize + 2, avg, empty: if secs == 0 { 10 } else { 0 } };
+            let counters = TrackCounters { jitter_occupancy: Some(occupancy), ..Default::default() };
+            registry.report_at(1, counters, start + Duration::from_secs(secs));
+        }
+        let now = start + Duration::from_secs(6);
+
+        let trend = registry.trend_at(1, Duration::from_secs(60), now);
+        assert_eq!(trend.points.len(), 7);
+        assert!((trend.window_secs - 6.0).abs() < 1e-9);
+        assert_eq!((trend.min, trend.max), (0, 8));
+        assert!((trend.avg - 4.0).abs() < 1e-6);
+        assert!((trend.empty_pct - 10.0 / 7.0).abs() < 1e-9);
+        assert!((trend.drift_per_min - 60.0).abs() < 1e-6);
+
+        // A shorter window only sees the latest reports
+        let trend = registry.trend_at(1, Duration::from_secs(2), now);
+        assert_eq!(trend.points.iter().map(|p| p.secs_ago).collect::<Vec<_>>(), vec![2.0, 1.0, 0.0]);
+        assert_eq!(trend.empty_pct, 0.0);
+        assert!(registry.trend_at(2, Duration::from_secs(60), now).points.is_empty());
+    }
+
+    #[test]
+    fn test_histogram_percentiles() {
+        let mut histogram = Histogram::new();
+        assert_eq!(histogram.percentiles(), None);
+        for value in 1..=1000 {
+            histogram.record(value);
+        }
+
+        let p = histogram.percentiles().unwrap();
+        assert_eq!(p.count, 1000);
+        assert_eq!(p.max, 1000);
+        // Within one bucket (~6%) of the true value
+        for (got, want) in [(p.p50, 500.0), (p.p95, 950.0), (p.p99, 990.0)] {
+            assert!((got as f64 - want).abs() / want <= 1.0 / 16.0, "{} vs {}", got, want);
+        }
+
+        // Small values are exact; bounds hold at the top of the range
+        histogram.clear();
+        histogram.record(3);
+        assert_eq!(histogram.percentile(0.5), 3);
+        assert_eq!(Histogram::upper_bound(Histogram::index(u64::MAX)), u64::MAX);
+    }
+}
@@ -1,30 +1,94 @@
 //! Individual track representation
 
-use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
 
 use crate::audio::buffer::{create_shared_buffer, SharedRingBuffer};
 use crate::config::OpusConfig;
+use crate::dsp::{DspMeters, ProcessorConfig};
 use crate::error::TrackError;
-use crate::protocol::{TrackConfig, TrackStatus, TrackType};
-use crate::constants::RING_BUFFER_CAPACITY;
+use crate::protocol::{CapabilityMismatch, TrackConfig, TrackStatus, TrackType};
+use crate::constants::{
+    DEFAULT_SAMPLE_RATE, MAX_PLAYOUT_DELAY_MS, MAX_SIMULCAST_LAYERS, MIN_TRACK_BITRATE, RING_BUFFER_CAPACITY,
+};
 
-/// Track state
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Track lifecycle state
+///
+/// Created → Starting → Running ⇄ Degraded → Stopping → Stopped, and back
+/// to Starting from Stopped; any state but Stopping can fall into Error,
+/// which is left by starting or stopping the track.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum TrackState {
-    /// Track is created but not started
-    Stopped,
+    /// Track is created but was never started
+    #[default]
+    Created,
     /// Track is starting
     Starting,
     /// Track is running
     Running,
+    /// Track is running with an alert raised
+    Degraded,
     /// Track is stopping
     Stopping,
+    /// Track is stopped
+    Stopped,
     /// Track encountered an error
     Error,
 }
 
+impl TrackState {
+    /// Check if a track in this state may move to `to`
+    pub fn can_transition(self, to: TrackState) -> bool {
+        use TrackState::*;
+        matches!(
+            (self, to),
+            (Created | Stopped | Error, Starting)
+                | (Starting, Running)
+                | (Running, Degraded)
+                | (Degraded, Running)
+                | (Starting | Running | Degraded | Error, Stopping)
+                | (Stopping, Stopped)
+                | (Created | Starting | Running | Degraded | Stopped, Error)
+                | (Error, Running)
+        )
+    }
+    
+    /// Check if audio flows in this state
+    pub fn is_running(self) -> bool {
+        matches!(self, TrackState::Running | TrackState::Degraded)
+    }
+}
+
+impl std::fmt::Display for TrackState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            TrackState::Created => "created",
+            TrackState::Starting => "starting",
+            TrackState::Running => "running",
+            TrackState::Degraded => "degraded",
+            TrackState::Stopping => "stopping",
+            TrackState::Stopped => "stopped",
+            TrackState::Error => "error",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A track's change of state
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrackTransition {
+    pub track_id: u8,
+    pub from: TrackState,
+    pub to: TrackState,
+}
+
+/// Shortest time between two logged errors of a track
+pub const ERROR_LOG_INTERVAL: Duration = Duration::from_secs(1);
+
 /// Audio track (sender or receiver)
 /// 
 /// Note: Encoders/decoders are NOT stored in Track to maintain thread safety.
@@ -51,6 +115,15 @@ pub struct Track {
     /// Solo flag
     solo: Arc<AtomicBool>,
     
+    /// Paused flag (no packets sent, pipeline kept)
+    paused: Arc<AtomicBool>,
+    
+    /// Processing chain state
+    meters: Arc<DspMeters>,
+    
+    /// Bitrate assigned by the budget manager (0 = use configured bitrate)
+    allocated_bitrate: Arc<AtomicU32>,
+    
     /// Audio buffer
     pub buffer: SharedRingBuffer,
     
@@ -66,8 +139,14 @@ pub struct Track {
     /// Last error message
     last_error: Option<String>,
     
+    /// When an error was last logged, and how many have been since
+    error_log: (Option<Instant>, u64),
+    
     /// Peak level (dB)
     peak_level_db: f32,
+    
+    /// What the receiver can't play as sent
+    receiver_mismatches: Vec<CapabilityMismatch>,
 }
 
 // Track is now Send + Sync safe (no raw pointers)
@@ -82,15 +161,20 @@ impl Track {
             name: config.name.clone(),
             device_id: config.device_id.clone(),
             config,
-            state: TrackState::Stopped,
+            state: TrackState::Created,
             muted: Arc::new(AtomicBool::new(false)),
             solo: Arc::new(AtomicBool::new(false)),
+            paused: Arc::new(AtomicBool::new(false)),
+            meters: Arc::new(DspMeters::new()),
+            allocated_bitrate: Arc::new(AtomicU32::new(0)),
             buffer: create_shared_buffer(RING_BUFFER_CAPACITY),
             packets_count: Arc::new(AtomicU64::new(0)),
             packets_lost: Arc::new(AtomicU64::new(0)),
             start_time: None,
             last_error: None,
+            error_log: (None, 0),
             peak_level_db: -96.0,
+            receiver_mismatches: Vec::new(),
         }
     }
     
@@ -108,7 +192,7 @@ impl Track {
         };
         
         OpusConfig {
-            bitrate: self.config.bitrate,
+            bitrate: self.effective_bitrate(),
             frame_size,
             channels: self.config.channels,
             fec: self.config.fec_enabled,
@@ -116,26 +200,51 @@ impl Track {
         }
     }
     
-    /// Start the track
-    pub fn start(&mut self) -> Result<(), TrackError> {
-        if self.state == TrackState::Running {
-            return Ok(());
+    /// Start the track; returns the transitions made (none if already running)
+    pub fn start(&mut self) -> Result<Vec<TrackTransition>, TrackError> {
+        if self.state.is_running() {
+            return Ok(Vec::new());
         }
         
-        self.state = TrackState::Starting;
+        let starting = self.transition(TrackState::Starting)?;
         self.start_time = Some(Instant::now());
         self.packets_count.store(0, Ordering::Relaxed);
         self.packets_lost.store(0, Ordering::Relaxed);
-        self.state = TrackState::Running;
+        self.last_error = None;
+        let running = self.transition(TrackState::Running)?;
         
-        Ok(())
+        Ok(vec![starting, running])
     }
     
-    /// Stop the track
-    pub fn stop(&mut self) {
-        self.state = TrackState::Stopping;
+    /// Stop the track; returns the transitions made (none if not started)
+    pub fn stop(&mut self) -> Vec<TrackTransition> {
+        if matches!(self.state, TrackState::Created | TrackState::Stopped) {
+            return Vec::new();
+        }
+        
+        let mut transitions: Vec<_> = self.transition(TrackState::Stopping).into_iter().collect();
         self.start_time = None;
-        self.state = TrackState::Stopped;
+        transitions.extend(self.transition(TrackState::Stopped));
+        transitions
+    }
+    
+    /// Move to `to` if the lifecycle allows it
+    pub fn transition(&mut self, to: TrackState) -> Result<TrackTransition, TrackError> {
+        let from = self.state;
+        if !from.can_transition(to) {
+            return Err(TrackError::InvalidTransition { track_id: self.id, from, to });
+        }
+        self.state = to;
+        Ok(TrackTransition { track_id: self.id, from, to })
+    }
+    
+    /// Mark a running track degraded (alert raised) or running again
+    pub fn set_degraded(&mut self, degraded: bool) -> Option<TrackTransition> {
+        let to = if degraded { TrackState::Degraded } else { TrackState::Running };
+        if !self.state.is_running() || self.state == to {
+            return None;
+        }
+        self.transition(to).ok()
     }
     
     /// Get current state
@@ -143,14 +252,9 @@ impl Track {
         self.state
     }
     
-    /// Set state (internal use)
-    pub fn set_state(&mut self, state: TrackState) {
-        self.state = state;
-    }
-    
     /// Check if running
     pub fn is_running(&self) -> bool {
-        self.state == TrackState::Running
+        self.state.is_running()
     }
     
     /// Set muted state
@@ -173,6 +277,21 @@ impl Track {
         self.solo.load(Ordering::Relaxed)
     }
     
+    /// Set paused state
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::Relaxed);
+    }
+    
+    /// Get paused state
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+    
+    /// Get processing chain meters
+    pub fn meters(&self) -> Arc<DspMeters> {
+        self.meters.clone()
+    }
+    
     /// Increment packet count
     pub fn increment_packets(&self) {
         self.packets_count.fetch_add(1, Ordering::Relaxed);
@@ -219,10 +338,30 @@ impl Track {
         self.peak_level_db
     }
     
-    /// Set error state
-    pub fn set_error(&mut self, error: String) {
-        self.state = TrackState::Error;
+    /// Set error state; returns the transition unless already in error
+    pub fn set_error(&mut self, error: String) -> Option<TrackTransition> {
         self.last_error = Some(error);
+        self.transition(TrackState::Error).ok()
+    }
+    
+    /// Back to running after an error, if the track was started
+    pub fn recover(&mut self) -> Option<TrackTransition> {
+        if self.state != TrackState::Error || self.start_time.is_none() {
+            return None;
+        }
+        self.transition(TrackState::Running).ok()
+    }
+    
+    /// Count an error; returns the errors left unlogged before it when one
+    /// is due to be logged, at most once per [`ERROR_LOG_INTERVAL`]
+    pub fn error_log_due(&mut self) -> Option<u64> {
+        let (last, suppressed) = &mut self.error_log;
+        if last.is_some_and(|at| at.elapsed() < ERROR_LOG_INTERVAL) {
+            *suppressed += 1;
+            return None;
+        }
+        *last = Some(Instant::now());
+        Some(std::mem::take(suppressed))
     }
     
     /// Get last error
@@ -230,6 +369,16 @@ impl Track {
         self.last_error.as_deref()
     }
     
+    /// What the receiver last said it can't play as sent
+    pub fn receiver_mismatches(&self) -> &[CapabilityMismatch] {
+        &self.receiver_mismatches
+    }
+    
+    /// Record the receiver's capability check of this track
+    pub fn set_receiver_mismatches(&mut self, mismatches: Vec<CapabilityMismatch>) {
+        self.receiver_mismatches = mismatches;
+    }
+    
     /// Update configuration
     pub fn update_config(&mut self, update: &crate::protocol::TrackConfigUpdate) -> Result<(), TrackError> {
         if let Some(ref name) = update.name {
@@ -237,6 +386,27 @@ impl Track {
             self.config.name = name.clone();
         }
         
+        if let Some(ref color) = update.color {
+            let hex = color.strip_prefix('#').unwrap_or_default();
+            if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(TrackError::InvalidConfig(format!("Color {} is not #rrggbb", color)));
+            }
+            self.config.color = Some(color.clone());
+        }
+        
+        if let Some(track_type) = update.track_type {
+            self.config.track_type = track_type;
+        }
+        
+        if let Some(auto_start) = update.auto_start {
+            self.config.auto_start = auto_start;
+        }
+        
+        if let Some(ref group) = update.group {
+            let group = group.trim();
+            self.config.group = (!group.is_empty()).then(|| group.to_string());
+        }
+        
         if let Some(ref device_id) = update.device_id {
             self.device_id = device_id.clone();
             self.config.device_id = device_id.clone();
@@ -257,26 +427,154 @@ impl Track {
             // Note: If encoder exists elsewhere, caller needs to update it
         }
         
+        if let Some(ref bitrates) = update.simulcast_bitrates {
+            if bitrates.len() > MAX_SIMULCAST_LAYERS {
+                return Err(TrackError::InvalidConfig(format!(
+                    "{} simulcast layers exceed the maximum of {}",
+                    bitrates.len(), MAX_SIMULCAST_LAYERS
+                )));
+            }
+            if let Some(bitrate) = bitrates.iter().find(|b| !(MIN_TRACK_BITRATE..=510_000).contains(*b)) {
+                return Err(TrackError::InvalidConfig(format!(
+                    "Simulcast bitrate {} out of range ({} to 510000)",
+                    bitrate, MIN_TRACK_BITRATE
+                )));
+            }
+            self.config.simulcast_bitrates = bitrates.clone();
+        }
+        
+        if let Some(delay_ms) = update.playout_delay_ms {
+            if delay_ms > MAX_PLAYOUT_DELAY_MS {
+                return Err(TrackError::InvalidConfig(format!(
+                    "Playout delay {} ms exceeds maximum of {} ms",
+                    delay_ms, MAX_PLAYOUT_DELAY_MS
+                )));
+            }
+            self.config.playout_delay_ms = delay_ms;
+        }
+        
+        if let Some(gain_db) = update.mix_gain_db {
+            if !(-96.0..=12.0).contains(&gain_db) {
+                return Err(TrackError::InvalidConfig(format!(
+                    "Mix gain {} dB out of range (-96 to +12)",
+                    gain_db
+                )));
+            }
+            self.config.mix_gain_db = gain_db;
+        }
+        
+        if let Some(pan) = update.mix_pan {
+            if !(-1.0..=1.0).contains(&pan) {
+                return Err(TrackError::InvalidConfig(format!(
+                    "Mix pan {} out of range (-1 to 1)",
+                    pan
+                )));
+            }
+            self.config.mix_pan = pan;
+        }
+        
+        if let Some(priority) = update.priority {
+            self.config.priority = Some(priority);
+        }
+        
+        if let Some(weight) = update.bitrate_weight {
+            if !(weight > 0.0 && weight <= 100.0) {
+                return Err(TrackError::InvalidConfig(format!(
+                    "Bitrate weight {} out of range (0 to 100)",
+                    weight
+                )));
+            }
+            self.config.bitrate_weight = weight;
+        }
+        
+        if let Some(gain_db) = update.decoder_gain_db {
+            if !(-96.0..=24.0).contains(&gain_db) {
+                return Err(TrackError::InvalidConfig(format!(
+                    "Decoder gain {} dB out of range (-96 to +24)",
+                    gain_db
+                )));
+            }
+            self.config.decoder_gain_db = gain_db;
+        }
+        
+        if let Some(soft_clip) = update.soft_clip {
+            self.config.soft_clip = soft_clip;
+        }
+        
+        if let Some(ref processors) = update.processors {
+            for processor in processors {
+                processor.validate(DEFAULT_SAMPLE_RATE).map_err(TrackError::InvalidConfig)?;
+                
+                if let ProcessorConfig::Duck { sidechain, .. } = processor {
+                    if *sidechain == self.id {
+                        return Err(TrackError::InvalidConfig(
+                            "A track cannot duck itself".to_string()
+                        ));
+                    }
+                }
+                
+                if self.config.track_type != TrackType::Voice {
+                    match processor {
+                        ProcessorConfig::Denoise => {
+                            return Err(TrackError::InvalidConfig(
+                                "Noise suppression is only available on voice tracks".to_string()
+                            ));
+                        }
+                        ProcessorConfig::Agc { .. } => {
+                            return Err(TrackError::InvalidConfig(
+                                "AGC is only available on voice tracks".to_string()
+                            ));
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            self.config.processors = processors.clone();
+        }
+        
         Ok(())
     }
     
+    /// Set the bitrate assigned by the budget manager (0 to clear)
+    pub fn set_allocated_bitrate(&self, bitrate: u32) {
+        self.allocated_bitrate.store(bitrate, Ordering::Relaxed);
+    }
+    
+    /// Bitrate the encoder should use
+    /// 
+    /// The budget allocation when one is set, the configured bitrate otherwise.
+    pub fn effective_bitrate(&self) -> u32 {
+        match self.allocated_bitrate.load(Ordering::Relaxed) {
+            0 => self.config.bitrate,
+            allocated => allocated,
+        }
+    }
+    
     /// Get track status for reporting
     pub fn status(&self) -> TrackStatus {
         TrackStatus {
             track_id: self.id,
             name: self.name.clone(),
+            color: self.config.color.clone(),
+            track_type: self.config.track_type,
             device_id: self.device_id.clone(),
             active: self.is_running(),
+            state: self.state,
             muted: self.is_muted(),
             solo: self.is_solo(),
-            bitrate: self.config.bitrate,
+            paused: self.is_paused(),
+            gate_open: self.meters.gate_open(),
+            gain_reduction_db: self.meters.gain_reduction_db(),
+            bitrate: self.effective_bitrate(),
             frame_size_ms: self.config.frame_size_ms,
+            playout_delay_ms: self.config.playout_delay_ms,
             packets_sent: self.packets_count(),
             packets_received: self.packets_count(),
             packets_lost: self.packets_lost(),
             current_latency_ms: 0.0, // TODO: Calculate actual latency
             jitter_ms: 0.0, // TODO: Calculate jitter
             level_db: self.peak_level_db,
+            receiver_mismatches: self.receiver_mismatches.iter().map(|m| m.to_string()).collect(),
         }
     }
 }
@@ -0,0 +1,79 @@
+//! Bearer-token authentication for the web UI
+//!
+//! When `ui.auth_token` is set, every API route and the WebSocket upgrade
+//! require `Authorization: Bearer <token>`. Browsers cannot set headers on
+//! a WebSocket handshake, so `?token=<token>` is accepted as well.
+
+use axum::{
+    extract::{Query, Request, State},
+    http::{header, StatusCode, Uri},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::ui::handlers::ApiResponse;
+
+/// Reject requests that don't carry the configured token
+pub async fn require_token(
+    State(token): State<Arc<str>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let presented = bearer_token(request.headers().get(header::AUTHORIZATION).and_then(|v| v.to_str().ok()))
+        .map(str::to_string)
+        .or_else(|| query_token(request.uri()));
+
+    match presented {
+        Some(presented) if constant_time_eq(presented.as_bytes(), token.as_bytes()) => {
+            next.run(request).await
+        }
+        _ => (
+            StatusCode::UNAUTHORIZED,
+            Json(ApiResponse::<()>::error("Missing or invalid token")),
+        )
+            .into_response(),
+    }
+}
+
+/// Extract the token from an `Authorization` header value
+fn bearer_token(value: Option<&str>) -> Option<&str> {
+    value?.strip_prefix("Bearer ").map(str::trim)
+}
+
+/// The `token` query parameter
+#[derive(Deserialize)]
+struct TokenQuery {
+    token: Option<String>,
+}
+
+/// Extract the token from a `token=` query parameter, URL-decoded
+fn query_token(uri: &Uri) -> Option<String> {
+    Query::<TokenQuery>::try_from_uri(uri).ok()?.0.token
+}
+
+/// Compare without leaking the mismatch position through timing
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_extraction() {
+        assert_eq!(bearer_token(Some("Bearer abc")), Some("abc"));
+        assert_eq!(bearer_token(Some("Basic abc")), None);
+        let query = |uri: &str| query_token(&uri.parse().unwrap());
+        assert_eq!(query("/ws?x=1&token=abc").as_deref(), Some("abc"));
+        assert_eq!(query("/ws?token=a%2Fb%3D%26c").as_deref(), Some("a/b=&c"));
+        assert_eq!(query("/ws?x=1"), None);
+        assert_eq!(query("/ws"), None);
+        assert!(constant_time_eq(b"abc", b"abc"));
+        assert!(!constant_time_eq(b"abc", b"abd"));
+        assert!(!constant_time_eq(b"abc", b"abcd"));
+    }
+}
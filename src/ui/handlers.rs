@@ -1,16 +1,29 @@
 //! HTTP API handlers
 
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
     Json,
 };
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::time::Duration;
 
 use crate::audio::device::list_devices;
+use crate::config::AppConfig;
+use crate::diagnostics::{redacted_config, Diagnostics};
+use crate::error::{Error, NetworkError, TrackError};
+use crate::events::{EventLog, EventRecord, Severity};
+use crate::network::{wol, LinkedControl};
+use crate::reload::ConfigReload;
 use crate::protocol::{
-    AudioDeviceInfo, ControlMessage, TrackConfig, TrackConfigUpdate, TrackStatus,
+    AudioDeviceInfo, CapabilityReport, ControlMessage, HealthLevel, HealthReport, RemoteTrackState, RemoteTrackUpdate,
+    Scene, SessionStats, TrackConfig, TrackConfigUpdate, TrackGroup, TrackProfile, TrackStatus,
 };
+use crate::tracks::stats::{OccupancyTrend, TREND_WINDOW};
+use crate::tracks::{GroupStats, ResetSource, Scenes, ThreadCpu, TrackStats};
 use crate::ui::server::AppState;
 
 /// API response wrapper
@@ -46,22 +59,47 @@ impl<T> ApiResponse<T> {
 pub struct SystemStatus {
     pub mode: String,
     pub track_count: usize,
+    pub max_tracks: usize,
     pub uptime_seconds: u64,
+    /// Ports in use, which differ from the configured ones after a fallback
+    pub udp_port: Option<u16>,
+    pub http_port: Option<u16>,
+    pub session: SessionStats,
 }
 
 /// Get system status
 pub async fn get_status(
     State(state): State<Arc<AppState>>,
 ) -> Json<ApiResponse<SystemStatus>> {
+    let session = state.track_manager.session().stats();
     let status = SystemStatus {
         mode: if state.is_sender { "sender" } else { "receiver" }.to_string(),
         track_count: state.track_manager.track_count(),
-        uptime_seconds: 0, // TODO: Track uptime
+        max_tracks: state.track_manager.max_tracks(),
+        uptime_seconds: session.uptime_seconds,
+        udp_port: Some(state.udp_port.load(Ordering::Relaxed)).filter(|&port| port != 0),
+        http_port: Some(state.http_port.load(Ordering::Relaxed)).filter(|&port| port != 0),
+        session,
     };
     
     Json(ApiResponse::ok(status))
 }
 
+/// Get health over the recent window
+///
+/// Responds 503 when critical so load balancers can act on the status alone.
+pub async fn get_health(
+    State(state): State<Arc<AppState>>,
+) -> (StatusCode, Json<ApiResponse<HealthReport>>) {
+    let report = state.track_manager.health_report();
+    let code = if report.status == HealthLevel::Critical {
+        StatusCode::SERVICE_UNAVAILABLE
+    } else {
+        StatusCode::OK
+    };
+    (code, Json(ApiResponse::ok(report)))
+}
+
 /// Get available audio devices
 pub async fn get_devices() -> Json<ApiResponse<Vec<AudioDeviceInfo>>> {
     let devices = list_devices();
@@ -114,6 +152,47 @@ pub async fn delete_track(
     }
 }
 
+/// Get track statistics
+pub async fn get_track_stats(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<u8>,
+) -> (StatusCode, Json<ApiResponse<TrackStats>>) {
+    match state.track_manager.stats(id) {
+        Ok(stats) => (StatusCode::OK, Json(ApiResponse::ok(stats))),
+        Err(e) => (StatusCode::NOT_FOUND, Json(ApiResponse::error(e.to_string()))),
+    }
+}
+
+/// Jitter trend options
+#[derive(Debug, Deserialize)]
+pub struct TrendQuery {
+    /// Span covered (default 60, at most 300)
+    pub window_secs: Option<u64>,
+}
+
+/// Get a track's jitter buffer fill over a recent window
+pub async fn get_jitter_trend(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<u8>,
+    Query(query): Query<TrendQuery>,
+) -> (StatusCode, Json<ApiResponse<OccupancyTrend>>) {
+    let window = Duration::from_secs(query.window_secs.unwrap_or(60)).min(TREND_WINDOW);
+    match state.track_manager.jitter_trend(id, window) {
+        Ok(trend) => (StatusCode::OK, Json(ApiResponse::ok(trend))),
+        Err(e) => (StatusCode::NOT_FOUND, Json(ApiResponse::error(e.to_string()))),
+    }
+}
+
+/// Get CPU usage per named thread
+pub async fn get_thread_cpu(
+    State(state): State<Arc<AppState>>,
+) -> Json<ApiResponse<Vec<ThreadCpu>>> {
+    let threads = tokio::task::spawn_blocking(move || state.track_manager.thread_cpu())
+        .await
+        .unwrap_or_default();
+    Json(ApiResponse::ok(threads))
+}
+
 /// Update a track
 pub async fn update_track(
     State(state): State<Arc<AppState>>,
@@ -184,6 +263,372 @@ pub async fn set_solo(
     }
 }
 
+/// Pause or resume a track
+#[derive(serde::Deserialize)]
+pub struct PauseRequest {
+    pub paused: bool,
+}
+
+pub async fn set_paused(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<u8>,
+    Json(req): Json<PauseRequest>,
+) -> (StatusCode, Json<ApiResponse<()>>) {
+    match state.track_manager.set_paused(id, req.paused) {
+        Ok(_) => {
+            let _ = state.control_tx.send(ControlMessage::SetPaused {
+                track_id: Some(id),
+                paused: req.paused,
+            });
+            (StatusCode::OK, Json(ApiResponse::ok(())))
+        }
+        Err(e) => {
+            (StatusCode::NOT_FOUND, Json(ApiResponse::error(e.to_string())))
+        }
+    }
+}
+
+/// Pause or resume all tracks
+pub async fn set_all_paused(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<PauseRequest>,
+) -> (StatusCode, Json<ApiResponse<()>>) {
+    state.track_manager.set_all_paused(req.paused);
+    let _ = state.control_tx.send(ControlMessage::SetPaused {
+        track_id: None,
+        paused: req.paused,
+    });
+    (StatusCode::OK, Json(ApiResponse::ok(())))
+}
+
+/// Talkback (push-to-talk) state
+#[derive(serde::Serialize)]
+pub struct TalkbackStatus {
+    /// Talkback is enabled on this end
+    pub available: bool,
+    /// Push-to-talk is held
+    pub active: bool,
+}
+
+impl TalkbackStatus {
+    fn of(state: &AppState) -> Self {
+        Self {
+            available: state.track_manager.is_talkback_available(),
+            active: state.track_manager.is_talkback_active(),
+        }
+    }
+}
+
+/// Push-to-talk request
+#[derive(serde::Deserialize)]
+pub struct TalkbackRequest {
+    pub active: bool,
+}
+
+/// Get talkback state
+pub async fn get_talkback(
+    State(state): State<Arc<AppState>>,
+) -> Json<ApiResponse<TalkbackStatus>> {
+    Json(ApiResponse::ok(TalkbackStatus::of(&state)))
+}
+
+/// Press or release push-to-talk
+pub async fn set_talkback(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<TalkbackRequest>,
+) -> (StatusCode, Json<ApiResponse<TalkbackStatus>>) {
+    match state.track_manager.set_talkback(req.active) {
+        Ok(()) => (StatusCode::OK, Json(ApiResponse::ok(TalkbackStatus::of(&state)))),
+        Err(e) => (StatusCode::BAD_REQUEST, Json(ApiResponse::error(e.to_string()))),
+    }
+}
+
+/// Sender tracks as last reported (linked control)
+pub async fn get_remote_tracks(
+    State(linked): State<Arc<LinkedControl>>,
+) -> Json<ApiResponse<Vec<RemoteTrackState>>> {
+    Json(ApiResponse::ok(linked.tracks()))
+}
+
+/// Change a sender track (linked control)
+/// 
+/// The new state shows in `get_remote_tracks` once the sender confirms it.
+pub async fn configure_remote_track(
+    State(linked): State<Arc<LinkedControl>>,
+    Path(id): Path<u8>,
+    Json(update): Json<RemoteTrackUpdate>,
+) -> (StatusCode, Json<ApiResponse<()>>) {
+    match linked.configure(id, update) {
+        Ok(()) => (StatusCode::OK, Json(ApiResponse::ok(()))),
+        Err(e) => (StatusCode::BAD_REQUEST, Json(ApiResponse::error(e.to_string()))),
+    }
+}
+
+/// The whole config, with the tracks as they are now and the secrets redacted
+pub async fn get_config(
+    State(reload): State<Arc<ConfigReload>>,
+) -> Json<ApiResponse<serde_json::Value>> {
+    Json(ApiResponse::ok(redacted_config(&reload.current())))
+}
+
+/// Result of replacing the config
+#[derive(serde::Serialize)]
+pub struct ConfigApplied {
+    /// File the config was saved to
+    pub path: String,
+    /// Config sections whose changes need a restart
+    pub restart: Vec<String>,
+}
+
+/// Replace and save the whole config, applying what can change at runtime
+pub async fn put_config(
+    State(reload): State<Arc<ConfigReload>>,
+    Json(config): Json<AppConfig>,
+) -> (StatusCode, Json<ApiResponse<ConfigApplied>>) {
+    match reload.replace(config) {
+        Ok((path, restart)) => {
+            let applied = ConfigApplied { path: path.display().to_string(), restart };
+            (StatusCode::OK, Json(ApiResponse::ok(applied)))
+        }
+        Err(e @ (Error::Track(_) | Error::Config(_))) => (StatusCode::BAD_REQUEST, Json(ApiResponse::error(e.to_string()))),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error(e.to_string()))),
+    }
+}
+
+/// Refuse to replace the config while the API is open to anyone
+pub async fn config_locked() -> (StatusCode, Json<ApiResponse<ConfigApplied>>) {
+    (StatusCode::FORBIDDEN, Json(ApiResponse::error("Set ui.auth_token to change the config over the API")))
+}
+
+/// Send a Wake-on-LAN packet to `wake.mac`; returns the MAC woken
+pub async fn wake_receiver(
+    State(reload): State<Arc<ConfigReload>>,
+) -> (StatusCode, Json<ApiResponse<String>>) {
+    match wol::wake(&reload.current().wake) {
+        Ok(mac) => (StatusCode::OK, Json(ApiResponse::ok(mac))),
+        Err(e @ NetworkError::InvalidMac(_)) => (StatusCode::BAD_REQUEST, Json(ApiResponse::error(e.to_string()))),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error(e.to_string()))),
+    }
+}
+
+/// List stored scenes
+pub async fn get_scenes(
+    State(scenes): State<Arc<Scenes>>,
+) -> Json<ApiResponse<Vec<Scene>>> {
+    Json(ApiResponse::ok(scenes.list()))
+}
+
+/// Scene store request
+#[derive(Deserialize)]
+pub struct StoreSceneRequest {
+    pub name: String,
+}
+
+/// Store the current mixer state as a scene
+pub async fn store_scene(
+    State(scenes): State<Arc<Scenes>>,
+    Json(req): Json<StoreSceneRequest>,
+) -> (StatusCode, Json<ApiResponse<Scene>>) {
+    match scenes.store(&req.name) {
+        Ok(scene) => (StatusCode::OK, Json(ApiResponse::ok(scene))),
+        Err(e @ TrackError::SceneSave(_)) => (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error(e.to_string()))),
+        Err(e) => (StatusCode::BAD_REQUEST, Json(ApiResponse::error(e.to_string()))),
+    }
+}
+
+/// Delete a scene
+pub async fn delete_scene(
+    State(scenes): State<Arc<Scenes>>,
+    Path(name): Path<String>,
+) -> (StatusCode, Json<ApiResponse<()>>) {
+    match scenes.delete(&name) {
+        Ok(()) => (StatusCode::OK, Json(ApiResponse::ok(()))),
+        Err(e @ TrackError::SceneNotFound(_)) => (StatusCode::NOT_FOUND, Json(ApiResponse::error(e.to_string()))),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error(e.to_string()))),
+    }
+}
+
+/// Scene recall options
+#[derive(Debug, Deserialize)]
+pub struct RecallQuery {
+    /// Crossfade length (default `scenes.crossfade_ms`)
+    pub crossfade_ms: Option<u32>,
+}
+
+/// Recall a scene (GET or POST, no body, for HTTP buttons)
+pub async fn recall_scene(
+    State(scenes): State<Arc<Scenes>>,
+    Path(name): Path<String>,
+    Query(query): Query<RecallQuery>,
+) -> (StatusCode, Json<ApiResponse<Scene>>) {
+    match scenes.recall(&name, query.crossfade_ms) {
+        Ok(scene) => (StatusCode::OK, Json(ApiResponse::ok(scene))),
+        Err(e) => (StatusCode::NOT_FOUND, Json(ApiResponse::error(e.to_string()))),
+    }
+}
+
+/// List track groups with their tracks and mixer state
+pub async fn get_groups(
+    State(state): State<Arc<AppState>>,
+) -> Json<ApiResponse<Vec<TrackGroup>>> {
+    Json(ApiResponse::ok(state.track_manager.groups()))
+}
+
+/// Get a group's statistics, summed over its tracks
+pub async fn get_group_stats(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+) -> (StatusCode, Json<ApiResponse<GroupStats>>) {
+    match state.track_manager.group_stats(&name) {
+        Ok(stats) => (StatusCode::OK, Json(ApiResponse::ok(stats))),
+        Err(e) => (StatusCode::NOT_FOUND, Json(ApiResponse::error(e.to_string()))),
+    }
+}
+
+/// Mute or unmute a group
+pub async fn set_group_mute(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+    Json(req): Json<MuteRequest>,
+) -> (StatusCode, Json<ApiResponse<()>>) {
+    match state.track_manager.set_group_muted(&name, req.muted) {
+        Ok(()) => {
+            let _ = state.control_tx.send(ControlMessage::SetGroupMute { group: name, muted: req.muted });
+            (StatusCode::OK, Json(ApiResponse::ok(())))
+        }
+        Err(e) => (StatusCode::NOT_FOUND, Json(ApiResponse::error(e.to_string()))),
+    }
+}
+
+/// Solo a group
+pub async fn set_group_solo(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+    Json(req): Json<SoloRequest>,
+) -> (StatusCode, Json<ApiResponse<()>>) {
+    match state.track_manager.set_group_solo(&name, req.solo) {
+        Ok(()) => {
+            let _ = state.control_tx.send(ControlMessage::SetGroupSolo { group: name, solo: req.solo });
+            (StatusCode::OK, Json(ApiResponse::ok(())))
+        }
+        Err(e) => (StatusCode::NOT_FOUND, Json(ApiResponse::error(e.to_string()))),
+    }
+}
+
+/// Group gain request
+#[derive(Deserialize)]
+pub struct GroupGainRequest {
+    pub gain_db: f32,
+}
+
+/// Set a group's gain
+pub async fn set_group_gain(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+    Json(req): Json<GroupGainRequest>,
+) -> (StatusCode, Json<ApiResponse<()>>) {
+    match state.track_manager.set_group_gain(&name, req.gain_db) {
+        Ok(()) => {
+            let _ = state.control_tx.send(ControlMessage::SetGroupGain { group: name, gain_db: req.gain_db });
+            (StatusCode::OK, Json(ApiResponse::ok(())))
+        }
+        Err(e @ TrackError::GroupNotFound(_)) => (StatusCode::NOT_FOUND, Json(ApiResponse::error(e.to_string()))),
+        Err(e) => (StatusCode::BAD_REQUEST, Json(ApiResponse::error(e.to_string()))),
+    }
+}
+
+/// Track profiles from the config, by name
+pub async fn get_profiles(
+    State(state): State<Arc<AppState>>,
+) -> Json<ApiResponse<BTreeMap<String, TrackProfile>>> {
+    Json(ApiResponse::ok(state.track_manager.profiles()))
+}
+
+/// What the receiver can play and which tracks don't fit (sender; null until it reports)
+pub async fn get_receiver_capabilities(
+    State(state): State<Arc<AppState>>,
+) -> Json<ApiResponse<Option<CapabilityReport>>> {
+    Json(ApiResponse::ok(state.track_manager.receiver_capabilities()))
+}
+
+/// Bitrate budget request
+#[derive(serde::Deserialize)]
+pub struct BitrateBudgetRequest {
+    pub bitrate: u32,
+}
+
+/// Set the total bitrate shared by all tracks
+pub async fn set_bitrate_budget(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<BitrateBudgetRequest>,
+) -> (StatusCode, Json<ApiResponse<()>>) {
+    state.track_manager.set_bitrate_budget(req.bitrate);
+    let _ = state.control_tx.send(ControlMessage::SetBitrateBudget { bitrate: req.bitrate });
+    (StatusCode::OK, Json(ApiResponse::ok(())))
+}
+
+/// Reset a garbled track: the decoder and jitter buffer on the receiver,
+/// the encoder on the sender; the other end is told to follow
+pub async fn reset_track(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<u8>,
+) -> (StatusCode, Json<ApiResponse<()>>) {
+    match state.track_manager.request_reset(id, ResetSource::Local) {
+        Ok(()) => (StatusCode::OK, Json(ApiResponse::ok(()))),
+        Err(e) => (StatusCode::NOT_FOUND, Json(ApiResponse::error(e.to_string()))),
+    }
+}
+
+/// Enable or disable noise suppression
+#[derive(serde::Deserialize)]
+pub struct DenoiseRequest {
+    pub enabled: bool,
+}
+
+pub async fn set_denoise(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<u8>,
+    Json(req): Json<DenoiseRequest>,
+) -> (StatusCode, Json<ApiResponse<()>>) {
+    match state.track_manager.set_denoise(id, req.enabled) {
+        Ok(_) => {
+            let _ = state.control_tx.send(ControlMessage::SetDenoise {
+                track_id: id,
+                enabled: req.enabled,
+            });
+            (StatusCode::OK, Json(ApiResponse::ok(())))
+        }
+        Err(e) => {
+            (StatusCode::BAD_REQUEST, Json(ApiResponse::error(e.to_string())))
+        }
+    }
+}
+
+/// Set track playout delay
+#[derive(serde::Deserialize)]
+pub struct DelayRequest {
+    pub delay_ms: u32,
+}
+
+pub async fn set_delay(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<u8>,
+    Json(req): Json<DelayRequest>,
+) -> (StatusCode, Json<ApiResponse<()>>) {
+    match state.track_manager.set_playout_delay(id, req.delay_ms) {
+        Ok(_) => {
+            let _ = state.control_tx.send(ControlMessage::SetDelay {
+                track_id: id,
+                delay_ms: req.delay_ms,
+            });
+            (StatusCode::OK, Json(ApiResponse::ok(())))
+        }
+        Err(e) => {
+            (StatusCode::BAD_REQUEST, Json(ApiResponse::error(e.to_string())))
+        }
+    }
+}
+
 /// Start a track
 pub async fn start_track(
     State(state): State<Arc<AppState>>,
@@ -213,3 +658,126 @@ pub async fn stop_track(
         }
     }
 }
+
+/// Flip a track's mute state and return the track
+/// 
+/// One-shot endpoints like this take no body so Stream Deck / Companion
+/// HTTP buttons can call them with a plain GET or POST.
+pub async fn toggle_mute(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<u8>,
+) -> (StatusCode, Json<ApiResponse<TrackStatus>>) {
+    let Some(muted) = state.track_manager.get_track(id).map(|t| !t.is_muted()) else {
+        return (StatusCode::NOT_FOUND, Json(ApiResponse::error(format!("Track not found: {}", id))));
+    };
+    match state.track_manager.set_muted(id, muted) {
+        Ok(_) => {
+            let _ = state.control_tx.send(ControlMessage::SetMute { track_id: id, muted });
+            match state.track_manager.get_track(id) {
+                Some(track) => (StatusCode::OK, Json(ApiResponse::ok(track.status()))),
+                None => (StatusCode::NOT_FOUND, Json(ApiResponse::error(format!("Track not found: {}", id)))),
+            }
+        }
+        Err(e) => {
+            (StatusCode::NOT_FOUND, Json(ApiResponse::error(e.to_string())))
+        }
+    }
+}
+
+/// All tracks ordered by ID
+fn sorted_statuses(state: &AppState) -> Vec<TrackStatus> {
+    let mut statuses = state.track_manager.get_all_statuses();
+    statuses.sort_by_key(|s| s.track_id);
+    statuses
+}
+
+/// Start every track and return them all
+pub async fn start_all(
+    State(state): State<Arc<AppState>>,
+) -> Json<ApiResponse<Vec<TrackStatus>>> {
+    for result in state.track_manager.start_all() {
+        if let Err(e) = result {
+            tracing::debug!("Start all: {}", e);
+        }
+    }
+    Json(ApiResponse::ok(sorted_statuses(&state)))
+}
+
+/// Stop every track and return them all
+pub async fn stop_all(
+    State(state): State<Arc<AppState>>,
+) -> Json<ApiResponse<Vec<TrackStatus>>> {
+    state.track_manager.stop_all();
+    Json(ApiResponse::ok(sorted_statuses(&state)))
+}
+
+/// Event log query
+#[derive(Debug, Deserialize)]
+pub struct EventsQuery {
+    /// Minimum severity (default info)
+    pub level: Option<String>,
+    /// Only events with a higher ID
+    pub since: Option<u64>,
+    /// Most events returned (default 200)
+    pub limit: Option<usize>,
+}
+
+/// Get recent events
+pub async fn get_events(
+    State(log): State<Arc<EventLog>>,
+    Query(query): Query<EventsQuery>,
+) -> (StatusCode, Json<ApiResponse<Vec<EventRecord>>>) {
+    let level = match query.level.as_deref().map(str::parse::<Severity>) {
+        None => Severity::Info,
+        Some(Ok(level)) => level,
+        Some(Err(e)) => return (StatusCode::BAD_REQUEST, Json(ApiResponse::error(e))),
+    };
+    
+    let events = log.query(level, query.since, query.limit.unwrap_or(200));
+    (StatusCode::OK, Json(ApiResponse::ok(events)))
+}
+
+/// Tracing filter, in `RUST_LOG` syntax
+#[derive(Debug, serde::Serialize, Deserialize)]
+pub struct LogFilter {
+    pub filter: String,
+}
+
+/// Get the current log filter
+pub async fn get_log_filter(
+    State(diagnostics): State<Arc<Diagnostics>>,
+) -> Json<ApiResponse<LogFilter>> {
+    Json(ApiResponse::ok(LogFilter { filter: diagnostics.log().filter() }))
+}
+
+/// Change the log filter at runtime
+pub async fn set_log_filter(
+    State(diagnostics): State<Arc<Diagnostics>>,
+    Json(request): Json<LogFilter>,
+) -> (StatusCode, Json<ApiResponse<LogFilter>>) {
+    match diagnostics.log().set_filter(&request.filter) {
+        Ok(()) => (StatusCode::OK, Json(ApiResponse::ok(LogFilter { filter: diagnostics.log().filter() }))),
+        Err(e) => (StatusCode::BAD_REQUEST, Json(ApiResponse::error(e.to_string()))),
+    }
+}
+
+/// Download a diagnostics bundle as a zip
+pub async fn get_diagnostics(
+    State(diagnostics): State<Arc<Diagnostics>>,
+) -> ([(header::HeaderName, String); 2], Vec<u8>) {
+    let name = format!(
+        "las-diagnostics-{}-{}.zip",
+        diagnostics.role(),
+        chrono::Local::now().format("%Y%m%d-%H%M%S")
+    );
+    let bundle = tokio::task::spawn_blocking(move || diagnostics.bundle(&list_devices()))
+        .await
+        .unwrap_or_default();
+    (
+        [
+            (header::CONTENT_TYPE, "application/zip".to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", name)),
+        ],
+        bundle,
+    )
+}
@@ -1,7 +1,11 @@
 //! Web UI module
 
 pub mod server;
+pub mod auth;
 pub mod handlers;
+pub mod openapi;
 pub mod websocket;
+#[cfg(feature = "tls")]
+pub mod tls;
 
 pub use server::WebServer;
@@ -0,0 +1,827 @@
+//! OpenAPI description of the REST API
+//!
+//! Served at `/api/openapi.json` with a Swagger UI at `/api/docs`, so
+//! external controllers (Stream Deck, Companion, scripts) can discover the
+//! API and generate typed clients from it, e.g. with openapi-generator.
+//!
+//! The document is written by hand next to the handlers; keep both in step
+//! when adding routes (the test below checks every `/api` route is listed).
+
+use axum::response::Html;
+use axum::Json;
+use serde_json::{json, Map, Value};
+
+/// API version reported in the spec
+const API_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Swagger UI page pointing at the spec
+const SWAGGER_UI: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+    <title>LAN Audio Streamer API</title>
+    <link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@5/swagger-ui.css">
+</head>
+<body>
+    <div id="swagger-ui"></div>
+    <script src="https://unpkg.com/swagger-ui-dist@5/swagger-ui-bundle.js"></script>
+    <script>
+        SwaggerUIBundle({ url: '/api/openapi.json', dom_id: '#swagger-ui' });
+    </script>
+</body>
+</html>"#;
+
+/// Serve the OpenAPI document
+pub async fn openapi_json() -> Json<Value> {
+    Json(spec())
+}
+
+/// Serve the Swagger UI
+pub async fn swagger_ui() -> Html<&'static str> {
+    Html(SWAGGER_UI)
+}
+
+/// Reference to a component schema
+fn schema_ref(name: &str) -> Value {
+    json!({ "$ref": format!("#/components/schemas/{}", name) })
+}
+
+/// `ApiResponse` envelope around `data`
+fn envelope(data: Value) -> Value {
+    json!({
+        "type": "object",
+        "required": ["success"],
+        "properties": {
+            "success": { "type": "boolean" },
+            "data": data,
+            "error": { "type": "string" }
+        }
+    })
+}
+
+/// Operation with an optional JSON body and a typed response
+fn operation(id: &str, summary: &str, body: Option<Value>, data: Value, track_path: bool) -> Value {
+    let mut op = Map::new();
+    op.insert("operationId".into(), json!(id));
+    op.insert("summary".into(), json!(summary));
+    op.insert("tags".into(), json!([if track_path { "tracks" } else { "system" }]));
+    if track_path {
+        op.insert("parameters".into(), json!([{
+            "name": "id",
+            "in": "path",
+            "required": true,
+            "schema": { "type": "integer", "minimum": 0, "maximum": 255 }
+        }]));
+    }
+    if let Some(body) = body {
+        op.insert("requestBody".into(), json!({
+            "required": true,
+            "content": { "application/json": { "schema": body } }
+        }));
+    }
+    op.insert("responses".into(), json!({
+        "200": {
+            "description": "Success",
+            "content": { "application/json": { "schema": envelope(data) } }
+        },
+        "400": { "description": "Invalid request", "content": { "application/json": { "schema": schema_ref("ErrorResponse") } } },
+        "401": { "description": "Missing or invalid token" },
+        "404": { "description": "Track not found", "content": { "application/json": { "schema": schema_ref("ErrorResponse") } } }
+    }));
+    Value::Object(op)
+}
+
+/// Single boolean/integer field request body
+fn field_body(field: &str, schema: Value) -> Value {
+    json!({
+        "type": "object",
+        "required": [field],
+        "properties": { field: schema }
+    })
+}
+
+/// Body-less action reachable by both GET and POST (for HTTP buttons)
+fn one_shot(id: &str, summary: &str, data: Value, track_path: bool) -> Value {
+    json!({
+        "get": operation(&format!("{}Get", id), summary, None, data.clone(), track_path),
+        "post": operation(id, summary, None, data, track_path)
+    })
+}
+
+/// Build the OpenAPI document
+pub fn spec() -> Value {
+    let empty = json!({ "type": "object", "nullable": true });
+    let boolean = json!({ "type": "boolean" });
+
+    let scene_name = json!({ "name": "name", "in": "path", "required": true, "schema": { "type": "string" } });
+    let recall_scene = |id: &str| json!({
+        "operationId": id,
+        "summary": "Recall a scene, optionally crossfading gains and pan (no body, for HTTP buttons)",
+        "tags": ["scenes"],
+        "parameters": [
+            scene_name,
+            { "name": "crossfade_ms", "in": "query", "description": "Default scenes.crossfade_ms", "schema": { "type": "integer", "minimum": 0 } }
+        ],
+        "responses": {
+            "200": {
+                "description": "Success",
+                "content": { "application/json": { "schema": envelope(schema_ref("Scene")) } }
+            },
+            "404": { "description": "Scene not found", "content": { "application/json": { "schema": schema_ref("ErrorResponse") } } }
+        }
+    });
+
+    // Group routes take the group name in the path
+    let group_op = |id: &str, summary: &str, body: Option<Value>, data: Value| {
+        let mut op = operation(id, summary, body, data, false);
+        op["tags"] = json!(["groups"]);
+        op["parameters"] = json!([{ "name": "name", "in": "path", "required": true, "schema": { "type": "string" } }]);
+        op["responses"]["404"]["description"] = json!("Group not found");
+        op
+    };
+
+    let mut jitter_trend = operation("getJitterTrend", "Jitter buffer fill over a recent window (receiver)", None, schema_ref("OccupancyTrend"), true);
+    jitter_trend["parameters"].as_array_mut().unwrap().push(json!({
+        "name": "window_secs", "in": "query", "schema": { "type": "integer", "minimum": 1, "maximum": 300, "default": 60 }
+    }));
+
+    let mut paths = json!({
+        "/api/status": {
+            "get": operation("getStatus", "System status", None, schema_ref("SystemStatus"), false)
+        },
+        "/api/devices": {
+            "get": operation("getDevices", "List audio devices", None,
+                json!({ "type": "array", "items": schema_ref("AudioDeviceInfo") }), false)
+        },
+        "/api/tracks": {
+            "get": operation("getTracks", "List tracks", None,
+                json!({ "type": "array", "items": schema_ref("TrackStatus") }), false),
+            "post": operation("createTrack", "Create a track", Some(schema_ref("TrackConfig")),
+                json!({ "type": "integer", "description": "New track ID" }), false)
+        },
+        "/api/tracks/{id}": {
+            "delete": operation("deleteTrack", "Remove a track", None, empty.clone(), true),
+            "patch": operation("updateTrack", "Update track settings", Some(schema_ref("TrackConfigUpdate")), empty.clone(), true)
+        },
+        "/api/tracks/{id}/stats": {
+            "get": operation("getTrackStats", "Track counters with rates over the last 10 s", None, schema_ref("TrackStats"), true)
+        },
+        "/api/tracks/{id}/jitter": {
+            "get": jitter_trend
+        },
+        "/api/stats/threads": {
+            "get": operation("getThreadCpu", "CPU usage per named thread, busiest first (Linux only)", None,
+                json!({ "type": "array", "items": schema_ref("ThreadCpu") }), false)
+        },
+        "/api/tracks/{id}/mute": {
+            "post": operation("setMute", "Mute or unmute a track", Some(field_body("muted", boolean.clone())), empty.clone(), true)
+        },
+        "/api/tracks/{id}/solo": {
+            "post": operation("setSolo", "Solo a track", Some(field_body("solo", boolean.clone())), empty.clone(), true)
+        },
+        "/api/tracks/{id}/delay": {
+            "post": operation("setDelay", "Set playout delay (receiver)",
+                Some(field_body("delay_ms", json!({ "type": "integer", "minimum": 0 }))), empty.clone(), true)
+        },
+        "/api/tracks/{id}/pause": {
+            "post": operation("setPaused", "Pause or resume a track", Some(field_body("paused", boolean.clone())), empty.clone(), true)
+        },
+        "/api/tracks/{id}/denoise": {
+            "post": operation("setDenoise", "Toggle noise suppression", Some(field_body("enabled", boolean.clone())), empty.clone(), true)
+        },
+        "/api/tracks/{id}/reset": {
+            "post": operation("resetTrack",
+                "Reset a garbled track's decoder and jitter buffer (receiver) or encoder (sender); the other end follows",
+                None, empty.clone(), true)
+        },
+        "/api/tracks/{id}/start": {
+            "post": operation("startTrack", "Start a track", None, empty.clone(), true)
+        },
+        "/api/tracks/{id}/stop": {
+            "post": operation("stopTrack", "Stop a track", None, empty.clone(), true)
+        },
+        "/api/tracks/{id}/toggle_mute": one_shot("toggleMute", "Flip mute and return the track", schema_ref("TrackStatus"), true),
+        "/api/stream/start_all": one_shot("startAll", "Start every track and return them all",
+            json!({ "type": "array", "items": schema_ref("TrackStatus") }), false),
+        "/api/stream/stop_all": one_shot("stopAll", "Stop every track and return them all",
+            json!({ "type": "array", "items": schema_ref("TrackStatus") }), false),
+        "/api/pause": {
+            "post": operation("setAllPaused", "Pause or resume all tracks", Some(field_body("paused", boolean.clone())), empty.clone(), false)
+        },
+        "/api/talkback": {
+            "get": operation("getTalkback", "Talkback state", None, schema_ref("TalkbackStatus"), false),
+            "post": operation("setTalkback", "Press or release push-to-talk (400 when talkback is disabled)",
+                Some(field_body("active", boolean.clone())), schema_ref("TalkbackStatus"), false)
+        },
+        "/api/remote/tracks": {
+            "get": operation("getRemoteTracks", "Sender tracks as last reported (linked control)", None,
+                json!({ "type": "array", "items": schema_ref("RemoteTrackState") }), false)
+        },
+        "/api/remote/tracks/{id}": {
+            "post": operation("configureRemoteTrack",
+                "Change a sender track (400 before the sender is known; the change shows once the sender confirms it)",
+                Some(schema_ref("RemoteTrackUpdate")), empty.clone(), true)
+        },
+        "/api/scenes": {
+            "get": operation("getScenes", "List stored scenes", None,
+                json!({ "type": "array", "items": schema_ref("Scene") }), false),
+            "post": operation("storeScene", "Store the current mixer state as a scene (replaces one of the same name)",
+                Some(field_body("name", json!({ "type": "string" }))), schema_ref("Scene"), false)
+        },
+        "/api/scenes/{name}": {
+            "delete": {
+                "operationId": "deleteScene",
+                "summary": "Delete a scene",
+                "tags": ["scenes"],
+                "parameters": [scene_name.clone()],
+                "responses": { "200": { "description": "Success" }, "404": { "description": "Scene not found" } }
+            }
+        },
+        "/api/scenes/{name}/recall": {
+            "get": recall_scene("recallSceneGet"),
+            "post": recall_scene("recallScene")
+        },
+        "/api/health": {
+            "get": {
+                "operationId": "getHealth",
+                "summary": "Health over the recent window (503 when critical)",
+                "tags": ["system"],
+                "responses": {
+                    "200": {
+                        "description": "OK or degraded",
+                        "content": { "application/json": { "schema": envelope(schema_ref("HealthReport")) } }
+                    },
+                    "503": {
+                        "description": "Critical",
+                        "content": { "application/json": { "schema": envelope(schema_ref("HealthReport")) } }
+                    }
+                }
+            }
+        },
+        "/api/config": {
+            "get": operation("getConfig", "The whole config, with the tracks as they are now and secrets reading \"<redacted>\"", None, schema_ref("AppConfig"), false),
+            "put": operation("putConfig", "Replace and save the whole config (400 for an unknown track profile or a changed hook command, 403 without ui.auth_token)",
+                Some(schema_ref("AppConfig")), schema_ref("ConfigApplied"), false)
+        },
+        "/api/wake": {
+            "post": operation("wakeReceiver", "Send a Wake-on-LAN packet to the receiver PC at wake.mac (400 if unset or invalid)",
+                None, json!({ "type": "string", "description": "MAC address woken" }), false)
+        },
+        "/api/events": {
+            "get": {
+                "operationId": "getEvents",
+                "summary": "Recent events from the log ring",
+                "tags": ["system"],
+                "parameters": [
+                    { "name": "level", "in": "query", "schema": { "type": "string", "enum": ["trace", "debug", "info", "warn", "error"] } },
+                    { "name": "since", "in": "query", "description": "Only events with a higher ID", "schema": { "type": "integer" } },
+                    { "name": "limit", "in": "query", "schema": { "type": "integer", "default": 200 } }
+                ],
+                "responses": {
+                    "200": {
+                        "description": "Success",
+                        "content": { "application/json": { "schema": envelope(json!({ "type": "array", "items": schema_ref("EventRecord") })) } }
+                    },
+                    "400": { "description": "Unknown level", "content": { "application/json": { "schema": schema_ref("ErrorResponse") } } }
+                }
+            }
+        },
+        "/api/log": {
+            "get": operation("getLogFilter", "Current tracing filter, in RUST_LOG syntax", None,
+                field_body("filter", json!({ "type": "string" })), false),
+            "put": operation("setLogFilter", "Change the tracing filter at runtime (400 if it doesn't parse)",
+                Some(field_body("filter", json!({ "type": "string", "example": "info,lan_audio_streamer::network=debug" }))),
+                field_body("filter", json!({ "type": "string" })), false)
+        },
+        "/api/diagnostics": {
+            "get": {
+                "operationId": "getDiagnostics",
+                "summary": "Zip of the redacted config, audio devices, recent events and a stats dump",
+                "tags": ["system"],
+                "responses": {
+                    "200": {
+                        "description": "Diagnostics bundle",
+                        "content": { "application/zip": { "schema": { "type": "string", "format": "binary" } } }
+                    }
+                }
+            }
+        },
+        "/api/bitrate-budget": {
+            "post": operation("setBitrateBudget", "Set the shared bitrate budget (0 = unlimited)",
+                Some(field_body("bitrate", json!({ "type": "integer", "minimum": 0 }))), empty.clone(), false)
+        },
+        "/api/profiles": {
+            "get": operation("getProfiles", "Track profiles from the config, by name", None,
+                json!({ "type": "object", "additionalProperties": schema_ref("TrackProfile") }), false)
+        },
+        "/api/groups": {
+            "get": operation("getGroups", "Track groups with their tracks and mixer state", None,
+                json!({ "type": "array", "items": schema_ref("TrackGroup") }), false)
+        },
+        "/api/groups/{name}/stats": {
+            "get": group_op("getGroupStats", "Counters and rates of a group's tracks, summed", None, schema_ref("GroupStats"))
+        },
+        "/api/groups/{name}/mute": {
+            "post": group_op("setGroupMute", "Mute or unmute every track of a group",
+                Some(field_body("muted", boolean.clone())), empty.clone())
+        },
+        "/api/groups/{name}/solo": {
+            "post": group_op("setGroupSolo", "Solo a group", Some(field_body("solo", boolean.clone())), empty.clone())
+        },
+        "/api/groups/{name}/gain": {
+            "post": group_op("setGroupGain", "Set a group's gain, added to each track's receiver trim",
+                Some(field_body("gain_db", json!({ "type": "number", "minimum": -96, "maximum": 12 }))), empty.clone())
+        },
+        "/api/receiver/capabilities": {
+            "get": operation("getReceiverCapabilities",
+                "What the receiver plays as sent, and the tracks that don't fit (sender; null until the receiver reports)",
+                None, schema_ref("CapabilityReport"), false)
+        }
+    });
+
+    if cfg!(feature = "webrtc") {
+        paths["/api/webrtc/offer"] = json!({
+            "post": operation("webrtcOffer", "Start a browser listening session",
+                Some(json!({
+                    "type": "object",
+                    "required": ["sdp", "tracks"],
+                    "properties": {
+                        "sdp": { "type": "string" },
+                        "tracks": { "type": "array", "items": { "type": "integer" } },
+                        "layer": { "type": "integer", "description": "Simulcast layer (default webrtc.layer)" }
+                    }
+                })),
+                json!({
+                    "type": "object",
+                    "properties": { "session_id": { "type": "string" }, "sdp": { "type": "string" } }
+                }),
+                false)
+        });
+        paths["/api/webrtc/sessions/{id}"] = json!({
+            "delete": {
+                "operationId": "webrtcClose",
+                "summary": "End a browser listening session",
+                "tags": ["system"],
+                "parameters": [{ "name": "id", "in": "path", "required": true, "schema": { "type": "string" } }],
+                "responses": { "200": { "description": "Success" }, "404": { "description": "Session not found" } }
+            }
+        });
+    }
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "LAN Audio Streamer API",
+            "version": API_VERSION,
+            "description": "Control tracks on a sender or receiver. Live updates are on the /ws WebSocket."
+        },
+        // Token only needed when ui.auth_token is set
+        "security": [{}, { "bearerAuth": [] }],
+        "paths": paths,
+        "components": {
+            "securitySchemes": {
+                "bearerAuth": { "type": "http", "scheme": "bearer" }
+            },
+            "schemas": schemas()
+        }
+    })
+}
+
+/// Component schemas mirroring the serde types
+fn schemas() -> Value {
+    let number = json!({ "type": "number" });
+    let integer = json!({ "type": "integer" });
+    let string = json!({ "type": "string" });
+    let boolean = json!({ "type": "boolean" });
+    let priority = json!({ "type": "string", "enum": ["high", "normal"], "nullable": true });
+    let color = json!({ "type": "string", "pattern": "^#[0-9a-fA-F]{6}$" });
+    let processors = json!({
+        "type": "array",
+        "description": "Processing chain; each entry is an object tagged by processor type",
+        "items": { "type": "object" }
+    });
+
+    json!({
+        "ErrorResponse": {
+            "type": "object",
+            "properties": {
+                "success": { "type": "boolean", "enum": [false] },
+                "error": string
+            }
+        },
+        "TalkbackStatus": {
+            "type": "object",
+            "properties": {
+                "available": boolean,
+                "active": boolean
+            }
+        },
+        "Scene": {
+            "type": "object",
+            "properties": {
+                "name": string,
+                "tracks": { "type": "array", "items": schema_ref("SceneTrack") }
+            }
+        },
+        "SceneTrack": {
+            "type": "object",
+            "properties": {
+                "track_id": integer,
+                "muted": boolean,
+                "solo": boolean,
+                "paused": boolean,
+                "decoder_gain_db": number,
+                "mix_gain_db": number,
+                "mix_pan": number,
+                "bitrate": integer,
+                "device_id": string
+            }
+        },
+        "RemoteTrackUpdate": {
+            "type": "object",
+            "properties": {
+                "bitrate": integer,
+                "fec_enabled": boolean,
+                "enabled": { "type": "boolean", "description": "False pauses the track" }
+            }
+        },
+        "AppConfig": {
+            "type": "object",
+            "description": "The complete config as in config.toml, including every track and any secrets",
+            "required": ["network", "audio", "ui", "tracks"],
+            "properties": {
+                "tracks": { "type": "array", "items": schema_ref("TrackConfig") },
+                "profiles": { "type": "object", "additionalProperties": schema_ref("TrackProfile") }
+            },
+            "additionalProperties": true
+        },
+        "ConfigApplied": {
+            "type": "object",
+            "properties": {
+                "path": { "type": "string", "description": "File the config was saved to" },
+                "restart": { "type": "array", "items": { "type": "string" }, "description": "Sections whose changes need a restart" }
+            }
+        },
+        "ReceiverCapabilities": {
+            "type": "object",
+            "properties": {
+                "sample_rates": { "type": "array", "items": integer },
+                "channels": { "type": "array", "items": integer },
+                "codecs": { "type": "array", "items": string },
+                "max_tracks": integer
+            }
+        },
+        "CapabilityMismatch": {
+            "type": "object",
+            "description": "codec: not decoded (dropped); sample_rate: resampled; channels: remixed; too_many_tracks: dropped",
+            "properties": {
+                "kind": { "type": "string", "enum": ["codec", "sample_rate", "channels", "too_many_tracks"] },
+                "codec": string,
+                "sent": integer,
+                "supported": { "type": "array", "items": integer },
+                "max_tracks": integer
+            },
+            "required": ["kind"]
+        },
+        "CapabilityReport": {
+            "type": "object",
+            "properties": {
+                "capabilities": schema_ref("ReceiverCapabilities"),
+                "tracks": {
+                    "type": "object",
+                    "description": "Track ID -> mismatches, for tracks that don't fit",
+                    "additionalProperties": { "type": "array", "items": schema_ref("CapabilityMismatch") }
+                }
+            }
+        },
+        "RemoteTrackState": {
+            "type": "object",
+            "properties": {
+                "track_id": integer,
+                "name": string,
+                "bitrate": integer,
+                "fec_enabled": boolean,
+                "enabled": boolean
+            }
+        },
+        "SystemStatus": {
+            "type": "object",
+            "properties": {
+                "mode": { "type": "string", "enum": ["sender", "receiver"] },
+                "track_count": integer,
+                "max_tracks": integer,
+                "uptime_seconds": integer,
+                "udp_port": { "type": "integer", "nullable": true },
+                "http_port": { "type": "integer", "nullable": true },
+                "session": schema_ref("SessionStats")
+            }
+        },
+        "HealthReport": {
+            "type": "object",
+            "properties": {
+                "status": { "type": "string", "enum": ["OK", "DEGRADED", "CRITICAL"] },
+                "window_secs": integer,
+                "reasons": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "signal": { "type": "string", "enum": ["xrun", "packet_loss", "deadline_miss", "device_error"] },
+                            "track_id": { "type": "integer", "nullable": true },
+                            "level": { "type": "string", "enum": ["OK", "DEGRADED", "CRITICAL"] },
+                            "value": { "type": "number" },
+                            "message": string
+                        }
+                    }
+                }
+            }
+        },
+        "TrackStats": {
+            "type": "object",
+            "properties": {
+                "track_id": integer,
+                "packets": integer,
+                "bytes": integer,
+                "frames": integer,
+                "packets_lost": integer,
+                "late_packets": integer,
+                "reordered_packets": integer,
+                "overflows": integer,
+                "duplicate_packets": integer,
+                "underruns": integer,
+                "jitter_level": { "type": "integer", "nullable": true },
+                "jitter_capacity": { "type": "integer", "nullable": true },
+                "transit_ms": { "type": "number", "nullable": true },
+                "jitter_us": schema_ref("Percentiles"),
+                "gap_packets": schema_ref("Percentiles"),
+                "window_secs": { "type": "number" },
+                "packets_per_sec": { "type": "number" },
+                "bitrate_kbps": { "type": "number" },
+                "loss_pct": { "type": "number" },
+                "latency": { "type": "array", "items": schema_ref("StageLatency") },
+                "latency_ms": { "type": "number", "description": "Sum of the stage means" }
+            }
+        },
+        "StageLatency": {
+            "type": "object",
+            "properties": {
+                "stage": {
+                    "type": "string",
+                    "enum": ["capture", "ring", "frame", "process", "encode", "send", "network", "receive", "decode", "jitter", "playback"]
+                },
+                "mean_ms": { "type": "number" },
+                "max_ms": { "type": "number" },
+                "samples": integer
+            }
+        },
+        "ThreadCpu": {
+            "type": "object",
+            "properties": {
+                "name": string,
+                "threads": integer,
+                "cpu_seconds": { "type": "number" },
+                "cpu_pct": { "type": "number", "description": "100 = one full core" }
+            }
+        },
+        "Percentiles": {
+            "type": "object",
+            "nullable": true,
+            "properties": {
+                "count": integer,
+                "p50": integer,
+                "p95": integer,
+                "p99": integer,
+                "max": integer
+            }
+        },
+        "OccupancyTrend": {
+            "type": "object",
+            "properties": {
+                "track_id": integer,
+                "window_secs": number,
+                "min": integer,
+                "max": integer,
+                "avg": number,
+                "empty_pct": number,
+                "drift_per_min": { "type": "number", "description": "Change in average fill (frames) per minute; positive means latency is creeping up" },
+                "points": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "secs_ago": number,
+                            "min": integer,
+                            "max": integer,
+                            "avg": number
+                        }
+                    }
+                }
+            }
+        },
+        "SessionStats": {
+            "type": "object",
+            "properties": {
+                "uptime_seconds": integer,
+                "bytes_total": integer,
+                "reconnects": integer,
+                "peak_tracks": integer,
+                "tracks": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "track_id": integer,
+                            "active_seconds": { "type": "number" },
+                            "bytes": integer
+                        }
+                    }
+                }
+            }
+        },
+        "AudioDeviceInfo": {
+            "type": "object",
+            "properties": {
+                "id": string,
+                "name": string,
+                "is_input": boolean,
+                "is_output": boolean,
+                "is_default": boolean,
+                "sample_rates": { "type": "array", "items": integer },
+                "channels": { "type": "array", "items": integer }
+            }
+        },
+        "EventRecord": {
+            "type": "object",
+            "properties": {
+                "id": integer,
+                "timestamp_us": integer,
+                "level": { "type": "string", "enum": ["trace", "debug", "info", "warn", "error"] },
+                "target": string,
+                "message": string,
+                "fields": { "type": "object", "additionalProperties": { "type": "string" } }
+            }
+        },
+        "TrackType": { "type": "string", "enum": ["Voice", "Music", "LowLatency"] },
+        "TrackConfig": {
+            "type": "object",
+            "required": ["name", "device_id", "bitrate", "frame_size_ms", "channels", "track_type", "fec_enabled"],
+            "properties": {
+                "track_id": { "type": "integer", "nullable": true },
+                "name": string,
+                "device_id": string,
+                "bitrate": integer,
+                "frame_size_ms": { "type": "number", "enum": [2.5, 5, 10, 20] },
+                "channels": { "type": "integer", "enum": [1, 2] },
+                "track_type": schema_ref("TrackType"),
+                "fec_enabled": boolean,
+                "playout_delay_ms": integer,
+                "mix_gain_db": number,
+                "mix_pan": { "type": "number", "minimum": -1, "maximum": 1 },
+                "priority": priority,
+                "bitrate_weight": number,
+                "decoder_gain_db": number,
+                "soft_clip": boolean,
+                "processors": processors,
+                "profile": { "type": "string", "nullable": true, "description": "Profile filling in settings left at their defaults" },
+                "color": { "type": "string", "nullable": true, "pattern": "^#[0-9a-fA-F]{6}$" },
+                "source_channel": { "type": "integer", "nullable": true, "minimum": 0, "description": "Device channel published as a mono track" },
+                "simulcast_bitrates": { "type": "array", "items": integer, "maxItems": 3, "description": "Extra encodings sent as simulcast layers 1, 2, ..." },
+                "group": { "type": "string", "nullable": true, "description": "Track group sharing mute, solo and gain" }
+            }
+        },
+        "TrackGroup": {
+            "type": "object",
+            "properties": {
+                "name": string,
+                "tracks": { "type": "array", "items": integer },
+                "muted": boolean,
+                "solo": boolean,
+                "gain_db": number
+            }
+        },
+        "GroupStats": {
+            "type": "object",
+            "properties": {
+                "name": string,
+                "tracks": { "type": "array", "items": integer },
+                "packets": integer,
+                "bytes": integer,
+                "packets_lost": integer,
+                "late_packets": integer,
+                "overflows": integer,
+                "underruns": integer,
+                "packets_per_sec": number,
+                "bitrate_kbps": number,
+                "current_kbps": number,
+                "wire_kbps": number,
+                "max_loss_pct": { "type": "number", "description": "Loss of the worst track in the window" },
+                "max_transit_ms": { "type": "number", "nullable": true, "description": "Transit time of the slowest track" }
+            }
+        },
+        "TrackProfile": {
+            "type": "object",
+            "description": "Only fields present are applied",
+            "properties": {
+                "bitrate": integer,
+                "frame_size_ms": { "type": "number", "enum": [2.5, 5, 10, 20] },
+                "channels": { "type": "integer", "enum": [1, 2] },
+                "track_type": schema_ref("TrackType"),
+                "fec_enabled": boolean,
+                "playout_delay_ms": integer,
+                "mix_gain_db": number,
+                "mix_pan": { "type": "number", "minimum": -1, "maximum": 1 },
+                "priority": priority,
+                "bitrate_weight": number,
+                "decoder_gain_db": number,
+                "soft_clip": boolean,
+                "processors": processors
+            }
+        },
+        "TrackConfigUpdate": {
+            "type": "object",
+            "description": "Only fields present are changed",
+            "properties": {
+                "name": string,
+                "device_id": string,
+                "bitrate": integer,
+                "frame_size_ms": number,
+                "fec_enabled": boolean,
+                "playout_delay_ms": integer,
+                "mix_gain_db": number,
+                "mix_pan": number,
+                "priority": priority,
+                "bitrate_weight": number,
+                "decoder_gain_db": number,
+                "soft_clip": boolean,
+                "processors": processors,
+                "color": color,
+                "track_type": schema_ref("TrackType"),
+                "simulcast_bitrates": { "type": "array", "items": integer, "maxItems": 3 },
+                "group": { "type": "string", "description": "Group to join (empty to leave)" }
+            }
+        },
+        "TrackStatus": {
+            "type": "object",
+            "properties": {
+                "track_id": integer,
+                "name": string,
+                "color": { "type": "string", "nullable": true },
+                "track_type": schema_ref("TrackType"),
+                "device_id": string,
+                "active": boolean,
+                "state": {
+                    "type": "string",
+                    "enum": ["created", "starting", "running", "degraded", "stopping", "stopped", "error"]
+                },
+                "muted": boolean,
+                "solo": boolean,
+                "paused": boolean,
+                "gate_open": boolean,
+                "gain_reduction_db": number,
+                "bitrate": integer,
+                "frame_size_ms": number,
+                "playout_delay_ms": integer,
+                "packets_sent": integer,
+                "packets_received": integer,
+                "packets_lost": integer,
+                "current_latency_ms": number,
+                "jitter_ms": number,
+                "level_db": number,
+                "receiver_mismatches": {
+                    "type": "array",
+                    "items": string,
+                    "description": "Why the receiver can't play the track as sent (sender)"
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spec_covers_routes() {
+        let spec = spec();
+        let paths = spec["paths"].as_object().unwrap();
+
+        // Every API route registered on the server is documented
+        let server = include_str!("server.rs");
+        for line in server.lines().filter(|l| l.contains(".route(\"/api/")) {
+            let path = line.split('"').nth(1).unwrap();
+            if path == "/api/openapi.json" || path == "/api/docs" {
+                continue;
+            }
+            let templated = path.replace(":id", "{id}").replace(":name", "{name}");
+            assert!(paths.contains_key(&templated), "undocumented route {}", path);
+        }
+
+        // Every schema reference resolves
+        let text = spec.to_string();
+        let schemas = spec["components"]["schemas"].as_object().unwrap();
+        for part in text.split("#/components/schemas/").skip(1) {
+            let name = part.split('"').next().unwrap();
+            assert!(schemas.contains_key(name), "missing schema {}", name);
+        }
+    }
+}
@@ -5,14 +5,20 @@ use axum::{
     Router,
 };
 use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU16, Ordering};
 use std::sync::Arc;
 use tokio::sync::broadcast;
 use tower_http::cors::{Any, CorsLayer};
 
 use crate::config::UiConfig;
+use crate::diagnostics::Diagnostics;
+use crate::events::EventLog;
+use crate::network::udp::port_candidates;
+use crate::network::LinkedControl;
 use crate::protocol::ControlMessage;
-use crate::tracks::TrackManager;
-use crate::ui::handlers;
+use crate::reload::ConfigReload;
+use crate::tracks::{Scenes, TrackManager};
+use crate::ui::{auth, handlers, openapi};
 use crate::ui::websocket;
 
 /// Shared application state
@@ -20,6 +26,10 @@ pub struct AppState {
     pub track_manager: Arc<TrackManager>,
     pub control_tx: broadcast::Sender<ControlMessage>,
     pub is_sender: bool,
+    /// Audio port in use after any fallback, 0 until the socket is bound
+    pub udp_port: AtomicU16,
+    /// Web UI port in use after any fallback, 0 until the server listens
+    pub http_port: AtomicU16,
 }
 
 impl AppState {
@@ -29,6 +39,8 @@ impl AppState {
             track_manager,
             control_tx,
             is_sender,
+            udp_port: AtomicU16::new(0),
+            http_port: AtomicU16::new(0),
         }
     }
     
@@ -41,6 +53,8 @@ impl AppState {
 pub struct WebServer {
     config: UiConfig,
     state: Arc<AppState>,
+    /// Routes contributed by optional subsystems
+    extra_routes: Vec<Router>,
 }
 
 impl WebServer {
@@ -49,9 +63,73 @@ impl WebServer {
         Self {
             config,
             state: Arc::new(AppState::new(track_manager, is_sender)),
+            extra_routes: Vec::new(),
         }
     }
     
+    /// Merge additional routes (with their own state) into the server
+    pub fn merge(mut self, router: Router) -> Self {
+        self.extra_routes.push(router);
+        self
+    }
+    
+    /// Expose recent events at `/api/events`
+    pub fn with_event_log(self, log: Arc<EventLog>) -> Self {
+        self.merge(
+            Router::new()
+                .route("/api/events", get(handlers::get_events))
+                .with_state(log),
+        )
+    }
+    
+    /// Let the receiver's UI change the sender's tracks at `/api/remote/tracks`
+    pub fn with_linked_control(self, linked: Arc<LinkedControl>) -> Self {
+        self.merge(
+            Router::new()
+                .route("/api/remote/tracks", get(handlers::get_remote_tracks))
+                .route("/api/remote/tracks/:id", post(handlers::configure_remote_track))
+                .with_state(linked),
+        )
+    }
+    
+    /// Export and replace the whole config at `/api/config`, and wake the receiver at `/api/wake`
+    ///
+    /// Replacing the config needs `auth_token` to be set.
+    pub fn with_config(self, reload: Arc<ConfigReload>) -> Self {
+        let config = if self.config.auth_token.is_some() {
+            get(handlers::get_config).put(handlers::put_config)
+        } else {
+            get(handlers::get_config).put(handlers::config_locked)
+        };
+        self.merge(
+            Router::new()
+                .route("/api/config", config)
+                .route("/api/wake", post(handlers::wake_receiver))
+                .with_state(reload),
+        )
+    }
+    
+    /// Change the log filter at `/api/log` and download a support bundle at `/api/diagnostics`
+    pub fn with_diagnostics(self, diagnostics: Arc<Diagnostics>) -> Self {
+        self.merge(
+            Router::new()
+                .route("/api/log", get(handlers::get_log_filter).put(handlers::set_log_filter))
+                .route("/api/diagnostics", get(handlers::get_diagnostics))
+                .with_state(diagnostics),
+        )
+    }
+    
+    /// Store and recall mixer scenes at `/api/scenes`
+    pub fn with_scenes(self, scenes: Arc<Scenes>) -> Self {
+        self.merge(
+            Router::new()
+                .route("/api/scenes", get(handlers::get_scenes).post(handlers::store_scene))
+                .route("/api/scenes/:name", axum::routing::delete(handlers::delete_scene))
+                .route("/api/scenes/:name/recall", get(handlers::recall_scene).post(handlers::recall_scene))
+                .with_state(scenes),
+        )
+    }
+    
     /// Get shared state
     pub fn state(&self) -> Arc<AppState> {
         self.state.clone()
@@ -59,12 +137,7 @@ impl WebServer {
     
     /// Build the router
     fn build_router(&self) -> Router {
-        let cors = CorsLayer::new()
-            .allow_origin(Any)
-            .allow_methods(Any)
-            .allow_headers(Any);
-        
-        Router::new()
+        let mut router = Router::new()
             // API routes
             .route("/api/status", get(handlers::get_status))
             .route("/api/devices", get(handlers::get_devices))
@@ -72,34 +145,118 @@ impl WebServer {
             .route("/api/tracks", post(handlers::create_track))
             .route("/api/tracks/:id", axum::routing::delete(handlers::delete_track))
             .route("/api/tracks/:id", axum::routing::patch(handlers::update_track))
+            .route("/api/tracks/:id/stats", get(handlers::get_track_stats))
+            .route("/api/tracks/:id/jitter", get(handlers::get_jitter_trend))
+            .route("/api/stats/threads", get(handlers::get_thread_cpu))
             .route("/api/tracks/:id/mute", post(handlers::set_mute))
             .route("/api/tracks/:id/solo", post(handlers::set_solo))
+            .route("/api/tracks/:id/delay", post(handlers::set_delay))
+            .route("/api/tracks/:id/pause", post(handlers::set_paused))
+            .route("/api/tracks/:id/denoise", post(handlers::set_denoise))
+            .route("/api/tracks/:id/reset", post(handlers::reset_track))
+            .route("/api/pause", post(handlers::set_all_paused))
+            .route("/api/talkback", get(handlers::get_talkback).post(handlers::set_talkback))
+            .route("/api/bitrate-budget", post(handlers::set_bitrate_budget))
+            .route("/api/profiles", get(handlers::get_profiles))
+            .route("/api/groups", get(handlers::get_groups))
+            .route("/api/groups/:name/stats", get(handlers::get_group_stats))
+            .route("/api/groups/:name/mute", post(handlers::set_group_mute))
+            .route("/api/groups/:name/solo", post(handlers::set_group_solo))
+            .route("/api/groups/:name/gain", post(handlers::set_group_gain))
+            .route("/api/receiver/capabilities", get(handlers::get_receiver_capabilities))
             .route("/api/tracks/:id/start", post(handlers::start_track))
             .route("/api/tracks/:id/stop", post(handlers::stop_track))
+            .route("/api/tracks/:id/toggle_mute", get(handlers::toggle_mute).post(handlers::toggle_mute))
+            .route("/api/stream/start_all", get(handlers::start_all).post(handlers::start_all))
+            .route("/api/stream/stop_all", get(handlers::stop_all).post(handlers::stop_all))
             // WebSocket
             .route("/ws", get(websocket::websocket_handler))
-            // Health check
+            .with_state(self.state.clone());
+        
+        for extra in &self.extra_routes {
+            router = router.merge(extra.clone());
+        }
+        
+        if let Some(ref token) = self.config.auth_token {
+            let token: Arc<str> = Arc::from(token.as_str());
+            router = router.route_layer(axum::middleware::from_fn_with_state(token, auth::require_token));
+        }
+        
+        // Health checks and API description stay open
+        let router = router
             .route("/health", get(|| async { "OK" }))
-            // Static files (if configured)
-            .layer(cors)
-            .with_state(self.state.clone())
+            .route("/api/health", get(handlers::get_health).with_state(self.state.clone()))
+            .route("/api/openapi.json", get(openapi::openapi_json))
+            .route("/api/docs", get(openapi::swagger_ui));
+        
+        // Other origins only reach an API that takes a token they would need to know
+        if self.config.enable_cors && self.config.auth_token.is_some() {
+            router.layer(CorsLayer::new().allow_origin(Any).allow_methods(Any).allow_headers(Any))
+        } else {
+            if self.config.enable_cors {
+                tracing::warn!("ui.enable_cors needs ui.auth_token; serving same-origin only");
+            }
+            router
+        }
     }
     
     /// Start the web server
     pub async fn start(&self) -> anyhow::Result<()> {
-        let addr: SocketAddr = format!("{}:{}", self.config.bind_address, self.config.http_port)
-            .parse()?;
+        let listener = self.bind().await?;
+        let addr = listener.local_addr()?;
+        self.state.http_port.store(addr.port(), Ordering::Relaxed);
         
         let router = self.build_router();
+        tokio::spawn(websocket::forward_track_events(self.state.clone()));
+        tokio::spawn(websocket::push_bandwidth(self.state.clone()));
+        
+        if self.config.tls.enabled {
+            return self.start_tls(listener, router).await;
+        }
         
-        tracing::info!("Web server listening on http://{}", addr);
+        tracing::info!("Web UI available at http://{}", addr);
         
-        let listener = tokio::net::TcpListener::bind(addr).await?;
         axum::serve(listener, router).await?;
         
         Ok(())
     }
     
+    /// Listen on `http_port`, or the next free port in `port_range`
+    async fn bind(&self) -> anyhow::Result<tokio::net::TcpListener> {
+        let mut last_error = None;
+        for port in port_candidates(self.config.http_port, self.config.port_range) {
+            let addr: SocketAddr = format!("{}:{}", self.config.bind_address, port).parse()?;
+            match tokio::net::TcpListener::bind(addr).await {
+                Ok(listener) => {
+                    if port != self.config.http_port {
+                        tracing::warn!("HTTP port {} is in use; using port {}", self.config.http_port, port);
+                    }
+                    return Ok(listener);
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::AddrInUse => last_error = Some(e),
+                Err(e) => return Err(e.into()),
+            }
+        }
+        let e = last_error.map_or_else(String::new, |e| e.to_string());
+        anyhow::bail!("No free HTTP port from {}: {}", self.config.http_port, e)
+    }
+    
+    #[cfg(feature = "tls")]
+    async fn start_tls(&self, listener: tokio::net::TcpListener, router: Router) -> anyhow::Result<()> {
+        let tls = crate::ui::tls::server_config(&self.config.tls, &self.config.bind_address)?;
+        
+        tracing::info!("Web UI available at https://{}", listener.local_addr()?);
+        
+        crate::ui::tls::serve(listener, router, tls).await?;
+        
+        Ok(())
+    }
+    
+    #[cfg(not(feature = "tls"))]
+    async fn start_tls(&self, _listener: tokio::net::TcpListener, _router: Router) -> anyhow::Result<()> {
+        anyhow::bail!("ui.tls.enabled is set but this build lacks the `tls` feature")
+    }
+    
     /// Start the web server in the background
     pub fn start_background(self) -> tokio::task::JoinHandle<anyhow::Result<()>> {
         tokio::spawn(async move {
@@ -0,0 +1,131 @@
+//! HTTPS/WSS for the web UI
+//!
+//! Serves the router over rustls. Certificates come from PEM files when
+//! configured; otherwise a self-signed certificate is generated at start
+//! and its fingerprint logged so it can be checked in the browser.
+//! Connections are carried by the same [`TlsStream`] as outgoing HTTPS.
+
+use axum::Router;
+use hyper::server::conn::http1;
+use hyper_util::rt::TokioIo;
+use hyper_util::service::TowerToHyperService;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::{ServerConfig, ServerConnection};
+use sha2::{Digest, Sha256};
+use std::io;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+
+use crate::config::TlsConfig;
+use crate::network::tls::TlsStream;
+
+/// Build the rustls server configuration
+pub fn server_config(config: &TlsConfig, bind_address: &str) -> anyhow::Result<Arc<ServerConfig>> {
+    let (certs, key) = match (&config.cert_path, &config.key_path) {
+        (Some(cert_path), Some(key_path)) => {
+            let certs = CertificateDer::pem_file_iter(cert_path)
+                .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+                .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", cert_path.display(), e))?;
+            let key = PrivateKeyDer::from_pem_file(key_path)
+                .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", key_path.display(), e))?;
+            tracing::info!("Loaded TLS certificate from {}", cert_path.display());
+            (certs, key)
+        }
+        (None, None) => self_signed(config, bind_address)?,
+        _ => anyhow::bail!("TLS needs both cert_path and key_path, or neither for self-signed"),
+    };
+
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let mut server_config = ServerConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)?;
+    server_config.alpn_protocols = vec![b"http/1.1".to_vec()];
+
+    Ok(Arc::new(server_config))
+}
+
+/// Generate a self-signed certificate for localhost, the bind address and
+/// any configured hostnames
+fn self_signed(
+    config: &TlsConfig,
+    bind_address: &str,
+) -> anyhow::Result<(Vec<CertificateDer<'static>>, PrivateKeyDer<'static>)> {
+    let mut names = vec!["localhost".to_string(), "127.0.0.1".to_string()];
+    if !bind_address.is_empty() && bind_address != "0.0.0.0" && !names.iter().any(|n| n == bind_address) {
+        names.push(bind_address.to_string());
+    }
+    names.extend(config.hostnames.iter().cloned());
+
+    let certified = rcgen::generate_simple_self_signed(names.clone())?;
+    let cert = certified.cert.der().clone();
+    let fingerprint: Vec<String> = Sha256::digest(&cert).iter().map(|b| format!("{:02X}", b)).collect();
+    tracing::info!(
+        "Generated self-signed TLS certificate for {:?} (SHA-256 {})",
+        names,
+        fingerprint.join(":")
+    );
+
+    let key = PrivateKeyDer::Pkcs8(certified.key_pair.serialize_der().into());
+    Ok((vec![cert], key))
+}
+
+/// Accept TLS connections and serve the router on each
+pub async fn serve(listener: TcpListener, router: Router, tls: Arc<ServerConfig>) -> io::Result<()> {
+    loop {
+        let (tcp, remote) = match listener.accept().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                tracing::debug!("Accept failed: {}", e);
+                continue;
+            }
+        };
+        let _ = tcp.set_nodelay(true);
+
+        let conn = match ServerConnection::new(tls.clone()) {
+            Ok(conn) => conn,
+            Err(e) => {
+                tracing::warn!("Failed to set up TLS for {}: {}", remote, e);
+                continue;
+            }
+        };
+
+        let service = TowerToHyperService::new(router.clone());
+        tokio::spawn(async move {
+            let io = TokioIo::new(TlsStream::new(tcp, conn));
+            // Upgrades are needed for the WebSocket
+            if let Err(e) = http1::Builder::new()
+                .serve_connection(io, service)
+                .with_upgrades()
+                .await
+            {
+                tracing::debug!("TLS connection from {} ended: {}", remote, e);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_self_signed_config() {
+        let config = TlsConfig {
+            enabled: true,
+            hostnames: vec!["studio.local".to_string()],
+            ..Default::default()
+        };
+        let server = server_config(&config, "192.168.1.10").unwrap();
+        assert_eq!(server.alpn_protocols, vec![b"http/1.1".to_vec()]);
+
+        // Half-configured file paths are rejected
+        let config = TlsConfig {
+            enabled: true,
+            cert_path: Some("cert.pem".into()),
+            ..Default::default()
+        };
+        assert!(server_config(&config, "127.0.0.1").is_err());
+    }
+}
@@ -1,4 +1,8 @@
 //! WebSocket handler for real-time communication
+//!
+//! JSON [`ControlMessage`]s both ways; clients on the versioned
+//! [`WS_SUBPROTOCOL`] negotiate with Hello/Welcome and may get track levels
+//! as binary meter frames (see [`crate::protocol`]).
 
 use axum::{
     extract::{
@@ -9,9 +13,15 @@ use axum::{
 };
 use futures_util::{SinkExt, StreamExt};
 use std::sync::Arc;
-use tokio::sync::broadcast;
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc, watch};
+use tokio::time::MissedTickBehavior;
 
-use crate::protocol::ControlMessage;
+use crate::protocol::{
+    encode_meter_frame, ControlMessage, StateSnapshot, TrackMeter, DEFAULT_METER_HZ, MAX_METER_HZ,
+    WS_FEATURE_BINARY_METERS, WS_PROTOCOL_VERSION, WS_SUBPROTOCOL,
+};
+use crate::tracks::manager::{TrackEvent, TrackManager};
 use crate::ui::server::AppState;
 
 /// WebSocket upgrade handler
@@ -19,7 +29,59 @@ pub async fn websocket_handler(
     ws: WebSocketUpgrade,
     State(state): State<Arc<AppState>>,
 ) -> impl IntoResponse {
-    ws.on_upgrade(move |socket| handle_socket(socket, state))
+    ws.protocols([WS_SUBPROTOCOL]).on_upgrade(move |socket| handle_socket(socket, state))
+}
+
+/// Answer a client's Hello; also returns the binary meter rate (0 = none)
+fn welcome(version: u16, features: &[String], meter_hz: Option<u32>) -> (ControlMessage, u32) {
+    let binary_meters = features.iter().any(|f| f == WS_FEATURE_BINARY_METERS);
+    let meter_hz = if binary_meters {
+        meter_hz.unwrap_or(DEFAULT_METER_HZ).clamp(1, MAX_METER_HZ)
+    } else {
+        0
+    };
+    let welcome = ControlMessage::Welcome {
+        version: version.clamp(1, WS_PROTOCOL_VERSION),
+        features: binary_meters.then(|| WS_FEATURE_BINARY_METERS.to_string()).into_iter().collect(),
+        meter_hz,
+    };
+    (welcome, meter_hz)
+}
+
+/// Current levels of every track as a binary frame
+fn meter_frame(track_manager: &TrackManager) -> Vec<u8> {
+    let bus = track_manager.meter_bus();
+    let meters: Vec<TrackMeter> = track_manager
+        .track_ids()
+        .into_iter()
+        .map(|track_id| TrackMeter {
+            track_id,
+            level_db: bus.level_db(track_id).unwrap_or(-96.0).max(-96.0),
+            gain_reduction_db: bus.get(track_id).map_or(0.0, |m| m.gain_reduction_db()),
+        })
+        .collect();
+    encode_meter_frame(&meters)
+}
+
+/// Build a full state snapshot for a client
+pub async fn build_snapshot(state: &AppState) -> StateSnapshot {
+    // Device enumeration can block on some backends
+    let devices = tokio::task::spawn_blocking(crate::audio::device::list_devices)
+        .await
+        .unwrap_or_default();
+    let track_manager = &state.track_manager;
+    
+    StateSnapshot {
+        is_sender: state.is_sender,
+        tracks: track_manager.snapshot_tracks(),
+        devices,
+        bitrate_budget: track_manager.bitrate_budget(),
+        all_paused: track_manager.is_all_paused(),
+        alerts: track_manager.active_alerts(),
+        session: track_manager.session().stats(),
+        groups: track_manager.groups(),
+        max_tracks: track_manager.max_tracks(),
+    }
 }
 
 /// Handle WebSocket connection
@@ -31,16 +93,51 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>) {
     let track_manager = state.track_manager.clone();
     let control_tx = state.control_tx.clone();
     
-    // Send initial status
-    let statuses = track_manager.get_all_statuses();
-    let status_msg = ControlMessage::Status(statuses);
-    if let Ok(json) = serde_json::to_string(&status_msg) {
+    // Replies meant for this client only
+    let (reply_tx, mut reply_rx) = mpsc::unbounded_channel::<ControlMessage>();
+    // Binary meter rate agreed in the handshake (0 = off)
+    let (meter_tx, mut meter_rx) = watch::channel(0u32);
+    
+    // Start the client off with the complete state
+    let snapshot = ControlMessage::Snapshot(build_snapshot(&state).await);
+    if let Ok(json) = serde_json::to_string(&snapshot) {
         let _ = sender.send(Message::Text(json)).await;
     }
     
-    // Spawn task to forward broadcast messages to WebSocket
+    // Spawn task to forward broadcast messages and replies to WebSocket
+    let snapshot_state = state.clone();
+    let meter_manager = state.track_manager.clone();
     let mut send_task = tokio::spawn(async move {
-        while let Ok(msg) = control_rx.recv().await {
+        let mut meter_hz = 0;
+        let mut meter_ticks = tokio::time::interval(Duration::from_secs(1));
+        loop {
+            let msg = tokio::select! {
+                msg = control_rx.recv() => match msg {
+                    Ok(msg) => msg,
+                    // Too slow to keep up; resend everything rather than drift
+                    Err(broadcast::error::RecvError::Lagged(missed)) => {
+                        tracing::debug!("WebSocket client missed {} messages, resyncing", missed);
+                        ControlMessage::Snapshot(build_snapshot(&snapshot_state).await)
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+                Some(msg) = reply_rx.recv() => msg,
+                Ok(()) = meter_rx.changed() => {
+                    meter_hz = *meter_rx.borrow_and_update();
+                    if meter_hz > 0 {
+                        meter_ticks = tokio::time::interval(Duration::from_secs(1) / meter_hz);
+                        meter_ticks.set_missed_tick_behavior(MissedTickBehavior::Skip);
+                    }
+                    continue;
+                }
+                _ = meter_ticks.tick(), if meter_hz > 0 => {
+                    if sender.send(Message::Binary(meter_frame(&meter_manager))).await.is_err() {
+                        break;
+                    }
+                    continue;
+                }
+            };
+            
             if let Ok(json) = serde_json::to_string(&msg) {
                 if sender.send(Message::Text(json)).await.is_err() {
                     break;
@@ -50,12 +147,25 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>) {
     });
     
     // Handle incoming messages
+    let recv_state = state.clone();
     let mut recv_task = tokio::spawn(async move {
         while let Some(Ok(msg)) = receiver.next().await {
             match msg {
                 Message::Text(text) => {
-                    if let Ok(control_msg) = serde_json::from_str::<ControlMessage>(&text) {
-                        handle_control_message(control_msg, &track_manager, &control_tx).await;
+                    match serde_json::from_str::<ControlMessage>(&text) {
+                        Ok(ControlMessage::Resync) => {
+                            let snapshot = build_snapshot(&recv_state).await;
+                            let _ = reply_tx.send(ControlMessage::Snapshot(snapshot));
+                        }
+                        Ok(ControlMessage::Hello { version, features, meter_hz }) => {
+                            let (welcome, meter_hz) = welcome(version, &features, meter_hz);
+                            let _ = reply_tx.send(welcome);
+                            let _ = meter_tx.send(meter_hz);
+                        }
+                        Ok(control_msg) => {
+                            handle_control_message(control_msg, &track_manager, &control_tx).await;
+                        }
+                        Err(_) => {}
                     }
                 }
                 Message::Binary(_) => {
@@ -85,6 +195,47 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>) {
     }
 }
 
+/// Forward audio-path track events to WebSocket clients
+pub async fn forward_track_events(state: Arc<AppState>) {
+    let mut events = state.track_manager.subscribe();
+    
+    loop {
+        match events.recv().await {
+            Ok(TrackEvent::GateChanged(track_id, open)) => {
+                let _ = state.control_tx.send(ControlMessage::GateState { track_id, open });
+            }
+            Ok(TrackEvent::Alert(track_id, kind, active)) => {
+                let _ = state.control_tx.send(ControlMessage::Alert { track_id, kind, active });
+            }
+            Ok(TrackEvent::Xrun(xrun)) => {
+                let _ = state.control_tx.send(ControlMessage::Xrun(xrun));
+            }
+            Ok(TrackEvent::DeviceMigrated(track_id, device_id, fallback)) => {
+                let _ = state.control_tx.send(ControlMessage::DeviceMigrated { track_id, device_id, fallback });
+            }
+            Ok(TrackEvent::StateChanged(transition)) => {
+                let _ = state.control_tx.send(ControlMessage::TrackState(transition));
+            }
+            Ok(_) => {}
+            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
+}
+
+/// Push the current bandwidth to WebSocket clients every second
+pub async fn push_bandwidth(state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(Duration::from_secs(1));
+    loop {
+        interval.tick().await;
+        // Nobody listening
+        if state.control_tx.receiver_count() == 0 {
+            continue;
+        }
+        let _ = state.control_tx.send(ControlMessage::Bandwidth(state.track_manager.bandwidth()));
+    }
+}
+
 /// Handle incoming control message
 async fn handle_control_message(
     msg: ControlMessage,
@@ -97,6 +248,11 @@ async fn handle_control_message(
             let _ = control_tx.send(ControlMessage::Status(statuses));
         }
         
+        ControlMessage::GetSessionStats => {
+            let stats = track_manager.session().stats();
+            let _ = control_tx.send(ControlMessage::SessionStats(stats));
+        }
+        
         ControlMessage::ListDevices => {
             let devices = crate::audio::device::list_devices();
             let _ = control_tx.send(ControlMessage::Devices(devices));
@@ -147,6 +303,75 @@ async fn handle_control_message(
             }
         }
         
+        ControlMessage::SetDelay { track_id, delay_ms } => {
+            if let Err(e) = track_manager.set_playout_delay(track_id, delay_ms) {
+                let _ = control_tx.send(ControlMessage::Error {
+                    message: e.to_string(),
+                });
+            }
+        }
+        
+        ControlMessage::SetDenoise { track_id, enabled } => {
+            if let Err(e) = track_manager.set_denoise(track_id, enabled) {
+                let _ = control_tx.send(ControlMessage::Error {
+                    message: e.to_string(),
+                });
+            }
+        }
+        
+        ControlMessage::SetBitrateBudget { bitrate } => {
+            track_manager.set_bitrate_budget(bitrate);
+        }
+        
+        ControlMessage::SetPaused { track_id, paused } => {
+            match track_id {
+                Some(id) => {
+                    if let Err(e) = track_manager.set_paused(id, paused) {
+                        let _ = control_tx.send(ControlMessage::Error {
+                            message: e.to_string(),
+                        });
+                    }
+                }
+                None => track_manager.set_all_paused(paused),
+            }
+        }
+        
+        ControlMessage::SetGroupMute { group, muted } => {
+            if let Err(e) = track_manager.set_group_muted(&group, muted) {
+                let _ = control_tx.send(ControlMessage::Error {
+                    message: e.to_string(),
+                });
+            }
+        }
+        
+        ControlMessage::SetGroupSolo { group, solo } => {
+            if let Err(e) = track_manager.set_group_solo(&group, solo) {
+                let _ = control_tx.send(ControlMessage::Error {
+                    message: e.to_string(),
+                });
+            }
+        }
+        
+        ControlMessage::SetGroupGain { group, gain_db } => {
+            if let Err(e) = track_manager.set_group_gain(&group, gain_db) {
+                let _ = control_tx.send(ControlMessage::Error {
+                    message: e.to_string(),
+                });
+            }
+        }
+        
+        ControlMessage::GetGroups => {
+            let _ = control_tx.send(ControlMessage::Groups(track_manager.groups()));
+        }
+        
+        ControlMessage::GetGroupStats { group } => {
+            let reply = match track_manager.group_stats(&group) {
+                Ok(stats) => ControlMessage::GroupStats(stats),
+                Err(e) => ControlMessage::Error { message: e.to_string() },
+            };
+            let _ = control_tx.send(reply);
+        }
+        
         ControlMessage::Ping => {
             let _ = control_tx.send(ControlMessage::Pong);
         }
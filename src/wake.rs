@@ -0,0 +1,120 @@
+//! Waking the application loops when there is work
+//!
+//! Capture callbacks and socket readers hand their data over through
+//! queues and channels. Instead of polling those, a loop builds a [`Wait`]
+//! over all of them and sleeps until one holds something or the timeout
+//! for its periodic work runs out. Queues that aren't channels, like the
+//! capture ring buffer, ring a [`Wakeup`] when they are pushed to.
+
+use crossbeam_channel::{bounded, Receiver, Select, Sender};
+use std::time::Duration;
+
+/// Doorbell for a queue that isn't a channel
+///
+/// Rings coalesce: however often it rings, the waiting loop wakes once
+/// and then takes everything queued.
+#[derive(Debug, Clone)]
+pub struct Wakeup {
+    tx: Sender<()>,
+    rx: Receiver<()>,
+}
+
+impl Wakeup {
+    pub fn new() -> Self {
+        let (tx, rx) = bounded(1);
+        Self { tx, rx }
+    }
+
+    /// Wake the loop waiting on this; never blocks
+    pub fn wake(&self) {
+        let _ = self.tx.try_send(());
+    }
+}
+
+impl Default for Wakeup {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Everything one loop iteration waits on
+pub struct Wait<'a> {
+    select: Select<'a>,
+    wakeups: Vec<&'a Wakeup>,
+}
+
+impl<'a> Wait<'a> {
+    pub fn new() -> Self {
+        Self { select: Select::new(), wakeups: Vec::new() }
+    }
+
+    /// Wake when `rx` has a message (the message stays queued)
+    pub fn channel<T>(mut self, rx: &'a Receiver<T>) -> Self {
+        self.select.recv(rx);
+        self
+    }
+
+    /// Wake when `wakeup` rings
+    pub fn wakeup(mut self, wakeup: &'a Wakeup) -> Self {
+        self.select.recv(&wakeup.rx);
+        self.wakeups.push(wakeup);
+        self
+    }
+
+    /// Block until something is ready or `timeout` passes; true if something is
+    ///
+    /// Rings are taken, so the caller must then drain its queues.
+    pub fn until(mut self, timeout: Duration) -> bool {
+        let ready = self.select.ready_timeout(timeout).is_ok();
+        for wakeup in self.wakeups {
+            while wakeup.rx.try_recv().is_ok() {}
+        }
+        ready
+    }
+}
+
+impl Default for Wait<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[test]
+    fn test_wait_wakes_on_data() {
+        let wakeup = Wakeup::new();
+        let (tx, rx) = bounded::<u32>(4);
+        let idle = Duration::from_millis(20);
+
+        // Nothing ready: the whole timeout
+        let started = Instant::now();
+        assert!(!Wait::new().channel(&rx).wakeup(&wakeup).until(idle));
+        assert!(started.elapsed() >= idle);
+
+        // Rings coalesce into one wake
+        wakeup.wake();
+        wakeup.wake();
+        assert!(Wait::new().channel(&rx).wakeup(&wakeup).until(idle));
+        assert!(!Wait::new().wakeup(&wakeup).until(Duration::ZERO));
+
+        // Channel messages stay for the caller to take
+        tx.send(7).unwrap();
+        assert!(Wait::new().channel(&rx).wakeup(&wakeup).until(idle));
+        assert_eq!(rx.try_recv(), Ok(7));
+
+        // A ring from another thread ends the wait early
+        let remote = wakeup.clone();
+        let ringer = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(5));
+            remote.wake();
+        });
+        let started = Instant::now();
+        assert!(Wait::new().wakeup(&wakeup).until(Duration::from_secs(5)));
+        assert!(started.elapsed() < Duration::from_secs(5));
+        ringer.join().unwrap();
+    }
+}